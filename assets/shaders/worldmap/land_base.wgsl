@@ -21,17 +21,21 @@ const USE_VOLUMETRIC_NOISE: u32 = 1u; // 0=flat fog, 1=domain-warped billow modu
 // Bindings / Uniform Layouts
 // ============================================================================
 
+// Unpacked from a texel of tile_data (see unpack_tile).
 struct TileUniform {
   tile_height:   f32,
   texture_size:  u32, // 0=small atlas, 1=big atlas
   texture_layer: u32,
-  texture_hue:   u32,
+  tile_flags:    u32, // TILE_FLAG_*
+  texture_hue:   u32, // 0=no hue, otherwise row of hue_palette
+  anim_kind:     u32, // ANIM_KIND_*
+  anim_speed:    f32, // UV units per second
 };
 
 struct LandUniform {
   chunk_origin: vec2<f32>, // world origin of chunk (x,z) in tile units
-  _pad1: vec2<f32>,
-  tiles: array<TileUniform, 169>, // 13×13 grid (8×8 core + 2 border)
+  chunk_size: u32,         // tiles per chunk side (8, 16 or 32, from the settings)
+  lod_step: u32,           // tiles per mesh quad side: 1 (full detail), 2 or 4 at far zoom (see land/lod.rs)
 };
 
 struct SceneUniform {
@@ -69,9 +73,18 @@ struct EffectsUniform {
 
   // Slot C
   blur_radius:       f32, // UV radius in *screen pixels* (we scale by fwidth)
-  _pad_c1:           f32,
-  _pad_c2:           f32,
-  _pad_c3:           f32,
+  overlay_mode:      u32, // TERRAIN_OVERLAY_*
+  grid_lines:        u32, // GRID_LINES_* bits
+  transition_width:  f32, // texture transitions across the tile edges, in tiles: 0=hard cut (classic), up to 1
+
+  // Slot D
+  altitude_params:   vec4<f32>, // [ramp_z_min, ramp_z_max, contour_interval (z units), contours_on]
+
+  // Slot E
+  water_params:      vec4<f32>, // [transparency, opaque_distance (tiles), shore_blend_width (tiles), water_on]
+
+  // Slot F
+  normal_map_params: vec4<f32>, // [strength, 0, 0, normal_maps_on]
 };
 
 // Lighting / look controls.
@@ -125,39 +138,433 @@ struct LightingUniforms {
 @group(2) @binding(100) var texarray_sampler: sampler;
 @group(2) @binding(101) var texarray_small: texture_2d_array<f32>;
 @group(2) @binding(102) var texarray_big:   texture_2d_array<f32>;
-@group(2) @binding(103) var<uniform> land:    LandUniform;
 @group(2) @binding(104) var<uniform> scene:   SceneUniform;
 @group(2) @binding(105) var<uniform> effects: EffectsUniform;
 @group(2) @binding(106) var<uniform> lighting: LightingUniforms;
+@group(2) @binding(107) var hue_palette: texture_2d<f32>; // 32 x (hue_count + 1), read with textureLoad
+// Normal maps of the textures, at the same layers (tangent space, see texture_cache::land::normal_map).
+@group(2) @binding(109) var normal_maps_small: texture_2d_array<f32>;
+@group(2) @binding(110) var normal_maps_big:   texture_2d_array<f32>;
+
+#ifdef LAND_BATCHED
+// Batched chunks (see land/batch.rs) share the material: their land uniform and tile data are in storage buffers,
+//  at the slot given by the mesh tag of the instance. select_land_slot must run first in each entry point.
+@group(2) @binding(103) var<storage, read> land_slots: array<LandUniform>;
+@group(2) @binding(108) var<storage, read> tile_data_slots: array<vec4<u32>>; // data grids, one per slot
+
+var<private> land: LandUniform;
+var<private> land_slot: u32;
+
+fn select_land_slot(instance_index: u32) {
+  land_slot = mesh_functions::get_tag(instance_index);
+  land = land_slots[land_slot];
+}
+#else
+@group(2) @binding(103) var<uniform> land:    LandUniform;
+@group(2) @binding(108) var tile_data: texture_2d<u32>;   // data grid (chunk + far edge + 2 border), one texel per tile
+
+fn select_land_slot(instance_index: u32) {}
+#endif
 
 // ============================================================================
 // Grid helpers & utilities
 // ============================================================================
 
-const CHUNK_TILE_NUM_DIM: u32 = 8u;
+// The chunk size comes from the land uniform: grid sizes depend on it.
 const DATA_GRID_BORDER:  i32 = 2;
-const DATA_GRID_SIDE:    i32 = 13;  // DATA_GRID_BORDER + CHUNK_TILE_NUM_DIM + DATA_GRID_BORDER
-const MESH_GRID_SIDE:    u32 = 9u;
 
-// Clamp safe index into the 13×13 “data grid”
-fn tile_index_clamped(ix: i32, iz: i32) -> u32 {
-  let gx = clamp(ix + DATA_GRID_BORDER, 0, DATA_GRID_SIDE - 1);
-  let gz = clamp(iz + DATA_GRID_BORDER, 0, DATA_GRID_SIDE - 1);
-  return u32(gz * DATA_GRID_SIDE + gx);
+fn chunk_tile_num_dim() -> u32 {
+  return land.chunk_size;
+}
+// DATA_GRID_BORDER + chunk size + 1 (far mesh edge) + DATA_GRID_BORDER; 13 for 8×8 chunks
+fn data_grid_side() -> i32 {
+  return i32(chunk_tile_num_dim()) + 1 + 2 * DATA_GRID_BORDER;
+}
+// Tiles per mesh quad side. A zeroed uniform means full detail.
+fn lod_step() -> u32 {
+  return max(land.lod_step, 1u);
+}
+// Mesh vertices per side: 9 for 8×8 chunks at full detail, 3 at lod step 4
+fn mesh_grid_side() -> u32 {
+  return chunk_tile_num_dim() / lod_step() + 1u;
+}
+
+// Clamp safe texel coordinates into the “data grid”
+fn tile_coords_clamped(ix: i32, iz: i32) -> vec2<i32> {
+  let side = data_grid_side();
+  let gx = clamp(ix + DATA_GRID_BORDER, 0, side - 1);
+  let gz = clamp(iz + DATA_GRID_BORDER, 0, side - 1);
+  return vec2<i32>(gx, gz);
+}
+// Keep in sync with TileUniform::to_texel.
+fn unpack_tile(texel: vec4<u32>) -> TileUniform {
+  var tile: TileUniform;
+  tile.tile_height   = bitcast<f32>(texel.r);
+  tile.texture_layer = texel.g & 0xFFFFu;
+  tile.tile_flags    = texel.g >> 16u;
+  tile.texture_hue   = texel.b & 0xFFFFu;
+  tile.texture_size  = (texel.b >> 16u) & 0xFFu;
+  tile.anim_kind     = (texel.b >> 24u) & 0xFFu;
+  tile.anim_speed    = bitcast<f32>(texel.a);
+  return tile;
+}
+fn tile_at_data_grid(ix: i32, iz: i32) -> TileUniform {
+#ifdef LAND_BATCHED
+  let coords = tile_coords_clamped(ix, iz);
+  let side = u32(data_grid_side());
+  return unpack_tile(tile_data_slots[land_slot * side * side + u32(coords.y) * side + u32(coords.x)]);
+#else
+  return unpack_tile(textureLoad(tile_data, tile_coords_clamped(ix, iz), 0));
+#endif
+}
+fn tile_height_at_data_grid(ix: i32, iz: i32) -> f32 {
+  return tile_at_data_grid(ix, iz).tile_height;
+}
+// Height of a coarse mesh node: the average of the step×step tiles around it. The window is the same for a node
+//  shared by two chunks at the same lod step, so their edges match. It stays within the data grid border (2).
+fn lod_height_at_data_grid(ix: i32, iz: i32) -> f32 {
+  let step = i32(lod_step());
+  if (step == 1) {
+    return tile_height_at_data_grid(ix, iz);
+  }
+  let half = step / 2;
+  var sum = 0.0;
+  for (var dz = -half; dz < step - half; dz++) {
+    for (var dx = -half; dx < step - half; dx++) {
+      sum += tile_height_at_data_grid(ix + dx, iz + dz);
+    }
+  }
+  return sum / f32(step * step);
+}
+
+// ============================================================================
+// Hues
+// ============================================================================
+
+const HUE_NONE:       u32 = 0u;
+const HUE_RAMP_COLORS: u32 = 32u;
+const SRGB_GAMMA:     f32 = 2.2;
+
+// Classic hue application: the 5-bit red component of the original (sRGB) color picks the color
+//  in the 32-colors ramp of the hue.
+fn apply_hue(albedo: vec3<f32>, hue: u32) -> vec3<f32> {
+  if (hue == HUE_NONE) {
+    return albedo;
+  }
+  let hue_row = min(hue, textureDimensions(hue_palette).y - 1u);
+  let red_srgb = pow(clamp(albedo.r, 0.0, 1.0), 1.0 / SRGB_GAMMA);
+  let ramp_idx = min(u32(red_srgb * f32(HUE_RAMP_COLORS - 1u) + 0.5), HUE_RAMP_COLORS - 1u);
+  return textureLoad(hue_palette, vec2<u32>(ramp_idx, hue_row), 0).rgb;
+}
+
+// ============================================================================
+// Animated terrain (water, lava)
+// ============================================================================
+
+// Keep in sync with animation.rs.
+const ANIM_KIND_NONE:  u32 = 0u;
+const ANIM_KIND_WATER: u32 = 1u;
+const ANIM_KIND_LAVA:  u32 = 2u;
+
+const ANIM_WATER_RIPPLE_AMPLITUDE: f32 = 0.03; // UV units
+const ANIM_WATER_RIPPLE_FREQUENCY: f32 = 1.7;  // waves per tile (world units)
+const ANIM_LAVA_PULSE_STRENGTH:    f32 = 0.15;
+const ANIM_LAVA_PULSE_SPEED:       f32 = 1.3;
+
+// Offsets the UVs of animated tiles. Offsets are computed from world coordinates, so they're
+//  continuous across adjacent tiles (and chunks) sharing the same texture.
+fn animate_tile_uv(uv: vec2<f32>, tile: TileUniform, world_xz: vec2<f32>) -> vec2<f32> {
+  if (tile.anim_kind == ANIM_KIND_NONE) {
+    return uv;
+  }
+  let t = scene.time_seconds * tile.anim_speed;
+  if (tile.anim_kind == ANIM_KIND_WATER) {
+    // Diagonal flow plus a gentle ripple.
+    let ripple = vec2<f32>(
+      sin((world_xz.y + t * 4.0) * ANIM_WATER_RIPPLE_FREQUENCY),
+      cos((world_xz.x + t * 4.0) * ANIM_WATER_RIPPLE_FREQUENCY)
+    ) * ANIM_WATER_RIPPLE_AMPLITUDE;
+    return fract(uv + vec2<f32>(t, t * 0.5) + ripple);
+  }
+  // Lava: slow flow along one axis.
+  return fract(uv + vec2<f32>(t, 0.0));
+}
+
+// Color modulation for animated tiles (applied to the sampled albedo).
+fn animate_tile_albedo(albedo: vec3<f32>, tile: TileUniform, world_xz: vec2<f32>) -> vec3<f32> {
+  if (tile.anim_kind != ANIM_KIND_LAVA) {
+    return albedo;
+  }
+  // Per-tile phase, to desynchronize the pulse of adjacent tiles.
+  let phase = hash(floor(world_xz)) * 6.2831;
+  let pulse = 1.0 + ANIM_LAVA_PULSE_STRENGTH * sin(scene.time_seconds * ANIM_LAVA_PULSE_SPEED + phase);
+  return albedo * pulse;
+}
+
+// ============================================================================
+// Terrain overlays (debug tints of the albedo, lighting still applies)
+// ============================================================================
+
+// Keep in sync with terrain_overlay.rs.
+const TERRAIN_OVERLAY_NONE:        u32 = 0u;
+const TERRAIN_OVERLAY_WALKABILITY: u32 = 1u;
+const TERRAIN_OVERLAY_ALTITUDE:    u32 = 2u;
+const TERRAIN_OVERLAY_MAP_DIFF:    u32 = 3u;
+const TILE_FLAG_IMPASSABLE: u32 = 1u;
+const TILE_FLAG_WET:        u32 = 2u;
+const TILE_FLAG_CHANGED_ID: u32 = 4u;
+const TILE_FLAG_CHANGED_Z:  u32 = 8u;
+
+const OVERLAY_TINT_STRENGTH: f32 = 0.6;
+const ALTITUDE_TINT_STRENGTH: f32 = 0.85; // Stronger: the ramp colors are what matters.
+const CONTOUR_LINE_WIDTH_PX: f32 = 1.0;
+const CONTOUR_LINE_DARKEN:   f32 = 0.7;
+// Keep in sync with scale_uo_z_to_bevy_units.
+const UO_Z_PER_WORLD_UNIT: f32 = 10.0;
+
+const GRID_LINES_TILES:  u32 = 1u;
+const GRID_LINES_BLOCKS: u32 = 2u;
+const GRID_LINES_CHUNKS: u32 = 4u;
+const GRID_LINE_WIDTH_PX: f32 = 1.0;
+const MAP_BLOCK_TILES:    f32 = 8.0; // MapBlock::CELLS_PER_ROW
+// rgb + opacity
+const GRID_COLOR_TILES:  vec4<f32> = vec4<f32>(0.05, 0.05, 0.05, 0.35);
+const GRID_COLOR_BLOCKS: vec4<f32> = vec4<f32>(1.00, 0.85, 0.10, 0.80);
+const GRID_COLOR_CHUNKS: vec4<f32> = vec4<f32>(1.00, 0.20, 0.90, 0.90);
+const WALKABILITY_COLOR_PASSABLE:   vec3<f32> = vec3<f32>(0.15, 0.85, 0.15);
+const WALKABILITY_COLOR_IMPASSABLE: vec3<f32> = vec3<f32>(0.90, 0.10, 0.10);
+const WALKABILITY_COLOR_WET:        vec3<f32> = vec3<f32>(0.10, 0.35, 0.95);
+const MAP_DIFF_COLOR_ID:   vec3<f32> = vec3<f32>(1.00, 0.50, 0.05);
+const MAP_DIFF_COLOR_Z:    vec3<f32> = vec3<f32>(0.10, 0.85, 0.95);
+const MAP_DIFF_COLOR_BOTH: vec3<f32> = vec3<f32>(0.95, 0.10, 0.85);
+const MAP_DIFF_UNCHANGED_DIM: f32 = 0.6; // Unchanged tiles are darkened, so that the changes stand out.
+
+// Wet wins over impassable: water is usually both.
+fn walkability_color(tile: TileUniform) -> vec3<f32> {
+  if ((tile.tile_flags & TILE_FLAG_WET) != 0u) {
+    return WALKABILITY_COLOR_WET;
+  }
+  if ((tile.tile_flags & TILE_FLAG_IMPASSABLE) != 0u) {
+    return WALKABILITY_COLOR_IMPASSABLE;
+  }
+  return WALKABILITY_COLOR_PASSABLE;
+}
+
+// Tiles differing from the other version of the compared map.
+fn map_diff_color(albedo: vec3<f32>, tile: TileUniform) -> vec3<f32> {
+  let changed_id = (tile.tile_flags & TILE_FLAG_CHANGED_ID) != 0u;
+  let changed_z = (tile.tile_flags & TILE_FLAG_CHANGED_Z) != 0u;
+  if (changed_id && changed_z) {
+    return mix(albedo, MAP_DIFF_COLOR_BOTH, OVERLAY_TINT_STRENGTH);
+  }
+  if (changed_id) {
+    return mix(albedo, MAP_DIFF_COLOR_ID, OVERLAY_TINT_STRENGTH);
+  }
+  if (changed_z) {
+    return mix(albedo, MAP_DIFF_COLOR_Z, OVERLAY_TINT_STRENGTH);
+  }
+  return albedo * MAP_DIFF_UNCHANGED_DIM;
+}
+
+// Hypsometric-like ramp: deep blue, green, yellow, brown, white.
+fn altitude_color(z: f32) -> vec3<f32> {
+  let z_min = effects.altitude_params.x;
+  let z_max = max(effects.altitude_params.y, z_min + 1.0);
+  let t = clamp((z - z_min) / (z_max - z_min), 0.0, 1.0) * 4.0;
+  let c0 = vec3<f32>(0.05, 0.15, 0.55);
+  let c1 = vec3<f32>(0.15, 0.60, 0.20);
+  let c2 = vec3<f32>(0.90, 0.85, 0.25);
+  let c3 = vec3<f32>(0.55, 0.35, 0.15);
+  let c4 = vec3<f32>(0.97, 0.97, 0.97);
+  if (t < 1.0) { return mix(c0, c1, t); }
+  if (t < 2.0) { return mix(c1, c2, t - 1.0); }
+  if (t < 3.0) { return mix(c2, c3, t - 2.0); }
+  return mix(c3, c4, t - 3.0);
+}
+
+// 1 on the contour lines, 0 elsewhere. The width is in screen pixels (via fwidth), so it must be called in uniform
+//  control flow: it's computed before any per-tile branch.
+fn contour_line(z: f32) -> f32 {
+  let zc = z / max(effects.altitude_params.z, 1.0);
+  let dist_px = abs(fract(zc - 0.5) - 0.5) / max(fwidth(zc), 1e-5);
+  return 1.0 - smoothstep(CONTOUR_LINE_WIDTH_PX * 0.5, CONTOUR_LINE_WIDTH_PX * 0.5 + 1.0, dist_px);
+}
+
+// 1 on the lines of a grid with the given spacing (world units), 0 elsewhere. Width in screen pixels, like
+//  contour_line: uniform control flow only.
+fn grid_line(world_xz: vec2<f32>, spacing: f32) -> f32 {
+  let c = world_xz / spacing;
+  let dist_px = abs(fract(c - 0.5) - 0.5) / max(fwidth(c), vec2<f32>(1e-5));
+  let d = min(dist_px.x, dist_px.y);
+  return 1.0 - smoothstep(GRID_LINE_WIDTH_PX * 0.5, GRID_LINE_WIDTH_PX * 0.5 + 1.0, d);
+}
+
+// Tile coordinates are world x/z. Coarser lines are drawn over the finer ones.
+fn apply_grid_lines(color: vec3<f32>, world_xz: vec2<f32>) -> vec3<f32> {
+  let tiles  = grid_line(world_xz, 1.0);
+  let blocks = grid_line(world_xz, MAP_BLOCK_TILES);
+  let chunks = grid_line(world_xz, f32(chunk_tile_num_dim()));
+  var out = color;
+  if ((effects.grid_lines & GRID_LINES_TILES) != 0u) {
+    out = mix(out, GRID_COLOR_TILES.rgb, tiles * GRID_COLOR_TILES.a);
+  }
+  if ((effects.grid_lines & GRID_LINES_BLOCKS) != 0u) {
+    out = mix(out, GRID_COLOR_BLOCKS.rgb, blocks * GRID_COLOR_BLOCKS.a);
+  }
+  if ((effects.grid_lines & GRID_LINES_CHUNKS) != 0u) {
+    out = mix(out, GRID_COLOR_CHUNKS.rgb, chunks * GRID_COLOR_CHUNKS.a);
+  }
+  return out;
+}
+
+fn apply_terrain_overlay(albedo: vec3<f32>, tile: TileUniform, world_y: f32) -> vec3<f32> {
+  let z = world_y * UO_Z_PER_WORLD_UNIT;
+  let contour = contour_line(z);
+  var color = albedo;
+  if (effects.overlay_mode == TERRAIN_OVERLAY_WALKABILITY) {
+    color = mix(color, walkability_color(tile), OVERLAY_TINT_STRENGTH);
+  } else if (effects.overlay_mode == TERRAIN_OVERLAY_ALTITUDE) {
+    color = mix(color, altitude_color(z), ALTITUDE_TINT_STRENGTH);
+  } else if (effects.overlay_mode == TERRAIN_OVERLAY_MAP_DIFF) {
+    color = map_diff_color(color, tile);
+  }
+  if (effects.altitude_params.w >= 0.5) {
+    color *= 1.0 - CONTOUR_LINE_DARKEN * contour;
+  }
+  return color;
+}
+
+// ============================================================================
+// Water: see-through wet tiles and soft shorelines
+// ============================================================================
+
+// UO has nothing under the water, so the "underlying land" is a second sample: the texture of the nearest dry tile,
+//  seen through the water where it's shallow. The depth is faked by the distance from the shore, up to
+//  WATER_SHORE_SEARCH tiles (the data grid border).
+// The shoreline is blended over shore_blend_width tiles across it: dry tiles near the water take some of the water
+//  texture, and the wet ones some of the land.
+const WATER_SHORE_SEARCH: i32 = 2;
+
+struct Shore {
+  distance: f32,     // from the fragment to the nearest tile of the other kind (wet/dry), in tiles
+  tile: TileUniform, // that tile
+  found: bool,       // false: none within WATER_SHORE_SEARCH tiles
+};
+
+fn is_wet(tile: TileUniform) -> bool {
+  return (tile.tile_flags & TILE_FLAG_WET) != 0u;
+}
+
+// Nearest tile around the fragment (local chunk coords) whose wetness isn't `wet`.
+fn find_shore(local_xz: vec2<f32>, wet: bool) -> Shore {
+  let cx = i32(floor(local_xz.x));
+  let cz = i32(floor(local_xz.y));
+  var shore: Shore;
+  shore.distance = f32(WATER_SHORE_SEARCH + 1);
+  shore.found = false;
+  for (var dz = -WATER_SHORE_SEARCH; dz <= WATER_SHORE_SEARCH; dz++) {
+    for (var dx = -WATER_SHORE_SEARCH; dx <= WATER_SHORE_SEARCH; dx++) {
+      let other = tile_at_data_grid(cx + dx, cz + dz);
+      if ((dx == 0 && dz == 0) || is_wet(other) == wet) {
+        continue;
+      }
+      // Distance to the square of the tile.
+      let tile_min = vec2<f32>(f32(cx + dx), f32(cz + dz));
+      let outside = max(max(tile_min - local_xz, local_xz - (tile_min + 1.0)), vec2<f32>(0.0));
+      let distance = length(outside);
+      if (distance < shore.distance) {
+        shore.distance = distance;
+        shore.tile = other;
+        shore.found = true;
+      }
+    }
+  }
+  return shore;
+}
+
+// The albedo of the tile, with the water (or the land) across the shore mixed in. The other tile is sampled with
+//  explicit gradients: this runs in non-uniform control flow.
+fn apply_water(albedo: vec3<f32>, tile: TileUniform, local_xz: vec2<f32>, world_xz: vec2<f32>,
+               ddx_uv: vec2<f32>, ddy_uv: vec2<f32>) -> vec3<f32> {
+  let wet = is_wet(tile);
+  let shore = find_shore(local_xz, wet);
+  if (!shore.found) {
+    return albedo; // Open water, or land away from it.
+  }
+  let transparency     = clamp(effects.water_params.x, 0.0, 1.0);
+  let opaque_distance  = max(effects.water_params.y, 0.01);
+  let half_blend_width = max(effects.water_params.z, 0.0) * 0.5;
+
+  let uv = fract(local_xz);
+  var other = sample_tile_albedo_grad(animate_tile_uv(uv, shore.tile, world_xz), shore.tile, ddx_uv, ddy_uv);
+  other = apply_hue(animate_tile_albedo(other, shore.tile, world_xz), shore.tile.texture_hue);
+  let water = select(other, albedo, wet);
+  let land  = select(albedo, other, wet);
+
+  // Signed distance from the shoreline: positive in the water.
+  let depth = select(-shore.distance, shore.distance, wet);
+  let water_opacity = mix(1.0 - transparency, 1.0, smoothstep(0.0, opaque_distance, max(depth, 0.0)));
+  let water_over_land = mix(land, water, water_opacity);
+  var water_weight = select(0.0, 1.0, wet);
+  if (half_blend_width > 1e-4) {
+    water_weight = smoothstep(-half_blend_width, half_blend_width, depth);
+  }
+  return mix(land, water_over_land, water_weight);
 }
-fn tile_at_13x13(ix: i32, iz: i32) -> TileUniform {
-  return land.tiles[tile_index_clamped(ix, iz)];
+
+// ============================================================================
+// Texture transitions between neighboring tiles
+// ============================================================================
+
+// The classic client cuts the textures at the tile edges. Here, within transition_width / 2 of an edge, the
+//  texture of the tile fades into the neighbor's: the 2×2 tiles around the nearest tile corner are blended
+//  bilinearly, with a ramp as wide as transition_width across each edge.
+// Wet and dry tiles aren't blended here when the water look is on: apply_water blends the shoreline.
+
+fn same_look(a: TileUniform, b: TileUniform) -> bool {
+  return a.texture_layer == b.texture_layer && a.texture_size == b.texture_size && a.texture_hue == b.texture_hue
+    && a.anim_kind == b.anim_kind;
+}
+
+// Albedo of a neighbor, at the fragment. The tile's own albedo is reused for the neighbors looking the same, or
+//  across the shoreline. Explicit gradients: this runs in non-uniform control flow.
+fn neighbor_albedo(albedo: vec3<f32>, tile: TileUniform, neighbor: TileUniform, uv: vec2<f32>,
+                   world_xz: vec2<f32>, ddx_uv: vec2<f32>, ddy_uv: vec2<f32>) -> vec3<f32> {
+  let water_on = effects.water_params.w >= 0.5;
+  if (same_look(tile, neighbor) || (water_on && is_wet(tile) != is_wet(neighbor))) {
+    return albedo;
+  }
+  var color = sample_tile_albedo_grad(animate_tile_uv(uv, neighbor, world_xz), neighbor, ddx_uv, ddy_uv);
+  color = animate_tile_albedo(color, neighbor, world_xz);
+  return apply_hue(color, neighbor.texture_hue);
 }
-fn tile_height_at_13x13(ix: i32, iz: i32) -> f32 {
-  return tile_at_13x13(ix, iz).tile_height;
+
+fn blend_tile_transitions(albedo: vec3<f32>, tile: TileUniform, local_xz: vec2<f32>, world_xz: vec2<f32>,
+                          ddx_uv: vec2<f32>, ddy_uv: vec2<f32>) -> vec3<f32> {
+  let half_width = clamp(effects.transition_width, 0.0, 1.0) * 0.5;
+  // Tile centers are at +0.5: base is the tile left of/above the nearest corner, t goes 0..1 between the centers.
+  let q = local_xz - vec2<f32>(0.5);
+  let base = vec2<i32>(floor(q));
+  let t = fract(q);
+  let w = smoothstep(vec2<f32>(0.5 - half_width), vec2<f32>(0.5 + half_width), t);
+  if ((w.x <= 0.0 || w.x >= 1.0) && (w.y <= 0.0 || w.y >= 1.0)) {
+    // Away from the edges: only this tile.
+    return albedo;
+  }
+  let uv = fract(local_xz);
+  let c00 = neighbor_albedo(albedo, tile, tile_at_data_grid(base.x,      base.y),      uv, world_xz, ddx_uv, ddy_uv);
+  let c10 = neighbor_albedo(albedo, tile, tile_at_data_grid(base.x + 1,  base.y),      uv, world_xz, ddx_uv, ddy_uv);
+  let c01 = neighbor_albedo(albedo, tile, tile_at_data_grid(base.x,      base.y + 1),  uv, world_xz, ddx_uv, ddy_uv);
+  let c11 = neighbor_albedo(albedo, tile, tile_at_data_grid(base.x + 1,  base.y + 1),  uv, world_xz, ddx_uv, ddy_uv);
+  return mix(mix(c00, c10, w.x), mix(c01, c11, w.x), w.y);
 }
 
 // Near the chunk edge, blend normals toward the original to hide seams.
 fn chunk_edge_blend_factor(local_x: f32, local_z: f32) -> f32 {
   let tx = floor(local_x);
   let tz = floor(local_z);
-  let dx = min(tx, f32(CHUNK_TILE_NUM_DIM - 1u) - tx);
-  let dz = min(tz, f32(CHUNK_TILE_NUM_DIM - 1u) - tz);
+  let dx = min(tx, f32(chunk_tile_num_dim() - 1u) - tx);
+  let dz = min(tz, f32(chunk_tile_num_dim() - 1u) - tz);
   let min_dist = min(dx, dz);
   return 1.0 - smoothstep(0.0, 2.0, min_dist);
 }
@@ -238,17 +645,17 @@ fn cubic_value(p0: f32, p1: f32, p2: f32, p3: f32, t: f32) -> f32 {
 
 fn get_geometric_normal_local(node_x: i32, node_z: i32) -> vec3<f32> {
   // Central differences on the discrete grid. Fast but can be “steppy”.
-  let hL = tile_height_at_13x13(node_x - 1, node_z);
-  let hR = tile_height_at_13x13(node_x + 1, node_z);
-  let hD = tile_height_at_13x13(node_x, node_z - 1);
-  let hU = tile_height_at_13x13(node_x, node_z + 1);
+  let hL = tile_height_at_data_grid(node_x - 1, node_z);
+  let hR = tile_height_at_data_grid(node_x + 1, node_z);
+  let hD = tile_height_at_data_grid(node_x, node_z - 1);
+  let hU = tile_height_at_data_grid(node_x, node_z + 1);
   let dHdx = 0.5 * (hR - hL);
   let dHdz = 0.5 * (hU - hD);
   return normalize(vec3<f32>(-dHdx, 1.0, -dHdz));
 }
 
 fn get_bicubic_normal(world_pos: vec3<f32>) -> vec3<f32> {
-  // Smooth analytic normal via bicubic interpolation of the 13×13 tile heights.
+  // Smooth analytic normal via bicubic interpolation of the data grid tile heights.
   // Greatly reduces shading “jaggies” compared to geometric normal above.
   let local_x = world_pos.x - land.chunk_origin.x;
   let local_z = world_pos.z - land.chunk_origin.y;
@@ -261,25 +668,25 @@ fn get_bicubic_normal(world_pos: vec3<f32>) -> vec3<f32> {
   let ix = i32(base_x);
   let iz = i32(base_z);
 
-  let h00 = tile_height_at_13x13(ix - 1, iz - 1);
-  let h10 = tile_height_at_13x13(ix + 0, iz - 1);
-  let h20 = tile_height_at_13x13(ix + 1, iz - 1);
-  let h30 = tile_height_at_13x13(ix + 2, iz - 1);
+  let h00 = tile_height_at_data_grid(ix - 1, iz - 1);
+  let h10 = tile_height_at_data_grid(ix + 0, iz - 1);
+  let h20 = tile_height_at_data_grid(ix + 1, iz - 1);
+  let h30 = tile_height_at_data_grid(ix + 2, iz - 1);
 
-  let h01 = tile_height_at_13x13(ix - 1, iz + 0);
-  let h11 = tile_height_at_13x13(ix + 0, iz + 0);
-  let h21 = tile_height_at_13x13(ix + 1, iz + 0);
-  let h31 = tile_height_at_13x13(ix + 2, iz + 0);
+  let h01 = tile_height_at_data_grid(ix - 1, iz + 0);
+  let h11 = tile_height_at_data_grid(ix + 0, iz + 0);
+  let h21 = tile_height_at_data_grid(ix + 1, iz + 0);
+  let h31 = tile_height_at_data_grid(ix + 2, iz + 0);
 
-  let h02 = tile_height_at_13x13(ix - 1, iz + 1);
-  let h12 = tile_height_at_13x13(ix + 0, iz + 1);
-  let h22 = tile_height_at_13x13(ix + 1, iz + 1);
-  let h32 = tile_height_at_13x13(ix + 2, iz + 1);
+  let h02 = tile_height_at_data_grid(ix - 1, iz + 1);
+  let h12 = tile_height_at_data_grid(ix + 0, iz + 1);
+  let h22 = tile_height_at_data_grid(ix + 1, iz + 1);
+  let h32 = tile_height_at_data_grid(ix + 2, iz + 1);
 
-  let h03 = tile_height_at_13x13(ix - 1, iz + 2);
-  let h13 = tile_height_at_13x13(ix + 0, iz + 2);
-  let h23 = tile_height_at_13x13(ix + 1, iz + 2);
-  let h33 = tile_height_at_13x13(ix + 2, iz + 2);
+  let h03 = tile_height_at_data_grid(ix - 1, iz + 2);
+  let h13 = tile_height_at_data_grid(ix + 0, iz + 2);
+  let h23 = tile_height_at_data_grid(ix + 1, iz + 2);
+  let h33 = tile_height_at_data_grid(ix + 2, iz + 2);
 
   let row0 = cubic_interp_value_and_derivative(h00, h10, h20, h30, frac_x);
   let row1 = cubic_interp_value_and_derivative(h01, h11, h21, h31, frac_x);
@@ -322,11 +729,11 @@ fn get_bent_normal(world_pos: vec3<f32>, base_normal_world: vec3<f32>) -> vec3<f
   let cx = i32(floor(local_x));
   let cz = i32(floor(local_z));
 
-  let hc = tile_height_at_13x13(cx, cz);
-  let hl = tile_height_at_13x13(cx - 1, cz);
-  let hr = tile_height_at_13x13(cx + 1, cz);
-  let hd = tile_height_at_13x13(cx, cz - 1);
-  let hu = tile_height_at_13x13(cx, cz + 1);
+  let hc = tile_height_at_data_grid(cx, cz);
+  let hl = tile_height_at_data_grid(cx - 1, cz);
+  let hr = tile_height_at_data_grid(cx + 1, cz);
+  let hd = tile_height_at_data_grid(cx, cz - 1);
+  let hu = tile_height_at_data_grid(cx, cz + 1);
 
 
   // Use only the *max* positive step: stable across ridges.
@@ -471,6 +878,29 @@ fn sample_tile_albedo_grad(uv: vec2<f32>, tile: TileUniform, ddx_uv: vec2<f32>,
   }
 }
 
+// Tangent-space normal of the tile texture (see texture_cache::land::normal_map): x along +u, y along +v, z out.
+fn sample_tile_normal_grad(uv: vec2<f32>, tile: TileUniform, ddx_uv: vec2<f32>, ddy_uv: vec2<f32>) -> vec3<f32> {
+  let layer: i32 = i32(tile.texture_layer);
+  var encoded: vec3<f32>;
+  if (tile.texture_size == 1u) {
+    encoded = textureSampleGrad(normal_maps_big,   texarray_sampler, uv, layer, ddx_uv, ddy_uv).rgb;
+  } else {
+    encoded = textureSampleGrad(normal_maps_small, texarray_sampler, uv, layer, ddx_uv, ddy_uv).rgb;
+  }
+  return encoded * 2.0 - 1.0;
+}
+
+// Surface detail from the normal map of the tile texture, over the world normal N. The tile UVs run along world +x
+//  and +z, so the tangent frame is N with +x and +z projected on its plane. The strength scales the slopes.
+fn apply_normal_map(N: vec3<f32>, tile: TileUniform, uv: vec2<f32>, ddx_uv: vec2<f32>, ddy_uv: vec2<f32>) -> vec3<f32> {
+  let strength = max(effects.normal_map_params.x, 0.0);
+  let n_tex = sample_tile_normal_grad(uv, tile, ddx_uv, ddy_uv);
+  let n = normalize(vec3<f32>(n_tex.xy * strength, max(n_tex.z, 1e-3)));
+  let T = normalize(vec3<f32>(1.0, 0.0, 0.0) - N * N.x);
+  let B = cross(T, N); // +z on flat ground
+  return normalize(T * n.x + B * n.y + N * n.z);
+}
+
 // cheap per-tile random in [0,1)
 fn rand01_from_tile(ix: i32, iz: i32, layer: u32) -> f32 {
   let p = vec2<f32>(f32(ix) + f32(layer) * 0.618, f32(iz) + f32(layer) * 1.732);
@@ -480,19 +910,17 @@ fn rand01_from_tile(ix: i32, iz: i32, layer: u32) -> f32 {
 // 9-tap blur with radius in *screen pixels* via fwidth — visible regardless of UV scale.
 // LOD is kept stable by using the same gradients for all taps.
 // 9-tap blur with decorrelated directions per tile
-fn blurred_albedo(uv: vec2<f32>, tile: TileUniform, radius_in_pixels: f32, world_xz: vec2<f32>) -> vec3<f32> {
-  // Approximate one screen pixel in UV space for this fragment
-  let fw = fwidth(uv);
+fn blurred_albedo(uv: vec2<f32>, tile: TileUniform, radius_in_pixels: f32, world_xz: vec2<f32>,
+                  ddx_uv: vec2<f32>, ddy_uv: vec2<f32>) -> vec3<f32> {
+  // Approximate one screen pixel in UV space for this fragment (fwidth, from the gradients of the caller)
+  let fw = abs(ddx_uv) + abs(ddy_uv);
   let px_uv = max(fw.x, fw.y) + 1e-6;
 
   // Ensure at least half-pixel radius so it’s *noticeable* even at low zoom
   let min_px = 0.5;
   let r = max(radius_in_pixels, min_px) * px_uv;
 
-  // Keep LOD stable for all taps
-  let ddx_uv = dpdx(uv);
-  let ddy_uv = dpdy(uv);
-
+  // Keep LOD stable for all taps: the same gradients for all of them.
   // decorrelation: derive a tiny rotation per tile/layer (+tiny world jitter)
   let jitter = rand01_from_tile(i32(floor(world_xz.x)), i32(floor(world_xz.y)), tile.texture_layer)
              + fract(world_xz.x * 0.173 + world_xz.y * 0.271) * 0.125;
@@ -548,24 +976,20 @@ fn blurred_albedo(uv: vec2<f32>, tile: TileUniform, radius_in_pixels: f32, world
 
 @vertex
 fn vertex(in: Vertex, @builtin(vertex_index) vertex_index: u32) -> VertexOutput {
+  select_land_slot(in.instance_index);
   var out: VertexOutput;
 
   let shading_mode: u32 = effects.shading_mode;
   let normal_mode:  u32 = effects.normal_mode;
   let enable_bent:  u32 = effects.enable_bent;
 
-  // Node indices in 9×9 grid
-  let grid_x: u32 = vertex_index % MESH_GRID_SIDE;
-  let grid_z: u32 = vertex_index / MESH_GRID_SIDE;
-
-  // Map node to 13×13 data index (+2 border)
-  let arr_x = i32(grid_x) + DATA_GRID_BORDER;
-  let arr_z = i32(grid_z) + DATA_GRID_BORDER;
-  let data_idx = u32(arr_z) * u32(DATA_GRID_SIDE) + u32(arr_x);
+  // Node indices in the mesh grid (9×9 for 8×8 chunks), in tiles: a coarse mesh node spans lod_step tiles
+  let grid_x: u32 = (vertex_index % mesh_grid_side()) * lod_step();
+  let grid_z: u32 = (vertex_index / mesh_grid_side()) * lod_step();
 
-  // Displace by pre-baked height
+  // Displace by pre-baked height (the node maps to the data grid, +2 border)
   var displaced_local_pos = in.position;
-  displaced_local_pos.y = land.tiles[data_idx].tile_height;
+  displaced_local_pos.y = lod_height_at_data_grid(i32(grid_x), i32(grid_z));
 
   // World transform / clip
   let world_from_local = mesh_functions::get_world_from_local(in.instance_index);
@@ -744,6 +1168,7 @@ fn shade_mode2_kr_fragment(base_albedo_in: vec3<f32>,
 
 @fragment
 fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+  select_land_slot(in.instance_index);
   let shading_mode   = effects.shading_mode;
   let normal_mode    = effects.normal_mode;
   let enable_bent    = effects.enable_bent;
@@ -770,15 +1195,30 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
   // Local coords and tile selection
   let local_x = in.world_position.x - land.chunk_origin.x;
   let local_z = in.world_position.z - land.chunk_origin.y;
-  let uv_in_tile = vec2<f32>(fract(local_x), fract(local_z));
-  let tile = tile_at_13x13(i32(floor(local_x)), i32(floor(local_z)));
+  let tile = tile_at_data_grid(i32(floor(local_x)), i32(floor(local_z)));
+  let uv_in_tile = animate_tile_uv(vec2<f32>(fract(local_x), fract(local_z)), tile, in.world_position.xz);
+  // Gradients of the tile UVs for all the texture samples, also those in non-uniform control flow
+  //  (blend_tile_transitions, apply_water, apply_normal_map). Taken from the continuous local coords: the UVs wrap at
+  //  the tile edges, where their own derivatives jump and would pick the smallest mip level along the edges.
+  let ddx_uv = dpdx(vec2<f32>(local_x, local_z));
+  let ddy_uv = dpdy(vec2<f32>(local_x, local_z));
 
   // Base albedo (optionally blurred with screen-pixel radius)
-  var base_albedo = sample_tile_albedo(uv_in_tile, tile);
+  var base_albedo = sample_tile_albedo_grad(uv_in_tile, tile, ddx_uv, ddy_uv);
   if (enable_blur == 1u && blur_strength > 0.001 && blur_radius > 0.0) {
-    let blurred = blurred_albedo(uv_in_tile, tile, blur_radius, vec2<f32>(local_x, local_z));
+    let blurred = blurred_albedo(uv_in_tile, tile, blur_radius, vec2<f32>(local_x, local_z), ddx_uv, ddy_uv);
     base_albedo = mix(base_albedo, blurred, clamp(blur_strength, 0.0, 1.0));
   }
+  base_albedo = animate_tile_albedo(base_albedo, tile, in.world_position.xz);
+  base_albedo = apply_hue(base_albedo, tile.texture_hue);
+  if (effects.transition_width > 1e-4) {
+    base_albedo = blend_tile_transitions(base_albedo, tile, vec2<f32>(local_x, local_z), in.world_position.xz,
+                                         ddx_uv, ddy_uv);
+  }
+  if (effects.water_params.w >= 0.5) {
+    base_albedo = apply_water(base_albedo, tile, vec2<f32>(local_x, local_z), in.world_position.xz, ddx_uv, ddy_uv);
+  }
+  base_albedo = apply_terrain_overlay(base_albedo, tile, in.world_position.y);
   let base_alpha: f32 = 1.0; // tile textures assumed opaque for terrain
 
   // Normals: we already computed in vertex and passed in.world_normal.
@@ -793,6 +1233,9 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
   if (enable_bent == 1u) {
     Nw = get_bent_normal(in.world_position.xyz, Nw);
   }
+  if (shading_mode != 0u && effects.normal_map_params.w >= 0.5) {
+    Nw = apply_normal_map(Nw, tile, uv_in_tile, ddx_uv, ddy_uv);
+  }
 
   // Light & view
   let L = scene.light_direction; // normalized by CPU
@@ -944,5 +1387,6 @@ fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
   }
 
   final_rgb = max(final_rgb, vec3<f32>(0.0));
+  final_rgb = apply_grid_lines(final_rgb, in.world_position.xz);
   return vec4<f32>(final_rgb, base_alpha);
 }
@@ -1,10 +1,15 @@
 pub mod app_states;
 pub mod constants;
 pub mod controls;
+pub mod live_world;
+pub mod loading;
+pub mod map_editor;
 pub mod maps;
+pub mod pathfinding;
 pub mod render;
 pub mod system_sets;
 mod texture_cache;
+mod uo_file_fingerprints;
 mod uo_files_loader;
 
 use crate::{
@@ -19,13 +24,13 @@
     prelude::*,
     render::{
         RenderPlugin,
+        diagnostic::RenderDiagnosticsPlugin,
         settings::{RenderCreation, WgpuFeatures, WgpuSettings},
     },
     window::WindowResolution,
-    winit::{UpdateMode, WinitSettings},
 };
 use bevy_framepace::FramepacePlugin;
-use std::{process::ExitCode, time::Duration};
+use std::process::ExitCode;
 use system_sets::*;
 use tracing_subscriber::fmt;
 
@@ -63,13 +68,6 @@ fn custom_bevy_log_config() -> LogPlugin {
     }
 }
 
-fn custom_winit_settings() -> WinitSettings {
-    WinitSettings {
-        focused_mode: UpdateMode::reactive(Duration::from_secs_f64(1.0 / 60.0)), // 60.0 Hz
-        unfocused_mode: UpdateMode::reactive_low_power(Duration::from_secs_f64(1.0 / 30.0)), // 30.0 Hz
-    }
-}
-
 fn custom_threadpool_settings() -> TaskPoolPlugin {
     TaskPoolPlugin {
         //task_pool_options: TaskPoolOptions::with_num_threads(3),
@@ -77,11 +75,12 @@ fn custom_threadpool_settings() -> TaskPoolPlugin {
     }
 }
 
-fn custom_window_plugin_settings(size: (f32, f32)) -> WindowPlugin {
+fn custom_window_plugin_settings(size: (f32, f32), visible: bool) -> WindowPlugin {
     WindowPlugin {
         primary_window: Some(Window {
             title: "UODynamapper".to_string(),
             resizable: true,
+            visible,
             // Force 1:1 aspect for virtual rendering (game world)
             // UO requires 'virtual' 44×44 diamonds, so...
             resolution: WindowResolution::new(size.0, size.1), //(1320.0, 924.0), // (44*30)x(44*21), etc
@@ -123,7 +122,16 @@ fn custom_render_plugin_settings() -> RenderPlugin {
 }
 
 pub fn run_bevy_app() -> ExitCode {
-    let cwd = std::env::current_dir().unwrap();
+    let render_test_args = match render::render_test::RenderTestArgs::from_args(std::env::args().skip(1)) {
+        Ok(render_test_args) => render_test_args,
+        Err(e) => {
+            logger::system(&format!("Bad command line: {e}"));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // No working directory in the browser: the assets are fetched relative to the page.
+    let cwd = std::env::current_dir().unwrap_or_default();
     let assets_folder = cwd.join(constants::ASSET_FOLDER);
 
     // Current working directory.
@@ -133,6 +141,7 @@ pub fn run_bevy_app() -> ExitCode {
     logger::system(&format!("Setting custom Assets folder: {assets_folder:?}"));
 
     let settings_data = settings::load_from_file();
+    settings_data.log.apply();
     logger::one(
         None,
         LogSev::Info,
@@ -142,20 +151,21 @@ pub fn run_bevy_app() -> ExitCode {
 
     let window_size: (f32, f32) = (settings_data.window.width, settings_data.window.height);
     let wireframe_enabled: bool = settings_data.debug.map_render_wireframe;
+    let gpu_timings_enabled: bool = settings_data.debug.gpu_timings;
 
     let mut app = App::new();
-    let result = app
-        .insert_resource(custom_winit_settings())
+    app.insert_resource(render::frame_rate_ui::winit_settings(&settings_data.frame_rate))
         .add_plugins(
             DefaultPlugins
                 .build()
                 .set(custom_bevy_log_config())
-                .set(custom_window_plugin_settings(window_size))
+                .set(custom_window_plugin_settings(window_size, render_test_args.is_none()))
                 .set(custom_threadpool_settings())
                 .set(custom_render_plugin_settings())
                 .set(ImagePlugin::default_linear())
                 .set(AssetPlugin {
-                    //watch_for_changes_override: true,
+                    // Needed for the hot reload of settings.toml. There's no file watcher in the browser.
+                    watch_for_changes_override: Some(cfg!(not(target_arch = "wasm32"))),
                     file_path: assets_folder.to_str().unwrap().to_string(),
                     ..default()
                 }),
@@ -165,8 +175,7 @@ pub fn run_bevy_app() -> ExitCode {
         //.edit_schedule(Update, |schedule| {
         //  schedule.set_executor_kind(ExecutorKind::SingleThreaded);
         //})
-        .add_plugins(FramepacePlugin) // caps at 60 FPS by default
-        //.use(bevy_framepace::FramepaceSettings::default().with_framerate(30.0))
+        .add_plugins(FramepacePlugin) // The cap is set from the [frame_rate] settings (see frame_rate_ui).
         .add_plugins((
             ExternalDataPlugin {
                 registered_by: "Core",
@@ -174,6 +183,18 @@ pub fn run_bevy_app() -> ExitCode {
             controls::ControlsPlugin {
                 registered_by: "Core",
             },
+            live_world::LiveWorldPlugin {
+                registered_by: "Core",
+            },
+            map_editor::MapEditorPlugin {
+                registered_by: "Core",
+            },
+            maps::manager::MapPlaneManagerPlugin {
+                registered_by: "Core",
+            },
+            maps::compare::MapComparePlugin {
+                registered_by: "Core",
+            },
             render::RenderPlugin {
                 registered_by: "Core",
             },
@@ -186,6 +207,8 @@ pub fn run_bevy_app() -> ExitCode {
         ))
         .init_state::<AppState>()
         .insert_state(AppState::StartupSetup)
+        .add_sub_state::<LoadingPhase>()
+        .init_resource::<loading::LoadingProgress>()
         .configure_sets(
             Startup,
             (
@@ -207,7 +230,19 @@ pub fn run_bevy_app() -> ExitCode {
             Startup,
             advance_state_after_scene_setup_stage_2.after(StartupSysSet::SetupSceneStage2),
         )
-        .run();
+        .add_systems(
+            Update,
+            advance_state_after_loading.run_if(in_state(AppState::Loading)),
+        );
+    if let Some(render_test_args) = render_test_args {
+        // The test renders off-screen, with the window hidden: see render::render_test.
+        app.insert_resource(render_test_args);
+    }
+    if gpu_timings_enabled {
+        // Render pass timings for the diagnostics overlay. Added after the DefaultPlugins: it needs the render app.
+        app.add_plugins(RenderDiagnosticsPlugin);
+    }
+    let result = app.run();
 
     match result {
         AppExit::Success => ExitCode::SUCCESS,
@@ -220,6 +255,18 @@ fn advance_state_after_init_core() {
 }
 
 fn advance_state_after_scene_setup_stage_2(mut next_state: ResMut<NextState<AppState>>) {
+    log_appstate_change("Loading");
+    next_state.set(AppState::Loading);
+}
+
+/// The UO files are read in background while loading: enter the game once every loading step is done.
+fn advance_state_after_loading(
+    loading_progress_r: Res<loading::LoadingProgress>,
+    mut next_state: ResMut<NextState<AppState>>,
+) {
+    if !loading_progress_r.all_done() {
+        return;
+    }
     log_appstate_change("InGame");
     next_state.set(AppState::InGame);
 }
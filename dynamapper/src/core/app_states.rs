@@ -1,5 +1,5 @@
 
-use bevy::state::state::States;
+use bevy::state::state::{States, SubStates};
 use crate::logger;
 
 // OnEnter systems only run for one frame
@@ -7,10 +7,23 @@
 pub enum AppState {
     #[default]
     StartupSetup,
+    /// UO files and textures are loaded, while a loading screen shows the progress.
+    Loading,
     InGame,
     Stop,
 }
 
+/// Phases of AppState::Loading.
+#[derive(strum_macros::AsRefStr, SubStates, Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[source(AppState = AppState::Loading)]
+pub enum LoadingPhase {
+    /// UO files are read in a background task.
+    #[default]
+    UoFiles,
+    /// UO data is available: set up what depends on it, then pre-warm the land textures.
+    Textures,
+}
+
 #[track_caller]
 pub fn log_appstate_change(new_appstate_name: &'static str) {
     logger::one(
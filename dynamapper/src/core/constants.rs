@@ -13,3 +13,13 @@
 // Hardcoded light direction vector.
 pub const BAKED_GLOBAL_LIGHT: Vec3 = Vec3::new(-1.0, 2.5, -1.0);
 
+//------------------------------------
+// Rendering
+//------------------------------------
+
+/// Default max distance (in chunks) of a drawn chunk from the player chunk. Overridden by settings.toml.
+pub const RENDER_DISTANCE_FROM_PLAYER: u32 = 32;
+
+/// Default number of extra chunks drawn on each side of the visible area. Overridden by settings.toml.
+pub const RENDER_CHUNK_PADDING: u32 = 0;
+
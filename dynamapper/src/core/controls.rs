@@ -1,4 +1,9 @@
+pub mod click_to_move;
+pub mod free_camera;
+pub mod key_bindings;
+pub mod location_history;
 pub mod player_movement;
+pub mod touch;
 
 use crate::prelude::*;
 use bevy::prelude::*;
@@ -10,8 +15,25 @@ pub struct ControlsPlugin {
 impl Plugin for ControlsPlugin {
     fn build(&self, app: &mut App) {
         log_plugin_build(self);
-        app.add_plugins((player_movement::PlayerMovementPlugin {
-            registered_by: "ControlsPlugin",
-        },));
+        app.add_plugins((
+            key_bindings::KeyBindingsPlugin {
+                registered_by: "ControlsPlugin",
+            },
+            player_movement::PlayerMovementPlugin {
+                registered_by: "ControlsPlugin",
+            },
+            click_to_move::ClickToMovePlugin {
+                registered_by: "ControlsPlugin",
+            },
+            free_camera::FreeCameraPlugin {
+                registered_by: "ControlsPlugin",
+            },
+            location_history::LocationHistoryPlugin {
+                registered_by: "ControlsPlugin",
+            },
+            touch::TouchPlugin {
+                registered_by: "ControlsPlugin",
+            },
+        ));
     }
 }
@@ -0,0 +1,132 @@
+use std::collections::VecDeque;
+
+use crate::core::controls::player_movement::{MoveDirection, PlayerWalk, step_duration};
+use crate::core::maps::surface::WorldSurface;
+use crate::core::pathfinding::Pathfinder;
+use crate::core::render::scene::picking::TilePicker;
+use crate::core::render::scene::player::Player;
+use crate::core::system_sets::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy_egui::input::EguiWantsInput;
+
+// Mouse movement, like the classic client: clicking (or holding) the right button walks the player to the tile under
+//  the cursor, one tile per step, along a path computed by the pathfinding module. The steps are taken like the ones
+//  of the movement keys (see player_movement).
+
+const MOVE_MOUSE_BUTTON: MouseButton = MouseButton::Right;
+/// While the button is held, the destination follows the cursor: recompute the path at most this often.
+pub const HOLD_REPATH_INTERVAL_SECS: f32 = 0.25;
+
+/// Steps (tile coordinates) left to reach the clicked destination.
+#[derive(Resource, Default)]
+pub struct ClickToMovePath {
+    pub steps: VecDeque<(u32, u32)>,
+}
+impl ClickToMovePath {
+    /// Replaces the steps with a path from a tile to a destination. No steps if it's unreachable.
+    pub fn walk_to(&mut self, pathfinder: &Pathfinder, from: UOVec4, destination: UOVec4) {
+        let path = pathfinder.find_path(from, destination);
+        let msg = match &path {
+            Some(path) => format!(
+                "Click to move: destination ({}, {}), {} steps.",
+                destination.x,
+                destination.y,
+                path.len()
+            ),
+            None => format!("Click to move: destination ({}, {}) unreachable.", destination.x, destination.y),
+        };
+        logger::one(None, LogSev::Debug, LogAbout::Player, &msg);
+        self.steps = path
+            .unwrap_or_default()
+            .into_iter()
+            .map(|pos| (pos.x as u32, pos.y as u32))
+            .collect();
+    }
+}
+
+pub struct ClickToMovePlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(ClickToMovePlugin);
+
+impl Plugin for ClickToMovePlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<ClickToMovePath>().add_systems(
+            Update,
+            (sys_click_to_move_input, sys_click_to_move_step)
+                .chain()
+                .in_set(MovementSysSet::MovementActions)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn sys_click_to_move_input(
+    time_r: Res<Time>,
+    mut since_last_path: Local<f32>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    egui_wants_input_r: Res<EguiWantsInput>,
+    move_dir_r: Res<MoveDirection>,
+    mut path_r: ResMut<ClickToMovePath>,
+    tile_picker: TilePicker,
+    pathfinder: Pathfinder,
+    player_q: Query<&Player>,
+) {
+    *since_last_path += time_r.delta_secs();
+
+    // Keyboard movement takes over.
+    if move_dir_r.dir.is_some() {
+        path_r.steps.clear();
+        return;
+    }
+
+    let repath = mouse_input.just_pressed(MOVE_MOUSE_BUTTON)
+        || (mouse_input.pressed(MOVE_MOUSE_BUTTON) && *since_last_path >= HOLD_REPATH_INTERVAL_SECS);
+    if !repath || egui_wants_input_r.wants_pointer_input() {
+        return;
+    }
+    let (Some(destination), Ok(player)) = (tile_picker.cursor_tile(), player_q.single()) else {
+        return;
+    };
+    *since_last_path = 0.0;
+
+    // The path starts from the tile the player is walking to.
+    if let Some(from) = player.current_pos {
+        path_r.walk_to(&pathfinder, from, destination.to_uo_vec4());
+    }
+}
+
+fn sys_click_to_move_step(
+    settings_r: Res<Settings>,
+    mut path_r: ResMut<ClickToMovePath>,
+    mut walk_r: ResMut<PlayerWalk>,
+    surface: WorldSurface,
+    mut player_q: Query<(&mut Player, &Transform)>,
+) {
+    if path_r.steps.is_empty() || walk_r.is_walking() {
+        return;
+    }
+    let Ok((mut player, transform)) = player_q.single_mut() else {
+        return;
+    };
+    let Some(pos) = player.current_pos else {
+        return;
+    };
+    let Some((x, y)) = path_r.steps.pop_front() else {
+        return;
+    };
+    // The path was found with the same rules: a step not taken means the player was moved meanwhile.
+    let dir = IVec2::new(x as i32 - pos.x as i32, y as i32 - pos.y as i32);
+    let adjacent = dir.x.abs() <= 1 && dir.y.abs() <= 1;
+    if !adjacent || !walk_r.try_step(&surface, &mut player, transform, dir, step_duration(&settings_r)) {
+        logger::one(
+            None,
+            LogSev::Debug,
+            LogAbout::Player,
+            &format!("Click to move: can't step to ({x}, {y}), path dropped."),
+        );
+        path_r.steps.clear();
+    }
+}
@@ -0,0 +1,159 @@
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::render::scene::camera::{PlayerCamera, RenderZoom, cursor_to_world_on_plane};
+use crate::core::render::scene::player::Player;
+use crate::core::render::scene::{SceneStateData, world::WorldGeoData};
+use crate::core::system_sets::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy_egui::input::EguiWantsInput;
+
+// Free camera (spectator) mode, to inspect the map without walking the player around.
+// - InputAction::ToggleFreeCamera detaches the camera from the player (PlayerCamera.free_focus): the movement keys pan
+//   the view instead of moving the player, in screen directions and faster with Shift. Dragging with the middle mouse
+//   button and the left stick of a gamepad pan too. The view stays within the map.
+// - InputAction::SnapCameraToPlayer (or toggling the mode off) centers the camera on the player again, also dropping
+//   the offset left by zooming at the cursor.
+// - The drawn chunks are the ones around the camera focus while the camera is free (see compute_visible_chunks).
+
+const PAN_MOUSE_BUTTON: MouseButton = MouseButton::Middle;
+/// Panning speed multiplier while Shift is held.
+const FAST_PAN_FACTOR: f32 = 4.0;
+
+pub struct FreeCameraPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(FreeCameraPlugin);
+
+impl Plugin for FreeCameraPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.add_systems(
+            Update,
+            (sys_free_camera_keys, sys_free_camera_pan)
+                .chain()
+                .in_set(MovementSysSet::MovementActions)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn sys_free_camera_keys(
+    action_input: ActionInput,
+    mut camera_q: Query<&mut PlayerCamera>,
+    player_q: Query<&Transform, With<Player>>,
+) {
+    let (Ok(mut player_camera), Ok(player_transform)) = (camera_q.single_mut(), player_q.single()) else {
+        return;
+    };
+    if action_input.just_pressed(InputAction::SnapCameraToPlayer) {
+        player_camera.snap_to_player();
+    } else if action_input.just_pressed(InputAction::ToggleFreeCamera) {
+        if player_camera.is_free() {
+            player_camera.snap_to_player();
+        } else {
+            // Start from the current view.
+            player_camera.free_focus = Some(player_camera.focus(player_transform.translation));
+        }
+    } else {
+        return;
+    }
+    let msg = if player_camera.is_free() {
+        "Free camera mode."
+    } else {
+        "Camera back on the player."
+    };
+    logger::one(None, LogSev::Debug, LogAbout::Camera, msg);
+}
+
+/// Pans the free camera with the movement keys, the left stick and by dragging with the middle mouse button.
+fn sys_free_camera_pan(
+    time_r: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    action_input: ActionInput,
+    egui_wants_input_r: Res<EguiWantsInput>,
+    settings_r: Res<Settings>,
+    render_zoom_r: Res<RenderZoom>,
+    world_geo_data_r: Res<WorldGeoData>,
+    scene_state_data_r: Res<SceneStateData>,
+    windows_q: Query<&Window>,
+    mut last_drag_cursor: Local<Option<Vec2>>,
+    mut camera_q: Query<(&Camera, &GlobalTransform, &mut PlayerCamera)>,
+) {
+    let Ok((camera, camera_transform, mut player_camera)) = camera_q.single_mut() else {
+        return;
+    };
+    let Some(free_focus) = player_camera.free_focus else {
+        *last_drag_cursor = None;
+        return;
+    };
+
+    // Keys: in screen directions, so up is up whatever the camera angle. The view covers more tiles when zoomed out,
+    //  so it pans faster.
+    let mut delta = Vec3::ZERO;
+    if !action_input.ui_wants_keyboard() {
+        let forward = camera_transform.forward().with_y(0.0).normalize_or_zero();
+        let right = camera_transform.right().with_y(0.0).normalize_or_zero();
+        for (action, direction) in [
+            (InputAction::MoveNorth, forward),
+            (InputAction::MoveSouth, -forward),
+            (InputAction::MoveWest, -right),
+            (InputAction::MoveEast, right),
+        ] {
+            if action_input.pressed(action) {
+                delta += direction;
+            }
+        }
+        // The stick pans slower when pushed less.
+        let stick = action_input.left_stick();
+        delta = if delta != Vec3::ZERO {
+            delta.normalize()
+        } else {
+            forward * stick.y + right * stick.x
+        };
+        let fast = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let speed = settings_r.input.free_camera_speed * render_zoom_r.0 * if fast { FAST_PAN_FACTOR } else { 1.0 };
+        delta *= speed * time_r.delta_secs();
+    }
+
+    // Mouse drag: the world point grabbed stays under the cursor.
+    //  The drag starts over our view, not over the split view (see split_view).
+    let cursor = windows_q.single().ok().and_then(Window::cursor_position);
+    let cursor_over_view = cursor.is_some_and(|cursor| {
+        camera
+            .logical_viewport_rect()
+            .is_none_or(|rect| rect.contains(cursor))
+    });
+    let dragging = mouse_input.pressed(PAN_MOUSE_BUTTON)
+        && (last_drag_cursor.is_some()
+            || (mouse_input.just_pressed(PAN_MOUSE_BUTTON)
+                && cursor_over_view
+                && !egui_wants_input_r.wants_pointer_input()));
+    if dragging
+        && let (Some(cursor), Some(last_cursor)) = (cursor, *last_drag_cursor)
+        && let (Some(grabbed), Some(now_under_cursor)) = (
+            cursor_to_world_on_plane(camera, camera_transform, last_cursor, free_focus.y),
+            cursor_to_world_on_plane(camera, camera_transform, cursor, free_focus.y),
+        )
+    {
+        delta += grabbed - now_under_cursor;
+    }
+    *last_drag_cursor = if dragging { cursor } else { None };
+
+    if delta != Vec3::ZERO {
+        pan_free_focus(&mut player_camera, delta, &world_geo_data_r, scene_state_data_r.map_id);
+    }
+}
+
+/// Moves the focus of the free camera (if free) by a horizontal delta, within the map.
+pub fn pan_free_focus(player_camera: &mut PlayerCamera, delta: Vec3, world_geo_data: &WorldGeoData, map_id: u32) {
+    let Some(free_focus) = player_camera.free_focus else {
+        return;
+    };
+    let mut free_focus = free_focus + delta.with_y(0.0);
+    if let Some(map_metadata) = world_geo_data.maps.get(&map_id) {
+        free_focus.x = free_focus.x.clamp(0.0, map_metadata.width as f32);
+        free_focus.z = free_focus.z.clamp(0.0, map_metadata.height as f32);
+    }
+    player_camera.free_focus = Some(free_focus);
+}
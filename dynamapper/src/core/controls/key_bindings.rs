@@ -0,0 +1,570 @@
+// Key bindings
+// - Keyboard actions are bound to keys in the [input.key_bindings] table of settings.toml (e.g. move_north = "W").
+//   Actions missing from the table keep their default key.
+// - They can also be bound to gamepad buttons, in the [input.gamepad_bindings] table (e.g. move_north = "DPadUp", or
+//   "None" to unbind the default button). Any connected gamepad triggers them.
+// - Systems check the actions through the ActionInput system param, instead of hard-wired key codes. The analog sticks
+//   and triggers aren't bound to actions: the left stick walks (see player_movement), the right stick and the
+//   triggers zoom (see camera).
+// - While an egui widget has the keyboard focus, the keys are characters typed in it: the systems handling actions
+//   bound to character keys check ActionInput::ui_wants_keyboard first.
+//
+
+use crate::core::system_sets::StartupSysSet;
+use crate::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_egui::input::EguiWantsInput;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    MoveNorth,
+    MoveSouth,
+    MoveWest,
+    MoveEast,
+    NextMapPlane,
+    PrevMapPlane,
+    ExportAroundPlayer,
+    ExportMeshAroundPlayer,
+    ToggleMapExport,
+    ToggleDiagnostics,
+    ToggleMapEditor,
+    /// With Ctrl held, in the map editing mode.
+    UndoMapEdit,
+    /// With Ctrl held, in the map editing mode.
+    RedoMapEdit,
+    ToggleWalkabilityOverlay,
+    ToggleGridOverlay,
+    ToggleFreeCamera,
+    SnapCameraToPlayer,
+    RotateCameraLeft,
+    RotateCameraRight,
+    ToggleGoTo,
+    GoToBookmark1,
+    GoToBookmark2,
+    GoToBookmark3,
+    GoToBookmark4,
+    GoToBookmark5,
+    /// With Alt held.
+    LocationBack,
+    /// With Alt held.
+    LocationForward,
+    ToggleSplitView,
+    ToggleMapCompare,
+    FlipMapCompare,
+    ToggleLiveShard,
+    ToggleShardOverlays,
+    ToggleAnnotations,
+    /// At the tile under the cursor.
+    PlaceAnnotation,
+    /// With Shift: in sextant format.
+    CopyCursorCoordinates,
+    /// With Shift: in sextant format.
+    CopyPlayerCoordinates,
+}
+impl InputAction {
+    pub const ALL: [InputAction; 36] = [
+        InputAction::MoveNorth,
+        InputAction::MoveSouth,
+        InputAction::MoveWest,
+        InputAction::MoveEast,
+        InputAction::NextMapPlane,
+        InputAction::PrevMapPlane,
+        InputAction::ExportAroundPlayer,
+        InputAction::ExportMeshAroundPlayer,
+        InputAction::ToggleMapExport,
+        InputAction::ToggleDiagnostics,
+        InputAction::ToggleMapEditor,
+        InputAction::UndoMapEdit,
+        InputAction::RedoMapEdit,
+        InputAction::ToggleWalkabilityOverlay,
+        InputAction::ToggleGridOverlay,
+        InputAction::ToggleFreeCamera,
+        InputAction::SnapCameraToPlayer,
+        InputAction::RotateCameraLeft,
+        InputAction::RotateCameraRight,
+        InputAction::ToggleGoTo,
+        InputAction::GoToBookmark1,
+        InputAction::GoToBookmark2,
+        InputAction::GoToBookmark3,
+        InputAction::GoToBookmark4,
+        InputAction::GoToBookmark5,
+        InputAction::LocationBack,
+        InputAction::LocationForward,
+        InputAction::ToggleSplitView,
+        InputAction::ToggleMapCompare,
+        InputAction::FlipMapCompare,
+        InputAction::ToggleLiveShard,
+        InputAction::ToggleShardOverlays,
+        InputAction::ToggleAnnotations,
+        InputAction::PlaceAnnotation,
+        InputAction::CopyCursorCoordinates,
+        InputAction::CopyPlayerCoordinates,
+    ];
+    /// Jump to the first bookmarks of the list, in order.
+    pub const GO_TO_BOOKMARK: [InputAction; 5] = [
+        InputAction::GoToBookmark1,
+        InputAction::GoToBookmark2,
+        InputAction::GoToBookmark3,
+        InputAction::GoToBookmark4,
+        InputAction::GoToBookmark5,
+    ];
+
+    /// Name of the action in the [input.key_bindings] table of settings.toml.
+    pub fn setting_name(self) -> &'static str {
+        match self {
+            InputAction::MoveNorth => "move_north",
+            InputAction::MoveSouth => "move_south",
+            InputAction::MoveWest => "move_west",
+            InputAction::MoveEast => "move_east",
+            InputAction::NextMapPlane => "next_map_plane",
+            InputAction::PrevMapPlane => "prev_map_plane",
+            InputAction::ExportAroundPlayer => "export_around_player",
+            InputAction::ExportMeshAroundPlayer => "export_mesh_around_player",
+            InputAction::ToggleMapExport => "toggle_map_export",
+            InputAction::ToggleDiagnostics => "toggle_diagnostics",
+            InputAction::ToggleMapEditor => "toggle_map_editor",
+            InputAction::UndoMapEdit => "undo_map_edit",
+            InputAction::RedoMapEdit => "redo_map_edit",
+            InputAction::ToggleWalkabilityOverlay => "toggle_walkability_overlay",
+            InputAction::ToggleGridOverlay => "toggle_grid_overlay",
+            InputAction::ToggleFreeCamera => "toggle_free_camera",
+            InputAction::SnapCameraToPlayer => "snap_camera_to_player",
+            InputAction::RotateCameraLeft => "rotate_camera_left",
+            InputAction::RotateCameraRight => "rotate_camera_right",
+            InputAction::ToggleGoTo => "toggle_go_to",
+            InputAction::GoToBookmark1 => "go_to_bookmark_1",
+            InputAction::GoToBookmark2 => "go_to_bookmark_2",
+            InputAction::GoToBookmark3 => "go_to_bookmark_3",
+            InputAction::GoToBookmark4 => "go_to_bookmark_4",
+            InputAction::GoToBookmark5 => "go_to_bookmark_5",
+            InputAction::LocationBack => "location_back",
+            InputAction::LocationForward => "location_forward",
+            InputAction::ToggleSplitView => "toggle_split_view",
+            InputAction::ToggleMapCompare => "toggle_map_compare",
+            InputAction::FlipMapCompare => "flip_map_compare",
+            InputAction::ToggleLiveShard => "toggle_live_shard",
+            InputAction::ToggleShardOverlays => "toggle_shard_overlays",
+            InputAction::ToggleAnnotations => "toggle_annotations",
+            InputAction::PlaceAnnotation => "place_annotation",
+            InputAction::CopyCursorCoordinates => "copy_cursor_coordinates",
+            InputAction::CopyPlayerCoordinates => "copy_player_coordinates",
+        }
+    }
+
+    pub fn from_setting_name(name: &str) -> Option<InputAction> {
+        Self::ALL.into_iter().find(|action| action.setting_name() == name)
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InputAction::MoveNorth => "Move north",
+            InputAction::MoveSouth => "Move south",
+            InputAction::MoveWest => "Move west",
+            InputAction::MoveEast => "Move east",
+            InputAction::NextMapPlane => "Next map plane",
+            InputAction::PrevMapPlane => "Previous map plane",
+            InputAction::ExportAroundPlayer => "Export map around player",
+            InputAction::ExportMeshAroundPlayer => "Export 3D mesh around player",
+            InputAction::ToggleMapExport => "Map export (area)",
+            InputAction::ToggleDiagnostics => "Toggle diagnostics overlay",
+            InputAction::ToggleMapEditor => "Toggle map editing mode",
+            InputAction::UndoMapEdit => "Undo map edit (Ctrl +)",
+            InputAction::RedoMapEdit => "Redo map edit (Ctrl +)",
+            InputAction::ToggleWalkabilityOverlay => "Toggle walkability overlay",
+            InputAction::ToggleGridOverlay => "Toggle tile/block/chunk grid",
+            InputAction::ToggleFreeCamera => "Toggle free camera",
+            InputAction::SnapCameraToPlayer => "Camera back to player",
+            InputAction::RotateCameraLeft => "Rotate view 90° left",
+            InputAction::RotateCameraRight => "Rotate view 90° right",
+            InputAction::ToggleGoTo => "Go to coordinates",
+            InputAction::GoToBookmark1 => "Go to bookmark 1",
+            InputAction::GoToBookmark2 => "Go to bookmark 2",
+            InputAction::GoToBookmark3 => "Go to bookmark 3",
+            InputAction::GoToBookmark4 => "Go to bookmark 4",
+            InputAction::GoToBookmark5 => "Go to bookmark 5",
+            InputAction::LocationBack => "Previous location (Alt +)",
+            InputAction::LocationForward => "Next location (Alt +)",
+            InputAction::ToggleSplitView => "Toggle split view",
+            InputAction::ToggleMapCompare => "Map compare (A/B)",
+            InputAction::FlipMapCompare => "Flip compared map version",
+            InputAction::ToggleLiveShard => "Live shard entities",
+            InputAction::ToggleShardOverlays => "Shard regions and spawns",
+            InputAction::ToggleAnnotations => "Annotations",
+            InputAction::PlaceAnnotation => "Place annotation at cursor",
+            InputAction::CopyCursorCoordinates => "Copy cursor coordinates (Shift: sextant)",
+            InputAction::CopyPlayerCoordinates => "Copy player coordinates (Shift: sextant)",
+        }
+    }
+
+    pub fn default_key(self) -> KeyCode {
+        match self {
+            InputAction::MoveNorth => KeyCode::KeyW,
+            InputAction::MoveSouth => KeyCode::KeyS,
+            InputAction::MoveWest => KeyCode::KeyA,
+            InputAction::MoveEast => KeyCode::KeyD,
+            InputAction::NextMapPlane => KeyCode::PageUp,
+            InputAction::PrevMapPlane => KeyCode::PageDown,
+            InputAction::ExportAroundPlayer => KeyCode::F12,
+            InputAction::ExportMeshAroundPlayer => KeyCode::F11,
+            InputAction::ToggleMapExport => KeyCode::KeyX,
+            InputAction::ToggleDiagnostics => KeyCode::F3,
+            InputAction::ToggleMapEditor => KeyCode::F4,
+            InputAction::UndoMapEdit => KeyCode::KeyZ,
+            InputAction::RedoMapEdit => KeyCode::KeyY,
+            InputAction::ToggleWalkabilityOverlay => KeyCode::F5,
+            InputAction::ToggleGridOverlay => KeyCode::F6,
+            InputAction::ToggleFreeCamera => KeyCode::F7,
+            InputAction::SnapCameraToPlayer => KeyCode::Home,
+            InputAction::RotateCameraLeft => KeyCode::KeyQ,
+            InputAction::RotateCameraRight => KeyCode::KeyE,
+            InputAction::ToggleGoTo => KeyCode::F8,
+            InputAction::GoToBookmark1 => KeyCode::Digit1,
+            InputAction::GoToBookmark2 => KeyCode::Digit2,
+            InputAction::GoToBookmark3 => KeyCode::Digit3,
+            InputAction::GoToBookmark4 => KeyCode::Digit4,
+            InputAction::GoToBookmark5 => KeyCode::Digit5,
+            InputAction::LocationBack => KeyCode::ArrowLeft,
+            InputAction::LocationForward => KeyCode::ArrowRight,
+            InputAction::ToggleSplitView => KeyCode::F9,
+            InputAction::ToggleMapCompare => KeyCode::F10,
+            InputAction::FlipMapCompare => KeyCode::KeyB,
+            InputAction::ToggleLiveShard => KeyCode::F2,
+            InputAction::ToggleShardOverlays => KeyCode::F1,
+            InputAction::ToggleAnnotations => KeyCode::KeyN,
+            InputAction::PlaceAnnotation => KeyCode::KeyM,
+            InputAction::CopyCursorCoordinates => KeyCode::KeyC,
+            InputAction::CopyPlayerCoordinates => KeyCode::KeyP,
+        }
+    }
+
+    pub fn default_gamepad_button(self) -> Option<GamepadButton> {
+        match self {
+            InputAction::MoveNorth => Some(GamepadButton::DPadUp),
+            InputAction::MoveSouth => Some(GamepadButton::DPadDown),
+            InputAction::MoveWest => Some(GamepadButton::DPadLeft),
+            InputAction::MoveEast => Some(GamepadButton::DPadRight),
+            InputAction::NextMapPlane => Some(GamepadButton::North),
+            InputAction::PrevMapPlane => Some(GamepadButton::West),
+            InputAction::ToggleFreeCamera => Some(GamepadButton::Select),
+            InputAction::SnapCameraToPlayer => Some(GamepadButton::RightThumb),
+            InputAction::RotateCameraLeft => Some(GamepadButton::LeftTrigger),
+            InputAction::RotateCameraRight => Some(GamepadButton::RightTrigger),
+            InputAction::ToggleGoTo => Some(GamepadButton::Start),
+            _ => None,
+        }
+    }
+}
+
+/// Keys which can be bound, with their name in settings.toml (case insensitive).
+#[rustfmt::skip]
+const KEY_NAMES: &[(&str, KeyCode)] = &[
+    ("A", KeyCode::KeyA), ("B", KeyCode::KeyB), ("C", KeyCode::KeyC), ("D", KeyCode::KeyD), ("E", KeyCode::KeyE),
+    ("F", KeyCode::KeyF), ("G", KeyCode::KeyG), ("H", KeyCode::KeyH), ("I", KeyCode::KeyI), ("J", KeyCode::KeyJ),
+    ("K", KeyCode::KeyK), ("L", KeyCode::KeyL), ("M", KeyCode::KeyM), ("N", KeyCode::KeyN), ("O", KeyCode::KeyO),
+    ("P", KeyCode::KeyP), ("Q", KeyCode::KeyQ), ("R", KeyCode::KeyR), ("S", KeyCode::KeyS), ("T", KeyCode::KeyT),
+    ("U", KeyCode::KeyU), ("V", KeyCode::KeyV), ("W", KeyCode::KeyW), ("X", KeyCode::KeyX), ("Y", KeyCode::KeyY),
+    ("Z", KeyCode::KeyZ),
+    ("0", KeyCode::Digit0), ("1", KeyCode::Digit1), ("2", KeyCode::Digit2), ("3", KeyCode::Digit3),
+    ("4", KeyCode::Digit4), ("5", KeyCode::Digit5), ("6", KeyCode::Digit6), ("7", KeyCode::Digit7),
+    ("8", KeyCode::Digit8), ("9", KeyCode::Digit9),
+    ("F1", KeyCode::F1), ("F2", KeyCode::F2), ("F3", KeyCode::F3), ("F4", KeyCode::F4), ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6), ("F7", KeyCode::F7), ("F8", KeyCode::F8), ("F9", KeyCode::F9), ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11), ("F12", KeyCode::F12),
+    ("Up", KeyCode::ArrowUp), ("Down", KeyCode::ArrowDown), ("Left", KeyCode::ArrowLeft),
+    ("Right", KeyCode::ArrowRight),
+    ("PageUp", KeyCode::PageUp), ("PageDown", KeyCode::PageDown), ("Home", KeyCode::Home), ("End", KeyCode::End),
+    ("Insert", KeyCode::Insert), ("Delete", KeyCode::Delete),
+    ("Space", KeyCode::Space), ("Enter", KeyCode::Enter), ("Tab", KeyCode::Tab), ("Backspace", KeyCode::Backspace),
+    ("Numpad0", KeyCode::Numpad0), ("Numpad1", KeyCode::Numpad1), ("Numpad2", KeyCode::Numpad2),
+    ("Numpad3", KeyCode::Numpad3), ("Numpad4", KeyCode::Numpad4), ("Numpad5", KeyCode::Numpad5),
+    ("Numpad6", KeyCode::Numpad6), ("Numpad7", KeyCode::Numpad7), ("Numpad8", KeyCode::Numpad8),
+    ("Numpad9", KeyCode::Numpad9),
+    ("Minus", KeyCode::Minus), ("Equal", KeyCode::Equal), ("Comma", KeyCode::Comma), ("Period", KeyCode::Period),
+    ("Slash", KeyCode::Slash), ("Semicolon", KeyCode::Semicolon), ("Quote", KeyCode::Quote),
+    ("BracketLeft", KeyCode::BracketLeft), ("BracketRight", KeyCode::BracketRight),
+    ("Backslash", KeyCode::Backslash), ("Backquote", KeyCode::Backquote),
+];
+
+pub fn key_from_name(name: &str) -> Option<KeyCode> {
+    let name = name.trim();
+    KEY_NAMES
+        .iter()
+        .find(|(key_name, _)| key_name.eq_ignore_ascii_case(name))
+        .map(|&(_, key)| key)
+}
+
+/// Name of a key, or None if it can't be bound.
+pub fn key_name(key: KeyCode) -> Option<&'static str> {
+    KEY_NAMES
+        .iter()
+        .find(|&&(_, named_key)| named_key == key)
+        .map(|&(name, _)| name)
+}
+
+/// Gamepad buttons which can be bound, with their name in settings.toml (case insensitive). The shoulder buttons are
+///  LeftBumper and RightBumper, the analog triggers LeftTrigger and RightTrigger.
+#[rustfmt::skip]
+const GAMEPAD_BUTTON_NAMES: &[(&str, GamepadButton)] = &[
+    ("South", GamepadButton::South), ("East", GamepadButton::East), ("North", GamepadButton::North),
+    ("West", GamepadButton::West),
+    ("LeftBumper", GamepadButton::LeftTrigger), ("RightBumper", GamepadButton::RightTrigger),
+    ("LeftTrigger", GamepadButton::LeftTrigger2), ("RightTrigger", GamepadButton::RightTrigger2),
+    ("Select", GamepadButton::Select), ("Start", GamepadButton::Start), ("Mode", GamepadButton::Mode),
+    ("LeftStick", GamepadButton::LeftThumb), ("RightStick", GamepadButton::RightThumb),
+    ("DPadUp", GamepadButton::DPadUp), ("DPadDown", GamepadButton::DPadDown),
+    ("DPadLeft", GamepadButton::DPadLeft), ("DPadRight", GamepadButton::DPadRight),
+];
+/// Name of an action without gamepad button in settings.toml.
+pub const NO_GAMEPAD_BUTTON_NAME: &str = "None";
+
+pub fn gamepad_button_from_name(name: &str) -> Option<GamepadButton> {
+    let name = name.trim();
+    GAMEPAD_BUTTON_NAMES
+        .iter()
+        .find(|(button_name, _)| button_name.eq_ignore_ascii_case(name))
+        .map(|&(_, button)| button)
+}
+
+/// Name of a gamepad button, or None if it can't be bound.
+pub fn gamepad_button_name(button: GamepadButton) -> Option<&'static str> {
+    GAMEPAD_BUTTON_NAMES
+        .iter()
+        .find(|&&(_, named_button)| named_button == button)
+        .map(|&(name, _)| name)
+}
+
+/// Key and gamepad button bound to each input action.
+#[derive(Resource, Clone, Debug)]
+pub struct KeyBindings {
+    keys: HashMap<InputAction, KeyCode>,
+    buttons: HashMap<InputAction, GamepadButton>,
+}
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            keys: InputAction::ALL
+                .into_iter()
+                .map(|action| (action, action.default_key()))
+                .collect(),
+            buttons: InputAction::ALL
+                .into_iter()
+                .filter_map(|action| Some((action, action.default_gamepad_button()?)))
+                .collect(),
+        }
+    }
+}
+impl KeyBindings {
+    /// Unknown actions or key names are reported and skipped.
+    pub fn from_settings(input_settings: &SectInput) -> Self {
+        let mut bindings = Self::default();
+        for (action_name, key_name) in &input_settings.key_bindings {
+            let Some(action) = InputAction::from_setting_name(action_name) else {
+                logger::one(
+                    None,
+                    LogSev::Warn,
+                    LogAbout::General,
+                    &format!("Unknown input action '{action_name}' in the key bindings."),
+                );
+                continue;
+            };
+            match key_from_name(key_name) {
+                Some(key) => bindings.set_key(action, key),
+                None => logger::one(
+                    None,
+                    LogSev::Warn,
+                    LogAbout::General,
+                    &format!("Unknown key '{key_name}' bound to '{action_name}': using the default key."),
+                ),
+            }
+        }
+        for (action_name, button_name) in &input_settings.gamepad_bindings {
+            let Some(action) = InputAction::from_setting_name(action_name) else {
+                logger::one(
+                    None,
+                    LogSev::Warn,
+                    LogAbout::General,
+                    &format!("Unknown input action '{action_name}' in the gamepad bindings."),
+                );
+                continue;
+            };
+            if button_name.trim().eq_ignore_ascii_case(NO_GAMEPAD_BUTTON_NAME) {
+                bindings.set_button(action, None);
+                continue;
+            }
+            match gamepad_button_from_name(button_name) {
+                Some(button) => bindings.set_button(action, Some(button)),
+                None => logger::one(
+                    None,
+                    LogSev::Warn,
+                    LogAbout::General,
+                    &format!(
+                        "Unknown gamepad button '{button_name}' bound to '{action_name}': using the default button."
+                    ),
+                ),
+            }
+        }
+        bindings
+    }
+
+    /// The [input.key_bindings] table, as stored in settings.toml.
+    pub fn to_settings_table(&self) -> BTreeMap<String, String> {
+        InputAction::ALL
+            .into_iter()
+            .filter_map(|action| Some((action.setting_name().to_owned(), key_name(self.key(action))?.to_owned())))
+            .collect()
+    }
+
+    /// The [input.gamepad_bindings] table, as stored in settings.toml. Actions without button are listed too, so that
+    ///  they don't get their default one back.
+    pub fn to_gamepad_settings_table(&self) -> BTreeMap<String, String> {
+        InputAction::ALL
+            .into_iter()
+            .map(|action| {
+                let button_name = self
+                    .button(action)
+                    .and_then(gamepad_button_name)
+                    .unwrap_or(NO_GAMEPAD_BUTTON_NAME);
+                (action.setting_name().to_owned(), button_name.to_owned())
+            })
+            .collect()
+    }
+
+    pub fn key(&self, action: InputAction) -> KeyCode {
+        self.keys[&action]
+    }
+
+    pub fn set_key(&mut self, action: InputAction, key: KeyCode) {
+        self.keys.insert(action, key);
+    }
+
+    /// Binds a key to an action. If the key was bound to another action, that one gets the previous key of this one.
+    pub fn rebind(&mut self, action: InputAction, key: KeyCode) {
+        let previous_key = self.key(action);
+        if let Some(other_action) = self.action_of_key(key)
+            && other_action != action
+        {
+            self.set_key(other_action, previous_key);
+        }
+        self.set_key(action, key);
+    }
+
+    pub fn action_of_key(&self, key: KeyCode) -> Option<InputAction> {
+        InputAction::ALL.into_iter().find(|action| self.key(*action) == key)
+    }
+
+    pub fn button(&self, action: InputAction) -> Option<GamepadButton> {
+        self.buttons.get(&action).copied()
+    }
+
+    pub fn set_button(&mut self, action: InputAction, button: Option<GamepadButton>) {
+        match button {
+            Some(button) => self.buttons.insert(action, button),
+            None => self.buttons.remove(&action),
+        };
+    }
+
+    /// Binds a gamepad button to an action, swapping it with the action it was bound to, like rebind.
+    pub fn rebind_button(&mut self, action: InputAction, button: GamepadButton) {
+        let previous_button = self.button(action);
+        if let Some(other_action) = self.action_of_button(button)
+            && other_action != action
+        {
+            self.set_button(other_action, previous_button);
+        }
+        self.set_button(action, Some(button));
+    }
+
+    pub fn action_of_button(&self, button: GamepadButton) -> Option<InputAction> {
+        InputAction::ALL
+            .into_iter()
+            .find(|action| self.button(*action) == Some(button))
+    }
+}
+
+/// The input actions, from the keyboard and the gamepads through the KeyBindings, and the analog gamepad input.
+#[derive(SystemParam)]
+pub struct ActionInput<'w, 's> {
+    keyboard_input_r: Res<'w, ButtonInput<KeyCode>>,
+    key_bindings_r: Res<'w, KeyBindings>,
+    gamepads_q: Query<'w, 's, &'static Gamepad>,
+    egui_wants_input_r: Res<'w, EguiWantsInput>,
+}
+impl ActionInput<'_, '_> {
+    pub fn pressed(&self, action: InputAction) -> bool {
+        self.keyboard_input_r.pressed(self.key_bindings_r.key(action))
+            || self
+                .key_bindings_r
+                .button(action)
+                .is_some_and(|button| self.gamepads_q.iter().any(|gamepad| gamepad.pressed(button)))
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        self.keyboard_input_r.just_pressed(self.key_bindings_r.key(action))
+            || self
+                .key_bindings_r
+                .button(action)
+                .is_some_and(|button| self.gamepads_q.iter().any(|gamepad| gamepad.just_pressed(button)))
+    }
+
+    /// An egui widget (e.g. a text field) has the keyboard focus: the pressed keys are characters being typed in it,
+    ///  not actions.
+    pub fn ui_wants_keyboard(&self) -> bool {
+        self.egui_wants_input_r.wants_keyboard_input()
+    }
+
+    /// Left stick position (up is +y), summed over the gamepads. Within the unit circle.
+    pub fn left_stick(&self) -> Vec2 {
+        self.gamepads_q
+            .iter()
+            .map(Gamepad::left_stick)
+            .sum::<Vec2>()
+            .clamp_length_max(1.0)
+    }
+
+    /// Right stick position (up is +y), summed over the gamepads. Within the unit circle.
+    pub fn right_stick(&self) -> Vec2 {
+        self.gamepads_q
+            .iter()
+            .map(Gamepad::right_stick)
+            .sum::<Vec2>()
+            .clamp_length_max(1.0)
+    }
+
+    /// How far an analog button (e.g. a trigger) is pressed, from 0 to 1, on the gamepad pressing it the most.
+    pub fn analog_button(&self, button: GamepadButton) -> f32 {
+        self.gamepads_q
+            .iter()
+            .filter_map(|gamepad| gamepad.get(button))
+            .fold(0.0, f32::max)
+    }
+}
+
+pub struct KeyBindingsPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(KeyBindingsPlugin);
+impl Plugin for KeyBindingsPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<KeyBindings>()
+            .add_systems(Startup, sys_setup_key_bindings.in_set(StartupSysSet::First))
+            .add_systems(Update, sys_evlisten_reload_key_bindings);
+    }
+}
+
+fn sys_setup_key_bindings(settings_r: Res<Settings>, mut key_bindings_r: ResMut<KeyBindings>) {
+    log_system_add_startup::<KeyBindingsPlugin>(StartupSysSet::First, fname!());
+    *key_bindings_r = KeyBindings::from_settings(&settings_r.input);
+}
+
+fn sys_evlisten_reload_key_bindings(
+    mut events: EventReader<KeyBindingsSettingsChangedEvent>,
+    settings_r: Res<Settings>,
+    mut key_bindings_r: ResMut<KeyBindings>,
+) {
+    if events.read().last().is_some() {
+        *key_bindings_r = KeyBindings::from_settings(&settings_r.input);
+    }
+}
@@ -0,0 +1,139 @@
+use std::collections::VecDeque;
+
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::render::scene::player::{Player, TeleportPlayerEvent};
+use crate::core::system_sets::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+
+// Back/forward navigation between the places the player teleported from, like in a web browser.
+// - Every teleport (Go to window, bookmarks) records the location the player left: going back returns there, going
+//   forward redoes the teleport. A new teleport clears the locations which could be gone forward to.
+// - Alt + InputAction::LocationBack / LocationForward (Left / Right by default), or the buttons of the Go to window,
+//   send a NavigateLocationHistoryEvent. The move is a TeleportPlayerEvent, marked so that it isn't recorded again.
+
+/// Locations kept to go back to: the oldest ones are dropped.
+const LOCATION_HISTORY_MAX_LEN: usize = 50;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LocationHistoryDirection {
+    Back,
+    Forward,
+}
+
+/// Request to go back to the previous location, or forward to the next one.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct NavigateLocationHistoryEvent(pub LocationHistoryDirection);
+
+#[derive(Resource, Default)]
+pub struct LocationHistory {
+    // Most recent last, in both.
+    back: VecDeque<UOVec4>,
+    forward: Vec<UOVec4>,
+}
+impl LocationHistory {
+    /// The player left this location for another one.
+    pub fn record(&mut self, from: UOVec4) {
+        self.back.push_back(from);
+        if self.back.len() > LOCATION_HISTORY_MAX_LEN {
+            self.back.pop_front();
+        }
+        self.forward.clear();
+    }
+
+    pub fn back_len(&self) -> usize {
+        self.back.len()
+    }
+
+    pub fn forward_len(&self) -> usize {
+        self.forward.len()
+    }
+
+    pub fn next(&self, direction: LocationHistoryDirection) -> Option<UOVec4> {
+        match direction {
+            LocationHistoryDirection::Back => self.back.back().copied(),
+            LocationHistoryDirection::Forward => self.forward.last().copied(),
+        }
+    }
+
+    /// Location to move to from `current`, which becomes the next one in the opposite direction.
+    fn step(&mut self, direction: LocationHistoryDirection, current: UOVec4) -> Option<UOVec4> {
+        match direction {
+            LocationHistoryDirection::Back => {
+                let location = self.back.pop_back()?;
+                self.forward.push(current);
+                Some(location)
+            }
+            LocationHistoryDirection::Forward => {
+                let location = self.forward.pop()?;
+                self.back.push_back(current);
+                Some(location)
+            }
+        }
+    }
+}
+
+pub struct LocationHistoryPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(LocationHistoryPlugin);
+
+impl Plugin for LocationHistoryPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<LocationHistory>()
+            .add_event::<NavigateLocationHistoryEvent>()
+            .add_systems(
+                Update,
+                (sys_location_history_shortcuts, sys_navigate_location_history)
+                    .chain()
+                    .in_set(MovementSysSet::MovementActions)
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_location_history_shortcuts(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    action_input: ActionInput,
+    mut writer: EventWriter<NavigateLocationHistoryEvent>,
+) {
+    if action_input.ui_wants_keyboard() || !keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight]) {
+        return;
+    }
+    if action_input.just_pressed(InputAction::LocationBack) {
+        writer.write(NavigateLocationHistoryEvent(LocationHistoryDirection::Back));
+    }
+    if action_input.just_pressed(InputAction::LocationForward) {
+        writer.write(NavigateLocationHistoryEvent(LocationHistoryDirection::Forward));
+    }
+}
+
+fn sys_navigate_location_history(
+    mut events: EventReader<NavigateLocationHistoryEvent>,
+    mut history_r: ResMut<LocationHistory>,
+    player_q: Query<(&Player, &Transform)>,
+    mut teleport_writer: EventWriter<TeleportPlayerEvent>,
+) {
+    // One step per frame: the player location only changes with the teleport.
+    let Some(&NavigateLocationHistoryEvent(direction)) = events.read().last() else {
+        return;
+    };
+    let Some(current) = player_q
+        .single()
+        .ok()
+        .and_then(|(player, transform)| player.location(transform))
+    else {
+        return;
+    };
+    let Some(location) = history_r.step(direction, current) else {
+        return;
+    };
+    teleport_writer.write(TeleportPlayerEvent {
+        x: location.x,
+        y: location.y,
+        z: Some(location.z),
+        map_id: location.m as u32,
+        from_history: true,
+    });
+}
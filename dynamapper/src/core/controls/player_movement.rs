@@ -1,9 +1,23 @@
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::maps::surface::WorldSurface;
+use crate::core::render::scene::camera::PlayerCamera;
 use crate::core::render::scene::player::Player;
 use crate::core::system_sets::*;
 use crate::prelude::*;
 use bevy::prelude::*;
 
-const MOVE_COOLDOWN: f32 = 0.01; // seconds
+// Walking, one tile per step, with the movement keys, the left stick of a gamepad or along a click-to-move path (see
+//  click_to_move).
+// - A step goes to one of the 8 neighbor tiles, at the z reached stepping into it (see maps::surface): on the land or
+//   on a surface, climbing at most MAX_STEP_UP_Z. Tiles without such a spot (impassable, water, too steep) can't be
+//   entered, and diagonal steps can't cut the corner of one.
+// - Player::current_pos moves to the new tile when the step starts. The transform follows it over the step time, so
+//   the player and the camera glide from tile to tile, up and down the slopes.
+
+/// Time a step takes, at movement_speed_multiplier 1.0: the walking speed of the classic client.
+pub const STEP_INTERVAL_SECS: f32 = 0.1;
+/// How far the left stick must be pushed to walk.
+const STICK_WALK_THRESHOLD: f32 = 0.5;
 
 pub struct PlayerMovementPlugin {
     pub registered_by: &'static str,
@@ -12,61 +26,185 @@ pub struct PlayerMovementPlugin {
 impl Plugin for PlayerMovementPlugin {
     fn build(&self, app: &mut App) {
         log_plugin_build(self);
-        app
-            .insert_resource(MoveCooldown(Timer::from_seconds(
-                MOVE_COOLDOWN,
-                TimerMode::Repeating,
-            )))
-            .insert_resource(MoveDirection::default())
-            .add_systems(Update, (sys_player_input, sys_player_move).in_set(MovementSysSet::MovementActions));
+        app.init_resource::<MoveDirection>()
+            .init_resource::<PlayerWalk>()
+            .add_systems(
+                Update,
+                (sys_player_input, sys_player_move)
+                    .chain()
+                    .in_set(MovementSysSet::MovementActions)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                sys_advance_player_walk
+                    .after(MovementSysSet::MovementActions)
+                    .before(MovementSysSet::UpdateCamera)
+                    .run_if(in_state(AppState::InGame)),
+            );
     }
 }
 
-#[derive(Resource, Default)]
-pub struct MoveCooldown(Timer);
-
 #[derive(Debug, Default, Resource)]
 pub struct MoveDirection {
     pub dir: Option<IVec2>,
 }
-// Reads WASD "intent" and stores it
-fn sys_player_input(
-    keyboard_input: Res<ButtonInput<KeyCode>>,
-    mut move_dir: ResMut<MoveDirection>,
-) {
+
+/// A step in progress: the player transform going from a tile to the next one.
+struct WalkStep {
+    from: Vec3,
+    to: Vec3,
+    elapsed: f32,
+    duration: f32,
+}
+
+#[derive(Resource, Default)]
+pub struct PlayerWalk {
+    step: Option<WalkStep>,
+}
+impl PlayerWalk {
+    pub fn is_walking(&self) -> bool {
+        self.step.is_some()
+    }
+
+    /// Drops the step in progress, e.g. when the player is moved elsewhere: the transform stays where it's put.
+    pub fn stop(&mut self) {
+        self.step = None;
+    }
+
+    /// Starts a step in the given direction (one of the 8 neighbors), if the rules allow it. Returns whether it did.
+    pub fn try_step(
+        &mut self,
+        surface: &WorldSurface,
+        player: &mut Player,
+        transform: &Transform,
+        dir: IVec2,
+        duration: f32,
+    ) -> bool {
+        let Some(pos) = player.location(transform) else {
+            return false;
+        };
+        let dir = dir.clamp(IVec2::NEG_ONE, IVec2::ONE);
+        if dir == IVec2::ZERO {
+            return false;
+        }
+        let (map_id, from_z) = (pos.m as u32, pos.z as i32);
+        let (x, y) = (pos.x as i32, pos.y as i32);
+        let step_z = |dx: i32, dy: i32| {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 {
+                return None;
+            }
+            surface.step_z(map_id, nx as u32, ny as u32, from_z)
+        };
+        let Some(z) = step_z(dir.x, dir.y) else {
+            return false;
+        };
+        // Don't cut the corners of blocked tiles.
+        if dir.x != 0 && dir.y != 0 && (step_z(dir.x, 0).is_none() || step_z(0, dir.y).is_none()) {
+            return false;
+        }
+
+        let destination = UOVec4::new((x + dir.x) as u16, (y + dir.y) as u16, z as i8, pos.m);
+        player.current_pos = Some(destination);
+        self.step = Some(WalkStep {
+            from: transform.translation,
+            to: destination.to_bevy_vec3_ignore_map(),
+            elapsed: 0.0,
+            duration: duration.max(f32::EPSILON),
+        });
+        true
+    }
+}
+
+/// Time a step takes with the current settings.
+pub fn step_duration(settings: &Settings) -> f32 {
+    STEP_INTERVAL_SECS / settings.input.movement_speed_multiplier.max(f32::EPSILON)
+}
+
+/// Screen direction (up is north) of the 8 neighbors the stick points to, or zero if not pushed far enough.
+fn stick_direction(stick: Vec2) -> IVec2 {
+    if stick.length() < STICK_WALK_THRESHOLD {
+        return IVec2::ZERO;
+    }
+    // 8 sectors of 45°: a direction has a component along an axis if within 67.5° of it.
+    let stick = stick.normalize();
+    let component = |value: f32| {
+        if value.abs() < std::f32::consts::FRAC_PI_8.sin() {
+            0
+        } else {
+            value.signum() as i32
+        }
+    };
+    IVec2::new(component(stick.x), -component(stick.y))
+}
+
+// Reads the movement keys (or left stick) "intent" and stores it
+fn sys_player_input(action_input: ActionInput, mut move_dir: ResMut<MoveDirection>, camera_q: Query<&PlayerCamera>) {
+    // The free camera uses the movement keys to pan.
+    if camera_q.single().is_ok_and(PlayerCamera::is_free) {
+        move_dir.dir = None;
+        return;
+    }
     let mut dir = IVec2::ZERO;
-    if keyboard_input.pressed(KeyCode::KeyW) {
+    if action_input.pressed(InputAction::MoveNorth) {
         dir.y -= 1;
     }
-    if keyboard_input.pressed(KeyCode::KeyS) {
+    if action_input.pressed(InputAction::MoveSouth) {
         dir.y += 1;
     }
-    if keyboard_input.pressed(KeyCode::KeyA) {
+    if action_input.pressed(InputAction::MoveWest) {
         dir.x -= 1;
     }
-    if keyboard_input.pressed(KeyCode::KeyD) {
+    if action_input.pressed(InputAction::MoveEast) {
         dir.x += 1;
     }
+    if dir == IVec2::ZERO {
+        dir = stick_direction(action_input.left_stick());
+    }
+    // Keys move on screen the same way whatever the view rotation.
+    if let Ok(camera) = camera_q.single() {
+        dir = camera.rotate_map_direction(dir);
+    }
     move_dir.dir = if dir != IVec2::ZERO { Some(dir) } else { None };
 }
 
+/// Steps in the direction of the keys, once the previous step is over.
 fn sys_player_move(
-    time: Res<Time>,
-    mut cooldown: ResMut<MoveCooldown>,
+    settings_r: Res<Settings>,
     move_dir: Res<MoveDirection>,
-    mut query: Query<&mut Transform, With<Player>>,
+    mut walk_r: ResMut<PlayerWalk>,
+    surface: WorldSurface,
+    mut player_q: Query<(&mut Player, &Transform)>,
 ) {
-    cooldown.0.tick(time.delta());
+    let Some(dir) = move_dir.dir else {
+        return;
+    };
+    if walk_r.is_walking() {
+        return;
+    }
+    let Ok((mut player, transform)) = player_q.single_mut() else {
+        return;
+    };
+    walk_r.try_step(&surface, &mut player, transform, dir, step_duration(&settings_r));
+}
 
-    // Only move if cooldown finished and a direction is pressed
-    if cooldown.0.finished() {
-        if let Some(dir) = move_dir.dir {
-            for mut transform in query.iter_mut() {
-                // Move by exactly 1.0 per tile/step
-                let delta = Vec3::new(dir.x as f32, 0.0, dir.y as f32);
-                transform.translation += delta;
-            }
-            cooldown.0.reset();
-        }
+/// Moves the player transform along the step in progress.
+fn sys_advance_player_walk(
+    time_r: Res<Time>,
+    mut walk_r: ResMut<PlayerWalk>,
+    mut player_q: Query<&mut Transform, With<Player>>,
+) {
+    let Some(step) = walk_r.step.as_mut() else {
+        return;
+    };
+    let Ok(mut transform) = player_q.single_mut() else {
+        return;
+    };
+    step.elapsed += time_r.delta_secs();
+    let t = (step.elapsed / step.duration).min(1.0);
+    transform.translation = step.from.lerp(step.to, t);
+    if t >= 1.0 {
+        walk_r.stop();
     }
 }
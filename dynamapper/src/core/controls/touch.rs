@@ -0,0 +1,234 @@
+use crate::core::controls::click_to_move::{ClickToMovePath, HOLD_REPATH_INTERVAL_SECS};
+use crate::core::controls::free_camera::pan_free_focus;
+use crate::core::pathfinding::Pathfinder;
+use crate::core::render::scene::camera::{PlayerCamera, ZoomTarget, cursor_to_world_on_plane, max_zoom};
+use crate::core::render::scene::picking::TilePicker;
+use crate::core::render::scene::player::{Player, TeleportPlayerEvent};
+use crate::core::render::scene::{SceneStateData, world::WorldGeoData};
+use crate::core::system_sets::*;
+use crate::prelude::*;
+use bevy::input::touch::{Touch, Touches};
+use bevy::prelude::*;
+use bevy_egui::input::EguiWantsInput;
+use std::collections::{HashMap, HashSet};
+
+// Touch gestures, for tablets and touch screens.
+// - One finger drag: in free camera mode, pans the view (the touched world point stays under the finger). Otherwise,
+//   walks the player towards the tile under the finger, like holding the right mouse button (see click_to_move). The
+//   walk starts once the finger moved or was held beyond a tap, so that the taps of a double tap don't start one.
+// - Two finger pinch: zooms, at the middle of the fingers if window.zoom_to_cursor is set (like the mouse wheel).
+// - Double tap: teleports the player to the tapped tile.
+// Touches starting over an egui window are left to egui. Once two fingers are down, the gesture is a pinch until all of
+//  them are lifted.
+
+/// A touch lifted within this time and distance (logical pixels) from where it started is a tap.
+const TAP_MAX_SECS: f64 = 0.3;
+const TAP_MAX_DISTANCE: f32 = 12.0;
+/// Two taps within this time and distance make a double tap.
+const DOUBLE_TAP_MAX_SECS: f64 = 0.4;
+const DOUBLE_TAP_MAX_DISTANCE: f32 = 30.0;
+
+#[derive(Resource, Default)]
+struct TouchGestures {
+    /// Touches started over an egui window.
+    ignored: HashSet<u64>,
+    /// Elapsed time when each touch started.
+    started_at: HashMap<u64, f64>,
+    /// Two fingers were down: no drag nor tap until all are lifted.
+    pinching: bool,
+    /// Time and position of the last tap, waiting for a second one.
+    last_tap: Option<(f64, Vec2)>,
+}
+impl TouchGestures {
+    /// The touches in use by the gestures.
+    fn active<'a>(&self, touches: &'a Touches) -> Vec<&'a Touch> {
+        touches
+            .iter()
+            .filter(|touch| !self.ignored.contains(&touch.id()))
+            .collect()
+    }
+
+    /// The touch moved or was held beyond a tap (TAP_MAX_DISTANCE, TAP_MAX_SECS).
+    fn is_beyond_tap(&self, touch: &Touch, now: f64) -> bool {
+        let held_secs = self
+            .started_at
+            .get(&touch.id())
+            .map_or(f64::INFINITY, |started_at| now - started_at);
+        held_secs > TAP_MAX_SECS || touch.start_position().distance(touch.position()) > TAP_MAX_DISTANCE
+    }
+}
+
+pub struct TouchPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(TouchPlugin);
+
+impl Plugin for TouchPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<TouchGestures>().add_systems(
+            Update,
+            (
+                sys_track_touches,
+                sys_touch_pinch_zoom,
+                sys_touch_drag,
+                sys_touch_double_tap,
+            )
+                .chain()
+                .in_set(MovementSysSet::MovementActions)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn sys_track_touches(
+    time_r: Res<Time>,
+    touches_r: Res<Touches>,
+    egui_wants_input_r: Res<EguiWantsInput>,
+    mut gestures_r: ResMut<TouchGestures>,
+) {
+    let now = time_r.elapsed_secs_f64();
+    for touch in touches_r.iter_just_pressed() {
+        if egui_wants_input_r.wants_pointer_input() {
+            gestures_r.ignored.insert(touch.id());
+        } else {
+            gestures_r.started_at.insert(touch.id(), now);
+        }
+    }
+    if gestures_r.active(&touches_r).len() >= 2 {
+        gestures_r.pinching = true;
+        gestures_r.last_tap = None;
+    }
+    // Lifted touches are cleaned up by sys_touch_double_tap, which still needs them.
+}
+
+/// Scales the zoom by how much the distance between the first two fingers changed.
+fn sys_touch_pinch_zoom(
+    touches_r: Res<Touches>,
+    gestures_r: Res<TouchGestures>,
+    settings_r: Res<Settings>,
+    mut zoom_target_r: ResMut<ZoomTarget>,
+) {
+    let active = gestures_r.active(&touches_r);
+    let [first, second, ..] = active[..] else {
+        return;
+    };
+    let previous_distance = first.previous_position().distance(second.previous_position());
+    let distance = first.position().distance(second.position());
+    if previous_distance <= 0.0 || distance <= 0.0 || previous_distance == distance {
+        return;
+    }
+    // Spreading the fingers zooms in: a smaller scale.
+    let zoom = (zoom_target_r.zoom * previous_distance / distance).min(max_zoom(&settings_r.render));
+    let middle = (first.position() + second.position()) / 2.0;
+    zoom_target_r.write_val(zoom, Some(middle).filter(|_| settings_r.window.zoom_to_cursor));
+}
+
+/// One finger: pans the free camera, or walks the player towards the finger.
+fn sys_touch_drag(
+    time_r: Res<Time>,
+    mut since_last_path: Local<f32>,
+    mut walking_touch: Local<Option<u64>>,
+    touches_r: Res<Touches>,
+    gestures_r: Res<TouchGestures>,
+    world_geo_data_r: Res<WorldGeoData>,
+    scene_state_data_r: Res<SceneStateData>,
+    mut path_r: ResMut<ClickToMovePath>,
+    tile_picker: TilePicker,
+    pathfinder: Pathfinder,
+    player_q: Query<&Player>,
+    mut camera_q: Query<(&Camera, &GlobalTransform, &mut PlayerCamera)>,
+) {
+    *since_last_path += time_r.delta_secs();
+    if gestures_r.pinching {
+        return;
+    }
+    let active = gestures_r.active(&touches_r);
+    let [touch] = active[..] else {
+        return;
+    };
+    let Ok((camera, camera_transform, mut player_camera)) = camera_q.single_mut() else {
+        return;
+    };
+
+    if let Some(free_focus) = player_camera.free_focus {
+        if let (Some(grabbed), Some(now_under_finger)) = (
+            cursor_to_world_on_plane(camera, camera_transform, touch.previous_position(), free_focus.y),
+            cursor_to_world_on_plane(camera, camera_transform, touch.position(), free_focus.y),
+        ) {
+            let delta = grabbed - now_under_finger;
+            pan_free_focus(&mut player_camera, delta, &world_geo_data_r, scene_state_data_r.map_id);
+        }
+        return;
+    }
+
+    let walking = *walking_touch == Some(touch.id());
+    if !walking && !gestures_r.is_beyond_tap(touch, time_r.elapsed_secs_f64()) {
+        return;
+    }
+    let repath = !walking || *since_last_path >= HOLD_REPATH_INTERVAL_SECS;
+    if !repath {
+        return;
+    }
+    let (Some(destination), Ok(player)) = (tile_picker.tile_at(touch.position()), player_q.single()) else {
+        return;
+    };
+    *since_last_path = 0.0;
+    *walking_touch = Some(touch.id());
+    if let Some(from) = player.current_pos {
+        path_r.walk_to(&pathfinder, from, destination.to_uo_vec4());
+    }
+}
+
+/// Teleports the player to the tile tapped twice.
+fn sys_touch_double_tap(
+    time_r: Res<Time>,
+    touches_r: Res<Touches>,
+    mut gestures_r: ResMut<TouchGestures>,
+    tile_picker: TilePicker,
+    mut teleport_writer: EventWriter<TeleportPlayerEvent>,
+) {
+    let now = time_r.elapsed_secs_f64();
+    for touch in touches_r.iter_just_released().chain(touches_r.iter_just_canceled()) {
+        let id = touch.id();
+        let is_tap = !gestures_r.is_beyond_tap(touch, now);
+        gestures_r.started_at.remove(&id);
+        if gestures_r.ignored.remove(&id) || gestures_r.pinching || touches_r.just_canceled(id) {
+            continue;
+        }
+        if !is_tap {
+            gestures_r.last_tap = None;
+            continue;
+        }
+        let double_tap = gestures_r.last_tap.is_some_and(|(time, position)| {
+            now - time <= DOUBLE_TAP_MAX_SECS && position.distance(touch.position()) <= DOUBLE_TAP_MAX_DISTANCE
+        });
+        if !double_tap {
+            gestures_r.last_tap = Some((now, touch.position()));
+            continue;
+        }
+        gestures_r.last_tap = None;
+        let Some(tile) = tile_picker.tile_at(touch.position()) else {
+            continue;
+        };
+        logger::one(
+            None,
+            LogSev::Debug,
+            LogAbout::Player,
+            &format!("Double tap: teleport to ({}, {}).", tile.x, tile.y),
+        );
+        teleport_writer.write(TeleportPlayerEvent {
+            x: tile.x as u16,
+            y: tile.y as u16,
+            z: None,
+            map_id: tile.map_id,
+            from_history: false,
+        });
+    }
+
+    // The pinch ends when all the fingers are lifted.
+    if touches_r.iter().next().is_none() {
+        gestures_r.pinching = false;
+        gestures_r.ignored.clear();
+    }
+}
@@ -0,0 +1,116 @@
+// Live world: the dynamic entities of a shard (players, NPCs, items), shown on top of the static map.
+// - LiveWorld holds them by serial. It's updated only through LiveUpdate messages, whatever their source: the live
+//   feed of a shard (shard_link), or the replay of packet logs (replay).
+// - The entities are drawn by render::scene::live_entities, and listed in the Live Shard window
+//   (render::live_shard_ui).
+//
+
+pub mod packet_log;
+pub mod replay;
+pub mod shard_link;
+
+use crate::prelude::*;
+use bevy::prelude::*;
+use std::collections::BTreeMap;
+
+#[derive(strum_macros::AsRefStr, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LiveEntityKind {
+    Player,
+    Npc,
+    Item,
+}
+impl LiveEntityKind {
+    pub const ALL: [LiveEntityKind; 3] = [LiveEntityKind::Player, LiveEntityKind::Npc, LiveEntityKind::Item];
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiveEntity {
+    pub kind: LiveEntityKind,
+    pub pos: UOVec4,
+    /// Item graphic (art id). None for the mobiles.
+    pub graphic: Option<u16>,
+    pub name: String,
+    /// Region the entity is in, as told by the shard. Empty if unknown.
+    pub region: String,
+}
+
+/// A change of the live world.
+#[derive(Clone, Debug, PartialEq)]
+pub enum LiveUpdate {
+    /// An entity appeared or changed (moved, renamed...). Its region is kept.
+    Entity {
+        serial: u32,
+        kind: LiveEntityKind,
+        pos: UOVec4,
+        graphic: Option<u16>,
+        name: String,
+    },
+    /// The entity moved to another region.
+    Region { serial: u32, region: String },
+    Remove { serial: u32 },
+    /// Every entity is gone (e.g. the shard is sending everything again).
+    Clear,
+}
+
+#[derive(Resource, Default)]
+pub struct LiveWorld {
+    /// Sorted by serial, so that the lists don't shuffle at each update.
+    pub entities: BTreeMap<u32, LiveEntity>,
+}
+impl LiveWorld {
+    pub fn apply(&mut self, update: LiveUpdate) {
+        match update {
+            LiveUpdate::Entity {
+                serial,
+                kind,
+                pos,
+                graphic,
+                name,
+            } => {
+                let region = self
+                    .entities
+                    .remove(&serial)
+                    .map(|entity| entity.region)
+                    .unwrap_or_default();
+                self.entities.insert(serial, LiveEntity {
+                    kind,
+                    pos,
+                    graphic,
+                    name,
+                    region,
+                });
+            }
+            LiveUpdate::Region { serial, region } => {
+                if let Some(entity) = self.entities.get_mut(&serial) {
+                    entity.region = region;
+                }
+            }
+            LiveUpdate::Remove { serial } => {
+                self.entities.remove(&serial);
+            }
+            LiveUpdate::Clear => self.entities.clear(),
+        }
+    }
+
+    pub fn count(&self, kind: LiveEntityKind) -> usize {
+        self.entities.values().filter(|entity| entity.kind == kind).count()
+    }
+}
+
+pub struct LiveWorldPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(LiveWorldPlugin);
+impl Plugin for LiveWorldPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<LiveWorld>().add_plugins((
+            shard_link::ShardLinkPlugin {
+                registered_by: "LiveWorldPlugin",
+            },
+            replay::PacketLogReplayPlugin {
+                registered_by: "LiveWorldPlugin",
+            },
+        ));
+    }
+}
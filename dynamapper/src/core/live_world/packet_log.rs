@@ -0,0 +1,360 @@
+// Packet logs: the UO packets logged by a client or a shard (ClassicUO, RunUO/ServUO packet logs...), read as a
+//  timeline of LiveUpdates for the replay (see replay).
+// - The logs are text hex dumps. A packet starts with a header line telling its direction: "Server -> Client" or
+//   "Client -> Server", else "<-"/"<<" (received by the client) or "->"/">>" (sent). A time "HH:MM:SS[.mmm]" on
+//   the header line is the time of the packet; without one, packets are 100 ms apart. The bytes follow, 16 or fewer
+//   per line, after an optional offset ("0000", "0010:"...); an ASCII column after them is skipped.
+// - The packets must be decrypted and decompressed, as they're logged by the clients and the emulators.
+// - Understood: login confirm (0x1B), player update (0x20), mobile moving and incoming (0x77, 0x78), world items
+//   (0x1A, 0xF3), delete (0x1D), names (0x11, 0x98), map change (0xBF 0x08), and the walk of the logging client
+//   (0x02 requests, 0x21 reject, 0x22 ack). The other packets are skipped.
+// - The packets don't tell players from NPCs: the mobiles are NPCs, but for the character of the logging client.
+//
+
+use crate::core::live_world::{LiveEntityKind, LiveUpdate};
+use crate::prelude::*;
+use color_eyre::eyre::{self, WrapErr};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Time between the packets of a log without times.
+const UNTIMED_PACKET_STEP_MS: u64 = 100;
+const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+
+/// An update of the live world, at a time of the log.
+#[derive(Clone, Debug)]
+pub struct TimedUpdate {
+    /// From midnight of the first day of the log, or from the log start if it has no times.
+    pub time_ms: u64,
+    pub update: LiveUpdate,
+}
+
+struct LoggedPacket {
+    time_ms: Option<u64>,
+    from_server: bool,
+    data: Vec<u8>,
+}
+
+/// The updates of a packet log file. client_name names the character of the logging client if the log doesn't.
+pub fn load(path: &Path, client_name: &str) -> eyre::Result<Vec<TimedUpdate>> {
+    let text = std::fs::read(path).wrap_err_with(|| format!("Read packet log '{}'", path.display()))?;
+    let packets = read_packets(&String::from_utf8_lossy(&text));
+    if packets.is_empty() {
+        eyre::bail!("No packets in '{}'.", path.display());
+    }
+    let mut client = ClientState::new(client_name);
+    let mut updates = Vec::new();
+    let mut last_time_ms = 0;
+    let mut day_start_ms = 0;
+    for (index, packet) in packets.iter().enumerate() {
+        let time_ms = match packet.time_ms {
+            Some(time_ms) => {
+                // Past midnight.
+                if time_ms + day_start_ms + DAY_MS / 2 < last_time_ms {
+                    day_start_ms += DAY_MS;
+                }
+                time_ms + day_start_ms
+            }
+            None => index as u64 * UNTIMED_PACKET_STEP_MS,
+        };
+        last_time_ms = time_ms;
+        for update in client.decode(packet) {
+            updates.push(TimedUpdate { time_ms, update });
+        }
+    }
+    Ok(updates)
+}
+
+fn read_packets(text: &str) -> Vec<LoggedPacket> {
+    let mut packets = Vec::new();
+    let mut current: Option<LoggedPacket> = None;
+    for line in text.lines() {
+        if let Some(from_server) = header_direction(line) {
+            packets.extend(current.take().filter(|packet| !packet.data.is_empty()));
+            current = Some(LoggedPacket {
+                time_ms: header_time_ms(line),
+                from_server,
+                data: Vec::new(),
+            });
+            continue;
+        }
+        let Some(packet) = current.as_mut() else {
+            continue;
+        };
+        if line.trim().is_empty() {
+            packets.extend(current.take().filter(|packet| !packet.data.is_empty()));
+            continue;
+        }
+        packet.data.extend(hex_dump_bytes(line));
+    }
+    packets.extend(current.filter(|packet| !packet.data.is_empty()));
+    packets
+}
+
+/// Some(true) for the header of a packet sent by the server, Some(false) for one sent by the client.
+fn header_direction(line: &str) -> Option<bool> {
+    let lower = line.to_ascii_lowercase();
+    if lower.contains("server -> client") || lower.contains("server->client") {
+        Some(true)
+    } else if lower.contains("client -> server") || lower.contains("client->server") {
+        Some(false)
+    } else {
+        // The first token, or the one after the time.
+        let mut tokens = lower.split_whitespace();
+        let mut marker = tokens.next()?;
+        if header_time_ms(marker).is_some() {
+            marker = tokens.next()?;
+        }
+        match marker {
+            "<-" | "<<" => Some(true),
+            "->" | ">>" => Some(false),
+            _ => None,
+        }
+    }
+}
+
+/// Milliseconds from midnight of the first "HH:MM:SS[.mmm]" of the line.
+fn header_time_ms(line: &str) -> Option<u64> {
+    line.split(|c: char| c.is_whitespace() || c == '[' || c == ']').find_map(|token| {
+        let (hms, millis) = token.split_once(['.', ',']).unwrap_or((token, "0"));
+        let mut parts = hms.split(':').map(|part| part.parse::<u64>().ok());
+        let (Some(Some(h)), Some(Some(m)), Some(Some(s)), None) = (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return None;
+        };
+        let millis: u64 = format!("{millis:0<3}").get(..3)?.parse().ok()?;
+        (h < 24 && m < 60 && s < 61).then_some(((h * 60 + m) * 60 + s) * 1000 + millis)
+    })
+}
+
+fn is_hex_byte(token: &str) -> bool {
+    token.len() == 2 && token.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// The bytes of a hex dump line: skips the offset, stops at the ASCII column.
+fn hex_dump_bytes(line: &str) -> Vec<u8> {
+    let mut tokens = line.split_whitespace().peekable();
+    if let Some(first) = tokens.peek() {
+        let offset = first.trim_end_matches(':');
+        if offset.len() >= 4 && offset.chars().all(|c| c.is_ascii_hexdigit()) {
+            tokens.next();
+        }
+    }
+    tokens
+        .take(16)
+        .map_while(|token| is_hex_byte(token).then(|| u8::from_str_radix(token, 16).ok()).flatten())
+        .collect()
+}
+
+fn u8_at(data: &[u8], at: usize) -> Option<u8> {
+    data.get(at).copied()
+}
+
+fn u16_at(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_at(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+/// The null-terminated ASCII string of at most len bytes at `at`.
+fn ascii_at(data: &[u8], at: usize, len: usize) -> Option<String> {
+    let bytes = data.get(at..at + len)?;
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(len);
+    Some(String::from_utf8_lossy(&bytes[..end]).trim().to_owned())
+}
+
+/// Tile offset of a step in each direction (0 = north, clockwise).
+const DIRECTION_STEPS: [(i32, i32); 8] = [(0, -1), (1, -1), (1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1)];
+
+#[derive(Clone)]
+struct KnownEntity {
+    kind: LiveEntityKind,
+    pos: UOVec4,
+    graphic: Option<u16>,
+}
+
+/// What the logging client knows: its character, its map, and the entities it saw.
+struct ClientState {
+    client_name: String,
+    player_serial: Option<u32>,
+    player_direction: u8,
+    map: u8,
+    entities: HashMap<u32, KnownEntity>,
+    names: HashMap<u32, String>,
+    /// Directions of the walk requests waiting for an answer, by sequence number.
+    pending_steps: HashMap<u8, u8>,
+}
+impl ClientState {
+    fn new(client_name: &str) -> Self {
+        Self {
+            client_name: client_name.to_owned(),
+            player_serial: None,
+            player_direction: 0,
+            map: 0,
+            entities: HashMap::new(),
+            names: HashMap::new(),
+            pending_steps: HashMap::new(),
+        }
+    }
+
+    fn decode(&mut self, packet: &LoggedPacket) -> Vec<LiveUpdate> {
+        let data = &packet.data;
+        let decoded = if packet.from_server {
+            self.decode_server_packet(data)
+        } else {
+            self.decode_client_packet(data);
+            None
+        };
+        decoded.unwrap_or_default()
+    }
+
+    fn decode_client_packet(&mut self, data: &[u8]) {
+        // Walk request: the step is taken when the server accepts it.
+        if let (Some(0x02), Some(direction), Some(sequence)) = (u8_at(data, 0), u8_at(data, 1), u8_at(data, 2)) {
+            self.pending_steps.insert(sequence, direction & 0x07);
+        }
+    }
+
+    /// None if the packet is too short for its kind.
+    fn decode_server_packet(&mut self, data: &[u8]) -> Option<Vec<LiveUpdate>> {
+        let mobile = |serial: u32, this: &Self| {
+            if Some(serial) == this.player_serial {
+                LiveEntityKind::Player
+            } else {
+                LiveEntityKind::Npc
+            }
+        };
+        let update = match u8_at(data, 0)? {
+            // Login confirm.
+            0x1B => {
+                let serial = u32_at(data, 1)?;
+                self.player_serial = Some(serial);
+                self.player_direction = u8_at(data, 17)? & 0x07;
+                let pos = UOVec4::new(u16_at(data, 11)?, u16_at(data, 13)?, u16_at(data, 15)? as i16 as i8, self.map);
+                self.entity(serial, LiveEntityKind::Player, pos, None)
+            }
+            // Player update (teleports, map changes...).
+            0x20 => {
+                let serial = u32_at(data, 1)?;
+                if self.player_serial.is_none() {
+                    self.player_serial = Some(serial);
+                }
+                self.player_direction = u8_at(data, 17)? & 0x07;
+                let pos = UOVec4::new(u16_at(data, 11)?, u16_at(data, 13)?, u8_at(data, 18)? as i8, self.map);
+                self.entity(serial, LiveEntityKind::Player, pos, None)
+            }
+            // Mobile moving.
+            0x77 => {
+                let serial = u32_at(data, 1)?;
+                let pos = UOVec4::new(u16_at(data, 7)?, u16_at(data, 9)?, u8_at(data, 11)? as i8, self.map);
+                self.entity(serial, mobile(serial, self), pos, None)
+            }
+            // Mobile incoming.
+            0x78 => {
+                let serial = u32_at(data, 3)?;
+                let pos = UOVec4::new(u16_at(data, 9)?, u16_at(data, 11)?, u8_at(data, 13)? as i8, self.map);
+                self.entity(serial, mobile(serial, self), pos, None)
+            }
+            // World item (old format): the optional fields are flagged in the high bits of the others.
+            0x1A => {
+                let serial = u32_at(data, 3)?;
+                let graphic = u16_at(data, 7)?;
+                let mut at = 9;
+                if serial & 0x8000_0000 != 0 {
+                    at += 2; // Amount.
+                }
+                if graphic & 0x8000 != 0 {
+                    at += 1; // Graphic increment.
+                }
+                let x = u16_at(data, at)?;
+                let y = u16_at(data, at + 2)?;
+                at += 4;
+                if x & 0x8000 != 0 {
+                    at += 1; // Direction.
+                }
+                let pos = UOVec4::new(x & 0x7FFF, y & 0x3FFF, u8_at(data, at)? as i8, self.map);
+                self.entity(serial & 0x7FFF_FFFF, LiveEntityKind::Item, pos, Some(graphic & 0x7FFF))
+            }
+            // World item (Stygian Abyss format).
+            0xF3 => {
+                let serial = u32_at(data, 4)?;
+                let graphic = u16_at(data, 8)?;
+                let pos = UOVec4::new(u16_at(data, 15)?, u16_at(data, 17)?, u8_at(data, 19)? as i8, self.map);
+                self.entity(serial, LiveEntityKind::Item, pos, Some(graphic))
+            }
+            // Delete object.
+            0x1D => {
+                let serial = u32_at(data, 1)?;
+                self.entities.remove(&serial);
+                LiveUpdate::Remove { serial }
+            }
+            // Status bar and name: only the names are used.
+            0x11 | 0x98 => {
+                let serial = u32_at(data, 3)?;
+                let name = ascii_at(data, 7, 30)?;
+                self.names.insert(serial, name);
+                let known = self.entities.get(&serial)?.clone();
+                self.entity(serial, known.kind, known.pos, known.graphic)
+            }
+            // General info: map change.
+            0xBF if u16_at(data, 3)? == 0x08 => {
+                self.map = u8_at(data, 5)?;
+                let player = self.player_serial.and_then(|serial| Some((serial, self.entities.remove(&serial)?)));
+                // The entities of the old map plane are gone: the server sends the ones of the new one.
+                let mut updates: Vec<LiveUpdate> =
+                    self.entities.drain().map(|(serial, _)| LiveUpdate::Remove { serial }).collect();
+                if let Some((serial, player)) = player {
+                    let pos = UOVec4 { m: self.map, ..player.pos };
+                    updates.push(self.entity(serial, player.kind, pos, None));
+                }
+                return Some(updates);
+            }
+            // Walk rejected: the player is put back.
+            0x21 => {
+                let serial = self.player_serial?;
+                self.pending_steps.clear();
+                self.player_direction = u8_at(data, 6)? & 0x07;
+                let pos = UOVec4::new(u16_at(data, 2)?, u16_at(data, 4)?, u8_at(data, 7)? as i8, self.map);
+                self.entity(serial, LiveEntityKind::Player, pos, None)
+            }
+            // Walk accepted: a step, or a turn when the direction changes.
+            0x22 => {
+                let serial = self.player_serial?;
+                let direction = self.pending_steps.remove(&u8_at(data, 1)?)?;
+                let player = self.entities.get(&serial)?.clone();
+                if direction != self.player_direction {
+                    self.player_direction = direction;
+                    return Some(Vec::new());
+                }
+                let (dx, dy) = DIRECTION_STEPS[direction as usize];
+                let pos = UOVec4 {
+                    x: (player.pos.x as i32 + dx).max(0) as u16,
+                    y: (player.pos.y as i32 + dy).max(0) as u16,
+                    ..player.pos
+                };
+                self.entity(serial, LiveEntityKind::Player, pos, None)
+            }
+            _ => return None,
+        };
+        Some(vec![update])
+    }
+
+    /// Records the entity, and returns its update.
+    fn entity(&mut self, serial: u32, kind: LiveEntityKind, pos: UOVec4, graphic: Option<u16>) -> LiveUpdate {
+        self.entities.insert(serial, KnownEntity { kind, pos, graphic });
+        let name = match self.names.get(&serial) {
+            Some(name) => name.clone(),
+            None if kind == LiveEntityKind::Player => self.client_name.clone(),
+            None => String::new(),
+        };
+        LiveUpdate::Entity {
+            serial,
+            kind,
+            pos,
+            graphic,
+            name,
+        }
+    }
+}
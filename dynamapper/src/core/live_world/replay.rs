@@ -0,0 +1,210 @@
+// Packet log replay: the updates read from packet logs (see packet_log) applied to the LiveWorld over time, like a
+//  recording of the shard.
+// - Several logs can be replayed together, e.g. those of the clients of a test session: their updates are merged by
+//   time. The logging client of each one is named "Client 1", "Client 2"... unless the log has its name.
+// - Played, paused, sped up and sought from the Live shard window (render::live_shard_ui). Seeking back replays the
+//   log from the start, up to the new time.
+// - A character is a player in every log if it is in one: the other logging clients see it as an NPC.
+// - Loading logs disconnects the shard link; the replay pauses while the shard link is connected.
+//
+
+use crate::core::live_world::{
+    LiveEntityKind, LiveUpdate, LiveWorld,
+    packet_log::{self, TimedUpdate},
+    shard_link::{DisconnectShardEvent, ShardLink, ShardLinkState},
+};
+use crate::prelude::*;
+use bevy::prelude::*;
+use std::{collections::HashSet, path::PathBuf};
+
+/// Request to replay these packet logs, instead of the current ones.
+#[derive(Event, Debug, Clone)]
+pub struct LoadPacketLogsEvent {
+    pub files: Vec<PathBuf>,
+}
+
+/// Request to move the replay to a time, from its start.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SeekPacketLogReplayEvent {
+    pub time_ms: u64,
+}
+
+#[derive(Resource)]
+pub struct PacketLogReplay {
+    /// Logs being replayed.
+    pub files: Vec<PathBuf>,
+    /// Sorted by time, from 0.
+    updates: Vec<TimedUpdate>,
+    /// Index of the first update not applied yet.
+    next: usize,
+    /// Replay time, from the start of the first log.
+    pub time_ms: f64,
+    pub playing: bool,
+    /// Replay time per real time.
+    pub speed: f32,
+}
+impl Default for PacketLogReplay {
+    fn default() -> Self {
+        Self {
+            files: Vec::new(),
+            updates: Vec::new(),
+            next: 0,
+            time_ms: 0.0,
+            playing: false,
+            speed: 1.0,
+        }
+    }
+}
+impl PacketLogReplay {
+    pub fn is_loaded(&self) -> bool {
+        !self.updates.is_empty()
+    }
+
+    pub fn update_count(&self) -> usize {
+        self.updates.len()
+    }
+
+    pub fn duration_ms(&self) -> u64 {
+        self.updates.last().map_or(0, |update| update.time_ms)
+    }
+
+    /// Applies the updates up to time_ms. Returns whether there were any.
+    fn advance(&mut self, live_world: &mut LiveWorld) -> bool {
+        let first = self.next;
+        while let Some(update) = self.updates.get(self.next)
+            && update.time_ms as f64 <= self.time_ms
+        {
+            live_world.apply(update.update.clone());
+            self.next += 1;
+        }
+        self.next > first
+    }
+}
+
+pub struct PacketLogReplayPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(PacketLogReplayPlugin);
+impl Plugin for PacketLogReplayPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<PacketLogReplay>()
+            .add_event::<LoadPacketLogsEvent>()
+            .add_event::<SeekPacketLogReplayEvent>()
+            .add_systems(
+                Update,
+                (sys_load_packet_logs, sys_play_packet_log_replay)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_load_packet_logs(
+    mut events: EventReader<LoadPacketLogsEvent>,
+    mut replay_r: ResMut<PacketLogReplay>,
+    mut live_world_r: ResMut<LiveWorld>,
+    mut disconnect_writer: EventWriter<DisconnectShardEvent>,
+) {
+    let Some(LoadPacketLogsEvent { files }) = events.read().last().cloned() else {
+        return;
+    };
+    let mut updates = Vec::new();
+    for (index, file) in files.iter().enumerate() {
+        match packet_log::load(file, &format!("Client {}", index + 1)) {
+            Ok(file_updates) => {
+                logger::one(
+                    None,
+                    LogSev::Info,
+                    LogAbout::LiveWorld,
+                    &format!("Read {} updates from packet log '{}'.", file_updates.len(), file.display()),
+                );
+                updates.extend(file_updates);
+            }
+            Err(e) => {
+                logger::one(
+                    None,
+                    LogSev::Error,
+                    LogAbout::LiveWorld,
+                    &format!("Can't replay packet log '{}': {e:?}", file.display()),
+                );
+                return;
+            }
+        }
+    }
+    // A client sees the characters of the other logging clients as NPCs: they're players in every log.
+    let players: HashSet<u32> = updates
+        .iter()
+        .filter_map(|update| match update.update {
+            LiveUpdate::Entity {
+                serial,
+                kind: LiveEntityKind::Player,
+                ..
+            } => Some(serial),
+            _ => None,
+        })
+        .collect();
+    for update in &mut updates {
+        if let LiveUpdate::Entity { serial, kind, .. } = &mut update.update
+            && players.contains(serial)
+        {
+            *kind = LiveEntityKind::Player;
+        }
+    }
+    // Stable: the updates of a log at the same time keep their order.
+    updates.sort_by_key(|update| update.time_ms);
+    let start_ms = updates.first().map_or(0, |update| update.time_ms);
+    for update in &mut updates {
+        update.time_ms -= start_ms;
+    }
+
+    disconnect_writer.write(DisconnectShardEvent);
+    live_world_r.apply(LiveUpdate::Clear);
+    *replay_r = PacketLogReplay {
+        files,
+        updates,
+        speed: replay_r.speed,
+        ..default()
+    };
+}
+
+fn sys_play_packet_log_replay(
+    mut seek_events: EventReader<SeekPacketLogReplayEvent>,
+    time_r: Res<Time>,
+    link_r: Res<ShardLink>,
+    mut replay_r: ResMut<PacketLogReplay>,
+    mut live_world_r: ResMut<LiveWorld>,
+) {
+    let replay = replay_r.bypass_change_detection();
+    if let Some(seek) = seek_events.read().last() {
+        let time_ms = seek.time_ms.min(replay.duration_ms()) as f64;
+        if time_ms < replay.time_ms {
+            live_world_r.apply(LiveUpdate::Clear);
+            replay.next = 0;
+        }
+        replay.time_ms = time_ms;
+        if replay.advance(live_world_r.bypass_change_detection()) {
+            live_world_r.set_changed();
+        }
+        replay_r.set_changed();
+        return;
+    }
+    if !replay.playing {
+        return;
+    }
+    if matches!(link_r.state, ShardLinkState::Connecting(_) | ShardLinkState::Connected(_)) {
+        replay.playing = false;
+        replay_r.set_changed();
+        return;
+    }
+    replay.time_ms += time_r.delta_secs_f64() * 1000.0 * replay.speed as f64;
+    if replay.time_ms >= replay.duration_ms() as f64 {
+        replay.time_ms = replay.duration_ms() as f64;
+        replay.playing = false;
+    }
+    // Only the frames with updates change the live world.
+    if replay.advance(live_world_r.bypass_change_detection()) {
+        live_world_r.set_changed();
+    }
+    replay_r.set_changed();
+}
@@ -0,0 +1,325 @@
+// Shard link: live data feed of a shard, read over TCP and applied to the LiveWorld.
+// - The shard (a SphereServer script, a ServUO plugin...) listens on live_shard.server and sends a line of text for
+//   each change, fields separated by spaces, serials in decimal or 0x hexadecimal, names last (they can have spaces):
+//     player <serial> <x> <y> <z> <map> [name]
+//     npc <serial> <x> <y> <z> <map> [name]
+//     item <serial> <x> <y> <z> <map> <graphic> [name]
+//     region <serial> <region name>
+//     remove <serial>
+//     clear
+//   Empty lines and lines starting with # are skipped. Nothing is sent to the shard. A line longer than
+//   MAX_LINE_LEN drops the connection, so that a peer not sending newlines can't fill the memory.
+// - The connection is read by a thread, which passes the updates to sys_receive_shard_updates through a channel.
+// - Connected from the Live Shard window (render::live_shard_ui), or when entering the game with
+//   live_shard.connect_at_startup. Not in the browser build, which has no TCP sockets.
+//
+
+use crate::core::live_world::{LiveEntityKind, LiveUpdate, LiveWorld};
+use crate::prelude::*;
+use bevy::prelude::*;
+use std::net::TcpStream;
+use std::sync::{
+    Mutex,
+    mpsc::{Receiver, Sender, channel},
+};
+
+/// Longest line accepted from the shard, in bytes (without the newline).
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_LINE_LEN: u64 = 4096;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ShardLinkState {
+    Disconnected,
+    Connecting(String),
+    Connected(String),
+    /// The connection failed or dropped.
+    Failed(String),
+}
+
+/// Request to connect to the live feed of a shard, at "host:port", dropping the current connection.
+#[derive(Event, Debug, Clone)]
+pub struct ConnectShardEvent {
+    pub server: String,
+}
+
+#[derive(Event, Debug, Clone, Copy)]
+pub struct DisconnectShardEvent;
+
+enum LinkMessage {
+    /// A clone of the stream, to close it from here.
+    Connected(TcpStream),
+    Update(LiveUpdate),
+    /// A line which couldn't be parsed (logged by the thread).
+    BadLine,
+    /// The connection ended: None if closed by the shard.
+    Ended(Option<String>),
+}
+
+#[derive(Resource)]
+pub struct ShardLink {
+    pub state: ShardLinkState,
+    /// Lines the shard sent which couldn't be parsed.
+    pub bad_lines: usize,
+    stream: Option<TcpStream>,
+    /// Incremented at each connection: messages of the threads of the previous ones are dropped.
+    generation: u64,
+    sender: Sender<(u64, LinkMessage)>,
+    receiver: Mutex<Receiver<(u64, LinkMessage)>>,
+}
+impl Default for ShardLink {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self {
+            state: ShardLinkState::Disconnected,
+            bad_lines: 0,
+            stream: None,
+            generation: 0,
+            sender,
+            receiver: Mutex::new(receiver),
+        }
+    }
+}
+impl ShardLink {
+    fn close(&mut self) {
+        self.generation += 1;
+        if let Some(stream) = self.stream.take() {
+            // Unblocks the reading thread, which then ends.
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+    }
+}
+
+pub struct ShardLinkPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(ShardLinkPlugin);
+impl Plugin for ShardLinkPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<ShardLink>()
+            .add_event::<ConnectShardEvent>()
+            .add_event::<DisconnectShardEvent>()
+            .add_systems(OnEnter(AppState::InGame), sys_connect_at_startup)
+            .add_systems(
+                Update,
+                (sys_connect_shard, sys_receive_shard_updates)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_connect_at_startup(settings_r: Res<Settings>, mut connect_writer: EventWriter<ConnectShardEvent>) {
+    let server = settings_r.live_shard.server.trim();
+    if settings_r.live_shard.connect_at_startup && !server.is_empty() {
+        connect_writer.write(ConnectShardEvent {
+            server: server.to_owned(),
+        });
+    }
+}
+
+fn sys_connect_shard(
+    mut connect_events: EventReader<ConnectShardEvent>,
+    mut disconnect_events: EventReader<DisconnectShardEvent>,
+    mut link_r: ResMut<ShardLink>,
+) {
+    let connect = connect_events.read().last().cloned();
+    let disconnect = disconnect_events.read().last().is_some();
+    if connect.is_none() && !disconnect {
+        return;
+    }
+    let link = link_r.as_mut();
+    if link.stream.is_some() || matches!(link.state, ShardLinkState::Connecting(_)) {
+        logger::one(None, LogSev::Info, LogAbout::LiveWorld, "Disconnected from the shard.");
+    }
+    link.close();
+    link.state = ShardLinkState::Disconnected;
+    let Some(ConnectShardEvent { server }) = connect else {
+        return;
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    {
+        link.state = ShardLinkState::Failed(format!("no TCP connections in the browser, can't reach {server}"));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        logger::one(
+            None,
+            LogSev::Info,
+            LogAbout::LiveWorld,
+            &format!("Connecting to the shard at {server}."),
+        );
+        link.state = ShardLinkState::Connecting(server.clone());
+        link.bad_lines = 0;
+        let generation = link.generation;
+        let sender = link.sender.clone();
+        std::thread::spawn(move || {
+            let message = match read_shard_feed(&server, |message| sender.send((generation, message)).is_ok()) {
+                Ok(()) => LinkMessage::Ended(None),
+                Err(e) => LinkMessage::Ended(Some(e.to_string())),
+            };
+            let _ = sender.send((generation, message));
+        });
+    }
+}
+
+/// Connects to the shard and passes what it sends to `send`, until the connection ends or `send` returns false.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_shard_feed(server: &str, mut send: impl FnMut(LinkMessage) -> bool) -> std::io::Result<()> {
+    use std::io::{BufRead, Error, ErrorKind, Read};
+
+    let stream = TcpStream::connect(server)?;
+    if !send(LinkMessage::Connected(stream.try_clone()?)) {
+        return Ok(());
+    }
+    let mut reader = std::io::BufReader::new(stream);
+    let mut bytes = Vec::new();
+    loop {
+        bytes.clear();
+        // The longest line fits with its \r\n, and a longer one has more than MAX_LINE_LEN bytes without them.
+        let read = (&mut reader).take(MAX_LINE_LEN + 2).read_until(b'\n', &mut bytes)?;
+        if read == 0 {
+            break;
+        }
+        if bytes.last() == Some(&b'\n') {
+            bytes.pop();
+            if bytes.last() == Some(&b'\r') {
+                bytes.pop();
+            }
+        }
+        if bytes.len() as u64 > MAX_LINE_LEN {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("line longer than {MAX_LINE_LEN} bytes"),
+            ));
+        }
+        let line = std::str::from_utf8(&bytes).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let message = match parse_line(line) {
+            Ok(Some(update)) => LinkMessage::Update(update),
+            Ok(None) => continue,
+            Err(e) => {
+                logger::one(
+                    None,
+                    LogSev::Warn,
+                    LogAbout::LiveWorld,
+                    &format!("Bad line from the shard ({e}): '{line}'"),
+                );
+                LinkMessage::BadLine
+            }
+        };
+        if !send(message) {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn sys_receive_shard_updates(mut link_r: ResMut<ShardLink>, mut live_world_r: ResMut<LiveWorld>) {
+    let link = link_r.as_mut();
+    let messages: Vec<(u64, LinkMessage)> = match link.receiver.lock() {
+        Ok(receiver) => receiver.try_iter().collect(),
+        Err(_) => return,
+    };
+    for (generation, message) in messages {
+        if generation != link.generation {
+            if let LinkMessage::Connected(stream) = message {
+                let _ = stream.shutdown(std::net::Shutdown::Both);
+            }
+            continue;
+        }
+        match message {
+            LinkMessage::Connected(stream) => {
+                let ShardLinkState::Connecting(server) = &link.state else {
+                    continue;
+                };
+                logger::one(
+                    None,
+                    LogSev::Info,
+                    LogAbout::LiveWorld,
+                    &format!("Connected to the shard at {server}."),
+                );
+                link.state = ShardLinkState::Connected(server.clone());
+                link.stream = Some(stream);
+                // The shard sends its entities again.
+                live_world_r.apply(LiveUpdate::Clear);
+            }
+            LinkMessage::Update(update) => live_world_r.apply(update),
+            LinkMessage::BadLine => link.bad_lines += 1,
+            LinkMessage::Ended(reason) => {
+                let reason = reason.unwrap_or_else(|| "closed by the shard".to_owned());
+                logger::one(
+                    None,
+                    LogSev::Warn,
+                    LogAbout::LiveWorld,
+                    &format!("Shard connection ended: {reason}."),
+                );
+                link.stream = None;
+                link.state = ShardLinkState::Failed(reason);
+            }
+        }
+    }
+}
+
+fn parse_serial(text: &str) -> Result<u32, String> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => text.parse(),
+    };
+    parsed.map_err(|_| format!("bad serial '{text}'"))
+}
+
+fn parse_field<T: std::str::FromStr>(field: Option<&str>, what: &str) -> Result<T, String> {
+    let field = field.ok_or_else(|| format!("missing {what}"))?;
+    field.parse().map_err(|_| format!("bad {what} '{field}'"))
+}
+
+/// The update sent with a line of the feed. None for the lines to skip.
+pub fn parse_line(line: &str) -> Result<Option<LiveUpdate>, String> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    let (command, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+    let mut fields = rest.split_whitespace();
+    let mut serial = || parse_serial(fields.next().ok_or("missing serial")?);
+    let kind = match command.to_ascii_lowercase().as_str() {
+        "player" => LiveEntityKind::Player,
+        "npc" => LiveEntityKind::Npc,
+        "item" => LiveEntityKind::Item,
+        "region" => {
+            let serial = serial()?;
+            let region = rest.trim_start().split_once(char::is_whitespace).map_or("", |(_, region)| region);
+            return Ok(Some(LiveUpdate::Region {
+                serial,
+                region: region.trim().to_owned(),
+            }));
+        }
+        "remove" => return Ok(Some(LiveUpdate::Remove { serial: serial()? })),
+        "clear" => return Ok(Some(LiveUpdate::Clear)),
+        _ => return Err(format!("unknown command '{command}'")),
+    };
+    let serial = serial()?;
+    let pos = UOVec4::new(
+        parse_field(fields.next(), "x")?,
+        parse_field(fields.next(), "y")?,
+        parse_field(fields.next(), "z")?,
+        parse_field(fields.next(), "map")?,
+    );
+    let graphic = match kind {
+        LiveEntityKind::Item => {
+            let graphic = fields.next().ok_or("missing graphic")?;
+            let parsed = parse_serial(graphic).ok().and_then(|graphic| u16::try_from(graphic).ok());
+            Some(parsed.ok_or_else(|| format!("bad graphic '{graphic}'"))?)
+        }
+        _ => None,
+    };
+    let name = fields.collect::<Vec<_>>().join(" ");
+    Ok(Some(LiveUpdate::Entity {
+        serial,
+        kind,
+        pos,
+        graphic,
+        name,
+    }))
+}
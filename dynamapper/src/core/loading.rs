@@ -0,0 +1,92 @@
+// Loading progress
+// - LoadingProgress is shared by the loaders (even the ones running in background tasks), which update the progress of
+//   their step, and by the loading screen, which shows it.
+//
+
+use bevy::prelude::*;
+use std::sync::{Arc, Mutex};
+
+/// Steps of AppState::Loading, in the order they run.
+#[derive(strum_macros::AsRefStr, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoadingStep {
+    MapIndex,
+    Tiledata,
+    Hues,
+    Texmap,
+    Art,
+    TexturePrewarm,
+}
+impl LoadingStep {
+    pub const ALL: [LoadingStep; 6] = [
+        LoadingStep::MapIndex,
+        LoadingStep::Tiledata,
+        LoadingStep::Hues,
+        LoadingStep::Texmap,
+        LoadingStep::Art,
+        LoadingStep::TexturePrewarm,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LoadingStep::MapIndex => "Map index",
+            LoadingStep::Tiledata => "Tiledata",
+            LoadingStep::Hues => "Hues",
+            LoadingStep::Texmap => "Texmaps",
+            LoadingStep::Art => "Art index",
+            LoadingStep::TexturePrewarm => "Land textures",
+        }
+    }
+
+    fn idx(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LoadingStepState {
+    #[default]
+    Pending,
+    /// Completion from 0.0 to 1.0, or None if the loader can't tell.
+    Running(Option<f32>),
+    Done,
+}
+
+/// Progress of every loading step. Cloning it gives another handle to the same progress data.
+#[derive(Resource, Clone, Default)]
+pub struct LoadingProgress(Arc<Mutex<[LoadingStepState; LoadingStep::ALL.len()]>>);
+impl LoadingProgress {
+    pub fn get(&self, step: LoadingStep) -> LoadingStepState {
+        self.0.lock().unwrap()[step.idx()]
+    }
+
+    pub fn set(&self, step: LoadingStep, state: LoadingStepState) {
+        self.0.lock().unwrap()[step.idx()] = state;
+    }
+
+    pub fn start(&self, step: LoadingStep) {
+        self.set(step, LoadingStepState::Running(None));
+    }
+
+    pub fn finish(&self, step: LoadingStep) {
+        self.set(step, LoadingStepState::Done);
+    }
+
+    /// Every step back to pending, to load again.
+    pub fn reset(&self) {
+        *self.0.lock().unwrap() = Default::default();
+    }
+
+    /// Steps done, out of all of them.
+    pub fn done_count(&self) -> usize {
+        self.0
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|state| **state == LoadingStepState::Done)
+            .count()
+    }
+
+    pub fn all_done(&self) -> bool {
+        self.done_count() == LoadingStep::ALL.len()
+    }
+}
@@ -0,0 +1,337 @@
+// Map editing mode
+// - Toggled with InputAction::ToggleMapEditor (F4 by default), or from the Map Editor toolbar.
+// - Holding the left mouse button applies the selected brush (raise, lower, flatten, paint texture) to the land tiles
+//   around the one under the cursor. Changes go to the in-memory map blocks (MapPlane::set_cell), not to the files.
+// - Every change sends a LandCellsEditedEvent, so that the land chunks showing the changed tiles are rebuilt.
+// - A stroke (from pressing the button to releasing it) is a single entry of the undo/redo history (see history.rs).
+// - ImportHeightmapEvent sets the z of a rectangle of tiles from a heightmap file (see heightmap_import.rs).
+// - SaveMapEditsEvent writes the edited blocks of a map plane back to its map*.mul (MapPlane::save_edits), after
+//   backing up the original file as map*.mul.bak. uop map files can't be written.
+//
+
+pub mod heightmap_import;
+pub mod history;
+
+use std::collections::HashMap;
+
+use heightmap_import::ImportHeightmapEvent;
+use history::{LandCellChange, MapEditCommand, MapEditHistory, RedoMapEditEvent, UndoMapEditEvent};
+
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::render::scene::SceneStateData;
+use crate::core::render::scene::picking::TilePicker;
+use crate::core::uo_files_loader::MapPlanesRes;
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy_egui::input::EguiWantsInput;
+use uocf::geo::map::{MapBlock, MapCell, MapRectCells};
+
+const EDIT_MOUSE_BUTTON: MouseButton = MouseButton::Left;
+/// While the button is held, the brush is applied again this often (raise and lower add up).
+const BRUSH_REPEAT_INTERVAL_SECS: f32 = 0.1;
+/// Largest brush radius, in tiles.
+pub const BRUSH_RADIUS_MAX: u32 = 8;
+/// Largest z change of a single raise/lower application.
+pub const BRUSH_STRENGTH_MAX: i8 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapEditTool {
+    Raise,
+    Lower,
+    /// Sets the z of the tiles to the z of the tile where the stroke started.
+    Flatten,
+    /// Sets the land tile id (so the texture) of the tiles.
+    PaintTexture,
+}
+impl MapEditTool {
+    pub const ALL: [MapEditTool; 4] = [
+        MapEditTool::Raise,
+        MapEditTool::Lower,
+        MapEditTool::Flatten,
+        MapEditTool::PaintTexture,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            MapEditTool::Raise => "Raise",
+            MapEditTool::Lower => "Lower",
+            MapEditTool::Flatten => "Flatten",
+            MapEditTool::PaintTexture => "Paint texture",
+        }
+    }
+}
+
+/// Tiles changed in a map plane, whose land chunks have to be rebuilt.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LandCellsEditedEvent {
+    pub map_id: u32,
+    pub rect: MapRectCells,
+}
+
+/// Cells changed by a stroke.
+struct EditStroke {
+    map_id: u32,
+    /// z of the tile where the stroke started, for MapEditTool::Flatten.
+    flatten_z: i8,
+    /// (before the stroke, now)
+    cells: HashMap<(u32, u32), (MapCell, MapCell)>,
+}
+impl EditStroke {
+    /// The command for the history: tiles changed back to how they were don't count.
+    fn into_command(self) -> MapEditCommand {
+        let changes = self
+            .cells
+            .into_iter()
+            .filter(|(_, (before, after))| before.id != after.id || before.z != after.z)
+            .map(|((x, y), (before, after))| LandCellChange { x, y, before, after })
+            .collect();
+        MapEditCommand::LandCells {
+            map_id: self.map_id,
+            changes,
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct MapEditorState {
+    pub enabled: bool,
+    pub tool: MapEditTool,
+    /// 0 changes only the tile under the cursor.
+    pub brush_radius: u32,
+    /// z added or removed by each application of raise and lower.
+    pub brush_strength: i8,
+    /// Land tile id set by MapEditTool::PaintTexture.
+    pub paint_tile_id: u16,
+    /// The Import Heightmap window is open.
+    pub show_heightmap_import: bool,
+    stroke: Option<EditStroke>,
+    since_last_apply: f32,
+}
+impl Default for MapEditorState {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tool: MapEditTool::Raise,
+            brush_radius: 1,
+            brush_strength: 1,
+            paint_tile_id: 0x0003, // grass
+            show_heightmap_import: false,
+            stroke: None,
+            since_last_apply: 0.0,
+        }
+    }
+}
+impl MapEditorState {
+    pub fn is_stroke_in_progress(&self) -> bool {
+        self.stroke.is_some()
+    }
+}
+
+/// Request to write the edits of a map plane to its files.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SaveMapEditsEvent {
+    pub map_id: u32,
+}
+
+pub struct MapEditorPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(MapEditorPlugin);
+
+impl Plugin for MapEditorPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<MapEditorState>()
+            .init_resource::<MapEditHistory>()
+            .add_event::<LandCellsEditedEvent>()
+            .add_event::<UndoMapEditEvent>()
+            .add_event::<RedoMapEditEvent>()
+            .add_event::<ImportHeightmapEvent>()
+            .add_event::<SaveMapEditsEvent>()
+            .add_systems(
+                Update,
+                (
+                    history::sys_apply_history_size,
+                    sys_toggle_map_editor,
+                    sys_apply_brush,
+                    history::sys_history_shortcuts,
+                    history::sys_undo_redo_map_edit,
+                    heightmap_import::sys_import_heightmap,
+                    sys_save_map_edits,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_toggle_map_editor(action_input: ActionInput, mut editor_r: ResMut<MapEditorState>) {
+    if action_input.just_pressed(InputAction::ToggleMapEditor) {
+        editor_r.enabled = !editor_r.enabled;
+        logger::one(
+            None,
+            LogSev::Info,
+            LogAbout::MapEditor,
+            if editor_r.enabled { "Map editing mode on." } else { "Map editing mode off." },
+        );
+    }
+}
+
+/// Tiles covered by a round brush centered on (x, y), inside the map.
+fn brush_tiles(x: u32, y: u32, radius: u32, map_width: u32, map_height: u32) -> impl Iterator<Item = (u32, u32)> {
+    let r = radius as i64;
+    (-r..=r)
+        .flat_map(move |dy| (-r..=r).map(move |dx| (dx, dy)))
+        .filter(move |(dx, dy)| dx * dx + dy * dy <= r * r)
+        .map(move |(dx, dy)| (x as i64 + dx, y as i64 + dy))
+        .filter(move |&(tx, ty)| tx >= 0 && ty >= 0 && tx < map_width as i64 && ty < map_height as i64)
+        .map(|(tx, ty)| (tx as u32, ty as u32))
+}
+
+/// Smallest rectangle holding the given tiles.
+fn bounding_rect(tiles: impl Iterator<Item = (u32, u32)>) -> Option<MapRectCells> {
+    let (x0, y0, x1, y1) = tiles.fold((u32::MAX, u32::MAX, 0, 0), |(x0, y0, x1, y1), (x, y)| {
+        (x0.min(x), y0.min(y), x1.max(x), y1.max(y))
+    });
+    (x0 <= x1).then(|| MapRectCells {
+        x0,
+        y0,
+        width: x1 - x0 + 1,
+        height: y1 - y0 + 1,
+    })
+}
+
+fn sys_apply_brush(
+    time_r: Res<Time>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    egui_wants_input_r: Res<EguiWantsInput>,
+    mut editor_r: ResMut<MapEditorState>,
+    mut history_r: ResMut<MapEditHistory>,
+    tile_picker: TilePicker,
+    map_planes_r: Res<MapPlanesRes>,
+    scene_state_data_r: Res<SceneStateData>,
+    mut writer: EventWriter<LandCellsEditedEvent>,
+) {
+    let editor = editor_r.as_mut();
+    editor.since_last_apply += time_r.delta_secs();
+
+    // Releasing the button (or leaving the editing mode) ends the stroke.
+    if !editor.enabled || !mouse_input.pressed(EDIT_MOUSE_BUTTON) {
+        if let Some(stroke) = editor.stroke.take() {
+            logger::one(
+                None,
+                LogSev::Debug,
+                LogAbout::MapEditor,
+                &format!("{} stroke: changed {} tiles.", editor.tool.label(), stroke.cells.len()),
+            );
+            history_r.push(stroke.into_command());
+        }
+        return;
+    }
+    if mouse_input.just_pressed(EDIT_MOUSE_BUTTON) && egui_wants_input_r.wants_pointer_input() {
+        return;
+    }
+    let Some(picked) = tile_picker.cursor_tile() else {
+        return;
+    };
+    if mouse_input.just_pressed(EDIT_MOUSE_BUTTON) {
+        // Eyedropper: Alt + click picks the tile to paint with.
+        if editor.tool == MapEditTool::PaintTexture
+            && keyboard_input.any_pressed([KeyCode::AltLeft, KeyCode::AltRight])
+        {
+            editor.paint_tile_id = picked.cell.id;
+            return;
+        }
+        editor.stroke = Some(EditStroke {
+            map_id: picked.map_id,
+            flatten_z: picked.cell.z,
+            cells: HashMap::new(),
+        });
+        editor.since_last_apply = BRUSH_REPEAT_INTERVAL_SECS;
+    }
+    if editor.since_last_apply < BRUSH_REPEAT_INTERVAL_SECS {
+        return;
+    }
+    let (tool, radius, strength, paint_tile_id) =
+        (editor.tool, editor.brush_radius, editor.brush_strength, editor.paint_tile_id);
+    let Some(stroke) = editor.stroke.as_mut() else {
+        return;
+    };
+    // The stroke ends if the scene moves to another map plane.
+    if stroke.map_id != scene_state_data_r.map_id {
+        return;
+    }
+    let Some(map_plane) = map_planes_r.get(stroke.map_id) else {
+        return;
+    };
+    editor.since_last_apply = 0.0;
+
+    let map_width = map_plane.size_blocks().width * MapBlock::CELLS_PER_ROW;
+    let map_height = map_plane.size_blocks().height * MapBlock::CELLS_PER_COLUMN;
+    let changes: Vec<(u32, u32, MapCell)> = brush_tiles(picked.x, picked.y, radius, map_width, map_height)
+        .filter_map(|(x, y)| {
+            let cell = map_plane.cell(x, y).ok()?;
+            let new_cell = match tool {
+                MapEditTool::Raise => MapCell {
+                    z: cell.z.saturating_add(strength),
+                    ..cell
+                },
+                MapEditTool::Lower => MapCell {
+                    z: cell.z.saturating_sub(strength),
+                    ..cell
+                },
+                MapEditTool::Flatten => MapCell {
+                    z: stroke.flatten_z,
+                    ..cell
+                },
+                MapEditTool::PaintTexture => MapCell {
+                    id: paint_tile_id,
+                    ..cell
+                },
+            };
+            (new_cell.id != cell.id || new_cell.z != cell.z).then_some((x, y, new_cell))
+        })
+        .collect();
+    let Some(rect) = bounding_rect(changes.iter().map(|&(x, y, _)| (x, y))) else {
+        return;
+    };
+
+    let prev_cells = match map_plane.set_cells(changes.iter().copied()) {
+        Ok(prev_cells) => prev_cells,
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::MapEditor,
+                &format!("Can't edit map plane {}: {e}", stroke.map_id),
+            );
+            return;
+        }
+    };
+    for ((x, y, prev_cell), &(_, _, new_cell)) in prev_cells.into_iter().zip(&changes) {
+        // Keep the cell as it was before the stroke.
+        stroke.cells.entry((x, y)).or_insert((prev_cell, new_cell)).1 = new_cell;
+    }
+    writer.write(LandCellsEditedEvent {
+        map_id: stroke.map_id,
+        rect,
+    });
+}
+
+fn sys_save_map_edits(mut events: EventReader<SaveMapEditsEvent>, map_planes_r: Res<MapPlanesRes>) {
+    for &SaveMapEditsEvent { map_id } in events.read() {
+        let Some(map_plane) = map_planes_r.get(map_id) else {
+            continue;
+        };
+        let (severity, msg) = match map_plane.save_edits(true) {
+            Ok(0) => (LogSev::Info, format!("Map plane {map_id}: no edits to save.")),
+            Ok(written) => (
+                LogSev::Info,
+                format!("Map plane {map_id}: saved {written} edited blocks (original file backed up as .bak)."),
+            ),
+            Err(e) => (LogSev::Error, format!("Can't save the edits of map plane {map_id}: {e}")),
+        };
+        logger::one(None, severity, LogAbout::MapEditor, &msg);
+    }
+}
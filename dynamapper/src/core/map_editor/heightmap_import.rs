@@ -0,0 +1,291 @@
+// Heightmap import
+// - Reads an external heightmap and sets the z of the land tiles in a rectangle of a map plane from it, e.g. to
+//   prototype the terrain of a shard in another tool. Tile ids are left as they are.
+// - Formats, by file extension:
+//   - png: grayscale (colors are converted), 8 or 16 bit. Gray levels span the whole z range: 0 is -128, 255 is 127.
+//   - csv: a grid of numbers (one row per line; separated by commas, semicolons or whitespace), used as they are.
+//     Empty lines and lines starting with # are skipped.
+//   - json: an array of rows, each an array of numbers, used as they are.
+// - The heightmap covers the rectangle starting at (x0, y0), one sample per tile, or stretched (bilinear) over a
+//   rectangle of the given size. Values are scaled (HeightmapScaling) and then clamped to [z_min, z_max].
+// - The import is a single entry of the undo/redo history, and sends a LandCellsEditedEvent so that the land chunks
+//   showing the changed tiles are rebuilt.
+//
+
+use std::path::{Path, PathBuf};
+
+use super::history::{LandCellChange, MapEditCommand, MapEditHistory};
+use super::{LandCellsEditedEvent, bounding_rect};
+use crate::core::uo_files_loader::MapPlanesRes;
+use crate::prelude::*;
+use bevy::prelude::*;
+use color_eyre::eyre::{self, WrapErr, bail, eyre};
+use uocf::geo::map::{MapBlock, MapCell};
+
+/// Grid of height samples, row by row.
+pub struct Heightmap {
+    pub width: u32,
+    pub height: u32,
+    values: Vec<f32>,
+}
+impl Heightmap {
+    pub fn load(file_path: &Path) -> eyre::Result<Heightmap> {
+        let extension = file_path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_ascii_lowercase())
+            .unwrap_or_default();
+        match extension.as_str() {
+            "png" => Self::load_png(file_path),
+            "csv" => {
+                let text = std::fs::read_to_string(file_path)
+                    .wrap_err_with(|| format!("Reading '{}'", file_path.display()))?;
+                Self::parse_csv(&text).wrap_err_with(|| format!("Parsing '{}'", file_path.display()))
+            }
+            "json" => {
+                let text = std::fs::read_to_string(file_path)
+                    .wrap_err_with(|| format!("Reading '{}'", file_path.display()))?;
+                Self::parse_json(&text).wrap_err_with(|| format!("Parsing '{}'", file_path.display()))
+            }
+            _ => bail!(
+                "Unsupported heightmap format '{}': use png, csv or json.",
+                file_path.display()
+            ),
+        }
+    }
+
+    fn load_png(file_path: &Path) -> eyre::Result<Heightmap> {
+        let image = image::open(file_path)
+            .wrap_err_with(|| format!("Reading '{}'", file_path.display()))?
+            .into_luma16();
+        Ok(Heightmap {
+            width: image.width(),
+            height: image.height(),
+            // 8 bit levels are stored as level * 257 in a 16 bit image.
+            values: image.pixels().map(|pixel| pixel.0[0] as f32 / 257.0 - 128.0).collect(),
+        })
+    }
+
+    fn parse_csv(text: &str) -> eyre::Result<Heightmap> {
+        let mut rows = Vec::new();
+        for (line_idx, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let row = line
+                .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+                .filter(|value| !value.is_empty())
+                .map(|value| {
+                    value
+                        .parse::<f32>()
+                        .map_err(|_| eyre!("line {}: '{value}' isn't a number", line_idx + 1))
+                })
+                .collect::<eyre::Result<Vec<f32>>>()?;
+            rows.push(row);
+        }
+        Self::from_rows(rows)
+    }
+
+    fn parse_json(text: &str) -> eyre::Result<Heightmap> {
+        let rows: Vec<Vec<f32>> = serde_json::from_str(text).wrap_err("Expected an array of arrays of numbers")?;
+        Self::from_rows(rows)
+    }
+
+    fn from_rows(rows: Vec<Vec<f32>>) -> eyre::Result<Heightmap> {
+        let width = rows.first().map_or(0, Vec::len);
+        if width == 0 {
+            bail!("The heightmap is empty.");
+        }
+        if let Some(row_idx) = rows.iter().position(|row| row.len() != width) {
+            bail!(
+                "Row {} has {} values, the first one has {width}: rows must have the same length.",
+                row_idx + 1,
+                rows[row_idx].len()
+            );
+        }
+        Ok(Heightmap {
+            width: width as u32,
+            height: rows.len() as u32,
+            values: rows.into_iter().flatten().collect(),
+        })
+    }
+
+    pub fn value(&self, x: u32, y: u32) -> f32 {
+        self.values[(y * self.width + x) as usize]
+    }
+
+    /// Bilinear sample at fractional coordinates (clamped to the grid).
+    pub fn sample(&self, x: f32, y: f32) -> f32 {
+        let x = x.clamp(0.0, (self.width - 1) as f32);
+        let y = y.clamp(0.0, (self.height - 1) as f32);
+        let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+        let (x1, y1) = ((x0 + 1).min(self.width - 1), (y0 + 1).min(self.height - 1));
+        let (fx, fy) = (x - x0 as f32, y - y0 as f32);
+        let top = self.value(x0, y0) * (1.0 - fx) + self.value(x1, y0) * fx;
+        let bottom = self.value(x0, y1) * (1.0 - fx) + self.value(x1, y1) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    /// Lowest and highest sample.
+    pub fn min_max(&self) -> (f32, f32) {
+        self.values.iter().fold((f32::MAX, f32::MIN), |(min, max), &value| {
+            (min.min(value), max.max(value))
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum HeightmapScaling {
+    /// z = value * scale + offset.
+    Linear { scale: f32, offset: f32 },
+    /// The lowest sample becomes z_min, the highest z_max.
+    FitRange,
+}
+
+#[derive(Clone, Debug)]
+pub struct HeightmapImportParams {
+    /// Top left tile of the rectangle.
+    pub x0: u32,
+    pub y0: u32,
+    /// Size of the rectangle, in tiles: None for one sample per tile.
+    pub size: Option<(u32, u32)>,
+    pub scaling: HeightmapScaling,
+    pub z_min: i8,
+    pub z_max: i8,
+}
+impl Default for HeightmapImportParams {
+    fn default() -> Self {
+        Self {
+            x0: 0,
+            y0: 0,
+            size: None,
+            scaling: HeightmapScaling::Linear {
+                scale: 1.0,
+                offset: 0.0,
+            },
+            z_min: i8::MIN,
+            z_max: i8::MAX,
+        }
+    }
+}
+
+/// Request to import a heightmap file into a map plane.
+#[derive(Event, Debug, Clone)]
+pub struct ImportHeightmapEvent {
+    pub map_id: u32,
+    pub file_path: PathBuf,
+    pub params: HeightmapImportParams,
+}
+
+/// z of every tile of the rectangle (width x height, row by row) from the heightmap.
+pub fn heightmap_z_values(heightmap: &Heightmap, width: u32, height: u32, params: &HeightmapImportParams) -> Vec<i8> {
+    let (z_min, z_max) = (params.z_min.min(params.z_max), params.z_min.max(params.z_max));
+    let (scale, offset) = match params.scaling {
+        HeightmapScaling::Linear { scale, offset } => (scale, offset),
+        HeightmapScaling::FitRange => {
+            let (min, max) = heightmap.min_max();
+            if max > min {
+                let scale = (z_max as f32 - z_min as f32) / (max - min);
+                (scale, z_min as f32 - min * scale)
+            } else {
+                (0.0, z_min as f32)
+            }
+        }
+    };
+    // Sample positions: tile centers, spread over the whole heightmap.
+    let step_x = heightmap.width as f32 / width as f32;
+    let step_y = heightmap.height as f32 / height as f32;
+    (0..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .map(|(x, y)| {
+            let value = heightmap.sample((x as f32 + 0.5) * step_x - 0.5, (y as f32 + 0.5) * step_y - 0.5);
+            (value * scale + offset).round().clamp(z_min as f32, z_max as f32) as i8
+        })
+        .collect()
+}
+
+pub(super) fn sys_import_heightmap(
+    mut events: EventReader<ImportHeightmapEvent>,
+    map_planes_r: Res<MapPlanesRes>,
+    mut history_r: ResMut<MapEditHistory>,
+    mut writer: EventWriter<LandCellsEditedEvent>,
+) {
+    for request in events.read() {
+        match import_heightmap(request, &map_planes_r) {
+            Ok(changes) => {
+                logger::one(
+                    None,
+                    LogSev::Info,
+                    LogAbout::MapEditor,
+                    &format!(
+                        "Imported heightmap '{}' into map plane {}: changed {} tiles.",
+                        request.file_path.display(),
+                        request.map_id,
+                        changes.len()
+                    ),
+                );
+                if let Some(rect) = bounding_rect(changes.iter().map(|change| (change.x, change.y))) {
+                    writer.write(LandCellsEditedEvent {
+                        map_id: request.map_id,
+                        rect,
+                    });
+                }
+                history_r.push(MapEditCommand::LandCells {
+                    map_id: request.map_id,
+                    changes,
+                });
+            }
+            Err(e) => logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::MapEditor,
+                &format!("Can't import heightmap '{}': {e:#}", request.file_path.display()),
+            ),
+        }
+    }
+}
+
+/// Writes the heightmap to the map plane. Returns the changed tiles, for the history.
+fn import_heightmap(request: &ImportHeightmapEvent, map_planes_r: &MapPlanesRes) -> eyre::Result<Vec<LandCellChange>> {
+    let map_plane = map_planes_r
+        .get(request.map_id)
+        .ok_or_else(|| eyre!("map plane {} isn't loaded", request.map_id))?;
+    let heightmap = Heightmap::load(&request.file_path)?;
+
+    let params = &request.params;
+    let (width, height) = params.size.unwrap_or((heightmap.width, heightmap.height));
+    let map_width = map_plane.size_blocks().width * MapBlock::CELLS_PER_ROW;
+    let map_height = map_plane.size_blocks().height * MapBlock::CELLS_PER_COLUMN;
+    if width == 0 || height == 0 || params.x0 >= map_width || params.y0 >= map_height {
+        bail!(
+            "the rectangle at {},{} of {width}x{height} tiles is outside the map ({map_width}x{map_height})",
+            params.x0,
+            params.y0
+        );
+    }
+    let z_values = heightmap_z_values(&heightmap, width, height, params);
+
+    // Tiles out of the map are skipped.
+    let (x1, y1) = ((params.x0 + width).min(map_width), (params.y0 + height).min(map_height));
+    let mut changes = Vec::new();
+    for y in params.y0..y1 {
+        for x in params.x0..x1 {
+            let before = map_plane
+                .cell(x, y)
+                .wrap_err_with(|| format!("reading the tile at {x},{y}"))?;
+            let z = z_values[((y - params.y0) * width + (x - params.x0)) as usize];
+            if z != before.z {
+                changes.push(LandCellChange {
+                    x,
+                    y,
+                    before,
+                    after: MapCell { z, ..before },
+                });
+            }
+        }
+    }
+    map_plane
+        .set_cells(changes.iter().map(|change| (change.x, change.y, change.after)))
+        .wrap_err_with(|| format!("writing the cells of map plane {}", request.map_id))?;
+    Ok(changes)
+}
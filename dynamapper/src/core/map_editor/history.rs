@@ -0,0 +1,227 @@
+// Undo/redo history of the map edits
+// - Each edit is a command (MapEditCommand) holding the data it changed both as it was before and after it: undoing
+//   writes back the "before" values, redoing the "after" ones.
+// - Land cells (z and tile id) are the only editable data for now; statics edits will be new MapEditCommand variants.
+// - The history holds up to [editor] history_size commands (settings.toml, applied on reload too): the oldest ones are
+//   dropped. A new edit clears the commands which could be redone.
+// - Ctrl + InputAction::UndoMapEdit / RedoMapEdit (Z / Y by default) in the map editing mode, or the toolbar buttons,
+//   send UndoMapEditEvent / RedoMapEditEvent. Applying a command sends a LandCellsEditedEvent, so that the land chunks
+//   showing the changed tiles are rebuilt.
+//
+
+use std::collections::VecDeque;
+
+use super::{LandCellsEditedEvent, MapEditorState, bounding_rect};
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::uo_files_loader::MapPlanesRes;
+use crate::prelude::*;
+use bevy::prelude::*;
+use color_eyre::eyre::{self, WrapErr, eyre};
+use uocf::geo::map::MapCell;
+
+/// A land cell changed by an edit.
+#[derive(Clone, Copy)]
+pub struct LandCellChange {
+    pub x: u32,
+    pub y: u32,
+    pub before: MapCell,
+    pub after: MapCell,
+}
+
+/// An edit of the map data, which can be undone and redone.
+#[derive(Clone)]
+pub enum MapEditCommand {
+    /// Land cells changed in a map plane, e.g. by a brush stroke.
+    LandCells { map_id: u32, changes: Vec<LandCellChange> },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HistoryDirection {
+    Undo,
+    Redo,
+}
+
+impl MapEditCommand {
+    /// Short description, for the log and the UI.
+    pub fn description(&self) -> String {
+        match self {
+            MapEditCommand::LandCells { map_id, changes } => {
+                format!("{} land tiles of map plane {map_id}", changes.len())
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        match self {
+            MapEditCommand::LandCells { changes, .. } => changes.is_empty(),
+        }
+    }
+
+    /// Writes the data as it was before (undo) or after (redo) the edit. Returns the tiles to redraw.
+    fn apply(&self, direction: HistoryDirection, map_planes_r: &MapPlanesRes) -> eyre::Result<LandCellsEditedEvent> {
+        match self {
+            MapEditCommand::LandCells { map_id, changes } => {
+                let map_plane = map_planes_r
+                    .get(*map_id)
+                    .ok_or_else(|| eyre!("map plane {map_id} isn't loaded"))?;
+                let rect = bounding_rect(changes.iter().map(|change| (change.x, change.y)))
+                    .ok_or_else(|| eyre!("no changed tiles"))?;
+                let cells = changes.iter().map(|change| {
+                    let cell = match direction {
+                        HistoryDirection::Undo => change.before,
+                        HistoryDirection::Redo => change.after,
+                    };
+                    (change.x, change.y, cell)
+                });
+                map_plane
+                    .set_cells(cells)
+                    .wrap_err_with(|| format!("writing the cells of map plane {map_id}"))?;
+                Ok(LandCellsEditedEvent { map_id: *map_id, rect })
+            }
+        }
+    }
+}
+
+/// Edits which can be undone (oldest first) and redone (the next one last).
+#[derive(Resource, Default)]
+pub struct MapEditHistory {
+    undo: VecDeque<MapEditCommand>,
+    redo: Vec<MapEditCommand>,
+    max_len: usize,
+}
+impl MapEditHistory {
+    pub fn undo_len(&self) -> usize {
+        self.undo.len()
+    }
+
+    pub fn redo_len(&self) -> usize {
+        self.redo.len()
+    }
+
+    /// The edit which the next undo reverts.
+    pub fn next_undo(&self) -> Option<&MapEditCommand> {
+        self.undo.back()
+    }
+
+    /// The edit which the next redo applies again.
+    pub fn next_redo(&self) -> Option<&MapEditCommand> {
+        self.redo.last()
+    }
+
+    /// Records an edit, already applied to the map data. The edits which could be redone are dropped.
+    pub fn push(&mut self, command: MapEditCommand) {
+        if command.is_empty() {
+            return;
+        }
+        self.redo.clear();
+        self.undo.push_back(command);
+        self.trim();
+    }
+
+    /// Changes the max number of edits kept, dropping the oldest ones if there are more.
+    pub fn set_max_len(&mut self, max_len: usize) {
+        self.max_len = max_len;
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.undo.len() > self.max_len {
+            self.undo.pop_front();
+        }
+        if self.redo.len() > self.max_len {
+            let excess = self.redo.len() - self.max_len;
+            self.redo.drain(..excess);
+        }
+    }
+}
+
+/// Request to revert the last edit.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct UndoMapEditEvent;
+
+/// Request to apply again the last reverted edit.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RedoMapEditEvent;
+
+pub(super) fn sys_apply_history_size(settings_r: Res<Settings>, mut history_r: ResMut<MapEditHistory>) {
+    // Also true at the first run, when the history has no size yet.
+    if settings_r.is_changed() {
+        history_r.set_max_len(settings_r.editor.history_size);
+    }
+}
+
+pub(super) fn sys_history_shortcuts(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    action_input: ActionInput,
+    editor_r: Res<MapEditorState>,
+    mut undo_writer: EventWriter<UndoMapEditEvent>,
+    mut redo_writer: EventWriter<RedoMapEditEvent>,
+) {
+    if !editor_r.enabled
+        || action_input.ui_wants_keyboard()
+        || !keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+    {
+        return;
+    }
+    if action_input.just_pressed(InputAction::UndoMapEdit) {
+        undo_writer.write(UndoMapEditEvent);
+    }
+    if action_input.just_pressed(InputAction::RedoMapEdit) {
+        redo_writer.write(RedoMapEditEvent);
+    }
+}
+
+pub(super) fn sys_undo_redo_map_edit(
+    mut undo_events: EventReader<UndoMapEditEvent>,
+    mut redo_events: EventReader<RedoMapEditEvent>,
+    editor_r: Res<MapEditorState>,
+    mut history_r: ResMut<MapEditHistory>,
+    map_planes_r: Res<MapPlanesRes>,
+    mut writer: EventWriter<LandCellsEditedEvent>,
+) {
+    let requests: Vec<HistoryDirection> = undo_events
+        .read()
+        .map(|_| HistoryDirection::Undo)
+        .chain(redo_events.read().map(|_| HistoryDirection::Redo))
+        .collect();
+    // The stroke in progress is recorded when it ends: until then, the cells it changed would be overwritten.
+    if requests.is_empty() || editor_r.is_stroke_in_progress() {
+        return;
+    }
+    let history = history_r.as_mut();
+    for direction in requests {
+        let command = match direction {
+            HistoryDirection::Undo => history.undo.pop_back(),
+            HistoryDirection::Redo => history.redo.pop(),
+        };
+        let Some(command) = command else {
+            continue;
+        };
+        let verb = match direction {
+            HistoryDirection::Undo => "undo",
+            HistoryDirection::Redo => "redo",
+        };
+        match command.apply(direction, &map_planes_r) {
+            Ok(event) => {
+                logger::one(
+                    None,
+                    LogSev::Debug,
+                    LogAbout::MapEditor,
+                    &format!("Map edit {verb}: {}.", command.description()),
+                );
+                writer.write(event);
+                match direction {
+                    HistoryDirection::Undo => history.redo.push(command),
+                    HistoryDirection::Redo => history.undo.push_back(command),
+                }
+            }
+            // The command is dropped: applying it again would fail the same way.
+            Err(e) => logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::MapEditor,
+                &format!("Can't {verb} the edit of {}: {e:#}", command.description()),
+            ),
+        }
+    }
+}
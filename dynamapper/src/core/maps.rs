@@ -1,3 +1,7 @@
+pub mod compare;
+pub mod manager;
+pub mod surface;
+
 use bevy::ecs::resource::Resource;
 
 #[derive(Resource, Default)]
@@ -0,0 +1,310 @@
+use std::path::PathBuf;
+
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::render::scene::world::land::{
+    LCDirty, LCMesh,
+    terrain_overlay::{TERRAIN_OVERLAY_MAP_DIFF, TERRAIN_OVERLAY_NONE},
+};
+use crate::core::system_sets::*;
+use crate::core::uo_files_loader::{MapPlanesRes, UoInterfaceSettingsRes, load_map_plane_file};
+use crate::external_data::shader_presets::UniformState;
+use crate::prelude::*;
+use bevy::prelude::*;
+use color_eyre::eyre::WrapErr;
+use uocf::geo::map::MapPlaneShared;
+use uocf::installation::UoInstallation;
+
+// A/B map compare: two versions of the map files of a map plane (e.g. the original one and an edited one, for shard
+//  developers), shown one at a time, with the tiles differing between them highlighted.
+// - A is the version in the UO folder, loaded as usual. B is loaded from another folder (SectUoFiles.compare_folder,
+//   or the one typed in the Map Compare window, see render::map_compare_ui). Both must have the same size.
+// - Only the map files (the terrain) are compared: the statics are always the ones of the UO folder.
+// - The version shown is the one in MapPlanesRes, so picking, editing, saving and exporting work on it. Flipping
+//   (InputAction::FlipMapCompare, B by default) swaps it with the other one, and rebuilds the chunks of the plane.
+// - The land chunks of the plane are built reading both versions (see draw_mesh), which sets the
+//   TILE_FLAG_CHANGED_* tile flags shown by the TERRAIN_OVERLAY_MAP_DIFF overlay.
+// - The MapPlaneManager keeps the compared plane loaded.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MapCompareSource {
+    /// Map files of the UO folder.
+    A,
+    /// Map files of the compare folder.
+    B,
+}
+impl MapCompareSource {
+    pub fn other(self) -> Self {
+        match self {
+            MapCompareSource::A => MapCompareSource::B,
+            MapCompareSource::B => MapCompareSource::A,
+        }
+    }
+}
+
+pub struct MapComparison {
+    pub map_id: u32,
+    /// Folder of the B version.
+    pub folder: PathBuf,
+    a: MapPlaneShared,
+    b: MapPlaneShared,
+    pub shown: MapCompareSource,
+}
+impl MapComparison {
+    fn plane(&self, source: MapCompareSource) -> &MapPlaneShared {
+        match source {
+            MapCompareSource::A => &self.a,
+            MapCompareSource::B => &self.b,
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct MapCompare {
+    pub comparison: Option<MapComparison>,
+}
+impl MapCompare {
+    pub fn compared_map(&self) -> Option<u32> {
+        self.comparison.as_ref().map(|comparison| comparison.map_id)
+    }
+
+    /// The version of the compared map plane which isn't shown (so isn't in MapPlanesRes).
+    pub fn hidden_plane(&self, map_id: u32) -> Option<&MapPlaneShared> {
+        self.comparison
+            .as_ref()
+            .filter(|comparison| comparison.map_id == map_id)
+            .map(|comparison| comparison.plane(comparison.shown.other()))
+    }
+}
+
+/// Request to compare the map files of a map plane with the ones in another folder.
+#[derive(Event, Debug, Clone)]
+pub struct CompareMapPlaneEvent {
+    pub map_id: u32,
+    pub folder: PathBuf,
+}
+
+/// Request to show the other version of the compared map plane.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct FlipMapCompareEvent;
+
+/// Request to stop comparing, showing the map files of the UO folder again.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct StopMapCompareEvent;
+
+pub struct MapComparePlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(MapComparePlugin);
+
+impl Plugin for MapComparePlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<MapCompare>()
+            .add_event::<CompareMapPlaneEvent>()
+            .add_event::<FlipMapCompareEvent>()
+            .add_event::<StopMapCompareEvent>()
+            .add_systems(
+                Update,
+                (
+                    sys_flip_map_compare_input,
+                    (sys_start_map_compare, sys_flip_map_compare, sys_stop_map_compare).chain(),
+                )
+                    .chain()
+                    .in_set(SceneRenderLandSysSet::ListenSyncRequests)
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn lg(sev: LogSev, text: &str) {
+    logger::one(None, sev, LogAbout::UoFiles, text);
+}
+
+/// Puts a version of the compared map plane in MapPlanesRes, and has its land chunks rebuilt.
+fn show_plane(
+    commands: &mut Commands,
+    map_planes_r: &MapPlanesRes,
+    chunks_q: &Query<(Entity, &LCMesh)>,
+    map_id: u32,
+    map_plane: &MapPlaneShared,
+) {
+    map_planes_r.0.insert(map_id, map_plane.clone());
+    for (entity, chunk) in chunks_q.iter() {
+        if chunk.parent_map_id == map_id {
+            commands.entity(entity).insert(LCDirty);
+        }
+    }
+}
+
+/// Switching the shown version with unsaved edits would make the undo history apply them to the other version.
+fn has_unsaved_edits(comparison: &MapComparison) -> bool {
+    let has_edits = comparison.plane(comparison.shown).read().has_edits();
+    if has_edits {
+        lg(
+            LogSev::Warn,
+            &format!(
+                "Map plane {} has unsaved edits: save or undo them before switching the compared map files.",
+                comparison.map_id
+            ),
+        );
+    }
+    has_edits
+}
+
+fn sys_flip_map_compare_input(
+    action_input: ActionInput,
+    map_compare_r: Res<MapCompare>,
+    mut writer: EventWriter<FlipMapCompareEvent>,
+) {
+    if map_compare_r.comparison.is_none()
+        || action_input.ui_wants_keyboard()
+        || !action_input.just_pressed(InputAction::FlipMapCompare)
+    {
+        return;
+    }
+    writer.write(FlipMapCompareEvent);
+}
+
+/// Loads the B version of the map plane and starts comparing it with the loaded one, replacing any other comparison.
+fn sys_start_map_compare(
+    mut commands: Commands,
+    mut events: EventReader<CompareMapPlaneEvent>,
+    mut map_compare_r: ResMut<MapCompare>,
+    mut uniform_state_r: ResMut<UniformState>,
+    map_planes_r: Res<MapPlanesRes>,
+    uo_interface_settings_r: Res<UoInterfaceSettingsRes>,
+    chunks_q: Query<(Entity, &LCMesh)>,
+) {
+    // Only the last request matters.
+    let Some(CompareMapPlaneEvent { map_id, folder }) = events.read().last().cloned() else {
+        return;
+    };
+    if let Some(comparison) = &map_compare_r.comparison
+        && comparison.shown == MapCompareSource::B
+        && has_unsaved_edits(comparison)
+    {
+        return;
+    }
+    // MapPlanesRes holds B if it's the plane compared and B is shown.
+    let a = match &map_compare_r.comparison {
+        Some(comparison) if comparison.map_id == map_id => Some(comparison.a.clone()),
+        _ => map_planes_r.get(map_id),
+    };
+    let Some(a) = a else {
+        lg(
+            LogSev::Error,
+            &format!("Can't compare map plane {map_id}: it isn't loaded."),
+        );
+        return;
+    };
+    // Loading B fails if its size isn't the one of A.
+    let uo_settings = &uo_interface_settings_r.0;
+    let map_size = Some(a.size_blocks().to_cells());
+    let b = UoInstallation::scan(&folder).wrap_err("Scan the folder").and_then(|installation| {
+        load_map_plane_file(&installation, map_id, uo_settings.map_file_backend, &uo_settings.map_defs, map_size)
+    });
+    let b = match b {
+        Ok(map_plane) => MapPlaneShared::new(map_plane),
+        Err(e) => {
+            lg(
+                LogSev::Error,
+                &format!("Can't load map plane {map_id} from {}: {e:?}", folder.display()),
+            );
+            return;
+        }
+    };
+    // Put back the UO folder version of a previously compared plane.
+    if let Some(previous) = map_compare_r.comparison.take()
+        && previous.shown == MapCompareSource::B
+    {
+        show_plane(&mut commands, &map_planes_r, &chunks_q, previous.map_id, &previous.a);
+    }
+    // The chunks shown with A are rebuilt with the diff flags.
+    show_plane(&mut commands, &map_planes_r, &chunks_q, map_id, &a);
+    lg(
+        LogSev::Info,
+        &format!("Comparing map plane {map_id} with the one in {}.", folder.display()),
+    );
+    map_compare_r.comparison = Some(MapComparison {
+        map_id,
+        folder,
+        a,
+        b,
+        shown: MapCompareSource::A,
+    });
+    let u = uniform_state_r.as_mut();
+    u.effects.overlay_mode = TERRAIN_OVERLAY_MAP_DIFF;
+    u.dirty = true;
+}
+
+/// Shows the other version of the compared map plane.
+fn sys_flip_map_compare(
+    mut commands: Commands,
+    mut events: EventReader<FlipMapCompareEvent>,
+    mut map_compare_r: ResMut<MapCompare>,
+    map_planes_r: Res<MapPlanesRes>,
+    chunks_q: Query<(Entity, &LCMesh)>,
+) {
+    // Flipping twice in a frame shows the same version.
+    if events.read().count() % 2 == 0 {
+        return;
+    }
+    let Some(comparison) = map_compare_r.comparison.as_mut() else {
+        return;
+    };
+    if has_unsaved_edits(comparison) {
+        return;
+    }
+    comparison.shown = comparison.shown.other();
+    show_plane(
+        &mut commands,
+        &map_planes_r,
+        &chunks_q,
+        comparison.map_id,
+        comparison.plane(comparison.shown),
+    );
+    lg(
+        LogSev::Debug,
+        &format!(
+            "Showing version {:?} of map plane {}.",
+            comparison.shown, comparison.map_id
+        ),
+    );
+}
+
+fn sys_stop_map_compare(
+    mut commands: Commands,
+    mut events: EventReader<StopMapCompareEvent>,
+    mut map_compare_r: ResMut<MapCompare>,
+    mut uniform_state_r: ResMut<UniformState>,
+    map_planes_r: Res<MapPlanesRes>,
+    chunks_q: Query<(Entity, &LCMesh)>,
+) {
+    if events.read().count() == 0 {
+        return;
+    }
+    let Some(comparison) = map_compare_r.comparison.as_ref() else {
+        return;
+    };
+    if comparison.shown == MapCompareSource::B && has_unsaved_edits(comparison) {
+        return;
+    }
+    let comparison = map_compare_r.comparison.take().unwrap();
+    // Rebuilt without the diff flags.
+    show_plane(
+        &mut commands,
+        &map_planes_r,
+        &chunks_q,
+        comparison.map_id,
+        &comparison.a,
+    );
+    let u = uniform_state_r.as_mut();
+    if u.effects.overlay_mode == TERRAIN_OVERLAY_MAP_DIFF {
+        u.effects.overlay_mode = TERRAIN_OVERLAY_NONE;
+        u.dirty = true;
+    }
+    lg(
+        LogSev::Info,
+        &format!("Stopped comparing map plane {}.", comparison.map_id),
+    );
+}
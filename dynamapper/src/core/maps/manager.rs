@@ -0,0 +1,293 @@
+use std::collections::VecDeque;
+
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::controls::player_movement::PlayerWalk;
+use crate::core::maps::MapPlaneMetadata;
+use crate::core::maps::compare::MapCompare;
+use crate::core::render::scene::SceneStateData;
+use crate::core::render::scene::player::Player;
+use crate::core::render::scene::split_view::SplitView;
+use crate::core::render::scene::world::{WorldGeoData, land::LCMesh};
+use crate::core::system_sets::*;
+use crate::core::uo_files_loader::{
+    MapPlanesRes, StaticsPlanesRes, UoInterfaceSettingsRes, load_map_plane_files,
+};
+use crate::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use uocf::geo::map::{MapBlock, MapPlaneShared};
+
+// Switching map plane keeps the data (MapPlane, StaticsPlane and their block caches) and the chunk entities of the
+//  recently visited planes around, so that going back to one of them doesn't reload everything.
+// The chunk entities of the other planes are only hidden by the scene (see sys_update_worldmap_chunks_to_render),
+//  they are despawned when their plane is dropped from the recently visited list.
+
+/// How many map planes (data and chunk entities) are kept in memory, including the current one.
+pub const MAX_RESIDENT_MAP_PLANES: usize = 3;
+
+/// Request to move the player to another map plane, keeping its position (clamped to the new map size).
+#[derive(Event, Debug, Clone, Copy)]
+pub struct SwitchMapPlaneEvent {
+    pub map_id: u32,
+}
+
+/// Request to load a map plane, without moving the player there.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct LoadMapPlaneEvent {
+    pub map_id: u32,
+}
+
+#[derive(Resource, Default)]
+pub struct MapPlaneManager {
+    // Loaded map planes, most recently visited first.
+    resident: VecDeque<u32>,
+}
+impl MapPlaneManager {
+    pub fn resident_planes(&self) -> impl Iterator<Item = &u32> {
+        self.resident.iter()
+    }
+
+    pub fn is_resident(&self, map_id: u32) -> bool {
+        self.resident.contains(&map_id)
+    }
+
+    /// Marks the plane as the most recently visited one.
+    /// Returns the planes exceeding MAX_RESIDENT_MAP_PLANES, which have to be unloaded.
+    fn touch(&mut self, map_id: u32) -> Vec<u32> {
+        self.resident.retain(|&id| id != map_id);
+        self.resident.push_front(map_id);
+        let mut to_unload = Vec::new();
+        while self.resident.len() > MAX_RESIDENT_MAP_PLANES {
+            to_unload.extend(self.resident.pop_back());
+        }
+        to_unload
+    }
+
+    /// Keeps a plane returned by touch as the least recently visited one, instead of unloading it.
+    fn keep_resident(&mut self, map_id: u32) {
+        self.resident.push_back(map_id);
+    }
+}
+
+pub struct MapPlaneManagerPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(MapPlaneManagerPlugin);
+
+impl Plugin for MapPlaneManagerPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<MapPlaneManager>()
+            .add_event::<SwitchMapPlaneEvent>()
+            .add_event::<LoadMapPlaneEvent>()
+            // Needs the map planes loaded from the UO files.
+            .add_systems(OnEnter(LoadingPhase::Textures), sys_setup_map_plane_manager)
+            .add_systems(
+                Update,
+                (
+                    sys_map_plane_switch_input.in_set(MovementSysSet::MovementActions),
+                    (sys_switch_map_plane, sys_load_map_plane)
+                        .chain()
+                        .after(MovementSysSet::MovementActions)
+                        .in_set(SceneRenderLandSysSet::ListenSyncRequests),
+                )
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn map_plane_metadata(map_plane: &MapPlaneShared) -> MapPlaneMetadata {
+    MapPlaneMetadata {
+        id: map_plane.index() as u8,
+        width: map_plane.size_blocks().width * MapBlock::CELLS_PER_ROW,
+        height: map_plane.size_blocks().height * MapBlock::CELLS_PER_COLUMN,
+    }
+}
+
+/// Registers the map planes loaded at startup.
+pub fn sys_setup_map_plane_manager(
+    mut manager_r: ResMut<MapPlaneManager>,
+    mut world_geo_data_r: ResMut<WorldGeoData>,
+    map_planes_r: Res<MapPlanesRes>,
+) {
+    log_system_add_onenter::<MapPlaneManagerPlugin>(LoadingPhase::Textures.as_ref(), fname!());
+
+    for map_plane in map_planes_r.0.iter() {
+        world_geo_data_r
+            .maps
+            .insert(map_plane.index(), map_plane_metadata(&map_plane));
+        manager_r.touch(map_plane.index());
+    }
+}
+
+/// Cycles through the map planes with a definition and a map file (see UoInterfaceSettings::map_plane_indices).
+fn sys_map_plane_switch_input(
+    action_input: ActionInput,
+    uo_interface_settings_r: Res<UoInterfaceSettingsRes>,
+    player_q: Query<&Player>,
+    mut writer: EventWriter<SwitchMapPlaneEvent>,
+) {
+    let step: i32 = if action_input.just_pressed(InputAction::NextMapPlane) {
+        1
+    } else if action_input.just_pressed(InputAction::PrevMapPlane) {
+        -1
+    } else {
+        return;
+    };
+    let Some(player_pos) = player_q.single().ok().and_then(|player| player.current_pos) else {
+        return;
+    };
+    let map_ids: Vec<u32> = uo_interface_settings_r.0.map_plane_indices().collect();
+    let Some(current) = map_ids.iter().position(|&map_id| map_id == player_pos.m as u32) else {
+        return;
+    };
+    let map_id = map_ids[(current as i32 + step).rem_euclid(map_ids.len() as i32) as usize];
+    writer.write(SwitchMapPlaneEvent { map_id });
+}
+
+/// Loads map planes and keeps the number of resident ones within MAX_RESIDENT_MAP_PLANES.
+#[derive(SystemParam)]
+pub struct MapPlaneLoader<'w, 's> {
+    commands: Commands<'w, 's>,
+    manager_r: ResMut<'w, MapPlaneManager>,
+    world_geo_data_r: ResMut<'w, WorldGeoData>,
+    uo_interface_settings_r: Res<'w, UoInterfaceSettingsRes>,
+    map_planes_r: Res<'w, MapPlanesRes>,
+    statics_planes_r: Res<'w, StaticsPlanesRes>,
+    scene_state_data_r: Res<'w, SceneStateData>,
+    split_view_r: Res<'w, SplitView>,
+    map_compare_r: Res<'w, MapCompare>,
+    chunks_q: Query<'w, 's, (Entity, &'static LCMesh)>,
+}
+impl MapPlaneLoader<'_, '_> {
+    /// Loads the map plane data, if it isn't already cached, and marks the plane as the most recently visited one.
+    /// Returns None if its files can't be loaded.
+    pub fn make_resident(&mut self, map_id: u32) -> Option<MapPlaneMetadata> {
+        if !self.map_planes_r.0.contains_key(&map_id) {
+            match load_map_plane_files(&self.uo_interface_settings_r.0, map_id) {
+                Ok((map_plane, statics_plane)) => {
+                    self.map_planes_r.0.insert(map_id, MapPlaneShared::new(map_plane));
+                    self.statics_planes_r.0.insert(map_id, statics_plane);
+                }
+                Err(e) => {
+                    logger::one(
+                        None,
+                        LogSev::Error,
+                        LogAbout::UoFiles,
+                        &format!("Can't load map plane {map_id}: {e:?}"),
+                    );
+                    return None;
+                }
+            }
+        }
+        let metadata = {
+            let map_plane = self.map_planes_r.get(map_id).unwrap();
+            map_plane_metadata(&map_plane)
+        };
+        self.world_geo_data_r.maps.insert(map_id, metadata);
+
+        // Drop the least recently visited planes: their data and their (hidden) chunk entities.
+        // Planes with changes made in the map editing mode are kept: they'd be lost. So are the planes on screen, and
+        //  the compared one (see maps::compare).
+        for unloaded_map_id in self.manager_r.touch(map_id) {
+            let keep_reason = if self
+                .map_planes_r
+                .get(unloaded_map_id)
+                .is_some_and(|map_plane| map_plane.read().has_edits())
+            {
+                Some("it has unsaved edits")
+            } else if unloaded_map_id == self.scene_state_data_r.map_id
+                || self.split_view_r.shown_map() == Some(unloaded_map_id)
+            {
+                Some("it's on screen")
+            } else if self.map_compare_r.compared_map() == Some(unloaded_map_id) {
+                Some("it's compared with other map files")
+            } else {
+                None
+            };
+            if let Some(keep_reason) = keep_reason {
+                self.manager_r.keep_resident(unloaded_map_id);
+                logger::one(
+                    None,
+                    LogSev::Info,
+                    LogAbout::UoFiles,
+                    &format!("Keeping map plane {unloaded_map_id} loaded: {keep_reason}."),
+                );
+                continue;
+            }
+            self.map_planes_r.0.remove(&unloaded_map_id);
+            self.statics_planes_r.0.remove(&unloaded_map_id);
+            let mut despawned_chunks: usize = 0;
+            for (entity, chunk) in self.chunks_q.iter() {
+                if chunk.parent_map_id == unloaded_map_id {
+                    self.commands.entity(entity).despawn();
+                    despawned_chunks += 1;
+                }
+            }
+            logger::one(
+                None,
+                LogSev::Info,
+                LogAbout::UoFiles,
+                &format!("Unloaded map plane {unloaded_map_id} ({despawned_chunks} pooled chunks despawned)."),
+            );
+        }
+        Some(metadata)
+    }
+}
+
+/// Loads (if needed) the requested map plane and moves the player there. The scene reacts to the player map change.
+pub fn sys_switch_map_plane(
+    mut events: EventReader<SwitchMapPlaneEvent>,
+    mut loader: MapPlaneLoader,
+    mut walk_r: ResMut<PlayerWalk>,
+    mut player_q: Query<(&mut Player, &mut Transform)>,
+) {
+    // Only the last request matters.
+    let Some(&SwitchMapPlaneEvent { map_id }) = events.read().last() else {
+        return;
+    };
+    let Ok((mut player, mut player_transform)) = player_q.single_mut() else {
+        return;
+    };
+    let Some(mut player_pos) = player.current_pos else {
+        return;
+    };
+    if player_pos.m as u32 == map_id {
+        return;
+    }
+    let Some(metadata) = loader.make_resident(map_id) else {
+        return;
+    };
+
+    // Move the player, keeping it inside the new map.
+    player_pos.m = map_id as u8;
+    player_pos.x = player_pos.x.min(metadata.width.saturating_sub(1) as u16);
+    player_pos.y = player_pos.y.min(metadata.height.saturating_sub(1) as u16);
+    player.current_pos = Some(player_pos);
+    // A step in progress would take the player back to its tile on the old map plane.
+    walk_r.stop();
+    let translation = &mut player_transform.translation;
+    translation.x = player_pos.x as f32;
+    translation.z = player_pos.y as f32;
+
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::Player,
+        &format!("Player moved to map plane {map_id}."),
+    );
+}
+
+/// Loads map planes shown without moving the player there (e.g. in the split view).
+fn sys_load_map_plane(mut events: EventReader<LoadMapPlaneEvent>, mut loader: MapPlaneLoader) {
+    for &LoadMapPlaneEvent { map_id } in events.read() {
+        if loader.make_resident(map_id).is_some() {
+            logger::one(
+                None,
+                LogSev::Info,
+                LogAbout::UoFiles,
+                &format!("Map plane {map_id} loaded."),
+            );
+        }
+    }
+}
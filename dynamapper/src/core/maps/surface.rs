@@ -0,0 +1,40 @@
+use crate::core::uo_files_loader::{MapPlanesRes, StaticsPlanesRes, TileDataRes};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use uocf::geo::surface::{SurfaceSpot, WorldQuery, step_spot};
+
+// Standing heights on the loaded map planes, for the player movement, click-to-move and anything placed on the
+//  ground: the rules of uocf::geo::surface, applied to the shared map and statics data.
+// Each query locks the statics plane for its cell only. The path finder reads many cells, and keeps its own copies of
+//  the blocks instead (see pathfinding).
+
+/// System param for the surface queries. Cells outside of a map plane, or of a map plane not loaded, have no spots.
+#[derive(SystemParam)]
+pub struct WorldSurface<'w> {
+    map_planes_r: Res<'w, MapPlanesRes>,
+    statics_planes_r: Res<'w, StaticsPlanesRes>,
+    tiledata_r: Res<'w, TileDataRes>,
+}
+impl WorldSurface<'_> {
+    /// Where a walker can stand in a cell, lowest first.
+    pub fn standing_spots(&self, map_id: u32, x: u32, y: u32) -> Vec<SurfaceSpot> {
+        let Some(map_plane) = self.map_planes_r.get(map_id) else {
+            return Vec::new();
+        };
+        // Without statics data we still stand on the land.
+        let mut statics_plane = self.statics_planes_r.0.get_mut(&map_id);
+        WorldQuery::new(&self.tiledata_r.0, &map_plane, statics_plane.as_deref_mut())
+            .standing_spots(x, y)
+            .unwrap_or_default()
+    }
+
+    /// The highest z a walker can stand at in a cell.
+    pub fn surface_top_z(&self, map_id: u32, x: u32, y: u32) -> Option<i32> {
+        self.standing_spots(map_id, x, y).last().map(|spot| spot.z)
+    }
+
+    /// The z reached stepping into a cell from from_z, if it can be entered.
+    pub fn step_z(&self, map_id: u32, x: u32, y: u32, from_z: i32) -> Option<i32> {
+        step_spot(&self.standing_spots(map_id, x, y), from_z).map(|spot| spot.z)
+    }
+}
@@ -0,0 +1,203 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+
+use crate::core::uo_files_loader::{MapPlanesRes, StaticsPlanesRes, TileDataRes};
+use crate::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use uocf::geo::map::{MapBlock, MapBlockRelPos, MapCell};
+use uocf::geo::statics::StaticsBlock;
+use uocf::geo::surface::{self, SurfaceSpot};
+use uocf::tiledata::TileData;
+
+// A* over the map cells. A cell can be entered at the standing spot reached stepping into it from the current z, by
+//  the rules of uocf::geo::surface (land, surface and bridge statics, impassable items, the step up limit). Diagonal
+//  moves can't cut the corner of a blocked cell.
+// Every move (orthogonal or diagonal) costs the same, like in the game.
+
+/// Give up after visiting this many cells, to avoid stalling on unreachable destinations.
+const MAX_VISITED_CELLS: usize = 50_000;
+
+#[rustfmt::skip]
+const NEIGHBOR_OFFSETS: [(i32, i32); 8] = [
+    (0, -1), (1, -1), (1, 0), (1, 1),
+    (0, 1), (-1, 1), (-1, 0), (-1, -1),
+];
+
+/// Map and statics blocks copied from the shared caches, so that we lock them once per block instead of once per cell.
+struct WalkGridCache<'a> {
+    map_planes: &'a MapPlanesRes,
+    statics_planes: &'a StaticsPlanesRes,
+    tiledata: &'a TileData,
+    map_id: u32,
+    blocks: HashMap<MapBlockRelPos, Option<(MapBlock, StaticsBlock)>>,
+}
+impl<'a> WalkGridCache<'a> {
+    fn block(&mut self, pos: MapBlockRelPos) -> Option<&(MapBlock, StaticsBlock)> {
+        if !self.blocks.contains_key(&pos) {
+            let loaded = self.load_block(pos);
+            self.blocks.insert(pos, loaded);
+        }
+        self.blocks.get(&pos).and_then(|block| block.as_ref())
+    }
+
+    fn load_block(&self, pos: MapBlockRelPos) -> Option<(MapBlock, StaticsBlock)> {
+        let map_block = {
+            let map_plane = self.map_planes.get(self.map_id)?;
+            if !map_plane.contains_block(pos) {
+                return None;
+            }
+            map_plane.load_block(pos).ok()?
+        };
+        // Without statics data we still walk on the land.
+        let statics_block = self
+            .statics_planes
+            .0
+            .get_mut(&self.map_id)
+            .and_then(|mut statics_plane| {
+                statics_plane.load_blocks(&mut [pos]).ok()?;
+                statics_plane.block(pos).cloned()
+            })
+            .unwrap_or_default();
+        Some((map_block, statics_block))
+    }
+
+    /// Where a walker can stand, in the given cell.
+    fn standing_spots(&mut self, x: i32, y: i32) -> Vec<SurfaceSpot> {
+        if x < 0 || y < 0 {
+            return Vec::new();
+        }
+        let (x, y) = (x as u32, y as u32);
+        let tiledata = self.tiledata;
+        let block_pos = MapBlockRelPos {
+            x: MapCell::coords_of_parent_block_x(x),
+            y: MapCell::coords_of_parent_block_y(y),
+        };
+        let Some((map_block, statics_block)) = self.block(block_pos) else {
+            return Vec::new();
+        };
+        let (cx, cy) = (MapCell::coords_in_block_x(x), MapCell::coords_in_block_y(y));
+        let Ok(land_cell) = map_block.cell(cx, cy) else {
+            return Vec::new();
+        };
+        surface::standing_spots(tiledata, *land_cell, statics_block.items_at(cx, cy))
+    }
+
+    /// Where we end up stepping into the cell from the given z.
+    fn step_z(&mut self, x: i32, y: i32, from_z: i32) -> Option<i32> {
+        surface::step_spot(&self.standing_spots(x, y), from_z).map(|spot| spot.z)
+    }
+}
+
+/// Chebyshev distance: diagonal moves cost the same as the orthogonal ones.
+fn heuristic(a: (i32, i32), b: (i32, i32)) -> u32 {
+    a.0.abs_diff(b.0).max(a.1.abs_diff(b.1))
+}
+
+/// Path finder working on the shared UO data. Usable outside systems too (see the Pathfinder system param).
+pub struct PathFinder<'a> {
+    pub map_planes: &'a MapPlanesRes,
+    pub statics_planes: &'a StaticsPlanesRes,
+    pub tiledata: &'a TileData,
+}
+impl PathFinder<'_> {
+    /// Path from a position to another one on the same map plane, excluding the start position.
+    /// The z of the destination is ignored: we stop on any standing spot of the destination cell.
+    /// Returns None if the destination can't be reached (or it's too far to be found in reasonable time).
+    pub fn find_path(&self, from: UOVec4, to: UOVec4) -> Option<Vec<UOVec4>> {
+        if from.m != to.m {
+            return None;
+        }
+        let map_id = from.m as u32;
+        let start = (from.x as i32, from.y as i32);
+        let goal = (to.x as i32, to.y as i32);
+        if start == goal {
+            return Some(Vec::new());
+        }
+
+        let mut grid = WalkGridCache {
+            map_planes: self.map_planes,
+            statics_planes: self.statics_planes,
+            tiledata: self.tiledata,
+            map_id,
+            blocks: HashMap::new(),
+        };
+
+        // cell -> (cost from start, z, previous cell)
+        let mut visited: HashMap<(i32, i32), (u32, i32, Option<(i32, i32)>)> = HashMap::new();
+        // (estimated total cost, cost from start, cell)
+        let mut open: BinaryHeap<Reverse<(u32, u32, (i32, i32))>> = BinaryHeap::new();
+        visited.insert(start, (0, from.z as i32, None));
+        open.push(Reverse((heuristic(start, goal), 0, start)));
+
+        while let Some(Reverse((_, cost, cell))) = open.pop() {
+            if cell == goal {
+                break;
+            }
+            let (best_cost, z, _) = visited[&cell];
+            if cost > best_cost {
+                // Stale entry: the cell was reached later with a lower cost.
+                continue;
+            }
+            if visited.len() > MAX_VISITED_CELLS {
+                return None;
+            }
+
+            for (dx, dy) in NEIGHBOR_OFFSETS {
+                let next = (cell.0 + dx, cell.1 + dy);
+                let Some(next_z) = grid.step_z(next.0, next.1, z) else {
+                    continue;
+                };
+                // Don't cut the corners of blocked cells.
+                if dx != 0
+                    && dy != 0
+                    && (grid.step_z(cell.0 + dx, cell.1, z).is_none()
+                        || grid.step_z(cell.0, cell.1 + dy, z).is_none())
+                {
+                    continue;
+                }
+                let next_cost = cost + 1;
+                if visited
+                    .get(&next)
+                    .is_some_and(|&(known_cost, _, _)| known_cost <= next_cost)
+                {
+                    continue;
+                }
+                visited.insert(next, (next_cost, next_z, Some(cell)));
+                open.push(Reverse((next_cost + heuristic(next, goal), next_cost, next)));
+            }
+        }
+
+        // Walk back from the goal.
+        visited.get(&goal)?;
+        let mut path = Vec::new();
+        let mut cell = goal;
+        while let Some(&(_, z, prev)) = visited.get(&cell) {
+            let Some(prev) = prev else {
+                break;
+            };
+            path.push(UOVec4::new(cell.0 as u16, cell.1 as u16, z as i8, from.m));
+            cell = prev;
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/// System param giving access to the path finder.
+#[derive(SystemParam)]
+pub struct Pathfinder<'w> {
+    map_planes_r: Res<'w, MapPlanesRes>,
+    statics_planes_r: Res<'w, StaticsPlanesRes>,
+    tiledata_r: Res<'w, TileDataRes>,
+}
+impl Pathfinder<'_> {
+    pub fn find_path(&self, from: UOVec4, to: UOVec4) -> Option<Vec<UOVec4>> {
+        PathFinder {
+            map_planes: &self.map_planes_r,
+            statics_planes: &self.statics_planes_r,
+            tiledata: &self.tiledata_r.0,
+        }
+        .find_path(from, to)
+    }
+}
@@ -1,6 +1,27 @@
+pub mod annotations_ui;
+pub mod background_pause;
+pub mod day_night;
+pub mod diagnostics_ui;
+pub mod export;
+pub mod export_ui;
+pub mod frame_rate_ui;
+pub mod go_to_ui;
+pub mod key_bindings_ui;
+pub mod live_shard_ui;
+pub mod loading_ui;
+pub mod log_console_ui;
+pub mod map_compare_ui;
+pub mod map_editor_ui;
 pub mod overlays;
+pub mod render_compare_ui;
+pub mod render_test;
 pub mod scene;
+pub mod shader_status_ui;
+pub mod shard_overlays_ui;
+pub mod split_view_ui;
 pub mod terrain_shader_ui;
+pub mod tile_inspector_ui;
+pub mod uo_files_ui;
 
 use crate::prelude::*;
 use bevy::prelude::*;
@@ -19,9 +40,77 @@ fn build(&self, app: &mut App) {
             overlays::OverlaysPlugin {
                 registered_by: "RenderPlugin",
             },
+            export::MapExportPlugin {
+                registered_by: "RenderPlugin",
+            },
+            export::mesh::MapMeshExportPlugin {
+                registered_by: "RenderPlugin",
+            },
+            day_night::DayNightPlugin {
+                registered_by: "RenderPlugin",
+            },
             terrain_shader_ui::TerrainUiPlugin {
                 registered_by: "RenderPlugin",
             },
+            tile_inspector_ui::TileInspectorUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            loading_ui::LoadingUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            key_bindings_ui::KeyBindingsUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            diagnostics_ui::DiagnosticsUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            log_console_ui::LogConsoleUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            map_editor_ui::MapEditorUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            go_to_ui::GoToUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            split_view_ui::SplitViewUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            map_compare_ui::MapCompareUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+        ))
+        .add_plugins((
+            uo_files_ui::UoFilesUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            export_ui::MapExportUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            live_shard_ui::LiveShardUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            shard_overlays_ui::ShardOverlaysUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            annotations_ui::AnnotationsUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            shader_status_ui::ShaderStatusUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            render_compare_ui::RenderCompareUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            render_test::RenderTestPlugin {
+                registered_by: "RenderPlugin",
+            },
+            frame_rate_ui::FrameRateUiPlugin {
+                registered_by: "RenderPlugin",
+            },
+            background_pause::BackgroundPausePlugin {
+                registered_by: "RenderPlugin",
+            },
         ));
     }
 }
@@ -0,0 +1,255 @@
+// Annotations (egui window)
+// - Toggled with InputAction::ToggleAnnotations (N by default).
+// - Sets up the next annotation (external_data::annotations): kind, name or note text, color. It's placed at the tile
+//   under the cursor with InputAction::PlaceAnnotation (M by default), at the player location, or at typed
+//   coordinates ("x, y" or "x, y, z", as in the Go to window).
+// - Lists the annotations of the map plane shown: each one can be jumped to or removed. They can all be hidden.
+//
+
+use crate::{
+    core::{
+        controls::key_bindings::{ActionInput, InputAction, KeyBindings, key_name},
+        render::{
+            go_to_ui::parse_go_to_coords,
+            scene::{
+                SceneStateData,
+                picking::{TilePicker, land_cell_at},
+                player::{Player, TeleportPlayerEvent},
+                world::WorldGeoData,
+            },
+        },
+        uo_files_loader::{MapPlanesRes, UoInterfaceSettingsRes},
+    },
+    external_data::annotations::{Annotation, AnnotationKind, Annotations},
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::EguiWantsInput};
+
+#[derive(Resource)]
+pub struct AnnotationsWindow {
+    pub visible: bool,
+    /// Of the next annotation placed.
+    pub kind: AnnotationKind,
+    pub text: String,
+    pub color: [u8; 3],
+    /// Coordinates typed to place the next annotation.
+    pub coords: String,
+    /// Why the typed coordinates were rejected.
+    pub error: Option<String>,
+}
+impl Default for AnnotationsWindow {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            kind: AnnotationKind::Marker,
+            text: String::new(),
+            color: [255, 200, 40],
+            coords: String::new(),
+            error: None,
+        }
+    }
+}
+impl AnnotationsWindow {
+    /// The next annotation, at a location. Without a text, it's named after the location.
+    fn annotation_at(&self, x: u16, y: u16, z: i8) -> Annotation {
+        let text = match self.text.trim() {
+            "" => format!("{x}, {y}"),
+            text => text.to_owned(),
+        };
+        Annotation {
+            kind: self.kind,
+            text,
+            x,
+            y,
+            z,
+            color: self.color,
+        }
+    }
+}
+
+pub struct AnnotationsUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(AnnotationsUiPlugin);
+
+impl Plugin for AnnotationsUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<AnnotationsWindow>()
+            .add_systems(
+                Update,
+                (sys_toggle_annotations_window, sys_place_annotation_at_cursor).run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                annotations_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_toggle_annotations_window(action_input: ActionInput, mut window_r: ResMut<AnnotationsWindow>) {
+    if action_input.ui_wants_keyboard() || !action_input.just_pressed(InputAction::ToggleAnnotations) {
+        return;
+    }
+    window_r.visible = !window_r.visible;
+}
+
+fn sys_place_annotation_at_cursor(
+    action_input: ActionInput,
+    egui_wants_input_r: Res<EguiWantsInput>,
+    window_r: Res<AnnotationsWindow>,
+    mut annotations_r: ResMut<Annotations>,
+    tile_picker: TilePicker,
+) {
+    if action_input.ui_wants_keyboard()
+        || egui_wants_input_r.wants_pointer_input()
+        || !action_input.just_pressed(InputAction::PlaceAnnotation)
+    {
+        return;
+    }
+    let Some(tile) = tile_picker.cursor_tile() else {
+        return;
+    };
+    annotations_r.add(window_r.annotation_at(tile.x as u16, tile.y as u16, tile.cell.z));
+}
+
+fn annotations_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut window_r: ResMut<AnnotationsWindow>,
+    mut annotations_r: ResMut<Annotations>,
+    scene_state_data_r: Res<SceneStateData>,
+    world_geo_data_r: Res<WorldGeoData>,
+    map_planes_r: Res<MapPlanesRes>,
+    uo_interface_settings_r: Res<UoInterfaceSettingsRes>,
+    key_bindings_r: Res<KeyBindings>,
+    player_q: Query<(&Player, &Transform)>,
+    mut teleport_writer: EventWriter<TeleportPlayerEvent>,
+) {
+    if !window_r.visible {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let map_id = scene_state_data_r.map_id;
+    let window = window_r.as_mut();
+    egui::Window::new("Annotations")
+        .default_pos([360.0, 420.0])
+        .default_width(360.0)
+        .collapsible(false)
+        .open(&mut window.visible)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for kind in AnnotationKind::ALL {
+                    ui.selectable_value(&mut window.kind, kind, kind.as_ref());
+                }
+                ui.color_edit_button_srgb(&mut window.color);
+            });
+            let hint = match window.kind {
+                AnnotationKind::Note => "Note text",
+                _ => "Name",
+            };
+            if window.kind == AnnotationKind::Note {
+                ui.add(
+                    egui::TextEdit::multiline(&mut window.text)
+                        .hint_text(hint)
+                        .desired_rows(3)
+                        .desired_width(f32::INFINITY),
+                );
+            } else {
+                ui.add(
+                    egui::TextEdit::singleline(&mut window.text)
+                        .hint_text(hint)
+                        .desired_width(f32::INFINITY),
+                );
+            }
+            let place_key = key_name(key_bindings_r.key(InputAction::PlaceAnnotation)).unwrap_or("?");
+            ui.label(format!("{place_key}: place it at the tile under the cursor."));
+            ui.horizontal(|ui| {
+                let player_pos = player_q
+                    .single()
+                    .ok()
+                    .and_then(|(player, transform)| player.location(transform));
+                if ui
+                    .add_enabled(player_pos.is_some(), egui::Button::new("At player location"))
+                    .clicked()
+                    && let Some(pos) = player_pos
+                {
+                    let z = land_cell_at(&map_planes_r, map_id, pos.x as u32, pos.y as u32)
+                        .map_or(pos.z, |cell| cell.z);
+                    annotations_r.add(window.annotation_at(pos.x, pos.y, z));
+                }
+                ui.add(
+                    egui::TextEdit::singleline(&mut window.coords)
+                        .hint_text("x, y[, z]")
+                        .desired_width(100.0),
+                );
+                if ui.button("At").clicked() {
+                    match parse_go_to_coords(
+                        &window.coords,
+                        map_id,
+                        &world_geo_data_r,
+                        &uo_interface_settings_r.0.map_defs,
+                    ) {
+                        Ok(request) if request.map_id != map_id => {
+                            window.error = Some("Annotations are placed on the map plane shown.".to_owned());
+                        }
+                        Ok(request) => {
+                            let z = request.z.unwrap_or_else(|| {
+                                land_cell_at(&map_planes_r, map_id, request.x as u32, request.y as u32)
+                                    .map_or(0, |cell| cell.z)
+                            });
+                            annotations_r.add(window.annotation_at(request.x, request.y, z));
+                            window.error = None;
+                        }
+                        Err(e) => window.error = Some(e),
+                    }
+                }
+            });
+            if let Some(error) = &window.error {
+                ui.colored_label(egui::Color32::from_rgb(230, 70, 70), error);
+            }
+            ui.separator();
+
+            // ---------------------- Annotations -----------------------
+            ui.horizontal(|ui| {
+                ui.label(format!("{} annotations on map {map_id}", annotations_r.list.len()));
+                ui.checkbox(&mut annotations_r.visible, "Shown");
+            });
+            if annotations_r.list.is_empty() {
+                return;
+            }
+            let mut jump_to: Option<usize> = None;
+            let mut remove: Option<usize> = None;
+            egui::ScrollArea::vertical().max_height(260.0).show(ui, |ui| {
+                egui::Grid::new("annotations_grid")
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (i, annotation) in annotations_r.list.iter().enumerate() {
+                            let [r, g, b] = annotation.color;
+                            // The first line of the notes.
+                            let text = annotation.text.lines().next().unwrap_or_default();
+                            ui.colored_label(
+                                egui::Color32::from_rgb(r, g, b),
+                                format!("{}: {text}", annotation.kind.as_ref()),
+                            )
+                            .on_hover_text(&annotation.text);
+                            ui.label(format!("{}, {}, {}", annotation.x, annotation.y, annotation.z));
+                            if ui.button("Go").clicked() {
+                                jump_to = Some(i);
+                            }
+                            if ui.button("Remove").clicked() {
+                                remove = Some(i);
+                            }
+                            ui.end_row();
+                        }
+                    });
+            });
+            if let Some(i) = jump_to {
+                teleport_writer.write(annotations_r.list[i].teleport_event(map_id));
+            }
+            if let Some(i) = remove {
+                annotations_r.remove(i);
+            }
+        });
+}
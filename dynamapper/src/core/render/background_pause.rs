@@ -0,0 +1,72 @@
+// Background pause
+// - With frame_rate.pause_in_background, the world stops while the window isn't focused, instead of only updating less
+//   often (see frame_rate_ui): the virtual time (Time in the Update systems) is paused, so the walking, the animated
+//   terrain and the day/night cycle stop where they are, and the chunk streaming systems don't run.
+// - On focus gain, the virtual time goes on from where it stopped (no jump), and the visible chunks are recomputed, in
+//   case the window changed meanwhile.
+// - Not in the render test mode, whose window is hidden.
+//
+
+use crate::core::render::render_test::RenderTestArgs;
+use crate::core::render::scene::RecomputeVisibleChunksEvent;
+use crate::core::system_sets::SceneRenderLandSysSet;
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+#[derive(Resource, Default)]
+pub struct WorldPause {
+    pub paused: bool,
+}
+
+/// Run condition of the systems stopped by the background pause.
+pub fn world_running(pause_r: Res<WorldPause>) -> bool {
+    !pause_r.paused
+}
+
+pub struct BackgroundPausePlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(BackgroundPausePlugin);
+
+impl Plugin for BackgroundPausePlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<WorldPause>()
+            .configure_sets(
+                Update,
+                (
+                    SceneRenderLandSysSet::SyncLandChunks,
+                    SceneRenderLandSysSet::RenderLandChunks,
+                    SceneRenderLandSysSet::RenderStatics,
+                )
+                    .run_if(world_running),
+            )
+            .add_systems(First, sys_pause_world_on_focus_change);
+    }
+}
+
+fn sys_pause_world_on_focus_change(
+    settings_r: Res<Settings>,
+    render_test_args_r: Option<Res<RenderTestArgs>>,
+    windows_q: Query<&Window, With<PrimaryWindow>>,
+    mut pause_r: ResMut<WorldPause>,
+    mut virtual_time_r: ResMut<Time<Virtual>>,
+    mut recompute_writer: EventWriter<RecomputeVisibleChunksEvent>,
+) {
+    let focused = windows_q.single().ok().is_none_or(|window| window.focused);
+    let pause = settings_r.frame_rate.pause_in_background && !focused && render_test_args_r.is_none();
+    if pause == pause_r.paused {
+        return;
+    }
+    pause_r.paused = pause;
+    let msg = if pause {
+        virtual_time_r.pause();
+        "Window not focused: world paused."
+    } else {
+        virtual_time_r.unpause();
+        recompute_writer.write(RecomputeVisibleChunksEvent);
+        "World resumed."
+    };
+    logger::one(None, LogSev::Info, LogAbout::General, msg);
+}
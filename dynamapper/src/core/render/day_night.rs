@@ -0,0 +1,160 @@
+// Day/night cycle
+// - WorldClock keeps the in-game time of day, advancing at a configurable day length.
+// - While the cycle is enabled, the terrain lighting (UniformState) is blended between the presets of the current
+//   shading mode: night -> morning -> afternoon -> night. The cave preset isn't part of the cycle.
+// - The clock can be paused and scrubbed from the terrain shader UI.
+//
+
+use crate::{
+    core::{
+        render::scene::world::land::mesh_material::{
+            LandMaterialUniformsPresets, LandRenderStylePresetsPerMode, LandShaderModePresets,
+        },
+        render::terrain_shader_ui::push_uniforms_if_dirty,
+        system_sets::StartupSysSet,
+    },
+    external_data::shader_presets::UniformState,
+    prelude::*,
+};
+use bevy::prelude::*;
+
+pub const HOURS_PER_DAY: f32 = 24.0;
+
+#[derive(Clone, Copy, Debug)]
+enum DayPhase {
+    Night,
+    Morning,
+    Afternoon,
+}
+impl DayPhase {
+    fn preset(self, presets: &LandRenderStylePresetsPerMode) -> &LandMaterialUniformsPresets {
+        match self {
+            DayPhase::Night => &presets.night,
+            DayPhase::Morning => &presets.morning,
+            DayPhase::Afternoon => &presets.afternoon,
+        }
+    }
+}
+
+/// (hour, phase): the lighting matches the phase preset at that hour, and blends towards the next keyframe.
+/// Must start at hour 0 and end at HOURS_PER_DAY.
+#[rustfmt::skip]
+const DAY_KEYFRAMES: [(f32, DayPhase); 7] = [
+    (0.0,  DayPhase::Night),
+    (5.0,  DayPhase::Night),
+    (8.0,  DayPhase::Morning),
+    (12.0, DayPhase::Afternoon),
+    (17.0, DayPhase::Afternoon),
+    (21.0, DayPhase::Night),
+    (24.0, DayPhase::Night),
+];
+
+/// In-game time of day.
+#[derive(Resource, Debug)]
+pub struct WorldClock {
+    /// Drive the terrain lighting with the clock.
+    pub enabled: bool,
+    pub paused: bool,
+    /// Hour of the day, in [0, HOURS_PER_DAY).
+    pub hour: f32,
+    /// Real seconds for a full in-game day.
+    pub day_length_secs: f32,
+}
+impl WorldClock {
+    pub fn set_hour(&mut self, hour: f32) {
+        self.hour = hour.rem_euclid(HOURS_PER_DAY);
+    }
+
+    pub fn advance(&mut self, delta_secs: f32) {
+        let day_length_secs = self.day_length_secs.max(f32::EPSILON);
+        self.set_hour(self.hour + delta_secs * HOURS_PER_DAY / day_length_secs);
+    }
+
+    /// Time of day as "hh:mm".
+    pub fn hhmm(&self) -> String {
+        let minutes = (self.hour * 60.0) as u32;
+        format!("{:02}:{:02}", minutes / 60, minutes % 60)
+    }
+}
+
+/// Lighting for the given hour, blended between the presets of a shading mode.
+pub fn lighting_at_hour(presets: &LandRenderStylePresetsPerMode, hour: f32) -> LandMaterialUniformsPresets {
+    let hour = hour.rem_euclid(HOURS_PER_DAY);
+    let segment = DAY_KEYFRAMES
+        .windows(2)
+        .find(|pair| hour < pair[1].0)
+        .unwrap_or(&DAY_KEYFRAMES[DAY_KEYFRAMES.len() - 2..]);
+    let ((h0, phase0), (h1, phase1)) = (segment[0], segment[1]);
+    let t = ((hour - h0) / (h1 - h0)).clamp(0.0, 1.0);
+    // Ease in and out, so that the phases don't change abruptly at the keyframes.
+    let t = t * t * (3.0 - 2.0 * t);
+
+    let (a, b) = (phase0.preset(presets), phase1.preset(presets));
+    LandMaterialUniformsPresets {
+        effects: a.effects.lerp(&b.effects, t),
+        lighting: a.lighting.lerp(&b.lighting, t),
+    }
+}
+
+pub struct DayNightPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(DayNightPlugin);
+
+impl Plugin for DayNightPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.add_systems(Startup, sys_setup_world_clock).add_systems(
+            Update,
+            (sys_advance_world_clock, sys_apply_day_night_lighting)
+                .chain()
+                .before(push_uniforms_if_dirty)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn sys_setup_world_clock(mut commands: Commands, settings_r: Res<Settings>) {
+    log_system_add_startup::<DayNightPlugin>(StartupSysSet::First, fname!());
+    let mut clock = WorldClock {
+        enabled: settings_r.day_night.enabled,
+        paused: false,
+        hour: 0.0,
+        day_length_secs: settings_r.day_night.day_length_secs,
+    };
+    clock.set_hour(settings_r.day_night.start_hour);
+    commands.insert_resource(clock);
+}
+
+fn sys_advance_world_clock(time_r: Res<Time>, mut clock_r: ResMut<WorldClock>) {
+    // Don't touch the resource if the clock is stopped: a change triggers a lighting update.
+    if !clock_r.enabled || clock_r.paused {
+        return;
+    }
+    clock_r.advance(time_r.delta_secs());
+}
+
+fn sys_apply_day_night_lighting(
+    clock_r: Res<WorldClock>,
+    shader_presets_r: Res<LandShaderModePresets>,
+    mut uniform_state_r: ResMut<UniformState>,
+    mut last_shading_mode: Local<Option<u32>>,
+) {
+    if !clock_r.enabled {
+        *last_shading_mode = None;
+        return;
+    }
+    let shading_mode = uniform_state_r.effects.shading_mode;
+    if !clock_r.is_changed() && *last_shading_mode == Some(shading_mode) {
+        return;
+    }
+    *last_shading_mode = Some(shading_mode);
+
+    let blended = lighting_at_hour(shader_presets_r.for_mode(shading_mode), clock_r.hour);
+    let u = uniform_state_r.as_mut();
+    u.effects = blended.effects.with_overlay_of(&u.effects);
+    u.effects.shading_mode = shading_mode;
+    u.lighting = blended.lighting;
+    u.global_lighting = 1.0;
+    u.dirty = true;
+}
@@ -0,0 +1,376 @@
+// Diagnostics overlay (egui window)
+// - Toggled with InputAction::ToggleDiagnostics (F3 by default).
+// - Shows FPS and a frame time graph (from FrameTimeDiagnosticsPlugin), the land chunks on screen, the occupancy of
+//   each land texture array and the land texture cache counters, the map blocks cached by each loaded map plane and
+//   the land chunk build timings (MeshBuildPerfHistory).
+// - Shows the land rendering costs: the land draw calls (estimated from the visible chunks) and texture uploads of the
+//   last frame, and the CPU/GPU time of the opaque 3d pass, where the land chunks are drawn. The pass timings come from
+//   Bevy's RenderDiagnosticsPlugin, added only with debug.gpu_timings in settings.toml. GPU timestamps need Vulkan or
+//   DX12: elsewhere only the CPU time is shown.
+// - Shows the land uniform pushes (land::shared_uniforms): the UniformState changes written to the shared uniforms,
+//   and the ones that didn't change anything; and the writes of the shared uniform buffers, in the last frame and
+//   since startup.
+// - Enables the seam check of the land chunks (land::seam_check) and shows its result.
+// - In builds with the profiling feature, toggles the land profiling spans (util_lib::profiling).
+//
+
+use crate::{
+    core::{
+        controls::key_bindings::{ActionInput, InputAction},
+        render::scene::world::land::{
+            LCMesh,
+            draw_mesh::MeshBuildPerfHistory,
+            mesh_material::{LandBatchedMaterial, LandCustomMaterial},
+            seam_check::SeamCheck,
+            shared_uniforms::{LandUniformSyncStats, sys_count_land_shared_uniform_writes},
+        },
+        texture_cache::land::cache::{LandTextureArrayWrapper, LandTextureCache},
+        uo_files_loader::MapPlanesRes,
+    },
+    prelude::*,
+};
+use bevy::diagnostic::{
+    Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsStore, FrameTimeDiagnosticsPlugin, RegisterDiagnostic,
+};
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use std::collections::HashSet;
+
+const FRAME_TIME_GRAPH_SIZE: [f32; 2] = [240.0, 60.0];
+/// Lowest top of the frame time graph scale, so that small variations of a steady frame rate don't look like spikes.
+const FRAME_TIME_GRAPH_MIN_SCALE_MS: f64 = 1000.0 / 30.0;
+
+/// Land draw calls of the last frame, estimated by sys_measure_land_rendering.
+pub const LAND_DRAW_CALLS: DiagnosticPath = DiagnosticPath::const_new("land/draw_calls");
+/// Land textures uploaded to the texture arrays in the last frame.
+pub const LAND_TEXTURE_UPLOADS: DiagnosticPath = DiagnosticPath::const_new("land/texture_uploads");
+/// Shared land uniform buffers written in the last frame (see land::shared_uniforms).
+pub const LAND_UNIFORM_WRITES: DiagnosticPath = DiagnosticPath::const_new("land/uniform_writes");
+/// Timings of the opaque 3d pass, recorded by RenderDiagnosticsPlugin (for the last view drawn, if more than one).
+const OPAQUE_PASS_GPU_TIME: DiagnosticPath = DiagnosticPath::const_new("render/main_opaque_pass_3d/elapsed_gpu");
+const OPAQUE_PASS_CPU_TIME: DiagnosticPath = DiagnosticPath::const_new("render/main_opaque_pass_3d/elapsed_cpu");
+
+#[derive(Resource, Default)]
+pub struct DiagnosticsOverlay {
+    pub visible: bool,
+}
+
+pub struct DiagnosticsUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(DiagnosticsUiPlugin);
+
+impl Plugin for DiagnosticsUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        if !app.is_plugin_added::<FrameTimeDiagnosticsPlugin>() {
+            app.add_plugins(FrameTimeDiagnosticsPlugin::default());
+        }
+        app.init_resource::<DiagnosticsOverlay>()
+            .register_diagnostic(Diagnostic::new(LAND_DRAW_CALLS))
+            .register_diagnostic(Diagnostic::new(LAND_TEXTURE_UPLOADS))
+            .register_diagnostic(Diagnostic::new(LAND_UNIFORM_WRITES))
+            .add_systems(
+                Update,
+                sys_toggle_diagnostics_overlay.run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                PostUpdate,
+                sys_measure_land_rendering
+                    .after(sys_count_land_shared_uniform_writes)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                diagnostics_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_toggle_diagnostics_overlay(action_input: ActionInput, mut overlay_r: ResMut<DiagnosticsOverlay>) {
+    if action_input.just_pressed(InputAction::ToggleDiagnostics) {
+        overlay_r.visible = !overlay_r.visible;
+    }
+}
+
+/// Measures the land draw calls, texture uploads and uniform buffer writes of the frame.
+/// Draw calls aren't counted by Bevy: they're estimated from the visible chunks. A chunk with its own material is a
+///  draw call, while the batched chunks (see land::batch) sharing a mesh (lod step) and the views drawing them
+///  (render layers) are instanced in a single one.
+fn sys_measure_land_rendering(
+    mut diagnostics: Diagnostics,
+    mut uploads_before: Local<Option<u64>>,
+    mut uniform_writes_before: Local<Option<u64>>,
+    land_texture_cache_r: Res<LandTextureCache>,
+    uniform_sync_stats_r: Res<LandUniformSyncStats>,
+    own_material_chunk_q: Query<&ViewVisibility, (With<LCMesh>, With<MeshMaterial3d<LandCustomMaterial>>)>,
+    batched_chunk_q: Query<
+        (&ViewVisibility, &Mesh3d, Option<&RenderLayers>),
+        (With<LCMesh>, With<MeshMaterial3d<LandBatchedMaterial>>),
+    >,
+) {
+    let own_material_draws = own_material_chunk_q.iter().filter(|visibility| visibility.get()).count();
+    let batched_draws = batched_chunk_q
+        .iter()
+        .filter(|(visibility, _, _)| visibility.get())
+        .map(|(_, mesh, render_layers)| (mesh.0.id(), render_layers.map(|layers| layers.bits().to_vec())))
+        .collect::<HashSet<_>>()
+        .len();
+    diagnostics.add_measurement(&LAND_DRAW_CALLS, || (own_material_draws + batched_draws) as f64);
+
+    let uploads = land_texture_cache_r.stats.uploads;
+    let uploads_in_frame = uploads.saturating_sub(uploads_before.unwrap_or(uploads));
+    *uploads_before = Some(uploads);
+    diagnostics.add_measurement(&LAND_TEXTURE_UPLOADS, || uploads_in_frame as f64);
+
+    let uniform_writes = uniform_sync_stats_r.buffer_writes;
+    let uniform_writes_in_frame = uniform_writes.saturating_sub(uniform_writes_before.unwrap_or(uniform_writes));
+    *uniform_writes_before = Some(uniform_writes);
+    diagnostics.add_measurement(&LAND_UNIFORM_WRITES, || uniform_writes_in_frame as f64);
+}
+
+fn diagnostics_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut overlay_r: ResMut<DiagnosticsOverlay>,
+    diagnostics_r: Res<DiagnosticsStore>,
+    chunk_q: Query<&ViewVisibility, With<LCMesh>>,
+    land_texture_cache_r: Res<LandTextureCache>,
+    map_planes_r: Res<MapPlanesRes>,
+    perf_history_r: Res<MeshBuildPerfHistory>,
+    uniform_sync_stats_r: Res<LandUniformSyncStats>,
+    mut seam_check_r: ResMut<SeamCheck>,
+    settings_r: Res<Settings>,
+) {
+    if !overlay_r.visible {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    egui::Window::new("Diagnostics")
+        .anchor(egui::Align2::RIGHT_TOP, [-10.0, 10.0])
+        .resizable(false)
+        .open(&mut overlay_r.visible)
+        .show(ctx, |ui| {
+            // ------------------------ Frames --------------------------
+            let fps = diagnostics_r
+                .get(&FrameTimeDiagnosticsPlugin::FPS)
+                .and_then(|fps| fps.smoothed());
+            let frame_time = diagnostics_r.get(&FrameTimeDiagnosticsPlugin::FRAME_TIME);
+            ui.label(format!(
+                "FPS: {}    Frame time: {}",
+                fps.map_or("-".to_owned(), |fps| format!("{fps:.0}")),
+                frame_time
+                    .and_then(|frame_time| frame_time.smoothed())
+                    .map_or("-".to_owned(), |ms| format!("{ms:.2} ms")),
+            ));
+            let frame_times: Vec<f64> = frame_time
+                .map(|frame_time| frame_time.values().copied().collect())
+                .unwrap_or_default();
+            draw_frame_time_graph(ui, &frame_times);
+            ui.separator();
+
+            // ------------------------ Scene ---------------------------
+            let chunks_total = chunk_q.iter().count();
+            let chunks_on_screen = chunk_q.iter().filter(|visibility| visibility.get()).count();
+            ui.label(format!("Land chunks on screen: {chunks_on_screen} (spawned: {chunks_total})"));
+            ui.separator();
+
+            // ---------------------- Land rendering --------------------
+            egui::Grid::new("diagnostics_land_rendering_grid").num_columns(2).show(ui, |ui| {
+                let last_and_average = |path: &DiagnosticPath| {
+                    diagnostics_r.get(path).map_or("-".to_owned(), |diagnostic| {
+                        format!(
+                            "{:.0} (avg {:.1})",
+                            diagnostic.value().unwrap_or_default(),
+                            diagnostic.average().unwrap_or_default()
+                        )
+                    })
+                };
+                ui.label("Land draw calls (estimated)");
+                ui.label(last_and_average(&LAND_DRAW_CALLS));
+                ui.end_row();
+                ui.label("Land texture uploads / frame");
+                ui.label(last_and_average(&LAND_TEXTURE_UPLOADS));
+                ui.end_row();
+                let uniform_stats = &uniform_sync_stats_r;
+                ui.label("Land uniform pushes");
+                ui.label(format!(
+                    "{} (unchanged: {}), last: {}",
+                    uniform_stats.pushes,
+                    uniform_stats.unchanged_pushes,
+                    uniform_stats.last_push_fields.label()
+                ));
+                ui.end_row();
+                ui.label("Land uniform writes / frame");
+                ui.label(last_and_average(&LAND_UNIFORM_WRITES));
+                ui.end_row();
+                ui.label("Land uniform writes total");
+                ui.label(uniform_stats.buffer_writes.to_string());
+                ui.end_row();
+                ui.label("Opaque pass (land, statics)");
+                let pass_time = |path: &DiagnosticPath| {
+                    diagnostics_r
+                        .get(path)
+                        .and_then(|diagnostic| diagnostic.smoothed())
+                        .map(|ms| format!("{ms:.2} ms"))
+                };
+                ui.label(
+                    match (pass_time(&OPAQUE_PASS_GPU_TIME), pass_time(&OPAQUE_PASS_CPU_TIME)) {
+                        (Some(gpu), Some(cpu)) => format!("GPU {gpu}, CPU {cpu}"),
+                        (None, Some(cpu)) => format!("CPU {cpu} (no GPU timestamps)"),
+                        (_, None) if settings_r.debug.gpu_timings => "Waiting for timings...".to_owned(),
+                        (_, None) => "Off (debug.gpu_timings)".to_owned(),
+                    },
+                );
+                ui.end_row();
+            });
+            ui.separator();
+
+            // ------------------------ Caches --------------------------
+            egui::Grid::new("diagnostics_caches_grid").num_columns(2).show(ui, |ui| {
+                for (label, texture_array) in [
+                    ("Small land textures", &land_texture_cache_r.small),
+                    ("Big land textures", &land_texture_cache_r.big),
+                ] {
+                    texture_array_occupancy_row(ui, label, texture_array);
+                }
+                let stats = &land_texture_cache_r.stats;
+                ui.label("Land textures in use");
+                ui.label(match settings_r.render.land_texture_budget_mb {
+                    0 => format!("{:.1} MB (no budget)", bytes_to_mb(land_texture_cache_r.used_bytes())),
+                    budget_mb => format!("{:.1} / {budget_mb} MB", bytes_to_mb(land_texture_cache_r.used_bytes())),
+                });
+                ui.end_row();
+                ui.label("Land texture hits / uploads");
+                ui.label(format!("{} / {}", stats.hits, stats.uploads));
+                ui.end_row();
+                ui.label("Land texture evictions");
+                ui.label(format!(
+                    "{} array full, {} over budget",
+                    stats.evicted_array_full, stats.evicted_over_budget
+                ));
+                ui.end_row();
+                let mut map_planes: Vec<_> = map_planes_r.0.iter().map(|entry| entry.value().clone()).collect();
+                map_planes.sort_by_key(|map_plane| map_plane.index());
+                for map_plane in map_planes {
+                    let map_plane_data = map_plane.read();
+                    ui.label(format!("Map {} blocks", map_plane.index()));
+                    ui.label(match map_plane_data.max_cached_blocks() {
+                        Some(max) => format!("{} / {max}", map_plane_data.cached_block_count()),
+                        None => format!("{}", map_plane_data.cached_block_count()),
+                    });
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+
+            // --------------------- Chunk building ---------------------
+            match perf_history_r.last() {
+                Some(last) => {
+                    let (runs, chunks, total_us, max_us) = perf_history_r.samples().fold(
+                        (0u64, 0u64, 0u64, 0u64),
+                        |(runs, chunks, total_us, max_us), sample| {
+                            (
+                                runs + 1,
+                                chunks + sample.chunks as u64,
+                                total_us + sample.build_time_us,
+                                max_us.max(sample.build_time_us),
+                            )
+                        },
+                    );
+                    ui.label(format!(
+                        "Last chunk build: {} chunks in {} µs",
+                        last.chunks, last.build_time_us
+                    ));
+                    ui.label(format!(
+                        "Last {runs} builds: avg {} µs ({:.0} µs/chunk), max {max_us} µs",
+                        total_us / runs,
+                        total_us as f64 / chunks.max(1) as f64,
+                    ));
+                }
+                None => {
+                    ui.label("No land chunks built yet.");
+                }
+            }
+            ui.separator();
+
+            // ------------------------ Seams ---------------------------
+            let seam_check = seam_check_r.as_mut();
+            if ui
+                .checkbox(&mut seam_check.enabled, "Check land chunk seams")
+                .on_hover_text("Compares the edge vertices of adjacent chunks; mismatches are logged and marked.")
+                .changed()
+                && !seam_check.enabled
+            {
+                seam_check.report = None;
+            }
+            if let Some(report) = &seam_check.report {
+                ui.label(format!(
+                    "{} mismatching vertices on {} chunk edges",
+                    report.mismatches.len(),
+                    report.checked_edges
+                ));
+            }
+
+            // ----------------------- Profiling ------------------------
+            #[cfg(feature = "profiling")]
+            {
+                use crate::util_lib::profiling;
+                ui.separator();
+                let mut spans_enabled = profiling::spans_enabled();
+                if ui
+                    .checkbox(&mut spans_enabled, "Record land profiling spans")
+                    .on_hover_text("Chunk building, block loading, texture cache and uniform spans, for Tracy.")
+                    .changed()
+                {
+                    profiling::set_spans_enabled(spans_enabled);
+                }
+            }
+        });
+}
+
+fn bytes_to_mb(bytes: u64) -> f64 {
+    bytes as f64 / (1024.0 * 1024.0)
+}
+
+fn texture_array_occupancy_row(ui: &mut egui::Ui, label: &str, texture_array: &LandTextureArrayWrapper) {
+    let used = texture_array.used_layers();
+    let max = texture_array.max_layers();
+    ui.label(label);
+    ui.add(
+        egui::ProgressBar::new(used as f32 / max.max(1) as f32)
+            .text(format!("{used} / {max} layers"))
+            .desired_width(FRAME_TIME_GRAPH_SIZE[0] / 2.0),
+    );
+    ui.end_row();
+}
+
+/// Frame times (ms) as a line, oldest on the left.
+fn draw_frame_time_graph(ui: &mut egui::Ui, frame_times: &[f64]) {
+    let (rect, _) = ui.allocate_exact_size(FRAME_TIME_GRAPH_SIZE.into(), egui::Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, egui::Color32::from_black_alpha(140));
+    if frame_times.len() < 2 {
+        return;
+    }
+    let max_ms = frame_times.iter().copied().fold(FRAME_TIME_GRAPH_MIN_SCALE_MS, f64::max);
+    let step_x = rect.width() / (frame_times.len() - 1) as f32;
+    let points: Vec<egui::Pos2> = frame_times
+        .iter()
+        .enumerate()
+        .map(|(i, ms)| {
+            egui::pos2(
+                rect.left() + i as f32 * step_x,
+                rect.bottom() - (ms / max_ms) as f32 * rect.height(),
+            )
+        })
+        .collect();
+    painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, egui::Color32::LIGHT_GREEN)));
+    painter.text(
+        rect.left_top() + egui::vec2(4.0, 2.0),
+        egui::Align2::LEFT_TOP,
+        format!("{max_ms:.1} ms"),
+        egui::FontId::monospace(10.0),
+        egui::Color32::GRAY,
+    );
+}
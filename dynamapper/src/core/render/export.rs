@@ -0,0 +1,471 @@
+pub mod mesh;
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::render::scene::{
+    SceneStateData,
+    player::Player,
+    world::{WorldGeoData, land::{LCMesh, LandChunkSize}},
+};
+use crate::core::system_sets::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::render::{
+    camera::{RenderTarget, ScalingMode},
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    view::{
+        RenderLayers,
+        screenshot::{Screenshot, ScreenshotCaptured},
+    },
+};
+use bevy::tasks::IoTaskPool;
+use uocf::geo::map::MapRectCells;
+
+// Renders a map region off-screen, top-down, one pixel per tile, through the same land chunks (and shader) used by
+//  the scene, then saves it to a PNG file.
+// Big regions are split in pages: for each page we spawn its chunks and a camera rendering to an image, wait for the
+//  chunks to be built, capture the image and copy it into the final one. The statics of the chunks are drawn on
+//  their render layer too (see draw_statics).
+// Any rectangle can be exported from the Map export window (export_ui); InputAction::ExportAroundPlayer exports the
+//  square around the player right away.
+
+/// Side of the square region exported around the player with InputAction::ExportAroundPlayer.
+pub const EXPORT_AROUND_PLAYER_SIZE_TILES: u32 = 512;
+const EXPORT_FOLDER: &str = "exports";
+
+/// Side of a page, in tiles (a multiple of every allowed chunk size).
+const EXPORT_PAGE_SIZE_TILES: u32 = 256;
+/// Frames to wait after every chunk of the page got its mesh, before capturing it: the render pipelines for the
+///  export camera are compiled asynchronously, and the textures need to reach the GPU.
+const EXPORT_SETTLE_FRAMES: u32 = 10;
+/// Export chunks and camera live on their own render layer, so that the player camera doesn't draw them (and the
+///  export camera doesn't draw the scene).
+const EXPORT_RENDER_LAYER: usize = 1;
+const EXPORT_CAMERA_ORDER: isize = -1;
+const EXPORT_CAMERA_HEIGHT: f32 = 1000.0;
+
+/// Request to export a map region (in tiles, x1 and y1 excluded) to a PNG file, one pixel per tile.
+#[derive(Event, Debug, Clone)]
+pub struct ExportMapRegionEvent {
+    pub map_id: u32,
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+    pub file_path: PathBuf,
+}
+
+/// Tag component: land chunks spawned for an export, ignored by the scene chunk management.
+#[derive(Component)]
+pub struct MapExportChunk;
+
+#[derive(Component)]
+struct MapExportCamera;
+
+struct MapExportPage {
+    rect: MapRectCells,
+    frames_ready: u32,
+    screenshot_requested: bool,
+    render_target: Handle<Image>,
+}
+
+struct MapExportTask {
+    request: ExportMapRegionEvent,
+    // Region origin, to place the pages in the output image.
+    x0: u32,
+    y0: u32,
+    pages: VecDeque<MapRectCells>,
+    pages_total: usize,
+    current_page: Option<MapExportPage>,
+    output: image::RgbaImage,
+}
+
+#[derive(Resource, Default)]
+pub struct MapExportJob {
+    task: Option<MapExportTask>,
+    // Filled by the screenshot observer.
+    captured_page: Option<Image>,
+}
+impl MapExportJob {
+    pub fn is_running(&self) -> bool {
+        self.task.is_some()
+    }
+
+    /// Pages done and pages in all, while an export is running.
+    pub fn progress(&self) -> Option<(usize, usize)> {
+        self.task.as_ref().map(|task| {
+            (
+                task.pages_total - task.pages.len() - usize::from(task.current_page.is_some()),
+                task.pages_total,
+            )
+        })
+    }
+}
+
+/// Request to export the rectangle (x1 and y1 excluded) of a map plane to exports/, named after them.
+pub fn export_region_event(map_id: u32, x0: u32, y0: u32, x1: u32, y1: u32) -> ExportMapRegionEvent {
+    ExportMapRegionEvent {
+        map_id,
+        x0,
+        y0,
+        x1,
+        y1,
+        file_path: PathBuf::from(EXPORT_FOLDER).join(format!("map{map_id}_{x0}_{y0}_{x1}_{y1}.png")),
+    }
+}
+
+/// The square of EXPORT_AROUND_PLAYER_SIZE_TILES centered on the tile, as (x0, y0, x1, y1).
+pub fn region_around(x: u32, y: u32) -> (u32, u32, u32, u32) {
+    let half_size = EXPORT_AROUND_PLAYER_SIZE_TILES / 2;
+    let x0 = x.saturating_sub(half_size);
+    let y0 = y.saturating_sub(half_size);
+    (
+        x0,
+        y0,
+        x0 + EXPORT_AROUND_PLAYER_SIZE_TILES,
+        y0 + EXPORT_AROUND_PLAYER_SIZE_TILES,
+    )
+}
+
+pub struct MapExportPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(MapExportPlugin);
+
+impl Plugin for MapExportPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<MapExportJob>()
+            .add_event::<ExportMapRegionEvent>()
+            .add_systems(
+                Update,
+                (
+                    sys_map_export_input,
+                    sys_start_map_export,
+                    sys_advance_map_export.before(SceneRenderLandSysSet::RenderLandChunks),
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// Exports the region around the player.
+fn sys_map_export_input(
+    action_input: ActionInput,
+    player_q: Query<&Player>,
+    mut writer: EventWriter<ExportMapRegionEvent>,
+) {
+    if !action_input.just_pressed(InputAction::ExportAroundPlayer) {
+        return;
+    }
+    let Some(player_pos) = player_q.single().ok().and_then(|player| player.current_pos) else {
+        return;
+    };
+    let (x0, y0, x1, y1) = region_around(player_pos.x as u32, player_pos.y as u32);
+    writer.write(export_region_event(player_pos.m as u32, x0, y0, x1, y1));
+}
+
+/// Validates the request and splits the region in pages.
+fn sys_start_map_export(
+    mut events: EventReader<ExportMapRegionEvent>,
+    mut job_r: ResMut<MapExportJob>,
+    world_geo_data_r: Res<WorldGeoData>,
+    scene_state_data_r: Res<SceneStateData>,
+) {
+    let log_err = |msg: &str| logger::one(None, LogSev::Error, LogAbout::Renderer, msg);
+    for request in events.read() {
+        if job_r.is_running() {
+            log_err("Map export already in progress: request ignored.");
+            continue;
+        }
+        // Land chunks are built only for the map plane shown by the scene.
+        if request.map_id != scene_state_data_r.map_id {
+            log_err(&format!(
+                "Can't export map plane {}: only the current map plane ({}) can be exported.",
+                request.map_id, scene_state_data_r.map_id
+            ));
+            continue;
+        }
+        let Some(metadata) = world_geo_data_r.maps.get(&request.map_id) else {
+            log_err(&format!("Can't export uncached map plane {}.", request.map_id));
+            continue;
+        };
+        let x1 = request.x1.min(metadata.width);
+        let y1 = request.y1.min(metadata.height);
+        if request.x0 >= x1 || request.y0 >= y1 {
+            log_err(&format!("Can't export an empty map region: {request:?}."));
+            continue;
+        }
+
+        let mut pages = VecDeque::new();
+        for page_y0 in (request.y0..y1).step_by(EXPORT_PAGE_SIZE_TILES as usize) {
+            for page_x0 in (request.x0..x1).step_by(EXPORT_PAGE_SIZE_TILES as usize) {
+                pages.push_back(MapRectCells {
+                    x0: page_x0,
+                    y0: page_y0,
+                    width: EXPORT_PAGE_SIZE_TILES.min(x1 - page_x0),
+                    height: EXPORT_PAGE_SIZE_TILES.min(y1 - page_y0),
+                });
+            }
+        }
+
+        logger::one(
+            None,
+            LogSev::Info,
+            LogAbout::Renderer,
+            &format!(
+                "Exporting map {} region ({},{})-({x1},{y1}) in {} pages to {:?}.",
+                request.map_id,
+                request.x0,
+                request.y0,
+                pages.len(),
+                request.file_path
+            ),
+        );
+        job_r.captured_page = None;
+        job_r.task = Some(MapExportTask {
+            request: request.clone(),
+            x0: request.x0,
+            y0: request.y0,
+            pages_total: pages.len(),
+            pages,
+            current_page: None,
+            output: image::RgbaImage::new(x1 - request.x0, y1 - request.y0),
+        });
+    }
+}
+
+fn spawn_export_page(
+    commands: &mut Commands,
+    images_r: &mut Assets<Image>,
+    chunk_size: LandChunkSize,
+    map_id: u32,
+    rect: MapRectCells,
+) -> MapExportPage {
+    let size = Extent3d {
+        width: rect.width,
+        height: rect.height,
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::RENDER_ATTACHMENT;
+    let render_target = images_r.add(image);
+
+    // Top-down orthographic camera, covering exactly the page: map north (lower y, so lower z) is up.
+    let center = Vec3::new(
+        rect.x0 as f32 + rect.width as f32 / 2.0,
+        0.0,
+        rect.y0 as f32 + rect.height as f32 / 2.0,
+    );
+    commands.spawn((
+        MapExportCamera,
+        Camera3d::default(),
+        Camera {
+            order: EXPORT_CAMERA_ORDER,
+            target: RenderTarget::Image(render_target.clone().into()),
+            clear_color: ClearColorConfig::Custom(Color::BLACK),
+            ..default()
+        },
+        Projection::Orthographic(OrthographicProjection {
+            scaling_mode: ScalingMode::Fixed {
+                width: rect.width as f32,
+                height: rect.height as f32,
+            },
+            near: -10000.0,
+            far: 10000.0,
+            ..OrthographicProjection::default_3d()
+        }),
+        Transform::from_translation(center + Vec3::Y * EXPORT_CAMERA_HEIGHT)
+            .looking_at(center, Vec3::NEG_Z),
+        RenderLayers::layer(EXPORT_RENDER_LAYER),
+    ));
+
+    // The chunks are built by the land draw system, like the ones spawned by the scene.
+    let gx_range = chunk_size.chunk_of_tile(rect.x0)..=chunk_size.chunk_of_tile(rect.x0 + rect.width - 1);
+    let gy_range = chunk_size.chunk_of_tile(rect.y0)..=chunk_size.chunk_of_tile(rect.y0 + rect.height - 1);
+    for gx in gx_range {
+        for gy in gy_range.clone() {
+            commands.spawn((
+                LCMesh {
+                    parent_map_id: map_id,
+                    gx,
+                    gy,
+                },
+                MapExportChunk,
+                RenderLayers::layer(EXPORT_RENDER_LAYER),
+                Transform::default(),
+                GlobalTransform::default(),
+            ));
+        }
+    }
+
+    MapExportPage {
+        rect,
+        frames_ready: 0,
+        screenshot_requested: false,
+        render_target,
+    }
+}
+
+/// Writes the PNG in the background, so that big exports don't block the app.
+fn save_export(output: image::RgbaImage, file_path: PathBuf) {
+    IoTaskPool::get()
+        .spawn(async move {
+            if let Some(parent) = file_path.parent() {
+                if let Err(e) = std::fs::create_dir_all(parent) {
+                    logger::one(
+                        None,
+                        LogSev::Error,
+                        LogAbout::Renderer,
+                        &format!("Can't create the export folder {parent:?}: {e}"),
+                    );
+                    return;
+                }
+            }
+            match output.save_with_format(&file_path, image::ImageFormat::Png) {
+                Ok(()) => logger::one(
+                    None,
+                    LogSev::Info,
+                    LogAbout::Renderer,
+                    &format!("Map export saved to {file_path:?}."),
+                ),
+                Err(e) => logger::one(
+                    None,
+                    LogSev::Error,
+                    LogAbout::Renderer,
+                    &format!("Can't save the map export to {file_path:?}: {e}"),
+                ),
+            }
+        })
+        .detach();
+}
+
+/// Export state machine: spawns a page, waits for it to be rendered, captures it, then goes to the next one.
+fn sys_advance_map_export(
+    mut commands: Commands,
+    mut job_r: ResMut<MapExportJob>,
+    mut images_r: ResMut<Assets<Image>>,
+    scene_state_data_r: Res<SceneStateData>,
+    chunk_size_r: Res<LandChunkSize>,
+    export_chunks_q: Query<(Entity, Option<&Mesh3d>), With<MapExportChunk>>,
+    export_camera_q: Query<Entity, With<MapExportCamera>>,
+) {
+    let job = &mut *job_r;
+    let Some(task) = job.task.as_mut() else {
+        return;
+    };
+
+    // Chunks are built only for the current map plane: if the player left it, the export can't complete.
+    if task.request.map_id != scene_state_data_r.map_id {
+        logger::one(
+            None,
+            LogSev::Error,
+            LogAbout::Renderer,
+            &format!("Map export aborted: left map plane {}.", task.request.map_id),
+        );
+        if let Some(page) = &task.current_page {
+            images_r.remove(&page.render_target);
+        }
+        for (entity, _) in export_chunks_q.iter() {
+            commands.entity(entity).despawn();
+        }
+        for entity in export_camera_q.iter() {
+            commands.entity(entity).despawn();
+        }
+        job.task = None;
+        job.captured_page = None;
+        return;
+    }
+
+    let Some(page) = task.current_page.as_mut() else {
+        match task.pages.pop_front() {
+            Some(rect) => {
+                task.current_page = Some(spawn_export_page(
+                    &mut commands,
+                    &mut images_r,
+                    *chunk_size_r,
+                    task.request.map_id,
+                    rect,
+                ));
+            }
+            None => {
+                let task = job.task.take().unwrap();
+                save_export(task.output, task.request.file_path);
+            }
+        }
+        return;
+    };
+
+    // Page captured: copy it to the output image and clean up.
+    if let Some(captured) = job.captured_page.take() {
+        match captured.try_into_dynamic() {
+            Ok(page_image) => {
+                image::imageops::replace(
+                    &mut task.output,
+                    &page_image.to_rgba8(),
+                    (page.rect.x0 - task.x0) as i64,
+                    (page.rect.y0 - task.y0) as i64,
+                );
+            }
+            Err(e) => logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::Renderer,
+                &format!("Can't read the captured map export page {:?}: {e}", page.rect),
+            ),
+        }
+        for (entity, _) in export_chunks_q.iter() {
+            commands.entity(entity).despawn();
+        }
+        for entity in export_camera_q.iter() {
+            commands.entity(entity).despawn();
+        }
+        images_r.remove(&page.render_target);
+        logger::one(
+            None,
+            LogSev::Debug,
+            LogAbout::Renderer,
+            &format!(
+                "Map export: page {}/{} done.",
+                task.pages_total - task.pages.len(),
+                task.pages_total
+            ),
+        );
+        task.current_page = None;
+        return;
+    }
+
+    if page.screenshot_requested {
+        return;
+    }
+    let all_chunks_built = !export_chunks_q.is_empty()
+        && export_chunks_q.iter().all(|(_, mesh)| mesh.is_some());
+    if !all_chunks_built {
+        return;
+    }
+    page.frames_ready += 1;
+    if page.frames_ready < EXPORT_SETTLE_FRAMES {
+        return;
+    }
+    page.screenshot_requested = true;
+    commands
+        .spawn(Screenshot::image(page.render_target.clone()))
+        .observe(
+            |trigger: Trigger<ScreenshotCaptured>, mut job_r: ResMut<MapExportJob>| {
+                job_r.captured_page = Some(trigger.event().0.clone());
+            },
+        );
+}
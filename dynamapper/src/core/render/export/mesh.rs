@@ -0,0 +1,429 @@
+// Exports a map region as a 3D mesh (Wavefront OBJ or glTF), to view or post-process the terrain in Blender & co.
+// - One quad per tile, with its own 4 vertices: corner heights are the z of the tile and of its east, south and
+//   south-east neighbors, like in the scene. Axes are the scene ones: x east, y up, z south; one unit per tile, z
+//   scaled with scale_uo_z_to_bevy_units. The region origin is at (0, 0, 0).
+// - Each tile maps the full texture (UVs 0-1), and tiles are grouped by land tile id: one material per id, with the
+//   radarcol color as base color and its texmap, if it has one, saved as png in the <file name>_textures folder.
+// - OBJ: <file>.obj + <file>.mtl. glTF: <file>.gltf + <file>.bin, one primitive per material.
+// - Unlike the png export, the mesh is built from the map data only: any loaded map plane can be exported. The work
+//   is done on the IO task pool.
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use super::EXPORT_FOLDER;
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::render::scene::player::Player;
+use crate::core::uo_files_loader::{MapPlanesRes, RadarColRes, TexMap2DRes};
+use crate::prelude::*;
+use crate::util_lib::uo_coords::scale_uo_z_to_bevy_units;
+use bevy::prelude::*;
+use bevy::tasks::IoTaskPool;
+use color_eyre::eyre::{self, WrapErr, bail};
+use uocf::geo::{
+    land_texture_2d::TexMap2D,
+    map::{MapBlock, MapPlaneShared, MapRectCells},
+};
+use uocf::radarcol::RadarColors;
+
+/// Side of the square region exported around the player with InputAction::ExportMeshAroundPlayer.
+const EXPORT_MESH_AROUND_PLAYER_SIZE_TILES: u32 = 256;
+
+/// Request to export a map region (in tiles, x1 and y1 excluded) to a 3D mesh file: .obj or .gltf.
+#[derive(Event, Debug, Clone)]
+pub struct ExportMapMeshEvent {
+    pub map_id: u32,
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+    pub file_path: PathBuf,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MeshExportFormat {
+    Obj,
+    Gltf,
+}
+impl MeshExportFormat {
+    pub fn from_path(file_path: &Path) -> Option<Self> {
+        let extension = file_path.extension()?.to_string_lossy().to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => Some(Self::Obj),
+            "gltf" => Some(Self::Gltf),
+            _ => None,
+        }
+    }
+}
+
+/// Terrain of a map region: a quad per tile, with its own vertices so that every tile maps the whole texture.
+pub struct TerrainMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    /// Triangles (vertex indices) of the tiles of each land tile id.
+    pub triangles_by_tile_id: BTreeMap<u16, Vec<u32>>,
+}
+impl TerrainMesh {
+    pub fn build(map_plane: &MapPlaneShared, rect: &MapRectCells) -> eyre::Result<Self> {
+        let map_width = map_plane.size_blocks().width * MapBlock::CELLS_PER_ROW;
+        let map_height = map_plane.size_blocks().height * MapBlock::CELLS_PER_COLUMN;
+        // The tiles of the region plus a row and a column for the heights of the east and south corners, taken from
+        //  the last tile at the map edge.
+        let (grid_w, grid_h) = (rect.width + 1, rect.height + 1);
+        let mut grid = Vec::with_capacity((grid_w * grid_h) as usize);
+        for gy in 0..grid_h {
+            for gx in 0..grid_w {
+                let x = (rect.x0 + gx).min(map_width - 1);
+                let y = (rect.y0 + gy).min(map_height - 1);
+                grid.push(
+                    map_plane
+                        .cell(x, y)
+                        .wrap_err_with(|| format!("Reading the tile at {x},{y}"))?,
+                );
+            }
+        }
+        let z_at = |gx: u32, gy: u32| scale_uo_z_to_bevy_units(grid[(gy * grid_w + gx) as usize].z as f32);
+
+        let tile_count = (rect.width * rect.height) as usize;
+        let mut mesh = TerrainMesh {
+            positions: Vec::with_capacity(tile_count * 4),
+            uvs: Vec::with_capacity(tile_count * 4),
+            triangles_by_tile_id: BTreeMap::new(),
+        };
+        for ty in 0..rect.height {
+            for tx in 0..rect.width {
+                let v0 = mesh.positions.len() as u32;
+                for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                    let (gx, gy) = (tx + dx, ty + dy);
+                    mesh.positions.push([gx as f32, z_at(gx, gy), gy as f32]);
+                    mesh.uvs.push([dx as f32, dy as f32]);
+                }
+                // Same winding and diagonal as the land chunk mesh.
+                let (v1, v2, v3) = (v0 + 1, v0 + 2, v0 + 3);
+                let tile_id = grid[(ty * grid_w + tx) as usize].id;
+                mesh.triangles_by_tile_id
+                    .entry(tile_id)
+                    .or_default()
+                    .extend_from_slice(&[v0, v3, v1, v0, v2, v3]);
+            }
+        }
+        Ok(mesh)
+    }
+}
+
+/// Material of the tiles with a land tile id.
+struct MeshMaterial {
+    name: String,
+    color: [u8; 4],
+    /// Path of the texture, relative to the mesh file.
+    texture: Option<String>,
+}
+
+/// Saves the texmaps of the tile ids as png in a folder next to the mesh file, and builds the materials.
+fn write_materials(
+    mesh: &TerrainMesh,
+    mesh_file_path: &Path,
+    texmap_2d: &TexMap2D,
+    radar_colors: &RadarColors,
+) -> eyre::Result<BTreeMap<u16, MeshMaterial>> {
+    let stem = mesh_file_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let textures_folder_name = format!("{stem}_textures");
+    let textures_folder = mesh_file_path.with_file_name(&textures_folder_name);
+    let mut materials = BTreeMap::new();
+    for &tile_id in mesh.triangles_by_tile_id.keys() {
+        let name = format!("land_0x{tile_id:04X}");
+        let texture = match texmap_2d.element(tile_id as usize).map(|element| element.to_image()) {
+            Some(Ok(image)) => {
+                std::fs::create_dir_all(&textures_folder)
+                    .wrap_err_with(|| format!("Creating the textures folder {textures_folder:?}"))?;
+                let texture_file_name = format!("{name}.png");
+                image
+                    .save_with_format(textures_folder.join(&texture_file_name), image::ImageFormat::Png)
+                    .wrap_err_with(|| format!("Saving the texture of land tile 0x{tile_id:04X}"))?;
+                Some(format!("{textures_folder_name}/{texture_file_name}"))
+            }
+            Some(Err(e)) => {
+                logger::one(
+                    None,
+                    LogSev::Warn,
+                    LogAbout::Renderer,
+                    &format!("Mesh export: can't read the texmap of land tile 0x{tile_id:04X}: {e}"),
+                );
+                None
+            }
+            None => None,
+        };
+        let material = MeshMaterial {
+            name,
+            color: radar_colors.land_color_rgba8888(tile_id),
+            texture,
+        };
+        materials.insert(tile_id, material);
+    }
+    Ok(materials)
+}
+
+fn write_obj(mesh: &TerrainMesh, materials: &BTreeMap<u16, MeshMaterial>, file_path: &Path) -> eyre::Result<()> {
+    let mtl_path = file_path.with_extension("mtl");
+    let mtl_file_name = mtl_path.file_name().unwrap_or_default().to_string_lossy();
+
+    let mut mtl = String::new();
+    for material in materials.values() {
+        let [r, g, b, _] = material.color.map(|c| c as f32 / 255.0);
+        writeln!(mtl, "newmtl {}\nKd {r:.4} {g:.4} {b:.4}", material.name)?;
+        if let Some(texture) = &material.texture {
+            writeln!(mtl, "map_Kd {texture}")?;
+        }
+        writeln!(mtl)?;
+    }
+    std::fs::write(&mtl_path, mtl).wrap_err_with(|| format!("Writing {mtl_path:?}"))?;
+
+    let mut obj = String::with_capacity(mesh.positions.len() * 48);
+    writeln!(obj, "# UODynamapper terrain export\nmtllib {mtl_file_name}\no terrain")?;
+    for [x, y, z] in &mesh.positions {
+        writeln!(obj, "v {x} {y} {z}")?;
+    }
+    for [u, v] in &mesh.uvs {
+        // OBJ texture coordinates start from the bottom of the image.
+        writeln!(obj, "vt {u} {}", 1.0 - v)?;
+    }
+    for (tile_id, triangles) in &mesh.triangles_by_tile_id {
+        writeln!(obj, "usemtl {}", materials[tile_id].name)?;
+        for triangle in triangles.chunks_exact(3) {
+            // 1-based; vertices and texture coordinates have the same indices.
+            let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+            writeln!(obj, "f {a}/{a} {b}/{b} {c}/{c}")?;
+        }
+    }
+    std::fs::write(file_path, obj).wrap_err_with(|| format!("Writing {file_path:?}"))?;
+    Ok(())
+}
+
+fn write_gltf(mesh: &TerrainMesh, materials: &BTreeMap<u16, MeshMaterial>, file_path: &Path) -> eyre::Result<()> {
+    // glTF component types and buffer view targets.
+    const FLOAT: u32 = 5126;
+    const UNSIGNED_INT: u32 = 5125;
+    const ARRAY_BUFFER: u32 = 34962;
+    const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+    let bin_path = file_path.with_extension("bin");
+    let bin_file_name = bin_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+    // Buffer: positions, UVs, then the indices of each material, all little endian.
+    let mut bin = Vec::new();
+    for value in mesh.positions.iter().flatten().chain(mesh.uvs.iter().flatten()) {
+        bin.extend_from_slice(&value.to_le_bytes());
+    }
+    let positions_len = mesh.positions.len() * 12;
+    let uvs_len = mesh.uvs.len() * 8;
+    let indices_offset = bin.len();
+    for index in mesh.triangles_by_tile_id.values().flatten() {
+        bin.extend_from_slice(&index.to_le_bytes());
+    }
+    std::fs::write(&bin_path, &bin).wrap_err_with(|| format!("Writing {bin_path:?}"))?;
+
+    let (min, max) = mesh
+        .positions
+        .iter()
+        .fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), p| {
+            (
+                [min[0].min(p[0]), min[1].min(p[1]), min[2].min(p[2])],
+                [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])],
+            )
+        });
+    let mut accessors = vec![
+        serde_json::json!({
+            "bufferView": 0, "componentType": FLOAT, "count": mesh.positions.len(), "type": "VEC3",
+            "min": min, "max": max,
+        }),
+        serde_json::json!({ "bufferView": 1, "componentType": FLOAT, "count": mesh.uvs.len(), "type": "VEC2" }),
+    ];
+    let mut primitives = Vec::new();
+    let mut gltf_materials = Vec::new();
+    let mut images = Vec::new();
+    let mut textures = Vec::new();
+    let mut indices_byte_offset = 0;
+    for (tile_id, triangles) in &mesh.triangles_by_tile_id {
+        let material = &materials[tile_id];
+        let mut pbr = serde_json::json!({ "metallicFactor": 0.0, "roughnessFactor": 1.0 });
+        if let Some(texture) = &material.texture {
+            images.push(serde_json::json!({ "uri": texture }));
+            textures.push(serde_json::json!({ "source": images.len() - 1, "sampler": 0 }));
+            pbr["baseColorTexture"] = serde_json::json!({ "index": textures.len() - 1 });
+        } else {
+            pbr["baseColorFactor"] = serde_json::json!(material.color.map(|c| c as f32 / 255.0));
+        }
+        gltf_materials.push(serde_json::json!({ "name": material.name, "pbrMetallicRoughness": pbr }));
+
+        accessors.push(serde_json::json!({
+            "bufferView": 2, "byteOffset": indices_byte_offset, "componentType": UNSIGNED_INT,
+            "count": triangles.len(), "type": "SCALAR",
+        }));
+        indices_byte_offset += triangles.len() * 4;
+        primitives.push(serde_json::json!({
+            "attributes": { "POSITION": 0, "TEXCOORD_0": 1 },
+            "indices": accessors.len() - 1,
+            "material": gltf_materials.len() - 1,
+        }));
+    }
+
+    let mut gltf = serde_json::json!({
+        "asset": { "version": "2.0", "generator": "UODynamapper" },
+        "scene": 0,
+        "scenes": [{ "nodes": [0] }],
+        "nodes": [{ "name": "terrain", "mesh": 0 }],
+        "meshes": [{ "name": "terrain", "primitives": primitives }],
+        "materials": gltf_materials,
+        "accessors": accessors,
+        "bufferViews": [
+            { "buffer": 0, "byteOffset": 0, "byteLength": positions_len, "target": ARRAY_BUFFER },
+            { "buffer": 0, "byteOffset": positions_len, "byteLength": uvs_len, "target": ARRAY_BUFFER },
+            {
+                "buffer": 0, "byteOffset": indices_offset, "byteLength": bin.len() - indices_offset,
+                "target": ELEMENT_ARRAY_BUFFER,
+            },
+        ],
+        "buffers": [{ "uri": bin_file_name, "byteLength": bin.len() }],
+    });
+    if !images.is_empty() {
+        // Linear filtering, repeat wrapping.
+        gltf["samplers"] =
+            serde_json::json!([{ "magFilter": 9729, "minFilter": 9729, "wrapS": 10497, "wrapT": 10497 }]);
+        gltf["images"] = serde_json::json!(images);
+        gltf["textures"] = serde_json::json!(textures);
+    }
+    let text = serde_json::to_string_pretty(&gltf)?;
+    std::fs::write(file_path, text).wrap_err_with(|| format!("Writing {file_path:?}"))?;
+    Ok(())
+}
+
+/// Builds the mesh and writes it, with its materials and textures.
+fn export_map_mesh(
+    map_plane: &MapPlaneShared,
+    rect: &MapRectCells,
+    file_path: &Path,
+    texmap_2d: &TexMap2D,
+    radar_colors: &RadarColors,
+) -> eyre::Result<usize> {
+    let Some(format) = MeshExportFormat::from_path(file_path) else {
+        bail!("Unsupported mesh format: use .obj or .gltf.");
+    };
+    if let Some(parent) = file_path.parent() {
+        std::fs::create_dir_all(parent).wrap_err_with(|| format!("Creating the export folder {parent:?}"))?;
+    }
+    let mesh = TerrainMesh::build(map_plane, rect)?;
+    let materials = write_materials(&mesh, file_path, texmap_2d, radar_colors)?;
+    match format {
+        MeshExportFormat::Obj => write_obj(&mesh, &materials, file_path)?,
+        MeshExportFormat::Gltf => write_gltf(&mesh, &materials, file_path)?,
+    }
+    Ok(mesh.positions.len() / 4)
+}
+
+pub struct MapMeshExportPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(MapMeshExportPlugin);
+
+impl Plugin for MapMeshExportPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.add_event::<ExportMapMeshEvent>().add_systems(
+            Update,
+            (sys_map_mesh_export_input, sys_start_map_mesh_export)
+                .chain()
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// Exports the region around the player, as glTF.
+fn sys_map_mesh_export_input(
+    action_input: ActionInput,
+    player_q: Query<&Player>,
+    mut writer: EventWriter<ExportMapMeshEvent>,
+) {
+    if !action_input.just_pressed(InputAction::ExportMeshAroundPlayer) {
+        return;
+    }
+    let Some(player_pos) = player_q.single().ok().and_then(|player| player.current_pos) else {
+        return;
+    };
+    let half_size = EXPORT_MESH_AROUND_PLAYER_SIZE_TILES / 2;
+    let x0 = (player_pos.x as u32).saturating_sub(half_size);
+    let y0 = (player_pos.y as u32).saturating_sub(half_size);
+    let (x1, y1) = (
+        x0 + EXPORT_MESH_AROUND_PLAYER_SIZE_TILES,
+        y0 + EXPORT_MESH_AROUND_PLAYER_SIZE_TILES,
+    );
+    let map_id = player_pos.m as u32;
+    writer.write(ExportMapMeshEvent {
+        map_id,
+        x0,
+        y0,
+        x1,
+        y1,
+        file_path: PathBuf::from(EXPORT_FOLDER).join(format!("map{map_id}_{x0}_{y0}_{x1}_{y1}.gltf")),
+    });
+}
+
+/// Validates the request and exports the mesh in the background.
+fn sys_start_map_mesh_export(
+    mut events: EventReader<ExportMapMeshEvent>,
+    map_planes_r: Res<MapPlanesRes>,
+    texmap_2d_r: Res<TexMap2DRes>,
+    radar_col_r: Res<RadarColRes>,
+) {
+    let log_err = |msg: &str| logger::one(None, LogSev::Error, LogAbout::Renderer, msg);
+    for request in events.read() {
+        let Some(map_plane) = map_planes_r.get(request.map_id).map(|map_plane| map_plane.clone()) else {
+            log_err(&format!(
+                "Can't export the mesh of map plane {}: not loaded.",
+                request.map_id
+            ));
+            continue;
+        };
+        let map_width = map_plane.size_blocks().width * MapBlock::CELLS_PER_ROW;
+        let map_height = map_plane.size_blocks().height * MapBlock::CELLS_PER_COLUMN;
+        let (x1, y1) = (request.x1.min(map_width), request.y1.min(map_height));
+        if request.x0 >= x1 || request.y0 >= y1 {
+            log_err(&format!(
+                "Can't export the mesh of map plane {}: empty region {},{} - {},{}.",
+                request.map_id, request.x0, request.y0, request.x1, request.y1
+            ));
+            continue;
+        }
+        let rect = MapRectCells {
+            x0: request.x0,
+            y0: request.y0,
+            width: x1 - request.x0,
+            height: y1 - request.y0,
+        };
+        let file_path = request.file_path.clone();
+        let texmap_2d = Arc::clone(&texmap_2d_r.0);
+        let radar_colors = Arc::clone(&radar_col_r.0);
+        IoTaskPool::get()
+            .spawn(async move {
+                match export_map_mesh(&map_plane, &rect, &file_path, &texmap_2d, &radar_colors) {
+                    Ok(tiles) => logger::one(
+                        None,
+                        LogSev::Info,
+                        LogAbout::Renderer,
+                        &format!("Map mesh export ({tiles} tiles) saved to {file_path:?}."),
+                    ),
+                    Err(e) => logger::one(
+                        None,
+                        LogSev::Error,
+                        LogAbout::Renderer,
+                        &format!("Can't export the map mesh to {file_path:?}: {e:#}"),
+                    ),
+                }
+            })
+            .detach();
+    }
+}
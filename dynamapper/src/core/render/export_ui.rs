@@ -0,0 +1,225 @@
+// Map export (egui window)
+// - Toggled with InputAction::ToggleMapExport (X by default).
+// - Picks the rectangle of the map plane shown to export to a PNG file (export::ExportMapRegionEvent): typed corners,
+//   the square around the player, or two corners clicked on the map (left mouse button, not while the map editor
+//   is enabled).
+// - Shows the progress of the running export.
+//
+
+use crate::{
+    core::{
+        controls::key_bindings::{ActionInput, InputAction},
+        map_editor::MapEditorState,
+        render::{
+            export::{ExportMapRegionEvent, MapExportJob, export_region_event, region_around},
+            scene::{SceneStateData, picking::TilePicker, player::Player, world::WorldGeoData},
+        },
+    },
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui, input::EguiWantsInput};
+
+const PICK_MOUSE_BUTTON: MouseButton = MouseButton::Left;
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum CornerPicking {
+    #[default]
+    Off,
+    FirstCorner,
+    /// The first corner clicked.
+    SecondCorner(u32, u32),
+}
+
+#[derive(Resource)]
+pub struct MapExportWindow {
+    pub visible: bool,
+    /// Rectangle to export, x1 and y1 excluded.
+    pub x0: u32,
+    pub y0: u32,
+    pub x1: u32,
+    pub y1: u32,
+    pub picking: CornerPicking,
+}
+
+impl Default for MapExportWindow {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            x0: 0,
+            y0: 0,
+            x1: 512,
+            y1: 512,
+            picking: CornerPicking::Off,
+        }
+    }
+}
+
+pub struct MapExportUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(MapExportUiPlugin);
+
+impl Plugin for MapExportUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<MapExportWindow>()
+            .add_systems(
+                Update,
+                (sys_toggle_map_export_window, sys_pick_export_corners).run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                map_export_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_toggle_map_export_window(action_input: ActionInput, mut window_r: ResMut<MapExportWindow>) {
+    if action_input.ui_wants_keyboard() || !action_input.just_pressed(InputAction::ToggleMapExport) {
+        return;
+    }
+    window_r.visible = !window_r.visible;
+    window_r.picking = CornerPicking::Off;
+}
+
+/// Sets the rectangle from two tiles clicked on the map, in any order.
+fn sys_pick_export_corners(
+    mouse_buttons_r: Res<ButtonInput<MouseButton>>,
+    egui_wants_input_r: Res<EguiWantsInput>,
+    editor_r: Res<MapEditorState>,
+    mut window_r: ResMut<MapExportWindow>,
+    tile_picker: TilePicker,
+) {
+    if !window_r.visible || window_r.picking == CornerPicking::Off {
+        return;
+    }
+    // Left clicks edit the map.
+    if editor_r.enabled {
+        window_r.picking = CornerPicking::Off;
+        return;
+    }
+    if !mouse_buttons_r.just_pressed(PICK_MOUSE_BUTTON) || egui_wants_input_r.wants_pointer_input() {
+        return;
+    }
+    let Some(tile) = tile_picker.cursor_tile() else {
+        return;
+    };
+    window_r.picking = match window_r.picking {
+        CornerPicking::SecondCorner(x, y) => {
+            window_r.x0 = x.min(tile.x);
+            window_r.y0 = y.min(tile.y);
+            window_r.x1 = x.max(tile.x) + 1;
+            window_r.y1 = y.max(tile.y) + 1;
+            CornerPicking::Off
+        }
+        _ => CornerPicking::SecondCorner(tile.x, tile.y),
+    };
+}
+
+fn map_export_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut window_r: ResMut<MapExportWindow>,
+    job_r: Res<MapExportJob>,
+    scene_state_data_r: Res<SceneStateData>,
+    world_geo_data_r: Res<WorldGeoData>,
+    player_q: Query<&Player>,
+    mut export_writer: EventWriter<ExportMapRegionEvent>,
+) {
+    if !window_r.visible {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let map_id = scene_state_data_r.map_id;
+    let map_size = world_geo_data_r
+        .maps
+        .get(&map_id)
+        .map(|metadata| (metadata.width, metadata.height));
+    let window = window_r.as_mut();
+    egui::Window::new("Map export")
+        .default_pos([16.0, 480.0])
+        .resizable(false)
+        .collapsible(false)
+        .open(&mut window.visible)
+        .show(ctx, |ui| {
+            let Some((width, height)) = map_size else {
+                ui.label(format!("Map plane {map_id} isn't loaded."));
+                return;
+            };
+            ui.label(format!("Map plane {map_id} ({width}x{height})"));
+            egui::Grid::new("map_export_grid").num_columns(3).show(ui, |ui| {
+                ui.label("");
+                ui.label("x");
+                ui.label("y");
+                ui.end_row();
+                ui.label("From");
+                ui.add(egui::DragValue::new(&mut window.x0).range(0..=width - 1));
+                ui.add(egui::DragValue::new(&mut window.y0).range(0..=height - 1));
+                ui.end_row();
+                ui.label("To (excluded)");
+                ui.add(egui::DragValue::new(&mut window.x1).range(1..=width));
+                ui.add(egui::DragValue::new(&mut window.y1).range(1..=height));
+                ui.end_row();
+            });
+            let (x1, y1) = (window.x1.min(width), window.y1.min(height));
+            let empty = window.x0 >= x1 || window.y0 >= y1;
+            if empty {
+                ui.label("Empty area.");
+            } else {
+                ui.label(format!("{}x{} tiles", x1 - window.x0, y1 - window.y0));
+            }
+
+            ui.horizontal(|ui| {
+                let player_pos = player_q.single().ok().and_then(|player| player.current_pos);
+                if ui
+                    .add_enabled(player_pos.is_some(), egui::Button::new("Around player"))
+                    .clicked()
+                    && let Some(player_pos) = player_pos
+                {
+                    (window.x0, window.y0, window.x1, window.y1) =
+                        region_around(player_pos.x as u32, player_pos.y as u32);
+                }
+                match window.picking {
+                    CornerPicking::Off => {
+                        if ui
+                            .button("Pick on the map")
+                            .on_hover_text("Click two opposite corners on the map.")
+                            .clicked()
+                        {
+                            window.picking = CornerPicking::FirstCorner;
+                        }
+                    }
+                    _ => {
+                        if ui.button("Cancel").clicked() {
+                            window.picking = CornerPicking::Off;
+                        }
+                    }
+                }
+            });
+            match window.picking {
+                CornerPicking::Off => {}
+                CornerPicking::FirstCorner => {
+                    ui.label("Click the first corner.");
+                }
+                CornerPicking::SecondCorner(x, y) => {
+                    ui.label(format!("First corner: {x}, {y}. Click the opposite one."));
+                }
+            }
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                if ui
+                    .add_enabled(!empty && !job_r.is_running(), egui::Button::new("Export"))
+                    .clicked()
+                {
+                    export_writer.write(export_region_event(map_id, window.x0, window.y0, x1, y1));
+                }
+                if let Some((pages_done, pages_total)) = job_r.progress() {
+                    ui.add(
+                        egui::ProgressBar::new(pages_done as f32 / pages_total as f32)
+                            .text(format!("Page {pages_done}/{pages_total}")),
+                    );
+                }
+            });
+        });
+}
@@ -0,0 +1,214 @@
+// Frame rate (egui window)
+// - The [frame_rate] settings set the frame cap (bevy_framepace), the vsync (the present mode of the window) and the
+//   update rate of the app loop (WinitSettings), focused and in the background.
+// - The window changes them live, and writes them back to the [frame_rate] section of settings.toml. A hot reload of
+//   the file applies them too (FrameRateSettingsChangedEvent).
+//
+
+use crate::core::system_sets::StartupSysSet;
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::window::{PresentMode, PrimaryWindow};
+use bevy::winit::{UpdateMode, WinitSettings};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use bevy_framepace::{FramepaceSettings, Limiter};
+use std::time::Duration;
+
+/// Least update rate of the focused window, without a custom limit: it updates sooner on input.
+const FOCUSED_REACTIVE_FPS: f64 = 60.0;
+/// Range of the custom limits in the window.
+const CUSTOM_FPS_RANGE: std::ops::RangeInclusive<f32> = 10.0..=240.0;
+const BACKGROUND_FPS_RANGE: std::ops::RangeInclusive<f32> = 1.0..=60.0;
+
+/// Update modes of the app loop for the frame rate settings.
+pub fn winit_settings(frame_rate: &SectFrameRate) -> WinitSettings {
+    let focused_mode = if frame_rate.fps_limit < 0.0 {
+        UpdateMode::Continuous
+    } else if frame_rate.fps_limit > 0.0 {
+        UpdateMode::reactive(Duration::from_secs_f64(1.0 / f64::from(frame_rate.fps_limit)))
+    } else {
+        UpdateMode::reactive(Duration::from_secs_f64(1.0 / FOCUSED_REACTIVE_FPS))
+    };
+    let unfocused_mode = if frame_rate.background_throttle {
+        UpdateMode::reactive_low_power(Duration::from_secs_f64(
+            1.0 / f64::from(frame_rate.background_fps.max(*BACKGROUND_FPS_RANGE.start())),
+        ))
+    } else {
+        focused_mode
+    };
+    WinitSettings {
+        focused_mode,
+        unfocused_mode,
+    }
+}
+
+fn framepace_limiter(frame_rate: &SectFrameRate) -> Limiter {
+    if frame_rate.fps_limit < 0.0 {
+        Limiter::Off
+    } else if frame_rate.fps_limit > 0.0 {
+        Limiter::from_framerate(f64::from(frame_rate.fps_limit))
+    } else {
+        Limiter::Auto
+    }
+}
+
+fn fps_limit_label(frame_rate: &SectFrameRate) -> String {
+    if frame_rate.fps_limit < 0.0 {
+        "none".to_owned()
+    } else if frame_rate.fps_limit > 0.0 {
+        format!("{} FPS", frame_rate.fps_limit)
+    } else {
+        "monitor refresh rate".to_owned()
+    }
+}
+
+fn present_mode(frame_rate: &SectFrameRate) -> PresentMode {
+    if frame_rate.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    }
+}
+
+pub struct FrameRateUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(FrameRateUiPlugin);
+
+impl Plugin for FrameRateUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.add_systems(Startup, sys_setup_frame_rate.in_set(StartupSysSet::First))
+            .add_systems(Update, sys_evlisten_apply_frame_rate)
+            .add_systems(
+                EguiPrimaryContextPass,
+                frame_rate_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn apply_frame_rate(
+    frame_rate: &SectFrameRate,
+    framepace: &mut FramepaceSettings,
+    winit_settings: &mut WinitSettings,
+    window: Option<Mut<Window>>,
+) {
+    framepace.limiter = framepace_limiter(frame_rate);
+    *winit_settings = self::winit_settings(frame_rate);
+    if let Some(mut window) = window
+        && window.present_mode != present_mode(frame_rate)
+    {
+        window.present_mode = present_mode(frame_rate);
+    }
+}
+
+fn sys_setup_frame_rate(
+    settings_r: Res<Settings>,
+    mut framepace_r: ResMut<FramepaceSettings>,
+    mut winit_settings_r: ResMut<WinitSettings>,
+    mut windows_q: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    log_system_add_startup::<FrameRateUiPlugin>(StartupSysSet::First, fname!());
+    apply_frame_rate(
+        &settings_r.frame_rate,
+        &mut framepace_r,
+        &mut winit_settings_r,
+        windows_q.single_mut().ok(),
+    );
+}
+
+fn sys_evlisten_apply_frame_rate(
+    mut events: EventReader<FrameRateSettingsChangedEvent>,
+    settings_r: Res<Settings>,
+    mut framepace_r: ResMut<FramepaceSettings>,
+    mut winit_settings_r: ResMut<WinitSettings>,
+    mut windows_q: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    if events.read().last().is_none() {
+        return;
+    }
+    apply_frame_rate(
+        &settings_r.frame_rate,
+        &mut framepace_r,
+        &mut winit_settings_r,
+        windows_q.single_mut().ok(),
+    );
+    let frame_rate = &settings_r.frame_rate;
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::Renderer,
+        &format!(
+            "Frame rate: limit {}, vsync {}, background throttle {}.",
+            fps_limit_label(frame_rate),
+            if frame_rate.vsync { "on" } else { "off" },
+            if frame_rate.background_throttle {
+                format!("{} FPS", frame_rate.background_fps)
+            } else {
+                "off".to_owned()
+            },
+        ),
+    );
+}
+
+/// A control change to save: not while a slider is being dragged.
+fn commit(response: &egui::Response) -> bool {
+    response.drag_stopped() || (response.changed() && !response.dragged())
+}
+
+fn frame_rate_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut settings_r: ResMut<Settings>,
+    mut changed_writer: EventWriter<FrameRateSettingsChangedEvent>,
+) {
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let mut frame_rate = settings_r.frame_rate.clone();
+    let mut save = false;
+    egui::Window::new("Frame Rate")
+        .default_open(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label("FPS limit:");
+            ui.horizontal(|ui| {
+                let custom = frame_rate.fps_limit > 0.0;
+                save |= commit(&ui.radio_value(&mut frame_rate.fps_limit, 0.0, "Monitor refresh rate"));
+                save |= commit(&ui.radio_value(&mut frame_rate.fps_limit, -1.0, "None"));
+                if ui.radio(custom, "Custom").clicked() && !custom {
+                    frame_rate.fps_limit = FOCUSED_REACTIVE_FPS as f32;
+                    save = true;
+                }
+            });
+            if frame_rate.fps_limit > 0.0 {
+                let response = ui.add(egui::Slider::new(&mut frame_rate.fps_limit, CUSTOM_FPS_RANGE).suffix(" FPS"));
+                save |= commit(&response);
+            }
+            save |= commit(&ui.checkbox(&mut frame_rate.vsync, "Vsync"));
+            ui.separator();
+            save |= commit(
+                &ui.checkbox(&mut frame_rate.background_throttle, "Throttle in the background")
+                    .on_hover_text("While the window isn't focused, update less often and only on window events."),
+            );
+            ui.add_enabled_ui(frame_rate.background_throttle, |ui| {
+                let response =
+                    ui.add(egui::Slider::new(&mut frame_rate.background_fps, BACKGROUND_FPS_RANGE).suffix(" FPS"));
+                save |= commit(&response);
+            });
+            save |= commit(
+                &ui.checkbox(&mut frame_rate.pause_in_background, "Pause the world in the background")
+                    .on_hover_text("Stop the walking, the animations, the day/night cycle and the chunk streaming."),
+            );
+        });
+
+    if frame_rate != settings_r.frame_rate {
+        settings_r.frame_rate = frame_rate;
+        changed_writer.write(FrameRateSettingsChangedEvent);
+    }
+    if save && let Err(e) = save_frame_rate(&settings_r.frame_rate) {
+        logger::one(
+            None,
+            LogSev::Error,
+            LogAbout::General,
+            &format!("Can't save the frame rate settings to the settings file: {e}"),
+        );
+    }
+}
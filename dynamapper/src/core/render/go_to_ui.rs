@@ -0,0 +1,299 @@
+// Go to (egui window)
+// - Toggled with InputAction::ToggleGoTo (F8 by default).
+// - Teleports the player to typed coordinates: "x, y", "x, y, z" or "x, y, z, map" (commas and/or spaces). Without a
+//   z the player lands on the land tile, without a map it stays on the current map plane.
+// - The move itself is done by player::sys_teleport_player (TeleportPlayerEvent), which also switches the map plane.
+// - Back and Forward buttons for the location history (controls::location_history).
+// - Lists the bookmarks (external_data::bookmarks): the player location can be added as a new one, and each one can
+//   be jumped to or removed. The first ones are jumped to with InputAction::GO_TO_BOOKMARK too (1 to 5 by default).
+//
+
+use crate::{
+    core::{
+        controls::{
+            key_bindings::{ActionInput, InputAction, KeyBindings, key_name},
+            location_history::{LocationHistory, LocationHistoryDirection, NavigateLocationHistoryEvent},
+        },
+        render::scene::{
+            picking::land_cell_at,
+            player::{Player, TeleportPlayerEvent},
+            world::WorldGeoData,
+        },
+        uo_files_loader::{MapPlanesRes, UoInterfaceSettingsRes},
+    },
+    external_data::bookmarks::{Bookmark, Bookmarks},
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use uocf::geo::map_def::MapDefinitions;
+
+#[derive(Resource, Default)]
+pub struct GoToWindow {
+    pub visible: bool,
+    pub text: String,
+    /// Why the last typed coordinates were rejected.
+    pub error: Option<String>,
+    /// Name of the next bookmark added.
+    pub bookmark_name: String,
+    /// Give the keyboard focus to the text field when the window opens.
+    focus_text: bool,
+}
+
+pub struct GoToUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(GoToUiPlugin);
+
+impl Plugin for GoToUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<GoToWindow>()
+            .add_systems(
+                Update,
+                (sys_toggle_go_to_window, sys_go_to_bookmark_keys).run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                go_to_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_toggle_go_to_window(action_input: ActionInput, mut window_r: ResMut<GoToWindow>) {
+    if action_input.ui_wants_keyboard() || !action_input.just_pressed(InputAction::ToggleGoTo) {
+        return;
+    }
+    window_r.visible = !window_r.visible;
+    window_r.focus_text = window_r.visible;
+}
+
+/// Jumps to one of the first bookmarks.
+fn sys_go_to_bookmark_keys(
+    action_input: ActionInput,
+    bookmarks_r: Res<Bookmarks>,
+    mut teleport_writer: EventWriter<TeleportPlayerEvent>,
+) {
+    if action_input.ui_wants_keyboard() {
+        return;
+    }
+    for (action, bookmark) in InputAction::GO_TO_BOOKMARK.into_iter().zip(&bookmarks_r.list) {
+        if action_input.just_pressed(action) {
+            teleport_writer.write(bookmark.teleport_event());
+            return;
+        }
+    }
+}
+
+/// Parses "x, y[, z[, map]]". Returns the request, or why the text isn't valid.
+/// The map must be in map_defs. Its size is checked only if the map plane is already loaded.
+pub fn parse_go_to_coords(
+    text: &str,
+    current_map_id: u32,
+    world_geo_data: &WorldGeoData,
+    map_defs: &MapDefinitions,
+) -> Result<TeleportPlayerEvent, String> {
+    let fields: Vec<&str> = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|field| !field.is_empty())
+        .collect();
+    if !(2..=4).contains(&fields.len()) {
+        return Err("Expected x, y[, z[, map]].".to_owned());
+    }
+    let x: u16 = fields[0].parse().map_err(|_| format!("Invalid x: '{}'.", fields[0]))?;
+    let y: u16 = fields[1].parse().map_err(|_| format!("Invalid y: '{}'.", fields[1]))?;
+    let z: Option<i8> = match fields.get(2) {
+        Some(field) => Some(
+            field
+                .parse()
+                .map_err(|_| format!("Invalid z: '{field}' (-128 to 127)."))?,
+        ),
+        None => None,
+    };
+    let map_id: u32 = match fields.get(3) {
+        Some(field) => field.parse().map_err(|_| format!("Invalid map: '{field}'."))?,
+        None => current_map_id,
+    };
+    if map_defs.get(map_id).is_none() {
+        let map_ids: Vec<String> = map_defs.indices().map(|map_id| map_id.to_string()).collect();
+        return Err(format!("Unknown map: {map_id} (known: {}).", map_ids.join(", ")));
+    }
+    if let Some(metadata) = world_geo_data.maps.get(&map_id)
+        && (x as u32 >= metadata.width || y as u32 >= metadata.height)
+    {
+        return Err(format!(
+            "Out of map {map_id}, which is {}x{}.",
+            metadata.width, metadata.height
+        ));
+    }
+    Ok(TeleportPlayerEvent {
+        x,
+        y,
+        z,
+        map_id,
+        from_history: false,
+    })
+}
+
+fn go_to_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut window_r: ResMut<GoToWindow>,
+    world_geo_data_r: Res<WorldGeoData>,
+    map_planes_r: Res<MapPlanesRes>,
+    uo_interface_settings_r: Res<UoInterfaceSettingsRes>,
+    key_bindings_r: Res<KeyBindings>,
+    mut bookmarks_r: ResMut<Bookmarks>,
+    location_history_r: Res<LocationHistory>,
+    player_q: Query<(&Player, &Transform)>,
+    mut teleport_writer: EventWriter<TeleportPlayerEvent>,
+    mut navigate_writer: EventWriter<NavigateLocationHistoryEvent>,
+) {
+    if !window_r.visible {
+        return;
+    }
+    let Ok((player, player_transform)) = player_q.single() else {
+        return;
+    };
+    let Some(player_pos) = player.location(player_transform) else {
+        return;
+    };
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let window = window_r.as_mut();
+    egui::Window::new("Go to")
+        .default_pos([16.0, 360.0])
+        .resizable(false)
+        .collapsible(false)
+        .open(&mut window.visible)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Player: {}, {} (map {})",
+                    player_pos.x, player_pos.y, player_pos.m
+                ));
+                for (direction, label, action, count) in [
+                    (
+                        LocationHistoryDirection::Back,
+                        "Back",
+                        InputAction::LocationBack,
+                        location_history_r.back_len(),
+                    ),
+                    (
+                        LocationHistoryDirection::Forward,
+                        "Forward",
+                        InputAction::LocationForward,
+                        location_history_r.forward_len(),
+                    ),
+                ] {
+                    let hint = location_history_r.next(direction).map_or(String::new(), |location| {
+                        format!(
+                            "{}, {} (map {}) (Alt + {})",
+                            location.x,
+                            location.y,
+                            location.m,
+                            key_name(key_bindings_r.key(action)).unwrap_or("?")
+                        )
+                    });
+                    if ui
+                        .add_enabled(count > 0, egui::Button::new(format!("{label} ({count})")))
+                        .on_hover_text(hint)
+                        .clicked()
+                    {
+                        navigate_writer.write(NavigateLocationHistoryEvent(direction));
+                    }
+                }
+            });
+            let mut go = false;
+            ui.horizontal(|ui| {
+                let text_edit = ui.add(
+                    egui::TextEdit::singleline(&mut window.text)
+                        .hint_text("x, y[, z[, map]]")
+                        .desired_width(140.0),
+                );
+                if window.focus_text {
+                    text_edit.request_focus();
+                    window.focus_text = false;
+                }
+                go = text_edit.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter));
+                go |= ui.button("Go").clicked();
+            });
+            if go {
+                match parse_go_to_coords(
+                    &window.text,
+                    player_pos.m as u32,
+                    &world_geo_data_r,
+                    &uo_interface_settings_r.0.map_defs,
+                ) {
+                    Ok(request) => {
+                        teleport_writer.write(request);
+                        window.error = None;
+                    }
+                    Err(e) => window.error = Some(e),
+                }
+            }
+            if let Some(error) = &window.error {
+                ui.colored_label(egui::Color32::from_rgb(230, 70, 70), error);
+            }
+            ui.separator();
+
+            // ------------------------ Bookmarks -----------------------
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut window.bookmark_name)
+                        .hint_text("Bookmark name")
+                        .desired_width(140.0),
+                );
+                if ui.button("Add player location").clicked() {
+                    let (x, y) = (player_pos.x, player_pos.y);
+                    let z = land_cell_at(&map_planes_r, player_pos.m as u32, x as u32, y as u32)
+                        .map_or(player_pos.z, |cell| cell.z);
+                    let name = match window.bookmark_name.trim() {
+                        "" => format!("{x}, {y} (map {})", player_pos.m),
+                        name => name.to_owned(),
+                    };
+                    bookmarks_r.add(Bookmark {
+                        name,
+                        x,
+                        y,
+                        z,
+                        map: player_pos.m,
+                    });
+                    window.bookmark_name.clear();
+                }
+            });
+            if bookmarks_r.list.is_empty() {
+                ui.label("No bookmarks.");
+                return;
+            }
+            let mut jump_to: Option<usize> = None;
+            let mut remove: Option<usize> = None;
+            egui::Grid::new("bookmarks_grid")
+                .num_columns(5)
+                .striped(true)
+                .show(ui, |ui| {
+                    for (i, bookmark) in bookmarks_r.list.iter().enumerate() {
+                        let key = InputAction::GO_TO_BOOKMARK
+                            .get(i)
+                            .and_then(|&action| key_name(key_bindings_r.key(action)));
+                        ui.label(key.unwrap_or(""));
+                        ui.label(&bookmark.name);
+                        ui.label(format!(
+                            "{}, {}, {} (map {})",
+                            bookmark.x, bookmark.y, bookmark.z, bookmark.map
+                        ));
+                        if ui.button("Go").clicked() {
+                            jump_to = Some(i);
+                        }
+                        if ui.button("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                    }
+                });
+            if let Some(i) = jump_to {
+                teleport_writer.write(bookmarks_r.list[i].teleport_event());
+            }
+            if let Some(i) = remove {
+                bookmarks_r.remove(i);
+            }
+        });
+}
@@ -0,0 +1,162 @@
+// Key bindings (egui window)
+// - Lists the key and the gamepad button bound to each input action. Clicking one waits for the next key or button
+//   press and binds it to the action (Escape cancels). One already bound to another action is swapped with it.
+//   Right-clicking a gamepad button unbinds it.
+// - Every change is written back to the [input.key_bindings] and [input.gamepad_bindings] tables of settings.toml.
+//
+
+use crate::{
+    core::controls::key_bindings::{InputAction, KeyBindings, gamepad_button_name, key_name},
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RebindTarget {
+    Key(InputAction),
+    GamepadButton(InputAction),
+}
+
+/// Binding waiting for a key or gamepad button press to be rebound, if any.
+#[derive(Resource, Default)]
+pub struct KeyRebindCapture(pub Option<RebindTarget>);
+
+pub struct KeyBindingsUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(KeyBindingsUiPlugin);
+
+impl Plugin for KeyBindingsUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<KeyRebindCapture>()
+            .add_systems(
+                Update,
+                sys_capture_rebound_key.run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                key_bindings_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_capture_rebound_key(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    gamepads_q: Query<&Gamepad>,
+    mut capture_r: ResMut<KeyRebindCapture>,
+    mut key_bindings_r: ResMut<KeyBindings>,
+    mut settings_r: ResMut<Settings>,
+) {
+    let Some(target) = capture_r.0 else {
+        return;
+    };
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        capture_r.0 = None;
+        return;
+    }
+    match target {
+        RebindTarget::Key(action) => {
+            let Some(&key) = keyboard_input.get_just_pressed().next() else {
+                return;
+            };
+            capture_r.0 = None;
+            if key_name(key).is_none() {
+                lg_unbindable(&format!("Key {key:?}"));
+                return;
+            }
+            key_bindings_r.rebind(action, key);
+        }
+        RebindTarget::GamepadButton(action) => {
+            let Some(&button) = gamepads_q.iter().find_map(|gamepad| gamepad.get_just_pressed().next()) else {
+                return;
+            };
+            capture_r.0 = None;
+            if gamepad_button_name(button).is_none() {
+                lg_unbindable(&format!("Gamepad button {button:?}"));
+                return;
+            }
+            key_bindings_r.rebind_button(action, button);
+        }
+    }
+    store_key_bindings(&key_bindings_r, &mut settings_r);
+}
+
+fn lg_unbindable(input_name: &str) {
+    logger::one(
+        None,
+        LogSev::Warn,
+        LogAbout::General,
+        &format!("{input_name} can't be bound to an action."),
+    );
+}
+
+fn key_bindings_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut capture_r: ResMut<KeyRebindCapture>,
+    mut key_bindings_r: ResMut<KeyBindings>,
+    mut settings_r: ResMut<Settings>,
+) {
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    egui::Window::new("Key Bindings")
+        .default_open(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("key_bindings_grid").num_columns(3).show(ui, |ui| {
+                ui.label("");
+                ui.strong("Key");
+                ui.strong("Gamepad");
+                ui.end_row();
+                for action in InputAction::ALL {
+                    ui.label(action.label());
+                    let text = if capture_r.0 == Some(RebindTarget::Key(action)) {
+                        "Press a key...".to_owned()
+                    } else {
+                        let key = key_bindings_r.key(action);
+                        key_name(key).map_or_else(|| format!("{key:?}"), str::to_owned)
+                    };
+                    if ui.button(text).clicked() {
+                        capture_r.0 = Some(RebindTarget::Key(action));
+                    }
+                    let text = if capture_r.0 == Some(RebindTarget::GamepadButton(action)) {
+                        "Press a button..."
+                    } else {
+                        key_bindings_r
+                            .button(action)
+                            .and_then(gamepad_button_name)
+                            .unwrap_or("-")
+                    };
+                    let response = ui.button(text).on_hover_text("Right click: unbind");
+                    if response.clicked() {
+                        capture_r.0 = Some(RebindTarget::GamepadButton(action));
+                    } else if response.secondary_clicked() {
+                        capture_r.0 = None;
+                        key_bindings_r.set_button(action, None);
+                        store_key_bindings(&key_bindings_r, &mut settings_r);
+                    }
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            if ui.button("Reset to defaults").clicked() {
+                capture_r.0 = None;
+                *key_bindings_r = KeyBindings::default();
+                store_key_bindings(&key_bindings_r, &mut settings_r);
+            }
+        });
+}
+
+/// Updates the settings with the current bindings and saves them to settings.toml.
+fn store_key_bindings(key_bindings: &KeyBindings, settings: &mut Settings) {
+    settings.input.key_bindings = key_bindings.to_settings_table();
+    settings.input.gamepad_bindings = key_bindings.to_gamepad_settings_table();
+    if let Err(e) = save_key_bindings(&settings.input.key_bindings, &settings.input.gamepad_bindings) {
+        logger::one(
+            None,
+            LogSev::Error,
+            LogAbout::General,
+            &format!("Can't save the key bindings to the settings file: {e}"),
+        );
+    }
+}
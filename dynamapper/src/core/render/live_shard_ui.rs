@@ -0,0 +1,229 @@
+// Live Shard (egui window)
+// - Toggled with InputAction::ToggleLiveShard (F2 by default).
+// - Connects to the live data feed of a shard (core::live_world::shard_link): the server defaults to
+//   live_shard.server. Shows the state of the connection.
+// - Replays packet logs (core::live_world::replay) instead: loads them, plays, pauses, changes the speed and seeks.
+// - Lists the live entities (players, NPCs, items), filtered by kind and by name. "Go" teleports the player to one
+//   of them.
+//
+
+use crate::{
+    core::{
+        controls::key_bindings::{ActionInput, InputAction},
+        live_world::{
+            LiveEntityKind, LiveWorld,
+            replay::{LoadPacketLogsEvent, PacketLogReplay, SeekPacketLogReplayEvent},
+            shard_link::{ConnectShardEvent, DisconnectShardEvent, ShardLink, ShardLinkState},
+        },
+        render::scene::player::TeleportPlayerEvent,
+    },
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use std::path::PathBuf;
+
+/// Entities listed at most: a shard can have a lot of items.
+const MAX_LISTED: usize = 500;
+const REPLAY_SPEEDS: [f32; 6] = [0.25, 0.5, 1.0, 2.0, 5.0, 20.0];
+
+#[derive(Resource)]
+pub struct LiveShardWindow {
+    pub visible: bool,
+    pub server: String,
+    /// Kinds of entities listed, in the order of LiveEntityKind::ALL.
+    pub shown_kinds: [bool; LiveEntityKind::ALL.len()],
+    /// Only the entities whose name contains it (case insensitive).
+    pub name_filter: String,
+    /// Packet logs to replay, one path per line.
+    pub log_files: String,
+}
+impl Default for LiveShardWindow {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            server: String::new(),
+            // Items are usually too many to be useful in the list.
+            shown_kinds: [true, true, false],
+            name_filter: String::new(),
+            log_files: String::new(),
+        }
+    }
+}
+
+pub struct LiveShardUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(LiveShardUiPlugin);
+
+impl Plugin for LiveShardUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<LiveShardWindow>()
+            .add_systems(Update, sys_toggle_live_shard_window.run_if(in_state(AppState::InGame)))
+            .add_systems(
+                EguiPrimaryContextPass,
+                live_shard_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_toggle_live_shard_window(
+    action_input: ActionInput,
+    settings_r: Res<Settings>,
+    mut window_r: ResMut<LiveShardWindow>,
+) {
+    if action_input.ui_wants_keyboard() || !action_input.just_pressed(InputAction::ToggleLiveShard) {
+        return;
+    }
+    window_r.visible = !window_r.visible;
+    if window_r.visible && window_r.server.is_empty() {
+        window_r.server = settings_r.live_shard.server.clone();
+    }
+}
+
+fn live_shard_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut window_r: ResMut<LiveShardWindow>,
+    link_r: Res<ShardLink>,
+    live_world_r: Res<LiveWorld>,
+    mut replay_r: ResMut<PacketLogReplay>,
+    mut connect_writer: EventWriter<ConnectShardEvent>,
+    mut disconnect_writer: EventWriter<DisconnectShardEvent>,
+    mut load_logs_writer: EventWriter<LoadPacketLogsEvent>,
+    mut seek_writer: EventWriter<SeekPacketLogReplayEvent>,
+    mut teleport_writer: EventWriter<TeleportPlayerEvent>,
+) {
+    if !window_r.visible {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let window = window_r.as_mut();
+    egui::Window::new("Live shard")
+        .default_pos([16.0, 200.0])
+        .default_width(360.0)
+        .collapsible(false)
+        .open(&mut window.visible)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Shard");
+                ui.add(
+                    egui::TextEdit::singleline(&mut window.server)
+                        .hint_text("host:port")
+                        .desired_width(180.0),
+                );
+                let server = window.server.trim();
+                if ui.add_enabled(!server.is_empty(), egui::Button::new("Connect")).clicked() {
+                    connect_writer.write(ConnectShardEvent {
+                        server: server.to_owned(),
+                    });
+                }
+                let connected = matches!(link_r.state, ShardLinkState::Connecting(_) | ShardLinkState::Connected(_));
+                if ui.add_enabled(connected, egui::Button::new("Disconnect")).clicked() {
+                    disconnect_writer.write(DisconnectShardEvent);
+                }
+            });
+            match &link_r.state {
+                ShardLinkState::Disconnected => ui.label("Not connected."),
+                ShardLinkState::Connecting(server) => ui.label(format!("Connecting to {server}...")),
+                ShardLinkState::Connected(server) => ui.label(format!("Connected to {server}.")),
+                ShardLinkState::Failed(reason) => {
+                    ui.colored_label(egui::Color32::LIGHT_RED, format!("Connection ended: {reason}"))
+                }
+            };
+            if link_r.bad_lines > 0 {
+                ui.label(format!("{} lines not understood (see the log).", link_r.bad_lines));
+            }
+
+            egui::CollapsingHeader::new("Packet log replay").show(ui, |ui| {
+                ui.add(
+                    egui::TextEdit::multiline(&mut window.log_files)
+                        .hint_text("Packet log files, one per line")
+                        .desired_rows(2)
+                        .desired_width(f32::INFINITY),
+                );
+                let files: Vec<PathBuf> = window
+                    .log_files
+                    .lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(PathBuf::from)
+                    .collect();
+                if ui.add_enabled(!files.is_empty(), egui::Button::new("Load")).clicked() {
+                    load_logs_writer.write(LoadPacketLogsEvent { files });
+                }
+                if !replay_r.is_loaded() {
+                    ui.label("No packet log loaded.");
+                    return;
+                }
+                ui.label(format!(
+                    "{} updates from {} logs.",
+                    replay_r.update_count(),
+                    replay_r.files.len()
+                ));
+                let replay = replay_r.as_mut();
+                ui.horizontal(|ui| {
+                    let play_label = if replay.playing { "Pause" } else { "Play" };
+                    if ui.button(play_label).clicked() {
+                        replay.playing = !replay.playing;
+                    }
+                    egui::ComboBox::from_id_salt("replay_speed")
+                        .selected_text(format!("{}x", replay.speed))
+                        .show_ui(ui, |ui| {
+                            for speed in REPLAY_SPEEDS {
+                                ui.selectable_value(&mut replay.speed, speed, format!("{speed}x"));
+                            }
+                        });
+                });
+                let mut time_secs = replay.time_ms / 1000.0;
+                let duration_secs = replay.duration_ms() as f64 / 1000.0;
+                let slider = egui::Slider::new(&mut time_secs, 0.0..=duration_secs).suffix(" s").max_decimals(1);
+                if ui.add(slider).changed() {
+                    seek_writer.write(SeekPacketLogReplayEvent {
+                        time_ms: (time_secs * 1000.0) as u64,
+                    });
+                }
+            });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                for (kind, shown) in LiveEntityKind::ALL.into_iter().zip(window.shown_kinds.iter_mut()) {
+                    ui.checkbox(shown, format!("{}s: {}", kind.as_ref(), live_world_r.count(kind)));
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.add(egui::TextEdit::singleline(&mut window.name_filter).desired_width(180.0));
+            });
+            let name_filter = window.name_filter.trim().to_lowercase();
+            let listed = live_world_r.entities.iter().filter(|(_, entity)| {
+                window.shown_kinds[entity.kind as usize]
+                    && (name_filter.is_empty() || entity.name.to_lowercase().contains(&name_filter))
+            });
+            egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                egui::Grid::new("live_entities").striped(true).show(ui, |ui| {
+                    for (serial, entity) in listed.take(MAX_LISTED) {
+                        let pos = entity.pos;
+                        let name = match entity.graphic {
+                            Some(graphic) if entity.name.is_empty() => format!("0x{graphic:04X}"),
+                            _ => entity.name.clone(),
+                        };
+                        ui.label(format!("0x{serial:08X}"));
+                        ui.label(name);
+                        ui.label(format!("{}, {}, {} ({})", pos.x, pos.y, pos.z, pos.m));
+                        ui.label(entity.region.as_str());
+                        if ui.small_button("Go").clicked() {
+                            teleport_writer.write(TeleportPlayerEvent {
+                                x: pos.x,
+                                y: pos.y,
+                                z: Some(pos.z),
+                                map_id: pos.m as u32,
+                                from_history: false,
+                            });
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+        });
+}
@@ -0,0 +1,159 @@
+// Loading screen (egui window)
+// - Shown while in AppState::Loading: a progress bar for each loading step, from the LoadingProgress resource.
+// - Steps which can't tell their progress show an animated bar while running.
+// - If loading the UO files fails (UoDataLoadingError), an error dialog replaces it: it shows the error and the UO
+//   folder, which can be corrected (typed or picked with the folder dialog, see uo_files_ui) and saved to
+//   settings.toml before loading again.
+//
+
+use crate::{
+    core::{
+        loading::{LoadingProgress, LoadingStep, LoadingStepState},
+        render::uo_files_ui::{BROWSE_BUTTON_LABEL, FolderDialog, check_result_label, check_uo_folder},
+        uo_files_loader::{RetryUoDataLoadingEvent, UoDataLoadingError},
+    },
+    prelude::*,
+};
+use bevy::{app::AppExit, prelude::*};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+const LOADING_BAR_WIDTH: f32 = 280.0;
+const ERROR_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 70, 70);
+
+/// UO folder being typed or picked in the error dialog.
+#[derive(Resource, Default)]
+struct UoFolderEdit {
+    folder: String,
+    dialog: FolderDialog,
+    /// Result of check_uo_folder for a picked folder.
+    check: Option<Result<String, String>>,
+}
+
+pub struct LoadingUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(LoadingUiPlugin);
+
+impl Plugin for LoadingUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<UoFolderEdit>().add_systems(
+            EguiPrimaryContextPass,
+            (loading_ui_system, loading_error_ui_system).run_if(in_state(AppState::Loading)),
+        );
+    }
+}
+
+fn loading_ui_system(
+    mut egui_ctx: EguiContexts,
+    loading_progress_r: Res<LoadingProgress>,
+    loading_error_r: Option<Res<UoDataLoadingError>>,
+) {
+    if loading_error_r.is_some() {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    egui::Window::new("Loading")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            egui::Grid::new("loading_grid").num_columns(2).show(ui, |ui| {
+                for step in LoadingStep::ALL {
+                    ui.label(step.label());
+                    let bar = match loading_progress_r.get(step) {
+                        LoadingStepState::Pending => egui::ProgressBar::new(0.0).text("Waiting"),
+                        LoadingStepState::Running(None) => egui::ProgressBar::new(0.0).animate(true),
+                        LoadingStepState::Running(Some(fraction)) => {
+                            egui::ProgressBar::new(fraction).show_percentage()
+                        }
+                        LoadingStepState::Done => egui::ProgressBar::new(1.0).text("Done"),
+                    };
+                    ui.add(bar.desired_width(LOADING_BAR_WIDTH));
+                    ui.end_row();
+                }
+            });
+            ui.separator();
+            ui.label(format!(
+                "{} / {} steps done.",
+                loading_progress_r.done_count(),
+                LoadingStep::ALL.len()
+            ));
+        });
+}
+
+fn loading_error_ui_system(
+    mut egui_ctx: EguiContexts,
+    loading_error_r: Option<Res<UoDataLoadingError>>,
+    mut folder_edit_r: ResMut<UoFolderEdit>,
+    mut settings_r: ResMut<Settings>,
+    mut retry_writer: EventWriter<RetryUoDataLoadingEvent>,
+    mut exit_writer: EventWriter<AppExit>,
+) {
+    let Some(loading_error_r) = loading_error_r else {
+        return;
+    };
+    let UoFolderEdit { folder, dialog, check } = folder_edit_r.as_mut();
+    if loading_error_r.is_added() {
+        *folder = loading_error_r.folder.clone();
+        *check = None;
+    }
+    if let Some(picked) = dialog.poll() {
+        *folder = picked.to_string_lossy().into_owned();
+        *check = Some(check_uo_folder(folder));
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    egui::Window::new("Can't load the UO files")
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!("UO folder: {}", loading_error_r.folder));
+            ui.add_space(4.0);
+            for (i, message) in loading_error_r.messages.iter().enumerate() {
+                let text = if i == 0 { message.clone() } else { format!("Caused by: {message}") };
+                ui.colored_label(ERROR_COLOR, text);
+            }
+            ui.separator();
+
+            ui.label("Correct the UO client folder and load again:");
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(folder)
+                        .hint_text("Folder with the UO client files")
+                        .desired_width(LOADING_BAR_WIDTH * 1.5),
+                );
+                if response.changed() {
+                    *check = None;
+                }
+                if ui
+                    .add_enabled(!dialog.is_open(), egui::Button::new(BROWSE_BUTTON_LABEL))
+                    .clicked()
+                {
+                    dialog.open("UO client folder", folder);
+                }
+            });
+            if let Some(check) = check {
+                check_result_label(ui, check);
+            }
+            ui.horizontal(|ui| {
+                let folder = folder.trim();
+                if ui.add_enabled(!folder.is_empty(), egui::Button::new("Retry")).clicked() {
+                    // Kept in sync first, so that the hot reload of the written file doesn't see any change.
+                    settings_r.uo_files.folder = folder.to_owned();
+                    if let Err(e) = save_uo_folder(folder) {
+                        logger::one(
+                            None,
+                            LogSev::Error,
+                            LogAbout::General,
+                            &format!("Can't save the UO folder to the settings file: {e}"),
+                        );
+                    }
+                    retry_writer.write(RetryUoDataLoadingEvent);
+                }
+                if ui.button("Quit").clicked() {
+                    exit_writer.write(AppExit::Success);
+                }
+            });
+        });
+}
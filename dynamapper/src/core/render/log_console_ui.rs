@@ -0,0 +1,134 @@
+// Log console (egui window)
+// - Lists the recent messages shown by the logger, filtered by LogAbout, severity and text.
+// - The "Levels" section changes at runtime the lowest severity shown for each LogAbout (the [log] settings are
+//   applied again if settings.toml is reloaded).
+//
+
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use strum::IntoEnumIterator;
+
+const LOG_CONSOLE_HEIGHT: f32 = 320.0;
+
+/// Filters of the messages listed by the console (they don't change what the logger shows).
+#[derive(Resource)]
+pub struct LogConsoleFilter {
+    pub about: Option<LogAbout>,
+    pub min_severity: LogSev,
+    pub text: String,
+}
+impl Default for LogConsoleFilter {
+    fn default() -> Self {
+        Self {
+            about: None,
+            min_severity: LogSev::DebugVerbose,
+            text: String::new(),
+        }
+    }
+}
+impl LogConsoleFilter {
+    fn matches(&self, record: &logger::LogRecord) -> bool {
+        self.about.is_none_or(|about| about == record.about)
+            && record.severity.rank() >= self.min_severity.rank()
+            && (self.text.is_empty() || record.msg.to_lowercase().contains(&self.text.to_lowercase()))
+    }
+}
+
+pub struct LogConsoleUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(LogConsoleUiPlugin);
+
+impl Plugin for LogConsoleUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<LogConsoleFilter>().add_systems(
+            EguiPrimaryContextPass,
+            log_console_ui_system.run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn severity_color(severity: LogSev) -> egui::Color32 {
+    match severity {
+        LogSev::Debug | LogSev::DebugVerbose => egui::Color32::from_rgb(200, 120, 220),
+        LogSev::Diagnostics => egui::Color32::from_rgb(80, 170, 80),
+        LogSev::Error => egui::Color32::from_rgb(230, 70, 70),
+        LogSev::Info => egui::Color32::from_rgb(90, 200, 220),
+        LogSev::Warn => egui::Color32::from_rgb(230, 210, 80),
+    }
+}
+
+fn severity_combo(ui: &mut egui::Ui, id: impl std::hash::Hash, severity: &mut LogSev) -> bool {
+    let mut changed = false;
+    egui::ComboBox::from_id_salt(id)
+        .selected_text(severity.to_string())
+        .show_ui(ui, |ui| {
+            for candidate in LogSev::BY_RANK {
+                changed |= ui.selectable_value(severity, candidate, candidate.to_string()).changed();
+            }
+        });
+    changed
+}
+
+fn log_console_ui_system(mut egui_ctx: EguiContexts, mut filter_r: ResMut<LogConsoleFilter>) {
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    egui::Window::new("Log Console")
+        .default_open(false)
+        .default_width(720.0)
+        .resizable(true)
+        .show(ctx, |ui| {
+            egui::CollapsingHeader::new("Levels").show(ui, |ui| {
+                egui::Grid::new("log_levels_grid").num_columns(2).show(ui, |ui| {
+                    for about in LogAbout::iter() {
+                        ui.label(about.to_string());
+                        let mut severity = logger::min_severity(about);
+                        if severity_combo(ui, ("log_level", about), &mut severity) {
+                            logger::set_min_severity(about, severity);
+                        }
+                        ui.end_row();
+                    }
+                });
+            });
+
+            ui.horizontal(|ui| {
+                ui.label("About:");
+                egui::ComboBox::from_id_salt("log_console_about")
+                    .selected_text(filter_r.about.map_or("All".to_owned(), |about| about.to_string()))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut filter_r.about, None, "All");
+                        for about in LogAbout::iter() {
+                            ui.selectable_value(&mut filter_r.about, Some(about), about.to_string());
+                        }
+                    });
+                ui.label("Min severity:");
+                severity_combo(ui, "log_console_severity", &mut filter_r.min_severity);
+                ui.label("Text:");
+                ui.text_edit_singleline(&mut filter_r.text);
+                if ui.button("Clear").clicked() {
+                    logger::clear_recent_messages();
+                }
+            });
+            ui.separator();
+
+            let records = logger::recent_messages();
+            egui::ScrollArea::vertical()
+                .max_height(LOG_CONSOLE_HEIGHT)
+                .stick_to_bottom(true)
+                .auto_shrink([false, false])
+                .show(ui, |ui| {
+                    for record in records.iter().filter(|record| filter_r.matches(record)) {
+                        ui.label(
+                            egui::RichText::new(format!(
+                                "{} [{}] {}: {}",
+                                record.time, record.about, record.severity, record.msg
+                            ))
+                            .monospace()
+                            .color(severity_color(record.severity)),
+                        )
+                        .on_hover_text(record.location.as_str());
+                    }
+                });
+        });
+}
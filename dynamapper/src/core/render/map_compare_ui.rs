@@ -0,0 +1,159 @@
+// Map Compare (egui window)
+// - Toggled with InputAction::ToggleMapCompare (F10 by default).
+// - Compares the map files of the current map plane with the ones in another folder (maps::compare): the folder
+//   defaults to SectUoFiles.compare_folder.
+// - Shows which version is on screen, flips between them (InputAction::FlipMapCompare, B by default) and stops the
+//   comparison. The differences are highlighted by the map diff terrain overlay, which can be turned off here.
+//
+
+use crate::{
+    core::{
+        controls::key_bindings::{ActionInput, InputAction, KeyBindings, key_name},
+        maps::compare::{CompareMapPlaneEvent, FlipMapCompareEvent, MapCompare, MapCompareSource, StopMapCompareEvent},
+        render::scene::{
+            SceneStateData,
+            world::land::terrain_overlay::{TERRAIN_OVERLAY_MAP_DIFF, TERRAIN_OVERLAY_NONE},
+        },
+    },
+    external_data::shader_presets::UniformState,
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+/// Same colors as the map diff overlay in land_base.wgsl.
+const LEGEND: [(&str, egui::Color32); 3] = [
+    ("Changed tile", egui::Color32::from_rgb(255, 128, 13)),
+    ("Changed z", egui::Color32::from_rgb(26, 217, 242)),
+    ("Both", egui::Color32::from_rgb(242, 26, 217)),
+];
+
+#[derive(Resource, Default)]
+pub struct MapCompareWindow {
+    pub visible: bool,
+    /// Folder with the other version of the map files.
+    pub folder: String,
+}
+
+pub struct MapCompareUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(MapCompareUiPlugin);
+
+impl Plugin for MapCompareUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<MapCompareWindow>()
+            .add_systems(Update, sys_toggle_map_compare_window.run_if(in_state(AppState::InGame)))
+            .add_systems(
+                EguiPrimaryContextPass,
+                map_compare_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_toggle_map_compare_window(
+    action_input: ActionInput,
+    settings_r: Res<Settings>,
+    mut window_r: ResMut<MapCompareWindow>,
+) {
+    if action_input.ui_wants_keyboard() || !action_input.just_pressed(InputAction::ToggleMapCompare) {
+        return;
+    }
+    window_r.visible = !window_r.visible;
+    if window_r.visible && window_r.folder.is_empty() {
+        window_r.folder = settings_r.uo_files.compare_folder.clone();
+    }
+}
+
+fn map_compare_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut window_r: ResMut<MapCompareWindow>,
+    map_compare_r: Res<MapCompare>,
+    scene_state_data_r: Res<SceneStateData>,
+    key_bindings_r: Res<KeyBindings>,
+    mut uniform_state_r: ResMut<UniformState>,
+    mut compare_writer: EventWriter<CompareMapPlaneEvent>,
+    mut flip_writer: EventWriter<FlipMapCompareEvent>,
+    mut stop_writer: EventWriter<StopMapCompareEvent>,
+) {
+    if !window_r.visible {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let window = window_r.as_mut();
+    let map_id = scene_state_data_r.map_id;
+    egui::Window::new("Map compare")
+        .default_pos([16.0, 760.0])
+        .resizable(false)
+        .collapsible(false)
+        .open(&mut window.visible)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Other map files");
+                ui.add(
+                    egui::TextEdit::singleline(&mut window.folder)
+                        .hint_text("Folder with map*.mul")
+                        .desired_width(220.0),
+                );
+            });
+            let folder = window.folder.trim();
+            if ui
+                .add_enabled(!folder.is_empty(), egui::Button::new(format!("Compare map {map_id}")))
+                .clicked()
+            {
+                compare_writer.write(CompareMapPlaneEvent {
+                    map_id,
+                    folder: folder.into(),
+                });
+            }
+            ui.separator();
+
+            let Some(comparison) = &map_compare_r.comparison else {
+                ui.label("No map compared.");
+                return;
+            };
+            ui.label(format!(
+                "Map {}: A is the UO folder, B is {}.",
+                comparison.map_id,
+                comparison.folder.display()
+            ));
+            let shown = match comparison.shown {
+                MapCompareSource::A => "A (UO folder)",
+                MapCompareSource::B => "B (other map files)",
+            };
+            ui.label(format!("Shown: {shown}"));
+            if comparison.map_id != map_id {
+                ui.label(format!("Map {} isn't the current map plane.", comparison.map_id));
+            }
+            ui.horizontal(|ui| {
+                let flip_hint = format!(
+                    "Key: {}",
+                    key_name(key_bindings_r.key(InputAction::FlipMapCompare)).unwrap_or("?")
+                );
+                if ui.button("Flip A/B").on_hover_text(flip_hint).clicked() {
+                    flip_writer.write(FlipMapCompareEvent);
+                }
+                if ui.button("Stop").clicked() {
+                    stop_writer.write(StopMapCompareEvent);
+                }
+            });
+
+            let u = uniform_state_r.as_mut();
+            let mut highlight = u.effects.overlay_mode == TERRAIN_OVERLAY_MAP_DIFF;
+            if ui.checkbox(&mut highlight, "Highlight differences").changed() {
+                u.effects.overlay_mode = if highlight {
+                    TERRAIN_OVERLAY_MAP_DIFF
+                } else {
+                    TERRAIN_OVERLAY_NONE
+                };
+                u.dirty = true;
+            }
+            ui.horizontal(|ui| {
+                for (label, color) in LEGEND {
+                    ui.colored_label(color, label);
+                }
+            });
+            ui.label("Unchanged tiles are darkened.");
+        });
+}
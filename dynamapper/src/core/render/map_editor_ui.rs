@@ -0,0 +1,306 @@
+// Map editor toolbar (egui window)
+// - Shown while the map editing mode is on (InputAction::ToggleMapEditor, F4 by default); closing it leaves the mode.
+// - Picks the brush (tool, radius, strength, tile to paint with) of MapEditorState, undoes and redoes the strokes
+//   (MapEditHistory) and saves the edits of the map plane shown to its map file.
+// - "Import heightmap" opens a window to import a heightmap file (png, csv, json) into the map plane shown.
+//
+
+use std::path::PathBuf;
+
+use crate::{
+    core::{
+        controls::key_bindings::{InputAction, KeyBindings, key_name},
+        map_editor::{
+            BRUSH_RADIUS_MAX, BRUSH_STRENGTH_MAX, MapEditTool, MapEditorState, SaveMapEditsEvent,
+            heightmap_import::{HeightmapImportParams, HeightmapScaling, ImportHeightmapEvent},
+            history::{MapEditHistory, RedoMapEditEvent, UndoMapEditEvent},
+        },
+        render::scene::{SceneStateData, player::Player},
+        uo_files_loader::{LocalizationRes, MapPlanesRes, TileDataRes},
+    },
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+pub struct MapEditorUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(MapEditorUiPlugin);
+
+impl Plugin for MapEditorUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.add_systems(
+            EguiPrimaryContextPass,
+            (map_editor_ui_system, heightmap_import_ui_system).run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn map_editor_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut editor_r: ResMut<MapEditorState>,
+    history_r: Res<MapEditHistory>,
+    key_bindings_r: Res<KeyBindings>,
+    mut undo_writer: EventWriter<UndoMapEditEvent>,
+    mut redo_writer: EventWriter<RedoMapEditEvent>,
+    mut save_writer: EventWriter<SaveMapEditsEvent>,
+    map_planes_r: Res<MapPlanesRes>,
+    scene_state_data_r: Res<SceneStateData>,
+    tiledata_r: Res<TileDataRes>,
+    localization_r: Res<LocalizationRes>,
+) {
+    if !editor_r.enabled {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let editor = editor_r.as_mut();
+    let mut open = true;
+    egui::Window::new("Map Editor")
+        .anchor(egui::Align2::CENTER_TOP, [0.0, 10.0])
+        .resizable(false)
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                for tool in MapEditTool::ALL {
+                    ui.selectable_value(&mut editor.tool, tool, tool.label());
+                }
+            });
+            ui.separator();
+
+            egui::Grid::new("map_editor_brush_grid").num_columns(2).show(ui, |ui| {
+                ui.label("Brush radius");
+                ui.add(egui::Slider::new(&mut editor.brush_radius, 0..=BRUSH_RADIUS_MAX).suffix(" tiles"));
+                ui.end_row();
+
+                match editor.tool {
+                    MapEditTool::Raise | MapEditTool::Lower => {
+                        ui.label("Strength");
+                        ui.add(egui::Slider::new(&mut editor.brush_strength, 1..=BRUSH_STRENGTH_MAX).suffix(" z"));
+                        ui.end_row();
+                    }
+                    MapEditTool::Flatten => {}
+                    MapEditTool::PaintTexture => {
+                        ui.label("Land tile");
+                        ui.horizontal(|ui| {
+                            ui.add(
+                                egui::DragValue::new(&mut editor.paint_tile_id)
+                                    .range(0..=tiledata_r.0.land_tile_count().saturating_sub(1))
+                                    .hexadecimal(4, false, true)
+                                    .prefix("0x"),
+                            );
+                            ui.label(localization_r.land_tile_name(&tiledata_r.0, editor.paint_tile_id));
+                        });
+                        ui.end_row();
+                    }
+                }
+            });
+
+            ui.label(match editor.tool {
+                MapEditTool::Flatten => "Hold the left button: tiles take the z of the tile where you started.",
+                MapEditTool::PaintTexture => "Hold the left button to paint. Alt + click picks the tile under the cursor.",
+                _ => "Hold the left button on the terrain.",
+            });
+            ui.separator();
+
+            ui.horizontal(|ui| {
+                let idle = !editor.is_stroke_in_progress();
+                // The buttons are disabled when there's nothing to undo or redo, so the hints aren't shown empty.
+                let shortcut = |action| key_name(key_bindings_r.key(action)).unwrap_or("?");
+                let undo_hint = history_r.next_undo().map_or(String::new(), |command| {
+                    format!(
+                        "Revert {} (Ctrl + {})",
+                        command.description(),
+                        shortcut(InputAction::UndoMapEdit)
+                    )
+                });
+                if ui
+                    .add_enabled(
+                        idle && history_r.undo_len() > 0,
+                        egui::Button::new(format!("Undo ({})", history_r.undo_len())),
+                    )
+                    .on_hover_text(undo_hint)
+                    .clicked()
+                {
+                    undo_writer.write(UndoMapEditEvent);
+                }
+                let redo_hint = history_r.next_redo().map_or(String::new(), |command| {
+                    format!(
+                        "Apply again {} (Ctrl + {})",
+                        command.description(),
+                        shortcut(InputAction::RedoMapEdit)
+                    )
+                });
+                if ui
+                    .add_enabled(
+                        idle && history_r.redo_len() > 0,
+                        egui::Button::new(format!("Redo ({})", history_r.redo_len())),
+                    )
+                    .on_hover_text(redo_hint)
+                    .clicked()
+                {
+                    redo_writer.write(RedoMapEditEvent);
+                }
+                let map_id = scene_state_data_r.map_id;
+                let edited_blocks = map_planes_r
+                    .get(map_id)
+                    .map_or(0, |map_plane| map_plane.read().edited_block_count());
+                let can_save = edited_blocks > 0 && idle;
+                if ui
+                    .add_enabled(can_save, egui::Button::new("Save"))
+                    .on_hover_text("Write the edits to the map file. The original file is backed up as .bak.")
+                    .clicked()
+                {
+                    save_writer.write(SaveMapEditsEvent { map_id });
+                }
+                ui.label(format!("Edited map blocks: {edited_blocks} (not saved)"));
+            });
+            ui.toggle_value(&mut editor.show_heightmap_import, "Import heightmap...");
+        });
+    if !open {
+        editor.enabled = false;
+    }
+}
+
+/// Values of the Import Heightmap window, kept while it's closed.
+struct HeightmapImportForm {
+    file_path: String,
+    x0: u32,
+    y0: u32,
+    stretch: bool,
+    width: u32,
+    height: u32,
+    fit_range: bool,
+    scale: f32,
+    offset: f32,
+    z_min: i8,
+    z_max: i8,
+}
+impl Default for HeightmapImportForm {
+    fn default() -> Self {
+        let params = HeightmapImportParams::default();
+        Self {
+            file_path: String::new(),
+            x0: params.x0,
+            y0: params.y0,
+            stretch: false,
+            width: 256,
+            height: 256,
+            fit_range: false,
+            scale: 1.0,
+            offset: 0.0,
+            z_min: params.z_min,
+            z_max: params.z_max,
+        }
+    }
+}
+impl HeightmapImportForm {
+    fn params(&self) -> HeightmapImportParams {
+        HeightmapImportParams {
+            x0: self.x0,
+            y0: self.y0,
+            size: self.stretch.then_some((self.width, self.height)),
+            scaling: if self.fit_range {
+                HeightmapScaling::FitRange
+            } else {
+                HeightmapScaling::Linear {
+                    scale: self.scale,
+                    offset: self.offset,
+                }
+            },
+            z_min: self.z_min,
+            z_max: self.z_max,
+        }
+    }
+}
+
+fn heightmap_import_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut form: Local<HeightmapImportForm>,
+    mut editor_r: ResMut<MapEditorState>,
+    mut import_writer: EventWriter<ImportHeightmapEvent>,
+    scene_state_data_r: Res<SceneStateData>,
+    player_q: Query<&Player>,
+) {
+    if !editor_r.enabled || !editor_r.show_heightmap_import {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let form = form.as_mut();
+    let mut open = true;
+    egui::Window::new("Import Heightmap")
+        .resizable(false)
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            egui::Grid::new("heightmap_import_grid").num_columns(2).show(ui, |ui| {
+                ui.label("File");
+                ui.add(egui::TextEdit::singleline(&mut form.file_path).hint_text("png, csv or json"));
+                ui.end_row();
+
+                ui.label("Top left tile");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut form.x0).prefix("x "));
+                    ui.add(egui::DragValue::new(&mut form.y0).prefix("y "));
+                    let player_pos = player_q.single().ok().and_then(|player| player.current_pos);
+                    if ui
+                        .add_enabled(player_pos.is_some(), egui::Button::new("Player position"))
+                        .clicked()
+                        && let Some(pos) = player_pos
+                    {
+                        (form.x0, form.y0) = (pos.x as u32, pos.y as u32);
+                    }
+                });
+                ui.end_row();
+
+                ui.label("Size");
+                ui.horizontal(|ui| {
+                    ui.checkbox(&mut form.stretch, "Stretch to")
+                        .on_hover_text("Unchecked: one heightmap sample per tile.");
+                    ui.add_enabled(form.stretch, egui::DragValue::new(&mut form.width).range(1..=4096));
+                    ui.label("x");
+                    ui.add_enabled(form.stretch, egui::DragValue::new(&mut form.height).range(1..=4096));
+                    ui.label("tiles");
+                });
+                ui.end_row();
+
+                ui.label("Scaling");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut form.fit_range, false, "z = value *");
+                    ui.add_enabled(!form.fit_range, egui::DragValue::new(&mut form.scale).speed(0.01));
+                    ui.label("+");
+                    ui.add_enabled(!form.fit_range, egui::DragValue::new(&mut form.offset));
+                    ui.radio_value(&mut form.fit_range, true, "Fit to the z range")
+                        .on_hover_text("The lowest value becomes the min z, the highest the max z.");
+                });
+                ui.end_row();
+
+                ui.label("z range");
+                ui.horizontal(|ui| {
+                    ui.add(egui::DragValue::new(&mut form.z_min).prefix("min "));
+                    ui.add(egui::DragValue::new(&mut form.z_max).prefix("max "));
+                });
+                ui.end_row();
+            });
+            ui.label("png gray levels span z -128 (black) to 127 (white); csv and json values are used as they are.");
+            ui.separator();
+
+            let can_import = !form.file_path.trim().is_empty() && !editor_r.is_stroke_in_progress();
+            if ui
+                .add_enabled(can_import, egui::Button::new("Import"))
+                .on_hover_text("Sets the z of the tiles of the map plane shown. It can be undone.")
+                .clicked()
+            {
+                import_writer.write(ImportHeightmapEvent {
+                    map_id: scene_state_data_r.map_id,
+                    file_path: PathBuf::from(form.file_path.trim()),
+                    params: form.params(),
+                });
+            }
+        });
+    if !open {
+        editor_r.show_heightmap_import = false;
+    }
+}
@@ -1,8 +1,24 @@
+pub mod annotations;
+pub mod minimap;
+pub mod shard_regions;
+pub mod shard_spawns;
+
 use crate::{
-    core::{render::scene::player::Player, system_sets::StartupSysSet},
+    core::{
+        controls::key_bindings::{ActionInput, InputAction, KeyBindings, key_name},
+        render::scene::{
+            camera::PlayerCamera,
+            picking::TilePicker,
+            player::Player,
+            world::WorldGeoData,
+        },
+        system_sets::StartupSysSet,
+    },
     prelude::*,
 };
 use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass};
+use uocf::geo::sextant::Sextant;
 
 pub struct OverlaysPlugin {
     pub registered_by: &'static str,
@@ -12,17 +28,96 @@ pub struct OverlaysPlugin {
 impl Plugin for OverlaysPlugin {
     fn build(&self, app: &mut App) {
         log_plugin_build(self);
-        app.add_systems(
+        app.add_plugins((
+            minimap::MinimapPlugin {
+                registered_by: "OverlaysPlugin",
+            },
+            shard_regions::ShardRegionsPlugin {
+                registered_by: "OverlaysPlugin",
+            },
+            shard_spawns::ShardSpawnsPlugin {
+                registered_by: "OverlaysPlugin",
+            },
+            annotations::AnnotationsPlugin {
+                registered_by: "OverlaysPlugin",
+            },
+        ))
+        .add_systems(
             Startup,
             setup_overlay_player_position.in_set(StartupSysSet::SetupSceneStage2),
         )
         .add_systems(
             Update,
             update_player_position_text.run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(
+            EguiPrimaryContextPass,
+            sys_copy_coordinates.run_if(in_state(AppState::InGame)),
         );
     }
 }
 
+/// "x, y, z, map", as the Go to window reads it.
+fn format_coordinates(pos: UOVec4) -> String {
+    format!("{}, {}, {}, {}", pos.x, pos.y, pos.z, pos.m)
+}
+
+/// Sextant coordinates of a tile, if the sextant works there.
+fn format_sextant(pos: UOVec4, world_geo_data: &WorldGeoData) -> Option<String> {
+    let metadata = world_geo_data.maps.get(&(pos.m as u32))?;
+    let sextant = Sextant::from_coords(pos.m as u32, pos.x as u32, pos.y as u32, metadata.width, metadata.height)?;
+    Some(sextant.to_string())
+}
+
+/// Copies the coordinates of the cursor tile or of the player to the clipboard, raw or (with Shift) in sextant format.
+fn sys_copy_coordinates(
+    mut egui_ctx: EguiContexts,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    action_input: ActionInput,
+    world_geo_data_r: Res<WorldGeoData>,
+    player_q: Query<(&Player, &Transform)>,
+    tile_picker: TilePicker,
+) {
+    if action_input.ui_wants_keyboard() {
+        return;
+    }
+    let pos = if action_input.just_pressed(InputAction::CopyCursorCoordinates) {
+        tile_picker.cursor_tile().map(|tile| tile.to_uo_vec4())
+    } else if action_input.just_pressed(InputAction::CopyPlayerCoordinates) {
+        player_q
+            .single()
+            .ok()
+            .and_then(|(player, transform)| player.location(transform))
+    } else {
+        return;
+    };
+    let Some(pos) = pos else {
+        return;
+    };
+    let sextant = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    let text = if sextant {
+        let Some(text) = format_sextant(pos, &world_geo_data_r) else {
+            logger::one(
+                None,
+                LogSev::Warn,
+                LogAbout::General,
+                &format!("No sextant coordinates at {}.", format_coordinates(pos)),
+            );
+            return;
+        };
+        text
+    } else {
+        format_coordinates(pos)
+    };
+    egui_ctx.ctx_mut().expect("No egui context?").copy_text(text.clone());
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::General,
+        &format!("Copied '{text}' to the clipboard."),
+    );
+}
+
 // Marker so we can update the text
 #[derive(Component)]
 pub struct OverlayPlayerPositionText;
@@ -69,15 +164,38 @@ pub fn setup_overlay_player_position(mut commands: Commands, asset_server: Res<A
 }
 
 pub fn update_player_position_text(
-    player_query: Query<&Transform, With<Player>>,
+    player_query: Query<(&Player, &Transform)>,
+    camera_query: Query<&PlayerCamera>,
+    key_bindings_r: Res<KeyBindings>,
+    world_geo_data_r: Res<WorldGeoData>,
+    tile_picker: TilePicker,
     mut text_query: Query<&mut Text, With<OverlayPlayerPositionText>>,
 ) {
-    if let (Ok(transform), Ok(mut text)) = (player_query.single(), text_query.single_mut()) {
-        let pos = transform.translation.to_uo_vec3();
-        *text = Text::new(format!(
-            "Player position: [{}, {}, {}]",
-            pos.x, pos.y, pos.z
-        ));
+    if let (Ok((player, transform)), Ok(mut text)) = (player_query.single(), text_query.single_mut())
+        && let Some(pos) = player.location(transform)
+    {
+        let line = |what: &str, pos: UOVec4| match format_sextant(pos, &world_geo_data_r) {
+            Some(sextant) => format!("{what}: [{}]  {sextant}", format_coordinates(pos)),
+            None => format!("{what}: [{}]", format_coordinates(pos)),
+        };
+        let mut msg = line("Player position", pos);
+        msg += "\n";
+        msg += &match tile_picker.cursor_tile().map(|tile| tile.to_uo_vec4()) {
+            Some(cursor_pos) => line("Cursor", cursor_pos),
+            None => "Cursor: -".to_owned(),
+        };
+        let copy_cursor_key = key_name(key_bindings_r.key(InputAction::CopyCursorCoordinates)).unwrap_or("?");
+        let copy_player_key = key_name(key_bindings_r.key(InputAction::CopyPlayerCoordinates)).unwrap_or("?");
+        msg += &format!("\n{copy_cursor_key}/{copy_player_key}: copy cursor/player (Shift: sextant)");
+        // Free camera indicator.
+        if let Ok(camera) = camera_query.single()
+            && let Some(focus) = camera.free_focus
+        {
+            let focus = focus.to_uo_vec3();
+            let snap_key = key_name(key_bindings_r.key(InputAction::SnapCameraToPlayer)).unwrap_or("?");
+            msg += &format!("\nFree camera at [{}, {}] ({snap_key}: back to the player)", focus.x, focus.y);
+        }
+        *text = Text::new(msg);
         /*
         let pos = transform.translation;
         *text = Text::new(format!(
@@ -0,0 +1,154 @@
+// Annotations overlay: the markers, pins and notes of the map plane shown (external_data::annotations), over the
+//  terrain.
+// - The annotations of a map plane are loaded when it's shown.
+// - Each one stands on a stem over its tile, drawn by gizmos over the terrain. Its head (the pin, the marker name, the
+//   note box) is painted by egui at the top of the stem: a billboard, facing the camera and keeping its size when
+//   zooming. Under the windows.
+//
+
+use crate::core::render::scene::{SceneStateData, camera::PlayerCamera};
+use crate::external_data::annotations::{AnnotationKind, Annotations};
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+/// World units.
+const ANNOTATION_STEM_HEIGHT: f32 = 3.0;
+const ANNOTATION_PIN_RADIUS: f32 = 6.0;
+const ANNOTATION_FONT_SIZE: f32 = 14.0;
+/// Width of the note boxes, after which their text wraps (logical pixels).
+const ANNOTATION_NOTE_WIDTH: f32 = 220.0;
+
+/// Draws the stems over the terrain.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct AnnotationGizmos;
+
+pub struct AnnotationsPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(AnnotationsPlugin);
+impl Plugin for AnnotationsPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<Annotations>()
+            .insert_gizmo_config(AnnotationGizmos, GizmoConfig {
+                depth_bias: -1.0,
+                ..default()
+            })
+            .add_systems(
+                Update,
+                (sys_load_map_annotations, sys_draw_annotation_stems)
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                sys_annotation_billboards_ui.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_load_map_annotations(mut annotations_r: ResMut<Annotations>, scene_state_data_r: Res<SceneStateData>) {
+    let map_id = scene_state_data_r.map_id;
+    if annotations_r.map_id == Some(map_id) {
+        return;
+    }
+    annotations_r.load_map(map_id);
+    logger::one(
+        None,
+        LogSev::Debug,
+        LogAbout::General,
+        &format!("Loaded {} annotations of map {map_id}.", annotations_r.list.len()),
+    );
+}
+
+/// Top of the stem of an annotation, at the center of its tile.
+fn stem_top(x: u16, y: u16, z: i8) -> Vec3 {
+    UOVec3::new(x, y, z).to_vec3() + Vec3::new(0.5, ANNOTATION_STEM_HEIGHT, 0.5)
+}
+
+fn sys_draw_annotation_stems(annotations_r: Res<Annotations>, mut gizmos: Gizmos<AnnotationGizmos>) {
+    if !annotations_r.visible {
+        return;
+    }
+    for annotation in &annotations_r.list {
+        let [r, g, b] = annotation.color;
+        let top = stem_top(annotation.x, annotation.y, annotation.z);
+        gizmos.line(top - Vec3::Y * ANNOTATION_STEM_HEIGHT, top, Color::srgb_u8(r, g, b));
+    }
+}
+
+fn sys_annotation_billboards_ui(
+    mut egui_ctx: EguiContexts,
+    annotations_r: Res<Annotations>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+) {
+    if !annotations_r.visible || annotations_r.list.is_empty() {
+        return;
+    }
+    let Ok((camera, camera_transform)) = camera_q.single() else {
+        return;
+    };
+    let Some(viewport) = camera.logical_viewport_rect() else {
+        return;
+    };
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("annotation_billboards"),
+    ));
+    let font = egui::FontId::proportional(ANNOTATION_FONT_SIZE);
+    for annotation in &annotations_r.list {
+        let top = stem_top(annotation.x, annotation.y, annotation.z);
+        let Ok(pos) = camera.world_to_viewport(camera_transform, top) else {
+            continue;
+        };
+        if !viewport.contains(pos) {
+            continue;
+        }
+        let pos = egui::pos2(pos.x, pos.y);
+        let [r, g, b] = annotation.color;
+        let color = egui::Color32::from_rgb(r, g, b);
+        painter.circle(pos, ANNOTATION_PIN_RADIUS, color, egui::Stroke::new(1.5, egui::Color32::BLACK));
+        let text_pos = pos + egui::vec2(ANNOTATION_PIN_RADIUS + 4.0, 0.0);
+        match annotation.kind {
+            AnnotationKind::Pin => {}
+            AnnotationKind::Marker => {
+                // With a shadow, to be readable on any terrain.
+                for (offset, text_color) in [
+                    (egui::vec2(1.0, 1.0), egui::Color32::BLACK),
+                    (egui::Vec2::ZERO, color),
+                ] {
+                    painter.text(
+                        text_pos + offset,
+                        egui::Align2::LEFT_CENTER,
+                        &annotation.text,
+                        font.clone(),
+                        text_color,
+                    );
+                }
+            }
+            AnnotationKind::Note => {
+                let galley = painter.layout(
+                    annotation.text.clone(),
+                    font.clone(),
+                    egui::Color32::WHITE,
+                    ANNOTATION_NOTE_WIDTH,
+                );
+                let margin = egui::vec2(6.0, 4.0);
+                let box_rect = egui::Rect::from_min_size(
+                    text_pos - egui::vec2(0.0, galley.size().y / 2.0 + margin.y),
+                    galley.size() + margin * 2.0,
+                );
+                painter.rect(
+                    box_rect,
+                    4.0,
+                    egui::Color32::from_black_alpha(200),
+                    egui::Stroke::new(1.5, color),
+                    egui::StrokeKind::Inside,
+                );
+                painter.galley(box_rect.min + margin, galley, egui::Color32::WHITE);
+            }
+        }
+    }
+}
@@ -0,0 +1,386 @@
+use crate::core::controls::player_movement::PlayerWalk;
+use crate::core::render::scene::player::Player;
+use crate::core::system_sets::*;
+use crate::core::uo_files_loader::{MapPlanesRes, RadarColRes, StaticsPlanesRes, TileDataRes};
+use crate::prelude::*;
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+#[cfg(debug_assertions)]
+use bevy::ui::RelativeCursorPosition;
+use uocf::geo::map::{MapBlock, MapBlockRelPos, MapCell, MapPlaneShared};
+use uocf::geo::statics::StaticsPlane;
+use uocf::radarcol::RadarColors;
+use uocf::tiledata::TileData;
+
+// The minimap is a texture with a pixel per tile, colored with the radar colors (radarcol.mul) of the land tile or of
+//  the topmost static item of each cell, like the classic client radar. It's centered on the player and rebuilt
+//  when the player moves to another tile.
+
+/// Side of the area shown by the minimap, in tiles (and texture pixels).
+const MINIMAP_SIZE_TILES: u32 = 128;
+/// Side of the minimap on screen, in logical pixels.
+const MINIMAP_DISPLAY_SIZE_PX: f32 = 256.0;
+/// Distance from the top right corner of the window.
+const MINIMAP_MARGIN_PX: f32 = 20.0;
+const MINIMAP_BORDER_PX: f32 = 2.0;
+const MINIMAP_PLAYER_MARKER_SIZE_PX: f32 = 4.0;
+/// Don't rebuild the texture more often than this, even if the player moves faster.
+const MINIMAP_UPDATE_INTERVAL_SECS: f32 = 0.1;
+const MINIMAP_BYTES_PER_PIXEL: usize = 4; // RGBA8888
+/// Color of the pixels outside the map plane bounds.
+const MINIMAP_COLOR_OUT_OF_MAP: [u8; 4] = [0, 0, 0, 255];
+
+pub struct MinimapPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(MinimapPlugin);
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.add_systems(
+            Startup,
+            sys_setup_minimap.in_set(StartupSysSet::SetupSceneStage2),
+        )
+        .add_systems(
+            Update,
+            sys_update_minimap
+                .after(MovementSysSet::MovementActions)
+                .run_if(in_state(AppState::InGame)),
+        );
+
+        #[cfg(debug_assertions)]
+        app.add_systems(
+            Update,
+            sys_minimap_click_teleport
+                .in_set(MovementSysSet::MovementActions)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// Area of the world currently drawn in the minimap texture.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MinimapArea {
+    pub map_id: u32,
+    // Top-left tile. Can be negative, when the player is near the map borders.
+    pub x0: i32,
+    pub y0: i32,
+}
+
+#[derive(Resource)]
+pub struct MinimapImage {
+    pub image_handle: Handle<Image>,
+    pub shown_area: Option<MinimapArea>,
+}
+
+/// Marker for the UI node showing the minimap texture.
+#[derive(Component)]
+pub struct MinimapNode;
+
+pub fn sys_setup_minimap(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    log_system_add_startup::<MinimapPlugin>(StartupSysSet::SetupSceneStage2, fname!());
+
+    // Keep the pixel data in the main world too, since we rewrite it every time the player moves.
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: MINIMAP_SIZE_TILES,
+            height: MINIMAP_SIZE_TILES,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &MINIMAP_COLOR_OUT_OF_MAP,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    );
+    // One pixel per tile: don't blur them when upscaling.
+    image.sampler = ImageSampler::nearest();
+    let image_handle = images.add(image);
+
+    // Root UI node, pinned to the top right with margin. The background acts as a border.
+    let root_id = commands
+        .spawn((
+            Node {
+                position_type: PositionType::Absolute,
+                right: Val::Px(MINIMAP_MARGIN_PX),
+                top: Val::Px(MINIMAP_MARGIN_PX),
+                padding: UiRect::all(Val::Px(MINIMAP_BORDER_PX)),
+                ..default()
+            },
+            BackgroundColor(Color::BLACK.with_alpha(0.65)),
+        ))
+        .id();
+
+    let minimap_id = commands
+        .spawn((
+            Node {
+                width: Val::Px(MINIMAP_DISPLAY_SIZE_PX),
+                height: Val::Px(MINIMAP_DISPLAY_SIZE_PX),
+                ..default()
+            },
+            ImageNode::new(image_handle.clone()),
+            MinimapNode,
+        ))
+        .with_children(|builder| {
+            // Player marker: the player is always at the center of the minimap.
+            builder.spawn((
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Percent(50.0),
+                    top: Val::Percent(50.0),
+                    width: Val::Px(MINIMAP_PLAYER_MARKER_SIZE_PX),
+                    height: Val::Px(MINIMAP_PLAYER_MARKER_SIZE_PX),
+                    margin: UiRect {
+                        left: Val::Px(-MINIMAP_PLAYER_MARKER_SIZE_PX / 2.0),
+                        top: Val::Px(-MINIMAP_PLAYER_MARKER_SIZE_PX / 2.0),
+                        ..default()
+                    },
+                    ..default()
+                },
+                BackgroundColor(Color::WHITE),
+            ));
+        })
+        .id();
+
+    // We need the cursor position over the minimap only to teleport the player.
+    #[cfg(debug_assertions)]
+    commands
+        .entity(minimap_id)
+        .insert((Interaction::default(), RelativeCursorPosition::default()));
+
+    // Assemble node tree
+    commands.entity(root_id).add_child(minimap_id);
+
+    commands.insert_resource(MinimapImage {
+        image_handle,
+        shown_area: None,
+    });
+}
+
+pub fn sys_update_minimap(
+    time_r: Res<Time>,
+    mut elapsed_since_update: Local<f32>,
+    mut minimap_r: ResMut<MinimapImage>,
+    mut images_r: ResMut<Assets<Image>>,
+    map_planes_r: Res<MapPlanesRes>,
+    statics_planes_r: Res<StaticsPlanesRes>,
+    tiledata_r: Res<TileDataRes>,
+    radarcol_r: Res<RadarColRes>,
+    player_q: Query<(&Player, &Transform)>,
+) {
+    *elapsed_since_update += time_r.delta_secs();
+    if minimap_r.shown_area.is_some() && *elapsed_since_update < MINIMAP_UPDATE_INTERVAL_SECS {
+        return;
+    }
+
+    let Ok((player, player_transform)) = player_q.single() else {
+        return;
+    };
+    let Some(player_pos) = player.current_pos else {
+        return;
+    };
+    let player_tile = player_transform.translation.to_uo_vec3();
+    let half_size = (MINIMAP_SIZE_TILES / 2) as i32;
+    let area = MinimapArea {
+        map_id: player_pos.m as u32,
+        x0: player_tile.x as i32 - half_size,
+        y0: player_tile.y as i32 - half_size,
+    };
+    if minimap_r.shown_area == Some(area) {
+        return;
+    }
+    *elapsed_since_update = 0.0;
+
+    let data = {
+        let Some(map_plane) = map_planes_r.get(area.map_id) else {
+            logger::one(
+                None,
+                LogSev::Warn,
+                LogAbout::Renderer,
+                &format!("Minimap: map plane {} is not loaded.", area.map_id),
+            );
+            minimap_r.shown_area = Some(area);
+            return;
+        };
+        let statics_planes_arc = statics_planes_r.0.clone();
+        let mut statics_plane = statics_planes_arc.get_mut(&area.map_id);
+        build_minimap_data(
+            &map_plane,
+            statics_plane.as_deref_mut(),
+            &tiledata_r.0,
+            &radarcol_r.0,
+            area,
+        )
+    };
+
+    if let Some(image) = images_r.get_mut(&minimap_r.image_handle) {
+        image.data = Some(data);
+    }
+    minimap_r.shown_area = Some(area);
+}
+
+/// Builds the RGBA8 pixel data of the minimap for the given area.
+fn build_minimap_data(
+    map_plane: &MapPlaneShared,
+    mut statics_plane: Option<&mut StaticsPlane>,
+    tiledata: &TileData,
+    radarcol: &RadarColors,
+    area: MinimapArea,
+) -> Vec<u8> {
+    let size = MINIMAP_SIZE_TILES as i32;
+    let mut data: Vec<u8> = MINIMAP_COLOR_OUT_OF_MAP.repeat((size * size) as usize);
+
+    // Blocks overlapping the area, clipped to the map plane bounds.
+    let map_width_cells = (map_plane.size_blocks().width * MapBlock::CELLS_PER_ROW) as i32;
+    let map_height_cells = (map_plane.size_blocks().height * MapBlock::CELLS_PER_COLUMN) as i32;
+    let x_start = area.x0.clamp(0, map_width_cells) as u32;
+    let y_start = area.y0.clamp(0, map_height_cells) as u32;
+    let x_end = (area.x0 + size).clamp(0, map_width_cells) as u32;
+    let y_end = (area.y0 + size).clamp(0, map_height_cells) as u32;
+    if x_start >= x_end || y_start >= y_end {
+        return data;
+    }
+
+    let mut blocks_to_load: Vec<MapBlockRelPos> = Vec::new();
+    for bx in MapCell::coords_of_parent_block_x(x_start)..=MapCell::coords_of_parent_block_x(x_end - 1) {
+        for by in MapCell::coords_of_parent_block_y(y_start)..=MapCell::coords_of_parent_block_y(y_end - 1) {
+            blocks_to_load.push(MapBlockRelPos { x: bx, y: by });
+        }
+    }
+
+    if let Err(e) = map_plane.load_blocks(&mut blocks_to_load) {
+        logger::one(
+            None,
+            LogSev::Error,
+            LogAbout::Renderer,
+            &format!("Minimap: can't load map blocks: {e}"),
+        );
+        return data;
+    }
+    if let Some(statics_plane) = statics_plane.as_deref_mut() {
+        if let Err(e) = statics_plane.load_blocks(&mut blocks_to_load) {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::Renderer,
+                &format!("Minimap: can't load statics blocks: {e}"),
+            );
+        }
+    }
+
+    let map_plane = map_plane.read();
+    for block_pos in &blocks_to_load {
+        let Some(map_block) = map_plane.block(*block_pos) else {
+            continue;
+        };
+
+        // Topmost drawable static item of each cell: (z, item id).
+        let mut top_statics: [Option<(i8, u16)>; MapBlock::CELLS_PER_BLOCK as usize] =
+            [None; MapBlock::CELLS_PER_BLOCK as usize];
+        if let Some(statics_block) = statics_plane.as_deref().and_then(|sp| sp.block(*block_pos)) {
+            for item in statics_block.items() {
+                let drawable = tiledata
+                    .item_tile(item.id)
+                    .is_some_and(|item_tile| !item_tile.is_nodraw().unwrap_or(true));
+                if !drawable {
+                    continue;
+                }
+                let cell_idx = (item.y as u32 * MapBlock::CELLS_PER_ROW + item.x as u32) as usize;
+                let Some(top) = top_statics.get_mut(cell_idx) else {
+                    continue;
+                };
+                // Items later in the block are drawn over the previous ones with the same z.
+                if top.is_none_or(|(top_z, _)| item.z >= top_z) {
+                    *top = Some((item.z, item.id));
+                }
+            }
+        }
+
+        let first_cell = MapBlock::coords_first_cell(block_pos);
+        for cy in 0..MapBlock::CELLS_PER_COLUMN {
+            for cx in 0..MapBlock::CELLS_PER_ROW {
+                let px = (first_cell.x + cx) as i32 - area.x0;
+                let py = (first_cell.y + cy) as i32 - area.y0;
+                if px < 0 || py < 0 || px >= size || py >= size {
+                    continue;
+                }
+                let Ok(map_cell) = map_block.cell(cx, cy) else {
+                    continue;
+                };
+                let color = match top_statics[(cy * MapBlock::CELLS_PER_ROW + cx) as usize] {
+                    Some((static_z, item_id)) if static_z >= map_cell.z => {
+                        radarcol.item_color_rgba8888(item_id)
+                    }
+                    _ => radarcol.land_color_rgba8888(map_cell.id),
+                };
+                let pixel_start = (py * size + px) as usize * MINIMAP_BYTES_PER_PIXEL;
+                data[pixel_start..pixel_start + MINIMAP_BYTES_PER_PIXEL].copy_from_slice(&color);
+            }
+        }
+    }
+
+    data
+}
+
+/// Debug helper: clicking on the minimap moves the player to the clicked tile.
+#[cfg(debug_assertions)]
+pub fn sys_minimap_click_teleport(
+    mouse_buttons_r: Res<ButtonInput<MouseButton>>,
+    minimap_r: Res<MinimapImage>,
+    map_planes_r: Res<MapPlanesRes>,
+    minimap_q: Query<&RelativeCursorPosition, With<MinimapNode>>,
+    mut walk_r: ResMut<PlayerWalk>,
+    mut player_q: Query<(&mut Player, &mut Transform)>,
+) {
+    if !mouse_buttons_r.just_pressed(MouseButton::Left) {
+        return;
+    }
+    let Some(area) = minimap_r.shown_area else {
+        return;
+    };
+    let Ok(cursor) = minimap_q.single() else {
+        return;
+    };
+    if !cursor.mouse_over() {
+        return;
+    }
+    let Some(cursor_normalized) = cursor.normalized else {
+        return;
+    };
+
+    let Some(map_plane) = map_planes_r.get(area.map_id) else {
+        return;
+    };
+    let map_width_cells = (map_plane.size_blocks().width * MapBlock::CELLS_PER_ROW) as i32;
+    let map_height_cells = (map_plane.size_blocks().height * MapBlock::CELLS_PER_COLUMN) as i32;
+    let tile_x = area.x0 + (cursor_normalized.x * MINIMAP_SIZE_TILES as f32) as i32;
+    let tile_y = area.y0 + (cursor_normalized.y * MINIMAP_SIZE_TILES as f32) as i32;
+    if tile_x < 0 || tile_y < 0 || tile_x >= map_width_cells || tile_y >= map_height_cells {
+        return;
+    }
+    let (tile_x, tile_y) = (tile_x as u32, tile_y as u32);
+
+    // The block is in the cache, since it's shown in the minimap.
+    let tile_z = map_plane.cell(tile_x, tile_y).map_or(0, |cell| cell.z);
+
+    let Ok((mut player, mut player_transform)) = player_q.single_mut() else {
+        return;
+    };
+    let Some(player_pos) = player.current_pos else {
+        return;
+    };
+    let destination = UOVec4::new(tile_x as u16, tile_y as u16, tile_z, player_pos.m);
+    player.current_pos = Some(destination);
+    player_transform.translation = destination.to_bevy_vec3_ignore_map();
+    walk_r.stop();
+    logger::one(
+        None,
+        LogSev::Debug,
+        LogAbout::Player,
+        &format!("Minimap: teleported player to [{tile_x}, {tile_y}, {tile_z}]."),
+    );
+}
@@ -0,0 +1,306 @@
+// Shard regions overlay: the regions of the shard files (external_data::shard_regions) drawn over the map. Each rect
+//  of a region is a translucent rectangle colored by the region kind, with its outline, and the region is labeled
+//  with its name.
+// - Loaded from the Shard Overlays window (render::shard_overlays_ui), or when entering the game from
+//   shard_overlays.regions_file.
+// - Only the regions of the current map plane and of the shown kinds are drawn. The rectangles are flat, at the
+//   ground height of their center; their outlines are drawn over the terrain, so that hills don't hide them.
+// - Hovering the map lists the regions under the cursor in a tooltip (see render::shard_overlays_ui).
+//
+
+use crate::core::render::scene::{SceneStateData, camera::PlayerCamera, picking::land_cell_at};
+use crate::core::system_sets::StartupSysSet;
+use crate::core::uo_files_loader::MapPlanesRes;
+use crate::external_data::shard_regions::{self, RegionKind, ShardRegion};
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+
+const REGION_FILL_ALPHA: f32 = 0.2;
+/// Height of the rectangles over the ground (world units), so that they don't flicker with it.
+const REGION_GROUND_OFFSET: f32 = 0.05;
+const REGION_LABEL_FONT_SIZE: f32 = 14.0;
+
+/// Color of the regions of a kind (sRGB).
+pub fn region_kind_color(kind: RegionKind) -> [u8; 3] {
+    match kind {
+        RegionKind::Town => [60, 130, 255],
+        RegionKind::Dungeon => [210, 60, 60],
+        RegionKind::Guarded => [60, 200, 90],
+        RegionKind::Other => [200, 200, 200],
+    }
+}
+
+/// Request to draw the regions of this file, instead of the current ones.
+#[derive(Event, Debug, Clone)]
+pub struct LoadShardRegionsEvent {
+    pub file: PathBuf,
+}
+
+#[derive(Resource)]
+pub struct ShardRegions {
+    /// File of the regions drawn. Empty if none.
+    pub file: PathBuf,
+    pub regions: Vec<ShardRegion>,
+    /// Kinds of regions drawn, in the order of RegionKind::ALL.
+    pub shown_kinds: [bool; RegionKind::ALL.len()],
+    pub show_labels: bool,
+}
+impl Default for ShardRegions {
+    fn default() -> Self {
+        Self {
+            file: PathBuf::new(),
+            regions: Vec::new(),
+            shown_kinds: [true; RegionKind::ALL.len()],
+            show_labels: true,
+        }
+    }
+}
+impl ShardRegions {
+    pub fn count(&self, kind: RegionKind) -> usize {
+        self.regions.iter().filter(|region| region.kind == kind).count()
+    }
+
+    fn is_drawn(&self, region: &ShardRegion, map_id: u32) -> bool {
+        region.map as u32 == map_id && self.shown_kinds[region.kind as usize]
+    }
+
+    /// The regions drawn on a tile.
+    pub fn drawn_at(&self, map_id: u32, x: u16, y: u16) -> impl Iterator<Item = &ShardRegion> {
+        self.regions.iter().filter(move |region| {
+            self.is_drawn(region, map_id) && region.rects.iter().any(|rect| rect.contains(x, y))
+        })
+    }
+}
+
+/// A rectangle drawn for a rect of ShardRegions.regions[region]. Its transform scales a unit square to the rect.
+#[derive(Component)]
+pub struct ShardRegionRect {
+    pub region: usize,
+}
+
+/// Draws the outlines over the terrain.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct ShardRegionGizmos;
+
+/// Mesh of the rectangles, and their material for each kind.
+#[derive(Resource, Default)]
+struct ShardRegionLooks {
+    mesh: Handle<Mesh>,
+    materials: HashMap<RegionKind, Handle<StandardMaterial>>,
+}
+
+pub struct ShardRegionsPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(ShardRegionsPlugin);
+impl Plugin for ShardRegionsPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<ShardRegions>()
+            .init_resource::<ShardRegionLooks>()
+            .add_event::<LoadShardRegionsEvent>()
+            .insert_gizmo_config(ShardRegionGizmos, GizmoConfig {
+                depth_bias: -1.0,
+                ..default()
+            })
+            .add_systems(
+                Startup,
+                sys_setup_shard_region_looks.in_set(StartupSysSet::SetupSceneStage1),
+            )
+            .add_systems(OnEnter(AppState::InGame), sys_load_shard_regions_at_startup)
+            .add_systems(
+                Update,
+                (
+                    sys_load_shard_regions,
+                    sys_sync_shard_region_rects,
+                    sys_draw_shard_region_outlines,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                sys_shard_region_labels_ui.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_setup_shard_region_looks(
+    mut looks_r: ResMut<ShardRegionLooks>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    log_system_add_startup::<ShardRegionsPlugin>(StartupSysSet::SetupSceneStage1, fname!());
+    looks_r.mesh = meshes.add(Mesh::from(Plane3d::new(Vec3::Y, Vec2::splat(0.5))));
+    for kind in RegionKind::ALL {
+        let [r, g, b] = region_kind_color(kind);
+        let material = materials.add(StandardMaterial {
+            base_color: Color::srgb_u8(r, g, b).with_alpha(REGION_FILL_ALPHA),
+            unlit: true,
+            alpha_mode: AlphaMode::Blend,
+            cull_mode: None,
+            ..default()
+        });
+        looks_r.materials.insert(kind, material);
+    }
+}
+
+fn sys_load_shard_regions_at_startup(
+    settings_r: Res<Settings>,
+    mut load_writer: EventWriter<LoadShardRegionsEvent>,
+) {
+    let file = settings_r.shard_overlays.regions_file.trim();
+    if !file.is_empty() {
+        load_writer.write(LoadShardRegionsEvent {
+            file: PathBuf::from(file),
+        });
+    }
+}
+
+fn sys_load_shard_regions(mut events: EventReader<LoadShardRegionsEvent>, mut regions_r: ResMut<ShardRegions>) {
+    let Some(LoadShardRegionsEvent { file }) = events.read().last().cloned() else {
+        return;
+    };
+    match shard_regions::load(&file) {
+        Ok(regions) => {
+            logger::one(
+                None,
+                LogSev::Info,
+                LogAbout::ShardData,
+                &format!("Read {} regions from '{}'.", regions.len(), file.display()),
+            );
+            regions_r.file = file;
+            regions_r.regions = regions;
+        }
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::ShardData,
+                &format!("Can't read the shard regions: {e:?}"),
+            );
+        }
+    }
+}
+
+/// Spawns the rectangles of the regions drawn again, when they or the map plane change.
+fn sys_sync_shard_region_rects(
+    mut commands: Commands,
+    regions_r: Res<ShardRegions>,
+    scene_state_data_r: Res<SceneStateData>,
+    map_planes_r: Res<MapPlanesRes>,
+    looks_r: Res<ShardRegionLooks>,
+    rect_q: Query<Entity, With<ShardRegionRect>>,
+) {
+    if !regions_r.is_changed() && !scene_state_data_r.is_changed() {
+        return;
+    }
+    for entity in &rect_q {
+        commands.entity(entity).despawn();
+    }
+    let map_id = scene_state_data_r.map_id;
+    for (index, region) in regions_r.regions.iter().enumerate() {
+        if !regions_r.is_drawn(region, map_id) {
+            continue;
+        }
+        let Some(material) = looks_r.materials.get(&region.kind) else {
+            continue;
+        };
+        for rect in &region.rects {
+            let size = Vec2::new((rect.x2 - rect.x1) as f32, (rect.y2 - rect.y1) as f32);
+            if size.x == 0.0 || size.y == 0.0 {
+                continue;
+            }
+            let center = Vec2::new(rect.x1 as f32, rect.y1 as f32) + size / 2.0;
+            let ground_z =
+                land_cell_at(&map_planes_r, map_id, center.x as u32, center.y as u32).map_or(0, |cell| cell.z);
+            let translation = Vec3::new(
+                center.x,
+                scale_uo_z_to_bevy_units(ground_z as f32) + REGION_GROUND_OFFSET,
+                center.y,
+            );
+            commands.spawn((
+                Mesh3d(looks_r.mesh.clone()),
+                MeshMaterial3d(material.clone()),
+                Transform::from_translation(translation).with_scale(Vec3::new(size.x, 1.0, size.y)),
+                ShardRegionRect { region: index },
+            ));
+        }
+    }
+}
+
+fn sys_draw_shard_region_outlines(
+    regions_r: Res<ShardRegions>,
+    rect_q: Query<(&ShardRegionRect, &Transform)>,
+    mut gizmos: Gizmos<ShardRegionGizmos>,
+) {
+    for (rect, transform) in &rect_q {
+        let Some(region) = regions_r.regions.get(rect.region) else {
+            continue;
+        };
+        let half = transform.scale / 2.0;
+        let center = transform.translation;
+        let corners = [
+            Vec3::new(-half.x, 0.0, -half.z),
+            Vec3::new(half.x, 0.0, -half.z),
+            Vec3::new(half.x, 0.0, half.z),
+            Vec3::new(-half.x, 0.0, half.z),
+            Vec3::new(-half.x, 0.0, -half.z),
+        ];
+        let [r, g, b] = region_kind_color(region.kind);
+        gizmos.linestrip(corners.map(|corner| center + corner), Color::srgb_u8(r, g, b));
+    }
+}
+
+/// The names of the regions drawn.
+fn sys_shard_region_labels_ui(
+    mut egui_ctx: EguiContexts,
+    regions_r: Res<ShardRegions>,
+    rect_q: Query<(&ShardRegionRect, &Transform)>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<PlayerCamera>>,
+) {
+    if !regions_r.show_labels || regions_r.regions.is_empty() {
+        return;
+    }
+    let Ok((camera, camera_transform)) = camera_q.single() else {
+        return;
+    };
+    let Some(viewport) = camera.logical_viewport_rect() else {
+        return;
+    };
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    // Under the windows.
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Background,
+        egui::Id::new("shard_region_labels"),
+    ));
+    let font = egui::FontId::proportional(REGION_LABEL_FONT_SIZE);
+    // A label for each region, on its first rect.
+    let mut labeled = HashSet::new();
+    for (rect, transform) in &rect_q {
+        let Some(region) = regions_r.regions.get(rect.region) else {
+            continue;
+        };
+        if !labeled.insert(rect.region) {
+            continue;
+        }
+        let Ok(pos) = camera.world_to_viewport(camera_transform, transform.translation) else {
+            continue;
+        };
+        if !viewport.contains(pos) {
+            continue;
+        }
+        let pos = egui::pos2(pos.x, pos.y);
+        // With a shadow, to be readable on any terrain.
+        let shadow_and_text = [
+            (egui::vec2(1.0, 1.0), egui::Color32::BLACK),
+            (egui::Vec2::ZERO, egui::Color32::WHITE),
+        ];
+        for (offset, color) in shadow_and_text {
+            painter.text(pos + offset, egui::Align2::CENTER_CENTER, &region.name, font.clone(), color);
+        }
+    }
+}
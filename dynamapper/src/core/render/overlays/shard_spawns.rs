@@ -0,0 +1,167 @@
+// Shard spawns overlay: the spawn points of the shard files (external_data::shard_spawns) drawn over the map, a pin on
+//  each one and a circle of its radius.
+// - Loaded from the Shard Overlays window (render::shard_overlays_ui), or when entering the game from
+//   shard_overlays.spawns_file.
+// - Only the spawns of the current map plane are drawn, and only the ones spawning a creature whose name contains
+//   ShardSpawns.creature_filter, if it isn't empty. They're gizmos drawn over the terrain, at the z of the spawn point.
+// - Hovering the map lists the spawns whose circle is under the cursor in a tooltip (see render::shard_overlays_ui).
+//
+
+use crate::core::render::scene::SceneStateData;
+use crate::external_data::shard_spawns::{self, ShardSpawn};
+use crate::prelude::*;
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+use std::path::PathBuf;
+
+/// World units.
+const SPAWN_PIN_HEIGHT: f32 = 2.0;
+const SPAWN_PIN_COLOR: Color = Color::srgb(1.0, 0.55, 0.1);
+const SPAWN_RADIUS_COLOR: Color = Color::srgba(1.0, 0.55, 0.1, 0.6);
+
+/// Request to draw the spawns of this file, instead of the current ones.
+#[derive(Event, Debug, Clone)]
+pub struct LoadShardSpawnsEvent {
+    pub file: PathBuf,
+}
+
+#[derive(Resource)]
+pub struct ShardSpawns {
+    /// File of the spawns drawn. Empty if none.
+    pub file: PathBuf,
+    pub spawns: Vec<ShardSpawn>,
+    pub visible: bool,
+    /// Only the spawns of the creatures whose name contains it (case insensitive). Empty: all of them.
+    pub creature_filter: String,
+    /// Indices of the spawns drawn: on the current map plane and matching the filter.
+    drawn: Vec<usize>,
+}
+impl Default for ShardSpawns {
+    fn default() -> Self {
+        Self {
+            file: PathBuf::new(),
+            spawns: Vec::new(),
+            visible: true,
+            creature_filter: String::new(),
+            drawn: Vec::new(),
+        }
+    }
+}
+impl ShardSpawns {
+    pub fn drawn(&self) -> impl Iterator<Item = &ShardSpawn> {
+        self.drawn.iter().filter_map(|index| self.spawns.get(*index))
+    }
+
+    pub fn drawn_count(&self) -> usize {
+        self.drawn.len()
+    }
+
+    /// The spawns drawn whose circle covers a tile (its pin, for the spawns without a radius).
+    pub fn drawn_at(&self, x: u16, y: u16) -> impl Iterator<Item = &ShardSpawn> {
+        self.drawn().filter(move |spawn| {
+            let distance = Vec2::new(x as f32 - spawn.x as f32, y as f32 - spawn.y as f32).length();
+            distance <= spawn.radius.max(1) as f32
+        })
+    }
+}
+
+/// Draws the pins and the circles over the terrain.
+#[derive(Default, Reflect, GizmoConfigGroup)]
+struct ShardSpawnGizmos;
+
+pub struct ShardSpawnsPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(ShardSpawnsPlugin);
+impl Plugin for ShardSpawnsPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<ShardSpawns>()
+            .add_event::<LoadShardSpawnsEvent>()
+            .insert_gizmo_config(ShardSpawnGizmos, GizmoConfig {
+                depth_bias: -1.0,
+                ..default()
+            })
+            .add_systems(OnEnter(AppState::InGame), sys_load_shard_spawns_at_startup)
+            .add_systems(
+                Update,
+                (
+                    sys_load_shard_spawns,
+                    sys_update_drawn_shard_spawns,
+                    sys_draw_shard_spawns,
+                )
+                    .chain()
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_load_shard_spawns_at_startup(settings_r: Res<Settings>, mut load_writer: EventWriter<LoadShardSpawnsEvent>) {
+    let file = settings_r.shard_overlays.spawns_file.trim();
+    if !file.is_empty() {
+        load_writer.write(LoadShardSpawnsEvent {
+            file: PathBuf::from(file),
+        });
+    }
+}
+
+fn sys_load_shard_spawns(mut events: EventReader<LoadShardSpawnsEvent>, mut spawns_r: ResMut<ShardSpawns>) {
+    let Some(LoadShardSpawnsEvent { file }) = events.read().last().cloned() else {
+        return;
+    };
+    match shard_spawns::load(&file) {
+        Ok(spawns) => {
+            logger::one(
+                None,
+                LogSev::Info,
+                LogAbout::ShardData,
+                &format!("Read {} spawns from '{}'.", spawns.len(), file.display()),
+            );
+            spawns_r.file = file;
+            spawns_r.spawns = spawns;
+        }
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::ShardData,
+                &format!("Can't read the shard spawns: {e:?}"),
+            );
+        }
+    }
+}
+
+/// Picks the spawns drawn again, when they, their filter or the map plane change.
+fn sys_update_drawn_shard_spawns(mut spawns_r: ResMut<ShardSpawns>, scene_state_data_r: Res<SceneStateData>) {
+    if !spawns_r.is_changed() && !scene_state_data_r.is_changed() {
+        return;
+    }
+    let spawns = spawns_r.bypass_change_detection();
+    let map_id = scene_state_data_r.map_id;
+    let filter = spawns.creature_filter.trim().to_lowercase();
+    spawns.drawn = spawns
+        .spawns
+        .iter()
+        .enumerate()
+        .filter(|(_, spawn)| {
+            spawns.visible
+                && spawn.map as u32 == map_id
+                && (filter.is_empty()
+                    || spawn.creatures.iter().any(|creature| creature.to_lowercase().contains(&filter)))
+        })
+        .map(|(index, _)| index)
+        .collect();
+}
+
+fn sys_draw_shard_spawns(spawns_r: Res<ShardSpawns>, mut gizmos: Gizmos<ShardSpawnGizmos>) {
+    for spawn in spawns_r.drawn() {
+        // On the center of the tile.
+        let pos = UOVec3::new(spawn.x, spawn.y, spawn.z).to_vec3() + Vec3::new(0.5, 0.0, 0.5);
+        gizmos.line(pos, pos + Vec3::Y * SPAWN_PIN_HEIGHT, SPAWN_PIN_COLOR);
+        if spawn.radius > 0 {
+            // Circles are drawn on the XY plane of their isometry: turn it to the ground.
+            let isometry = Isometry3d::new(pos, Quat::from_rotation_x(FRAC_PI_2));
+            gizmos.circle(isometry, spawn.radius as f32, SPAWN_RADIUS_COLOR);
+        }
+    }
+}
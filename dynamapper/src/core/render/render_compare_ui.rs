@@ -0,0 +1,509 @@
+// Render Path Compare (egui window)
+// - Opened from the Terrain Shader Controls window.
+// - Captures what the main view shows through both render paths, for the same viewport: the land chunks through the
+//   land shader (a camera rendering to an image, placed like the main camera without rotation), and the classic 2D
+//   canvas (scene::classic_2d). Both at the classic canvas size: a pixel is a logical pixel at zoom 1.
+// - Shows them side by side, or one over the other with a wipe to drag, and a heat map of the difference of each
+//   pixel: the darker, the closer the land shader is to the classic client.
+// - With the classic 2D render path, the land chunks of the main view are spawned for the capture
+//   (ClassicView::keep_main_chunks), and only its camera draws them.
+// - A capture is a still: capture again after moving.
+//
+
+use crate::core::render::export::MapExportChunk;
+use crate::core::render::scene::{
+    SceneStateData,
+    camera::{PlayerCamera, RenderZoom},
+    classic_2d::{ClassicDrawTask, ClassicView, ClassicViewParams, LandArtCache, spawn_classic_draw},
+    player::Player,
+    world::land::LCMesh,
+};
+use crate::core::uo_files_loader::{ArtRes, MapPlanesRes};
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::render::{
+    camera::RenderTarget,
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    view::{
+        RenderLayers,
+        screenshot::{Screenshot, ScreenshotCaptured},
+    },
+};
+use bevy::tasks::{block_on, poll_once};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+/// Frames to wait after every chunk of the view got its mesh, before capturing it: the render pipelines for the
+///  capture camera are compiled asynchronously.
+const COMPARE_SETTLE_FRAMES: u32 = 10;
+/// Before the export camera.
+const COMPARE_CAMERA_ORDER: isize = -2;
+const COMPARE_BYTES_PER_PIXEL: usize = 4; // RGBA8888
+/// Difference (largest of the r, g, b ones) over which a pixel counts as differing.
+const COMPARE_DIFF_THRESHOLD: u8 = 24;
+/// Difference shown white in the heat map.
+const HEAT_MAP_FULL_SCALE_DIFF: f32 = 128.0;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+enum CompareViewMode {
+    #[default]
+    SideBySide,
+    Wipe,
+    HeatMap,
+}
+
+/// A capture in progress.
+struct CompareCapture {
+    params: ClassicViewParams,
+    camera: Entity,
+    render_target: Handle<Image>,
+    frames_ready: u32,
+    screenshot_requested: bool,
+    classic_task: Option<ClassicDrawTask>,
+    classic_data: Option<Vec<u8>>,
+}
+
+/// The captured images, RGBA8 of the same size, with the heat map.
+struct CompareImages {
+    params: ClassicViewParams,
+    mesh: Vec<u8>,
+    classic: Vec<u8>,
+    heat_map: Vec<u8>,
+    /// Mean difference of the pixels, 0..1.
+    mean_diff: f32,
+    /// Share of the pixels differing by more than COMPARE_DIFF_THRESHOLD, 0..1.
+    differing_share: f32,
+}
+
+/// The captured images, uploaded to egui.
+struct CompareTextures {
+    params: ClassicViewParams,
+    mesh: egui::TextureHandle,
+    classic: egui::TextureHandle,
+    heat_map: egui::TextureHandle,
+    mean_diff: f32,
+    differing_share: f32,
+}
+
+#[derive(Resource)]
+pub struct RenderCompareWindow {
+    pub visible: bool,
+    mode: CompareViewMode,
+    /// Wipe position, 0..1 from the left: the classic canvas is on its left.
+    wipe: f32,
+    capture_requested: bool,
+    capture: Option<CompareCapture>,
+    /// Filled by the screenshot observer.
+    captured_mesh: Option<Image>,
+    /// None while a canvas is drawn.
+    art_cache: Option<LandArtCache>,
+    /// Captured, to upload.
+    images: Option<CompareImages>,
+    textures: Option<CompareTextures>,
+}
+impl Default for RenderCompareWindow {
+    fn default() -> Self {
+        Self {
+            visible: false,
+            mode: CompareViewMode::default(),
+            wipe: 0.5,
+            capture_requested: false,
+            capture: None,
+            captured_mesh: None,
+            art_cache: None,
+            images: None,
+            textures: None,
+        }
+    }
+}
+impl RenderCompareWindow {
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some() || self.capture_requested
+    }
+}
+
+pub struct RenderCompareUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(RenderCompareUiPlugin);
+
+impl Plugin for RenderCompareUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<RenderCompareWindow>()
+            .add_systems(Update, sys_advance_render_compare.run_if(in_state(AppState::InGame)))
+            .add_systems(
+                EguiPrimaryContextPass,
+                render_compare_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// Starts a capture when requested, and collects both images once they're ready.
+fn sys_advance_render_compare(
+    mut commands: Commands,
+    mut window_r: ResMut<RenderCompareWindow>,
+    mut images_r: ResMut<Assets<Image>>,
+    mut classic_view_r: ResMut<ClassicView>,
+    render_zoom_r: Res<RenderZoom>,
+    scene_state_data_r: Res<SceneStateData>,
+    map_planes_r: Res<MapPlanesRes>,
+    art_r: Res<ArtRes>,
+    camera_q: Query<(&Camera, &PlayerCamera, &Projection, &RenderLayers)>,
+    player_q: Query<&Transform, With<Player>>,
+    chunk_q: Query<(&LCMesh, Option<&Mesh3d>), Without<MapExportChunk>>,
+) {
+    let window = window_r.as_mut();
+    if window.capture_requested && window.capture.is_none() {
+        window.capture_requested = false;
+        let (Ok((camera, player_camera, projection, render_layers)), Ok(player_transform)) =
+            (camera_q.single(), player_q.single())
+        else {
+            return;
+        };
+        let Some(params) = ClassicViewParams::of_main_view(
+            camera,
+            player_camera,
+            player_transform.translation,
+            render_zoom_r.0,
+            scene_state_data_r.map_id,
+        ) else {
+            return;
+        };
+        let Some(map_plane) = map_planes_r.get(params.map_id) else {
+            return;
+        };
+        let art_cache = window.art_cache.take().unwrap_or_default();
+        let focus = player_camera.focus(player_transform.translation);
+        let (camera, render_target) = spawn_compare_camera(
+            &mut commands,
+            &mut images_r,
+            &params,
+            projection.clone(),
+            Transform::from_translation(focus + PlayerCamera::BASE_OFFSET_FROM_PLAYER).looking_at(focus, Vec3::Y),
+            render_layers.clone(),
+        );
+        window.capture = Some(CompareCapture {
+            params,
+            camera,
+            render_target,
+            frames_ready: 0,
+            screenshot_requested: false,
+            classic_task: Some(spawn_classic_draw(map_plane, art_r.0.clone(), art_cache, params)),
+            classic_data: None,
+        });
+        window.captured_mesh = None;
+        classic_view_r.keep_main_chunks = true;
+        return;
+    }
+
+    let Some(capture) = window.capture.as_mut() else {
+        return;
+    };
+    // The chunks are the ones of the current map plane.
+    if capture.params.map_id != scene_state_data_r.map_id {
+        logger::one(
+            None,
+            LogSev::Warn,
+            LogAbout::Renderer,
+            &format!(
+                "Render path comparison aborted: left map plane {}.",
+                capture.params.map_id
+            ),
+        );
+        end_capture(&mut commands, &mut images_r, &mut classic_view_r, window);
+        return;
+    }
+
+    if let Some(task) = &mut capture.classic_task
+        && let Some((art_cache, result)) = block_on(poll_once(task))
+    {
+        capture.classic_task = None;
+        window.art_cache = Some(art_cache);
+        match result {
+            Ok(canvas) => capture.classic_data = Some(canvas.data),
+            Err(e) => {
+                logger::one(
+                    None,
+                    LogSev::Error,
+                    LogAbout::Renderer,
+                    &format!("Render path comparison: can't draw the classic 2D canvas: {e}"),
+                );
+                end_capture(&mut commands, &mut images_r, &mut classic_view_r, window);
+                return;
+            }
+        }
+    }
+
+    if !capture.screenshot_requested {
+        let map_id = capture.params.map_id;
+        let all_chunks_built = chunk_q
+            .iter()
+            .filter(|(chunk, _)| chunk.parent_map_id == map_id)
+            .all(|(_, mesh)| mesh.is_some());
+        capture.frames_ready = if all_chunks_built { capture.frames_ready + 1 } else { 0 };
+        if capture.frames_ready >= COMPARE_SETTLE_FRAMES {
+            capture.screenshot_requested = true;
+            commands
+                .spawn(Screenshot::image(capture.render_target.clone()))
+                .observe(
+                    |trigger: Trigger<ScreenshotCaptured>, mut window_r: ResMut<RenderCompareWindow>| {
+                        window_r.captured_mesh = Some(trigger.event().0.clone());
+                    },
+                );
+        }
+        return;
+    }
+
+    let (Some(captured_mesh), Some(_)) = (&window.captured_mesh, &capture.classic_data) else {
+        return;
+    };
+    let params = capture.params;
+    let mesh = match captured_mesh.clone().try_into_dynamic() {
+        Ok(mesh_image) => mesh_image.to_rgba8().into_raw(),
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::Renderer,
+                &format!("Render path comparison: can't read the captured 3D view: {e}"),
+            );
+            end_capture(&mut commands, &mut images_r, &mut classic_view_r, window);
+            return;
+        }
+    };
+    let classic = capture.classic_data.take().unwrap();
+    end_capture(&mut commands, &mut images_r, &mut classic_view_r, window);
+    if mesh.len() != classic.len() {
+        logger::one(
+            None,
+            LogSev::Error,
+            LogAbout::Renderer,
+            "Render path comparison: the captured 3D view doesn't have the size of the canvas.",
+        );
+        return;
+    }
+    let (heat_map, mean_diff, differing_share) = difference_heat_map(&mesh, &classic);
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::Renderer,
+        &format!(
+            "Render path comparison ({}x{}): mean difference {:.1}%, {:.1}% of the pixels differ.",
+            params.width,
+            params.height,
+            mean_diff * 100.0,
+            differing_share * 100.0
+        ),
+    );
+    window.images = Some(CompareImages {
+        params,
+        mesh,
+        classic,
+        heat_map,
+        mean_diff,
+        differing_share,
+    });
+}
+
+/// Spawns the camera rendering the land chunks to an image of the canvas size.
+fn spawn_compare_camera(
+    commands: &mut Commands,
+    images_r: &mut Assets<Image>,
+    params: &ClassicViewParams,
+    projection: Projection,
+    transform: Transform,
+    render_layers: RenderLayers,
+) -> (Entity, Handle<Image>) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: params.width,
+            height: params.height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::RENDER_ATTACHMENT;
+    let render_target = images_r.add(image);
+    // The main camera projection covers the same area at any target size.
+    let camera = commands
+        .spawn((
+            Camera3d::default(),
+            Camera {
+                order: COMPARE_CAMERA_ORDER,
+                target: RenderTarget::Image(render_target.clone().into()),
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+                ..default()
+            },
+            projection,
+            transform,
+            render_layers,
+        ))
+        .id();
+    (camera, render_target)
+}
+
+fn end_capture(
+    commands: &mut Commands,
+    images_r: &mut Assets<Image>,
+    classic_view_r: &mut ClassicView,
+    window: &mut RenderCompareWindow,
+) {
+    let Some(capture) = window.capture.take() else {
+        return;
+    };
+    commands.entity(capture.camera).despawn();
+    images_r.remove(&capture.render_target);
+    window.captured_mesh = None;
+    // A task still drawing is dropped, which cancels it: the art cache starts over (see sys_advance_render_compare).
+    classic_view_r.keep_main_chunks = false;
+}
+
+/// Heat map of the difference between two RGBA8 images of the same size, with the mean difference (0..1) and the
+///  share of the pixels differing by more than COMPARE_DIFF_THRESHOLD.
+pub fn difference_heat_map(a: &[u8], b: &[u8]) -> (Vec<u8>, f32, f32) {
+    let mut heat_map = Vec::with_capacity(a.len());
+    let (mut diff_sum, mut differing) = (0u64, 0usize);
+    for (pa, pb) in a
+        .chunks_exact(COMPARE_BYTES_PER_PIXEL)
+        .zip(b.chunks_exact(COMPARE_BYTES_PER_PIXEL))
+    {
+        let diff = (0..3).map(|i| pa[i].abs_diff(pb[i])).max().unwrap();
+        diff_sum += diff as u64;
+        if diff > COMPARE_DIFF_THRESHOLD {
+            differing += 1;
+        }
+        heat_map.extend_from_slice(&heat_color(diff as f32 / HEAT_MAP_FULL_SCALE_DIFF));
+    }
+    let pixels = (a.len() / COMPARE_BYTES_PER_PIXEL).max(1);
+    (
+        heat_map,
+        diff_sum as f32 / 255.0 / pixels as f32,
+        differing as f32 / pixels as f32,
+    )
+}
+
+/// Black, red, yellow, white from 0 to 1.
+fn heat_color(t: f32) -> [u8; 4] {
+    let t = t.clamp(0.0, 1.0) * 3.0;
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    [channel(t), channel(t - 1.0), channel(t - 2.0), 255]
+}
+
+fn render_compare_ui_system(mut egui_ctx: EguiContexts, mut window_r: ResMut<RenderCompareWindow>) {
+    if !window_r.visible {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let window = window_r.as_mut();
+    if let Some(images) = window.images.take() {
+        let size = [images.params.width as usize, images.params.height as usize];
+        let load = |name: &str, data: &[u8]| {
+            ctx.load_texture(
+                name,
+                egui::ColorImage::from_rgba_unmultiplied(size, data),
+                egui::TextureOptions::NEAREST,
+            )
+        };
+        window.textures = Some(CompareTextures {
+            params: images.params,
+            mesh: load("render_compare_mesh", &images.mesh),
+            classic: load("render_compare_classic", &images.classic),
+            heat_map: load("render_compare_heat_map", &images.heat_map),
+            mean_diff: images.mean_diff,
+            differing_share: images.differing_share,
+        });
+    }
+
+    let capturing = window.is_capturing();
+    egui::Window::new("Render Path Compare")
+        .default_pos([420.0, 80.0])
+        .default_width(720.0)
+        .resizable(true)
+        .open(&mut window.visible)
+        .show(ctx, |ui| {
+            ui.label("The land shader (3D) and the classic 2D art, for the main view without rotation.");
+            ui.horizontal(|ui| {
+                if ui.add_enabled(!capturing, egui::Button::new("Capture")).clicked() {
+                    window.capture_requested = true;
+                }
+                if capturing {
+                    ui.spinner();
+                    ui.label("Capturing...");
+                }
+            });
+            let Some(textures) = &window.textures else {
+                return;
+            };
+            let params = &textures.params;
+            ui.label(format!(
+                "{}x{} around ({:.0}, {:.0}, {:.0}) on map {}.",
+                params.width, params.height, params.center.x, params.center.y, params.center.z, params.map_id
+            ));
+            ui.label(format!(
+                "Mean difference {:.1}%, {:.1}% of the pixels differ by more than {}.",
+                textures.mean_diff * 100.0,
+                textures.differing_share * 100.0,
+                COMPARE_DIFF_THRESHOLD
+            ));
+            ui.horizontal(|ui| {
+                for (label, mode) in [
+                    ("Side by side", CompareViewMode::SideBySide),
+                    ("Wipe", CompareViewMode::Wipe),
+                    ("Heat map", CompareViewMode::HeatMap),
+                ] {
+                    ui.selectable_value(&mut window.mode, mode, label);
+                }
+            });
+            ui.separator();
+
+            let image_size = egui::vec2(params.width as f32, params.height as f32);
+            let fit = |width: f32| image_size * (width / image_size.x).min(1.0);
+            let full_uv = egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(1.0, 1.0));
+            match window.mode {
+                CompareViewMode::SideBySide => {
+                    let size = fit((ui.available_width() - ui.spacing().item_spacing.x) / 2.0);
+                    ui.horizontal(|ui| {
+                        ui.vertical(|ui| {
+                            ui.label("3D");
+                            ui.image((textures.mesh.id(), size));
+                        });
+                        ui.vertical(|ui| {
+                            ui.label("Classic 2D");
+                            ui.image((textures.classic.id(), size));
+                        });
+                    });
+                }
+                CompareViewMode::Wipe => {
+                    ui.label("Classic 2D on the left of the line, 3D on the right: drag to move it.");
+                    let (rect, response) =
+                        ui.allocate_exact_size(fit(ui.available_width()), egui::Sense::click_and_drag());
+                    if let Some(pointer) = response.interact_pointer_pos() {
+                        window.wipe = ((pointer.x - rect.left()) / rect.width()).clamp(0.0, 1.0);
+                    }
+                    let split_x = rect.left() + rect.width() * window.wipe;
+                    let painter = ui.painter_at(rect);
+                    painter.image(textures.mesh.id(), rect, full_uv, egui::Color32::WHITE);
+                    painter.image(
+                        textures.classic.id(),
+                        egui::Rect::from_min_max(rect.min, egui::pos2(split_x, rect.max.y)),
+                        egui::Rect::from_min_max(egui::pos2(0.0, 0.0), egui::pos2(window.wipe, 1.0)),
+                        egui::Color32::WHITE,
+                    );
+                    painter.vline(split_x, rect.y_range(), egui::Stroke::new(2.0, egui::Color32::WHITE));
+                }
+                CompareViewMode::HeatMap => {
+                    ui.label(format!(
+                        "Black: same color. Red, yellow, white: up to {HEAT_MAP_FULL_SCALE_DIFF:.0} (of 255) apart."
+                    ));
+                    ui.image((textures.heat_map.id(), fit(ui.available_width())));
+                }
+            }
+        });
+}
@@ -0,0 +1,613 @@
+use std::path::{Path, PathBuf};
+
+use crate::core::maps::MapPlaneMetadata;
+use crate::core::render::{
+    day_night::WorldClock,
+    export::MapExportChunk,
+    render_compare_ui::difference_heat_map,
+    scene::{
+        SceneStateData,
+        camera::{PlayerCamera, ortho_scaling_mode},
+        player::TeleportPlayerEvent,
+        world::{
+            WorldGeoData,
+            land::{LCDirty, LCMesh, LandChunkSize},
+            statics::LCStaticsDrawn,
+        },
+    },
+};
+use crate::core::system_sets::*;
+use crate::core::uo_files_loader::MapPlanesRes;
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::render::{
+    camera::RenderTarget,
+    render_asset::RenderAssetUsages,
+    render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages},
+    view::{
+        RenderLayers,
+        screenshot::{Screenshot, ScreenshotCaptured},
+    },
+};
+use serde::Deserialize;
+use std::f32::consts::FRAC_PI_2;
+use uocf::geo::map::{MapBlock, MapCell, MapPlane, MapPlaneShared, MapSizeCells};
+
+// Render regression test of the land pipeline: `dynamapper --render-test <case.toml> [--bless]`.
+// - The case file (see render_tests/land_fixture.toml) has a seed, the size of a fixture map, a few viewpoints and the
+//   tolerance of the comparison.
+// - The app starts as usual (with a hidden window), then the map plane the player is on is replaced by the fixture
+//   map, generated from the seed: the same seed always gives the same tiles and heights.
+// - The whole fixture is spawned as land chunks on their own render layer (tagged like the export chunks, so that the
+//   scene leaves them alone), and each viewpoint is rendered by an off-screen camera looking at it like the player
+//   camera does, then read back like the map export pages.
+// - Each capture is compared with the reference image of the viewpoint (see render_compare_ui for the measures). The
+//   failing ones are saved next to it, with a heat map of the differences. With --bless the captures become the new
+//   references instead.
+// - The app then exits: 0 if every viewpoint passed, 1 otherwise.
+// - The references depend on the land art and textures of the UO client, and on the shading settings: bless them
+//   with the installation and settings used to run the test. The world clock is paused, and the fixture has no
+//   animated tiles.
+// - It isn't headless: the hidden window still needs a display and a GPU adapter. tests/render_regression.rs runs it
+//   under cargo test, ignored by default (see there).
+
+const RENDER_TEST_RENDER_LAYER: usize = 5;
+const RENDER_TEST_CAMERA_ORDER: isize = -3;
+/// Frames to wait after every chunk got its mesh, before capturing a viewpoint (see EXPORT_SETTLE_FRAMES).
+const RENDER_TEST_SETTLE_FRAMES: u32 = 10;
+/// A viewpoint not captured after this many frames fails, so that a broken pipeline doesn't hang the test.
+const RENDER_TEST_MAX_WAIT_FRAMES: u32 = 1800;
+
+/// Land tiles of the fixture map, from the lowest to the highest, 4 variants each: sand, grass, dirt, snow. They're in
+///  every client, and none of them is animated.
+const FIXTURE_LAND_TILES: [u16; 4] = [0x0016, 0x0003, 0x0071, 0x011A];
+/// Height (UO units) where each of FIXTURE_LAND_TILES starts.
+const FIXTURE_LAND_TILE_MIN_Z: [i8; 4] = [i8::MIN, 6, 18, 28];
+const FIXTURE_MIN_Z: f32 = -5.0;
+const FIXTURE_MAX_Z: f32 = 40.0;
+/// Side (tiles) of the cells of the coarse and of the fine height noise.
+const FIXTURE_NOISE_CELLS: [u32; 2] = [16, 5];
+/// Weight of the coarse and of the fine height noise.
+const FIXTURE_NOISE_WEIGHTS: [f32; 2] = [0.8, 0.2];
+
+/// Command line of a render test run.
+#[derive(Resource, Clone, Debug)]
+pub struct RenderTestArgs {
+    pub case_path: PathBuf,
+    /// Save the captures as the new references.
+    pub bless: bool,
+}
+impl RenderTestArgs {
+    /// None if the arguments don't ask for a render test.
+    pub fn from_args(mut args: impl Iterator<Item = String>) -> Result<Option<Self>, String> {
+        let (mut case_path, mut bless) = (None, false);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--render-test" => match args.next() {
+                    Some(path) => case_path = Some(PathBuf::from(path)),
+                    None => return Err("--render-test needs the path of a test case file.".to_string()),
+                },
+                "--bless" => bless = true,
+                _ => return Err(format!("Unknown argument: {arg}.")),
+            }
+        }
+        match case_path {
+            Some(case_path) => Ok(Some(Self { case_path, bless })),
+            None if bless => Err("--bless is only meaningful with --render-test.".to_string()),
+            None => Ok(None),
+        }
+    }
+}
+
+/// A render test case, read from a TOML file.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RenderTestCase {
+    pub seed: u64,
+    /// Side of the (square) fixture map, in map blocks.
+    pub size_blocks: u32,
+    pub image_width: u32,
+    pub image_height: u32,
+    /// Highest mean difference (0-1) between a capture and its reference.
+    pub max_mean_difference: f32,
+    /// Highest share (0-1) of pixels differing noticeably from the reference.
+    pub max_differing_share: f32,
+    pub viewpoints: Vec<RenderTestViewpoint>,
+}
+
+/// Where a capture is taken from: looking at a tile like the player camera does.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RenderTestViewpoint {
+    /// Also the file name of its reference image.
+    pub name: String,
+    pub x: u32,
+    pub y: u32,
+    /// See RenderZoom.
+    #[serde(default = "default_viewpoint_zoom")]
+    pub zoom: f32,
+    /// See PlayerCamera::rotation_steps.
+    #[serde(default)]
+    pub rotation_steps: u8,
+}
+
+fn default_viewpoint_zoom() -> f32 {
+    1.0
+}
+
+#[derive(Component)]
+struct RenderTestCamera;
+
+/// Tag component: the land chunks of the fixture map drawn by the test cameras. They're also MapExportChunks.
+#[derive(Component)]
+struct RenderTestChunk;
+
+struct RenderTestCapture {
+    viewpoint: usize,
+    render_target: Handle<Image>,
+    frames_waited: u32,
+    frames_ready: u32,
+    screenshot_requested: bool,
+}
+
+/// State of the render test run.
+#[derive(Resource)]
+struct RenderTest {
+    args: RenderTestArgs,
+    case: RenderTestCase,
+    /// References, and captures of the failing viewpoints.
+    folder: PathBuf,
+    /// The map plane replaced by the fixture map. None until the game starts.
+    map_id: Option<u32>,
+    next_viewpoint: usize,
+    capture: Option<RenderTestCapture>,
+    // Filled by the screenshot observer.
+    captured: Option<Image>,
+    failed: Vec<String>,
+}
+
+pub struct RenderTestPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(RenderTestPlugin);
+
+impl Plugin for RenderTestPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.add_systems(
+            Startup,
+            sys_load_render_test_case
+                .in_set(StartupSysSet::First)
+                .run_if(resource_exists::<RenderTestArgs>),
+        )
+        .add_systems(
+            OnEnter(AppState::InGame),
+            sys_setup_render_test_fixture.run_if(resource_exists::<RenderTest>),
+        )
+        .add_systems(
+            Update,
+            sys_advance_render_test
+                .before(SceneRenderLandSysSet::RenderLandChunks)
+                .run_if(in_state(AppState::InGame).and(resource_exists::<RenderTest>)),
+        );
+    }
+}
+
+fn lg(sev: LogSev, text: &str) {
+    logger::one(None, sev, LogAbout::Renderer, text);
+}
+
+fn sys_load_render_test_case(
+    mut commands: Commands,
+    args_r: Res<RenderTestArgs>,
+    mut exit_writer: EventWriter<AppExit>,
+) {
+    log_system_add_startup::<RenderTestPlugin>(StartupSysSet::First, fname!());
+    let args = args_r.clone();
+    let case = std::fs::read_to_string(&args.case_path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| toml::from_str::<RenderTestCase>(&text).map_err(|e| e.to_string()))
+        .and_then(|case| validate_case(&case).map(|()| case));
+    let case = match case {
+        Ok(case) => case,
+        Err(e) => {
+            lg(
+                LogSev::Error,
+                &format!("Can't read the render test case {:?}: {e}", args.case_path),
+            );
+            exit_writer.write(AppExit::error());
+            return;
+        }
+    };
+    // The references of a case are in a folder named like it, next to it.
+    let folder = args.case_path.with_extension("");
+    lg(
+        LogSev::Info,
+        &format!(
+            "Render test {:?}: {} viewpoints, {}.",
+            args.case_path,
+            case.viewpoints.len(),
+            if args.bless {
+                "saving the references"
+            } else {
+                "comparing with the references"
+            }
+        ),
+    );
+    commands.insert_resource(RenderTest {
+        args,
+        case,
+        folder,
+        map_id: None,
+        next_viewpoint: 0,
+        capture: None,
+        captured: None,
+        failed: Vec::new(),
+    });
+}
+
+fn validate_case(case: &RenderTestCase) -> Result<(), String> {
+    let side_tiles = case.size_blocks * MapBlock::CELLS_PER_ROW;
+    if case.size_blocks == 0 || case.image_width == 0 || case.image_height == 0 {
+        return Err("the fixture map and the images can't be empty.".to_string());
+    }
+    if case.viewpoints.is_empty() {
+        return Err("no viewpoints.".to_string());
+    }
+    for viewpoint in &case.viewpoints {
+        if viewpoint.x >= side_tiles || viewpoint.y >= side_tiles {
+            return Err(format!("viewpoint {} is outside of the fixture map.", viewpoint.name));
+        }
+        if viewpoint.zoom <= 0.0 {
+            return Err(format!("viewpoint {} has no zoom.", viewpoint.name));
+        }
+    }
+    Ok(())
+}
+
+/// Hash of a lattice point of the fixture noise, in 0..1.
+fn fixture_hash(seed: u64, octave: usize, x: u32, y: u32) -> f32 {
+    // splitmix64: the same on every platform, unlike the rand generators, which can change between versions.
+    let mut h = seed ^ ((octave as u64) << 56) ^ ((x as u64) << 28) ^ y as u64;
+    h = h.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    h = (h ^ (h >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    h ^= h >> 31;
+    (h >> 40) as f32 / (1u64 << 24) as f32
+}
+
+/// Land tile of the fixture map at x, y: smoothed value noise for the height, the tile from the height.
+pub fn fixture_cell(seed: u64, x: u32, y: u32) -> MapCell {
+    let mut height = 0.0;
+    for (octave, (&cell, &weight)) in FIXTURE_NOISE_CELLS.iter().zip(&FIXTURE_NOISE_WEIGHTS).enumerate() {
+        let (cx, cy) = (x / cell, y / cell);
+        let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+        let tx = smooth((x % cell) as f32 / cell as f32);
+        let ty = smooth((y % cell) as f32 / cell as f32);
+        let corner = |dx: u32, dy: u32| fixture_hash(seed, octave, cx + dx, cy + dy);
+        let top = corner(0, 0) + (corner(1, 0) - corner(0, 0)) * tx;
+        let bottom = corner(0, 1) + (corner(1, 1) - corner(0, 1)) * tx;
+        height += (top + (bottom - top) * ty) * weight;
+    }
+    let z = (FIXTURE_MIN_Z + height * (FIXTURE_MAX_Z - FIXTURE_MIN_Z)).round() as i8;
+    let band = FIXTURE_LAND_TILE_MIN_Z
+        .iter()
+        .rposition(|&min_z| z >= min_z)
+        .unwrap_or(0);
+    let variant = (fixture_hash(seed, FIXTURE_NOISE_CELLS.len(), x, y) * 4.0) as u16;
+    MapCell {
+        id: FIXTURE_LAND_TILES[band] + variant.min(3),
+        z,
+    }
+}
+
+/// The fixture map as the content of a map file: a square of size_blocks blocks.
+pub fn fixture_map_bytes(seed: u64, size_blocks: u32) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(MapBlock::PACKED_SIZE * (size_blocks * size_blocks) as usize);
+    // The blocks are stored column by column, the cells of a block row by row.
+    for bx in 0..size_blocks {
+        for by in 0..size_blocks {
+            bytes.extend_from_slice(&[0; 4]); // Block header, unused.
+            for cy in 0..MapBlock::CELLS_PER_COLUMN {
+                for cx in 0..MapBlock::CELLS_PER_ROW {
+                    let x = bx * MapBlock::CELLS_PER_ROW + cx;
+                    let y = by * MapBlock::CELLS_PER_COLUMN + cy;
+                    let cell = fixture_cell(seed, x, y);
+                    bytes.extend_from_slice(&cell.id.to_le_bytes());
+                    bytes.push(cell.z as u8);
+                }
+            }
+        }
+    }
+    bytes
+}
+
+/// Puts the fixture map in place of the map plane of the player, and spawns its chunks for the test cameras.
+fn sys_setup_render_test_fixture(
+    mut commands: Commands,
+    mut test_r: ResMut<RenderTest>,
+    map_planes_r: Res<MapPlanesRes>,
+    mut world_geo_data_r: ResMut<WorldGeoData>,
+    scene_state_data_r: Res<SceneStateData>,
+    chunk_size_r: Res<LandChunkSize>,
+    mut clock_r: ResMut<WorldClock>,
+    chunks_q: Query<(Entity, &LCMesh)>,
+    mut teleport_writer: EventWriter<TeleportPlayerEvent>,
+    mut exit_writer: EventWriter<AppExit>,
+) {
+    let map_id = scene_state_data_r.map_id;
+    let case = &test_r.case;
+    let side_tiles = case.size_blocks * MapBlock::CELLS_PER_ROW;
+    let size = MapSizeCells {
+        width: side_tiles,
+        height: side_tiles,
+    };
+    let map_plane = match MapPlane::from_bytes(fixture_map_bytes(case.seed, case.size_blocks), map_id, size) {
+        Ok(map_plane) => map_plane,
+        Err(e) => {
+            lg(LogSev::Error, &format!("Can't build the render test fixture map: {e}"));
+            exit_writer.write(AppExit::error());
+            return;
+        }
+    };
+    map_planes_r.0.insert(map_id, MapPlaneShared::new(map_plane));
+    world_geo_data_r.maps.insert(
+        map_id,
+        MapPlaneMetadata {
+            id: map_id as u8,
+            width: side_tiles,
+            height: side_tiles,
+        },
+    );
+    for (entity, chunk) in chunks_q.iter() {
+        if chunk.parent_map_id == map_id {
+            commands.entity(entity).insert(LCDirty);
+        }
+    }
+    // The lighting must not change between the captures.
+    clock_r.paused = true;
+    // Keep the scene within the fixture map.
+    teleport_writer.write(TeleportPlayerEvent {
+        x: (side_tiles / 2) as u16,
+        y: (side_tiles / 2) as u16,
+        z: None,
+        map_id,
+        from_history: true,
+    });
+
+    let last_chunk = chunk_size_r.chunk_of_tile(side_tiles - 1);
+    for gx in 0..=last_chunk {
+        for gy in 0..=last_chunk {
+            commands.spawn((
+                LCMesh {
+                    parent_map_id: map_id,
+                    gx,
+                    gy,
+                },
+                MapExportChunk,
+                RenderTestChunk,
+                // The fixture map has no statics: the ones of the replaced map plane don't match its land.
+                LCStaticsDrawn,
+                RenderLayers::layer(RENDER_TEST_RENDER_LAYER),
+                Transform::default(),
+                GlobalTransform::default(),
+            ));
+        }
+    }
+    lg(
+        LogSev::Info,
+        &format!(
+            "Render test: map plane {map_id} replaced by a {side_tiles}x{side_tiles} fixture map (seed {}).",
+            test_r.case.seed
+        ),
+    );
+    test_r.map_id = Some(map_id);
+}
+
+fn spawn_render_test_camera(
+    commands: &mut Commands,
+    images_r: &mut Assets<Image>,
+    case: &RenderTestCase,
+    viewpoint: &RenderTestViewpoint,
+) -> Handle<Image> {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: case.image_width,
+            height: case.image_height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::RENDER_ATTACHMENT;
+    let render_target = images_r.add(image);
+
+    // Like the player camera, looking at the land tile of the viewpoint.
+    let z = fixture_cell(case.seed, viewpoint.x, viewpoint.y).z;
+    let focus = Vec3::new(
+        viewpoint.x as f32,
+        scale_uo_z_to_bevy_units(z as f32),
+        viewpoint.y as f32,
+    );
+    let offset = Quat::from_rotation_y((viewpoint.rotation_steps % 4) as f32 * FRAC_PI_2)
+        * PlayerCamera::BASE_OFFSET_FROM_PLAYER;
+    commands.spawn((
+        RenderTestCamera,
+        Camera3d::default(),
+        Camera {
+            order: RENDER_TEST_CAMERA_ORDER,
+            target: RenderTarget::Image(render_target.clone().into()),
+            clear_color: ClearColorConfig::Custom(Color::BLACK),
+            ..default()
+        },
+        Projection::Orthographic(OrthographicProjection {
+            scale: viewpoint.zoom,
+            scaling_mode: ortho_scaling_mode(Vec2::new(case.image_width as f32, case.image_height as f32)),
+            near: -10000.0,
+            far: 10000.0,
+            ..OrthographicProjection::default_3d()
+        }),
+        Transform::from_translation(focus + offset).looking_at(focus, Vec3::Y),
+        RenderLayers::layer(RENDER_TEST_RENDER_LAYER),
+    ));
+    render_target
+}
+
+fn save_png(image: &image::RgbaImage, path: &Path) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    image
+        .save_with_format(path, image::ImageFormat::Png)
+        .map_err(|e| e.to_string())
+}
+
+/// Compares the capture of a viewpoint with its reference (or saves it as the reference). Err tells why it failed.
+fn check_capture(test: &RenderTest, name: &str, capture: image::RgbaImage) -> Result<(), String> {
+    let reference_path = test.folder.join(format!("{name}.png"));
+    if test.args.bless {
+        save_png(&capture, &reference_path).map_err(|e| format!("can't save the reference {reference_path:?}: {e}"))?;
+        lg(
+            LogSev::Info,
+            &format!("Render test: saved the reference {reference_path:?}."),
+        );
+        return Ok(());
+    }
+    let reference = image::open(&reference_path)
+        .map_err(|e| format!("can't read the reference {reference_path:?} (bless it first?): {e}"))?
+        .to_rgba8();
+    if reference.dimensions() != capture.dimensions() {
+        return Err(format!(
+            "the reference is {:?} pixels, the capture {:?}.",
+            reference.dimensions(),
+            capture.dimensions()
+        ));
+    }
+    let (heat_map, mean_difference, differing_share) = difference_heat_map(&capture, &reference);
+    let case = &test.case;
+    if mean_difference <= case.max_mean_difference && differing_share <= case.max_differing_share {
+        lg(
+            LogSev::Info,
+            &format!(
+                "Render test: {name} passed (mean difference {mean_difference:.4}, {:.2}% of the pixels differ).",
+                differing_share * 100.0
+            ),
+        );
+        return Ok(());
+    }
+    let (width, height) = capture.dimensions();
+    let actual_path = test.folder.join(format!("{name}.actual.png"));
+    let diff_path = test.folder.join(format!("{name}.diff.png"));
+    for (image, path) in [
+        (capture, &actual_path),
+        (image::RgbaImage::from_raw(width, height, heat_map).unwrap(), &diff_path),
+    ] {
+        if let Err(e) = save_png(&image, path) {
+            lg(LogSev::Error, &format!("Can't save {path:?}: {e}"));
+        }
+    }
+    Err(format!(
+        "mean difference {mean_difference:.4} (max {}), {:.2}% of the pixels differ (max {:.2}%). See {actual_path:?} \
+         and {diff_path:?}.",
+        case.max_mean_difference,
+        differing_share * 100.0,
+        case.max_differing_share * 100.0
+    ))
+}
+
+/// Test state machine: renders the viewpoints one at a time, checks their captures, then exits.
+fn sys_advance_render_test(
+    mut commands: Commands,
+    mut test_r: ResMut<RenderTest>,
+    mut images_r: ResMut<Assets<Image>>,
+    chunks_q: Query<Option<&Mesh3d>, With<RenderTestChunk>>,
+    camera_q: Query<Entity, With<RenderTestCamera>>,
+    mut exit_writer: EventWriter<AppExit>,
+) {
+    let test = &mut *test_r;
+    if test.map_id.is_none() {
+        return;
+    }
+    let Some(capture) = test.capture.as_mut() else {
+        if test.next_viewpoint < test.case.viewpoints.len() {
+            let viewpoint = &test.case.viewpoints[test.next_viewpoint];
+            let render_target = spawn_render_test_camera(&mut commands, &mut images_r, &test.case, viewpoint);
+            test.capture = Some(RenderTestCapture {
+                viewpoint: test.next_viewpoint,
+                render_target,
+                frames_waited: 0,
+                frames_ready: 0,
+                screenshot_requested: false,
+            });
+            test.next_viewpoint += 1;
+            return;
+        }
+        // Done: once.
+        test.map_id = None;
+        let total = test.case.viewpoints.len();
+        if test.failed.is_empty() {
+            lg(LogSev::Info, &format!("Render test passed: {total} viewpoints."));
+            exit_writer.write(AppExit::Success);
+        } else {
+            lg(
+                LogSev::Error,
+                &format!(
+                    "Render test failed: {} of {total} viewpoints ({}).",
+                    test.failed.len(),
+                    test.failed.join(", ")
+                ),
+            );
+            exit_writer.write(AppExit::error());
+        }
+        return;
+    };
+    let name = test.case.viewpoints[capture.viewpoint].name.clone();
+    let timed_out = capture.frames_waited >= RENDER_TEST_MAX_WAIT_FRAMES;
+
+    let result = if let Some(captured) = test.captured.take() {
+        let result = captured
+            .try_into_dynamic()
+            .map_err(|e| format!("can't read the capture: {e}"))
+            .map(|capture| capture.to_rgba8());
+        Some(result.and_then(|capture| check_capture(test, &name, capture)))
+    } else if timed_out {
+        Some(Err(format!("not rendered after {RENDER_TEST_MAX_WAIT_FRAMES} frames.")))
+    } else {
+        None
+    };
+    if let Some(result) = result {
+        if let Err(e) = result {
+            lg(LogSev::Error, &format!("Render test: {name} failed: {e}"));
+            test.failed.push(name);
+        }
+        for entity in camera_q.iter() {
+            commands.entity(entity).despawn();
+        }
+        let capture = test.capture.take().unwrap();
+        images_r.remove(&capture.render_target);
+        return;
+    }
+
+    let capture = test.capture.as_mut().unwrap();
+    capture.frames_waited += 1;
+    if capture.screenshot_requested {
+        return;
+    }
+    let all_chunks_built = !chunks_q.is_empty() && chunks_q.iter().all(|mesh| mesh.is_some());
+    if !all_chunks_built {
+        return;
+    }
+    capture.frames_ready += 1;
+    if capture.frames_ready < RENDER_TEST_SETTLE_FRAMES {
+        return;
+    }
+    capture.screenshot_requested = true;
+    commands
+        .spawn(Screenshot::image(capture.render_target.clone()))
+        .observe(|trigger: Trigger<ScreenshotCaptured>, mut test_r: ResMut<RenderTest>| {
+            test_r.captured = Some(trigger.event().0.clone());
+        });
+}
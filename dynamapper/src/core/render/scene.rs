@@ -1,18 +1,32 @@
 pub mod camera;
+pub mod classic_2d;
 pub mod dynamic_light;
+pub mod far_view;
+pub mod live_entities;
+pub mod picking;
 pub mod player;
+pub mod split_view;
 pub mod world;
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use crate::core::maps::MapPlaneMetadata;
+use crate::core::render::export::MapExportChunk;
 use crate::core::system_sets::*;
+use crate::core::uo_files_loader::{MapPlanesRes, StaticsPlanesRes};
 use crate::prelude::*;
+use bevy::math::Affine3A;
 use bevy::prelude::*;
-use bevy::window::{Window, WindowResized};
-use camera::{MAX_ZOOM, MIN_ZOOM, RenderZoom, UO_TILE_PIXEL_SIZE};
+use bevy::render::primitives::{Aabb, Frustum};
+use bevy::window::WindowResized;
+use camera::PlayerCamera;
+use classic_2d::ClassicView;
+use far_view::FarView;
 use player::Player;
-use world::land::TILE_NUM_PER_CHUNK_DIM;
+use split_view::{SplitView, SplitViewCamera};
+use uocf::geo::map::{MapBlock, MapBlockRelPos, MapCell, MapPlane, MapPlaneShared, MapRectBlocks, MapSizeBlocks};
+use world::land::LandChunkSize;
+use world::statics::LCStaticsDrawn;
 use world::{WorldGeoData, land};
 
 #[derive(Resource)]
@@ -47,18 +61,35 @@ fn build(&self, app: &mut App) {
             player::PlayerPlugin {
                 registered_by: "ScenePlugin",
             },
+            split_view::SplitViewPlugin {
+                registered_by: "ScenePlugin",
+            },
+            far_view::FarViewPlugin {
+                registered_by: "ScenePlugin",
+            },
+            live_entities::LiveEntitiesPlugin {
+                registered_by: "ScenePlugin",
+            },
+            classic_2d::Classic2dPlugin {
+                registered_by: "ScenePlugin",
+            },
         ))
         .insert_resource(SceneStateData {
             map_id: 0xFFFF, // placeholder
         })
         .add_event::<RecomputeVisibleChunksEvent>()
         .configure_sets(Update, (SceneRenderLandSysSet::SyncLandChunks.after(SceneRenderLandSysSet::ListenSyncRequests),
-    SceneRenderLandSysSet::RenderLandChunks.after(SceneRenderLandSysSet::SyncLandChunks)))
+    SceneRenderLandSysSet::RenderLandChunks.after(SceneRenderLandSysSet::SyncLandChunks),
+    SceneRenderLandSysSet::RenderStatics.after(SceneRenderLandSysSet::SyncLandChunks)))
         .add_systems(
             Startup,
             sys_setup_scene.in_set(StartupSysSet::SetupSceneStage2),
         )
 
+        .add_systems(
+            Update,
+            sys_update_scene_on_settings_changed.in_set(SceneRenderLandSysSet::ListenSyncRequests),
+        )
         .add_systems(
             Update,
             sys_update_worldmap_chunks_to_render
@@ -80,6 +111,16 @@ pub fn sys_setup_scene(
     writer.write(RecomputeVisibleChunksEvent{});
 }
 
+/// Draw distance and padding come from the settings: refresh the visible chunks when they change.
+pub fn sys_update_scene_on_settings_changed(
+    mut settings_events: EventReader<RenderDistanceSettingsChangedEvent>,
+    mut writer: EventWriter<RecomputeVisibleChunksEvent>,
+) {
+    if settings_events.read().last().is_some() {
+        writer.write(RecomputeVisibleChunksEvent{});
+    }
+}
+
 pub fn sys_update_scene_on_window_resize(mut resize_events: EventReader<WindowResized>, mut writer: EventWriter<RecomputeVisibleChunksEvent>) {
     let _event = resize_events.read().last().unwrap();
     writer.write(RecomputeVisibleChunksEvent{});
@@ -103,64 +144,247 @@ fn log_chunk_despawn(gx: u32, gy: u32, map: u32) {
     );
 }
 
-/// Calculates the set of visible chunk coordinates around the player,
-/// sized so that the window is covered, even after padding, based on window size and zoom.
+fn log_chunk_recycle(old: (u32, u32), new: (u32, u32), map: u32) {
+    logger::one(
+        None,
+        LogSev::Debug,
+        LogAbout::RenderWorldLand,
+        &format!("Recycled chunk from: \tgx={}\tgy={}\tto gx={}\tgy={}\t(map={map})", old.0, old.1, new.0, new.1),
+    );
+}
+
+/// Gives an entity to each chunk in missing_chunks. Chunk entities which aren't needed anymore (stale_chunks) are
+///  recycled first: they're moved to the new coordinates and their material is rebuilt in place by the draw system,
+///  avoiding to despawn and respawn them (and to create a new material asset). The stale chunks left are despawned.
+fn recycle_or_spawn_chunks(
+    commands: &mut Commands,
+    map_id: u32,
+    missing_chunks: impl Iterator<Item = (u32, u32)>,
+    stale_chunks: Vec<(Entity, Mut<land::LCMesh>)>,
+) {
+    let mut stale_chunks = stale_chunks.into_iter();
+    for (gx, gy) in missing_chunks {
+        if let Some((entity, mut tcm)) = stale_chunks.next() {
+            log_chunk_recycle((tcm.gx, tcm.gy), (gx, gy), map_id);
+            tcm.gx = gx;
+            tcm.gy = gy;
+            // The statics of the old coordinates have to go, they'll be built again for the new ones.
+            commands
+                .entity(entity)
+                .despawn_related::<Children>()
+                .remove::<LCStaticsDrawn>()
+                .insert(land::LCRecycled);
+            continue;
+        }
+        commands.spawn((
+            land::LCMesh {
+                parent_map_id: map_id,
+                gx,
+                gy,
+            },
+            Transform::default(),
+            GlobalTransform::default(),
+        ));
+        log_chunk_spawn(gx, gy, map_id);
+    }
+    for (entity, tcm) in stale_chunks {
+        commands.entity(entity).despawn();
+        log_chunk_despawn(tcm.gx, tcm.gy, map_id);
+    }
+}
+
+/// Drops from the UO data caches (map and statics blocks) every block far enough from the visible chunks.
+/// We keep an extra margin around the visible area, so that blocks are not reloaded on every small movement.
+fn evict_cached_blocks_outside(
+    map_planes_r: &MapPlanesRes,
+    statics_planes_r: &StaticsPlanesRes,
+    map_id: u32,
+    required_chunks: &HashSet<(u32, u32)>,
+    chunk_size: LandChunkSize,
+) {
+    let margin = MapPlane::EXTRA_BLOCKS_TO_CACHE_PER_SIDE;
+    let keep_rect: MapRectBlocks = if required_chunks.is_empty() {
+        // Nothing visible: drop everything.
+        MapRectBlocks { x0: 0, y0: 0, width: 0, height: 0 }
+    } else {
+        let (min_x, max_x) = required_chunks.iter().fold((u32::MAX, 0), |(lo, hi), c| (lo.min(c.0), hi.max(c.0)));
+        let (min_y, max_y) = required_chunks.iter().fold((u32::MAX, 0), |(lo, hi), c| (lo.min(c.1), hi.max(c.1)));
+        // Chunk coordinates to block coordinates: the last block is the one before the next chunk.
+        let blocks_per_chunk = chunk_size.block_num_dim();
+        let x0 = (min_x * blocks_per_chunk).saturating_sub(margin);
+        let y0 = (min_y * blocks_per_chunk).saturating_sub(margin);
+        MapRectBlocks {
+            x0,
+            y0,
+            width: ((max_x + 1) * blocks_per_chunk + margin) - x0,
+            height: ((max_y + 1) * blocks_per_chunk + margin) - y0,
+        }
+    };
+
+    let mut evicted_map_blocks = 0;
+    if let Some(map_plane) = map_planes_r.get(map_id) {
+        evicted_map_blocks = map_plane.evict_blocks_outside(&keep_rect);
+    }
+    let mut evicted_statics_blocks = 0;
+    if let Some(mut statics_plane) = statics_planes_r.0.get_mut(&map_id) {
+        evicted_statics_blocks = statics_plane.evict_blocks_outside(&keep_rect);
+    }
+    if evicted_map_blocks + evicted_statics_blocks > 0 {
+        logger::one(
+            None,
+            LogSev::Debug,
+            LogAbout::UoFiles,
+            &format!(
+                "Evicted {evicted_map_blocks} map blocks and {evicted_statics_blocks} statics blocks from the cache (map={map_id})."
+            ),
+        );
+    }
+}
+
+/// The camera frustum is updated after our systems run, so it lags one frame behind the camera: enlarge the chunk
+///  bounds a bit, so that chunks scrolling into view are already there.
+const FRUSTUM_CULLING_MARGIN_TILES: f32 = 2.0;
+
+/// Bounds of the chunk (Bevy units), from its lowest to its highest land z.
+/// z_range is None if we don't know the chunk heights: we use the whole UO z range.
+fn chunk_aabb(
+    chunk_size: LandChunkSize,
+    gx: u32,
+    gy: u32,
+    z_range: Option<(i8, i8)>,
+    horizontal_margin: f32,
+) -> Aabb {
+    let chunk_size = chunk_size.0 as f32;
+    let (z_min, z_max) = z_range.unwrap_or((i8::MIN, i8::MAX));
+    // The mesh edges take the height of the neighboring tiles, which can be out of the chunk: add some slack.
+    let slack = FRUSTUM_CULLING_MARGIN_TILES;
+    Aabb::from_min_max(
+        Vec3::new(
+            gx as f32 * chunk_size - horizontal_margin,
+            scale_uo_z_to_bevy_units(z_min as f32) - slack,
+            gy as f32 * chunk_size - horizontal_margin,
+        ),
+        Vec3::new(
+            (gx + 1) as f32 * chunk_size + horizontal_margin,
+            scale_uo_z_to_bevy_units(z_max as f32) + slack,
+            (gy + 1) as f32 * chunk_size + horizontal_margin,
+        ),
+    )
+}
+
+/// Map blocks covered by a chunk.
+fn chunk_blocks(
+    map_size_blocks: MapSizeBlocks,
+    chunk_size: LandChunkSize,
+    gx: u32,
+    gy: u32,
+) -> impl Iterator<Item = MapBlockRelPos> {
+    let chunk_size = chunk_size.0;
+    let bx0 = MapCell::coords_of_parent_block_x(gx * chunk_size);
+    let by0 = MapCell::coords_of_parent_block_y(gy * chunk_size);
+    let bx1 = MapCell::coords_of_parent_block_x((gx + 1) * chunk_size - 1).min(map_size_blocks.width - 1);
+    let by1 = MapCell::coords_of_parent_block_y((gy + 1) * chunk_size - 1).min(map_size_blocks.height - 1);
+    (bx0..=bx1).flat_map(move |x| (by0..=by1).map(move |y| MapBlockRelPos { x, y }))
+}
+
+/// Lowest and highest land z of a chunk, or None if some of its map blocks aren't cached.
+fn chunk_z_range(map_plane: &MapPlane, chunk_size: LandChunkSize, gx: u32, gy: u32) -> Option<(i8, i8)> {
+    chunk_blocks(map_plane.size_blocks, chunk_size, gx, gy).try_fold((i8::MAX, i8::MIN), |(lo, hi), pos| {
+        let (block_lo, block_hi) = map_plane.block(pos)?.z_range();
+        Some((lo.min(block_lo), hi.max(block_hi)))
+    })
+}
+
+/// Calculates the set of chunk coordinates seen by the camera, limited to the chunks within the draw distance from
+///  the player (or from the free camera focus). Padding adds some chunks outside of the visible area on each side.
+/// Chunks are tested against the camera frustum with the whole UO z range first, then with their actual heights
+///  (this needs their map blocks, so we load them only for the chunks passing the first test).
 fn compute_visible_chunks(
     player_pos: Vec3,
-    window_width: f32,
-    window_height: f32,
-    zoom: f32,
+    camera_frustum: &Frustum,
+    map_plane: Option<&MapPlaneShared>,
     map_width: u32,
     map_height: u32,
+    chunk_size: LandChunkSize,
+    render_settings: &SectRender,
 ) -> std::collections::HashSet<(u32, u32)> {
-    let corrected_pixel_size = UO_TILE_PIXEL_SIZE * zoom;
-
-    // Visible tile region (rounded up)
-    let visible_tiles_x = ((window_width / corrected_pixel_size).ceil()) as i32;
-    let visible_tiles_y = ((window_height / corrected_pixel_size).ceil()) as i32;
-
-    // Convert player's position to TILE coordinates
-    let player_tile_x = player_pos.x as i32;
-    let player_tile_y = player_pos.z as i32;
-
-    // Compute chunk region to fully cover the visible area, including all overlapping
-    // Start/end in TILES (not chunks yet)
-
-    let tile_x0 = player_tile_x - visible_tiles_x;
-    let tile_x1 = player_tile_x + (visible_tiles_x / 2);
-    let tile_y0 = player_tile_y - (visible_tiles_y * 3 / 2);
-    let tile_y1 = player_tile_y + (visible_tiles_y / 2);
-
-    // Now convert these to chunk indices (and always round DOWN for min, UP for max)
-    // so that *any partially overlapping chunk is included*.
-    let chunk_size = TILE_NUM_PER_CHUNK_DIM;
-    let chunk_x0 = (tile_x0.div_euclid(chunk_size as i32)).max(0);
-    let chunk_x1 = ((tile_x1 as f32) / chunk_size as f32).ceil() as i32;
-    let chunk_y0 = (tile_y0.div_euclid(chunk_size as i32)).max(0);
-    let chunk_y1 = ((tile_y1 as f32) / chunk_size as f32).ceil() as i32;
-
-    let map_chunks_x = (map_width / chunk_size) as i32;
-    let map_chunks_y = (map_height / chunk_size) as i32;
-
-    let mut set = std::collections::HashSet::new();
-    for gx in chunk_x0..=chunk_x1.min(map_chunks_x - 1) {
-        for gy in chunk_y0..=chunk_y1.min(map_chunks_y - 1) {
-            set.insert((gx as u32, gy as u32));
-        }
+    let player_chunk = (
+        chunk_size.chunk_of_tile(player_pos.x.max(0.0) as u32),
+        chunk_size.chunk_of_tile(player_pos.z.max(0.0) as u32),
+    );
+    let horizontal_margin =
+        FRUSTUM_CULLING_MARGIN_TILES + (render_settings.chunk_padding * chunk_size.0) as f32;
+
+    // The map size isn't always a multiple of the chunk size: the last chunk row/column can be partial.
+    let map_chunks_x = chunk_size.chunks_to_cover(map_width);
+    let map_chunks_y = chunk_size.chunks_to_cover(map_height);
+    // Chunks farther than the draw distance (in chunks, on both axes) from the player chunk are never drawn.
+    let draw_distance = render_settings.draw_distance_chunks;
+    let chunk_x0 = player_chunk.0.saturating_sub(draw_distance);
+    let chunk_x1 = (player_chunk.0 + draw_distance).min(map_chunks_x.saturating_sub(1));
+    let chunk_y0 = player_chunk.1.saturating_sub(draw_distance);
+    let chunk_y1 = (player_chunk.1 + draw_distance).min(map_chunks_y.saturating_sub(1));
+
+    let intersects = |aabb: &Aabb| camera_frustum.intersects_obb(aabb, &Affine3A::IDENTITY, false, false);
+    let candidates: Vec<(u32, u32)> = (chunk_x0..=chunk_x1)
+        .flat_map(|gx| (chunk_y0..=chunk_y1).map(move |gy| (gx, gy)))
+        .filter(|&(gx, gy)| intersects(&chunk_aabb(chunk_size, gx, gy, None, horizontal_margin)))
+        .collect();
+
+    let Some(map_plane) = map_plane else {
+        return candidates.into_iter().collect();
+    };
+    let mut blocks: Vec<MapBlockRelPos> = candidates
+        .iter()
+        .flat_map(|&(gx, gy)| chunk_blocks(map_plane.size_blocks(), chunk_size, gx, gy))
+        .collect();
+    let load_result = {
+        profile_span!("load_map_blocks_for_culling", blocks = blocks.len());
+        map_plane.load_blocks(&mut blocks)
+    };
+    if let Err(e) = load_result {
+        logger::one(
+            None,
+            LogSev::Error,
+            LogAbout::RenderWorldLand,
+            &format!("Can't load the map blocks of the chunks to cull: {e}"),
+        );
     }
-    set
+    let map_plane = map_plane.read();
+    candidates
+        .into_iter()
+        .filter(|&(gx, gy)| {
+            // Without the heights, keep the chunk.
+            let z_range = chunk_z_range(&map_plane, chunk_size, gx, gy);
+            z_range.is_none() || intersects(&chunk_aabb(chunk_size, gx, gy, z_range, horizontal_margin))
+        })
+        .collect()
 }
 
 fn sys_update_worldmap_chunks_to_render(
     mut _event: EventReader<RecomputeVisibleChunksEvent>,
     mut commands: Commands,
     world_geo_data_res: Res<WorldGeoData>,
-    render_zoom_res: Res<RenderZoom>,
     mut scene_state_data_res: ResMut<SceneStateData>,
-    windows_q: Query<&Window>,
+    map_planes_r: Res<MapPlanesRes>,
+    statics_planes_r: Res<StaticsPlanesRes>,
+    settings_r: Res<Settings>,
+    chunk_size_r: Res<LandChunkSize>,
+    split_view_r: Res<SplitView>,
+    far_view_r: Res<FarView>,
+    classic_view_r: Res<ClassicView>,
+    camera_q: Query<(&Frustum, &PlayerCamera)>,
+    split_camera_q: Query<&Frustum, With<SplitViewCamera>>,
+    mut prev_split_map_id: Local<Option<u32>>,
     mut player_q: Query<(&mut Player, &Transform)>,
-    existing_chunks_q: Query<(Entity, &land::LCMesh)>,
+    // Chunks spawned for a map export are managed by the export itself.
+    mut existing_chunks_q: Query<(Entity, &mut land::LCMesh), Without<MapExportChunk>>,
 ) {
+    // Visible chunks are recomputed every frame anyway: the event only tells us something relevant changed.
+    if _event.read().last().is_some() {
+        logger::one(None, LogSev::Debug, LogAbout::RenderWorldLand, "Requested recomputation of the visible chunks.");
+    }
+
     let (mut player_instance, player_transform) =
         player_q.single_mut().expect("More than 1 players?");
     let player_pos: Option<UOVec4> = player_instance.current_pos;
@@ -179,75 +403,111 @@ fn sys_update_worldmap_chunks_to_render(
     // TODO: move the rendered player position to another system, when we'll render more stuff (not only the land chunks).
     player_instance.prev_rendered_pos = Some(player_pos);
 
-    let window: &Window = windows_q.single().unwrap();
-    let zoom: f32 = render_zoom_res.0.clamp(MIN_ZOOM, MAX_ZOOM);
+    let Ok((camera_frustum, player_camera)) = camera_q.single() else {
+        return;
+    };
     //let current_map_id = scene_state_data_res.map_id;
     let new_map_plane_metadata: &MapPlaneMetadata = world_geo_data_res
         .maps
         .get(&new_map_id)
         .expect(&format!("Requested metadata for uncached map {new_map_id}"));
 
-    // Compute correct visible chunk set
-    let required_chunks: HashSet<(u32, u32)> = compute_visible_chunks(
-        player_pos_translation,
-        window.physical_width() as f32,
-        window.physical_height() as f32,
-        zoom,
-        new_map_plane_metadata.width,
-        new_map_plane_metadata.height,
-    );
+    // Compute correct visible chunk set, for each map plane on screen: the player one, and the one of the split view
+    //  (if it's a different plane, or a different place on the same plane).
+    // The free camera can be far from the player: draw around what it looks at.
+    // The far view replaces the chunks of the player plane when zoomed out: they're left hidden as they are. The
+    //  classic 2D render path replaces them altogether.
+    let mut required_by_map: HashMap<u32, HashSet<(u32, u32)>> = HashMap::new();
+    if far_view_r.bypassed_map != Some(new_map_id) && !classic_view_r.replaces_main_chunks() {
+        required_by_map.insert(
+            new_map_id,
+            compute_visible_chunks(
+                player_camera.free_focus.unwrap_or(player_pos_translation),
+                camera_frustum,
+                map_planes_r.get(new_map_id).as_ref(),
+                new_map_plane_metadata.width,
+                new_map_plane_metadata.height,
+                *chunk_size_r,
+                &settings_r.render,
+            ),
+        );
+    }
+    let split_map_id = split_view_r.shown_map();
+    // The split view plane is shown once it's loaded.
+    if let Some(split_map_id) = split_map_id
+        && let Ok(split_camera_frustum) = split_camera_q.single()
+        && let Some(split_map_plane_metadata) = world_geo_data_res.maps.get(&split_map_id)
+    {
+        let split_chunks = compute_visible_chunks(
+            split_view_r.focus,
+            split_camera_frustum,
+            map_planes_r.get(split_map_id).as_ref(),
+            split_map_plane_metadata.width,
+            split_map_plane_metadata.height,
+            *chunk_size_r,
+            &settings_r.render,
+        );
+        required_by_map.entry(split_map_id).or_default().extend(split_chunks);
+    }
+    let split_map_switch = *prev_split_map_id != split_map_id;
+    *prev_split_map_id = split_map_id;
 
-    // If map plane changes, brute-force despawn all and respawn
-    if map_switch {
+    // If map plane changes, hide the chunks of the planes not on screen anymore (they're kept pooled by plane, until
+    //  the plane is unloaded by the MapPlaneManager) and show again the pooled chunks of the new plane.
+    if map_switch || split_map_switch {
+        let msg = if map_switch {
+            "Detected Map Plane change: hide previously rendered land chunks and show/spawn the new ones."
+        } else {
+            "Detected split view Map Plane change: hide previously rendered land chunks and show/spawn the new ones."
+        };
+        logger::one(None, LogSev::Info, LogAbout::RenderWorldLand, msg);
+
+        let mut reused_chunks: usize = 0;
+        for (entity, tcm) in existing_chunks_q.iter() {
+            if !required_by_map.contains_key(&tcm.parent_map_id) {
+                commands.entity(entity).insert(Visibility::Hidden);
+                continue;
+            }
+            commands.entity(entity).insert(Visibility::Inherited);
+            if required_by_map[&tcm.parent_map_id].contains(&(tcm.gx, tcm.gy)) {
+                reused_chunks += 1;
+            }
+        }
         logger::one(
             None,
-            LogSev::Info,
+            LogSev::Debug,
             LogAbout::RenderWorldLand,
-            "Detected Map Plane change: despawn previously rendered land chunks and spawn new ones.",
+            &format!("Reused {reused_chunks} pooled chunks of the map planes on screen."),
         );
-
-        for (entity, tcm) in existing_chunks_q.iter() {
-            commands.entity(entity).despawn();
-            log_chunk_despawn(tcm.gx, tcm.gy, new_map_id);
-        }
-        for &(gx, gy) in required_chunks.iter() {
-            commands.spawn((
-                land::LCMesh {
-                    parent_map_id: new_map_id,
-                    gx,
-                    gy,
-                },
-                Transform::default(),
-                GlobalTransform::default(),
-            ));
-            log_chunk_spawn(gx, gy, new_map_id);
-        }
         scene_state_data_res.map_id = new_map_id;
-        return;
     }
 
-    // Otherwise, incrementally update as before
-    let mut currently_spawned = HashSet::with_capacity(required_chunks.len());
-    for (entity, tcm) in existing_chunks_q.iter() {
-        let coords: (u32, u32) = (tcm.gx, tcm.gy);
-        if required_chunks.contains(&coords) {
-            currently_spawned.insert(coords);
-        } else {
-            commands.entity(entity).despawn();
-            log_chunk_despawn(tcm.gx, tcm.gy, new_map_id);
+    // Then incrementally update each plane on screen.
+    for (&map_id, required_chunks) in &required_by_map {
+        let mut currently_spawned = HashSet::with_capacity(required_chunks.len());
+        let mut stale_chunks = Vec::new();
+        for (entity, tcm) in existing_chunks_q.iter_mut() {
+            // Pooled chunks of other map planes.
+            if tcm.parent_map_id != map_id {
+                continue;
+            }
+            let coords: (u32, u32) = (tcm.gx, tcm.gy);
+            if required_chunks.contains(&coords) {
+                currently_spawned.insert(coords);
+            } else {
+                stale_chunks.push((entity, tcm));
+            }
         }
-    }
-    for coords in required_chunks.difference(&currently_spawned) {
-        let (gx, gy) = *coords;
-        commands.spawn((
-            land::LCMesh {
-                parent_map_id: new_map_id,
-                gx,
-                gy,
-            },
-            Transform::default(),
-            GlobalTransform::default(),
-        ));
-        log_chunk_spawn(gx, gy, new_map_id);
+        // After a switch, blocks of the new plane cached far from here aren't needed anymore. The blocks of the
+        //  previous plane are kept, in case we go back.
+        if map_switch || split_map_switch || !stale_chunks.is_empty() {
+            evict_cached_blocks_outside(&map_planes_r, &statics_planes_r, map_id, required_chunks, *chunk_size_r);
+        }
+        recycle_or_spawn_chunks(
+            &mut commands,
+            map_id,
+            required_chunks.difference(&currently_spawned).copied(),
+            stale_chunks,
+        );
     }
 }
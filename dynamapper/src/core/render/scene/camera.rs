@@ -1,10 +1,19 @@
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::core::render::scene::RecomputeVisibleChunksEvent;
+use crate::core::render::scene::far_view::FAR_VIEW_RENDER_LAYER;
 use crate::core::render::scene::player::Player;
+use crate::core::render::scene::world::land::LandChunkSize;
 use crate::core::system_sets::*;
 use crate::prelude::*;
 use crate::util_lib::math::Between;
+use bevy::input::mouse::{AccumulatedMouseScroll, MouseScrollUnit};
 use bevy::prelude::*;
 use bevy::render::camera::ScalingMode;
-use bevy::window::Window;
+use bevy::window::{PrimaryWindow, Window};
+use std::f32::consts::FRAC_PI_2;
+use bevy::render::view::RenderLayers;
+use bevy_egui::input::EguiWantsInput;
+use bevy_egui::{EguiGlobalSettings, PrimaryEguiContext};
 use crate::external_data::settings::Settings;
 
 pub const UO_TILE_PIXEL_SIZE: f32 = 44.0;
@@ -13,6 +22,16 @@
 pub const DEFAULT_ZOOM: f32 = 1.0;
 pub const MIN_ZOOM: f32 = 0.1;
 pub const MAX_ZOOM: f32 = 6.0;
+/// Max zoom when the far view is enabled (see far_view): past MAX_ZOOM, only the far view is drawn.
+pub const FAR_VIEW_MAX_ZOOM: f32 = 32.0;
+/// Zoom factor of a mouse wheel notch.
+const ZOOM_WHEEL_STEP: f32 = 1.15;
+/// Scroll amount of a mouse wheel notch, for the devices reporting pixels (touchpads).
+const ZOOM_WHEEL_PIXELS_PER_LINE: f32 = 100.0;
+/// Zoom speed of the gamepad right stick and triggers, pushed all the way: (log) zoom change per second.
+const GAMEPAD_ZOOM_RATE: f32 = 1.5;
+/// The smooth zoom stops when the (log) distance from the target zoom is smaller than this.
+const ZOOM_SNAP_EPSILON: f32 = 1e-3;
 
 /* RENDERING MAGIC CONSTANTS */
 /// Magic number found through trial and error with the aim of rendering tiles of same width and height.
@@ -34,6 +53,22 @@
     DESIRED_TILE_PIXEL_SIZE / TILE_SIZE_FACTOR
 };
 
+/// Orthographic area (world units) showing the tiles at their UO pixel size at zoom 1, in a view of the given size
+///  (logical pixels).
+pub fn ortho_scaling_mode(view_size: Vec2) -> ScalingMode {
+    ScalingMode::Fixed {
+        width: view_size.x / ORTHO_SIZE_FACTOR,
+        height: view_size.y / ORTHO_WIDTH_SCALE_FACTOR / ORTHO_SIZE_FACTOR,
+    }
+}
+
+/// Draws the egui windows and the UI overlays over the whole window. The 3D cameras can have a viewport covering only
+///  part of it (see split_view), and egui draws within the viewport of the camera it's attached to.
+#[derive(Component)]
+pub struct UiCamera;
+/// After the 3D cameras.
+const UI_CAMERA_ORDER: isize = 10;
+
 #[derive(Resource, Clone, Copy, Debug)]
 pub struct RenderZoom(pub f32);
 
@@ -44,14 +79,88 @@ fn default() -> Self {
 }
 impl RenderZoom {
     pub fn write_val(&mut self, val: f32) {
-        self.0 = val.clamp(MIN_ZOOM, MAX_ZOOM);
+        self.0 = val.clamp(MIN_ZOOM, FAR_VIEW_MAX_ZOOM);
+    }
+}
+
+/// Zoom RenderZoom is moving toward (see sys_smooth_zoom). RenderZoom is the one in use.
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct ZoomTarget {
+    pub zoom: f32,
+    /// Cursor position (window logical pixels) which stays on the same world point while zooming, if zooming at the
+    ///  cursor. Otherwise, the zoom is centered on the view.
+    pub anchor: Option<Vec2>,
+}
+impl Default for ZoomTarget {
+    fn default() -> Self {
+        Self {
+            zoom: DEFAULT_ZOOM,
+            anchor: None,
+        }
+    }
+}
+impl ZoomTarget {
+    pub fn write_val(&mut self, val: f32, anchor: Option<Vec2>) {
+        self.zoom = val.clamp(MIN_ZOOM, FAR_VIEW_MAX_ZOOM);
+        self.anchor = anchor;
     }
 }
 
 #[derive(Component, Clone, Copy, Debug, Default)]
-pub struct PlayerCamera;
+pub struct PlayerCamera {
+    /// Offset of the point the camera looks at from the player, on the horizontal plane. Zooming at the cursor moves
+    ///  it; it's kept while the player moves.
+    pub focus_offset: Vec3,
+    /// Point the camera looks at in the free camera mode (see controls::free_camera), detached from the player.
+    pub free_focus: Option<Vec3>,
+    /// View rotation around the vertical axis, in quarter turns (0-3) counterclockwise seen from above.
+    pub rotation_steps: u8,
+}
 impl PlayerCamera {
+    /// Camera position from the point it looks at, without rotation.
     pub const BASE_OFFSET_FROM_PLAYER: Vec3 = Vec3::new(5.0, 5.0, 5.0);
+
+    /// Camera position from the point it looks at: the oblique view, rotated.
+    pub fn offset_from_focus(&self) -> Vec3 {
+        Quat::from_rotation_y(self.rotation_steps as f32 * FRAC_PI_2) * Self::BASE_OFFSET_FROM_PLAYER
+    }
+
+    /// Turns the view by quarter turns, counterclockwise if positive.
+    pub fn rotate(&mut self, quarter_turns: i32) {
+        self.rotation_steps = (self.rotation_steps as i32 + quarter_turns).rem_euclid(4) as u8;
+    }
+
+    /// Map direction (x, y) which looks on screen like the given one does without rotation, so that the movement
+    ///  keys keep their screen direction.
+    pub fn rotate_map_direction(&self, dir: IVec2) -> IVec2 {
+        // A quarter turn counterclockwise (seen from above) takes +x to -z, that is the map y.
+        (0..self.rotation_steps).fold(dir, |dir, _| IVec2::new(dir.y, -dir.x))
+    }
+
+    pub fn is_free(&self) -> bool {
+        self.free_focus.is_some()
+    }
+
+    /// Point the camera looks at.
+    pub fn focus(&self, player_translation: Vec3) -> Vec3 {
+        self.free_focus.unwrap_or(player_translation + self.focus_offset)
+    }
+
+    /// Moves the point the camera looks at, on the horizontal plane. When following the player, it stays within
+    ///  max_offset from it.
+    pub fn move_focus(&mut self, delta: Vec3, max_offset: f32) {
+        let delta = delta.with_y(0.0);
+        match &mut self.free_focus {
+            Some(free_focus) => *free_focus += delta,
+            None => self.focus_offset = (self.focus_offset + delta).clamp_length_max(max_offset),
+        }
+    }
+
+    /// Back to following the player, centered on it.
+    pub fn snap_to_player(&mut self) {
+        self.free_focus = None;
+        self.focus_offset = Vec3::ZERO;
+    }
 }
 
 pub struct CameraPlugin {
@@ -67,10 +176,22 @@ fn build(&self, app: &mut App) {
             sys_setup_cam.in_set(StartupSysSet::SetupSceneStage1),
         )
         .insert_resource(RenderZoom::default())
-        .add_systems(Update, sys_update_camera_projection_to_view)
+        .init_resource::<ZoomTarget>()
+        .add_systems(
+            Update,
+            sys_update_camera_projection_to_view.after(MovementSysSet::UpdateCamera),
+        )
         .add_systems(
             Update,
-            sys_camera_follow_player.in_set(MovementSysSet::UpdateCamera),
+            (
+                sys_rotate_camera_input,
+                sys_zoom_wheel_input,
+                sys_zoom_gamepad_input,
+                sys_smooth_zoom,
+                sys_camera_follow_player,
+            )
+                .chain()
+                .in_set(MovementSysSet::UpdateCamera),
         );
     }
 }
@@ -80,18 +201,11 @@ fn sys_setup_cam(
     windows: Query<&Window>,
     render_zoom: Res<RenderZoom>,
     settings: Res<Settings>,
+    mut egui_global_settings_r: ResMut<EguiGlobalSettings>,
 ) {
     let main_window = windows.single().unwrap();
-    let window_width = main_window.resolution.width() as f32;
-    let window_height = main_window.resolution.height() as f32 / ORTHO_WIDTH_SCALE_FACTOR;
     let zoom = render_zoom.0;
-    assert!(zoom.between(MIN_ZOOM, MAX_ZOOM));
-
-    // Compute the orthographic width/height (world units) so that visible tiles fill the window at tile size/zoom.
-    // How many world units can fit horizontally & vertically?
-    let ortho_width = window_width / ORTHO_SIZE_FACTOR;
-    let ortho_height = window_height / ORTHO_SIZE_FACTOR;
-    //println!("Ortographic camera width={ortho_width}, height={ortho_height}");
+    assert!(zoom.between(MIN_ZOOM, FAR_VIEW_MAX_ZOOM));
 
     // Find player start position for focus (if needed).
     let player_start_pos: Vec3 = settings.world.start_p.to_bevy_vec3_ignore_map();
@@ -103,10 +217,7 @@ fn sys_setup_cam(
         Projection::Orthographic(OrthographicProjection {
             // NOTE: You control zoom by adjusting .scale (or by adjusting orthographic width/height).
             scale: 1.0 * zoom,
-            scaling_mode: ScalingMode::Fixed {
-                width: ortho_width,
-                height: ortho_height,
-            },
+            scaling_mode: ortho_scaling_mode(main_window.resolution.size()),
             near: -10000.0,
             far: 10000.0,
             ..OrthographicProjection::default_3d()
@@ -114,11 +225,47 @@ fn sys_setup_cam(
         Transform::from_translation(player_start_pos + PlayerCamera::BASE_OFFSET_FROM_PLAYER)
             .looking_at(player_start_pos, Vec3::Y),
         GlobalTransform::default(),
+        // The far view is drawn only by the main camera.
+        RenderLayers::default().with(FAR_VIEW_RENDER_LAYER),
+    ));
+
+    // Draws over what the 3D cameras drew, without clearing it. It renders no entity by itself.
+    egui_global_settings_r.auto_create_primary_context = false;
+    commands.spawn((
+        UiCamera,
+        Camera2d,
+        Camera {
+            order: UI_CAMERA_ORDER,
+            clear_color: ClearColorConfig::None,
+            ..default()
+        },
+        RenderLayers::none(),
+        PrimaryEguiContext,
     ));
 
     logger::one(None, LogSev::Debug, LogAbout::Camera, "Spawned.");
 }
 
+//------------------------------------
+// Picking
+//------------------------------------
+
+/// Converts a cursor position (window logical pixels) into the point where the camera ray crosses the horizontal
+///  plane at the given height (Bevy units). Returns None if the ray can't be computed or doesn't cross the plane.
+pub fn cursor_to_world_on_plane(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    cursor_pos: Vec2,
+    plane_height: f32,
+) -> Option<Vec3> {
+    let ray = camera.viewport_to_world(camera_transform, cursor_pos).ok()?;
+    let distance = ray.intersect_plane(
+        Vec3::new(0.0, plane_height, 0.0),
+        InfinitePlane3d::new(Vec3::Y),
+    )?;
+    Some(ray.get_point(distance))
+}
+
 //------------------------------------
 // World light
 //------------------------------------
@@ -138,41 +285,175 @@ fn sys_setup_cam(
 */
 
 fn sys_update_camera_projection_to_view(
-    mut camera_q: Query<&mut Projection, With<Camera3d>>,
+    mut camera_q: Query<(&Camera, &mut Projection), With<PlayerCamera>>,
     windows: Query<&Window>,
     render_zoom: Res<RenderZoom>,
 ) {
     let main_window = windows.single().unwrap();
-    let window_width = main_window.resolution.width() as f32;
-    let window_height = main_window.resolution.height() as f32 / ORTHO_WIDTH_SCALE_FACTOR;
     let zoom = render_zoom.0;
-    assert!(zoom.between(MIN_ZOOM, MAX_ZOOM));
+    assert!(zoom.between(MIN_ZOOM, FAR_VIEW_MAX_ZOOM));
 
-    // Compute the orthographic width/height (world units) so that visible tiles fill the window at tile size/zoom.
-    // How many world units can fit horizontally & vertically?
-    let ortho_width = window_width / ORTHO_SIZE_FACTOR;
-    let ortho_height = window_height / ORTHO_SIZE_FACTOR;
-
-    let mut proj = camera_q.single_mut().unwrap();
+    let (camera, mut proj) = camera_q.single_mut().unwrap();
+    // The split view leaves only part of the window to the camera.
+    let view_size = camera.viewport.as_ref().map_or(main_window.resolution.size(), |viewport| {
+        viewport.physical_size.as_vec2() / main_window.resolution.scale_factor()
+    });
     if let Projection::Orthographic(ref mut ortho) = *proj {
-        ortho.scaling_mode = ScalingMode::Fixed {
-            width: ortho_width,
-            height: ortho_height,
-        };
+        ortho.scaling_mode = ortho_scaling_mode(view_size);
         ortho.scale = 1.0 * zoom;
     }
 }
 
+//------------------------------------
+// Zoom
+//------------------------------------
+
+/// Max zoom of the main view: zooming out past MAX_ZOOM needs the far view, since the land chunks are drawn only
+///  within the draw distance.
+pub fn max_zoom(render_settings: &SectRender) -> f32 {
+    if render_settings.far_view_zoom > 0.0 { FAR_VIEW_MAX_ZOOM } else { MAX_ZOOM }
+}
+
+/// Projection scale multiplier for the mouse wheel scrolled this frame. Scrolling up zooms in: a smaller scale.
+pub fn wheel_zoom_factor(mouse_scroll: &AccumulatedMouseScroll) -> f32 {
+    let notches = match mouse_scroll.unit {
+        MouseScrollUnit::Line => mouse_scroll.delta.y,
+        MouseScrollUnit::Pixel => mouse_scroll.delta.y / ZOOM_WHEEL_PIXELS_PER_LINE,
+    };
+    ZOOM_WHEEL_STEP.powf(-notches)
+}
+
+/// The mouse wheel zooms in and out, at the cursor if window.zoom_to_cursor is set.
+fn sys_zoom_wheel_input(
+    mouse_scroll_r: Res<AccumulatedMouseScroll>,
+    egui_wants_input_r: Res<EguiWantsInput>,
+    settings_r: Res<Settings>,
+    windows_q: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<&Camera, With<PlayerCamera>>,
+    mut zoom_target_r: ResMut<ZoomTarget>,
+) {
+    if mouse_scroll_r.delta.y == 0.0 || egui_wants_input_r.wants_pointer_input() {
+        return;
+    }
+    // Over the split view, which has its own zoom.
+    let cursor = windows_q.single().ok().and_then(Window::cursor_position);
+    if let (Some(cursor), Ok(camera)) = (cursor, camera_q.single())
+        && camera.logical_viewport_rect().is_some_and(|rect| !rect.contains(cursor))
+    {
+        return;
+    }
+    let zoom = (zoom_target_r.zoom * wheel_zoom_factor(&mouse_scroll_r)).min(max_zoom(&settings_r.render));
+    let anchor = cursor.filter(|_| settings_r.window.zoom_to_cursor);
+    zoom_target_r.write_val(zoom, anchor);
+}
+
+/// The gamepad right stick (up zooms in) and triggers (the right one zooms in, the left one out) zoom on the view
+///  center, at a speed following how far they are pushed.
+fn sys_zoom_gamepad_input(
+    time_r: Res<Time>,
+    action_input: ActionInput,
+    settings_r: Res<Settings>,
+    mut zoom_target_r: ResMut<ZoomTarget>,
+) {
+    let zoom_in = action_input.right_stick().y + action_input.analog_button(GamepadButton::RightTrigger2)
+        - action_input.analog_button(GamepadButton::LeftTrigger2);
+    if zoom_in == 0.0 {
+        return;
+    }
+    let factor = (-GAMEPAD_ZOOM_RATE * zoom_in * time_r.delta_secs()).exp();
+    let zoom = (zoom_target_r.zoom * factor).min(max_zoom(&settings_r.render));
+    zoom_target_r.write_val(zoom, None);
+}
+
+/// Moves RenderZoom toward the target zoom, exponentially (window.zoom_smoothing per second, 0 = instantly).
+/// When zooming at the cursor, the camera focus moves too, so that the world point under the cursor stays there.
+fn sys_smooth_zoom(
+    time_r: Res<Time>,
+    settings_r: Res<Settings>,
+    chunk_size_r: Res<LandChunkSize>,
+    zoom_target_r: Res<ZoomTarget>,
+    mut render_zoom_r: ResMut<RenderZoom>,
+    mut camera_q: Query<(&Camera, &GlobalTransform, &mut PlayerCamera)>,
+    player_q: Query<&Transform, With<Player>>,
+) {
+    let (old_zoom, target_zoom) = (render_zoom_r.0, zoom_target_r.zoom);
+    if old_zoom == target_zoom {
+        return;
+    }
+    // Interpolated in log space, so that zooming in and out look alike.
+    let (old_log, target_log) = (old_zoom.ln(), target_zoom.ln());
+    let smoothing = settings_r.window.zoom_smoothing;
+    let new_zoom = if smoothing <= 0.0 || (target_log - old_log).abs() < ZOOM_SNAP_EPSILON {
+        target_zoom
+    } else {
+        (old_log + (target_log - old_log) * (1.0 - (-smoothing * time_r.delta_secs()).exp())).exp()
+    };
+    render_zoom_r.write_val(new_zoom);
+
+    let (Some(anchor), Ok((camera, camera_transform, mut player_camera)), Ok(player_transform)) =
+        (zoom_target_r.anchor, camera_q.single_mut(), player_q.single())
+    else {
+        return;
+    };
+    let Some(viewport_center) = camera.logical_viewport_size().map(|size| size / 2.0) else {
+        return;
+    };
+    // The orthographic projection is scaled around the view center: scale the distance of the anchored point from
+    //  the center the other way. Both are taken on the plane of the player, with the projection still at the old zoom.
+    let plane_height = player_transform.translation.y;
+    let (Some(anchor_world), Some(center_world)) = (
+        cursor_to_world_on_plane(camera, camera_transform, anchor, plane_height),
+        cursor_to_world_on_plane(camera, camera_transform, viewport_center, plane_height),
+    ) else {
+        return;
+    };
+    let scale = render_zoom_r.0 / old_zoom;
+    let new_center_world = anchor_world + (center_world - anchor_world) * scale;
+    // Not farther from the player than the drawn chunks.
+    let max_offset = (settings_r.render.draw_distance_chunks * chunk_size_r.0) as f32;
+    player_camera.move_focus(new_center_world - center_world, max_offset);
+}
+
 fn sys_camera_follow_player(
-    mut camera_q: Query<&mut Transform, (With<Camera3d>, Without<Player>)>,
-    player_q: Query<&Transform, (With<Player>, Without<Camera3d>)>,
+    mut camera_q: Query<(&mut Transform, &PlayerCamera), Without<Player>>,
+    player_q: Query<&Transform, (With<Player>, Without<PlayerCamera>)>,
 ) {
-    let mut camera_transform = camera_q.single_mut().unwrap();
+    let (mut camera_transform, player_camera) = camera_q.single_mut().unwrap();
     let player_transform = player_q.single().unwrap();
 
-    *camera_transform = Transform::from_translation(
-        player_transform.translation.clone() + PlayerCamera::BASE_OFFSET_FROM_PLAYER,
-    )
-    .looking_at(player_transform.translation, Vec3::Y);
+    let focus = player_camera.focus(player_transform.translation);
+    *camera_transform =
+        Transform::from_translation(focus + player_camera.offset_from_focus()).looking_at(focus, Vec3::Y);
+}
+
+//------------------------------------
+// Rotation
+//------------------------------------
+
+/// Turns the view by 90° (InputAction::RotateCameraLeft/Right) around the point the camera looks at.
+fn sys_rotate_camera_input(
+    action_input: ActionInput,
+    mut camera_q: Query<&mut PlayerCamera>,
+    mut recompute_writer: EventWriter<RecomputeVisibleChunksEvent>,
+) {
+    let quarter_turns = if action_input.just_pressed(InputAction::RotateCameraLeft) {
+        -1
+    } else if action_input.just_pressed(InputAction::RotateCameraRight) {
+        1
+    } else {
+        return;
+    };
+    let Ok(mut player_camera) = camera_q.single_mut() else {
+        return;
+    };
+    player_camera.rotate(quarter_turns);
+    // The frustum turns too: other chunks come into view.
+    recompute_writer.write(RecomputeVisibleChunksEvent);
+    logger::one(
+        None,
+        LogSev::Debug,
+        LogAbout::Camera,
+        &format!("View rotated by {}°.", player_camera.rotation_steps as u32 * 90),
+    );
 }
 
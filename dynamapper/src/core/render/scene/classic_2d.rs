@@ -0,0 +1,551 @@
+use crate::core::map_editor::LandCellsEditedEvent;
+use crate::core::render::scene::SceneStateData;
+use crate::core::render::scene::camera::{PlayerCamera, RenderZoom};
+use crate::core::render::scene::player::Player;
+use crate::core::render::scene::split_view::is_viewport;
+use crate::core::system_sets::*;
+use crate::core::uo_files_loader::{ArtRes, MapPlanesRes};
+use crate::prelude::*;
+use bevy::platform::time::Instant;
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    prelude::*,
+    render::{
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        view::RenderLayers,
+    },
+    tasks::{AsyncComputeTaskPool, Task, block_on, poll_once},
+};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use uocf::art::Art;
+use uocf::geo::map::{MapBlock, MapBlockRelPos, MapCell, MapPlaneShared};
+
+// Classic 2D render path (render.render_path = "classic_2d"): the main view draws the land art (art.mul) like the
+//  classic client, instead of the 3D land chunks. Pixel-faithful to the original, and a reference to compare the land
+//  shader against.
+// - The canvas is drawn on the CPU, in background: the 44x44 diamond of each land tile, from the back rows (lower
+//   x + y) to the front ones, with its top corner at ((x - y) * 22, (x + y) * 22 - z * 4) from the view center.
+//   Flat tiles are copied pixel by pixel. The sloped ones have their art stretched over the heights of their 4
+//   corners, as the land mesh does (the client draws the texmap there, instead).
+// - It's redrawn when the view moves, is resized or zoomed, or its map plane is edited. A pixel is a logical pixel at
+//   zoom 1, and the canvas is scaled to the view without filtering. Zoomed out, the canvas stops growing at
+//   CLASSIC_2D_MAX_CANVAS_SIDE: it covers less of the map.
+// - The main camera is turned off, and the land chunks of the main view aren't spawned, unless the render path
+//   comparison needs them (see sys_update_worldmap_chunks_to_render). A 2D camera, with the main camera viewport,
+//   shows the canvas. Only the land is drawn, and the view rotation doesn't apply. The split view still uses the 3D
+//   path.
+// - Chosen at startup: the main camera and the chunks aren't set up again when it changes.
+
+/// The default layer is the main view one, the export uses layer 1, the split view layer 2 and the far view layer 3.
+pub const CLASSIC_2D_RENDER_LAYER: usize = 4;
+/// Max canvas side (pixels). Safe for the downlevel GPUs too.
+const CLASSIC_2D_MAX_CANVAS_SIDE: u32 = 4096;
+const CLASSIC_2D_BYTES_PER_PIXEL: usize = 4; // RGBA8888
+/// Half the side of a land art diamond: a tile step is this many pixels across and down.
+const TILE_HALF_PIXELS: i32 = Art::LAND_ART_SIZE as i32 / 2;
+/// Pixels a unit of height lifts a tile.
+const Z_PIXELS: i32 = 4;
+/// Most pixels a tile can be moved up or down by its height, from the center height.
+const Z_MAX_SHIFT_PIXELS: i32 = (i8::MAX as i32 - i8::MIN as i32) * Z_PIXELS;
+
+pub struct Classic2dPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(Classic2dPlugin);
+
+impl Plugin for Classic2dPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<ClassicView>()
+            .add_systems(Startup, sys_setup_classic_view.in_set(StartupSysSet::SetupSceneStage2))
+            .add_systems(
+                Update,
+                (sys_sync_classic_camera, sys_draw_classic_view)
+                    .chain()
+                    .after(MovementSysSet::UpdateCamera)
+                    .run_if(in_state(AppState::InGame).and(classic_view_enabled)),
+            );
+    }
+}
+
+/// Tag component: the camera showing the classic 2D canvas.
+#[derive(Component)]
+pub struct ClassicViewCamera;
+
+/// Tag component: the sprite with the classic 2D canvas.
+#[derive(Component)]
+pub struct ClassicViewSprite;
+
+/// What the classic view shows.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ClassicViewParams {
+    pub map_id: u32,
+    /// Map point at the center of the canvas: x and y in tiles, z in UO height units.
+    pub center: Vec3,
+    /// Canvas size, in pixels.
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A drawn classic 2D canvas.
+pub struct ClassicCanvas {
+    pub params: ClassicViewParams,
+    /// RGBA8, sRGB.
+    pub data: Vec<u8>,
+}
+
+/// Decoded land art, by tile id. None if the tile has no art.
+#[derive(Default)]
+pub struct LandArtCache(HashMap<u16, Option<Vec<u8>>>);
+impl LandArtCache {
+    fn get(&mut self, art: &Art, id: u16) -> Option<&[u8]> {
+        self.0
+            .entry(id)
+            .or_insert_with(|| match art.land(id) {
+                Ok(element) => element.map(|element| element.pixel_data().clone()),
+                Err(e) => {
+                    logger::one(
+                        None,
+                        LogSev::Warn,
+                        LogAbout::Renderer,
+                        &format!("Classic 2D: can't read the land art 0x{id:04X}: {e}"),
+                    );
+                    None
+                }
+            })
+            .as_deref()
+    }
+}
+
+/// The drawing task gets the art cache, and gives it back with the canvas.
+pub type ClassicDrawTask = Task<(LandArtCache, uocf::errors::Result<ClassicCanvas>)>;
+
+#[derive(Resource, Default)]
+pub struct ClassicView {
+    /// The main view uses the classic 2D render path.
+    pub enabled: bool,
+    /// Spawn the land chunks of the main view anyway, for another camera (see render_compare_ui).
+    pub keep_main_chunks: bool,
+    /// The canvas shown, and what it shows.
+    pub image: Handle<Image>,
+    pub shown: Option<ClassicViewParams>,
+    /// The map plane shown was edited after drawing it.
+    stale: bool,
+    /// None while a canvas is drawn.
+    art_cache: Option<LandArtCache>,
+    task: Option<ClassicDrawTask>,
+}
+
+impl ClassicView {
+    /// The land chunks of the main view aren't needed.
+    pub fn replaces_main_chunks(&self) -> bool {
+        self.enabled && !self.keep_main_chunks
+    }
+}
+
+fn classic_view_enabled(classic_view_r: Res<ClassicView>) -> bool {
+    classic_view_r.enabled
+}
+
+fn sys_setup_classic_view(
+    mut commands: Commands,
+    mut classic_view_r: ResMut<ClassicView>,
+    mut images_r: ResMut<Assets<Image>>,
+    settings_r: Res<Settings>,
+) {
+    log_system_add_startup::<Classic2dPlugin>(StartupSysSet::SetupSceneStage2, fname!());
+    if settings_r.render.render_path != RenderPath::Classic2d {
+        return;
+    }
+
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: 1,
+            height: 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 255],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::nearest();
+    let image = images_r.add(image);
+    commands.spawn((
+        ClassicViewCamera,
+        Camera2d,
+        Camera {
+            // In place of the main camera, which is turned off.
+            order: 0,
+            ..default()
+        },
+        RenderLayers::layer(CLASSIC_2D_RENDER_LAYER),
+    ));
+    commands.spawn((
+        ClassicViewSprite,
+        Sprite::from_image(image.clone()),
+        Transform::default(),
+        RenderLayers::layer(CLASSIC_2D_RENDER_LAYER),
+    ));
+    *classic_view_r = ClassicView {
+        enabled: true,
+        image,
+        ..default()
+    };
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::Renderer,
+        "Main view: using the classic 2D render path.",
+    );
+}
+
+/// Keeps the main camera off, and the classic camera and its sprite where the main view is.
+fn sys_sync_classic_camera(
+    mut main_camera_q: Query<&mut Camera, (With<PlayerCamera>, Without<ClassicViewCamera>)>,
+    mut classic_camera_q: Query<&mut Camera, With<ClassicViewCamera>>,
+    mut sprite_q: Query<&mut Sprite, With<ClassicViewSprite>>,
+) {
+    let (Ok(mut main_camera), Ok(mut classic_camera)) = (main_camera_q.single_mut(), classic_camera_q.single_mut())
+    else {
+        return;
+    };
+    if main_camera.is_active {
+        main_camera.is_active = false;
+    }
+    let same_viewport = match &main_camera.viewport {
+        Some(viewport) => is_viewport(classic_camera.viewport.as_ref(), viewport),
+        None => classic_camera.viewport.is_none(),
+    };
+    if !same_viewport {
+        classic_camera.viewport = main_camera.viewport.clone();
+    }
+    if let Some(view_size) = main_camera.logical_viewport_size()
+        && let Ok(mut sprite) = sprite_q.single_mut()
+        && sprite.custom_size != Some(view_size)
+    {
+        sprite.custom_size = Some(view_size);
+    }
+}
+
+/// Size of the canvas for a view of the given size (logical pixels) at the given zoom.
+fn canvas_size(view_size: Vec2, zoom: f32) -> (u32, u32) {
+    let size = view_size * zoom;
+    let size = size * (CLASSIC_2D_MAX_CANVAS_SIDE as f32 / size.max_element()).min(1.0);
+    (size.x.round().max(1.0) as u32, size.y.round().max(1.0) as u32)
+}
+
+impl ClassicViewParams {
+    /// The classic view of what the main camera looks at. None before its viewport is known.
+    pub fn of_main_view(
+        camera: &Camera,
+        player_camera: &PlayerCamera,
+        player_translation: Vec3,
+        zoom: f32,
+        map_id: u32,
+    ) -> Option<Self> {
+        let view_size = camera.logical_viewport_size()?;
+        let focus = player_camera.focus(player_translation);
+        let (width, height) = canvas_size(view_size, zoom);
+        Some(Self {
+            map_id,
+            center: Vec3::new(focus.x, focus.z, focus.y / scale_uo_z_to_bevy_units(1.0)),
+            width,
+            height,
+        })
+    }
+}
+
+/// Starts drawing the canvas when the view changed, and shows it once it's drawn.
+fn sys_draw_classic_view(
+    mut classic_view_r: ResMut<ClassicView>,
+    mut edited_events: EventReader<LandCellsEditedEvent>,
+    mut images_r: ResMut<Assets<Image>>,
+    render_zoom_r: Res<RenderZoom>,
+    scene_state_data_r: Res<SceneStateData>,
+    map_planes_r: Res<MapPlanesRes>,
+    art_r: Res<ArtRes>,
+    camera_q: Query<(&Camera, &PlayerCamera)>,
+    player_q: Query<&Transform, With<Player>>,
+) {
+    let classic_view = classic_view_r.as_mut();
+    for event in edited_events.read() {
+        if classic_view.shown.is_some_and(|shown| shown.map_id == event.map_id) {
+            classic_view.stale = true;
+        }
+    }
+
+    if let Some(task) = &mut classic_view.task
+        && let Some((art_cache, result)) = block_on(poll_once(task))
+    {
+        classic_view.task = None;
+        classic_view.art_cache = Some(art_cache);
+        match result {
+            Ok(canvas) => {
+                if let Some(image) = images_r.get_mut(&classic_view.image) {
+                    let mut new_image = Image::new(
+                        Extent3d {
+                            width: canvas.params.width,
+                            height: canvas.params.height,
+                            depth_or_array_layers: 1,
+                        },
+                        TextureDimension::D2,
+                        canvas.data,
+                        TextureFormat::Rgba8UnormSrgb,
+                        RenderAssetUsages::RENDER_WORLD,
+                    );
+                    new_image.sampler = ImageSampler::nearest();
+                    *image = new_image;
+                }
+            }
+            Err(e) => logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::Renderer,
+                &format!("Classic 2D: can't draw the view: {e}"),
+            ),
+        }
+    }
+
+    let (Ok((camera, player_camera)), Ok(player_transform)) = (camera_q.single(), player_q.single()) else {
+        return;
+    };
+    let Some(params) = ClassicViewParams::of_main_view(
+        camera,
+        player_camera,
+        player_transform.translation,
+        render_zoom_r.0,
+        scene_state_data_r.map_id,
+    ) else {
+        return;
+    };
+    if classic_view.task.is_some() || (classic_view.shown == Some(params) && !classic_view.stale) {
+        return;
+    }
+    let Some(map_plane) = map_planes_r.get(params.map_id) else {
+        return;
+    };
+    let art_cache = classic_view.art_cache.take().unwrap_or_default();
+    classic_view.task = Some(spawn_classic_draw(map_plane, art_r.0.clone(), art_cache, params));
+    classic_view.shown = Some(params);
+    classic_view.stale = false;
+}
+
+/// Draws a canvas in background (see draw_classic_land).
+pub fn spawn_classic_draw(
+    map_plane: MapPlaneShared,
+    art: Arc<Art>,
+    mut art_cache: LandArtCache,
+    params: ClassicViewParams,
+) -> ClassicDrawTask {
+    AsyncComputeTaskPool::get().spawn(async move {
+        let result = draw_classic_land(&map_plane, &art, &mut art_cache, params);
+        (art_cache, result)
+    })
+}
+
+/// Draws the land of a map plane as the classic client does, centered on params.center.
+pub fn draw_classic_land(
+    map_plane: &MapPlaneShared,
+    art: &Art,
+    art_cache: &mut LandArtCache,
+    params: ClassicViewParams,
+) -> uocf::errors::Result<ClassicCanvas> {
+    let draw_time_start = Instant::now();
+    let (w, h) = (params.width as i32, params.height as i32);
+    let mut data: Vec<u8> = [0, 0, 0, 255].repeat((w * h) as usize);
+
+    // Canvas position of the top corner of the center tile at height 0. A tile at (+du, +dv) from it, in row
+    //  s = du + dv and column d = du - dv, has its top corner at (origin_x + d * 22, origin_y + s * 22 - z * 4).
+    let (cx, cy) = (params.center.x.floor() as i32, params.center.y.floor() as i32);
+    let (fx, fy) = (params.center.x - cx as f32, params.center.y - cy as f32);
+    let half = TILE_HALF_PIXELS as f32;
+    let origin_x = (w as f32 / 2.0 - (fx - fy) * half).round() as i32;
+    let origin_y = (h as f32 / 2.0 - (fx + fy) * half + params.center.z * Z_PIXELS as f32).round() as i32;
+
+    // Rows and columns which can reach the canvas, whatever the heights.
+    let tile_side = 2 * TILE_HALF_PIXELS;
+    let s_min = (-tile_side - Z_MAX_SHIFT_PIXELS - origin_y).div_euclid(TILE_HALF_PIXELS);
+    let s_max = (h + Z_MAX_SHIFT_PIXELS - origin_y).div_euclid(TILE_HALF_PIXELS) + 1;
+    let d_min = (-TILE_HALF_PIXELS - origin_x).div_euclid(TILE_HALF_PIXELS);
+    let d_max = (w + TILE_HALF_PIXELS - origin_x).div_euclid(TILE_HALF_PIXELS) + 1;
+
+    // The blocks of those tiles, and of the next ones, for the corner heights.
+    let (x_min, x_max) = ((cx + (s_min + d_min) / 2 - 1).max(0), cx + (s_max + d_max) / 2 + 1);
+    let (y_min, y_max) = ((cy + (s_min - d_max) / 2 - 1).max(0), cy + (s_max - d_min) / 2 + 1);
+    let blocks: BTreeMap<MapBlockRelPos, MapBlock> = if x_max < 0 || y_max < 0 {
+        BTreeMap::new()
+    } else {
+        let mut blocks_to_load: Vec<MapBlockRelPos> = Vec::new();
+        for by in MapCell::coords_of_parent_block_y(y_min as u32)..=MapCell::coords_of_parent_block_y(y_max as u32) {
+            for bx in MapCell::coords_of_parent_block_x(x_min as u32)..=MapCell::coords_of_parent_block_x(x_max as u32)
+            {
+                let pos = MapBlockRelPos { x: bx, y: by };
+                if map_plane.contains_block(pos) {
+                    blocks_to_load.push(pos);
+                }
+            }
+        }
+        map_plane.load_blocks_cloned(&mut blocks_to_load)?
+    };
+    let cell_at = |x: i32, y: i32| -> Option<MapCell> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        let (x, y) = (x as u32, y as u32);
+        let block_pos = MapBlockRelPos {
+            x: MapCell::coords_of_parent_block_x(x),
+            y: MapCell::coords_of_parent_block_y(y),
+        };
+        blocks
+            .get(&block_pos)?
+            .cell(MapCell::coords_in_block_x(x), MapCell::coords_in_block_y(y))
+            .ok()
+            .copied()
+    };
+
+    let mut canvas = CanvasTarget {
+        data: &mut data,
+        width: w,
+        height: h,
+    };
+    let mut tiles_drawn: usize = 0;
+    for s in s_min..=s_max {
+        // A tile and the next one in the row (d + 2) don't overlap: only the row order matters.
+        let d_first = d_min + (s - d_min).rem_euclid(2);
+        for d in (d_first..=d_max).step_by(2) {
+            let (x, y) = (cx + (s + d) / 2, cy + (s - d) / 2);
+            let Some(cell) = cell_at(x, y) else {
+                continue;
+            };
+            let Some(pixels) = art_cache.get(art, cell.id) else {
+                continue;
+            };
+            // Corners as the land mesh has them: top (x, y), right (x + 1, y), bottom (x + 1, y + 1), left (x, y + 1).
+            // Past the map edge, the tile height.
+            let z_of = |x: i32, y: i32| cell_at(x, y).map_or(cell.z, |cell| cell.z) as i32;
+            let (z_right, z_bottom, z_left) = (z_of(x + 1, y), z_of(x + 1, y + 1), z_of(x, y + 1));
+            let z = cell.z as i32;
+            let top = (
+                origin_x + d * TILE_HALF_PIXELS,
+                origin_y + s * TILE_HALF_PIXELS - z * Z_PIXELS,
+            );
+            if z_right == z && z_bottom == z && z_left == z {
+                canvas.blit_diamond(pixels, top.0 - TILE_HALF_PIXELS, top.1);
+            } else {
+                let row_y = |row: i32, z: i32| origin_y + row * TILE_HALF_PIXELS - z * Z_PIXELS;
+                canvas.draw_stretched_diamond(
+                    pixels,
+                    [
+                        top,
+                        (top.0 + TILE_HALF_PIXELS, row_y(s + 1, z_right)),
+                        (top.0, row_y(s + 2, z_bottom)),
+                        (top.0 - TILE_HALF_PIXELS, row_y(s + 1, z_left)),
+                    ],
+                );
+            }
+            tiles_drawn += 1;
+        }
+    }
+    logger::one(
+        None,
+        LogSev::Debug,
+        LogAbout::Renderer,
+        &format!(
+            "Classic 2D: drew {tiles_drawn} tiles ({}x{}) in {} ms.",
+            params.width,
+            params.height,
+            draw_time_start.elapsed().as_millis()
+        ),
+    );
+    Ok(ClassicCanvas { params, data })
+}
+
+/// RGBA8 canvas the land art is drawn on.
+struct CanvasTarget<'a> {
+    data: &'a mut [u8],
+    width: i32,
+    height: i32,
+}
+impl CanvasTarget<'_> {
+    fn put(&mut self, x: i32, y: i32, pixel: &[u8]) {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            return;
+        }
+        let dst = (y * self.width + x) as usize * CLASSIC_2D_BYTES_PER_PIXEL;
+        self.data[dst..dst + CLASSIC_2D_BYTES_PER_PIXEL].copy_from_slice(pixel);
+    }
+
+    /// Copies the art with its top left corner at (left, top), skipping the transparent pixels around the diamond.
+    fn blit_diamond(&mut self, art_pixels: &[u8], left: i32, top: i32) {
+        let side = Art::LAND_ART_SIZE as i32;
+        for ay in 0..side {
+            for ax in 0..side {
+                let src = (ay * side + ax) as usize * CLASSIC_2D_BYTES_PER_PIXEL;
+                let pixel = &art_pixels[src..src + CLASSIC_2D_BYTES_PER_PIXEL];
+                if pixel[3] != 0 {
+                    self.put(left + ax, top + ay, pixel);
+                }
+            }
+        }
+    }
+
+    /// Stretches the art diamond over the given corners (top, right, bottom, left), as two triangles split along the
+    ///  left-right diagonal.
+    fn draw_stretched_diamond(&mut self, art_pixels: &[u8], corners: [(i32, i32); 4]) {
+        let half = TILE_HALF_PIXELS as f32;
+        let art_corners = [(half, 0.0), (2.0 * half, half), (half, 2.0 * half), (0.0, half)];
+        for [a, b, c] in [[0, 1, 3], [1, 2, 3]] {
+            self.draw_textured_triangle(
+                art_pixels,
+                [corners[a], corners[b], corners[c]],
+                [art_corners[a], art_corners[b], art_corners[c]],
+            );
+        }
+    }
+
+    /// Draws a triangle, mapping the art on it with the art coordinates (pixels) of its vertices.
+    fn draw_textured_triangle(&mut self, art_pixels: &[u8], vertices: [(i32, i32); 3], art_coords: [(f32, f32); 3]) {
+        let [p0, p1, p2] = vertices.map(|(x, y)| Vec2::new(x as f32, y as f32));
+        let edge = |a: Vec2, b: Vec2, p: Vec2| (b - a).perp_dot(p - a);
+        let area = edge(p0, p1, p2);
+        if area.abs() < 1e-3 {
+            return;
+        }
+        let min = p0.min(p1).min(p2).floor().as_ivec2().max(IVec2::ZERO);
+        let max = p0
+            .max(p1)
+            .max(p2)
+            .ceil()
+            .as_ivec2()
+            .min(IVec2::new(self.width, self.height) - 1);
+        let side = Art::LAND_ART_SIZE as i32;
+        let half = TILE_HALF_PIXELS as f32;
+        for y in min.y..=max.y {
+            for x in min.x..=max.x {
+                let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weights = [edge(p1, p2, p) / area, edge(p2, p0, p) / area, edge(p0, p1, p) / area];
+                if weights.iter().any(|&weight| weight < 0.0) {
+                    continue;
+                }
+                let (mut u, mut v) = (0.0, 0.0);
+                for (weight, (au, av)) in weights.iter().zip(art_coords) {
+                    u += weight * au;
+                    v += weight * av;
+                }
+                // Pulled a bit toward the center, so that the edges don't sample the transparent pixels around the
+                //  diamond.
+                let shrink = (half - 1.0) / half;
+                let (u, v) = (half + (u - half) * shrink, half + (v - half) * shrink);
+                let (ax, ay) = ((u as i32).clamp(0, side - 1), (v as i32).clamp(0, side - 1));
+                let src = (ay * side + ax) as usize * CLASSIC_2D_BYTES_PER_PIXEL;
+                let pixel = &art_pixels[src..src + CLASSIC_2D_BYTES_PER_PIXEL];
+                if pixel[3] != 0 {
+                    self.put(x, y, pixel);
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,8 @@
 use crate::core::system_sets::*;
 use crate::prelude::*;
 use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use crate::core::render::scene::split_view::SPLIT_VIEW_RENDER_LAYER;
 use crate::external_data::settings::Settings;
 
 #[derive(Component)]
@@ -57,6 +59,8 @@ pub fn sys_spawn_dynamic_light(
             .looking_at(player_start_pos, Vec3::Y),
         GlobalTransform::default(), // Needed for transforming the light in world space
         light_component,
+        // Lights only the views drawing one of its layers: light the split view too.
+        RenderLayers::from_layers(&[0, SPLIT_VIEW_RENDER_LAYER]),
     ));
 
     /*
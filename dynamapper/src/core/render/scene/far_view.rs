@@ -0,0 +1,376 @@
+use crate::core::map_editor::LandCellsEditedEvent;
+use crate::core::render::export::MapExportChunk;
+use crate::core::render::scene::camera::RenderZoom;
+use crate::core::render::scene::{SceneStateData, camera::PlayerCamera, split_view::SplitView, world::land::LCMesh};
+use crate::core::system_sets::*;
+use crate::core::uo_files_loader::{MapPlanesRes, RadarColRes};
+use crate::prelude::*;
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        view::RenderLayers,
+    },
+    tasks::{AsyncComputeTaskPool, Task, block_on, poll_once},
+};
+use bevy::platform::time::Instant;
+use uocf::geo::map::{MapBlock, MapPlaneShared};
+use uocf::radarcol::RadarColors;
+
+// Far view: zoomed out to continent scale, the land chunks of the main view are replaced by a single quad covering
+//  the whole map plane, textured with the radar colors (radarcol.mul) of its land tiles, like a big minimap laid on
+//  the map.
+// - The texture is built in background the first time the far view is needed for a map plane (and again after map
+//   edits), reading the map file a column of blocks at a time without filling the block cache
+//   (MapPlaneShared::read_block_column). A texel is the average color of 1x1 to 8x8 tiles, so that the texture side
+//   stays within FAR_VIEW_MAX_TEXTURE_SIDE. The statics aren't drawn: it would take reading the whole statics plane.
+// - From render.far_view_zoom * FAR_VIEW_FADE_START_RATIO the quad fades in over the chunks, and at
+//   render.far_view_zoom it's opaque: the chunks of the plane are hidden and left as they are (see
+//   sys_update_worldmap_chunks_to_render), until zooming back in. With the far view the zoom goes past MAX_ZOOM, up to
+//   FAR_VIEW_MAX_ZOOM.
+// - Only the main camera draws the far view (FAR_VIEW_RENDER_LAYER): the split view keeps its chunks, and so does the
+//   main view if the split view shows the same map plane.
+// - The quad lies at height 0, moved toward the camera along its view direction: with the orthographic projection it
+//   covers the same screen area, but it's drawn over the terrain instead of being hidden by the higher tiles.
+
+pub const FAR_VIEW_RENDER_LAYER: usize = 3;
+/// Zoom where the far view starts fading in, as a fraction of render.far_view_zoom.
+const FAR_VIEW_FADE_START_RATIO: f32 = 0.7;
+/// Max texture side: past it, a texel covers more tiles. Safe for the downlevel GPUs too.
+const FAR_VIEW_MAX_TEXTURE_SIDE: u32 = 4096;
+/// Tiles per texel side which can be picked. They divide the block size, so a block column covers whole texels.
+const FAR_VIEW_TILES_PER_TEXEL: [u32; 4] = [1, 2, 4, 8];
+/// Distance (world units) the quad is moved toward the camera: more than the highest terrain, seen from the camera.
+const FAR_VIEW_LIFT: f32 = 100.0;
+const FAR_VIEW_BYTES_PER_PIXEL: usize = 4; // RGBA8888
+
+pub struct FarViewPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(FarViewPlugin);
+
+impl Plugin for FarViewPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<FarView>().add_systems(
+            Update,
+            (sys_build_far_view, sys_fade_far_view)
+                .chain()
+                .after(MovementSysSet::UpdateCamera)
+                .before(SceneRenderLandSysSet::SyncLandChunks)
+                .run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+/// Tag component: the far view quad.
+#[derive(Component)]
+pub struct FarViewMesh;
+
+/// Pixel data of the far view texture of a map plane.
+struct FarViewImageData {
+    map_id: u32,
+    width: u32,
+    height: u32,
+    tiles_per_texel: u32,
+    data: Vec<u8>,
+}
+
+/// The far view quad of a map plane, with its assets.
+struct FarViewQuad {
+    map_id: u32,
+    entity: Entity,
+    material: Handle<StandardMaterial>,
+    /// The map plane was edited after building the texture.
+    stale: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct FarView {
+    /// Opacity of the quad: 0 when it isn't shown, 1 when it replaces the land chunks.
+    pub alpha: f32,
+    /// Map plane whose land chunks are replaced by the far view (hidden and not updated).
+    pub bypassed_map: Option<u32>,
+    quad: Option<FarViewQuad>,
+    task: Option<(u32, Task<uocf::errors::Result<FarViewImageData>>)>,
+}
+
+/// Opacity of the far view at the given zoom.
+fn far_view_alpha(render_settings: &SectRender, zoom: f32) -> f32 {
+    let full_zoom = render_settings.far_view_zoom;
+    if full_zoom <= 0.0 {
+        return 0.0;
+    }
+    let fade_start_zoom = full_zoom * FAR_VIEW_FADE_START_RATIO;
+    ((zoom - fade_start_zoom) / (full_zoom - fade_start_zoom)).clamp(0.0, 1.0)
+}
+
+/// Starts building the far view texture of the player map plane when it's about to be shown, and spawns its quad
+///  once it's built.
+fn sys_build_far_view(
+    mut commands: Commands,
+    mut far_view_r: ResMut<FarView>,
+    mut edited_events: EventReader<LandCellsEditedEvent>,
+    mut images_r: ResMut<Assets<Image>>,
+    mut meshes_r: ResMut<Assets<Mesh>>,
+    mut materials_r: ResMut<Assets<StandardMaterial>>,
+    settings_r: Res<Settings>,
+    render_zoom_r: Res<RenderZoom>,
+    scene_state_data_r: Res<SceneStateData>,
+    map_planes_r: Res<MapPlanesRes>,
+    radarcol_r: Res<RadarColRes>,
+) {
+    let far_view = far_view_r.as_mut();
+    for event in edited_events.read() {
+        if let Some(quad) = &mut far_view.quad
+            && quad.map_id == event.map_id
+        {
+            quad.stale = true;
+        }
+    }
+
+    if let Some((map_id, task)) = &mut far_view.task
+        && let Some(result) = block_on(poll_once(task))
+    {
+        let map_id = *map_id;
+        far_view.task = None;
+        match result {
+            Ok(image_data) => {
+                if let Some(old_quad) = far_view.quad.take() {
+                    commands.entity(old_quad.entity).despawn();
+                }
+                far_view.quad = Some(spawn_far_view_quad(
+                    &mut commands,
+                    &mut images_r,
+                    &mut meshes_r,
+                    &mut materials_r,
+                    image_data,
+                ));
+            }
+            Err(e) => logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::Renderer,
+                &format!("Far view: can't build the texture of map plane {map_id}: {e}"),
+            ),
+        }
+    }
+
+    // Built before it starts fading in. A task for another map plane is dropped, which cancels it.
+    let map_id = scene_state_data_r.map_id;
+    let needed = settings_r.render.far_view_zoom > 0.0
+        && render_zoom_r.0 >= settings_r.render.far_view_zoom * FAR_VIEW_FADE_START_RATIO;
+    let built = far_view
+        .quad
+        .as_ref()
+        .is_some_and(|quad| quad.map_id == map_id && !quad.stale);
+    let building = far_view
+        .task
+        .as_ref()
+        .is_some_and(|(task_map_id, _)| *task_map_id == map_id);
+    if !needed || built || building {
+        return;
+    }
+    let Some(map_plane) = map_planes_r.get(map_id) else {
+        return;
+    };
+    let radar_colors = radarcol_r.0.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move { build_far_view_image_data(&map_plane, &radar_colors) });
+    far_view.task = Some((map_id, task));
+}
+
+/// Builds the texture data of a map plane: the average land radar color of each texel.
+fn build_far_view_image_data(
+    map_plane: &MapPlaneShared,
+    radar_colors: &RadarColors,
+) -> uocf::errors::Result<FarViewImageData> {
+    let build_time_start = Instant::now();
+    let size_blocks = map_plane.size_blocks();
+    let size_cells = size_blocks.to_cells();
+    let tiles_per_texel = FAR_VIEW_TILES_PER_TEXEL
+        .into_iter()
+        .find(|&tiles| size_cells.width.max(size_cells.height) / tiles <= FAR_VIEW_MAX_TEXTURE_SIDE)
+        .unwrap_or(*FAR_VIEW_TILES_PER_TEXEL.last().unwrap());
+    let (width, height) = (size_cells.width / tiles_per_texel, size_cells.height / tiles_per_texel);
+    let mut data = vec![0u8; (width * height) as usize * FAR_VIEW_BYTES_PER_PIXEL];
+
+    // Color sums (rgb) of the texels of a block column.
+    let texel_columns_per_block = MapBlock::CELLS_PER_ROW / tiles_per_texel;
+    let mut sums = vec![[0u32; 3]; (texel_columns_per_block * height) as usize];
+    let tiles_per_sum = tiles_per_texel * tiles_per_texel;
+    for bx in 0..size_blocks.width {
+        sums.fill([0; 3]);
+        for block in map_plane.read_block_column(bx)? {
+            let first_cell = MapBlock::coords_first_cell(&block.internal_coords);
+            for cy in 0..MapBlock::CELLS_PER_COLUMN {
+                for cx in 0..MapBlock::CELLS_PER_ROW {
+                    let [r, g, b, _] = radar_colors.land_color_rgba8888(block.cell(cx, cy)?.id);
+                    let ty = (first_cell.y + cy) / tiles_per_texel;
+                    let sum = &mut sums[(ty * texel_columns_per_block + cx / tiles_per_texel) as usize];
+                    sum[0] += r as u32;
+                    sum[1] += g as u32;
+                    sum[2] += b as u32;
+                }
+            }
+        }
+        for ty in 0..height {
+            for tx in 0..texel_columns_per_block {
+                let [r, g, b] = sums[(ty * texel_columns_per_block + tx) as usize].map(|c| (c / tiles_per_sum) as u8);
+                let pixel = (ty * width + bx * texel_columns_per_block + tx) as usize;
+                let pixel_start = pixel * FAR_VIEW_BYTES_PER_PIXEL;
+                data[pixel_start..pixel_start + FAR_VIEW_BYTES_PER_PIXEL].copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::Renderer,
+        &format!(
+            "Far view: built the texture of map plane {} ({width}x{height}, {tiles_per_texel}x{tiles_per_texel} tiles per texel) in {} ms.",
+            map_plane.index(),
+            build_time_start.elapsed().as_millis()
+        ),
+    );
+    Ok(FarViewImageData {
+        map_id: map_plane.index(),
+        width,
+        height,
+        tiles_per_texel,
+        data,
+    })
+}
+
+fn spawn_far_view_quad(
+    commands: &mut Commands,
+    images_r: &mut Assets<Image>,
+    meshes_r: &mut Assets<Mesh>,
+    materials_r: &mut Assets<StandardMaterial>,
+    image_data: FarViewImageData,
+) -> FarViewQuad {
+    let image = Image::new(
+        Extent3d {
+            width: image_data.width,
+            height: image_data.height,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        image_data.data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    let material = materials_r.add(StandardMaterial {
+        base_color: Color::WHITE.with_alpha(0.0),
+        base_color_texture: Some(images_r.add(image)),
+        unlit: true,
+        alpha_mode: AlphaMode::Blend,
+        ..default()
+    });
+
+    // The whole map plane: a texel covers tiles_per_texel tiles.
+    let width = (image_data.width * image_data.tiles_per_texel) as f32;
+    let height = (image_data.height * image_data.tiles_per_texel) as f32;
+    let mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD)
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_POSITION,
+            vec![
+                [0.0, 0.0, 0.0],
+                [width, 0.0, 0.0],
+                [0.0, 0.0, height],
+                [width, 0.0, height],
+            ],
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; 4])
+        .with_inserted_attribute(
+            Mesh::ATTRIBUTE_UV_0,
+            vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]],
+        )
+        // Same winding as the land chunk mesh.
+        .with_inserted_indices(Indices::U32(vec![0, 3, 1, 0, 2, 3]));
+
+    let entity = commands
+        .spawn((
+            FarViewMesh,
+            Mesh3d(meshes_r.add(mesh)),
+            MeshMaterial3d(material.clone()),
+            Transform::default(),
+            Visibility::Hidden,
+            RenderLayers::layer(FAR_VIEW_RENDER_LAYER),
+        ))
+        .id();
+    FarViewQuad {
+        map_id: image_data.map_id,
+        entity,
+        material,
+        stale: false,
+    }
+}
+
+/// Fades the far view quad in and out with the zoom, and hides the land chunks it replaces.
+fn sys_fade_far_view(
+    mut commands: Commands,
+    mut far_view_r: ResMut<FarView>,
+    mut materials_r: ResMut<Assets<StandardMaterial>>,
+    settings_r: Res<Settings>,
+    render_zoom_r: Res<RenderZoom>,
+    scene_state_data_r: Res<SceneStateData>,
+    split_view_r: Res<SplitView>,
+    camera_q: Query<&PlayerCamera>,
+    mut quad_q: Query<(&mut Transform, &mut Visibility), With<FarViewMesh>>,
+    chunk_q: Query<(Entity, &LCMesh), Without<MapExportChunk>>,
+) {
+    let far_view = far_view_r.as_mut();
+    let map_id = scene_state_data_r.map_id;
+    let alpha = match &far_view.quad {
+        Some(quad) if quad.map_id == map_id => far_view_alpha(&settings_r.render, render_zoom_r.0),
+        _ => 0.0,
+    };
+
+    if let Some(quad) = &far_view.quad
+        && let Ok((mut transform, mut visibility)) = quad_q.get_mut(quad.entity)
+    {
+        // A rebuilt quad starts transparent.
+        let material_alpha = materials_r
+            .get(&quad.material)
+            .map(|material| material.base_color.alpha());
+        if material_alpha.is_some_and(|material_alpha| material_alpha != alpha)
+            && let Some(material) = materials_r.get_mut(&quad.material)
+        {
+            material.base_color.set_alpha(alpha);
+        }
+        visibility.set_if_neq(if alpha > 0.0 {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        });
+        if let Ok(player_camera) = camera_q.single() {
+            transform.translation = player_camera.offset_from_focus().normalize() * FAR_VIEW_LIFT;
+        }
+    }
+    far_view.alpha = alpha;
+
+    // The split view draws the chunks of its plane: they can't be hidden if it's the same one.
+    let bypassed_map = (alpha >= 1.0 && split_view_r.shown_map() != Some(map_id)).then_some(map_id);
+    if bypassed_map == far_view.bypassed_map {
+        return;
+    }
+    for (entity, chunk) in chunk_q.iter() {
+        if Some(chunk.parent_map_id) == bypassed_map {
+            commands.entity(entity).insert(Visibility::Hidden);
+        } else if Some(chunk.parent_map_id) == far_view.bypassed_map {
+            commands.entity(entity).insert(Visibility::Inherited);
+        }
+    }
+    logger::one(
+        None,
+        LogSev::Debug,
+        LogAbout::Renderer,
+        &match bypassed_map {
+            Some(map_id) => format!("Far view: replacing the land chunks of map plane {map_id}."),
+            None => "Far view: showing the land chunks again.".to_owned(),
+        },
+    );
+    far_view.bypassed_map = bypassed_map;
+}
@@ -0,0 +1,120 @@
+// Markers of the live world entities (core::live_world): a box for each one, colored by kind, on its tile. Only the
+//  ones on the current map plane are shown.
+
+use crate::core::live_world::{LiveEntityKind, LiveWorld};
+use crate::core::render::scene::SceneStateData;
+use crate::core::system_sets::StartupSysSet;
+use crate::prelude::*;
+use bevy::{color, prelude::*};
+use std::collections::HashMap;
+
+#[derive(Component)]
+pub struct LiveEntityMarker {
+    pub serial: u32,
+    pub kind: LiveEntityKind,
+}
+
+/// Mesh and material of the markers of each kind, and the marker entity of each live entity.
+#[derive(Resource, Default)]
+struct LiveEntityMarkers {
+    looks: HashMap<LiveEntityKind, (Handle<Mesh>, Handle<StandardMaterial>)>,
+    spawned: HashMap<u32, Entity>,
+}
+
+pub struct LiveEntitiesPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(LiveEntitiesPlugin);
+impl Plugin for LiveEntitiesPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<LiveEntityMarkers>()
+            .add_systems(
+                Startup,
+                sys_setup_live_entity_markers.in_set(StartupSysSet::SetupSceneStage1),
+            )
+            .add_systems(
+                Update,
+                sys_sync_live_entity_markers.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_setup_live_entity_markers(
+    mut markers_r: ResMut<LiveEntityMarkers>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    log_system_add_startup::<LiveEntitiesPlugin>(StartupSysSet::SetupSceneStage1, fname!());
+    for kind in LiveEntityKind::ALL {
+        // Mobiles stand taller than the items.
+        let (half_size, color) = match kind {
+            LiveEntityKind::Player => (Vec3::new(0.3, 0.8, 0.3), color::palettes::basic::AQUA),
+            LiveEntityKind::Npc => (Vec3::new(0.3, 0.8, 0.3), color::palettes::basic::RED),
+            LiveEntityKind::Item => (Vec3::splat(0.2), color::palettes::basic::YELLOW),
+        };
+        let mesh = meshes.add(Mesh::from(Cuboid { half_size }));
+        let material = materials.add(StandardMaterial {
+            base_color: Color::Srgba(color),
+            unlit: true,
+            ..default()
+        });
+        markers_r.looks.insert(kind, (mesh, material));
+    }
+}
+
+/// Spawns, moves and despawns the markers when the live world changes, and shows the ones of the current map plane.
+fn sys_sync_live_entity_markers(
+    mut commands: Commands,
+    live_world_r: Res<LiveWorld>,
+    scene_state_data_r: Res<SceneStateData>,
+    mut markers_r: ResMut<LiveEntityMarkers>,
+    mut marker_q: Query<(&LiveEntityMarker, &mut Transform, &mut Visibility)>,
+) {
+    if !live_world_r.is_changed() && !scene_state_data_r.is_changed() {
+        return;
+    }
+    let markers = markers_r.as_mut();
+    markers.spawned.retain(|serial, entity| {
+        let alive = live_world_r.entities.contains_key(serial);
+        if !alive {
+            commands.entity(*entity).despawn();
+        }
+        alive
+    });
+    for (serial, live_entity) in &live_world_r.entities {
+        let translation = live_entity.pos.to_bevy_vec3_ignore_map();
+        let visibility = if live_entity.pos.m as u32 == scene_state_data_r.map_id {
+            Visibility::Visible
+        } else {
+            Visibility::Hidden
+        };
+        if let Some(entity) = markers.spawned.get(serial)
+            && let Ok((marker, mut transform, mut marker_visibility)) = marker_q.get_mut(*entity)
+        {
+            if marker.kind == live_entity.kind {
+                transform.translation = translation;
+                marker_visibility.set_if_neq(visibility);
+                continue;
+            }
+            // Another kind of entity with the same serial: it needs another look.
+            commands.entity(*entity).despawn();
+        }
+        let Some((mesh, material)) = markers.looks.get(&live_entity.kind) else {
+            continue;
+        };
+        let entity = commands
+            .spawn((
+                Mesh3d(mesh.clone()),
+                MeshMaterial3d(material.clone()),
+                Transform::from_translation(translation),
+                visibility,
+                LiveEntityMarker {
+                    serial: *serial,
+                    kind: live_entity.kind,
+                },
+            ))
+            .id();
+        markers.spawned.insert(*serial, entity);
+    }
+}
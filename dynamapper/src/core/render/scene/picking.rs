@@ -0,0 +1,107 @@
+use crate::core::render::scene::{
+    SceneStateData,
+    camera::{PlayerCamera, cursor_to_world_on_plane},
+};
+use crate::core::uo_files_loader::MapPlanesRes;
+use crate::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use uocf::geo::map::MapCell;
+
+// Screen to tile picking. The camera is orthographic and oblique, so a cursor position matches different tiles
+//  depending on the height of the ground: we cast the cursor ray on a horizontal plane, read the height of the tile
+//  we hit, then cast it again on a plane at that height, and so on.
+// Zoom is part of the camera projection, so the ray already accounts for it.
+
+/// The ground under the cursor depends on the height of the tile we hit: refine the guess this many times.
+pub const PICKING_HEIGHT_REFINE_STEPS: usize = 3;
+
+/// A land tile found by picking.
+#[derive(Clone, Copy, Debug)]
+pub struct PickedTile {
+    pub map_id: u32,
+    pub x: u32,
+    pub y: u32,
+    pub cell: MapCell,
+}
+impl PickedTile {
+    pub fn to_uo_vec4(&self) -> UOVec4 {
+        UOVec4::new(self.x as u16, self.y as u16, self.cell.z, self.map_id as u8)
+    }
+}
+
+/// Reads a land cell, loading its map block if it isn't cached.
+pub fn land_cell_at(map_planes_r: &MapPlanesRes, map_id: u32, x: u32, y: u32) -> Option<MapCell> {
+    map_planes_r.get(map_id)?.cell(x, y).ok()
+}
+
+/// Converts a cursor position (window logical pixels) into the land tile under it.
+/// cell_at provides the land cell at the given tile coordinates, or None if out of the map.
+pub fn screen_to_tile(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    cursor_pos: Vec2,
+    map_id: u32,
+    mut cell_at: impl FnMut(u32, u32) -> Option<MapCell>,
+) -> Option<PickedTile> {
+    // Start from the ground at z 0, then move the plane to the height of the tile we hit.
+    let mut plane_height = 0.0;
+    let mut picked: Option<PickedTile> = None;
+    for _ in 0..PICKING_HEIGHT_REFINE_STEPS {
+        let Some(world_pos) = cursor_to_world_on_plane(camera, camera_transform, cursor_pos, plane_height)
+        else {
+            break;
+        };
+        if world_pos.x < 0.0 || world_pos.z < 0.0 {
+            break;
+        }
+        let (x, y) = (world_pos.x as u32, world_pos.z as u32);
+        let Some(cell) = cell_at(x, y) else {
+            break;
+        };
+        picked = Some(PickedTile { map_id, x, y, cell });
+        plane_height = scale_uo_z_to_bevy_units(cell.z as f32);
+    }
+    picked
+}
+
+/// System param to pick tiles from the player camera, on the map plane shown by the scene.
+#[derive(SystemParam)]
+pub struct TilePicker<'w, 's> {
+    window_q: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    camera_q: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<PlayerCamera>>,
+    map_planes_r: Res<'w, MapPlanesRes>,
+    scene_state_data_r: Res<'w, SceneStateData>,
+}
+impl TilePicker<'_, '_> {
+    /// Current cursor position in the primary window (logical pixels), if the cursor is inside it.
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.window_q.single().ok()?.cursor_position()
+    }
+
+    /// Land tile at the given window position (logical pixels).
+    pub fn tile_at(&self, cursor_pos: Vec2) -> Option<PickedTile> {
+        let (camera, camera_transform) = self.camera_q.single().ok()?;
+        // Not over the split view (see split_view), which covers part of the window.
+        if camera.logical_viewport_rect().is_some_and(|rect| !rect.contains(cursor_pos)) {
+            return None;
+        }
+        let map_id = self.scene_state_data_r.map_id;
+        screen_to_tile(camera, camera_transform, cursor_pos, map_id, |x, y| {
+            land_cell_at(&self.map_planes_r, map_id, x, y)
+        })
+    }
+
+    /// Land tile at the given map coordinates, on the map plane shown by the scene.
+    pub fn tile_at_map_coords(&self, x: u32, y: u32) -> Option<PickedTile> {
+        let map_id = self.scene_state_data_r.map_id;
+        let cell = land_cell_at(&self.map_planes_r, map_id, x, y)?;
+        Some(PickedTile { map_id, x, y, cell })
+    }
+
+    /// Land tile under the mouse cursor.
+    pub fn cursor_tile(&self) -> Option<PickedTile> {
+        self.tile_at(self.cursor_position()?)
+    }
+}
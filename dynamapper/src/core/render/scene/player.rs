@@ -1,13 +1,47 @@
+use crate::core::controls::click_to_move::ClickToMovePath;
+use crate::core::controls::location_history::LocationHistory;
+use crate::core::controls::player_movement::PlayerWalk;
+use crate::core::maps::manager::SwitchMapPlaneEvent;
+use crate::core::render::scene::camera::PlayerCamera;
+use crate::core::render::scene::picking::land_cell_at;
+use crate::core::render::scene::{RecomputeVisibleChunksEvent, world::WorldGeoData};
 use crate::core::system_sets::*;
+use crate::core::uo_files_loader::MapPlanesRes;
 use crate::prelude::*;
 use bevy::{color, prelude::*};
 use crate::external_data::settings::Settings;
 
 #[derive(Component)]
 pub struct Player {
+    /// Where the player stands, or walks to (see player_movement): the transform follows it.
     pub current_pos: Option<UOVec4>,
     pub prev_rendered_pos: Option<UOVec4>,
 }
+impl Player {
+    /// Where the player is: the tile of the transform, which is between two tiles while walking, and the z and the map
+    ///  plane of current_pos.
+    pub fn location(&self, transform: &Transform) -> Option<UOVec4> {
+        let current_pos = self.current_pos?;
+        Some(UOVec4::new(
+            transform.translation.x.round() as u16,
+            transform.translation.z.round() as u16,
+            current_pos.z,
+            current_pos.m,
+        ))
+    }
+}
+
+/// Request to move the player to a location (Go to window, bookmarks...). The map plane is switched first, if needed.
+#[derive(Event, Debug, Clone, Copy, PartialEq)]
+pub struct TeleportPlayerEvent {
+    pub x: u16,
+    pub y: u16,
+    /// None: on the land tile at the destination.
+    pub z: Option<i8>,
+    pub map_id: u32,
+    /// Back/forward navigation in the LocationHistory: not recorded as a new entry.
+    pub from_history: bool,
+}
 
 pub struct PlayerPlugin {
     pub registered_by: &'static str,
@@ -16,10 +50,17 @@ pub struct PlayerPlugin {
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         log_plugin_build(self);
-        app.add_systems(
-            Startup,
-            sys_spawn_player_entity.in_set(StartupSysSet::SetupSceneStage1),
-        );
+        app.add_event::<TeleportPlayerEvent>()
+            .add_systems(
+                Startup,
+                sys_spawn_player_entity.in_set(StartupSysSet::SetupSceneStage1),
+            )
+            .add_systems(
+                Update,
+                sys_teleport_player
+                    .in_set(MovementSysSet::MovementActions)
+                    .run_if(in_state(AppState::InGame)),
+            );
     }
 }
 
@@ -61,3 +102,98 @@ pub fn sys_spawn_player_entity(
         format!("Spawned player at pos {player_start_pos}.").as_str(),
     );
 }
+
+/// Moves the player to the requested location.
+/// A destination on another map plane is reached in two frames: the map plane switch (sys_switch_map_plane) happens
+///  later in this one, the player is moved in the next one, once the map size and its land heights are known.
+pub fn sys_teleport_player(
+    mut events: EventReader<TeleportPlayerEvent>,
+    mut pending: Local<Option<(TeleportPlayerEvent, UOVec4)>>,
+    mut switch_writer: EventWriter<SwitchMapPlaneEvent>,
+    mut recompute_writer: EventWriter<RecomputeVisibleChunksEvent>,
+    map_planes_r: Res<MapPlanesRes>,
+    world_geo_data_r: Res<WorldGeoData>,
+    mut click_to_move_path_r: ResMut<ClickToMovePath>,
+    mut walk_r: ResMut<PlayerWalk>,
+    mut location_history_r: ResMut<LocationHistory>,
+    mut player_q: Query<(&mut Player, &mut Transform)>,
+    mut camera_q: Query<&mut PlayerCamera>,
+) {
+    // Only the last request matters.
+    let new_request = events.read().last().copied();
+    let Ok((mut player, mut player_transform)) = player_q.single_mut() else {
+        return;
+    };
+    // The origin is where the player was when the request came, before any map plane switch.
+    let (request, origin, was_pending) = match new_request {
+        Some(request) => match player.location(&player_transform) {
+            Some(origin) => (request, origin, false),
+            None => return,
+        },
+        None => match pending.take() {
+            Some((request, origin)) => (request, origin, true),
+            None => return,
+        },
+    };
+    let Some(player_pos) = player.current_pos else {
+        return;
+    };
+    if player_pos.m as u32 != request.map_id {
+        if was_pending {
+            // The map plane couldn't be loaded: sys_switch_map_plane logged why.
+            logger::one(
+                None,
+                LogSev::Warn,
+                LogAbout::Player,
+                &format!("Can't teleport the player to map plane {}.", request.map_id),
+            );
+            return;
+        }
+        switch_writer.write(SwitchMapPlaneEvent { map_id: request.map_id });
+        *pending = Some((request, origin));
+        return;
+    }
+
+    let Some(metadata) = world_geo_data_r.maps.get(&request.map_id) else {
+        return;
+    };
+    if request.x as u32 >= metadata.width || request.y as u32 >= metadata.height {
+        logger::one(
+            None,
+            LogSev::Warn,
+            LogAbout::Player,
+            &format!(
+                "Can't teleport the player to {}, {}: map plane {} is {}x{}.",
+                request.x, request.y, request.map_id, metadata.width, metadata.height
+            ),
+        );
+        return;
+    }
+    let z = request
+        .z
+        .or_else(|| land_cell_at(&map_planes_r, request.map_id, request.x as u32, request.y as u32).map(|cell| cell.z))
+        .unwrap_or(0);
+    let destination = UOVec4::new(request.x, request.y, z, request.map_id as u8);
+    player.current_pos = Some(destination);
+    player_transform.translation = destination.to_bevy_vec3_ignore_map();
+
+    if !request.from_history {
+        location_history_r.record(origin);
+    }
+    // Drop what was going on at the old location.
+    click_to_move_path_r.steps.clear();
+    walk_r.stop();
+    if let Ok(mut player_camera) = camera_q.single_mut() {
+        player_camera.snap_to_player();
+    }
+    recompute_writer.write(RecomputeVisibleChunksEvent);
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::Player,
+        &format!(
+            "Player teleported to {}, {}, {} (map plane {}).",
+            destination.x, destination.y, destination.z, destination.m
+        ),
+    );
+}
@@ -0,0 +1,288 @@
+use crate::core::render::export::MapExportChunk;
+use crate::core::render::scene::SceneStateData;
+use crate::core::render::scene::camera::{
+    DEFAULT_ZOOM, MAX_ZOOM, MIN_ZOOM, PlayerCamera, cursor_to_world_on_plane, ortho_scaling_mode, wheel_zoom_factor,
+};
+use crate::core::render::scene::player::Player;
+use crate::core::render::scene::world::{WorldGeoData, land::LCMesh, statics::SCMesh};
+use crate::core::system_sets::*;
+use crate::prelude::*;
+use bevy::input::mouse::AccumulatedMouseScroll;
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::render::view::RenderLayers;
+use bevy::window::PrimaryWindow;
+use bevy_egui::input::EguiWantsInput;
+
+// Split view: a second view of the map next to the main one, to compare two places, or the same place on two map
+//  planes (Felucca and Trammel, or two revisions of a map installed as different planes).
+// - The main camera (PlayerCamera) gets the left half of the window, the SplitViewCamera the right half. Both look at
+//   the map with the same angle and rotation. The split view has its own zoom, and looks at the same point as the main
+//   view unless it's panned (dragging with the middle mouse button) or moved in the Split View window
+//   (render::split_view_ui). The mouse wheel over it zooms it.
+// - Its chunks are spawned by the scene like the main view ones (see sys_update_worldmap_chunks_to_render). The chunks
+//   of a map plane shown only in the split view are put on SPLIT_VIEW_RENDER_LAYER, which only the split camera draws.
+//   When both views show the same plane, they share its chunks on the default layer.
+// - Its map plane is loaded with a LoadMapPlaneEvent, and kept loaded by the MapPlaneManager while shown.
+// - The UI is drawn by camera::UiCamera over the whole window.
+
+/// The default layer is the main view one, the export uses layer 1.
+pub const SPLIT_VIEW_RENDER_LAYER: usize = 2;
+/// After the main camera.
+const SPLIT_VIEW_CAMERA_ORDER: isize = 1;
+const PAN_MOUSE_BUTTON: MouseButton = MouseButton::Middle;
+
+#[derive(Resource, Debug)]
+pub struct SplitView {
+    pub enabled: bool,
+    pub map_id: u32,
+    /// Point the split view looks at.
+    pub focus: Vec3,
+    /// Look at the same point as the main view.
+    pub follow_main_view: bool,
+    /// Orthographic projection scale, like RenderZoom.
+    pub zoom: f32,
+}
+impl Default for SplitView {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            // Trammel, to compare it with Felucca.
+            map_id: 1,
+            focus: Vec3::ZERO,
+            follow_main_view: true,
+            zoom: DEFAULT_ZOOM,
+        }
+    }
+}
+impl SplitView {
+    /// Map plane shown in the split view, if it's enabled.
+    pub fn shown_map(&self) -> Option<u32> {
+        self.enabled.then_some(self.map_id)
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.clamp(MIN_ZOOM, MAX_ZOOM);
+    }
+}
+
+#[derive(Component)]
+pub struct SplitViewCamera;
+
+pub struct SplitViewPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(SplitViewPlugin);
+
+impl Plugin for SplitViewPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<SplitView>()
+            .add_systems(
+                Update,
+                (sys_split_view_input, sys_sync_split_view_camera)
+                    .chain()
+                    .after(MovementSysSet::UpdateCamera)
+                    .run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                Update,
+                sys_assign_chunk_render_layers
+                    .after(SceneRenderLandSysSet::SyncLandChunks)
+                    .after(SceneRenderLandSysSet::RenderStatics)
+                    .run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+/// Zooms the split view with the mouse wheel, and pans it by dragging with the middle mouse button.
+fn sys_split_view_input(
+    mouse_scroll_r: Res<AccumulatedMouseScroll>,
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    egui_wants_input_r: Res<EguiWantsInput>,
+    world_geo_data_r: Res<WorldGeoData>,
+    windows_q: Query<&Window, With<PrimaryWindow>>,
+    camera_q: Query<(&Camera, &GlobalTransform), With<SplitViewCamera>>,
+    mut last_drag_cursor: Local<Option<Vec2>>,
+    mut split_view_r: ResMut<SplitView>,
+) {
+    let Ok((camera, camera_transform)) = camera_q.single() else {
+        *last_drag_cursor = None;
+        return;
+    };
+    let cursor = windows_q.single().ok().and_then(Window::cursor_position);
+    let cursor_over_view =
+        cursor.is_some_and(|cursor| camera.logical_viewport_rect().is_some_and(|rect| rect.contains(cursor)));
+
+    if mouse_scroll_r.delta.y != 0.0 && cursor_over_view && !egui_wants_input_r.wants_pointer_input() {
+        let zoom = split_view_r.zoom * wheel_zoom_factor(&mouse_scroll_r);
+        split_view_r.set_zoom(zoom);
+    }
+
+    // The world point grabbed stays under the cursor.
+    let dragging = mouse_input.pressed(PAN_MOUSE_BUTTON)
+        && (last_drag_cursor.is_some()
+            || (mouse_input.just_pressed(PAN_MOUSE_BUTTON)
+                && cursor_over_view
+                && !egui_wants_input_r.wants_pointer_input()));
+    let focus = split_view_r.focus;
+    if dragging
+        && let (Some(cursor), Some(last_cursor)) = (cursor, *last_drag_cursor)
+        && let (Some(grabbed), Some(now_under_cursor)) = (
+            cursor_to_world_on_plane(camera, camera_transform, last_cursor, focus.y),
+            cursor_to_world_on_plane(camera, camera_transform, cursor, focus.y),
+        )
+        && grabbed != now_under_cursor
+    {
+        let mut focus = focus + (grabbed - now_under_cursor).with_y(0.0);
+        // Within the map.
+        if let Some(map_metadata) = world_geo_data_r.maps.get(&split_view_r.map_id) {
+            focus.x = focus.x.clamp(0.0, map_metadata.width as f32);
+            focus.z = focus.z.clamp(0.0, map_metadata.height as f32);
+        }
+        split_view_r.focus = focus;
+        split_view_r.follow_main_view = false;
+    }
+    *last_drag_cursor = if dragging { cursor } else { None };
+}
+
+/// Checked before setting a camera viewport, which would set the camera up again in the renderer.
+pub fn is_viewport(current: Option<&Viewport>, viewport: &Viewport) -> bool {
+    current.is_some_and(|current| {
+        current.physical_position == viewport.physical_position && current.physical_size == viewport.physical_size
+    })
+}
+
+/// Spawns or despawns the split camera, shares the window between the two cameras and places the split camera.
+fn sys_sync_split_view_camera(
+    mut commands: Commands,
+    mut split_view_r: ResMut<SplitView>,
+    scene_state_data_r: Res<SceneStateData>,
+    windows_q: Query<&Window, With<PrimaryWindow>>,
+    player_q: Query<&Transform, With<Player>>,
+    mut main_camera_q: Query<(&mut Camera, &PlayerCamera), Without<SplitViewCamera>>,
+    mut split_camera_q: Query<
+        (Entity, &mut Camera, &mut Transform, &mut Projection, &mut RenderLayers),
+        (With<SplitViewCamera>, Without<Player>),
+    >,
+) {
+    let Ok((mut main_camera, player_camera)) = main_camera_q.single_mut() else {
+        return;
+    };
+    if !split_view_r.enabled {
+        if main_camera.viewport.is_some() {
+            main_camera.viewport = None;
+        }
+        for (entity, ..) in split_camera_q.iter() {
+            commands.entity(entity).despawn();
+            logger::one(None, LogSev::Debug, LogAbout::Camera, "Split view camera despawned.");
+        }
+        return;
+    }
+    let (Ok(window), Ok(player_transform)) = (windows_q.single(), player_q.single()) else {
+        return;
+    };
+    let window_size = window.physical_size();
+    if window_size.x < 2 || window_size.y == 0 {
+        // Minimized.
+        return;
+    }
+
+    // Left half for the main view, right half for the split view.
+    let main_width = window_size.x / 2;
+    let main_viewport = Viewport {
+        physical_position: UVec2::ZERO,
+        physical_size: UVec2::new(main_width, window_size.y),
+        ..default()
+    };
+    let split_viewport = Viewport {
+        physical_position: UVec2::new(main_width, 0),
+        physical_size: UVec2::new(window_size.x - main_width, window_size.y),
+        ..default()
+    };
+    if !is_viewport(main_camera.viewport.as_ref(), &main_viewport) {
+        main_camera.viewport = Some(main_viewport);
+    }
+
+    if split_view_r.follow_main_view {
+        split_view_r.focus = player_camera.focus(player_transform.translation);
+    }
+    let focus = split_view_r.focus;
+    let transform = Transform::from_translation(focus + player_camera.offset_from_focus()).looking_at(focus, Vec3::Y);
+    // Sharing the chunks of the main view, if it's the same map plane.
+    let render_layers = if split_view_r.map_id == scene_state_data_r.map_id {
+        RenderLayers::default()
+    } else {
+        RenderLayers::layer(SPLIT_VIEW_RENDER_LAYER)
+    };
+    let scaling_mode = ortho_scaling_mode(split_viewport.physical_size.as_vec2() / window.scale_factor());
+
+    let Ok((_, mut camera, mut camera_transform, mut projection, mut camera_layers)) = split_camera_q.single_mut()
+    else {
+        commands.spawn((
+            SplitViewCamera,
+            Camera3d::default(),
+            Camera {
+                order: SPLIT_VIEW_CAMERA_ORDER,
+                viewport: Some(split_viewport),
+                ..default()
+            },
+            Projection::Orthographic(OrthographicProjection {
+                scale: split_view_r.zoom,
+                scaling_mode,
+                near: -10000.0,
+                far: 10000.0,
+                ..OrthographicProjection::default_3d()
+            }),
+            transform,
+            render_layers,
+        ));
+        logger::one(None, LogSev::Debug, LogAbout::Camera, "Split view camera spawned.");
+        return;
+    };
+    if !is_viewport(camera.viewport.as_ref(), &split_viewport) {
+        camera.viewport = Some(split_viewport);
+    }
+    *camera_transform = transform;
+    if let Projection::Orthographic(ref mut ortho) = *projection {
+        ortho.scaling_mode = scaling_mode;
+        ortho.scale = split_view_r.zoom;
+    }
+    if *camera_layers != render_layers {
+        *camera_layers = render_layers;
+    }
+}
+
+/// Puts the chunks of the map plane shown only in the split view (and their statics) on its render layer, and the
+///  other ones on the default layer.
+fn sys_assign_chunk_render_layers(
+    mut commands: Commands,
+    split_view_r: Res<SplitView>,
+    scene_state_data_r: Res<SceneStateData>,
+    chunk_q: Query<(Entity, &LCMesh, Option<&RenderLayers>, Option<&Children>), Without<MapExportChunk>>,
+    statics_q: Query<Option<&RenderLayers>, With<SCMesh>>,
+) {
+    let split_only_map = split_view_r
+        .shown_map()
+        .filter(|&map_id| map_id != scene_state_data_r.map_id);
+    let split_layers = RenderLayers::layer(SPLIT_VIEW_RENDER_LAYER);
+    let default_layers = RenderLayers::default();
+    for (entity, chunk, chunk_layers, children) in chunk_q.iter() {
+        let layers = if split_only_map == Some(chunk.parent_map_id) {
+            &split_layers
+        } else {
+            &default_layers
+        };
+        if chunk_layers.unwrap_or(&default_layers) != layers {
+            commands.entity(entity).insert(layers.clone());
+        }
+        // The statics mesh is spawned later than its chunk, and isn't rendered with the layers of its parent.
+        for &child in children.into_iter().flat_map(|children| children.iter()) {
+            if let Ok(statics_layers) = statics_q.get(child)
+                && statics_layers.unwrap_or(&default_layers) != layers
+            {
+                commands.entity(child).insert(layers.clone());
+            }
+        }
+    }
+}
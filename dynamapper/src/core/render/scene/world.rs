@@ -1,4 +1,5 @@
 pub mod land;
+pub mod statics;
 
 use std::collections::HashMap;
 use bevy::prelude::*;
@@ -35,9 +36,10 @@ fn build(&self, app: &mut App) {
         log_plugin_build(self);
         app
             .insert_resource(WorldGeoData::default())
-            .add_plugins(
+            .add_plugins((
                 land::DrawLandChunkMeshPlugin { registered_by: "WorldPlugin" },
-            );
+                statics::DrawStaticsPlugin { registered_by: "WorldPlugin" },
+            ));
     }
 }
 
@@ -1,17 +1,67 @@
+pub mod animation;
+pub mod batch;
 pub mod draw_mesh;
+pub mod lod;
 pub mod mesh_material;
+pub mod seam_check;
 pub mod setup_base_mesh;
+pub mod shared_uniforms;
+pub mod terrain_overlay;
+pub mod tint;
 
 use crate::core::system_sets::*;
 use crate::prelude::*;
 use bevy::prelude::*;
-use mesh_material::LandCustomMaterial;
+use mesh_material::{LandBatchedMaterial, LandCustomMaterial};
+use uocf::geo::map::MapBlock;
 
-/// How many tiles per chunk row/column? (chunks are squared)
-pub const TILE_NUM_PER_CHUNK_DIM: u32 = 8;
-/// How many tiles in one chunk total?
-pub const TILE_NUM_PER_CHUNK_TOTAL: usize =
-    (TILE_NUM_PER_CHUNK_DIM * TILE_NUM_PER_CHUNK_DIM) as usize;
+/// Default number of tiles per chunk row/column (chunks are squared): one map block.
+pub const DEFAULT_TILE_NUM_PER_CHUNK_DIM: u32 = 8;
+/// Chunk sizes accepted from the settings. They're multiples of the map block size, so a chunk covers whole blocks.
+pub const ALLOWED_TILE_NUM_PER_CHUNK_DIM: [u32; 3] = [8, 16, 32];
+
+/// How many tiles per chunk row/column. Read from the settings at startup: the shared chunk mesh is built for it,
+///  so changing it requires a restart.
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LandChunkSize(pub u32);
+impl LandChunkSize {
+    /// Chunk size from the settings, falling back to the default one if it isn't allowed.
+    pub fn from_settings(render_settings: &SectRender) -> Self {
+        let size = render_settings.chunk_size_tiles;
+        if ALLOWED_TILE_NUM_PER_CHUNK_DIM.contains(&size) {
+            return Self(size);
+        }
+        logger::one(
+            None,
+            LogSev::Warn,
+            LogAbout::RenderWorldLand,
+            &format!(
+                "Invalid chunk size {size} in the settings (allowed: {ALLOWED_TILE_NUM_PER_CHUNK_DIM:?}), using {DEFAULT_TILE_NUM_PER_CHUNK_DIM}."
+            ),
+        );
+        Self(DEFAULT_TILE_NUM_PER_CHUNK_DIM)
+    }
+
+    /// How many tiles in one chunk total?
+    pub fn tile_num_total(self) -> usize {
+        (self.0 * self.0) as usize
+    }
+
+    /// How many map blocks per chunk row/column?
+    pub fn block_num_dim(self) -> u32 {
+        self.0 / MapBlock::CELLS_PER_ROW
+    }
+
+    /// Chunk grid coordinate of the chunk holding the given tile coordinate.
+    pub fn chunk_of_tile(self, tile: u32) -> u32 {
+        tile / self.0
+    }
+
+    /// Number of chunks needed to cover the given map size (tiles). The last chunk can be partially out of the map.
+    pub fn chunks_to_cover(self, map_size_tiles: u32) -> u32 {
+        map_size_tiles.div_ceil(self.0)
+    }
+}
 
 /// Tag component: Marks entities which are Land Chunk Meshes, allows queries for those entities.
 #[derive(Component)]
@@ -22,6 +72,16 @@ pub struct LCMesh {
     pub gy: u32,
 }
 
+/// Tag component: the LCMesh entity was moved to other chunk coordinates (gx, gy) instead of being despawned.
+/// Its material is rebuilt in place by the draw system, which then removes the tag.
+#[derive(Component)]
+pub struct LCRecycled;
+
+/// Tag component: tiles shown by the LCMesh entity were edited. Its material is rebuilt in place by the draw system,
+///  like for LCRecycled.
+#[derive(Component)]
+pub struct LCDirty;
+
 /// Establishes material, buffer pool, diagnostics, and the draw system.
 pub struct DrawLandChunkMeshPlugin {
     pub registered_by: &'static str,
@@ -30,14 +90,51 @@ pub struct DrawLandChunkMeshPlugin {
 
 impl Plugin for DrawLandChunkMeshPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(MaterialPlugin::<LandCustomMaterial>::default())
+        app.add_plugins((
+            MaterialPlugin::<LandCustomMaterial>::default(),
+            MaterialPlugin::<LandBatchedMaterial>::default(),
+            shared_uniforms::LandSharedUniformsPlugin {
+                registered_by: "DrawLandChunkMeshPlugin",
+            },
+        ))
+            .init_resource::<draw_mesh::MeshBuildPerfHistory>()
+            .init_resource::<seam_check::SeamCheck>()
+            .init_resource::<lod::LandChunkLods>()
+            .init_resource::<batch::LandBatch>()
+            .init_resource::<tint::LandTints>()
+            .add_event::<tint::TintRegionEvent>()
+            .add_event::<tint::ClearLandTintsEvent>()
             .add_systems(
                 Update,
-                (draw_mesh::sys_draw_spawned_land_chunks
-                    .in_set(SceneRenderLandSysSet::RenderLandChunks)
-                    .after(SceneRenderLandSysSet::SyncLandChunks)
-                    .run_if(in_state(AppState::InGame)),),
+                (
+                    draw_mesh::sys_mark_edited_land_chunks_dirty
+                        .before(SceneRenderLandSysSet::RenderLandChunks)
+                        .run_if(in_state(AppState::InGame)),
+                    tint::sys_update_land_tints
+                        .before(SceneRenderLandSysSet::RenderLandChunks)
+                        .run_if(in_state(AppState::InGame)),
+                    lod::sys_update_land_chunk_lods
+                        .before(SceneRenderLandSysSet::RenderLandChunks)
+                        .run_if(in_state(AppState::InGame)),
+                    draw_mesh::sys_draw_spawned_land_chunks
+                        .in_set(SceneRenderLandSysSet::RenderLandChunks)
+                        .after(SceneRenderLandSysSet::SyncLandChunks)
+                        .run_if(in_state(AppState::InGame)),
+                    animation::sys_update_animated_land_time
+                        .after(SceneRenderLandSysSet::RenderLandChunks)
+                        .run_if(in_state(AppState::InGame)),
+                    batch::sys_upload_land_batch
+                        .after(SceneRenderLandSysSet::RenderLandChunks)
+                        .run_if(in_state(AppState::InGame)),
+                    (terrain_overlay::sys_toggle_walkability_overlay, terrain_overlay::sys_toggle_grid_overlay)
+                        .run_if(in_state(AppState::InGame)),
+                    (seam_check::sys_check_land_chunk_seams, seam_check::sys_draw_seam_mismatches)
+                        .chain()
+                        .after(SceneRenderLandSysSet::RenderLandChunks)
+                        .run_if(in_state(AppState::InGame)),
+                ),
             )
-            .add_systems(Startup, setup_base_mesh::setup_land_mesh);
+            .add_systems(Startup, setup_base_mesh::setup_land_mesh)
+            .add_systems(OnEnter(AppState::InGame), batch::setup_land_batch);
     }
 }
@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use uocf::tiledata::TileData;
+
+use super::shared_uniforms::LandSharedUniforms;
+
+// Animated terrain (water, lava, ...) is animated in the land shader, by scrolling/warping the tile UVs over time.
+// The time comes from SceneUniform.time_seconds, shared by every land chunk (see shared_uniforms). It's refreshed only
+//  while some chunk contains animated tiles.
+
+/// Values for TileUniform.anim_kind. Keep in sync with the ANIM_KIND_* consts in land_base.wgsl.
+pub const ANIM_KIND_NONE: u32 = 0;
+pub const ANIM_KIND_WATER: u32 = 1;
+pub const ANIM_KIND_LAVA: u32 = 2;
+
+/// Scrolling speed (tile UV units per second) for each animation kind.
+const ANIM_SPEED_WATER: f32 = 0.05;
+const ANIM_SPEED_LAVA: f32 = 0.02;
+
+/// How often the time uniform is refreshed. Every refresh writes the shared uniform buffers, so we don't need to do
+///  that each frame.
+const ANIM_TIME_UPDATE_INTERVAL_SECS: f32 = 1.0 / 30.0;
+
+/// Tag component: added to LCMesh entities having at least one animated tile in their uniform grid.
+#[derive(Component)]
+pub struct LCAnimated;
+
+/// Per-tile animation parameters, as they are sent to the shader.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LandTileAnimation {
+    pub kind: u32,
+    pub speed: f32,
+}
+impl LandTileAnimation {
+    pub const NONE: Self = Self {
+        kind: ANIM_KIND_NONE,
+        speed: 0.0,
+    };
+
+    pub fn is_animated(&self) -> bool {
+        self.kind != ANIM_KIND_NONE
+    }
+
+    /// Animation metadata for a land tile, sourced from its tiledata flags.
+    pub fn from_tiledata(tiledata: &TileData, tile_id: u16) -> Self {
+        let Some(land_tile) = tiledata.land_tile(tile_id) else {
+            return Self::NONE;
+        };
+        let flags = &land_tile.flags;
+        if flags.wet() {
+            Self {
+                kind: ANIM_KIND_WATER,
+                speed: ANIM_SPEED_WATER,
+            }
+        } else if flags.animated() || flags.damaging() {
+            // Lava and other harmful terrain.
+            Self {
+                kind: ANIM_KIND_LAVA,
+                speed: ANIM_SPEED_LAVA,
+            }
+        } else {
+            Self::NONE
+        }
+    }
+}
+
+/// Keeps the time uniform in sync with the app time, while animated land chunks are spawned.
+pub fn sys_update_animated_land_time(
+    time_r: Res<Time>,
+    mut elapsed_since_update: Local<f32>,
+    mut shared_uniforms_r: ResMut<LandSharedUniforms>,
+    animated_chunks_q: Query<(), With<LCAnimated>>,
+) {
+    *elapsed_since_update += time_r.delta_secs();
+    if *elapsed_since_update < ANIM_TIME_UPDATE_INTERVAL_SECS || animated_chunks_q.is_empty() {
+        return;
+    }
+    *elapsed_since_update = 0.0;
+    shared_uniforms_r.scene.time_seconds = time_r.elapsed_secs();
+}
@@ -0,0 +1,198 @@
+use bevy::{
+    pbr::ExtendedMaterial,
+    prelude::*,
+    render::{render_asset::RenderAssetUsages, renderer::RenderDevice, storage::ShaderStorageBuffer},
+};
+use bytemuck::Zeroable;
+use std::collections::HashMap;
+
+use super::{LCMesh, mesh_material::*, shared_uniforms::land_material_base};
+use crate::{
+    core::texture_cache::{hues::HuePaletteTexture, land::cache::LandTextureCache},
+    prelude::*,
+};
+
+// Batched land chunks: instead of a material per chunk, every land chunk shares a single material (LandBatchedMaterial)
+//  and gets a slot in its storage buffers, where its land uniform and its tile data grid go. The slot is the MeshTag
+//  of the chunk entity: the shader reads it from the mesh instance (see LAND_BATCHED in land_base.wgsl).
+// Chunks then differ only by their mesh (one per lod step) and transform, so Bevy draws them in a few instanced
+//  batches instead of a draw call each, and building, recycling or editing a chunk doesn't create any asset: its slot
+//  is overwritten. Slots of despawned chunks are reused.
+// The buffers are uploaded whole when a slot changed, at most once per frame: a few hundred KB for the default chunk
+//  size and draw distance.
+// The per-chunk materials (LandCustomMaterial) are the fallback: they're used if render.land_batching is off, or if
+//  the GPU lacks storage buffers (WebGL2, some downlevel GPUs). The path is picked when entering the game.
+
+/// Storage buffers bound by LandBatchedMaterialExtension.
+const LAND_BATCH_STORAGE_BUFFERS: u32 = 2;
+
+/// Slots of the batched land chunks, and the material sharing them. Without a material, batching is off and the
+///  chunks get their own material.
+#[derive(Resource, Default)]
+pub struct LandBatch {
+    material: Option<Handle<LandBatchedMaterial>>,
+    land_uniforms: Vec<LandUniform>,
+    tile_texels: Vec<[u32; 4]>,
+    slot_by_chunk: HashMap<Entity, u32>,
+    free_slots: Vec<u32>,
+    /// Slots changed since the last upload.
+    dirty: bool,
+}
+impl LandBatch {
+    pub fn material(&self) -> Option<Handle<LandBatchedMaterial>> {
+        self.material.clone()
+    }
+
+    pub fn slot_count(&self) -> usize {
+        self.land_uniforms.len()
+    }
+
+    pub fn chunk_count(&self) -> usize {
+        self.slot_by_chunk.len()
+    }
+
+    fn texels_per_slot(&self) -> usize {
+        self.tile_texels.len() / self.land_uniforms.len().max(1)
+    }
+
+    /// Writes the land uniform and the tile data of a chunk in its slot, taking a free one if it has none yet.
+    ///  Returns the slot, to be set as the MeshTag of the chunk.
+    pub fn write_slot(&mut self, chunk: Entity, land_uniform: LandUniform, tile_texels: &[[u32; 4]]) -> u32 {
+        // The chunk size is fixed at startup, so every slot has the same number of texels.
+        debug_assert!(self.land_uniforms.is_empty() || self.texels_per_slot() == tile_texels.len());
+        let slot = match self.slot_by_chunk.get(&chunk) {
+            Some(&slot) => slot,
+            None => {
+                let slot = self.free_slots.pop().unwrap_or_else(|| {
+                    self.land_uniforms.push(LandUniform::zeroed());
+                    self.tile_texels.extend(std::iter::repeat_n([0; 4], tile_texels.len()));
+                    self.land_uniforms.len() as u32 - 1
+                });
+                self.slot_by_chunk.insert(chunk, slot);
+                slot
+            }
+        };
+        let texels_start = slot as usize * tile_texels.len();
+        self.land_uniforms[slot as usize] = land_uniform;
+        self.tile_texels[texels_start..texels_start + tile_texels.len()].copy_from_slice(tile_texels);
+        self.dirty = true;
+        slot
+    }
+
+    /// Changes the lod step in the land uniform of a batched chunk. Does nothing if the chunk has no slot.
+    pub fn set_lod_step(&mut self, chunk: Entity, lod_step: u32) {
+        if let Some(&slot) = self.slot_by_chunk.get(&chunk) {
+            self.land_uniforms[slot as usize].lod_step = lod_step;
+            self.dirty = true;
+        }
+    }
+
+    /// Tile data grid of a batched chunk, as written in its slot.
+    pub fn slot_texels(&self, chunk: Entity) -> Option<&[[u32; 4]]> {
+        let slot = *self.slot_by_chunk.get(&chunk)? as usize;
+        let texels_per_slot = self.texels_per_slot();
+        self.tile_texels
+            .get(slot * texels_per_slot..(slot + 1) * texels_per_slot)
+    }
+
+    /// Frees the slot of a despawned chunk. Its data is left there: no instance reads it anymore.
+    fn free_slot(&mut self, chunk: Entity) {
+        if let Some(slot) = self.slot_by_chunk.remove(&chunk) {
+            self.free_slots.push(slot);
+        }
+    }
+}
+
+/// Creates the shared material of the batched land chunks, unless batching is disabled or not supported by the GPU.
+pub fn setup_land_batch(
+    mut land_batch_r: ResMut<LandBatch>,
+    mut materials_batched_r: ResMut<Assets<LandBatchedMaterial>>,
+    mut buffers_r: ResMut<Assets<ShaderStorageBuffer>>,
+    settings_r: Res<Settings>,
+    render_device_r: Res<RenderDevice>,
+    land_texture_cache_r: Res<LandTextureCache>,
+    hue_palette_r: Res<HuePaletteTexture>,
+) {
+    if land_batch_r.material.is_some() {
+        return;
+    }
+    if !settings_r.render.land_batching {
+        logger::one(
+            None,
+            LogSev::Info,
+            LogAbout::RenderWorldLand,
+            "Land chunk batching disabled in the settings: a material per chunk.",
+        );
+        return;
+    }
+    let max_storage_buffers = render_device_r.limits().max_storage_buffers_per_shader_stage;
+    if max_storage_buffers < LAND_BATCH_STORAGE_BUFFERS {
+        logger::one(
+            None,
+            LogSev::Warn,
+            LogAbout::RenderWorldLand,
+            &format!(
+                "The GPU has {max_storage_buffers} storage buffers per shader stage, land chunk batching needs {LAND_BATCH_STORAGE_BUFFERS}: a material per chunk."
+            ),
+        );
+        return;
+    }
+
+    // Same bindings as a new per-chunk material (see draw_mesh::create_land_chunk_material). Storage buffers can't be
+    //  empty: they start with a zeroed slot, until the first chunk is written.
+    let material = ExtendedMaterial {
+        base: land_material_base(),
+        extension: LandBatchedMaterialExtension {
+            texarray_small: land_texture_cache_r.small.image_handle.clone(),
+            texarray_big: land_texture_cache_r.big.image_handle.clone(),
+            land_uniforms: buffers_r.add(ShaderStorageBuffer::new(
+                bytemuck::bytes_of(&LandUniform::zeroed()),
+                RenderAssetUsages::default(),
+            )),
+            hue_palette: hue_palette_r.image_handle.clone(),
+            tile_data: buffers_r.add(ShaderStorageBuffer::new(
+                bytemuck::bytes_of(&[0u32; 4]),
+                RenderAssetUsages::default(),
+            )),
+            normal_maps_small: land_texture_cache_r.small.normal_map_handle.clone(),
+            normal_maps_big: land_texture_cache_r.big.normal_map_handle.clone(),
+        },
+    };
+    land_batch_r.material = Some(materials_batched_r.add(material));
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::RenderWorldLand,
+        "Land chunk batching enabled.",
+    );
+}
+
+/// Frees the slots of the despawned land chunks, and uploads the slots changed this frame.
+pub fn sys_upload_land_batch(
+    mut land_batch_r: ResMut<LandBatch>,
+    mut removed_chunks: RemovedComponents<LCMesh>,
+    mut materials_batched_r: ResMut<Assets<LandBatchedMaterial>>,
+    mut buffers_r: ResMut<Assets<ShaderStorageBuffer>>,
+) {
+    for chunk in removed_chunks.read() {
+        land_batch_r.free_slot(chunk);
+    }
+    if !land_batch_r.dirty {
+        return;
+    }
+    let Some(material_handle) = land_batch_r.material.clone() else {
+        return;
+    };
+    profile_span!("upload_land_batch", slots = land_batch_r.slot_count());
+    // Touching the material also makes it bind the new buffers.
+    let Some(material) = materials_batched_r.get_mut(&material_handle) else {
+        return;
+    };
+    if let Some(buffer) = buffers_r.get_mut(&material.extension.land_uniforms) {
+        buffer.data = Some(bytemuck::cast_slice(&land_batch_r.land_uniforms).to_vec());
+    }
+    if let Some(buffer) = buffers_r.get_mut(&material.extension.tile_data) {
+        buffer.data = Some(bytemuck::cast_slice(&land_batch_r.tile_texels).to_vec());
+    }
+    land_batch_r.dirty = false;
+}
@@ -1,151 +1,297 @@
 #![allow(unused_parens, unused)]
 
 use bevy::{
+    ecs::system::SystemParam,
     pbr::{ExtendedMaterial, MaterialExtension},
     prelude::*,
     render::{
-        mesh::{Indices, PrimitiveTopology},
+        mesh::{Indices, MeshTag, PrimitiveTopology},
         render_asset::RenderAssetUsages,
-        render_resource::{AsBindGroup, ShaderRef, ShaderType},
+        render_resource::{AsBindGroup, Extent3d, ShaderRef, ShaderType, TextureDimension, TextureFormat},
     },
+    tasks::{ComputeTaskPool, ParallelSlice},
 };
 use bytemuck::Zeroable;
-use std::time::Instant;
+use bevy::platform::time::Instant;
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
     sync::Arc,
 };
+use uocf::art::Art;
 use uocf::geo::{
     land_texture_2d::{LandTextureSize, TexMap2D},
-    map::{MapBlock, MapBlockRelPos, MapCell, MapCellRelPos},
+    map::{MapBlock, MapBlockRelPos, MapCell, MapRectCells},
 };
+use uocf::tiledata::TileData;
 use wide::*;
 
-use super::TILE_NUM_PER_CHUNK_DIM;
-use super::{LCMesh, mesh_material::*};
+use super::animation::{LCAnimated, LandTileAnimation};
+use super::batch::LandBatch;
+use super::lod::{LOD_STEPS, LandChunkLods};
+use super::shared_uniforms::land_material_base;
+use super::terrain_overlay::{tile_flags_from_diff, tile_flags_from_tiledata};
+use super::tint::{LandTint, LandTints, tint_hue_at};
+use super::{LCDirty, LCMesh, LCRecycled, LandChunkSize, mesh_material::*};
 use crate::{
     core::{
-        constants,
-        maps::MapPlaneMetadata,
+        map_editor::LandCellsEditedEvent,
+        maps::{MapPlaneMetadata, compare::MapCompare},
+        render::export::MapExportChunk,
         render::scene::{
-            SceneStateData, camera::PlayerCamera, player::Player, world::WorldGeoData,
+            SceneStateData, camera::PlayerCamera, player::Player, split_view::SplitView, world::WorldGeoData,
         },
-        texture_cache::land::cache::*,
-        uo_files_loader::{MapPlanesRes, TexMap2DRes},
+        texture_cache::{hues::HuePaletteTexture, land::cache::*},
+        uo_files_loader::{ArtRes, MapPlanesRes, TexMap2DRes, TileDataRes},
     },
     prelude::*,
     util_lib::array::*,
 };
 
+// ---- Build timings ----
+
+/// Build timings kept for the diagnostics overlay.
+const MESH_BUILD_PERF_HISTORY_LEN: usize = 120;
+
+#[derive(Clone, Copy, Debug)]
+pub struct MeshBuildPerfSample {
+    pub chunks: usize,
+    pub build_time_us: u64,
+}
+
+/// Timings of the last runs of sys_draw_spawned_land_chunks which built chunks, oldest first.
+#[derive(Resource, Debug, Default)]
+pub struct MeshBuildPerfHistory {
+    samples: VecDeque<MeshBuildPerfSample>,
+}
+impl MeshBuildPerfHistory {
+    pub fn push(&mut self, sample: MeshBuildPerfSample) {
+        if self.samples.len() == MESH_BUILD_PERF_HISTORY_LEN {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    pub fn samples(&self) -> impl Iterator<Item = &MeshBuildPerfSample> {
+        self.samples.iter()
+    }
+
+    pub fn last(&self) -> Option<&MeshBuildPerfSample> {
+        self.samples.back()
+    }
+}
+
 // ---- Shared Mesh Resource and Setup ----
 
+/// Shared chunk meshes, one per lod step (see lod::LOD_STEPS).
 #[derive(Resource)]
-pub struct LandMeshHandle(pub Handle<Mesh>);
+pub struct LandMeshHandle(pub Vec<Handle<Mesh>>);
+impl LandMeshHandle {
+    pub fn for_lod_step(&self, lod_step: u32) -> Handle<Mesh> {
+        let lod = LOD_STEPS.iter().position(|&step| step == lod_step).unwrap_or(0);
+        self.0[lod].clone()
+    }
+}
 
-/// Creates a new material with the specific uniform data for a single land chunk.
-fn create_land_chunk_material(
-    materials_land_rref: &mut ResMut<Assets<LandCustomMaterial>>,
-    land_texture_cache_rref: &mut ResMut<LandTextureCache>,
-    images_rref: &mut ResMut<Assets<Image>>,
-    time_r: &Res<Time>,
-    shader_presets_r: &Res<LandShaderModePresets>,
-    texmap_2d: Arc<TexMap2D>,
+/// Tiles of the data grid around the chunk, on each side, needed for seamless normals.
+pub(super) const DATA_GRID_BORDER: u32 = 2;
+
+/// Side of the tile data grid of a chunk: the chunk tiles, the far edge of the mesh and the border (13 for 8x8 chunks).
+pub(super) fn chunk_tile_data_side(chunk_size: LandChunkSize) -> u32 {
+    chunk_size.0 + 1 + 2 * DATA_GRID_BORDER
+}
+
+/// First and last tile coordinates of the data grid of a chunk, on one axis, clamped to the map.
+fn data_grid_tile_span(chunk_size: LandChunkSize, chunk_coord: u32, map_size_tiles: u32) -> (u32, u32) {
+    let origin = chunk_coord * chunk_size.0;
+    let first = origin.saturating_sub(DATA_GRID_BORDER);
+    let last = (origin + chunk_size.0 + DATA_GRID_BORDER).min(map_size_tiles - 1);
+    (first, last)
+}
+
+/// Chunks (on one axis) whose data grid holds the given tile coordinate.
+fn chunks_with_tile_in_data_grid(chunk_size: LandChunkSize, tile: u32) -> std::ops::RangeInclusive<u32> {
+    let first = tile.saturating_sub(chunk_size.0 + DATA_GRID_BORDER).div_ceil(chunk_size.0);
+    let last = (tile + DATA_GRID_BORDER) / chunk_size.0;
+    first..=last
+}
+
+/// Creates the data texture holding the tile grid of a chunk (see TileUniform::to_texel).
+fn create_tile_data_image(texels: &[[u32; 4]], chunk_size: LandChunkSize) -> Image {
+    let side = chunk_tile_data_side(chunk_size);
+    Image::new(
+        Extent3d {
+            width: side,
+            height: side,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        bytemuck::cast_slice(texels).to_vec(),
+        TextureFormat::Rgba32Uint,
+        // Kept in the main world too, so that recycled chunks can overwrite it.
+        RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+    )
+}
+
+/// Cells of the tile data grid of a land chunk, with their animation and flags.
+struct LandChunkTileGrid {
+    chunk_data: LandChunkConstructionData,
+    cells: Vec<MapCell>,
+    animations: Vec<LandTileAnimation>,
+    tile_flags: Vec<u32>,
+    /// Hue of each cell, from the tints covering it (see tint).
+    hues: Vec<u16>,
+    has_animated_tiles: bool,
+}
+
+/// Land uniform and tile data of a land chunk, ready to be put in its material.
+struct LandChunkBuildOutput {
+    chunk_data: LandChunkConstructionData,
+    land_uniform: LandUniform,
+    tile_texels: Vec<[u32; 4]>,
+    has_animated_tiles: bool,
+}
+
+/// Gathers the tile data grid of a single land chunk from the loaded block data.
+/// other_blocks_data_ref holds the same blocks in the other version of a compared map plane (see maps::compare): the
+///  tiles which differ get the TILE_FLAG_CHANGED_* tile flags.
+/// tints are the ones of the map plane, giving the hue of the tiles.
+/// Only reads UO data, so it can run in parallel tasks.
+fn gather_land_chunk_tile_grid(
+    tiledata: &TileData,
+    chunk_size: LandChunkSize,
+    map_plane_metadata_ref: &MapPlaneMetadata,
     chunk_data_ref: &LandChunkConstructionData,
     blocks_data_ref: &BTreeMap<MapBlockRelPos, MapBlock>,
-) -> Handle<LandCustomMaterial> {
-    let chunk_origin_tile_units_x =
-        chunk_data_ref.chunk_origin_chunk_units_x * TILE_NUM_PER_CHUNK_DIM;
-    let chunk_origin_tile_units_z =
-        chunk_data_ref.chunk_origin_chunk_units_z * TILE_NUM_PER_CHUNK_DIM;
+    other_blocks_data_ref: Option<&BTreeMap<MapBlockRelPos, MapBlock>>,
+    tints: &[LandTint],
+) -> LandChunkTileGrid {
+    let chunk_origin_tile_units_x = chunk_data_ref.chunk_origin_chunk_units_x * chunk_size.0;
+    let chunk_origin_tile_units_z = chunk_data_ref.chunk_origin_chunk_units_z * chunk_size.0;
 
     // Helper to fetch a cell from the loaded block data.
-    fn get_cell<'a>(
-        blocks_data: &'a BTreeMap<MapBlockRelPos, MapBlock>,
-        world_tile_x: u32,
-        world_tile_z: u32,
-    ) -> &'a MapCell {
-        let chunk_rel_coords = MapBlockRelPos {
-            x: world_tile_x / TILE_NUM_PER_CHUNK_DIM,
-            y: world_tile_z / TILE_NUM_PER_CHUNK_DIM,
-        };
-        let tile_rel_coords = MapCellRelPos {
-            x: world_tile_x % TILE_NUM_PER_CHUNK_DIM,
-            y: world_tile_z % TILE_NUM_PER_CHUNK_DIM,
+    fn get_cell(blocks_data: &BTreeMap<MapBlockRelPos, MapBlock>, world_tile_x: u32, world_tile_z: u32) -> MapCell {
+        let block_rel_coords = MapBlockRelPos {
+            x: MapCell::coords_of_parent_block_x(world_tile_x),
+            y: MapCell::coords_of_parent_block_y(world_tile_z),
         };
-        blocks_data
-            .get(&chunk_rel_coords)
+        *blocks_data
+            .get(&block_rel_coords)
             .unwrap()
-            .cell(tile_rel_coords.x, tile_rel_coords.y)
+            .cell(MapCell::coords_in_block_x(world_tile_x), MapCell::coords_in_block_y(world_tile_z))
             .unwrap()
     }
 
-    const CHUNK_TILE_DATA_SIDE: i32 = (TILE_NUM_PER_CHUNK_DIM + 5) as i32; // 8 + 5 = 13
-    const BORDER: i32 = 2;
-
-    // 1) Gather all cell data for the 13x13 grid in one pass.
-    let mut cell_grid: Vec<&MapCell> =
-        Vec::with_capacity((CHUNK_TILE_DATA_SIDE * CHUNK_TILE_DATA_SIDE) as usize);
-    for gy in -BORDER..(TILE_NUM_PER_CHUNK_DIM as i32 + BORDER + 1) {
-        for gx in -BORDER..(TILE_NUM_PER_CHUNK_DIM as i32 + BORDER + 1) {
-            let world_tx = (chunk_origin_tile_units_x as i32 + gx).max(0) as u32;
-            let world_tz = (chunk_origin_tile_units_z as i32 + gy).max(0) as u32;
-            cell_grid.push(get_cell(blocks_data_ref, world_tx, world_tz));
+    const BORDER: i32 = DATA_GRID_BORDER as i32;
+    let data_side = chunk_tile_data_side(chunk_size);
+    // Chunks at the map edges can be partially out of the map (the map size isn't always a multiple of the chunk
+    //  size): tiles out of the map repeat the edge ones.
+    let map_last_tile_x = map_plane_metadata_ref.width as i32 - 1;
+    let map_last_tile_z = map_plane_metadata_ref.height as i32 - 1;
+
+    let mut cells: Vec<MapCell> = Vec::with_capacity((data_side * data_side) as usize);
+    let mut diff_flags: Vec<u32> = Vec::new();
+    let mut hues: Vec<u16> = Vec::with_capacity((data_side * data_side) as usize);
+    for gy in -BORDER..(data_side as i32 - BORDER) {
+        for gx in -BORDER..(data_side as i32 - BORDER) {
+            let world_tx = (chunk_origin_tile_units_x as i32 + gx).clamp(0, map_last_tile_x) as u32;
+            let world_tz = (chunk_origin_tile_units_z as i32 + gy).clamp(0, map_last_tile_z) as u32;
+            let cell = get_cell(blocks_data_ref, world_tx, world_tz);
+            if let Some(other_blocks_data) = other_blocks_data_ref {
+                diff_flags.push(tile_flags_from_diff(&cell, &get_cell(other_blocks_data, world_tx, world_tz)));
+            }
+            cells.push(cell);
+            hues.push(tint_hue_at(tints, world_tx, world_tz));
         }
     }
+    let animations: Vec<LandTileAnimation> = cells
+        .iter()
+        .map(|cell| LandTileAnimation::from_tiledata(tiledata, cell.id))
+        .collect();
+    let has_animated_tiles = animations.iter().any(LandTileAnimation::is_animated);
+    let mut tile_flags: Vec<u32> = cells
+        .iter()
+        .map(|cell| tile_flags_from_tiledata(tiledata, cell.id))
+        .collect();
+    for (tile_flags, diff_flags) in tile_flags.iter_mut().zip(&diff_flags) {
+        *tile_flags |= diff_flags;
+    }
+    LandChunkTileGrid {
+        chunk_data: *chunk_data_ref,
+        cells,
+        animations,
+        tile_flags,
+        hues,
+        has_animated_tiles,
+    }
+}
 
-    // 2) Prepare Uniforms. This now includes all data for the 13x13 grid.
-    let mut mat_ext_land_uniforms = LandUniform::zeroed();
-    mat_ext_land_uniforms.chunk_origin = Vec2::new(
-        chunk_origin_tile_units_x as f32,
-        chunk_origin_tile_units_z as f32,
+/// Builds the land uniform and the tile data (the tile data grid, as texels) of a single land chunk.
+/// The textures of the grid must be resident already: their layers are looked up in texture_layers, so that this can
+///  run in parallel tasks too.
+fn build_land_chunk_uniform(
+    chunk_size: LandChunkSize,
+    tile_grid: &LandChunkTileGrid,
+    texture_layers: &HashMap<u16, (LandTextureSize, u32)>,
+) -> LandChunkBuildOutput {
+    let mut land_uniform = LandUniform::zeroed();
+    land_uniform.chunk_origin = Vec2::new(
+        (tile_grid.chunk_data.chunk_origin_chunk_units_x * chunk_size.0) as f32,
+        (tile_grid.chunk_data.chunk_origin_chunk_units_z * chunk_size.0) as f32,
     );
+    land_uniform.chunk_size = chunk_size.0;
 
-    // Preload all unique textures for the 13x13 grid.
-    let unique_tile_ids: HashSet<u16> = cell_grid.iter().map(|cell| cell.id).collect();
-    land_texture_cache_rref.preload_textures(images_rref, texmap_2d.clone(), &unique_tile_ids);
-
-    // Fill the 13x13 uniform grid.
-    for i in 0..cell_grid.len() {
-        let tile_ref = cell_grid[i];
-        let (texture_size, layer) = land_texture_cache_rref.get_texture_size_layer(
-            images_rref,
-            texmap_2d.clone(),
-            tile_ref.id,
-        );
-        mat_ext_land_uniforms.tiles[i] = TileUniform {
-            tile_height: scale_uo_z_to_bevy_units(tile_ref.z as f32),
-            texture_size: match texture_size {
-                LandTextureSize::Small => 0,
-                LandTextureSize::Big => 1,
-            },
-            texture_layer: layer,
-            texture_hue: 0,
-        };
+    let tile_texels: Vec<[u32; 4]> = tile_grid
+        .cells
+        .iter()
+        .zip(&tile_grid.animations)
+        .zip(&tile_grid.tile_flags)
+        .zip(&tile_grid.hues)
+        .map(|(((cell, animation), &tile_flags), &hue)| {
+            let (texture_size, layer) = texture_layers[&cell.id];
+            TileUniform {
+                tile_height: scale_uo_z_to_bevy_units(cell.z as f32),
+                texture_size: match texture_size {
+                    LandTextureSize::Small => 0,
+                    LandTextureSize::Big => 1,
+                },
+                texture_layer: layer,
+                tile_flags,
+                texture_hue: hue as u32,
+                anim_kind: animation.kind,
+                anim_speed: animation.speed,
+            }
+            .to_texel()
+        })
+        .collect();
+    LandChunkBuildOutput {
+        chunk_data: tile_grid.chunk_data,
+        land_uniform,
+        tile_texels,
+        has_animated_tiles: tile_grid.has_animated_tiles,
     }
+}
 
-    // Scene data
-    let mut mat_ext_scene_uniform = SceneUniform {
-        camera_position: PlayerCamera::BASE_OFFSET_FROM_PLAYER,
-        light_direction: constants::BAKED_GLOBAL_LIGHT.normalize(),
-        time_seconds: time_r.elapsed().as_secs_f32(),
-        global_lighting: 1.0,
-    };
-
-    // Tunables are separate.
-    let preset = &shader_presets_r.classic.morning;
-    let mat_ext_tunables_uniform = preset.effects;
-    let mat_ext_lighting_uniform = preset.lighting;
-
-    // 3) Create and return the material handle.
+/// Creates a new material with the specific uniform data for a single land chunk.
+fn create_land_chunk_material(
+    materials_land_rref: &mut ResMut<Assets<LandCustomMaterial>>,
+    land_texture_cache_ref: &LandTextureCache,
+    images_rref: &mut ResMut<Assets<Image>>,
+    hue_palette_r: &Res<HuePaletteTexture>,
+    chunk_size: LandChunkSize,
+    built_chunk_ref: &LandChunkBuildOutput,
+) -> Handle<LandCustomMaterial> {
+    // Create and return the material handle. The scene, effects and lighting uniforms are shared by every chunk: the
+    //  base binds them (see shared_uniforms).
     let mat = ExtendedMaterial {
-        base: StandardMaterial::default(),
+        base: land_material_base(),
         extension: LandMaterialExtension {
-            texarray_small: land_texture_cache_rref.small.image_handle.clone(),
-            texarray_big: land_texture_cache_rref.big.image_handle.clone(),
-            land_uniform: mat_ext_land_uniforms,
-            scene_uniform: mat_ext_scene_uniform,
-            effects_uniform: mat_ext_tunables_uniform,
-            lighting_uniform: mat_ext_lighting_uniform,
+            texarray_small: land_texture_cache_ref.small.image_handle.clone(),
+            texarray_big: land_texture_cache_ref.big.image_handle.clone(),
+            land_uniform: built_chunk_ref.land_uniform,
+            hue_palette: hue_palette_r.image_handle.clone(),
+            tile_data: images_rref.add(create_tile_data_image(&built_chunk_ref.tile_texels, chunk_size)),
+            normal_maps_small: land_texture_cache_ref.small.normal_map_handle.clone(),
+            normal_maps_big: land_texture_cache_ref.big.normal_map_handle.clone(),
         },
     };
     materials_land_rref.add(mat)
@@ -172,209 +318,300 @@ struct LandChunkConstructionData {
     chunk_origin_chunk_units_z: u32,
 }
 
+/// What the land chunks are built from: the UO data of the map planes, and the map planes shown.
+#[derive(SystemParam)]
+pub struct LandChunkSources<'w> {
+    map_planes_r: Res<'w, MapPlanesRes>,
+    map_compare_r: Res<'w, MapCompare>,
+    texmap_2d_r: Res<'w, TexMap2DRes>,
+    art_r: Res<'w, ArtRes>,
+    tiledata_r: Res<'w, TileDataRes>,
+    world_geo_data_r: Res<'w, WorldGeoData>,
+    scene_state_data_r: Res<'w, SceneStateData>,
+    split_view_r: Res<'w, SplitView>,
+    land_chunk_lods_r: Res<'w, LandChunkLods>,
+    land_tints_r: Res<'w, LandTints>,
+}
+
 /// Main system: finds visible land map chunks and ensures their mesh is generated and rendered.
 pub fn sys_draw_spawned_land_chunks(
     mut commands: Commands,
-    mut meshes_r: ResMut<Assets<Mesh>>,
     mut materials_land_r: ResMut<Assets<LandCustomMaterial>>,
     mut cache_r: ResMut<LandTextureCache>,
     mut images_r: ResMut<Assets<Image>>,
-    mut map_planes_r: ResMut<MapPlanesRes>,
-    time_r: Res<Time>,
-    shader_presets_r: Res<LandShaderModePresets>,
-    texmap_2d_r: Res<TexMap2DRes>,
-    world_geo_data_r: Res<WorldGeoData>,
-    scene_state_data_r: Res<SceneStateData>,
+    sources: LandChunkSources,
+    hue_palette_r: Res<HuePaletteTexture>,
+    chunk_size_r: Res<LandChunkSize>,
     player_q: Query<&Player>,
-    cam_q: Query<&Transform, With<Camera3d>>,
-    chunk_q: Query<(Entity, &LCMesh, Option<&Mesh3d>)>,
+    cam_q: Query<&Transform, With<PlayerCamera>>,
+    chunk_q: Query<(
+        Entity,
+        &LCMesh,
+        Option<&Mesh3d>,
+        Option<&MeshMaterial3d<LandCustomMaterial>>,
+        Has<LCRecycled>,
+        Has<LCDirty>,
+        Has<MapExportChunk>,
+    )>,
     visible_chunk_q: Query<(&LCMesh, &Mesh3d)>,
     land_mesh_handle_r: Res<LandMeshHandle>,
+    mut land_batch_r: ResMut<LandBatch>,
+    mut perf_history_r: ResMut<MeshBuildPerfHistory>,
 ) {
+    let LandChunkSources {
+        map_planes_r,
+        map_compare_r,
+        texmap_2d_r,
+        art_r,
+        tiledata_r,
+        world_geo_data_r,
+        scene_state_data_r,
+        split_view_r,
+        land_chunk_lods_r,
+        land_tints_r,
+    } = sources;
+
     // Step 1: Get camera/player state.
     let cam_pos = cam_q.single().unwrap().translation;
     let player_entity = player_q.single().expect("More than 1 player!");
     let current_map_id = scene_state_data_r.map_id;
-    let map_plane_metadata = world_geo_data_r.maps.get(&current_map_id).expect(&format!(
-        "Requested metadata for uncached map {current_map_id}"
+    // Chunks of the split view plane (see split_view) are built once the player plane has none left to build: a
+    //  single map plane per run.
+    let needs_build = |map_id: u32| {
+        chunk_q.iter().any(|(_, chunk_data, mesh_handle, _, recycled, dirty, _)| {
+            chunk_data.parent_map_id == map_id && (mesh_handle.is_none() || recycled || dirty)
+        })
+    };
+    let build_map_id = match split_view_r.shown_map() {
+        Some(split_map_id) if world_geo_data_r.maps.contains_key(&split_map_id) && !needs_build(current_map_id) => {
+            split_map_id
+        }
+        _ => current_map_id,
+    };
+    let map_plane_metadata = world_geo_data_r.maps.get(&build_map_id).expect(&format!(
+        "Requested metadata for uncached map {build_map_id}"
     ));
 
     // Step 1: Collect all primary chunks that need meshing into a HashMap.
     // This maps coordinates to an entity, ensuring we don't lose the entity reference
     // and allows for fast lookups.
-    let mut primary_chunks = std::collections::HashMap::new();
-    for (entity, chunk_data, mesh_handle) in chunk_q.iter() {
-        // Process chunks that don't have a mesh yet.
-        if mesh_handle.is_none() {
+    let mut primary_chunks = HashMap::new();
+    // Materials of the recycled chunks, to be updated in place.
+    let mut recycled_materials = HashMap::new();
+    // Export chunks are drawn at full detail, the others with the lod step of the plane (see lod).
+    let mut export_chunks = HashSet::new();
+    for (entity, chunk_data, mesh_handle, material_handle, recycled, dirty, export) in chunk_q.iter() {
+        // Process chunks that don't have a mesh yet, were moved to other coordinates or show edited tiles.
+        // Chunks pooled for other map planes are built when they're shown again.
+        let recycled = recycled || dirty;
+        if (mesh_handle.is_none() || recycled) && chunk_data.parent_map_id == build_map_id {
             primary_chunks.insert((chunk_data.gx, chunk_data.gy), entity);
+            if export {
+                export_chunks.insert(entity);
+            }
+            if recycled && let Some(material_handle) = material_handle {
+                recycled_materials.insert(entity, material_handle.0.clone());
+            }
         }
     }
 
     if primary_chunks.is_empty() {
         return;
     }
+    profile_span!("build_land_chunks", map_id = build_map_id, chunks = primary_chunks.len());
 
-    // Step 2: Build the final set of chunks whose data we need to construct.
-    // This includes the primary chunks and their immediate non-primary neighbors
-    // (to get data for mesh stitching).
-    let mut spawn_targets = HashSet::<LandChunkConstructionData>::new();
-
-    #[rustfmt::skip]
-    const NEIGHBOR_OFFSETS: &[(i32, i32)] = &[
-        (-1, -1), (0, -1), (1, -1),
-        (-1,  0),          (1,  0), // The primary chunk (0,0) is handled separately.
-        (-1,  1), (0,  1), (1,  1),
-    ];
-
-    // Iterate through the primary chunks. Add them to the target list,
-    // then add any neighbors that are not already primary chunks themselves.
-    for (&(gx, gy), &entity) in primary_chunks.iter() {
-        // Add the primary chunk itself. Its entity is guaranteed to be Some(entity).
-        spawn_targets.insert(LandChunkConstructionData {
+    // Step 2: Build the set of chunks whose data we need to construct.
+    let chunk_size = *chunk_size_r;
+    let mut spawn_targets: Vec<LandChunkConstructionData> = primary_chunks
+        .iter()
+        .map(|(&(gx, gy), &entity)| LandChunkConstructionData {
             entity: Some(entity),
             chunk_origin_chunk_units_x: gx,
             chunk_origin_chunk_units_z: gy,
-        });
-
-        // Add its valid neighbors that ARE NOT already primary chunks.
-        // This ensures we get their data for seamless mesh generation without
-        // overwriting a primary chunk's entity reference.
-        for (dx, dy) in NEIGHBOR_OFFSETS {
-            let nx = gx as i32 + dx;
-            let ny = gy as i32 + dy;
-
-            // Ensure the neighbor is within map boundaries.
-            if nx >= 0
-                && nx < map_plane_metadata.width as i32
-                && ny >= 0
-                && ny < map_plane_metadata.height as i32
-            {
-                let neighbor_coords = (nx as u32, ny as u32);
-
-                // If the neighbor is not a primary chunk, we need its data for the mesh.
-                // Since `spawn_targets` is a HashSet, duplicate inserts of the same
-                // neighbor from different primary chunks are handled automatically.
-                if !primary_chunks.contains_key(&neighbor_coords) {
-                    spawn_targets.insert(LandChunkConstructionData {
-                        entity: None, // It's just a neighbor, not a spawned entity.
-                        chunk_origin_chunk_units_x: neighbor_coords.0,
-                        chunk_origin_chunk_units_z: neighbor_coords.1,
-                    });
-                }
+        })
+        .collect();
+    // Paranoid check, shouldn't ever happen.
+    spawn_targets.retain(|chunk_data| {
+        let valid = chunk_data.entity.is_some_and(|entity| commands.get_entity(entity).is_ok());
+        if !valid {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::RenderWorldLand,
+                "Skipping drawing of invalid/unspawned entity at stage 'sys_draw_spawned_land_chunks'.",
+            );
+        }
+        valid
+    });
+
+    // Step 3: Collect the MapBlockRelPos covered by the tile data grid of every target chunk and load them from UO
+    //  data. The grid reaches into the neighboring chunks (to get data for mesh stitching), and a block can be
+    //  shared by the grids of adjacent chunks: the set removes the duplicates.
+    let mut blocks_to_draw_set = BTreeSet::<MapBlockRelPos>::new();
+    for chunk_data in spawn_targets.iter() {
+        let (tx0, tx1) =
+            data_grid_tile_span(chunk_size, chunk_data.chunk_origin_chunk_units_x, map_plane_metadata.width);
+        let (tz0, tz1) =
+            data_grid_tile_span(chunk_size, chunk_data.chunk_origin_chunk_units_z, map_plane_metadata.height);
+        for by in MapCell::coords_of_parent_block_y(tz0)..=MapCell::coords_of_parent_block_y(tz1) {
+            for bx in MapCell::coords_of_parent_block_x(tx0)..=MapCell::coords_of_parent_block_x(tx1) {
+                blocks_to_draw_set.insert(MapBlockRelPos { x: bx, y: by });
             }
         }
     }
+    let mut blocks_to_draw: Vec<MapBlockRelPos> = blocks_to_draw_set.into_iter().collect();
+    //blocks_to_draw.sort();    // Already done by load_blocks.
 
-    // Step 3: Collect the MapBlockRelPos for all target chunks and load them from UO data.
-    let mut blocks_to_draw: Vec<MapBlockRelPos> = spawn_targets
-        .iter()
-        .map(|d| MapBlockRelPos {
-            x: d.chunk_origin_chunk_units_x,
-            y: d.chunk_origin_chunk_units_z,
+    // Copies of the blocks: the map plane is locked only while loading them from disk/memory.
+    let (blocks_data, other_blocks_data) = {
+        profile_span!("load_map_blocks", blocks = blocks_to_draw.len());
+        let blocks_data: BTreeMap<MapBlockRelPos, MapBlock> = map_planes_r
+            .get(build_map_id)
+            .expect("Requested map plane metadata is uncached?")
+            .load_blocks_cloned(&mut blocks_to_draw)
+            .expect("Can't load map blocks");
+        // The same blocks in the other version of a compared map plane, to find the tiles which differ.
+        let other_blocks_data: Option<BTreeMap<MapBlockRelPos, MapBlock>> =
+            map_compare_r.hidden_plane(build_map_id).map(|other_map_plane| {
+                other_map_plane
+                    .load_blocks_cloned(&mut blocks_to_draw)
+                    .expect("Can't load compared map blocks")
+            });
+        (blocks_data, other_blocks_data)
+    };
+
+    // Step 4: Gather the tile data grid of every chunk, in parallel tasks: they only read the block data loaded above.
+    let build_time_start = Instant::now();
+    let task_pool = ComputeTaskPool::get();
+    let tiledata: &TileData = &tiledata_r.0;
+    let tints = land_tints_r.on_map(build_map_id);
+    let tile_grids: Vec<LandChunkTileGrid> = spawn_targets
+        .par_splat_map(task_pool, None, |_, chunk_batch| {
+            profile_span!("gather_land_chunk_tile_grids", chunks = chunk_batch.len());
+            chunk_batch
+                .iter()
+                .map(|chunk_data| {
+                    gather_land_chunk_tile_grid(
+                        tiledata,
+                        chunk_size,
+                        map_plane_metadata,
+                        chunk_data,
+                        &blocks_data,
+                        other_blocks_data.as_ref(),
+                        &tints,
+                    )
+                })
+                .collect::<Vec<_>>()
         })
+        .into_iter()
+        .flatten()
         .collect();
-    //blocks_to_draw.sort();    // Already done by load_blocks.
 
-    let mut blocks_data = BTreeMap::<MapBlockRelPos, MapBlock>::new();
+    // Step 5: Make the textures of all the grids resident, with a single batched upload. The texture cache and the
+    //  image assets can't be shared with the tasks: this stays on the main thread.
+    let unique_tile_ids: HashSet<u16> = tile_grids
+        .iter()
+        .flat_map(|tile_grid| tile_grid.cells.iter().map(|cell| cell.id))
+        .collect();
+    cache_r.preload_textures(&mut images_r, texmap_2d_r.0.clone(), art_r.0.clone(), &unique_tile_ids);
+    let mut texture_layers = HashMap::<u16, (LandTextureSize, u32)>::with_capacity(unique_tile_ids.len());
     {
-        // This lock only needed during the block loading from disk/memory.
-        let mut uo_data_map_planes_arc = map_planes_r.0.clone();
-        let mut uo_data_map_plane = uo_data_map_planes_arc
-            .get_mut(&current_map_id)
-            .expect("Requested map plane metadata is uncached?");
-        uo_data_map_plane
-            .load_blocks(&mut blocks_to_draw)
-            .expect("Can't load map blocks");
-        for block_coords in blocks_to_draw {
-            let block_ref = uo_data_map_plane
-                .block(block_coords)
-                .expect("Requested map block is uncached?");
-            let unique = blocks_data
-                .insert(block_coords, block_ref.clone())
-                .is_none();
-            if !unique {
-                panic!("Adding again the same key?");
-            }
+        profile_span!("land_texture_layers", textures = unique_tile_ids.len());
+        for tile_id in unique_tile_ids {
+            let size_layer =
+                cache_r.get_texture_size_layer(&mut images_r, texmap_2d_r.0.clone(), art_r.0.clone(), tile_id);
+            texture_layers.insert(tile_id, size_layer);
         }
     }
 
-    // Step 4: For every chunk that corresponds to a current entity (not filler neighbors), build the mesh.
-    let build_time_start = Instant::now();
-    for chunk_data in spawn_targets {
-        let entity = chunk_data.entity;
-        if entity.is_none() {
-            continue;
-        }
-        // Paranoid check, shouldn't ever happen.
-        if commands.get_entity(entity.unwrap()).is_err() {
-            // TODO: change to logger::one.
-            println!(
-                "Skipping drawing of invalid/unspawned entity at stage 'sys_draw_spawned_land_chunks'."
-            );
-            continue;
-        }
+    // Step 6: Build the uniform and the tile data of every chunk, in parallel tasks.
+    let built_chunks: Vec<LandChunkBuildOutput> = tile_grids
+        .par_splat_map(task_pool, None, |_, tile_grid_batch| {
+            profile_span!("build_land_chunk_uniforms", chunks = tile_grid_batch.len());
+            tile_grid_batch
+                .iter()
+                .map(|tile_grid| build_land_chunk_uniform(chunk_size, tile_grid, &texture_layers))
+                .collect::<Vec<_>>()
+        })
+        .into_iter()
+        .flatten()
+        .collect();
 
+    // Step 7: Only the asset insertion is left to do, on the main thread.
+    let built_chunks_count = built_chunks.len();
+    profile_span!("insert_land_chunk_assets", chunks = built_chunks_count);
+    let plane_lod_step = land_chunk_lods_r.step(build_map_id);
+    for built_chunk in built_chunks {
+        let entity = built_chunk.chunk_data.entity.unwrap();
+        let recycled_material = recycled_materials.get(&entity);
+        let lod_step = if export_chunks.contains(&entity) { 1 } else { plane_lod_step };
         draw_land_chunk(
             &mut commands,
-            &mut meshes_r,
             &mut materials_land_r,
-            &mut cache_r,
+            &mut land_batch_r,
+            &cache_r,
             &mut images_r,
-            &time_r,
-            &shader_presets_r,
-            texmap_2d_r.0.clone(),
-            &map_plane_metadata,
-            &chunk_data,
-            &blocks_data,
+            &hue_palette_r,
+            chunk_size,
+            built_chunk,
             // pass the shared mesh handle
             &land_mesh_handle_r,
+            lod_step,
+            recycled_material,
         );
     }
-    let build_time: u128 = build_time_start.elapsed().as_micros();
-    println!("Perf: chunk rendered in {build_time} µs.");
+    perf_history_r.push(MeshBuildPerfSample {
+        chunks: built_chunks_count,
+        build_time_us: build_time_start.elapsed().as_micros() as u64,
+    });
 }
 
 // Completed!
 fn draw_land_chunk(
     commands: &mut Commands,
-    meshes_rref: &mut ResMut<Assets<Mesh>>,
     materials_land_rref: &mut ResMut<Assets<LandCustomMaterial>>,
-    land_texture_cache_rref: &mut ResMut<LandTextureCache>,
+    land_batch_rref: &mut ResMut<LandBatch>,
+    land_texture_cache_ref: &LandTextureCache,
     images_rref: &mut ResMut<Assets<Image>>,
-    time_r: &Res<Time>,
-    shader_presets_r: &Res<LandShaderModePresets>,
-    texmap_2d: Arc<TexMap2D>,
-    map_plane_metadata_ref: &MapPlaneMetadata,
-    chunk_data_ref: &LandChunkConstructionData,
-    blocks_data_ref: &BTreeMap<MapBlockRelPos, MapBlock>,
+    hue_palette_r: &Res<HuePaletteTexture>,
+    chunk_size: LandChunkSize,
+    mut built_chunk: LandChunkBuildOutput,
     land_mesh_handle_r: &Res<LandMeshHandle>,
+    lod_step: u32,
+    recycled_material: Option<&Handle<LandCustomMaterial>>,
 ) {
-    // Use the mesh prebuilt in setup_land_mesh.
-    let chunk_mesh_handle: Handle<Mesh> = land_mesh_handle_r.0.clone();
-
-    // Create the material with create_land_chunk_material and attach it to the entity for the new map chunk.
-    let chunk_material_handle: Handle<LandCustomMaterial> = create_land_chunk_material(
-        materials_land_rref,
-        land_texture_cache_rref,
-        images_rref,
-        time_r,
-        shader_presets_r,
-        texmap_2d,
-        chunk_data_ref,
-        blocks_data_ref,
-    );
+    // Use the mesh prebuilt in setup_land_mesh for the lod step.
+    let chunk_mesh_handle: Handle<Mesh> = land_mesh_handle_r.for_lod_step(lod_step);
+    built_chunk.land_uniform.lod_step = lod_step;
+
+    // Batched chunks share the material of the batch: their data goes in their slot (see batch). The others have their
+    //  own material.
+    let entity = built_chunk.chunk_data.entity.unwrap();
+    let chunk_material = match land_batch_rref.material() {
+        Some(batch_material) => {
+            let slot = land_batch_rref.write_slot(entity, built_chunk.land_uniform, &built_chunk.tile_texels);
+            LandChunkMaterial::Batched(batch_material, slot)
+        }
+        None => LandChunkMaterial::Own(own_land_chunk_material(
+            materials_land_rref,
+            land_texture_cache_ref,
+            images_rref,
+            hue_palette_r,
+            chunk_size,
+            &built_chunk,
+            recycled_material,
+        )),
+    };
 
     // Compute chunk origin (in tile units) for the transform.
-    let chunk_origin_tile_units_x =
-        chunk_data_ref.chunk_origin_chunk_units_x * TILE_NUM_PER_CHUNK_DIM;
-    let chunk_origin_tile_units_z =
-        chunk_data_ref.chunk_origin_chunk_units_z * TILE_NUM_PER_CHUNK_DIM;
+    let chunk_data_ref = &built_chunk.chunk_data;
+    let chunk_origin_tile_units_x = chunk_data_ref.chunk_origin_chunk_units_x * chunk_size.0;
+    let chunk_origin_tile_units_z = chunk_data_ref.chunk_origin_chunk_units_z * chunk_size.0;
 
     // 7) Attach to entity
-    if let Ok(mut entity_commands) = commands.get_entity(chunk_data_ref.entity.unwrap()) {
+    if let Ok(mut entity_commands) = commands.get_entity(entity) {
         entity_commands.insert((
             Mesh3d(chunk_mesh_handle),
-            MeshMaterial3d(chunk_material_handle),
             Transform::from_xyz(
                 chunk_origin_tile_units_x as f32,
                 0.0,
@@ -382,6 +619,21 @@ fn draw_land_chunk(
             ),
             GlobalTransform::default(),
         ));
+        match chunk_material {
+            LandChunkMaterial::Batched(batch_material, slot) => {
+                entity_commands.insert((MeshMaterial3d(batch_material), MeshTag(slot)));
+            }
+            LandChunkMaterial::Own(material) => {
+                entity_commands.insert(MeshMaterial3d(material));
+            }
+        }
+        if built_chunk.has_animated_tiles {
+            entity_commands.insert(LCAnimated);
+        } else {
+            // A recycled chunk could have been animated at its previous coordinates.
+            entity_commands.remove::<LCAnimated>();
+        }
+        entity_commands.remove::<(LCRecycled, LCDirty)>();
     } else {
         logger::one(
             None,
@@ -391,3 +643,100 @@ fn draw_land_chunk(
         );
     }
 }
+
+/// Material of a land chunk: the shared one of the batch with the slot of the chunk, or its own.
+enum LandChunkMaterial {
+    Batched(Handle<LandBatchedMaterial>, u32),
+    Own(Handle<LandCustomMaterial>),
+}
+
+/// Material of a land chunk not batched: its previous one updated in place if recycled, otherwise a new one.
+fn own_land_chunk_material(
+    materials_land_rref: &mut ResMut<Assets<LandCustomMaterial>>,
+    land_texture_cache_ref: &LandTextureCache,
+    images_rref: &mut ResMut<Assets<Image>>,
+    hue_palette_r: &Res<HuePaletteTexture>,
+    chunk_size: LandChunkSize,
+    built_chunk: &LandChunkBuildOutput,
+    recycled_material: Option<&Handle<LandCustomMaterial>>,
+) -> Handle<LandCustomMaterial> {
+    // A recycled chunk keeps its material: only the land uniform changes. The other uniforms are shared by every
+    //  chunk (see shared_uniforms).
+    let recycled_material = recycled_material
+        .filter(|handle| materials_land_rref.contains(*handle))
+        .cloned();
+    match recycled_material {
+        Some(handle) => {
+            // Touching the material also makes it pick the updated tile data texture.
+            if let Some(material) = materials_land_rref.get_mut(&handle) {
+                material.extension.land_uniform = built_chunk.land_uniform;
+                match images_rref.get_mut(&material.extension.tile_data) {
+                    Some(image) => {
+                        // The chunk size is fixed at startup, so the texture size can't change.
+                        debug_assert_eq!(image.width(), chunk_tile_data_side(chunk_size));
+                        image.data = Some(bytemuck::cast_slice(&built_chunk.tile_texels).to_vec());
+                    }
+                    None => {
+                        material.extension.tile_data =
+                            images_rref.add(create_tile_data_image(&built_chunk.tile_texels, chunk_size));
+                    }
+                }
+            }
+            handle
+        }
+        // Create the material with create_land_chunk_material, attached to the entity for the new map chunk.
+        None => create_land_chunk_material(
+            materials_land_rref,
+            land_texture_cache_ref,
+            images_rref,
+            hue_palette_r,
+            chunk_size,
+            built_chunk,
+        ),
+    }
+}
+
+/// Tags with LCDirty the land chunks whose data grid holds edited tiles (the grid border included: the neighbors of
+///  an edited tile take its height at their edges), so that their material is rebuilt.
+pub fn sys_mark_edited_land_chunks_dirty(
+    mut commands: Commands,
+    mut events: EventReader<LandCellsEditedEvent>,
+    chunk_size_r: Res<LandChunkSize>,
+    chunk_q: Query<(Entity, &LCMesh), Without<LCDirty>>,
+) {
+    mark_land_chunks_dirty(
+        &mut commands,
+        *chunk_size_r,
+        &chunk_q,
+        events.read().map(|event| (event.map_id, event.rect)),
+    );
+}
+
+/// Tags with LCDirty the land chunks whose data grid holds tiles of the given rectangles (map id, rectangle).
+pub(super) fn mark_land_chunks_dirty(
+    commands: &mut Commands,
+    chunk_size: LandChunkSize,
+    chunk_q: &Query<(Entity, &LCMesh), Without<LCDirty>>,
+    rects: impl IntoIterator<Item = (u32, MapRectCells)>,
+) {
+    let mut dirty_chunks = HashSet::<(u32, u32, u32)>::new();
+    for (map_id, rect) in rects {
+        let gx_range = *chunks_with_tile_in_data_grid(chunk_size, rect.x0).start()
+            ..=*chunks_with_tile_in_data_grid(chunk_size, rect.x0 + rect.width - 1).end();
+        let gy_range = *chunks_with_tile_in_data_grid(chunk_size, rect.y0).start()
+            ..=*chunks_with_tile_in_data_grid(chunk_size, rect.y0 + rect.height - 1).end();
+        for gx in gx_range {
+            for gy in gy_range.clone() {
+                dirty_chunks.insert((map_id, gx, gy));
+            }
+        }
+    }
+    if dirty_chunks.is_empty() {
+        return;
+    }
+    for (entity, chunk) in chunk_q.iter() {
+        if dirty_chunks.contains(&(chunk.parent_map_id, chunk.gx, chunk.gy)) {
+            commands.entity(entity).insert(LCDirty);
+        }
+    }
+}
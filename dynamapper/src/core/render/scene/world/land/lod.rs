@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::{LCMesh, batch::LandBatch, draw_mesh::LandMeshHandle, mesh_material::LandCustomMaterial};
+use crate::{
+    core::render::{
+        export::MapExportChunk,
+        scene::{
+            SceneStateData,
+            camera::{RenderZoom, UO_TILE_PIXEL_SIZE},
+            split_view::SplitView,
+        },
+    },
+    prelude::*,
+};
+
+// Terrain level of detail: zoomed out, a tile is a few pixels on screen and the full detail chunk meshes (one quad
+//  per tile) cost more vertices than the pixels they cover. The land chunks are then drawn with a coarser mesh, with a
+//  quad every lod step tiles (2x2 or 4x4). The shader averages the heights of the tiles around each node of the coarse
+//  mesh (see lod_height_at_data_grid in land_base.wgsl); textures, overlays and normals are still per tile, since they
+//  are read by the fragment shader from the tile data grid.
+// The camera is orthographic, so every chunk of a view has the same size on screen: the lod step is picked per map
+//  plane, from the on-screen tile size of the views showing it (the finest one wins). Adjacent chunks always have the
+//  same step, so there are no cracks between coarse and fine meshes.
+// Switching the step only swaps the shared mesh of the chunks and updates the step in their land uniform: the tile
+//  data is the same, nothing is rebuilt. Export chunks are always drawn at full detail.
+
+/// Tiles per mesh quad side, from the full detail one. Chunk sizes are multiples of all of them.
+pub const LOD_STEPS: [u32; 3] = [1, 2, 4];
+/// Margin on the on-screen quad size before switching step, so that zooming around the threshold doesn't flip it
+///  back and forth.
+const LOD_HYSTERESIS: f32 = 0.1;
+
+/// Lod step of the land chunks of each map plane. Planes not listed are at full detail.
+#[derive(Resource, Default, Debug)]
+pub struct LandChunkLods(HashMap<u32, u32>);
+impl LandChunkLods {
+    pub fn step(&self, map_id: u32) -> u32 {
+        self.0.get(&map_id).copied().unwrap_or(1)
+    }
+}
+
+/// Coarsest lod step whose mesh quads are at most max_quad_pixels on screen, given the tile size on screen.
+fn lod_step_for(tile_pixels: f32, max_quad_pixels: f32, current_step: u32) -> u32 {
+    if max_quad_pixels <= 0.0 {
+        return 1;
+    }
+    LOD_STEPS
+        .into_iter()
+        .rev()
+        .find(|&step| {
+            let margin = match step.cmp(&current_step) {
+                std::cmp::Ordering::Greater => 1.0 - LOD_HYSTERESIS,
+                std::cmp::Ordering::Equal => 1.0 + LOD_HYSTERESIS,
+                std::cmp::Ordering::Less => 1.0,
+            };
+            tile_pixels * step as f32 <= max_quad_pixels * margin
+        })
+        .unwrap_or(1)
+}
+
+/// Picks the lod step of the map planes on screen from their zoom. When one changes, the built chunks of the plane
+///  get the mesh and the land uniform of the new step; the ones still to build get them from the draw system.
+pub fn sys_update_land_chunk_lods(
+    mut lods_r: ResMut<LandChunkLods>,
+    mut materials_land_r: ResMut<Assets<LandCustomMaterial>>,
+    mut land_batch_r: ResMut<LandBatch>,
+    mut commands: Commands,
+    settings_r: Res<Settings>,
+    render_zoom_r: Res<RenderZoom>,
+    split_view_r: Res<SplitView>,
+    scene_state_data_r: Res<SceneStateData>,
+    land_mesh_handle_r: Res<LandMeshHandle>,
+    chunk_q: Query<(Entity, &LCMesh, Option<&MeshMaterial3d<LandCustomMaterial>>), Without<MapExportChunk>>,
+) {
+    let max_quad_pixels = settings_r.render.lod_max_quad_pixels;
+    // Zoom of each plane on screen: the closest one if both views show the same plane.
+    let mut zoom_by_map = HashMap::from([(scene_state_data_r.map_id, render_zoom_r.0)]);
+    if let Some(split_map_id) = split_view_r.shown_map() {
+        let zoom = zoom_by_map.entry(split_map_id).or_insert(split_view_r.zoom);
+        *zoom = zoom.min(split_view_r.zoom);
+    }
+
+    for (map_id, zoom) in zoom_by_map {
+        let current_step = lods_r.step(map_id);
+        let step = lod_step_for(UO_TILE_PIXEL_SIZE / zoom, max_quad_pixels, current_step);
+        if step == current_step {
+            continue;
+        }
+        lods_r.0.insert(map_id, step);
+        logger::one(
+            None,
+            LogSev::Debug,
+            LogAbout::RenderWorldLand,
+            &format!("Land chunks of map plane {map_id}: lod step {current_step} -> {step}."),
+        );
+        let mesh_handle = land_mesh_handle_r.for_lod_step(step);
+        for (entity, chunk, material_handle) in chunk_q.iter() {
+            if chunk.parent_map_id != map_id {
+                continue;
+            }
+            // Batched chunks have the step in their slot (see batch).
+            match material_handle {
+                // Only the materials with another step are touched.
+                Some(material_handle) => {
+                    let stale = materials_land_r
+                        .get(&material_handle.0)
+                        .is_some_and(|material| material.extension.land_uniform.lod_step != step);
+                    if stale && let Some(material) = materials_land_r.get_mut(&material_handle.0) {
+                        material.extension.land_uniform.lod_step = step;
+                    }
+                }
+                None => land_batch_r.set_lod_step(entity, step),
+            }
+            commands.entity(entity).insert(Mesh3d(mesh_handle.clone()));
+        }
+    }
+}
@@ -1,12 +1,22 @@
 use bevy::{
-    pbr::{ExtendedMaterial, MaterialExtension},
+    pbr::{ExtendedMaterial, MaterialExtension, MaterialExtensionKey, MaterialExtensionPipeline},
     prelude::*,
-    render::render_resource::{AsBindGroup, ShaderRef, ShaderType},
+    render::{
+        mesh::MeshVertexBufferLayoutRef,
+        render_resource::{AsBindGroup, RenderPipelineDescriptor, ShaderRef, ShaderType, SpecializedMeshPipelineError},
+        storage::ShaderStorageBuffer,
+    },
 };
 use serde::Deserialize;
 
+use super::shared_uniforms::LandMaterialBase;
+
 // ------------- Land material/shader data -------------
-pub type LandCustomMaterial = ExtendedMaterial<StandardMaterial, LandMaterialExtension>;
+/// Shader of both land materials, in the assets folder.
+pub const LAND_SHADER_PATH: &str = "shaders/worldmap/land_base.wgsl";
+
+/// The uniforms shared by every chunk (scene, effects, lighting) are bound by the base (see shared_uniforms).
+pub type LandCustomMaterial = ExtendedMaterial<LandMaterialBase, LandMaterialExtension>;
 
 #[derive(AsBindGroup, Asset, TypePath, Debug, Clone)]
 pub struct LandMaterialExtension {
@@ -18,20 +28,73 @@ pub struct LandMaterialExtension {
     pub texarray_big: Handle<Image>,
     #[uniform(103, min_binding_size = 16)]
     pub land_uniform: LandUniform,
-    #[uniform(104, min_binding_size = 16)]
-    pub scene_uniform: SceneUniform,
-    #[uniform(105, min_binding_size = 16)]
-    pub effects_uniform: LandEffectsUniform,
-    #[uniform(106, min_binding_size = 16)]
-    pub lighting_uniform: LandLightingUniforms,
+    // 104, 105, 106: the shared uniforms (LandSharedUniformsExtension).
+    // Hue ramps, one row per hue id (see texture_cache::hues).
+    #[texture(107)]
+    pub hue_palette: Handle<Image>,
+    // Per-tile data of the chunk (and its border), one texel per tile: see TileUniform::to_texel.
+    #[texture(108, sample_type = "u_int")]
+    pub tile_data: Handle<Image>,
+    // Normal maps of the textures of texarray_small and texarray_big, at the same layers (see texture_cache::land).
+    #[texture(109, dimension = "2d_array")]
+    pub normal_maps_small: Handle<Image>,
+    #[texture(110, dimension = "2d_array")]
+    pub normal_maps_big: Handle<Image>,
 }
 
 impl MaterialExtension for LandMaterialExtension {
     fn vertex_shader() -> ShaderRef {
-        "shaders/worldmap/land_base.wgsl".into()
+        LAND_SHADER_PATH.into()
     }
     fn fragment_shader() -> ShaderRef {
-        "shaders/worldmap/land_base.wgsl".into()
+        LAND_SHADER_PATH.into()
+    }
+}
+
+/// Material shared by the batched land chunks (see batch): same shader and bindings as LandMaterialExtension, but the
+///  land uniform and the tile data of every chunk are in storage buffers, indexed by the mesh tag of the chunk.
+pub type LandBatchedMaterial = ExtendedMaterial<LandMaterialBase, LandBatchedMaterialExtension>;
+
+#[derive(AsBindGroup, Asset, TypePath, Debug, Clone)]
+pub struct LandBatchedMaterialExtension {
+    #[sampler(100)]
+    #[texture(101, dimension = "2d_array")]
+    pub texarray_small: Handle<Image>,
+    #[texture(102, dimension = "2d_array")]
+    pub texarray_big: Handle<Image>,
+    // LandUniform of each slot.
+    #[storage(103, read_only)]
+    pub land_uniforms: Handle<ShaderStorageBuffer>,
+    // 104, 105, 106: the shared uniforms (LandSharedUniformsExtension).
+    #[texture(107)]
+    pub hue_palette: Handle<Image>,
+    // Tile data grid of each slot, one after the other: the texels of the tile data texture of LandMaterialExtension.
+    #[storage(108, read_only)]
+    pub tile_data: Handle<ShaderStorageBuffer>,
+    #[texture(109, dimension = "2d_array")]
+    pub normal_maps_small: Handle<Image>,
+    #[texture(110, dimension = "2d_array")]
+    pub normal_maps_big: Handle<Image>,
+}
+
+impl MaterialExtension for LandBatchedMaterialExtension {
+    fn vertex_shader() -> ShaderRef {
+        LAND_SHADER_PATH.into()
+    }
+    fn fragment_shader() -> ShaderRef {
+        LAND_SHADER_PATH.into()
+    }
+    fn specialize(
+        _pipeline: &MaterialExtensionPipeline,
+        descriptor: &mut RenderPipelineDescriptor,
+        _layout: &MeshVertexBufferLayoutRef,
+        _key: MaterialExtensionKey<Self>,
+    ) -> Result<(), SpecializedMeshPipelineError> {
+        descriptor.vertex.shader_defs.push("LAND_BATCHED".into());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader_defs.push("LAND_BATCHED".into());
+        }
+        Ok(())
     }
 }
 
@@ -49,27 +112,49 @@ fn fragment_shader() -> ShaderRef {
 // In order to have 16-bytes (not bit!) alignment, we can use some packing helpers.
 // UVec4 (from glam crate, used by Bevy) is a struct holding four unsigned 32-bit integers (u32 values), used as a “vector of four elements”:
 
-/// Each chunk mesh gets a shader material generated per-chunk, with this struct as its extension.
-#[repr(C, align(16))]
-#[derive(Debug, Clone, Copy, ShaderType, bytemuck::Pod, bytemuck::Zeroable)]
+// Per-tile data doesn't go in a uniform buffer: it would grow with the square of the chunk size and quickly hit
+//  the UBO size limits. It's stored in a small Rgba32Uint data texture per chunk instead, read with textureLoad.
+// A storage buffer would do too, but some platforms (WebGL2, downlevel GPUs) lack storage buffers in the vertex
+//  stage, while integer textures are readable in both stages everywhere. The batched chunks (see batch) do use
+//  storage buffers, and fall back to these per-chunk materials where they're missing.
+
+/// Data of a single tile, as read by the shader. Each chunk gets a data texture holding one texel per tile.
+#[derive(Debug, Clone, Copy, Default)]
 pub struct TileUniform {
     pub tile_height: f32,
     pub texture_size: u32, // 0: small, 1: big
     pub texture_layer: u32,
-    pub texture_hue: u32,
-    // Ensure to have 16 bytes alignment (WGSL std140 layout), add padding if needed.
+    pub tile_flags: u32,  // See terrain_overlay::TILE_FLAG_*
+    pub texture_hue: u32, // 0: no hue, otherwise the hue id (row of the hue palette texture)
+    pub anim_kind: u32,  // See animation::ANIM_KIND_*
+    pub anim_speed: f32, // UV units per second
+}
+impl TileUniform {
+    /// Packs the tile in a texel of the tile data texture. Keep in sync with unpack_tile in land_base.wgsl.
+    ///  r: tile_height bits, g: texture_layer (16 bits) | tile_flags (16 bits),
+    ///  b: texture_hue (16 bits) | texture_size (8 bits) | anim_kind (8 bits), a: anim_speed bits.
+    pub fn to_texel(&self) -> [u32; 4] {
+        [
+            self.tile_height.to_bits(),
+            (self.texture_layer & 0xFFFF) | ((self.tile_flags & 0xFFFF) << 16),
+            (self.texture_hue & 0xFFFF) | ((self.texture_size & 0xFF) << 16) | ((self.anim_kind & 0xFF) << 24),
+            self.anim_speed.to_bits(),
+        ]
+    }
 }
 
 #[repr(C, align(16))]
-#[derive(Debug, Clone, Copy, ShaderType, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Clone, Copy, PartialEq, ShaderType, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LandUniform {
     pub chunk_origin: Vec2,
-    pub _pad2: Vec2,
-    pub tiles: [TileUniform; 169], // 13x13 grid for seamless normals
+    /// Tiles per chunk row/column (LandChunkSize): the shader derives the mesh and tile data grid sizes from it.
+    pub chunk_size: u32,
+    /// Tiles per mesh quad side: 1 at full detail, more at far zoom (see lod). Selects the mesh grid in the shader.
+    pub lod_step: u32,
 }
 
 #[repr(C, align(16))]
-#[derive(Debug, Clone, Copy, ShaderType, bytemuck::Pod, bytemuck::Zeroable)]
+#[derive(Debug, Clone, Copy, PartialEq, ShaderType, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct SceneUniform {
     pub camera_position: Vec3,
     pub time_seconds: f32,
@@ -78,7 +163,7 @@ pub struct SceneUniform {
 }
 
 #[repr(C, align(16))]
-#[derive(Debug, Clone, Copy, ShaderType, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, ShaderType, Deserialize, Default)]
 pub struct LandEffectsUniform {
     // TODO: keep here only non-lighting data. Move the others to LandLightingUniforms, then update the shader and terrain_shader_ui.rs.
 
@@ -108,17 +193,91 @@ pub struct LandEffectsUniform {
     // Intensities (slot C, 16B)
     // blur radius in UV units (very small numbers like 0.001..0.005)
     pub blur_radius: f32,
+    // Debug overlay tinting the terrain (see terrain_overlay::TERRAIN_OVERLAY_*): not part of the presets.
     #[serde(default)]
-    pub _pad_c1: f32,
+    pub overlay_mode: u32,
+    // Grid lines drawn over the terrain (see terrain_overlay::GRID_LINES_*), not part of the presets.
     #[serde(default)]
-    pub _pad_c2: f32,
+    pub grid_lines: u32,
+    // Width of the texture transitions across the tile edges, in tiles (0..1): 0 is the classic hard cut. Not part of
+    //  the presets. See blend_tile_transitions in land_base.wgsl.
     #[serde(default)]
-    pub _pad_c3: f32,
+    pub transition_width: f32,
+
+    // Altitude overlay and contour lines (slot D), not part of the presets either:
+    //  [ramp_z_min, ramp_z_max, contour_interval (z units), contours_on]
+    #[serde(default = "default_altitude_params")]
+    pub altitude_params: Vec4,
+
+    // Wet tiles (slot E), not part of the presets: [transparency, opaque_distance (tiles from the shore),
+    //  shore_blend_width (tiles), water_on]. See apply_water in land_base.wgsl.
+    #[serde(default = "default_water_params")]
+    pub water_params: Vec4,
+
+    // Normal maps of the textures (slot F), not part of the presets: [strength, 0, 0, normal_maps_on]. Fragment
+    //  shading modes only. See apply_normal_map in land_base.wgsl.
+    #[serde(default = "default_normal_map_params")]
+    pub normal_map_params: Vec4,
+}
+
+fn default_altitude_params() -> Vec4 {
+    Vec4::new(-128.0, 127.0, 10.0, 0.0)
+}
+
+fn default_water_params() -> Vec4 {
+    Vec4::new(0.5, 2.0, 0.6, 0.0)
+}
+
+fn default_normal_map_params() -> Vec4 {
+    Vec4::new(1.0, 0.0, 0.0, 0.0)
 }
 
 
+impl LandEffectsUniform {
+    /// Takes the overlay, water, texture transition and normal map settings from another uniform: presets don't set
+    ///  them, so they're kept when switching presets.
+    pub fn with_overlay_of(self, other: &Self) -> Self {
+        Self {
+            overlay_mode: other.overlay_mode,
+            grid_lines: other.grid_lines,
+            altitude_params: other.altitude_params,
+            water_params: other.water_params,
+            transition_width: other.transition_width,
+            normal_map_params: other.normal_map_params,
+            ..self
+        }
+    }
+
+    /// Blends two uniforms: intensities are interpolated, modes and toggles are taken from the closest one.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let (a, b) = (self, other);
+        let pick = |x: u32, y: u32| if t < 0.5 { x } else { y };
+        let mix = |x: f32, y: f32| x + (y - x) * t;
+        Self {
+            shading_mode: pick(a.shading_mode, b.shading_mode),
+            normal_mode: pick(a.normal_mode, b.normal_mode),
+            enable_bent: pick(a.enable_bent, b.enable_bent),
+            enable_fog: pick(a.enable_fog, b.enable_fog),
+            enable_gloom: pick(a.enable_gloom, b.enable_gloom),
+            enable_tonemap: pick(a.enable_tonemap, b.enable_tonemap),
+            enable_grading: pick(a.enable_grading, b.enable_grading),
+            enable_blur: pick(a.enable_blur, b.enable_blur),
+            ambient_strength: mix(a.ambient_strength, b.ambient_strength),
+            diffuse_strength: mix(a.diffuse_strength, b.diffuse_strength),
+            specular_strength: mix(a.specular_strength, b.specular_strength),
+            rim_strength: mix(a.rim_strength, b.rim_strength),
+            fill_strength: mix(a.fill_strength, b.fill_strength),
+            sharpness_factor: mix(a.sharpness_factor, b.sharpness_factor),
+            sharpness_mix: mix(a.sharpness_mix, b.sharpness_mix),
+            blur_strength: mix(a.blur_strength, b.blur_strength),
+            blur_radius: mix(a.blur_radius, b.blur_radius),
+            ..*a
+        }
+    }
+}
+
 #[repr(C, align(16))]
-#[derive(Debug, Clone, Copy, ShaderType, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, ShaderType, Deserialize, Default)]
 pub struct LandLightingUniforms {
     // vec3 + pad
     pub light_color: Vec3,
@@ -145,6 +304,29 @@ pub struct LandLightingUniforms {
     //   fog_color = [r,g,b, max_mix]
     //   fog_params = [distance_density, height_density, noise_scale, noise_strength]
 }
+impl LandLightingUniforms {
+    /// Blends two uniforms, every field is interpolated.
+    pub fn lerp(&self, other: &Self, t: f32) -> Self {
+        let (a, b) = (self, other);
+        Self {
+            light_color: a.light_color.lerp(b.light_color, t),
+            ambient_color: a.ambient_color.lerp(b.ambient_color, t),
+            exposure: a.exposure + (b.exposure - a.exposure) * t,
+            gamma: a.gamma + (b.gamma - a.gamma) * t,
+            fill_sky_color: a.fill_sky_color.lerp(b.fill_sky_color, t),
+            fill_ground_color: a.fill_ground_color.lerp(b.fill_ground_color, t),
+            rim_color: a.rim_color.lerp(b.rim_color, t),
+            grade_warm_color: a.grade_warm_color.lerp(b.grade_warm_color, t),
+            grade_cool_color: a.grade_cool_color.lerp(b.grade_cool_color, t),
+            grade_params: a.grade_params.lerp(b.grade_params, t),
+            grade_extra: a.grade_extra.lerp(b.grade_extra, t),
+            gloom_params: a.gloom_params.lerp(b.gloom_params, t),
+            fog_color: a.fog_color.lerp(b.fog_color, t),
+            fog_params: a.fog_params.lerp(b.fog_params, t),
+            ..*a
+        }
+    }
+}
 
 
 
@@ -162,6 +344,17 @@ pub struct LandShaderModePresets {
     pub kr: LandRenderStylePresetsPerMode,
 }
 
+impl LandShaderModePresets {
+    /// Presets for the given shading mode (see LandShaderMode).
+    pub fn for_mode(&self, shading_mode: u32) -> &LandRenderStylePresetsPerMode {
+        match shading_mode {
+            0 => &self.classic,
+            1 => &self.enhanced,
+            _ => &self.kr,
+        }
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LandRenderStylePresetsPerMode {
     pub morning: LandMaterialUniformsPresets,
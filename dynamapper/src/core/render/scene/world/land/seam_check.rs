@@ -0,0 +1,260 @@
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+use super::draw_mesh::{DATA_GRID_BORDER, chunk_tile_data_side};
+use super::{LCDirty, LCMesh, LCRecycled, LandChunkSize, batch::LandBatch, mesh_material::LandCustomMaterial};
+use crate::{core::render::scene::SceneStateData, prelude::*};
+
+// Seam validation (diagnostics): checks that adjacent land chunks agree on the vertices of their shared edge.
+// Each chunk displaces its mesh and computes its normals in the vertex shader, from its own tile data grid (see
+//  draw_mesh::gather_land_chunk_tile_grid). The edge vertices exist in the meshes of both chunks: if their data grids
+//  disagree (stale data after an edit or a recycle, a bug in the grid gathering...), the terrain shows a crack or a
+//  lighting step there.
+// When enabled (Diagnostics window), the built chunks are checked periodically: the positions and the normals of the
+//  edge vertices are computed on the CPU from the tile data textures of both chunks (their slots, for batched chunks),
+//  like the shader does, and compared. Mismatches are logged and marked on screen with gizmos.
+// Edge vertices get the geometric normal in every normal mode (chunk_edge_blend_factor is 1 there): that's the one
+//  compared.
+
+const SEAM_CHECK_INTERVAL_SECS: f32 = 1.0;
+/// World units.
+const SEAM_POSITION_TOLERANCE: f32 = 1e-4;
+const SEAM_NORMAL_TOLERANCE_DEG: f32 = 0.01;
+/// Mismatches detailed in the log, for each check which finds a different number of them.
+const SEAM_MISMATCHES_LOGGED_MAX: usize = 8;
+const SEAM_MARKER_HEIGHT: f32 = 3.0;
+const SEAM_MARKER_COLOR: Color = Color::srgb(1.0, 0.0, 1.0);
+
+/// An edge vertex computed differently by two adjacent chunks.
+#[derive(Clone, Copy, Debug)]
+pub struct SeamMismatch {
+    /// Chunk grid coordinates of the two chunks.
+    pub chunks: [(u32, u32); 2],
+    /// Vertex position, as computed by the first chunk.
+    pub world_pos: Vec3,
+    /// Distance between the vertex positions computed by the two chunks (world units): their heights differ, or a
+    ///  chunk transform is stale.
+    pub position_delta: f32,
+    pub normal_angle_deg: f32,
+}
+
+#[derive(Debug, Default)]
+pub struct SeamReport {
+    /// Shared edges of adjacent built chunks.
+    pub checked_edges: usize,
+    pub mismatches: Vec<SeamMismatch>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct SeamCheck {
+    pub enabled: bool,
+    /// Result of the last check: none until the first check after enabling it.
+    pub report: Option<SeamReport>,
+}
+
+/// Tile heights of the data grid of a built chunk, read back from its tile data texture or batch slot (see
+///  TileUniform::to_texel).
+struct ChunkHeightGrid {
+    origin: Vec3,
+    side: i32,
+    heights: Vec<f32>,
+}
+impl ChunkHeightGrid {
+    fn from_tile_data(origin: Vec3, tile_data: &Image) -> Option<Self> {
+        let side = tile_data.width() as i32;
+        let heights: Vec<f32> = tile_data
+            .data
+            .as_ref()?
+            .chunks_exact(size_of::<[u32; 4]>())
+            .map(|texel| f32::from_bits(u32::from_ne_bytes([texel[0], texel[1], texel[2], texel[3]])))
+            .collect();
+        (heights.len() == (side * side) as usize).then_some(Self { origin, side, heights })
+    }
+
+    fn from_texels(origin: Vec3, side: u32, texels: &[[u32; 4]]) -> Option<Self> {
+        let side = side as i32;
+        let heights: Vec<f32> = texels.iter().map(|texel| f32::from_bits(texel[0])).collect();
+        (heights.len() == (side * side) as usize).then_some(Self { origin, side, heights })
+    }
+
+    /// Height at a mesh node, clamped to the data grid. Same as tile_height_at_data_grid in land_base.wgsl.
+    fn height(&self, node_x: i32, node_z: i32) -> f32 {
+        let gx = (node_x + DATA_GRID_BORDER as i32).clamp(0, self.side - 1);
+        let gz = (node_z + DATA_GRID_BORDER as i32).clamp(0, self.side - 1);
+        self.heights[(gz * self.side + gx) as usize]
+    }
+
+    /// Same as get_geometric_normal_local in land_base.wgsl.
+    fn geometric_normal(&self, node_x: i32, node_z: i32) -> Vec3 {
+        let dh_dx = 0.5 * (self.height(node_x + 1, node_z) - self.height(node_x - 1, node_z));
+        let dh_dz = 0.5 * (self.height(node_x, node_z + 1) - self.height(node_x, node_z - 1));
+        Vec3::new(-dh_dx, 1.0, -dh_dz).normalize()
+    }
+}
+
+/// Compares the vertices of the edge shared by two chunks: node_a and node_b give the mesh node of the i-th edge vertex
+///  in each of them.
+fn check_shared_edge(
+    chunk_size: LandChunkSize,
+    chunks: [(u32, u32); 2],
+    grid_a: &ChunkHeightGrid,
+    grid_b: &ChunkHeightGrid,
+    node_a: impl Fn(i32) -> (i32, i32),
+    node_b: impl Fn(i32) -> (i32, i32),
+    mismatches: &mut Vec<SeamMismatch>,
+) {
+    for i in 0..=chunk_size.0 as i32 {
+        let (ax, az) = node_a(i);
+        let (bx, bz) = node_b(i);
+        let pos_a = grid_a.origin + Vec3::new(ax as f32, grid_a.height(ax, az), az as f32);
+        let pos_b = grid_b.origin + Vec3::new(bx as f32, grid_b.height(bx, bz), bz as f32);
+        let position_delta = pos_a.distance(pos_b);
+        let normal_dot = grid_a.geometric_normal(ax, az).dot(grid_b.geometric_normal(bx, bz));
+        let normal_angle_deg = normal_dot.clamp(-1.0, 1.0).acos().to_degrees();
+        if position_delta > SEAM_POSITION_TOLERANCE || normal_angle_deg > SEAM_NORMAL_TOLERANCE_DEG {
+            mismatches.push(SeamMismatch {
+                chunks,
+                world_pos: pos_a,
+                position_delta,
+                normal_angle_deg,
+            });
+        }
+    }
+}
+
+/// Checks the seams of the built land chunks of the current map, while enabled.
+pub fn sys_check_land_chunk_seams(
+    time_r: Res<Time>,
+    mut elapsed_since_check: Local<f32>,
+    mut seam_check_r: ResMut<SeamCheck>,
+    materials_land_r: Res<Assets<LandCustomMaterial>>,
+    images_r: Res<Assets<Image>>,
+    land_batch_r: Res<LandBatch>,
+    chunk_size_r: Res<LandChunkSize>,
+    scene_state_data_r: Res<SceneStateData>,
+    // Chunks waiting to be rebuilt are skipped: their data is stale on purpose.
+    chunk_q: Query<
+        (Entity, &LCMesh, &Transform, Option<&MeshMaterial3d<LandCustomMaterial>>),
+        (Without<LCRecycled>, Without<LCDirty>),
+    >,
+) {
+    if !seam_check_r.enabled {
+        return;
+    }
+    *elapsed_since_check += time_r.delta_secs();
+    if seam_check_r.report.is_some() && *elapsed_since_check < SEAM_CHECK_INTERVAL_SECS {
+        return;
+    }
+    *elapsed_since_check = 0.0;
+
+    let chunk_size = *chunk_size_r;
+    let grids: HashMap<(u32, u32), ChunkHeightGrid> = chunk_q
+        .iter()
+        .filter(|(_, chunk, _, _)| chunk.parent_map_id == scene_state_data_r.map_id)
+        .filter_map(|(entity, chunk, transform, material_handle)| {
+            let grid = match material_handle {
+                Some(material_handle) => {
+                    let material = materials_land_r.get(&material_handle.0)?;
+                    let tile_data = images_r.get(&material.extension.tile_data)?;
+                    ChunkHeightGrid::from_tile_data(transform.translation, tile_data)?
+                }
+                None => {
+                    let side = chunk_tile_data_side(chunk_size);
+                    ChunkHeightGrid::from_texels(transform.translation, side, land_batch_r.slot_texels(entity)?)?
+                }
+            };
+            Some(((chunk.gx, chunk.gy), grid))
+        })
+        .collect();
+
+    let last_node = chunk_size.0 as i32;
+    let mut report = SeamReport::default();
+    for (&(gx, gy), grid) in &grids {
+        // East neighbor: our last column of vertices is its first one.
+        if let Some(east_grid) = grids.get(&(gx + 1, gy)) {
+            report.checked_edges += 1;
+            let chunks = [(gx, gy), (gx + 1, gy)];
+            check_shared_edge(
+                chunk_size,
+                chunks,
+                grid,
+                east_grid,
+                |i| (last_node, i),
+                |i| (0, i),
+                &mut report.mismatches,
+            );
+        }
+        // South neighbor: our last row of vertices is its first one.
+        if let Some(south_grid) = grids.get(&(gx, gy + 1)) {
+            report.checked_edges += 1;
+            let chunks = [(gx, gy), (gx, gy + 1)];
+            check_shared_edge(
+                chunk_size,
+                chunks,
+                grid,
+                south_grid,
+                |i| (i, last_node),
+                |i| (i, 0),
+                &mut report.mismatches,
+            );
+        }
+    }
+
+    let previous_mismatches = seam_check_r.report.as_ref().map_or(0, |report| report.mismatches.len());
+    if previous_mismatches != report.mismatches.len() {
+        log_seam_report(&report);
+    }
+    seam_check_r.report = Some(report);
+}
+
+fn log_seam_report(report: &SeamReport) {
+    if report.mismatches.is_empty() {
+        logger::one(
+            None,
+            LogSev::Info,
+            LogAbout::RenderWorldLand,
+            &format!("Seam check: no mismatches left ({} chunk edges).", report.checked_edges),
+        );
+        return;
+    }
+    logger::one(
+        None,
+        LogSev::Warn,
+        LogAbout::RenderWorldLand,
+        &format!(
+            "Seam check: {} mismatching vertices on {} chunk edges.",
+            report.mismatches.len(),
+            report.checked_edges
+        ),
+    );
+    for mismatch in report.mismatches.iter().take(SEAM_MISMATCHES_LOGGED_MAX) {
+        logger::one(
+            None,
+            LogSev::Warn,
+            LogAbout::RenderWorldLand,
+            &format!(
+                "  Tile ({}, {}) between chunks {:?} and {:?}: position differs by {:.4}, normal by {:.2}°.",
+                mismatch.world_pos.x,
+                mismatch.world_pos.z,
+                mismatch.chunks[0],
+                mismatch.chunks[1],
+                mismatch.position_delta,
+                mismatch.normal_angle_deg
+            ),
+        );
+    }
+}
+
+/// Marks the mismatching vertices found by the last seam check with vertical lines.
+pub fn sys_draw_seam_mismatches(seam_check_r: Res<SeamCheck>, mut gizmos: Gizmos) {
+    let Some(report) = seam_check_r.report.as_ref().filter(|_| seam_check_r.enabled) else {
+        return;
+    };
+    for mismatch in &report.mismatches {
+        gizmos.line(
+            mismatch.world_pos,
+            mismatch.world_pos + Vec3::Y * SEAM_MARKER_HEIGHT,
+            SEAM_MARKER_COLOR,
+        );
+    }
+}
@@ -1,42 +1,63 @@
-use super::{TILE_NUM_PER_CHUNK_DIM, draw_mesh::LandMeshHandle};
+use super::{LandChunkSize, draw_mesh::LandMeshHandle, lod::LOD_STEPS};
+use crate::prelude::*;
 use bevy::{
     asset::RenderAssetUsages,
     prelude::*,
     render::mesh::{Indices, PrimitiveTopology},
 };
 
-/// This startup system generates a single, shared 9x9 grid mesh for all land chunks.
-pub fn setup_land_mesh(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>) {
-    // Core: real tile number inside a map block.
-    const CORE_W: usize = TILE_NUM_PER_CHUNK_DIM as usize;
-    const CORE_H: usize = TILE_NUM_PER_CHUNK_DIM as usize;
+/// This startup system picks the chunk size from the settings and generates the grid meshes shared by all land
+///  chunks: one for each lod step (9x9 vertices for 8x8 chunks at full detail, 3x3 at lod step 4).
+pub fn setup_land_mesh(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, settings_r: Res<Settings>) {
+    let chunk_size = LandChunkSize::from_settings(&settings_r.render);
+    let handles = LOD_STEPS
+        .iter()
+        .map(|&lod_step| meshes.add(build_land_grid_mesh(chunk_size, lod_step)))
+        .collect();
+    commands.insert_resource(LandMeshHandle(handles));
+    commands.insert_resource(chunk_size);
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::RenderWorldLand,
+        &format!("Land chunk size: {0}x{0} tiles.", chunk_size.0),
+    );
+}
+
+/// Flat grid mesh of a chunk, with a quad every lod_step tiles. The vertex shader finds the grid node of a vertex
+///  from its index, so the vertex order must match mesh_grid_side in land_base.wgsl.
+fn build_land_grid_mesh(chunk_size: LandChunkSize, lod_step: u32) -> Mesh {
+    let step = lod_step as usize;
+    // Core: real tile number inside a chunk (mesh quads, at lod steps past 1).
+    let core_w = chunk_size.0 as usize / step;
+    let core_h = chunk_size.0 as usize / step;
     // The Grid we are making though has an additional row and column at south and east, so that it can contain the data about adjacent tiles.
-    const GRID_W: usize = (TILE_NUM_PER_CHUNK_DIM + 1) as usize;
-    const GRID_H: usize = (TILE_NUM_PER_CHUNK_DIM + 1) as usize;
+    let grid_w = core_w + 1;
+    let grid_h = core_h + 1;
 
-    let estimated_vertex_count = GRID_W * GRID_H;
+    let estimated_vertex_count = grid_w * grid_h;
     let mut positions = Vec::with_capacity(estimated_vertex_count);
     let mut uvs = Vec::with_capacity(estimated_vertex_count);
     let mut indices = Vec::new();
 
-    // Create a flat 9x9 grid of vertices at y=0
+    // Create a flat grid of vertices at y=0
     // Add dummy height values (0.0) because the real one will be calculated on the gpu, via the shader
-    //  (we send tile height through a uniform buffer).
+    //  (we send tile height through the tile data texture).
     // We are adding an extra row and column to avoid seam artifacts and to make the neighboring chunk minimum tiles data
     //  available for the shader to calculate normals.
-    for gy in 0..GRID_H {
-        for gx in 0..GRID_W {
-            positions.push([gx as f32, 0.0, gy as f32]);
-            uvs.push([gx as f32 / (CORE_W as f32), gy as f32 / (CORE_H as f32)]);
+    for gy in 0..grid_h {
+        for gx in 0..grid_w {
+            positions.push([(gx * step) as f32, 0.0, (gy * step) as f32]);
+            uvs.push([gx as f32 / (core_w as f32), gy as f32 / (core_h as f32)]);
         }
     }
 
-    // Create indices for the 8x8 core of the grid
-    for ty in 0..CORE_H {
-        for tx in 0..CORE_W {
-            let v0 = (ty * GRID_W + tx) as u32;
+    // Create indices for the core of the grid
+    for ty in 0..core_h {
+        for tx in 0..core_w {
+            let v0 = (ty * grid_w + tx) as u32;
             let v1 = v0 + 1;
-            let v2 = ((ty + 1) * GRID_W + tx) as u32;
+            let v2 = ((ty + 1) * grid_w + tx) as u32;
             let v3 = v2 + 1;
             indices.extend_from_slice(&[v0, v3, v1, v0, v2, v3]);
         }
@@ -58,7 +79,5 @@ pub fn setup_land_mesh(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>)
     //  to the fragment shader.
     mesh.insert_attribute(Mesh::ATTRIBUTE_UV_1, dummy_uv1s);
     mesh.insert_indices(Indices::U32(indices));
-
-    let handle = meshes.add(mesh);
-    commands.insert_resource(LandMeshHandle(handle));
+    mesh
 }
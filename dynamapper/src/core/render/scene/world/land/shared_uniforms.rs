@@ -0,0 +1,336 @@
+use bevy::{
+    ecs::system::{SystemParamItem, lifetimeless::SRes},
+    pbr::{ExtendedMaterial, MaterialExtension},
+    prelude::*,
+    render::{
+        Render, RenderApp, RenderSet,
+        extract_resource::{ExtractResource, ExtractResourcePlugin},
+        render_resource::{
+            AsBindGroup, AsBindGroupError, BindGroupLayout, BindGroupLayoutEntry, BindingResources, BindingType,
+            BufferBindingType, OwnedBindingResource, ShaderStages, ShaderType, UniformBuffer, UnpreparedBindGroup,
+        },
+        renderer::{RenderDevice, RenderQueue},
+    },
+};
+use std::ops::{BitOr, BitOrAssign};
+
+use super::mesh_material::{LandEffectsUniform, LandLightingUniforms, SceneUniform};
+use crate::{
+    core::{constants, render::scene::camera::PlayerCamera},
+    external_data::shader_presets::UniformState,
+    prelude::*,
+};
+
+// Uniforms shared by every land material: the scene (time, global lighting...), the effects and the lighting. They
+//  used to be copied in each material, so a slider change in the shader window wrote every chunk material, and Bevy
+//  prepared each of them again (bind group and uniform buffers).
+// Now they're in a single set of GPU uniform buffers (LandSharedUniformBuffers, in the render world), created once
+//  and written in place. Every land material binds them at the same bindings as before (104, 105, 106), through the
+//  base of its ExtendedMaterial: LandSharedUniformsExtension, whose bind group entries are those buffers. The land
+//  materials keep only their per-chunk data (land uniform, tile data) and the texture arrays.
+// The main world sets LandSharedUniforms: push_uniforms_if_dirty (terrain_shader_ui) copies the fields of
+//  UniformState that changed, sys_update_animated_land_time (animation) the time. Changing it is extracted to the
+//  render world, and the buffers whose value changed are written: O(1), whatever the number of chunks. No material
+//  is touched.
+// The counters (LandUniformSyncStats) are shown in the diagnostics window: the pushes, and the buffer writes
+//  (counted in the main world by sys_count_land_shared_uniform_writes, as the render world will write them).
+
+/// Bindings of the shared uniforms in the land shader (group 2, as the material bindings).
+const SCENE_UNIFORM_BINDING: u32 = 104;
+const EFFECTS_UNIFORM_BINDING: u32 = 105;
+const LIGHTING_UNIFORM_BINDING: u32 = 106;
+
+/// Base of the land materials: the standard material, extended with the shared uniforms.
+pub type LandMaterialBase = ExtendedMaterial<StandardMaterial, LandSharedUniformsExtension>;
+
+pub fn land_material_base() -> LandMaterialBase {
+    ExtendedMaterial {
+        base: StandardMaterial::default(),
+        extension: LandSharedUniformsExtension,
+    }
+}
+
+/// Values of the shared uniforms, extracted to the render world when they change.
+#[derive(Resource, ExtractResource, Clone, Copy, Debug)]
+pub struct LandSharedUniforms {
+    pub scene: SceneUniform,
+    pub effects: LandEffectsUniform,
+    pub lighting: LandLightingUniforms,
+}
+impl Default for LandSharedUniforms {
+    fn default() -> Self {
+        Self {
+            scene: SceneUniform {
+                camera_position: PlayerCamera::BASE_OFFSET_FROM_PLAYER,
+                time_seconds: 0.0,
+                light_direction: constants::BAKED_GLOBAL_LIGHT.normalize(),
+                global_lighting: 1.0,
+            },
+            effects: LandEffectsUniform::default(),
+            lighting: LandLightingUniforms::default(),
+        }
+    }
+}
+impl LandSharedUniforms {
+    /// How many of the buffers (scene, effects, lighting) hold different values in the two.
+    fn buffers_changed_from(&self, other: &Self) -> u64 {
+        (self.scene != other.scene) as u64
+            + (self.effects != other.effects) as u64
+            + (self.lighting != other.lighting) as u64
+    }
+
+    /// Copies the given groups of fields from the state.
+    pub fn set_from(&mut self, fields: LandUniformFields, u: &UniformState) {
+        if fields.contains(LandUniformFields::EFFECTS) {
+            self.effects = u.effects;
+        }
+        if fields.contains(LandUniformFields::LIGHTING) {
+            self.lighting = u.lighting;
+        }
+        if fields.contains(LandUniformFields::GLOBAL_LIGHTING) {
+            self.scene.global_lighting = u.global_lighting;
+        }
+    }
+}
+
+/// Render world: the GPU buffers of LandSharedUniforms, bound by every land material.
+#[derive(Resource)]
+pub struct LandSharedUniformBuffers {
+    scene: UniformBuffer<SceneUniform>,
+    effects: UniformBuffer<LandEffectsUniform>,
+    lighting: UniformBuffer<LandLightingUniforms>,
+    /// Values in the buffers.
+    written: LandSharedUniforms,
+}
+impl FromWorld for LandSharedUniformBuffers {
+    /// The buffers exist before the first material is prepared; they're only written afterwards, never replaced.
+    fn from_world(world: &mut World) -> Self {
+        let uniforms = LandSharedUniforms::default();
+        let mut buffers = Self {
+            scene: uniforms.scene.into(),
+            effects: uniforms.effects.into(),
+            lighting: uniforms.lighting.into(),
+            written: uniforms,
+        };
+        buffers.scene.set_label(Some("land_scene_uniform"));
+        buffers.effects.set_label(Some("land_effects_uniform"));
+        buffers.lighting.set_label(Some("land_lighting_uniform"));
+        let (render_device, render_queue) = (world.resource::<RenderDevice>(), world.resource::<RenderQueue>());
+        buffers.scene.write_buffer(render_device, render_queue);
+        buffers.effects.write_buffer(render_device, render_queue);
+        buffers.lighting.write_buffer(render_device, render_queue);
+        buffers
+    }
+}
+impl LandSharedUniformBuffers {
+    /// Writes the buffers whose value changed.
+    fn write(&mut self, uniforms: &LandSharedUniforms, render_device: &RenderDevice, render_queue: &RenderQueue) {
+        if uniforms.scene != self.written.scene {
+            self.scene.set(uniforms.scene);
+            self.scene.write_buffer(render_device, render_queue);
+        }
+        if uniforms.effects != self.written.effects {
+            self.effects.set(uniforms.effects);
+            self.effects.write_buffer(render_device, render_queue);
+        }
+        if uniforms.lighting != self.written.lighting {
+            self.lighting.set(uniforms.lighting);
+            self.lighting.write_buffer(render_device, render_queue);
+        }
+        self.written = *uniforms;
+    }
+}
+
+/// Material extension binding the shared uniform buffers. It has no data: every material using it binds the same
+///  buffers.
+#[derive(Asset, TypePath, Clone, Copy, Debug, Default)]
+pub struct LandSharedUniformsExtension;
+
+impl MaterialExtension for LandSharedUniformsExtension {}
+
+// Written by hand: the AsBindGroup derive can only bind uniform buffers holding the data of the material itself.
+impl AsBindGroup for LandSharedUniformsExtension {
+    type Data = ();
+    type Param = SRes<LandSharedUniformBuffers>;
+
+    fn label() -> Option<&'static str> {
+        Some("land_shared_uniforms")
+    }
+
+    fn unprepared_bind_group(
+        &self,
+        _layout: &BindGroupLayout,
+        _render_device: &RenderDevice,
+        buffers: &mut SystemParamItem<'_, '_, Self::Param>,
+        _force_no_bindless: bool,
+    ) -> Result<UnpreparedBindGroup<Self::Data>, AsBindGroupError> {
+        let (Some(scene), Some(effects), Some(lighting)) = (
+            buffers.scene.buffer(),
+            buffers.effects.buffer(),
+            buffers.lighting.buffer(),
+        ) else {
+            return Err(AsBindGroupError::RetryNextUpdate);
+        };
+        Ok(UnpreparedBindGroup {
+            bindings: BindingResources(vec![
+                (SCENE_UNIFORM_BINDING, OwnedBindingResource::Buffer(scene.clone())),
+                (EFFECTS_UNIFORM_BINDING, OwnedBindingResource::Buffer(effects.clone())),
+                (LIGHTING_UNIFORM_BINDING, OwnedBindingResource::Buffer(lighting.clone())),
+            ]),
+            data: (),
+        })
+    }
+
+    fn bind_group_layout_entries(_render_device: &RenderDevice, _force_no_bindless: bool) -> Vec<BindGroupLayoutEntry> {
+        vec![
+            uniform_layout_entry::<SceneUniform>(SCENE_UNIFORM_BINDING),
+            uniform_layout_entry::<LandEffectsUniform>(EFFECTS_UNIFORM_BINDING),
+            uniform_layout_entry::<LandLightingUniforms>(LIGHTING_UNIFORM_BINDING),
+        ]
+    }
+}
+
+fn uniform_layout_entry<T: ShaderType>(binding: u32) -> BindGroupLayoutEntry {
+    BindGroupLayoutEntry {
+        binding,
+        visibility: ShaderStages::VERTEX_FRAGMENT,
+        ty: BindingType::Buffer {
+            ty: BufferBindingType::Uniform,
+            has_dynamic_offset: false,
+            min_binding_size: Some(T::min_size()),
+        },
+        count: None,
+    }
+}
+
+/// Groups of uniform fields copied from UniformState, as a bit set.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct LandUniformFields(u8);
+impl LandUniformFields {
+    pub const NONE: Self = Self(0);
+    pub const EFFECTS: Self = Self(1);
+    pub const LIGHTING: Self = Self(1 << 1);
+    /// The global_lighting field of the scene uniform.
+    pub const GLOBAL_LIGHTING: Self = Self(1 << 2);
+    pub const ALL: Self = Self(Self::EFFECTS.0 | Self::LIGHTING.0 | Self::GLOBAL_LIGHTING.0);
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The groups with different values in the two states.
+    pub fn changed_between(a: &UniformState, b: &UniformState) -> Self {
+        let mut fields = Self::NONE;
+        if a.effects != b.effects {
+            fields |= Self::EFFECTS;
+        }
+        if a.lighting != b.lighting {
+            fields |= Self::LIGHTING;
+        }
+        if a.global_lighting != b.global_lighting {
+            fields |= Self::GLOBAL_LIGHTING;
+        }
+        fields
+    }
+
+    pub fn label(self) -> String {
+        let names: Vec<&str> = [
+            (Self::EFFECTS, "effects"),
+            (Self::LIGHTING, "lighting"),
+            (Self::GLOBAL_LIGHTING, "global lighting"),
+        ]
+        .into_iter()
+        .filter(|(fields, _)| self.contains(*fields))
+        .map(|(_, name)| name)
+        .collect();
+        if names.is_empty() {
+            "none".to_owned()
+        } else {
+            names.join(", ")
+        }
+    }
+}
+impl BitOr for LandUniformFields {
+    type Output = Self;
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+impl BitOrAssign for LandUniformFields {
+    fn bitor_assign(&mut self, other: Self) {
+        self.0 |= other.0;
+    }
+}
+
+/// Counters of the uniform pushes, since startup.
+#[derive(Resource, Default, Debug)]
+pub struct LandUniformSyncStats {
+    /// Pushes of UniformState that changed something.
+    pub pushes: u64,
+    /// Pushes of UniformState marked dirty without a changed value.
+    pub unchanged_pushes: u64,
+    /// Groups changed by the last push.
+    pub last_push_fields: LandUniformFields,
+    /// Writes of the shared buffers (scene, effects, lighting: up to 3 per frame).
+    pub buffer_writes: u64,
+}
+
+/// Sets up LandSharedUniforms and its GPU buffers.
+pub struct LandSharedUniformsPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(LandSharedUniformsPlugin);
+
+impl Plugin for LandSharedUniformsPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<LandSharedUniforms>()
+            .init_resource::<LandUniformSyncStats>()
+            .add_plugins(ExtractResourcePlugin::<LandSharedUniforms>::default())
+            .add_systems(PostUpdate, sys_count_land_shared_uniform_writes);
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app.add_systems(Render, write_land_shared_uniforms.in_set(RenderSet::PrepareResources));
+    }
+
+    // The render device only exists once the renderer is initialized.
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<LandSharedUniformBuffers>();
+        }
+    }
+}
+
+/// Counts the buffers that the render world will write for the changes of the frame (see write_land_shared_uniforms).
+pub fn sys_count_land_shared_uniform_writes(
+    mut extracted: Local<LandSharedUniforms>,
+    uniforms_r: Res<LandSharedUniforms>,
+    mut stats_r: ResMut<LandUniformSyncStats>,
+) {
+    if !uniforms_r.is_changed() {
+        return;
+    }
+    let writes = uniforms_r.buffers_changed_from(&extracted);
+    if writes > 0 {
+        stats_r.buffer_writes += writes;
+    }
+    *extracted = *uniforms_r;
+}
+
+fn write_land_shared_uniforms(
+    uniforms_r: Option<Res<LandSharedUniforms>>,
+    mut buffers_r: ResMut<LandSharedUniformBuffers>,
+    render_device_r: Res<RenderDevice>,
+    render_queue_r: Res<RenderQueue>,
+) {
+    let Some(uniforms_r) = uniforms_r.filter(|uniforms_r| uniforms_r.is_changed()) else {
+        return;
+    };
+    profile_span!("write_land_shared_uniforms");
+    buffers_r.write(&uniforms_r, &render_device_r, &render_queue_r);
+}
@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+use uocf::geo::map::MapCell;
+use uocf::tiledata::TileData;
+
+use crate::core::controls::key_bindings::{ActionInput, InputAction};
+use crate::external_data::shader_presets::UniformState;
+
+// Debug overlays of the land shader: they tint the terrain to show data which isn't visible otherwise.
+// The mode is LandEffectsUniform.overlay_mode, picked in the Terrain Shader Controls window or toggled with a key.
+// - Walkability: from the tiledata flags of the land tiles, packed per tile in TileUniform.tile_flags. Statics aren't
+//   taken into account.
+// - Altitude: a color ramp by z, between LandEffectsUniform.altitude_params x and y.
+// - Map diff: the tiles which differ from the other version of the map compared (see maps::compare), packed per tile
+//   in TileUniform.tile_flags when the chunk is built.
+// Contour lines every altitude_params.z z units (when altitude_params.w is 1) can be drawn over any overlay.
+// Grid lines (LandEffectsUniform.grid_lines) show the tile, map block and chunk boundaries, to debug seams and
+//  coordinate math. They're drawn over the final color, so they keep their colors whatever the lighting.
+
+/// Values for LandEffectsUniform.overlay_mode. Keep in sync with the TERRAIN_OVERLAY_* consts in land_base.wgsl.
+pub const TERRAIN_OVERLAY_NONE: u32 = 0;
+pub const TERRAIN_OVERLAY_WALKABILITY: u32 = 1;
+pub const TERRAIN_OVERLAY_ALTITUDE: u32 = 2;
+pub const TERRAIN_OVERLAY_MAP_DIFF: u32 = 3;
+
+/// Bits of LandEffectsUniform.grid_lines. Keep in sync with the GRID_LINES_* consts in land_base.wgsl.
+pub const GRID_LINES_TILES: u32 = 1 << 0;
+pub const GRID_LINES_BLOCKS: u32 = 1 << 1;
+pub const GRID_LINES_CHUNKS: u32 = 1 << 2;
+pub const GRID_LINES_ALL: u32 = GRID_LINES_TILES | GRID_LINES_BLOCKS | GRID_LINES_CHUNKS;
+
+/// Grid line kinds and their labels, for the UI.
+pub const GRID_LINE_KINDS: [(u32, &str); 3] = [
+    (GRID_LINES_TILES, "Tiles"),
+    (GRID_LINES_BLOCKS, "Map blocks (8x8)"),
+    (GRID_LINES_CHUNKS, "Chunks"),
+];
+
+/// Bits of TileUniform.tile_flags (16 bits). Keep in sync with the TILE_FLAG_* consts in land_base.wgsl.
+pub const TILE_FLAG_IMPASSABLE: u32 = 1 << 0;
+pub const TILE_FLAG_WET: u32 = 1 << 1;
+/// Different tile id in the other version of the map compared.
+pub const TILE_FLAG_CHANGED_ID: u32 = 1 << 2;
+/// Different z in the other version of the map compared.
+pub const TILE_FLAG_CHANGED_Z: u32 = 1 << 3;
+
+/// Overlay modes and their labels, for the UI.
+pub const TERRAIN_OVERLAYS: [(u32, &str); 4] = [
+    (TERRAIN_OVERLAY_NONE, "None"),
+    (TERRAIN_OVERLAY_WALKABILITY, "Walkability"),
+    (TERRAIN_OVERLAY_ALTITUDE, "Altitude"),
+    (TERRAIN_OVERLAY_MAP_DIFF, "Map diff"),
+];
+
+/// TileUniform.tile_flags of a land tile, from its tiledata flags.
+pub fn tile_flags_from_tiledata(tiledata: &TileData, tile_id: u16) -> u32 {
+    let Some(land_tile) = tiledata.land_tile(tile_id) else {
+        return 0;
+    };
+    let mut tile_flags = 0;
+    if land_tile.flags.impassable() {
+        tile_flags |= TILE_FLAG_IMPASSABLE;
+    }
+    if land_tile.flags.wet() {
+        tile_flags |= TILE_FLAG_WET;
+    }
+    tile_flags
+}
+
+/// TileUniform.tile_flags bits telling how a land tile differs from the same tile in the other version of the map.
+pub fn tile_flags_from_diff(cell: &MapCell, other_cell: &MapCell) -> u32 {
+    let mut tile_flags = 0;
+    if cell.id != other_cell.id {
+        tile_flags |= TILE_FLAG_CHANGED_ID;
+    }
+    if cell.z != other_cell.z {
+        tile_flags |= TILE_FLAG_CHANGED_Z;
+    }
+    tile_flags
+}
+
+/// Switches the walkability overlay on and off (InputAction::ToggleWalkabilityOverlay).
+pub fn sys_toggle_walkability_overlay(action_input: ActionInput, mut uniform_state_r: ResMut<UniformState>) {
+    if !action_input.just_pressed(InputAction::ToggleWalkabilityOverlay) {
+        return;
+    }
+    let u = uniform_state_r.as_mut();
+    u.effects.overlay_mode = if u.effects.overlay_mode == TERRAIN_OVERLAY_WALKABILITY {
+        TERRAIN_OVERLAY_NONE
+    } else {
+        TERRAIN_OVERLAY_WALKABILITY
+    };
+    u.dirty = true;
+}
+
+/// Shows and hides the grid lines (InputAction::ToggleGridOverlay): all of them, if none is shown.
+pub fn sys_toggle_grid_overlay(action_input: ActionInput, mut uniform_state_r: ResMut<UniformState>) {
+    if !action_input.just_pressed(InputAction::ToggleGridOverlay) {
+        return;
+    }
+    let u = uniform_state_r.as_mut();
+    u.effects.grid_lines = if u.effects.grid_lines == 0 { GRID_LINES_ALL } else { 0 };
+    u.dirty = true;
+}
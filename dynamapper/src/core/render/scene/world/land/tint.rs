@@ -0,0 +1,154 @@
+// Runtime tints of the land tiles: a rectangle of tiles shown with a hue, e.g. to highlight a house plot or a quest
+//  area. Other plugins and tools send a TintRegionEvent (or ClearLandTintsEvent), or change LandTints directly.
+// The hue goes in the texture_hue field of the tiles (TileUniform), set when the chunks are built: a tint added,
+//  expired or cleared tags with LCDirty the chunks whose data grid holds its tiles, like an edit of the map does.
+//  The hue is applied by the land shader (apply_hue), blended with the neighbor tiles like the texture.
+// Where tints overlap, the last one added wins.
+
+use bevy::prelude::*;
+use std::time::Duration;
+use uocf::geo::map::MapRectCells;
+
+use super::draw_mesh::mark_land_chunks_dirty;
+use super::{LCDirty, LCMesh, LandChunkSize};
+use crate::prelude::*;
+
+fn lg(sev: LogSev, msg: &str) {
+    logger::one(None, sev, LogAbout::RenderWorldLand, msg);
+}
+
+/// Request to tint the land tiles of a rectangle of a map plane with a hue.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct TintRegionEvent {
+    pub map_id: u32,
+    pub rect: MapRectCells,
+    /// Hue id (row of the hue palette, see texture_cache::hues). 0 shows the tiles without hue, over the tints below.
+    pub hue: u16,
+    /// How long the tint lasts. None: until it's cleared.
+    pub duration: Option<Duration>,
+}
+
+/// Request to remove the tints of a map plane, or of every map plane (None).
+#[derive(Event, Debug, Clone, Copy)]
+pub struct ClearLandTintsEvent {
+    pub map_id: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LandTint {
+    pub map_id: u32,
+    pub rect: MapRectCells,
+    pub hue: u16,
+    /// Time::elapsed when the tint expires. None: never.
+    pub expires_at: Option<Duration>,
+}
+impl LandTint {
+    pub fn contains(&self, x: u32, y: u32) -> bool {
+        x >= self.rect.x0
+            && x < self.rect.x0 + self.rect.width
+            && y >= self.rect.y0
+            && y < self.rect.y0 + self.rect.height
+    }
+}
+
+/// The active tints, oldest first.
+#[derive(Resource, Default, Debug)]
+pub struct LandTints {
+    tints: Vec<LandTint>,
+}
+impl LandTints {
+    pub fn iter(&self) -> impl Iterator<Item = &LandTint> {
+        self.tints.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tints.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tints.is_empty()
+    }
+
+    /// The tints of a map plane, oldest first, to look up the hue of its tiles (see tint_hue_at).
+    pub fn on_map(&self, map_id: u32) -> Vec<LandTint> {
+        self.tints
+            .iter()
+            .filter(|tint| tint.map_id == map_id)
+            .copied()
+            .collect()
+    }
+
+    /// Adds a tint over the others. The chunks showing it have to be tagged LCDirty (sys_update_land_tints does it).
+    pub fn add(&mut self, tint: LandTint) {
+        self.tints.push(tint);
+    }
+
+    /// Removes the tints matching the predicate, and returns them.
+    pub fn remove_where(&mut self, mut predicate: impl FnMut(&LandTint) -> bool) -> Vec<LandTint> {
+        let mut removed = Vec::new();
+        self.tints.retain(|tint| {
+            let remove = predicate(tint);
+            if remove {
+                removed.push(*tint);
+            }
+            !remove
+        });
+        removed
+    }
+}
+
+/// Hue of a tile: the one of the last tint covering it, or 0 (no hue).
+pub fn tint_hue_at(tints: &[LandTint], x: u32, y: u32) -> u16 {
+    tints
+        .iter()
+        .rev()
+        .find(|tint| tint.contains(x, y))
+        .map_or(0, |tint| tint.hue)
+}
+
+/// Adds and clears the tints requested by the events, removes the expired ones, and tags with LCDirty the chunks
+///  showing the tiles whose hue changed.
+pub fn sys_update_land_tints(
+    mut commands: Commands,
+    time_r: Res<Time>,
+    mut tint_events: EventReader<TintRegionEvent>,
+    mut clear_events: EventReader<ClearLandTintsEvent>,
+    mut land_tints_r: ResMut<LandTints>,
+    chunk_size_r: Res<LandChunkSize>,
+    chunk_q: Query<(Entity, &LCMesh), Without<LCDirty>>,
+) {
+    let now = time_r.elapsed();
+    let mut changed: Vec<LandTint> = Vec::new();
+
+    for event in clear_events.read() {
+        let removed = land_tints_r.remove_where(|tint| event.map_id.is_none_or(|map_id| map_id == tint.map_id));
+        changed.extend(removed);
+    }
+    for event in tint_events.read() {
+        if event.rect.width == 0 || event.rect.height == 0 {
+            lg(
+                LogSev::Warn,
+                &format!("Ignoring the tint of an empty rectangle: {event:?}."),
+            );
+            continue;
+        }
+        let tint = LandTint {
+            map_id: event.map_id,
+            rect: event.rect,
+            hue: event.hue,
+            expires_at: event.duration.map(|duration| now + duration),
+        };
+        lg(LogSev::Debug, &format!("Adding the land tint {tint:?}."));
+        land_tints_r.add(tint);
+        changed.push(tint);
+    }
+    let expired = land_tints_r.remove_where(|tint| tint.expires_at.is_some_and(|expires_at| expires_at <= now));
+    changed.extend(expired);
+
+    mark_land_chunks_dirty(
+        &mut commands,
+        *chunk_size_r,
+        &chunk_q,
+        changed.iter().map(|tint| (tint.map_id, tint.rect)),
+    );
+}
@@ -0,0 +1,45 @@
+pub mod art_atlas;
+pub mod draw_statics;
+
+use crate::core::system_sets::*;
+use crate::prelude::*;
+use bevy::prelude::*;
+
+/// Tag component: Marks a child entity holding the merged statics mesh of a land chunk (one for each art atlas used
+///  by its items).
+/// It's spawned as a child of the LCMesh entity, so it's despawned together with its parent chunk.
+#[derive(Component)]
+pub struct SCMesh {
+    #[allow(unused)]
+    pub parent_map_id: u32,
+    pub gx: u32, // chunk grid coordinates (same as the parent land chunk)
+    pub gy: u32,
+}
+
+/// Tag component: added to LCMesh entities whose statics have already been processed,
+/// even if the chunk has no statics at all (so we don't query the statics file each frame).
+#[derive(Component)]
+pub struct LCStaticsDrawn;
+
+/// Builds and draws the static items (buildings, trees, decorations) of every spawned land chunk.
+pub struct DrawStaticsPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(DrawStaticsPlugin);
+
+impl Plugin for DrawStaticsPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.add_systems(
+            Update,
+            (
+                draw_statics::sys_redraw_statics,
+                draw_statics::sys_draw_statics_for_spawned_chunks,
+            )
+                .chain()
+                .in_set(SceneRenderLandSysSet::RenderStatics)
+                .run_if(in_state(AppState::InGame)),
+        )
+        .add_systems(Startup, draw_statics::setup_statics_art);
+    }
+}
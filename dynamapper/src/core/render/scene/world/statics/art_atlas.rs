@@ -0,0 +1,152 @@
+// Texture atlases holding the item art drawn by the statics meshes (see draw_statics).
+// - Each art is decoded (uocf::art) and hued once, then copied into the first atlas with room for it (guillotiere
+//   allocator). Its UV rect is cached by tile id: the item id and its hue (see static_art_tile_id).
+// - Single entries are never evicted, since the meshes keep the UVs of their items. When the atlases are full, the
+//   caller clears them and draws every statics mesh again.
+
+use bevy::asset::RenderAssetUsages;
+use bevy::image::ImageSampler;
+use bevy::prelude::*;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use guillotiere::{AtlasAllocator, size2};
+use std::collections::HashMap;
+
+const ATLAS_DIM_PX: u32 = 2048;
+pub const NUM_ATLASES: usize = 2;
+/// Transparent pixels kept right and below each art, so the sampling never reaches the neighbour one.
+const ATLAS_PADDING_PX: i32 = 1;
+const BYTES_PER_PIXEL: u32 = 4; // RGBA8888
+
+#[derive(Clone, Copy, Debug)]
+pub struct AtlasUVInfo {
+    pub atlas_idx: usize,
+    pub uv_rect: [f32; 4], // (u0,v0,u1,v1) normalized
+    /// Size of the art, in pixels.
+    pub width: u32,
+    pub height: u32,
+}
+
+pub struct TileAtlasSet {
+    atlases: [Handle<Image>; NUM_ATLASES],
+    allocators: [AtlasAllocator; NUM_ATLASES],
+    /// None if the tile has no art.
+    tile_map: HashMap<u32, Option<AtlasUVInfo>>,
+    /// A tile didn't fit since the last clear.
+    overflowed: bool,
+}
+
+impl TileAtlasSet {
+    pub fn new(images: &mut Assets<Image>) -> Self {
+        let atlases = std::array::from_fn(|_| {
+            let mut atlas_img = Image::new_fill(
+                Extent3d {
+                    width: ATLAS_DIM_PX,
+                    height: ATLAS_DIM_PX,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &[0, 0, 0, 0], // transparent
+                TextureFormat::Rgba8UnormSrgb,
+                // Kept in the main world too, to copy the new tiles into it.
+                RenderAssetUsages::MAIN_WORLD | RenderAssetUsages::RENDER_WORLD,
+            );
+            // The art is pixel art: no blending across the art borders.
+            atlas_img.sampler = ImageSampler::nearest();
+            images.add(atlas_img)
+        });
+        Self {
+            atlases,
+            allocators: std::array::from_fn(|_| AtlasAllocator::new(size2(ATLAS_DIM_PX as i32, ATLAS_DIM_PX as i32))),
+            tile_map: HashMap::default(),
+            overflowed: false,
+        }
+    }
+
+    pub fn get_handles(&self) -> [Handle<Image>; NUM_ATLASES] {
+        self.atlases.clone()
+    }
+
+    /// Returns the atlas and the UV rect of a tile, inserting it if needed.
+    /// - On miss: runs `get_image_from_id`, which returns None if the tile has no art (cached as well).
+    /// - Returns None if the tile has no art, or if there's no room left for it (see overflowed).
+    pub fn get_tile_uv(
+        &mut self,
+        images: &mut Assets<Image>,
+        get_image_from_id: impl FnOnce(u32) -> Option<Image>,
+        tile_id: u32,
+    ) -> Option<AtlasUVInfo> {
+        if let Some(&cached) = self.tile_map.get(&tile_id) {
+            return cached;
+        }
+        let Some(tile_img) = get_image_from_id(tile_id) else {
+            self.tile_map.insert(tile_id, None);
+            return None;
+        };
+        let (tile_width, tile_height) = (tile_img.width(), tile_img.height());
+        let req_size = size2(
+            tile_width as i32 + ATLAS_PADDING_PX,
+            tile_height as i32 + ATLAS_PADDING_PX,
+        );
+
+        for atlas_idx in 0..NUM_ATLASES {
+            let Some(allocation) = self.allocators[atlas_idx].allocate(req_size) else {
+                continue;
+            };
+            let Some(atlas_img) = images.get_mut(&self.atlases[atlas_idx]) else {
+                return None;
+            };
+            let (x0, y0) = (allocation.rectangle.min.x as u32, allocation.rectangle.min.y as u32);
+            patch_into_atlas(atlas_img, x0, y0, &tile_img);
+
+            let uv_info = AtlasUVInfo {
+                atlas_idx,
+                uv_rect: uv_rect_px(x0, y0, tile_width, tile_height),
+                width: tile_width,
+                height: tile_height,
+            };
+            self.tile_map.insert(tile_id, Some(uv_info));
+            return Some(uv_info);
+        }
+
+        // Not cached: it's inserted again after the next clear.
+        self.overflowed = true;
+        None
+    }
+
+    pub fn overflowed(&self) -> bool {
+        self.overflowed
+    }
+
+    /// Forgets every tile. The meshes using them must be drawn again.
+    pub fn clear(&mut self) {
+        for allocator in &mut self.allocators {
+            allocator.clear();
+        }
+        self.tile_map.clear();
+        self.overflowed = false;
+    }
+}
+
+fn uv_rect_px(x0: u32, y0: u32, width: u32, height: u32) -> [f32; 4] {
+    let u0 = x0 as f32 / ATLAS_DIM_PX as f32;
+    let v0 = y0 as f32 / ATLAS_DIM_PX as f32;
+    let u1 = (x0 + width) as f32 / ATLAS_DIM_PX as f32;
+    let v1 = (y0 + height) as f32 / ATLAS_DIM_PX as f32;
+    [u0, v0, u1, v1]
+}
+
+/// Copies the tile image (RGBA8888) into the atlas, with its top left corner at (x0, y0).
+fn patch_into_atlas(atlas_img: &mut Image, x0: u32, y0: u32, tile_img: &Image) {
+    let (width, height) = (tile_img.width(), tile_img.height());
+    let src_bpr = (width * BYTES_PER_PIXEL) as usize;
+    let dst_bpr = (atlas_img.width() * BYTES_PER_PIXEL) as usize;
+
+    let (Some(tile_img_data), Some(atlas_img_data)) = (tile_img.data.as_deref(), atlas_img.data.as_deref_mut()) else {
+        return;
+    };
+    for row in 0..height as usize {
+        let src_start = row * src_bpr;
+        let dst_start = (y0 as usize + row) * dst_bpr + (x0 * BYTES_PER_PIXEL) as usize;
+        atlas_img_data[dst_start..dst_start + src_bpr].copy_from_slice(&tile_img_data[src_start..src_start + src_bpr]);
+    }
+}
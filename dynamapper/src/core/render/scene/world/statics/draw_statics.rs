@@ -0,0 +1,424 @@
+use bevy::{
+    asset::RenderAssetUsages,
+    platform::time::Instant,
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        view::RenderLayers,
+    },
+};
+use std::time::Duration;
+use uocf::art::Art;
+use uocf::geo::{
+    map::{MapBlock, MapBlockRelPos},
+    statics::StaticsBlock,
+};
+use uocf::hues::{HueEntry, Hues};
+use uocf::tiledata::TileData;
+
+use super::{
+    LCStaticsDrawn, SCMesh,
+    art_atlas::{AtlasUVInfo, NUM_ATLASES, TileAtlasSet},
+};
+use crate::{
+    core::{
+        render::{
+            export::MapExportChunk,
+            scene::{
+                camera::PlayerCamera,
+                world::land::{LCMesh, LandChunkSize},
+            },
+        },
+        uo_files_loader::{ArtRes, HuesRes, StaticsPlanesRes, TileDataRes},
+    },
+    prelude::*,
+};
+
+// Every static item is drawn with its art (art.mul), hued, on a quad textured from the art atlases (see art_atlas):
+// - Upright quads stand on the tile center at the item z, and face the player camera. The art keeps the proportions
+//   of the classic client: 44 pixels across the tile diagonal, 4 pixels per unit of height. They're drawn again when
+//   the view is rotated.
+// - Flat items (no height, with an art the size of a land tile) lie on their tile instead: the diamond of the art is
+//   mapped on the tile square, as land art is. The map export looks from above, so it draws every item flat, at the
+//   top of the item.
+// Transparent pixels are discarded (alpha mask): the quads share the depth buffer with the land chunks, so they're
+//  depth-sorted against the terrain for free. Their normal points up, so they're lit like the ground.
+
+/// Width of an art pixel, in tile units: the 44 pixels across a land art span the tile diagonal.
+const ART_PIXEL_WIDTH: f32 = std::f32::consts::SQRT_2 / Art::LAND_ART_SIZE as f32;
+/// Art pixels per unit of height.
+const ART_PIXELS_PER_Z: f32 = 4.0;
+/// Flat items are lifted by this much (bevy units) over their z, not to z-fight with the land under them.
+const FLAT_ITEM_LIFT: f32 = 0.01;
+/// Upright quads are moved towards the camera by this much (bevy units) per unit of z, so the higher items of a tile
+///  are drawn over the lower ones, as in the classic client.
+const UPRIGHT_Z_BIAS: f32 = 0.0005;
+/// Min time between two clears of the full atlases. If the items in view don't fit, the missing ones aren't drawn
+///  until then.
+const ATLAS_MIN_CLEAR_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Atlases of the item art, with a material for each one.
+#[derive(Resource)]
+pub struct StaticsArt {
+    pub atlases: TileAtlasSet,
+    pub materials: [Handle<StandardMaterial>; NUM_ATLASES],
+    last_clear: Option<Instant>,
+}
+
+pub fn setup_statics_art(
+    mut commands: Commands,
+    mut images_r: ResMut<Assets<Image>>,
+    mut materials_r: ResMut<Assets<StandardMaterial>>,
+) {
+    let atlases = TileAtlasSet::new(&mut images_r);
+    let materials = atlases.get_handles().map(|atlas| {
+        materials_r.add(StandardMaterial {
+            base_color_texture: Some(atlas),
+            perceptual_roughness: 1.0,
+            alpha_mode: AlphaMode::Mask(0.5),
+            double_sided: true,
+            cull_mode: None,
+            ..default()
+        })
+    });
+    commands.insert_resource(StaticsArt {
+        atlases,
+        materials,
+        last_clear: None,
+    });
+}
+
+/// Horizontal direction faced by the upright quads: towards the player camera.
+fn toward_camera(camera: Option<&PlayerCamera>) -> Vec3 {
+    let offset = camera.map_or(PlayerCamera::BASE_OFFSET_FROM_PLAYER, PlayerCamera::offset_from_focus);
+    Vec3::new(offset.x, 0.0, offset.z).normalize()
+}
+
+/// Draws every statics mesh again when the view is rotated, since the upright quads face the camera, or when the art
+///  atlases are full (they're cleared first).
+pub fn sys_redraw_statics(
+    mut commands: Commands,
+    mut statics_art_r: ResMut<StaticsArt>,
+    mut drawn_facing: Local<Option<Vec3>>,
+    camera_q: Query<&PlayerCamera>,
+    statics_q: Query<(Entity, &ChildOf), With<SCMesh>>,
+) {
+    let facing = toward_camera(camera_q.single().ok());
+    let rotated = drawn_facing.is_some_and(|drawn| drawn != facing);
+    *drawn_facing = Some(facing);
+    let statics_art = statics_art_r.as_mut();
+    let clear_atlases = statics_art.atlases.overflowed()
+        && statics_art
+            .last_clear
+            .is_none_or(|last_clear| last_clear.elapsed() >= ATLAS_MIN_CLEAR_INTERVAL);
+    if !rotated && !clear_atlases {
+        return;
+    }
+    if clear_atlases {
+        logger::one(
+            None,
+            LogSev::Info,
+            LogAbout::RenderWorldArt,
+            "Statics art atlases full: clearing them and drawing the statics again.",
+        );
+        statics_art.atlases.clear();
+        statics_art.last_clear = Some(Instant::now());
+    }
+    for (entity, child_of) in statics_q.iter() {
+        commands.entity(entity).try_despawn();
+        commands.entity(child_of.parent()).try_remove::<LCStaticsDrawn>();
+    }
+}
+
+/// Main system: for every land chunk spawned in the scene, load its statics blocks and attach a mesh with all their
+///  items (one for each atlas holding their art). The meshes get the render layers of their chunk, so the statics of
+///  off-screen chunks (e.g. the map export ones) are drawn by the camera of those chunks only.
+pub fn sys_draw_statics_for_spawned_chunks(
+    mut commands: Commands,
+    mut meshes_r: ResMut<Assets<Mesh>>,
+    mut images_r: ResMut<Assets<Image>>,
+    mut statics_art_r: ResMut<StaticsArt>,
+    statics_planes_r: Res<StaticsPlanesRes>,
+    art_r: Res<ArtRes>,
+    tiledata_r: Res<TileDataRes>,
+    hues_r: Res<HuesRes>,
+    chunk_size_r: Res<LandChunkSize>,
+    camera_q: Query<&PlayerCamera>,
+    chunk_q: Query<(Entity, &LCMesh, Option<&RenderLayers>, Has<MapExportChunk>), Without<LCStaticsDrawn>>,
+) {
+    if chunk_q.is_empty() {
+        return;
+    }
+    let blocks_per_chunk = chunk_size_r.block_num_dim();
+    let facing = toward_camera(camera_q.single().ok());
+    let statics_art = statics_art_r.as_mut();
+
+    for (entity, chunk, chunk_layers, export_chunk) in chunk_q.iter() {
+        // Load (or get from the cache) the statics blocks covered by this chunk, with their offset (in tiles) from
+        //  the chunk origin.
+        let statics_blocks: Vec<(UVec2, StaticsBlock)> = {
+            let statics_planes_arc = statics_planes_r.0.clone();
+            let Some(mut statics_plane) = statics_planes_arc.get_mut(&chunk.parent_map_id) else {
+                logger::one(
+                    None,
+                    LogSev::Warn,
+                    LogAbout::RenderWorldArt,
+                    &format!("No statics loaded for map plane {}.", chunk.parent_map_id),
+                );
+                commands.entity(entity).insert(LCStaticsDrawn);
+                continue;
+            };
+            // Chunks at the map edges can be partially out of the map.
+            let bx0 = chunk.gx * blocks_per_chunk;
+            let by0 = chunk.gy * blocks_per_chunk;
+            let bx1 = (bx0 + blocks_per_chunk).min(statics_plane.size_blocks.width);
+            let by1 = (by0 + blocks_per_chunk).min(statics_plane.size_blocks.height);
+            let mut block_positions: Vec<MapBlockRelPos> = (bx0..bx1)
+                .flat_map(|x| (by0..by1).map(move |y| MapBlockRelPos { x, y }))
+                .collect();
+            profile_span!("load_statics_blocks", blocks = block_positions.len());
+            if let Err(e) = statics_plane.load_blocks(&mut block_positions) {
+                logger::one(
+                    None,
+                    LogSev::Error,
+                    LogAbout::RenderWorldArt,
+                    &format!("Can't load the statics blocks of chunk ({}, {}): {e}", chunk.gx, chunk.gy),
+                );
+            }
+            block_positions
+                .iter()
+                .filter_map(|&pos| {
+                    let offset = UVec2::new(pos.x - bx0, pos.y - by0) * MapBlock::CELLS_PER_ROW;
+                    Some((offset, statics_plane.block(pos)?.clone()))
+                })
+                .collect()
+        };
+
+        commands.entity(entity).insert(LCStaticsDrawn);
+
+        let view = if export_chunk {
+            StaticsView::TopDown
+        } else {
+            StaticsView::Upright { toward_camera: facing }
+        };
+        let meshes = build_statics_meshes(
+            &art_r.0,
+            &tiledata_r.0,
+            &hues_r.0,
+            &mut statics_art.atlases,
+            &mut images_r,
+            &statics_blocks,
+            view,
+        );
+
+        // Local coordinates: the children inherit the chunk transform (placed at the chunk origin).
+        for (mesh, material) in meshes.into_iter().zip(&statics_art.materials) {
+            // No items with their art in this atlas.
+            let Some(mesh) = mesh else {
+                continue;
+            };
+            let mesh = meshes_r.add(mesh);
+            commands.entity(entity).with_children(|parent| {
+                let mut statics = parent.spawn((
+                    SCMesh {
+                        parent_map_id: chunk.parent_map_id,
+                        gx: chunk.gx,
+                        gy: chunk.gy,
+                    },
+                    Mesh3d(mesh),
+                    MeshMaterial3d(material.clone()),
+                    Transform::default(),
+                ));
+                if let Some(layers) = chunk_layers {
+                    statics.insert(layers.clone());
+                }
+            });
+        }
+    }
+}
+
+/// How the items are laid out.
+#[derive(Clone, Copy)]
+enum StaticsView {
+    /// Upright quads facing the camera, in the given horizontal direction (normalized), flat ones for the flat items.
+    Upright { toward_camera: Vec3 },
+    /// Every item flat, at its top.
+    TopDown,
+}
+
+/// Atlas tile id of the art of an item, with its hue (0: no hue).
+fn static_art_tile_id(item_id: u16, hue: u16) -> u32 {
+    ((hue as u32) << 16) | item_id as u32
+}
+
+/// Reads the art of an item and hues it. Returns None if the item has no art.
+fn hued_item_art(art: &Art, item_id: u16, hue: Option<&HueEntry>, partial_hue: bool) -> Option<Image> {
+    let element = match art.item(item_id) {
+        Ok(element) => element?,
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Warn,
+                LogAbout::RenderWorldArt,
+                &format!("Can't read the art of item 0x{item_id:04X}: {e}"),
+            );
+            return None;
+        }
+    };
+    let mut pixel_data = element.pixel_data().clone();
+    if let Some(hue) = hue {
+        apply_hue(&mut pixel_data, hue, partial_hue);
+    }
+    Some(Image::new(
+        Extent3d {
+            width: *element.width(),
+            height: *element.height(),
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        pixel_data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::MAIN_WORLD,
+    ))
+}
+
+/// Recolors the art (RGBA8888) with the hue ramp, picked by the gray level (red channel) of each pixel, as the
+///  classic client does. Partial hues recolor the gray pixels only.
+fn apply_hue(pixel_data: &mut [u8], hue: &HueEntry, partial: bool) {
+    let ramp = hue.colors_rgba8888();
+    for px in pixel_data.chunks_exact_mut(4) {
+        if px[3] == 0 || (partial && !(px[0] == px[1] && px[1] == px[2])) {
+            continue;
+        }
+        // 5 bits, as stored in art.mul.
+        let i_color = (px[0] >> 3) as usize;
+        px[..3].copy_from_slice(&ramp[i_color * 4..i_color * 4 + 3]);
+    }
+}
+
+/// UVs of the left, bottom, right and top corners of the diamond at the bottom of the art, which cover the
+///  (x, y + 1), (x + 1, y + 1), (x + 1, y) and (x, y) corners of the tile. The whole art if it's smaller than a
+///  land tile.
+fn flat_art_uvs(uv_info: &AtlasUVInfo) -> [[f32; 2]; 4] {
+    let [u0, v0, u1, v1] = uv_info.uv_rect;
+    let size = Art::LAND_ART_SIZE;
+    if uv_info.width < size || uv_info.height < size {
+        return [[u0, v1], [u1, v1], [u1, v0], [u0, v0]];
+    }
+    // Atlas UV of a pixel position of the art.
+    let u = |px: u32| u0 + (u1 - u0) * px as f32 / uv_info.width as f32;
+    let v = |py: u32| v0 + (v1 - v0) * py as f32 / uv_info.height as f32;
+    let (center_x, bottom) = (uv_info.width / 2, uv_info.height);
+    [
+        [u(center_x - size / 2), v(bottom - size / 2)],
+        [u(center_x), v(bottom)],
+        [u(center_x + size / 2), v(bottom - size / 2)],
+        [u(center_x), v(bottom - size)],
+    ]
+}
+
+/// Vertex buffers of the quads of a mesh.
+#[derive(Default)]
+struct QuadBuffers {
+    positions: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+}
+impl QuadBuffers {
+    fn push_quad(&mut self, corners: [Vec3; 4], uvs: [[f32; 2]; 4]) {
+        let base = self.positions.len() as u32;
+        self.positions.extend(corners.map(|corner| corner.to_array()));
+        self.uvs.extend_from_slice(&uvs);
+        self.indices
+            .extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3]);
+    }
+
+    fn into_mesh(self) -> Option<Mesh> {
+        if self.positions.is_empty() {
+            return None;
+        }
+        let normals = vec![[0.0, 1.0, 0.0]; self.positions.len()];
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::RENDER_WORLD);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+        mesh.insert_indices(Indices::U32(self.indices));
+        Some(mesh)
+    }
+}
+
+/// Builds a mesh for each atlas, with a quad for each drawable item of the blocks whose art is in that atlas, placed
+///  at its offset (in tiles) from the chunk origin.
+/// None for the atlases without items.
+fn build_statics_meshes(
+    art: &Art,
+    tiledata: &TileData,
+    hues: &Hues,
+    atlases: &mut TileAtlasSet,
+    images: &mut Assets<Image>,
+    blocks: &[(UVec2, StaticsBlock)],
+    view: StaticsView,
+) -> [Option<Mesh>; NUM_ATLASES] {
+    let mut buffers: [QuadBuffers; NUM_ATLASES] = Default::default();
+
+    for (block_offset, block) in blocks {
+        for item in block.items() {
+            let Some(item_tile) = tiledata.item_tile(item.id) else {
+                continue;
+            };
+            if item_tile.is_nodraw().unwrap_or(true) {
+                continue;
+            }
+            let hue = hues.hue(item.hue);
+            let tile_id = static_art_tile_id(item.id, hue.map_or(0, |_| item.hue));
+            let get_art = |_: u32| hued_item_art(art, item.id, hue, item_tile.flags.partialhue());
+            let Some(uv_info) = atlases.get_tile_uv(images, get_art, tile_id) else {
+                continue;
+            };
+            debug_assert!((item.x as u32) < MapBlock::CELLS_PER_ROW && (item.y as u32) < MapBlock::CELLS_PER_COLUMN);
+            let x = (block_offset.x + item.x as u32) as f32;
+            let y = (block_offset.y + item.y as u32) as f32;
+            let bottom = scale_uo_z_to_bevy_units(item.z as f32);
+            let quads = &mut buffers[uv_info.atlas_idx];
+
+            let flat_item =
+                item_tile.height() == 0 && uv_info.width == Art::LAND_ART_SIZE && uv_info.height == Art::LAND_ART_SIZE;
+            match view {
+                StaticsView::Upright { toward_camera } if !flat_item => {
+                    let [u0, v0, u1, v1] = uv_info.uv_rect;
+                    let center = Vec3::new(x + 0.5, bottom, y + 0.5)
+                        + toward_camera * (item.z as f32 - i8::MIN as f32) * UPRIGHT_Z_BIAS;
+                    let half_width = Vec3::Y.cross(toward_camera) * (uv_info.width as f32 * ART_PIXEL_WIDTH / 2.0);
+                    let up = Vec3::Y * scale_uo_z_to_bevy_units(uv_info.height as f32 / ART_PIXELS_PER_Z);
+                    quads.push_quad(
+                        [
+                            center - half_width,
+                            center + half_width,
+                            center + half_width + up,
+                            center - half_width + up,
+                        ],
+                        [[u0, v1], [u1, v1], [u1, v0], [u0, v0]],
+                    );
+                }
+                _ => {
+                    let top = match view {
+                        StaticsView::TopDown => bottom + scale_uo_z_to_bevy_units(item_tile.height() as f32),
+                        StaticsView::Upright { .. } => bottom,
+                    } + FLAT_ITEM_LIFT;
+                    quads.push_quad(
+                        [
+                            Vec3::new(x, top, y + 1.0),
+                            Vec3::new(x + 1.0, top, y + 1.0),
+                            Vec3::new(x + 1.0, top, y),
+                            Vec3::new(x, top, y),
+                        ],
+                        flat_art_uvs(&uv_info),
+                    );
+                }
+            }
+        }
+    }
+
+    buffers.map(QuadBuffers::into_mesh)
+}
@@ -0,0 +1,208 @@
+// Land shader status (egui window)
+// - land_base.wgsl is an asset: with the file watcher (native builds, see AssetPlugin in core.rs), saving it reloads
+//   it, and bevy rebuilds the pipelines of the land materials. Each reload is logged and counted here.
+// - When it fails to compile, the land isn't drawn, and the error only goes to the console. So the render world checks
+//   the land pipelines after they're processed (RenderSet::Cleanup), and shares their errors with the main world
+//   (ShaderCompileStatus). This window shows them, and opens by itself when they change. Fixing the file clears them.
+// - Also reachable from the Terrain Shader Controls window.
+//
+
+use crate::core::render::scene::world::land::mesh_material::LAND_SHADER_PATH;
+use crate::prelude::*;
+use bevy::platform::time::Instant;
+use bevy::prelude::*;
+use bevy::render::{
+    Render, RenderApp, RenderSet,
+    render_resource::{CachedPipelineState, PipelineCache, PipelineCacheError, PipelineDescriptor},
+};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use std::sync::{Arc, Mutex};
+
+#[derive(Default)]
+pub struct ShaderCompileState {
+    /// The land shader, checked by the render world. None until loaded.
+    shader_id: Option<AssetId<Shader>>,
+    /// Distinct compile errors of the land pipelines, as of the last check.
+    pub errors: Vec<String>,
+    /// Bumped whenever the errors change.
+    pub generation: u64,
+}
+
+/// Compile errors of the land shader, shared by the main and the render world. Cloning it gives another handle to the
+///  same data.
+#[derive(Resource, Clone, Default)]
+pub struct ShaderCompileStatus(Arc<Mutex<ShaderCompileState>>);
+impl ShaderCompileStatus {
+    /// The errors and their generation.
+    pub fn errors(&self) -> (Vec<String>, u64) {
+        let state = self.0.lock().unwrap();
+        (state.errors.clone(), state.generation)
+    }
+}
+
+/// Keeps the land shader loaded and tells its reloads apart from the other shaders'.
+#[derive(Resource)]
+struct LandShaderHandle(Handle<Shader>);
+
+#[derive(Resource, Default)]
+pub struct ShaderStatusWindow {
+    pub visible: bool,
+    /// Generation of the errors last seen, so that the window opens again only on new ones.
+    seen_generation: u64,
+    pub reloads: u32,
+    pub last_reload: Option<Instant>,
+}
+
+pub struct ShaderStatusUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(ShaderStatusUiPlugin);
+
+impl Plugin for ShaderStatusUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        let status = ShaderCompileStatus::default();
+        app.insert_resource(status.clone())
+            .init_resource::<ShaderStatusWindow>()
+            .add_systems(Startup, sys_load_land_shader)
+            .add_systems(Update, sys_watch_land_shader_reloads)
+            .add_systems(
+                EguiPrimaryContextPass,
+                shader_status_ui_system.run_if(in_state(AppState::InGame)),
+            );
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .insert_resource(status)
+            // The pipeline queue is processed in RenderSet::Render.
+            .add_systems(Render, check_land_pipeline_errors.in_set(RenderSet::Cleanup));
+    }
+}
+
+fn sys_load_land_shader(mut commands: Commands, asset_server: Res<AssetServer>, status_r: Res<ShaderCompileStatus>) {
+    // The same handle as the land materials', which load it by path.
+    let handle: Handle<Shader> = asset_server.load(LAND_SHADER_PATH);
+    status_r.0.lock().unwrap().shader_id = Some(handle.id());
+    commands.insert_resource(LandShaderHandle(handle));
+}
+
+fn sys_watch_land_shader_reloads(
+    mut events: EventReader<AssetEvent<Shader>>,
+    handle_r: Option<Res<LandShaderHandle>>,
+    mut window_r: ResMut<ShaderStatusWindow>,
+) {
+    let Some(handle_r) = handle_r else {
+        return;
+    };
+    let reloaded = events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Modified { id } if *id == handle_r.0.id()));
+    if !reloaded {
+        return;
+    }
+    window_r.reloads += 1;
+    window_r.last_reload = Some(Instant::now());
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::RenderWorldLand,
+        &format!("Reloaded {LAND_SHADER_PATH}."),
+    );
+}
+
+/// Render world: collects the errors of the pipelines using the land shader. The ones of a shader not loaded yet are
+///  retried by bevy, so they don't count.
+fn check_land_pipeline_errors(pipeline_cache_r: Res<PipelineCache>, status_r: Res<ShaderCompileStatus>) {
+    let Some(shader_id) = status_r.0.lock().unwrap().shader_id else {
+        return;
+    };
+    let mut errors: Vec<String> = Vec::new();
+    for pipeline in pipeline_cache_r.pipelines() {
+        let PipelineDescriptor::RenderPipelineDescriptor(descriptor) = &pipeline.descriptor else {
+            continue;
+        };
+        let uses_land_shader = descriptor.vertex.shader.id() == shader_id
+            || descriptor
+                .fragment
+                .as_ref()
+                .is_some_and(|fragment| fragment.shader.id() == shader_id);
+        if !uses_land_shader {
+            continue;
+        }
+        if let CachedPipelineState::Err(
+            e @ (PipelineCacheError::ProcessShaderError(_) | PipelineCacheError::CreateShaderModule(_)),
+        ) = &pipeline.state
+        {
+            let error = e.to_string();
+            if !errors.contains(&error) {
+                errors.push(error);
+            }
+        }
+    }
+    let mut state = status_r.0.lock().unwrap();
+    if state.errors != errors {
+        state.errors = errors;
+        state.generation += 1;
+    }
+}
+
+fn shader_status_ui_system(
+    mut egui_ctx: EguiContexts,
+    status_r: Res<ShaderCompileStatus>,
+    mut window_r: ResMut<ShaderStatusWindow>,
+) {
+    let (errors, generation) = status_r.errors();
+    if generation != window_r.seen_generation {
+        window_r.seen_generation = generation;
+        if !errors.is_empty() {
+            window_r.visible = true;
+        }
+    }
+    if !window_r.visible {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let window = window_r.as_mut();
+    egui::Window::new("Land Shader Status")
+        .default_pos([420.0, 80.0])
+        .default_width(560.0)
+        .resizable(true)
+        .open(&mut window.visible)
+        .show(ctx, |ui| {
+            ui.label(LAND_SHADER_PATH);
+            if cfg!(target_arch = "wasm32") {
+                ui.label("No hot reload in the browser.");
+            } else {
+                ui.label("Saving the file reloads it.");
+            }
+            match window.last_reload {
+                Some(at) => ui.label(format!(
+                    "Reloaded {} times, last {:.0} s ago.",
+                    window.reloads,
+                    at.elapsed().as_secs_f32()
+                )),
+                None => ui.label("Not reloaded yet."),
+            };
+            ui.separator();
+            if errors.is_empty() {
+                ui.colored_label(egui::Color32::from_rgb(80, 170, 80), "Compiled.");
+                return;
+            }
+            ui.colored_label(
+                egui::Color32::from_rgb(230, 70, 70),
+                format!("Failed to compile ({} errors): the land isn't drawn.", errors.len()),
+            );
+            ui.label("The console has the full report, with the lines of the shader.");
+            egui::ScrollArea::vertical().max_height(360.0).show(ui, |ui| {
+                for error in &errors {
+                    // Read-only, but selectable to copy it.
+                    ui.add(
+                        egui::TextEdit::multiline(&mut error.as_str())
+                            .code_editor()
+                            .desired_width(f32::INFINITY),
+                    );
+                }
+            });
+        });
+}
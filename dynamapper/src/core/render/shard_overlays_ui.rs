@@ -0,0 +1,218 @@
+// Shard Overlays (egui window)
+// - Toggled with InputAction::ToggleShardOverlays (F1 by default).
+// - Loads the regions of the shard files (render::overlays::shard_regions): the file defaults to
+//   shard_overlays.regions_file. Shows and hides them by kind, and their labels.
+// - Loads the spawns (render::overlays::shard_spawns), from shard_overlays.spawns_file by default. Shows and hides
+//   them, and filters them by creature.
+// - Hovering the map shows the regions and the spawns under the cursor in a tooltip, even without the window.
+//
+
+use crate::{
+    core::{
+        controls::key_bindings::{ActionInput, InputAction},
+        render::{
+            overlays::{
+                shard_regions::{LoadShardRegionsEvent, ShardRegions, region_kind_color},
+                shard_spawns::{LoadShardSpawnsEvent, ShardSpawns},
+            },
+            scene::{SceneStateData, picking::TilePicker},
+        },
+    },
+    external_data::shard_regions::RegionKind,
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use std::path::PathBuf;
+
+/// Spawns listed at most in the tooltip: their circles can overlap a lot.
+const TOOLTIP_MAX_SPAWNS: usize = 8;
+
+#[derive(Resource, Default)]
+pub struct ShardOverlaysWindow {
+    pub visible: bool,
+    pub regions_file: String,
+    pub spawns_file: String,
+}
+
+pub struct ShardOverlaysUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(ShardOverlaysUiPlugin);
+
+impl Plugin for ShardOverlaysUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<ShardOverlaysWindow>()
+            .add_systems(Update, sys_toggle_shard_overlays_window.run_if(in_state(AppState::InGame)))
+            .add_systems(
+                EguiPrimaryContextPass,
+                (shard_overlays_ui_system, sys_shard_overlays_tooltip).run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_toggle_shard_overlays_window(
+    action_input: ActionInput,
+    settings_r: Res<Settings>,
+    mut window_r: ResMut<ShardOverlaysWindow>,
+) {
+    if action_input.ui_wants_keyboard() || !action_input.just_pressed(InputAction::ToggleShardOverlays) {
+        return;
+    }
+    window_r.visible = !window_r.visible;
+    if window_r.visible && window_r.regions_file.is_empty() {
+        window_r.regions_file = settings_r.shard_overlays.regions_file.clone();
+    }
+    if window_r.visible && window_r.spawns_file.is_empty() {
+        window_r.spawns_file = settings_r.shard_overlays.spawns_file.clone();
+    }
+}
+
+fn shard_overlays_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut window_r: ResMut<ShardOverlaysWindow>,
+    mut regions_r: ResMut<ShardRegions>,
+    mut spawns_r: ResMut<ShardSpawns>,
+    mut load_regions_writer: EventWriter<LoadShardRegionsEvent>,
+    mut load_spawns_writer: EventWriter<LoadShardSpawnsEvent>,
+) {
+    if !window_r.visible {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let window = window_r.as_mut();
+    egui::Window::new("Shard overlays")
+        .default_pos([16.0, 420.0])
+        .default_width(340.0)
+        .collapsible(false)
+        .open(&mut window.visible)
+        .show(ctx, |ui| {
+            ui.heading("Regions");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut window.regions_file)
+                        .hint_text("Sphere map .scp or ServUO Regions.xml")
+                        .desired_width(240.0),
+                );
+                let file = window.regions_file.trim();
+                if ui.add_enabled(!file.is_empty(), egui::Button::new("Load")).clicked() {
+                    load_regions_writer.write(LoadShardRegionsEvent {
+                        file: PathBuf::from(file),
+                    });
+                }
+            });
+            if regions_r.file.as_os_str().is_empty() {
+                ui.label("No regions loaded.");
+            } else {
+                ui.label(format!("{} regions from {}", regions_r.regions.len(), regions_r.file.display()));
+                // Only actual changes: the overlay is rebuilt when ShardRegions changes.
+                ui.horizontal_wrapped(|ui| {
+                    for (index, kind) in RegionKind::ALL.into_iter().enumerate() {
+                        let [r, g, b] = region_kind_color(kind);
+                        let mut shown = regions_r.shown_kinds[index];
+                        let text = egui::RichText::new(format!("{}: {}", kind.as_ref(), regions_r.count(kind)))
+                            .color(egui::Color32::from_rgb(r, g, b));
+                        if ui.checkbox(&mut shown, text).changed() {
+                            regions_r.shown_kinds[index] = shown;
+                        }
+                    }
+                });
+                let mut show_labels = regions_r.show_labels;
+                if ui.checkbox(&mut show_labels, "Names").changed() {
+                    regions_r.show_labels = show_labels;
+                }
+            }
+            ui.separator();
+
+            ui.heading("Spawns");
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut window.spawns_file)
+                        .hint_text("Sphere world save or XmlSpawner .xml")
+                        .desired_width(240.0),
+                );
+                let file = window.spawns_file.trim();
+                if ui.add_enabled(!file.is_empty(), egui::Button::new("Load")).clicked() {
+                    load_spawns_writer.write(LoadShardSpawnsEvent {
+                        file: PathBuf::from(file),
+                    });
+                }
+            });
+            if spawns_r.file.as_os_str().is_empty() {
+                ui.label("No spawns loaded.");
+                return;
+            }
+            ui.label(format!("{} spawns from {}", spawns_r.spawns.len(), spawns_r.file.display()));
+            // Only actual changes: the spawns drawn are picked again when ShardSpawns changes.
+            let mut visible = spawns_r.visible;
+            if ui.checkbox(&mut visible, format!("Shown here: {}", spawns_r.drawn_count())).changed() {
+                spawns_r.visible = visible;
+            }
+            ui.horizontal(|ui| {
+                ui.label("Creature");
+                let mut filter = spawns_r.creature_filter.clone();
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut filter)
+                        .hint_text("e.g. orc")
+                        .desired_width(180.0),
+                );
+                if response.changed() {
+                    spawns_r.creature_filter = filter;
+                }
+            });
+        });
+}
+
+/// The regions and the spawns drawn under the cursor.
+fn sys_shard_overlays_tooltip(
+    mut egui_ctx: EguiContexts,
+    regions_r: Res<ShardRegions>,
+    spawns_r: Res<ShardSpawns>,
+    scene_state_data_r: Res<SceneStateData>,
+    tile_picker: TilePicker,
+) {
+    if regions_r.regions.is_empty() && spawns_r.drawn_count() == 0 {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    if ctx.is_pointer_over_area() {
+        return;
+    }
+    let Some(tile) = tile_picker.cursor_tile() else {
+        return;
+    };
+    let (x, y) = (tile.x as u16, tile.y as u16);
+    let regions: Vec<_> = regions_r.drawn_at(scene_state_data_r.map_id, x, y).collect();
+    let spawns: Vec<_> = spawns_r.drawn_at(x, y).collect();
+    if regions.is_empty() && spawns.is_empty() {
+        return;
+    }
+    egui::Tooltip::always_open(
+        ctx.clone(),
+        egui::LayerId::background(),
+        egui::Id::new("shard_overlays_tooltip"),
+        egui::PopupAnchor::Pointer,
+    )
+    .gap(12.0)
+    .show(|ui| {
+        for region in &regions {
+            let [r, g, b] = region_kind_color(region.kind);
+            ui.label(egui::RichText::new(&region.name).strong().color(egui::Color32::from_rgb(r, g, b)));
+            ui.label(format!("{}: {}", region.kind.as_ref(), region.definition));
+        }
+        if !regions.is_empty() && !spawns.is_empty() {
+            ui.separator();
+        }
+        for spawn in spawns.iter().take(TOOLTIP_MAX_SPAWNS) {
+            ui.label(egui::RichText::new(spawn.creatures.join(", ")).strong());
+            ui.label(format!(
+                "{} at {}, {}: up to {}, radius {}",
+                spawn.name, spawn.x, spawn.y, spawn.max_count, spawn.radius
+            ));
+        }
+        if spawns.len() > TOOLTIP_MAX_SPAWNS {
+            ui.label(format!("...and {} more spawns.", spawns.len() - TOOLTIP_MAX_SPAWNS));
+        }
+    });
+}
@@ -0,0 +1,147 @@
+// Split View (egui window)
+// - InputAction::ToggleSplitView (F9 by default) turns the split view (scene::split_view) on and off, and the window
+//   with it: closing the window turns the split view off.
+// - Picks the map plane shown in the split view (loaded on request), where it looks, and its zoom.
+//
+
+use crate::{
+    core::{
+        controls::key_bindings::{ActionInput, InputAction},
+        maps::manager::LoadMapPlaneEvent,
+        render::scene::{
+            camera::{MAX_ZOOM, MIN_ZOOM},
+            split_view::SplitView,
+            world::WorldGeoData,
+        },
+        uo_files_loader::UoInterfaceSettingsRes,
+    },
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+
+pub struct SplitViewUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(SplitViewUiPlugin);
+
+impl Plugin for SplitViewUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.add_systems(Update, sys_toggle_split_view.run_if(in_state(AppState::InGame)))
+            .add_systems(
+                EguiPrimaryContextPass,
+                split_view_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_toggle_split_view(
+    action_input: ActionInput,
+    mut split_view_r: ResMut<SplitView>,
+    mut load_writer: EventWriter<LoadMapPlaneEvent>,
+) {
+    if action_input.ui_wants_keyboard() || !action_input.just_pressed(InputAction::ToggleSplitView) {
+        return;
+    }
+    split_view_r.enabled = !split_view_r.enabled;
+    if split_view_r.enabled {
+        load_writer.write(LoadMapPlaneEvent {
+            map_id: split_view_r.map_id,
+        });
+    }
+    let msg = if split_view_r.enabled {
+        "Split view on."
+    } else {
+        "Split view off."
+    };
+    logger::one(None, LogSev::Debug, LogAbout::Camera, msg);
+}
+
+fn split_view_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut split_view_r: ResMut<SplitView>,
+    world_geo_data_r: Res<WorldGeoData>,
+    uo_interface_settings_r: Res<UoInterfaceSettingsRes>,
+    mut load_writer: EventWriter<LoadMapPlaneEvent>,
+) {
+    if !split_view_r.enabled {
+        return;
+    }
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    let split_view = split_view_r.as_mut();
+    let map_defs = &uo_interface_settings_r.0.map_defs;
+    let map_label = |map_id: u32| match map_defs.name(map_id) {
+        Some(name) => format!("{map_id} ({name})"),
+        None => map_id.to_string(),
+    };
+    let mut open = true;
+    egui::Window::new("Split view")
+        .default_pos([16.0, 560.0])
+        .resizable(false)
+        .collapsible(false)
+        .open(&mut open)
+        .show(ctx, |ui| {
+            let previous_map_id = split_view.map_id;
+            egui::ComboBox::from_label("Map plane")
+                .selected_text(map_label(split_view.map_id))
+                .show_ui(ui, |ui| {
+                    for map_id in uo_interface_settings_r.0.map_plane_indices() {
+                        ui.selectable_value(&mut split_view.map_id, map_id, map_label(map_id));
+                    }
+                });
+            if split_view.map_id != previous_map_id {
+                load_writer.write(LoadMapPlaneEvent {
+                    map_id: split_view.map_id,
+                });
+            }
+            let map_metadata = world_geo_data_r.maps.get(&split_view.map_id);
+            if map_metadata.is_none() {
+                ui.colored_label(
+                    egui::Color32::from_rgb(230, 70, 70),
+                    format!("Map plane {} isn't loaded.", split_view.map_id),
+                );
+            }
+            ui.separator();
+
+            ui.checkbox(&mut split_view.follow_main_view, "Same place as the main view");
+            let (max_x, max_y) = map_metadata.map_or((f32::MAX, f32::MAX), |metadata| {
+                (metadata.width as f32, metadata.height as f32)
+            });
+            ui.horizontal(|ui| {
+                ui.label("Looking at");
+                let x_changed = ui
+                    .add(
+                        egui::DragValue::new(&mut split_view.focus.x)
+                            .range(0.0..=max_x)
+                            .speed(1.0)
+                            .prefix("x: "),
+                    )
+                    .changed();
+                let y_changed = ui
+                    .add(
+                        egui::DragValue::new(&mut split_view.focus.z)
+                            .range(0.0..=max_y)
+                            .speed(1.0)
+                            .prefix("y: "),
+                    )
+                    .changed();
+                if x_changed || y_changed {
+                    split_view.follow_main_view = false;
+                }
+            });
+            let mut zoom = split_view.zoom;
+            if ui
+                .add(
+                    egui::Slider::new(&mut zoom, MIN_ZOOM..=MAX_ZOOM)
+                        .logarithmic(true)
+                        .text("Zoom"),
+                )
+                .changed()
+            {
+                split_view.set_zoom(zoom);
+            }
+            ui.label("Mouse wheel over the view: zoom. Middle button drag: pan.");
+        });
+    split_view.enabled = open;
+}
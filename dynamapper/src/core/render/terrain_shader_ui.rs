@@ -1,6 +1,6 @@
 // Terrain shader live UI (Bevy 0.16 + egui)
 // - Controls LandEffectsUniform and LandLightingUniform as used by your WGSL
-// - Writes to material assets so Bevy re-uploads uniforms automatically
+// - Writes to the uniforms shared by the land materials (see land::shared_uniforms), uploaded once per change
 // - Shading modes:
 //      0 = Classic 2D (vertex/Gouraud; faithful to original)
 //      1 = Enhanced 2D (fragment; subtle improvements, still faithful)
@@ -8,14 +8,71 @@
 //
 
 use crate::{
-    external_data::shader_presets::UniformState, impl_tracked_plugin, // prelude::*,
+    core::render::day_night::{HOURS_PER_DAY, WorldClock},
+    core::render::render_compare_ui::RenderCompareWindow,
+    core::render::shader_status_ui::{ShaderCompileStatus, ShaderStatusWindow},
+    external_data::shader_presets::{ActiveShaderPreset, ShaderPresetId, ShaderPresetKind, UniformState},
+    impl_tracked_plugin, profile_span, // prelude::*,
     util_lib::tracked_plugin::*,
 };
 
-use bevy::pbr::MeshMaterial3d;
 use bevy::prelude::*;
 use bevy_egui::{EguiContexts, EguiPlugin, EguiPrimaryContextPass, egui};
 use super::scene::world::land::mesh_material::*;
+use super::scene::world::land::shared_uniforms::{LandSharedUniforms, LandUniformFields, LandUniformSyncStats};
+use super::scene::world::land::terrain_overlay::{
+    GRID_LINE_KINDS, TERRAIN_OVERLAY_ALTITUDE, TERRAIN_OVERLAY_MAP_DIFF, TERRAIN_OVERLAY_WALKABILITY, TERRAIN_OVERLAYS,
+};
+
+/// Default duration of the transition to a picked preset.
+const PRESET_TWEEN_DEFAULT_SECS: f32 = 1.5;
+/// Width of the texture transitions across the tile edges (tiles) when turning them on.
+const TEXTURE_TRANSITION_WIDTH_DEFAULT: f32 = 0.5;
+
+// Transition from the uniforms in use to a picked preset, instead of snapping to it.
+struct ActiveTween {
+    from: UniformState,
+    to: UniformState,
+    elapsed_secs: f32,
+}
+
+/// Smooth transition of UniformState towards a preset, advanced by sys_tween_uniforms.
+#[derive(Resource)]
+pub struct UniformTween {
+    /// Transition duration; 0 snaps to the preset.
+    pub duration_secs: f32,
+    active: Option<ActiveTween>,
+}
+impl Default for UniformTween {
+    fn default() -> Self {
+        Self {
+            duration_secs: PRESET_TWEEN_DEFAULT_SECS,
+            active: None,
+        }
+    }
+}
+impl UniformTween {
+    /// Starts a transition from the current uniforms to the given preset.
+    pub fn start(&mut self, current: &UniformState, preset: &LandMaterialUniformsPresets) {
+        let mut to = *current;
+        to.effects = preset.effects;
+        to.lighting = preset.lighting;
+        to.global_lighting = 1.0;
+        self.active = Some(ActiveTween {
+            from: *current,
+            to,
+            elapsed_secs: 0.0,
+        });
+    }
+
+    pub fn cancel(&mut self) {
+        self.active = None;
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active.is_some()
+    }
+}
 
 // Plugin that draws the UI and applies changes to materials.
 pub struct TerrainUiPlugin {
@@ -26,21 +83,28 @@ pub struct TerrainUiPlugin {
 impl Plugin for TerrainUiPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugins(EguiPlugin::default())
+            .init_resource::<UniformTween>()
             // Draw UI in the egui pass
             .add_systems(EguiPrimaryContextPass, terrain_ui_system)
-            // Push "dirty" values into GPU materials
-            .add_systems(Update, push_uniforms_if_dirty);
+            // Advance preset transitions, then push "dirty" values into GPU materials
+            .add_systems(Update, (sys_tween_uniforms, push_uniforms_if_dirty).chain());
     }
 }
 
 // ============================== UI SYSTEM ===============================
 // Renders a window with controls for mode, toggles, intensities, colors,
-// grading, gloom, and presets. Updates UniformState + sets "dirty" when changed.
+// grading, gloom, day/night cycle and presets. Updates UniformState + sets "dirty" when changed.
 
 fn terrain_ui_system(
     mut egui_ctx: EguiContexts,
     mut u: ResMut<UniformState>,
     shader_presets: Res<LandShaderModePresets>,
+    mut clock: ResMut<WorldClock>,
+    mut tween: ResMut<UniformTween>,
+    mut active_preset: ResMut<ActiveShaderPreset>,
+    shader_status_r: Res<ShaderCompileStatus>,
+    mut shader_status_window_r: ResMut<ShaderStatusWindow>,
+    mut render_compare_window_r: ResMut<RenderCompareWindow>,
 ) {
     let ctx = egui_ctx.ctx_mut().expect("No egui context?");
     egui::Window::new("Terrain Shader Controls")
@@ -52,6 +116,20 @@ fn terrain_ui_system(
             ui.label(
                 "Classic aims for original fidelity. Enhanced is subtle. KR is vibrant/painterly.",
             );
+            ui.horizontal(|ui| {
+                let (errors, _) = shader_status_r.errors();
+                if errors.is_empty() {
+                    ui.label("Shader compiled.");
+                } else {
+                    ui.colored_label(egui::Color32::from_rgb(230, 70, 70), "Shader failed to compile.");
+                }
+                if ui.button("Status").clicked() {
+                    shader_status_window_r.visible = true;
+                }
+                if ui.button("Compare with classic 2D").clicked() {
+                    render_compare_window_r.visible = true;
+                }
+            });
             ui.add_space(6.0);
 
             // --------------------- Mode & Normals ---------------------
@@ -84,6 +162,100 @@ fn terrain_ui_system(
                 }
             });
 
+            // ------------------------ Overlays -------------------------
+            ui.horizontal(|ui| {
+                ui.strong("Overlay:");
+                let mut overlay = u.effects.overlay_mode;
+                for (val, label) in TERRAIN_OVERLAYS {
+                    if ui.selectable_label(overlay == val, label).clicked() {
+                        overlay = val;
+                    }
+                }
+                if overlay != u.effects.overlay_mode {
+                    u.effects.overlay_mode = overlay;
+                    u.dirty = true;
+                }
+            });
+            if u.effects.overlay_mode == TERRAIN_OVERLAY_WALKABILITY {
+                ui.label("Land tiles: passable green, impassable red, wet blue. Statics aren't taken into account.");
+            }
+            if u.effects.overlay_mode == TERRAIN_OVERLAY_MAP_DIFF {
+                ui.label("Against the other map version: changed tile orange, changed z cyan, both magenta.");
+            }
+            {
+                let mut changed = false;
+                // Edit a copy, to avoid overlapping borrows of u.
+                let mut params = u.effects.altitude_params;
+                if u.effects.overlay_mode == TERRAIN_OVERLAY_ALTITUDE {
+                    ui.label("Color ramp from the lowest z (blue) to the highest (white):");
+                    changed |= slider_s(ui, "Ramp z min", &mut params.x, -128.0..=127.0);
+                    changed |= slider_s(ui, "Ramp z max", &mut params.y, -128.0..=127.0);
+                }
+                let mut contours_on = params.w >= 0.5;
+                if ui.checkbox(&mut contours_on, "Contour lines").changed() {
+                    params.w = if contours_on { 1.0 } else { 0.0 };
+                    changed = true;
+                }
+                if contours_on {
+                    changed |= slider_s(ui, "Contour interval (z units)", &mut params.z, 1.0..=64.0);
+                }
+                if changed {
+                    u.effects.altitude_params = params;
+                    u.dirty = true;
+                }
+            }
+            ui.horizontal(|ui| {
+                ui.strong("Grid:");
+                let mut grid_lines = u.effects.grid_lines;
+                for (bit, label) in GRID_LINE_KINDS {
+                    let mut on = grid_lines & bit != 0;
+                    if ui.checkbox(&mut on, label).changed() {
+                        grid_lines ^= bit;
+                    }
+                }
+                if grid_lines != u.effects.grid_lines {
+                    u.effects.grid_lines = grid_lines;
+                    u.dirty = true;
+                }
+            });
+            {
+                let mut changed = false;
+                // Edit a copy, to avoid overlapping borrows of u.
+                let mut params = u.effects.water_params;
+                let mut water_on = params.w >= 0.5;
+                if ui.checkbox(&mut water_on, "See-through water and soft shorelines").changed() {
+                    params.w = if water_on { 1.0 } else { 0.0 };
+                    changed = true;
+                }
+                if water_on {
+                    ui.label("Near the shore, the nearest land tile shows through the water.");
+                    changed |= slider_s(ui, "Water transparency", &mut params.x, 0.0..=1.0);
+                    changed |= slider_s(ui, "Opaque from (tiles off the shore)", &mut params.y, 0.1..=2.5);
+                    changed |= slider_s(ui, "Shoreline blend width (tiles)", &mut params.z, 0.0..=2.0);
+                }
+                if changed {
+                    u.effects.water_params = params;
+                    u.dirty = true;
+                }
+            }
+            {
+                let mut changed = false;
+                let mut width = u.effects.transition_width;
+                let mut transitions_on = width > 0.0;
+                if ui.checkbox(&mut transitions_on, "Smooth texture transitions").changed() {
+                    width = if transitions_on { TEXTURE_TRANSITION_WIDTH_DEFAULT } else { 0.0 };
+                    changed = true;
+                }
+                if transitions_on {
+                    ui.label("Near the tile edges, the textures of the neighboring tiles blend in.");
+                    changed |= slider_s(ui, "Transition width (tiles)", &mut width, 0.05..=1.0);
+                }
+                if changed {
+                    u.effects.transition_width = width;
+                    u.dirty = true;
+                }
+            }
+
             ui.separator();
 
             // ------------------------- Toggles -------------------------
@@ -115,6 +287,22 @@ fn terrain_ui_system(
 
                     // Blur is fragment-only
                     changed |= toggle_u32(ui, "Blur (fragment)", &mut u.effects.enable_blur);
+
+                    // Normal maps baked from the textures (see texture_cache::land::normal_map), not in the presets.
+                    let mut params = u.effects.normal_map_params;
+                    let mut normal_maps_on = params.w >= 0.5;
+                    let mut params_changed = false;
+                    if ui.checkbox(&mut normal_maps_on, "Texture normal maps (fragment)").changed() {
+                        params.w = if normal_maps_on { 1.0 } else { 0.0 };
+                        params_changed = true;
+                    }
+                    if normal_maps_on {
+                        params_changed |= slider_s(ui, "Normal map strength", &mut params.x, 0.0..=3.0);
+                    }
+                    if params_changed {
+                        u.effects.normal_map_params = params;
+                        changed = true;
+                    }
                 } else {
                     // For classic path we can optionally display the state but disabled,
                     // but to keep the UI clean we simply hide fragment-only toggles here.
@@ -402,80 +590,113 @@ fn terrain_ui_system(
 
             ui.separator();
 
-            // ------------------------ Presets -------------------------
-            ui.horizontal(|ui| {
-                ui.strong("Presets:");
-                if ui.button("Morning").clicked() {
-                    let preset = match u.effects.shading_mode {
-                        0 => &shader_presets.classic.morning,
-                        1 => &shader_presets.enhanced.morning,
-                        _ => &shader_presets.kr.morning,
-                    };
-                    u.effects = preset.effects;
-                    u.lighting = preset.lighting;
-                    u.global_lighting = 1.0;
-                    u.dirty = true;
+            // -------------------- Day/Night Cycle ---------------------
+            ui.collapsing("Day/Night Cycle", |ui| {
+                // Edit copies, so that the clock is marked as changed only on actual edits.
+                let mut enabled = clock.enabled;
+                if ui.checkbox(&mut enabled, "Drive lighting with the world clock").changed() {
+                    clock.enabled = enabled;
+                    // The clock drives the lighting from now on.
+                    tween.cancel();
                 }
-                if ui.button("Afternoon").clicked() {
-                    let preset = match u.effects.shading_mode {
-                        0 => &shader_presets.classic.afternoon,
-                        1 => &shader_presets.enhanced.afternoon,
-                        _ => &shader_presets.kr.afternoon,
-                    };
-                    u.effects = preset.effects;
-                    u.lighting = preset.lighting;
-                    u.global_lighting = 1.0;
-                    u.dirty = true;
+                let mut paused = clock.paused;
+                if ui.checkbox(&mut paused, "Paused").changed() {
+                    clock.paused = paused;
                 }
-                if ui.button("Night").clicked() {
-                    let preset = match u.effects.shading_mode {
-                        0 => &shader_presets.classic.night,
-                        1 => &shader_presets.enhanced.night,
-                        _ => &shader_presets.kr.night,
-                    };
-                    u.effects = preset.effects;
-                    u.lighting = preset.lighting;
-                    u.global_lighting = 1.0;
-                    u.dirty = true;
+                let mut hour = clock.hour;
+                let hour_label = format!("Time of day ({})", clock.hhmm());
+                if slider_s(ui, &hour_label, &mut hour, 0.0..=HOURS_PER_DAY) {
+                    clock.set_hour(hour);
                 }
-                if ui.button("Cave").clicked() {
-                    let preset = match u.effects.shading_mode {
-                        0 => &shader_presets.classic.cave,
-                        1 => &shader_presets.enhanced.cave,
-                        _ => &shader_presets.kr.cave,
-                    };
-                    u.effects = preset.effects;
-                    u.lighting = preset.lighting;
-                    u.global_lighting = 1.0;
-                    u.dirty = true;
+                let mut day_length_secs = clock.day_length_secs;
+                if slider_s(ui, "Day length (real seconds)", &mut day_length_secs, 10.0..=7200.0) {
+                    clock.day_length_secs = day_length_secs;
+                }
+            });
+
+            ui.separator();
+
+            // ------------------------ Presets -------------------------
+            // Picking a preset stops the day/night cycle, otherwise it would be overwritten right away.
+            // The lighting moves to the preset over the transition duration.
+            ui.horizontal(|ui| {
+                ui.strong("Presets:");
+                for kind in ShaderPresetKind::ALL {
+                    if ui.button(kind.label()).clicked() {
+                        let preset_id = ShaderPresetId {
+                            shading_mode: u.effects.shading_mode,
+                            kind,
+                        };
+                        tween.start(&u, preset_id.preset(&shader_presets));
+                        active_preset.0 = preset_id;
+                        if clock.enabled {
+                            clock.enabled = false;
+                        }
+                    }
                 }
             });
+            let mut duration_secs = tween.duration_secs;
+            if slider_s(ui, "Preset transition (seconds)", &mut duration_secs, 0.0..=10.0) {
+                tween.duration_secs = duration_secs;
+            }
         });
 }
 
-// push_uniforms_if_dirty updates ALL LandCustomMaterial assets.
-// That guarantees that materials not referenced this frame still get the new values
-// (fixes "stale lighting when moving" problem).
-fn push_uniforms_if_dirty(
-    mut mats: ResMut<Assets<LandCustomMaterial>>,
-    _q_mat_handles: Query<&MeshMaterial3d<LandCustomMaterial>>, // kept for parity; unused
+// Moves UniformState along the active preset transition, marking it dirty every frame until it ends.
+// Modes and toggles switch halfway (see LandEffectsUniform::lerp).
+fn sys_tween_uniforms(time: Res<Time>, mut tween: ResMut<UniformTween>, mut u: ResMut<UniformState>) {
+    let duration_secs = tween.duration_secs;
+    let Some(active) = tween.active.as_mut() else {
+        return;
+    };
+    active.elapsed_secs += time.delta_secs();
+    let t = if duration_secs > 0.0 {
+        (active.elapsed_secs / duration_secs).min(1.0)
+    } else {
+        1.0
+    };
+    // Ease in and out.
+    let t = t * t * (3.0 - 2.0 * t);
+
+    let (from, to) = (&active.from, &active.to);
+    // The overlay isn't part of the presets: keep the one in use, even if it's changed during the transition.
+    u.effects = from.effects.lerp(&to.effects, t).with_overlay_of(&u.effects);
+    u.lighting = from.lighting.lerp(&to.lighting, t);
+    u.global_lighting = from.global_lighting + (to.global_lighting - from.global_lighting) * t;
+    u.dirty = true;
+
+    if t >= 1.0 {
+        tween.cancel();
+    }
+}
+
+// push_uniforms_if_dirty diffs UniformState with the state it pushed last, and copies the changed field groups to
+// LandSharedUniforms: the uniform buffers shared by every land material are written once, no material is touched
+// (see land::shared_uniforms).
+pub(crate) fn push_uniforms_if_dirty(
+    mut last_pushed: Local<Option<UniformState>>,
+    mut shared_uniforms_r: ResMut<LandSharedUniforms>,
     mut u: ResMut<UniformState>,
+    mut stats_r: ResMut<LandUniformSyncStats>,
 ) {
     if !u.dirty {
         return;
     }
+    profile_span!("push_land_uniforms");
+    u.dirty = false;
 
-    for (_handle, mat) in mats.iter_mut() {
-        // Overwrite the embedded uniforms used by the material extension.
-        mat.extension.effects_uniform = u.effects;
-        mat.extension.lighting_uniform = u.lighting;
-
-        // NEW: write global lighting into the land uniform so shader sees it
-        // NOTE: adjust the path if your extension uses a different name for the land UBO.
-        mat.extension.scene_uniform.global_lighting = u.global_lighting;
+    let fields = match last_pushed.as_ref() {
+        Some(last) => LandUniformFields::changed_between(last, &u),
+        None => LandUniformFields::ALL,
+    };
+    if fields.is_empty() {
+        stats_r.unchanged_pushes += 1;
+        return;
     }
-
-    u.dirty = false;
+    *last_pushed = Some(*u);
+    stats_r.pushes += 1;
+    stats_r.last_push_fields = fields;
+    shared_uniforms_r.set_from(fields, &u);
 }
 
 // ============================ UI HELPERS =================================
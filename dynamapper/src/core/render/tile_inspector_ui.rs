@@ -0,0 +1,139 @@
+// Tile inspector (debug egui window)
+// - Shows the data of the land tile under the mouse cursor: map coordinates, tile id, z, tiledata entry, texture
+//   and the texture array layer holding it (if resident).
+// - The name is the localized one (LocalizationRes, cliloc of the language in the settings), if there; the tiledata
+//   (20 ASCII chars) one otherwise.
+//
+
+use crate::{
+    core::{
+        render::scene::picking::TilePicker,
+        texture_cache::land::cache::LandTextureCache,
+        uo_files_loader::{LocalizationRes, TileDataRes},
+    },
+    prelude::*,
+};
+use bevy::prelude::*;
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use uocf::geo::{land_texture_2d::LandTextureSize, map::MapCell};
+
+/// Data of the land tile under the cursor, refreshed every frame.
+#[derive(Resource, Default)]
+pub struct InspectedTile {
+    pub map_id: u32,
+    pub x: u32,
+    pub y: u32,
+    pub cell: Option<MapCell>,
+}
+
+pub struct TileInspectorUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(TileInspectorUiPlugin);
+
+impl Plugin for TileInspectorUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<InspectedTile>()
+            .add_systems(
+                Update,
+                sys_update_inspected_tile.run_if(in_state(AppState::InGame)),
+            )
+            .add_systems(
+                EguiPrimaryContextPass,
+                tile_inspector_ui_system.run_if(in_state(AppState::InGame)),
+            );
+    }
+}
+
+fn sys_update_inspected_tile(mut inspected_r: ResMut<InspectedTile>, tile_picker: TilePicker) {
+    *inspected_r = match tile_picker.cursor_tile() {
+        Some(picked) => InspectedTile {
+            map_id: picked.map_id,
+            x: picked.x,
+            y: picked.y,
+            cell: Some(picked.cell),
+        },
+        None => InspectedTile::default(),
+    };
+}
+
+fn tile_inspector_ui_system(
+    mut egui_ctx: EguiContexts,
+    inspected_r: Res<InspectedTile>,
+    tiledata_r: Res<TileDataRes>,
+    localization_r: Res<LocalizationRes>,
+    land_texture_cache_r: Res<LandTextureCache>,
+) {
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    egui::Window::new("Tile Inspector")
+        .default_pos([16.0, 120.0])
+        .default_open(false)
+        .resizable(true)
+        .show(ctx, |ui| {
+            let Some(cell) = inspected_r.cell else {
+                ui.label("No land tile under the cursor.");
+                return;
+            };
+
+            egui::Grid::new("tile_inspector_grid").num_columns(2).show(ui, |ui| {
+                ui.strong("Map coords:");
+                ui.label(format!(
+                    "{}, {} (map {})",
+                    inspected_r.x, inspected_r.y, inspected_r.map_id
+                ));
+                ui.end_row();
+
+                ui.strong("Land tile:");
+                ui.label(format!("{:#06X}", cell.id));
+                ui.end_row();
+
+                ui.strong("Z:");
+                ui.label(format!("{}", cell.z));
+                ui.end_row();
+
+                ui.strong("Name:");
+                ui.label(localization_r.land_tile_name(&tiledata_r.0, cell.id));
+                ui.end_row();
+
+                match tiledata_r.0.land_tile(cell.id) {
+                    Some(land_tile) => {
+                        if localization_r.cliloc.land_tile_name(cell.id).is_some() {
+                            ui.strong("Tiledata name:");
+                            ui.label(land_tile.name_ascii());
+                            ui.end_row();
+                        }
+
+                        ui.strong("Flags:");
+                        ui.label(format!("{:#010X}", land_tile.flags.value()));
+                        ui.end_row();
+
+                        ui.strong("Texmap texture:");
+                        ui.label(format!("{:#06X}", land_tile.texture_id));
+                        ui.end_row();
+                    }
+                    None => {
+                        ui.strong("Tiledata:");
+                        ui.label("(no entry)");
+                        ui.end_row();
+                    }
+                }
+
+                // Textures are cached by land tile id.
+                ui.strong("Texture array layer:");
+                match land_texture_cache_r.resident_texture_size_layer(cell.id) {
+                    Some((size, layer)) => {
+                        let size_str = match size {
+                            LandTextureSize::Small => "small",
+                            LandTextureSize::Big => "big",
+                        };
+                        ui.label(format!("{layer} ({size_str})"));
+                    }
+                    None => {
+                        ui.label("(not resident)");
+                    }
+                }
+                ui.end_row();
+            });
+        });
+}
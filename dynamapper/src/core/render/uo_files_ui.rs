@@ -0,0 +1,188 @@
+// UO Files (egui window)
+// - Shows the UO client folder (uo_files.folder) and lets the user pick another one with the native folder dialog
+//   (FolderDialog, also used by the loading error dialog, see loading_ui), or type it.
+// - The folder is checked with UoInstallation::scan: it must hold the files needed to start (see
+//   UoInstallation::missing_files). Saving writes it to settings.toml; the files are loaded from it at the next start.
+// - In the browser (wasm32) there are no folders: the dialog picks the UO files themselves, which are copied in memory
+//   to the uocf vfs under BROWSER_UO_FOLDER, and that's the folder picked.
+//
+
+use std::path::{Path, PathBuf};
+
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on, poll_once};
+use bevy_egui::{EguiContexts, EguiPrimaryContextPass, egui};
+use uocf::installation::UoInstallation;
+
+const FOLDER_FIELD_WIDTH: f32 = 320.0;
+pub const CHECK_OK_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 200, 90);
+pub const CHECK_ERROR_COLOR: egui::Color32 = egui::Color32::from_rgb(230, 70, 70);
+
+/// Virtual folder of the UO files picked in the browser, in the uocf vfs.
+pub const BROWSER_UO_FOLDER: &str = "/uo";
+/// Label of the button opening the FolderDialog.
+pub const BROWSE_BUTTON_LABEL: &str =
+    if cfg!(target_arch = "wasm32") { "Pick the UO files..." } else { "Browse..." };
+
+/// Native folder dialog. It runs in background, so that the app keeps drawing frames while it's open.
+#[derive(Default)]
+pub struct FolderDialog(Option<Task<Option<PathBuf>>>);
+impl FolderDialog {
+    /// Opens the dialog, starting from start_folder if it exists. Does nothing if it's already open.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn open(&mut self, title: &str, start_folder: &str) {
+        if self.is_open() {
+            return;
+        }
+        let mut dialog = rfd::AsyncFileDialog::new().set_title(title);
+        if Path::new(start_folder).is_dir() {
+            dialog = dialog.set_directory(start_folder);
+        }
+        let task = AsyncComputeTaskPool::get()
+            .spawn(async move { dialog.pick_folder().await.map(|handle| handle.path().to_owned()) });
+        self.0 = Some(task);
+    }
+
+    /// Opens the browser file picker: the picked files replace the ones in BROWSER_UO_FOLDER. Does nothing if it's
+    ///  already open.
+    #[cfg(target_arch = "wasm32")]
+    pub fn open(&mut self, title: &str, _start_folder: &str) {
+        if self.is_open() {
+            return;
+        }
+        let dialog = rfd::AsyncFileDialog::new().set_title(title);
+        let task = AsyncComputeTaskPool::get().spawn(async move {
+            let handles = dialog.pick_files().await?;
+            let folder = Path::new(BROWSER_UO_FOLDER);
+            uocf::vfs::remove_folder(folder);
+            for handle in handles {
+                uocf::vfs::add_file(folder.join(handle.file_name()), handle.read().await);
+            }
+            Some(folder.to_owned())
+        });
+        self.0 = Some(task);
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.0.is_some()
+    }
+
+    /// The folder picked, once the dialog is closed. None while it's open, or if it was cancelled.
+    pub fn poll(&mut self) -> Option<PathBuf> {
+        let picked = block_on(poll_once(self.0.as_mut()?))?;
+        self.0 = None;
+        picked
+    }
+}
+
+/// Checks that a folder holds the UO files needed to start. Returns a summary of what was found, or what's wrong.
+pub fn check_uo_folder(folder: &str) -> Result<String, String> {
+    let installation = UoInstallation::scan(Path::new(folder)).map_err(|e| e.to_string())?;
+    let missing_files = installation.missing_files();
+    if !missing_files.is_empty() {
+        return Err(format!("Missing: {}.", missing_files.join(", ")));
+    }
+    let map_planes: Vec<&u32> = installation.map_planes.keys().collect();
+    Ok(format!("UO files found, map planes {map_planes:?}."))
+}
+
+/// Shows the result of check_uo_folder.
+pub fn check_result_label(ui: &mut egui::Ui, check: &Result<String, String>) {
+    match check {
+        Ok(summary) => ui.colored_label(CHECK_OK_COLOR, summary),
+        Err(what) => ui.colored_label(CHECK_ERROR_COLOR, what),
+    };
+}
+
+#[derive(Resource, Default)]
+pub struct UoFilesWindow {
+    /// Folder being typed or picked. None until the window is first drawn, then it's the one in the settings.
+    folder: Option<String>,
+    dialog: FolderDialog,
+    /// Result of check_uo_folder for folder, if it was checked.
+    check: Option<Result<String, String>>,
+}
+
+pub struct UoFilesUiPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(UoFilesUiPlugin);
+
+impl Plugin for UoFilesUiPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<UoFilesWindow>().add_systems(
+            EguiPrimaryContextPass,
+            uo_files_ui_system.run_if(in_state(AppState::InGame)),
+        );
+    }
+}
+
+fn uo_files_ui_system(
+    mut egui_ctx: EguiContexts,
+    mut window_r: ResMut<UoFilesWindow>,
+    mut settings_r: ResMut<Settings>,
+) {
+    let UoFilesWindow { folder, dialog, check } = window_r.as_mut();
+    let folder = folder.get_or_insert_with(|| settings_r.uo_files.folder.clone());
+    if let Some(picked) = dialog.poll() {
+        *folder = picked.to_string_lossy().into_owned();
+        *check = Some(check_uo_folder(folder));
+    }
+
+    let ctx = egui_ctx.ctx_mut().expect("No egui context?");
+    egui::Window::new("UO Files")
+        .default_open(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.label(format!("Loaded from: {}", settings_r.uo_files.source_label()));
+            ui.horizontal(|ui| {
+                let response = ui.add(
+                    egui::TextEdit::singleline(folder)
+                        .hint_text("Folder with the UO client files")
+                        .desired_width(FOLDER_FIELD_WIDTH),
+                );
+                if response.changed() {
+                    *check = None;
+                }
+                if ui
+                    .add_enabled(!dialog.is_open(), egui::Button::new(BROWSE_BUTTON_LABEL))
+                    .clicked()
+                {
+                    dialog.open("UO client folder", folder);
+                }
+            });
+            if let Some(check) = check {
+                check_result_label(ui, check);
+            }
+
+            let folder_trimmed = folder.trim();
+            ui.horizontal(|ui| {
+                if ui.button("Check").clicked() {
+                    *check = Some(check_uo_folder(folder_trimmed));
+                }
+                let can_save =
+                    check.as_ref().is_some_and(Result::is_ok) && folder_trimmed != settings_r.uo_files.folder;
+                if ui.add_enabled(can_save, egui::Button::new("Save")).clicked() {
+                    // Kept in sync first, so that the hot reload of the written file doesn't see any change.
+                    settings_r.uo_files.folder = folder_trimmed.to_owned();
+                    match save_uo_folder(folder_trimmed) {
+                        Ok(()) => logger::one(
+                            None,
+                            LogSev::Info,
+                            LogAbout::General,
+                            &format!("Saved the UO folder {folder_trimmed}: it's used at the next start."),
+                        ),
+                        Err(e) => logger::one(
+                            None,
+                            LogSev::Error,
+                            LogAbout::General,
+                            &format!("Can't save the UO folder to the settings file: {e}"),
+                        ),
+                    }
+                }
+            });
+            ui.label("The files of a new folder are loaded at the next start.");
+        });
+}
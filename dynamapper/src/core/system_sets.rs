@@ -15,6 +15,7 @@ pub enum SceneRenderLandSysSet {
     ListenSyncRequests,
     SyncLandChunks,
     RenderLandChunks,
+    RenderStatics,
 }
 
 #[derive(strum_macros::AsRefStr, SystemSet, Debug, Clone, Hash, PartialEq, Eq)]
@@ -1,3 +1,4 @@
+pub mod hues;
 pub mod land;
 
 use bevy::prelude::*;
@@ -13,7 +14,10 @@ impl Plugin for TextureCachePlugin
     /// Allocate GPU texture array and Tile Caches.
     fn build(&self, app: &mut App) {
         log_plugin_build(self);
-        app.add_plugins(land::LandTextureCachePlugin { registered_by: "TextureCachePlugin" });
+        app.add_plugins((
+            land::LandTextureCachePlugin { registered_by: "TextureCachePlugin" },
+            hues::HuePaletteTexturePlugin { registered_by: "TextureCachePlugin" },
+        ));
     }
 }
 
@@ -0,0 +1,71 @@
+use crate::core::uo_files_loader::HuesRes;
+use crate::prelude::*;
+use bevy::{
+    asset::RenderAssetUsages,
+    image::ImageSampler,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureDimension, TextureFormat},
+};
+use uocf::hues::{HueEntry, Hues};
+
+/// Hue palette texture: one row for each hue, each row holding the 32 colors of the hue ramp.
+/// Row index = hue id (row 0 is left blank, since hue 0 means "not hued").
+/// Shaders read it with textureLoad, so no filtering/sampler is involved.
+#[derive(Resource)]
+pub struct HuePaletteTexture {
+    pub image_handle: Handle<Image>,
+    pub hue_count: u32,
+}
+
+pub struct HuePaletteTexturePlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(HuePaletteTexturePlugin);
+
+impl Plugin for HuePaletteTexturePlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        // Needs the hues from the UO files.
+        app.add_systems(OnEnter(LoadingPhase::Textures), sys_setup_hue_palette_texture);
+    }
+}
+
+const HUE_PALETTE_BYTES_PER_PIXEL: usize = 4; // RGBA8888
+
+/// Builds the RGBA8 pixel data of the palette texture.
+fn build_hue_palette_data(hues: &Hues) -> Vec<u8> {
+    let row_size = HueEntry::COLORS_QTY * HUE_PALETTE_BYTES_PER_PIXEL;
+    let mut data = vec![0u8; row_size * (hues.len() + 1)];
+    for hue in hues.iter() {
+        let row_start = hue.hue_id as usize * row_size;
+        data[row_start..row_start + row_size].copy_from_slice(&hue.colors_rgba8888());
+    }
+    data
+}
+
+pub fn sys_setup_hue_palette_texture(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    hues_r: Res<HuesRes>,
+) {
+    log_system_add_onenter::<HuePaletteTexturePlugin>(LoadingPhase::Textures.as_ref(), fname!());
+
+    let hue_count = hues_r.0.len() as u32;
+    let mut image = Image::new(
+        Extent3d {
+            width: HueEntry::COLORS_QTY as u32,
+            height: hue_count + 1,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        build_hue_palette_data(&hues_r.0),
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+    image.sampler = ImageSampler::nearest();
+
+    commands.insert_resource(HuePaletteTexture {
+        image_handle: images.add(image),
+        hue_count,
+    });
+}
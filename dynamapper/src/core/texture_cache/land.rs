@@ -1,9 +1,15 @@
 pub mod cache;
+pub mod disk_cache;
+pub mod gpu_upload;
+pub mod mipmaps;
+pub mod normal_map;
+pub mod prewarm;
 pub mod texture_array;
 
 use crate::prelude::*;
 use crate::core::system_sets::*;
 use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
 use uocf::geo::land_texture_2d::LandTextureSize;
 
 pub struct LandTextureCachePlugin {
@@ -15,19 +21,77 @@ impl Plugin for LandTextureCachePlugin {
     /// Allocate GPU texture array for terrain tiles and TileCache.
     fn build(&self, app: &mut App) {
         log_plugin_build(self);
-        app.add_systems(
+        app.add_plugins((
+            gpu_upload::LandTextureGpuUploadPlugin {
+                registered_by: "LandTextureCachePlugin",
+            },
+            prewarm::LandTexturePrewarmPlugin {
+                registered_by: "LandTextureCachePlugin",
+            },
+        ))
+        .add_systems(
             Startup,
             sys_setup_terrain_cache
                 .in_set(StartupSysSet::SetupSceneStage1)
                 .after(StartupSysSet::LoadStartupUOFiles)
-        );
+        )
+        .add_systems(Update, sys_enforce_land_texture_budget.run_if(in_state(AppState::InGame)));
     }
 }
 
-pub fn sys_setup_terrain_cache(mut cmd: Commands, mut images: ResMut<Assets<Image>>) {
+pub fn sys_setup_terrain_cache(mut cmd: Commands, mut images: ResMut<Assets<Image>>, settings_r: Res<Settings>) {
     log_system_add_startup::<LandTextureCachePlugin>(StartupSysSet::SetupSceneStage1, fname!());
 
-    let handle_small = texture_array::create_gpu_texture_array("land_small_texture_cache", &mut images, LandTextureSize::Small);
-    let handle_big = texture_array::create_gpu_texture_array("land_big_texture_cache", &mut images, LandTextureSize::Big);
-    cmd.insert_resource(cache::LandTextureCache::new(handle_small, handle_big));
+    let small = create_array_handles(&mut images, &settings_r.render, LandTextureSize::Small);
+    let big = create_array_handles(&mut images, &settings_r.render, LandTextureSize::Big);
+    cmd.insert_resource(cache::LandTextureCache::new(small, big));
+}
+
+/// The texture array of a size, and the one of its normal maps.
+fn create_array_handles(
+    images: &mut Assets<Image>,
+    render_settings: &SectRender,
+    size: LandTextureSize,
+) -> cache::LandTextureArrayHandles {
+    let (texture_label, normal_map_label) = match size {
+        LandTextureSize::Small => ("land_small_texture_cache", "land_small_normal_map_cache"),
+        LandTextureSize::Big => ("land_big_texture_cache", "land_big_normal_map_cache"),
+    };
+    let filtering = render_settings.land_texture_filtering;
+    let anisotropy = render_settings.land_texture_anisotropy;
+    let (width, height) = size.dimensions();
+    let create = |images: &mut Assets<Image>, label, format| {
+        texture_array::create_gpu_texture_array(label, images, size, format, filtering, anisotropy)
+    };
+    cache::LandTextureArrayHandles {
+        texture: create(images, texture_label, TextureFormat::Rgba8UnormSrgb),
+        normal_map: create(images, normal_map_label, TextureFormat::Rgba8Unorm),
+        mip_level_count: mipmaps::mip_level_count(filtering, width, height),
+    }
+}
+
+/// With a memory budget set (render.land_texture_budget_mb), evicts the least recently used land textures when the
+/// resident ones take more than that.
+fn sys_enforce_land_texture_budget(settings_r: Res<Settings>, mut cache_r: ResMut<cache::LandTextureCache>) {
+    let budget_mb = settings_r.render.land_texture_budget_mb;
+    if budget_mb == 0 {
+        return;
+    }
+    let budget_bytes = budget_mb as u64 * 1024 * 1024;
+    // Checked without taking the resource as mutated, which would happen on every frame.
+    if cache_r.used_bytes() <= budget_bytes {
+        return;
+    }
+    let evicted = cache_r.evict_to_budget(budget_bytes, cache::BUDGET_EVICT_MIN_AGE);
+    if evicted > 0 {
+        logger::one(
+            None,
+            LogSev::Debug,
+            LogAbout::RenderWorldLand,
+            &format!(
+                "Land texture cache over budget ({budget_mb} MB): evicted {evicted} textures, {:.1} MB in use.",
+                cache_r.used_bytes() as f64 / (1024.0 * 1024.0)
+            ),
+        );
+    }
 }
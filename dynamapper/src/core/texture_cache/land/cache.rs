@@ -1,19 +1,44 @@
 //! GPU texture array LRU cache supporting two texture sizes
 //! Each texture_id can be either small or big and is mapped accordingly
+//! Each texture array has a parallel one with the normal maps of its textures (see normal_map), at the same layers.
 
 #![allow(dead_code)]
 
-use super::texture_array;
+use super::{
+    gpu_upload::LandTextureLayerUpload,
+    mipmaps::{self, MipContent},
+    normal_map, texture_array,
+};
+use crate::profile_span;
 use bevy::prelude::*;
 use std::{
     collections::{HashMap, HashSet, VecDeque},
     sync::Arc,
-    time::{Duration, Instant},
+    time::Duration,
 };
+// Not std::time::Instant: it panics in the browser.
+use bevy::platform::time::Instant;
+use uocf::art::Art;
 use uocf::geo::land_texture_2d::{LandTextureSize, TexMap2D};
 
 const CACHE_EVICT_AFTER: Duration = Duration::from_secs(300);
+/// Over the memory budget, textures are evicted only if unused for this long, since chunks still on screen may use
+/// the ones touched recently.
+pub const BUDGET_EVICT_MIN_AGE: Duration = Duration::from_secs(30);
 const TEXTURE_BYTES_PER_PIXEL: usize = 4; // RGBA8888
+/// Each layer is taken in two arrays: the texture and its normal map.
+const ARRAYS_PER_LAYER: usize = 2;
+
+fn layer_byte_size(texture_size: LandTextureSize, mip_level_count: u32) -> usize {
+    let (width, height) = texture_size.dimensions();
+    let texels: u32 = (0..mip_level_count)
+        .map(|level| {
+            let (w, h) = mipmaps::mip_level_size(width, height, level);
+            w * h
+        })
+        .sum();
+    texels as usize * TEXTURE_BYTES_PER_PIXEL * ARRAYS_PER_LAYER
+}
 
 #[derive(Clone, Copy, Debug)]
 pub struct LandTextureEntry {
@@ -21,20 +46,74 @@ pub struct LandTextureEntry {
     pub last_touch: Instant,
 }
 
+/// Texture array of a size, and the array of the normal maps of its textures.
+pub struct LandTextureArrayHandles {
+    pub texture: Handle<Image>,
+    pub normal_map: Handle<Image>,
+    /// Of both arrays (see mipmaps).
+    pub mip_level_count: u32,
+}
+
 /// A single TextureArray data (we use one for each size)
 pub struct LandTextureArrayWrapper {
     pub image_handle: Handle<Image>,
+    /// Normal maps of the textures, at the same layers.
+    pub normal_map_handle: Handle<Image>,
+    texture_size: LandTextureSize,
+    mip_level_count: u32,
+    max_layers: u32,
     free_layers: Vec<u32>,
     lru: VecDeque<u16>, // texture_id queue
 }
 impl LandTextureArrayWrapper {
-    fn new(image_handle: Handle<Image>, max_layers: u32) -> Self {
+    fn new(handles: LandTextureArrayHandles, texture_size: LandTextureSize, max_layers: u32) -> Self {
         Self {
-            image_handle,
+            image_handle: handles.texture,
+            normal_map_handle: handles.normal_map,
+            texture_size,
+            mip_level_count: handles.mip_level_count,
+            max_layers,
             free_layers: (0..max_layers).rev().collect(),
             lru: VecDeque::default(),
         }
     }
+
+    /// Layers holding a texture.
+    pub fn used_layers(&self) -> u32 {
+        self.max_layers - self.free_layers.len() as u32
+    }
+
+    pub fn max_layers(&self) -> u32 {
+        self.max_layers
+    }
+
+    /// GPU memory of a layer: the texture and its normal map, with their mipmaps.
+    fn layer_bytes(&self) -> u64 {
+        layer_byte_size(self.texture_size, self.mip_level_count) as u64
+    }
+
+    /// Approximate GPU memory taken by the layers holding a texture, normal maps included.
+    pub fn used_bytes(&self) -> u64 {
+        self.used_layers() as u64 * self.layer_bytes()
+    }
+
+    /// GPU memory of the whole array, allocated upfront.
+    pub fn allocated_bytes(&self) -> u64 {
+        self.max_layers as u64 * self.layer_bytes()
+    }
+}
+
+/// Counters since startup, for the diagnostics.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LandTextureCacheStats {
+    /// Requested textures already resident.
+    pub hits: u64,
+    /// Textures uploaded to a layer.
+    pub uploads: u64,
+    /// Textures evicted to reuse their layer, because the array was full.
+    pub evicted_array_full: u64,
+    /// Textures evicted to get back under the memory budget.
+    pub evicted_over_budget: u64,
 }
 
 #[derive(Resource)]
@@ -42,6 +121,9 @@ pub struct LandTextureCache {
     pub small: LandTextureArrayWrapper,
     pub big: LandTextureArrayWrapper,
     entry_by_id: HashMap<u16, (LandTextureSize, LandTextureEntry)>,
+    pub stats: LandTextureCacheStats,
+    /// Layers filled since the last extraction, written to the GPU textures by the render world (see gpu_upload).
+    pending_gpu_uploads: Vec<LandTextureLayerUpload>,
 }
 
 struct PreparedTextureUpload {
@@ -52,111 +134,121 @@ struct PreparedTextureUpload {
 }
 
 impl LandTextureCache {
-    pub fn new(small_tex_image_handle: Handle<Image>, big_tex_image_handle: Handle<Image>) -> Self {
+    pub fn new(small_handles: LandTextureArrayHandles, big_handles: LandTextureArrayHandles) -> Self {
         Self {
             small: LandTextureArrayWrapper::new(
-                small_tex_image_handle,
+                small_handles,
+                LandTextureSize::Small,
                 texture_array::TEXARRAY_SMALL_MAX_TILE_LAYERS,
             ),
             big: LandTextureArrayWrapper::new(
-                big_tex_image_handle,
+                big_handles,
+                LandTextureSize::Big,
                 texture_array::TEXARRAY_BIG_MAX_TILE_LAYERS,
             ),
             entry_by_id: HashMap::default(),
+            stats: LandTextureCacheStats::default(),
+            pending_gpu_uploads: Vec::new(),
+        }
+    }
+
+    pub fn has_pending_gpu_uploads(&self) -> bool {
+        !self.pending_gpu_uploads.is_empty()
+    }
+
+    /// Takes the layer uploads queued since the last call, in the order they were queued.
+    pub fn take_pending_gpu_uploads(&mut self) -> Vec<LandTextureLayerUpload> {
+        std::mem::take(&mut self.pending_gpu_uploads)
+    }
+
+    /// Approximate GPU memory taken by the resident textures and their normal maps, in both sizes.
+    pub fn used_bytes(&self) -> u64 {
+        self.small.used_bytes() + self.big.used_bytes()
+    }
+
+    /// Evicts the least recently used textures (only those unused for at least min_age) until the resident ones fit
+    /// in the given memory budget. Returns how many were evicted.
+    pub fn evict_to_budget(&mut self, budget_bytes: u64, min_age: Duration) -> usize {
+        let mut used_bytes = self.used_bytes();
+        if used_bytes <= budget_bytes {
+            return 0;
+        }
+        profile_span!("evict_land_textures");
+        let now = Instant::now();
+        let mut candidates: Vec<(Instant, u16, LandTextureSize)> = self
+            .entry_by_id
+            .iter()
+            .filter(|(_, (_, entry))| now - entry.last_touch >= min_age)
+            .map(|(&texture_id, &(size, entry))| (entry.last_touch, texture_id, size))
+            .collect();
+        candidates.sort_unstable();
+
+        let mut evicted = HashSet::new();
+        for (_, texture_id, size) in candidates {
+            if used_bytes <= budget_bytes {
+                break;
+            }
+            let (_, entry) = self.entry_by_id.remove(&texture_id).unwrap();
+            self.free_layer_for_entry(size, entry);
+            used_bytes -= match size {
+                LandTextureSize::Small => self.small.layer_bytes(),
+                LandTextureSize::Big => self.big.layer_bytes(),
+            };
+            evicted.insert(texture_id);
+        }
+        if !evicted.is_empty() {
+            self.small.lru.retain(|texture_id| !evicted.contains(texture_id));
+            self.big.lru.retain(|texture_id| !evicted.contains(texture_id));
         }
+        self.stats.evicted_over_budget += evicted.len() as u64;
+        evicted.len()
     }
 
-    /// Preloads a set of textures into the cache, performing one batched GPU upload.
+    /// Preloads a set of textures into the cache. Their layers are uploaded to the GPU together, during the next
+    /// render world extraction.
     pub fn preload_textures(
         &mut self,
         images_resmut: &mut ResMut<Assets<Image>>,
         texmap_2d: Arc<TexMap2D>,
+        art: Arc<Art>,
         texture_ids: &HashSet<u16>,
     ) {
-        let mut pending_uploads = Vec::new();
-
-        // --- Stage 1: Collection --- 
-        // For each texture, prepare it for upload without actually modifying the GPU asset.
+        profile_span!("preload_land_textures", textures = texture_ids.len());
         for &texture_id in texture_ids {
-            if let Some(prepared) = self.prepare_texture_residency(texture_id, images_resmut, &texmap_2d) {
-                pending_uploads.push(prepared);
-            }
-        }
-
-        if pending_uploads.is_empty() {
-            return;
-        }
-
-        // --- Stage 2: Batched Upload --- 
-        // Separate uploads by texture array size to avoid mutable borrow conflicts.
-        let mut small_uploads = Vec::new();
-        let mut big_uploads = Vec::new();
-        for upload in pending_uploads {
-            match upload.size {
-                LandTextureSize::Small => small_uploads.push(upload),
-                LandTextureSize::Big => big_uploads.push(upload),
-            }
-        }
-
-        if !small_uploads.is_empty() {
-            if let Some(data) = &mut images_resmut.get_mut(&self.small.image_handle).unwrap().data {
-                for upload in &small_uploads {
-                    let (width, height) = upload.size.dimensions();
-                    let layer_byte_size = (width * height) as usize * TEXTURE_BYTES_PER_PIXEL;
-                    let offset = upload.layer as usize * layer_byte_size;
-                    data[offset..offset + layer_byte_size].copy_from_slice(&upload.bytes);
-                }
+            if let Some(prepared) = self.prepare_texture_residency(texture_id, images_resmut, &texmap_2d, &art) {
+                self.queue_gpu_upload(prepared);
             }
         }
+    }
 
-        if !big_uploads.is_empty() {
-            if let Some(data) = &mut images_resmut.get_mut(&self.big.image_handle).unwrap().data {
-                for upload in &big_uploads {
-                    let (width, height) = upload.size.dimensions();
-                    let layer_byte_size = (width * height) as usize * TEXTURE_BYTES_PER_PIXEL;
-                    let offset = upload.layer as usize * layer_byte_size;
-                    data[offset..offset + layer_byte_size].copy_from_slice(&upload.bytes);
-                }
-            }
-        }
-        
-        // --- Stage 3: Bookkeeping ---
-        for upload in small_uploads.iter().chain(big_uploads.iter()) {
-            self.update_bookkeeping(upload.texture_id, upload.size, upload.layer);
-        }
+    /// Size and layer of a texture, only if it's already resident. Doesn't count as a use for the LRU.
+    pub fn resident_texture_size_layer(&self, texture_id: u16) -> Option<(LandTextureSize, u32)> {
+        self.entry_by_id
+            .get(&texture_id)
+            .map(|(size, entry)| (*size, entry.layer))
     }
 
-    /// Gets the layer for a single texture. If not resident, it will be loaded, causing an immediate GPU upload.
+    /// Gets the layer for a single texture. If not resident, it will be loaded and its layer queued for upload.
     pub fn get_texture_size_layer(
         &mut self,
         images_resmut: &mut ResMut<Assets<Image>>,
         texmap_2d: Arc<TexMap2D>,
+        art: Arc<Art>,
         texture_id: u16,
     ) -> (LandTextureSize, u32) {
         // If texture is already resident, just return its info.
         if let Some(entry) = self.entry_by_id.get_mut(&texture_id) {
             entry.1.last_touch = Instant::now();
+            self.stats.hits += 1;
             return (entry.0, entry.1.layer);
         }
 
         // Otherwise, prepare it for upload.
-        let prepared = self.prepare_texture_residency(texture_id, images_resmut, &texmap_2d).unwrap();
+        let prepared = self.prepare_texture_residency(texture_id, images_resmut, &texmap_2d, &art).unwrap();
 
-        // Perform the single upload.
-        let array_handle = match prepared.size {
-            LandTextureSize::Small => &self.small.image_handle,
-            LandTextureSize::Big => &self.big.image_handle,
-        };
-        if let Some(data) = &mut images_resmut.get_mut(array_handle).unwrap().data {
-            let (width, height) = prepared.size.dimensions();
-            let layer_byte_size = (width * height) as usize * TEXTURE_BYTES_PER_PIXEL;
-            let offset = prepared.layer as usize * layer_byte_size;
-            data[offset..offset + layer_byte_size].copy_from_slice(&prepared.bytes);
-        }
-
-        // Update bookkeeping and return.
-        self.update_bookkeeping(prepared.texture_id, prepared.size, prepared.layer);
-        (prepared.size, prepared.layer)
+        let size_layer = (prepared.size, prepared.layer);
+        self.queue_gpu_upload(prepared);
+        size_layer
     }
 
     /// Checks if a texture is resident. If not, allocates a layer and loads its data,
@@ -166,10 +258,12 @@ fn prepare_texture_residency(
         texture_id: u16,
         images_resmut: &mut ResMut<Assets<Image>>,
         texmap_2d: &Arc<TexMap2D>,
+        art: &Arc<Art>,
     ) -> Option<PreparedTextureUpload> {
         // If resident, touch timestamp and return None as no upload is needed.
         if let Some(entry) = self.entry_by_id.get_mut(&texture_id) {
             entry.1.last_touch = Instant::now();
+            self.stats.hits += 1;
             return None;
         }
 
@@ -177,7 +271,7 @@ fn prepare_texture_residency(
 
         // 1. Get the new texture data and metadata.
         let (texture_size, tile_handle) =
-            texture_array::get_texmap_image(texture_id, images_resmut, texmap_2d);
+            texture_array::get_texmap_image(texture_id, images_resmut, texmap_2d, art);
 
         // 2. Allocate a layer, evicting an old one if necessary.
         let layer = self.allocate_layer(texture_size);
@@ -199,6 +293,43 @@ fn prepare_texture_residency(
         })
     }
 
+    /// Queues the upload of a prepared texture and of its normal map (with their mipmaps, if any) to their layer, and
+    /// marks it as resident.
+    fn queue_gpu_upload(&mut self, prepared: PreparedTextureUpload) {
+        self.update_bookkeeping(prepared.texture_id, prepared.size, prepared.layer);
+        let array = match prepared.size {
+            LandTextureSize::Small => &self.small,
+            LandTextureSize::Big => &self.big,
+        };
+        let (width, height) = prepared.size.dimensions();
+        let normal_map_bytes = {
+            profile_span!("bake_land_normal_map");
+            normal_map::normal_map_from_texture(&prepared.bytes, width, height)
+        };
+        let (mips, normal_map_mips) = {
+            profile_span!("build_land_texture_mipmaps");
+            let levels = array.mip_level_count;
+            (
+                mipmaps::mip_chain(&prepared.bytes, width, height, levels, MipContent::Color),
+                mipmaps::mip_chain(&normal_map_bytes, width, height, levels, MipContent::NormalMap),
+            )
+        };
+        self.pending_gpu_uploads.push(LandTextureLayerUpload {
+            image_id: array.image_handle.id(),
+            layer: prepared.layer,
+            size: prepared.size,
+            bytes: prepared.bytes,
+            mips,
+        });
+        self.pending_gpu_uploads.push(LandTextureLayerUpload {
+            image_id: array.normal_map_handle.id(),
+            layer: prepared.layer,
+            size: prepared.size,
+            bytes: normal_map_bytes,
+            mips: normal_map_mips,
+        });
+    }
+
     /// Allocates a layer for a new texture, handling LRU eviction if the array is full.
     fn allocate_layer(&mut self, texture_size: LandTextureSize) -> u32 {
         let array = match texture_size {
@@ -214,15 +345,17 @@ fn allocate_layer(&mut self, texture_size: LandTextureSize) -> u32 {
                     .lru
                     .pop_front()
                     .expect("LRU should not be empty at this stage");
+                // Ids not resident anymore (evicted over budget) are dropped from the queue.
                 if let Some(still) = self.entry_by_id.get(&oldest) {
                     if Instant::now() - still.1.last_touch >= CACHE_EVICT_AFTER {
                         break oldest;
                     }
+                    array.lru.push_back(oldest);
                 }
-                array.lru.push_back(oldest);
             };
             let victim_entry: (LandTextureSize, LandTextureEntry) =
                 self.entry_by_id.remove(&victim_id).unwrap();
+            self.stats.evicted_array_full += 1;
             victim_entry.1.layer
         }
     }
@@ -245,6 +378,7 @@ fn update_bookkeeping(&mut self, texture_id: u16, texture_size: LandTextureSize,
             ),
         );
         array.lru.push_back(texture_id);
+        self.stats.uploads += 1;
     }
 
     fn free_layer_for_entry(&mut self, texture_size: LandTextureSize, entry: LandTextureEntry) {
@@ -0,0 +1,96 @@
+// Texmap disk cache
+// - The textures decoded from texmaps.mul (rgba8888) are written to uo_files.cache_folder, so that the next launches
+//   read them back instead of converting the bgra5551 pixels again (TexMap2D::write_rgba_cache).
+// - The cache file is keyed by the fingerprints of texmaps.mul, texidx.mul and verdata.mul (see uo_file_fingerprints).
+//   When one of them changes, the key doesn't match anymore: the textures are decoded from the mul files and cached
+//   again, and the stale cache files are deleted.
+// - Not used with the lazy texmaps, nor in the browser (no file system).
+//
+
+use crate::prelude::*;
+use color_eyre::eyre;
+use std::path::{Path, PathBuf};
+use uocf::geo::land_texture_2d::TexMap2D;
+use uocf::installation::IndexedFiles;
+use uocf::verdata::Verdata;
+
+const CACHE_FILE_PREFIX: &str = "texmaps_";
+const CACHE_FILE_EXTENSION: &str = "rgba";
+
+fn lg(sev: LogSev, msg: &str) {
+    logger::one(None, sev, LogAbout::UoFiles, msg);
+}
+
+fn cache_file_path(cache_folder: &Path, key: u64) -> PathBuf {
+    cache_folder.join(format!("{CACHE_FILE_PREFIX}{key:016x}.{CACHE_FILE_EXTENSION}"))
+}
+
+/// Loads the texmaps from the cache, if it's there and up to date, or else from the mul files, then caches them.
+/// cache_key identifies the content of the files (None: no cache). The progress is the one of
+///  TexMap2D::load_with_progress: there's none when the cache is used.
+pub fn load_texmaps(
+    texmaps: &IndexedFiles,
+    verdata: Option<&Verdata>,
+    cache_folder: &Path,
+    cache_key: Option<u64>,
+    progress: impl Fn(usize, usize) + Sync,
+) -> uocf::errors::Result<TexMap2D> {
+    let load = |progress| TexMap2D::load_with_progress(texmaps.data.clone(), texmaps.index.clone(), verdata, progress);
+    let Some(key) = cache_key.filter(|_| !cfg!(target_arch = "wasm32") && !cache_folder.as_os_str().is_empty()) else {
+        return load(progress);
+    };
+
+    let cache_path = cache_file_path(cache_folder, key);
+    if let Ok(file) = std::fs::File::open(&cache_path) {
+        match TexMap2D::read_rgba_cache(std::io::BufReader::new(file), key) {
+            Ok(Some(texmap)) => {
+                lg(LogSev::Info, &format!("Texmaps read from the cache {cache_path:?}."));
+                return Ok(texmap);
+            }
+            Ok(None) => lg(LogSev::Info, &format!("The texmaps cache {cache_path:?} is stale.")),
+            Err(e) => lg(
+                LogSev::Warn,
+                &format!("Can't read the texmaps cache {cache_path:?}: {e}"),
+            ),
+        }
+    }
+
+    let texmap = load(progress)?;
+    match write_cache(&texmap, cache_folder, &cache_path, key) {
+        Ok(()) => lg(LogSev::Info, &format!("Texmaps cached in {cache_path:?}.")),
+        Err(e) => lg(
+            LogSev::Warn,
+            &format!("Can't write the texmaps cache {cache_path:?}: {e}"),
+        ),
+    }
+    Ok(texmap)
+}
+
+/// Writes the cache file (through a temporary file, so that a cache file is always complete), and deletes the ones
+///  of other keys.
+fn write_cache(texmap: &TexMap2D, cache_folder: &Path, cache_path: &Path, key: u64) -> eyre::Result<()> {
+    std::fs::create_dir_all(cache_folder)?;
+    let temp_path = cache_path.with_extension("tmp");
+    let file = std::fs::File::create(&temp_path)?;
+    texmap.write_rgba_cache(std::io::BufWriter::new(file), key)?;
+    std::fs::rename(&temp_path, cache_path)?;
+
+    for entry in std::fs::read_dir(cache_folder)?.flatten() {
+        let path = entry.path();
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let is_cache_file = file_name.starts_with(CACHE_FILE_PREFIX)
+            && path
+                .extension()
+                .is_some_and(|extension| extension == CACHE_FILE_EXTENSION);
+        if is_cache_file && path != cache_path {
+            match std::fs::remove_file(&path) {
+                Ok(()) => lg(LogSev::Debug, &format!("Deleted the stale texmaps cache {path:?}.")),
+                Err(e) => lg(
+                    LogSev::Warn,
+                    &format!("Can't delete the stale texmaps cache {path:?}: {e}"),
+                ),
+            }
+        }
+    }
+    Ok(())
+}
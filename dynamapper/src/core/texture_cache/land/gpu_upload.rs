@@ -0,0 +1,135 @@
+// Land texture layer uploads
+// - The land texture arrays are created once, without CPU-side pixel data, and never mutated as assets afterwards:
+//   mutating Image.data would make bevy re-upload the whole array (every layer) for a single new texture.
+// - LandTextureCache queues instead the bytes of each layer it fills; they are moved to the render world during
+//   extraction and written with RenderQueue::write_texture, touching only that layer of the GPU texture.
+// - Uploads for an array whose GpuImage isn't prepared yet (first frames) are kept until it is.
+// - With mipmaps (see mipmaps), each level of the layer is written too.
+//
+
+use super::{cache::LandTextureCache, mipmaps};
+use crate::prelude::*;
+use bevy::prelude::*;
+use bevy::render::{
+    ExtractSchedule, MainWorld, Render, RenderApp, RenderSet,
+    render_asset::RenderAssets,
+    render_resource::{Extent3d, Origin3d, TexelCopyBufferLayout, TexelCopyTextureInfo, TextureAspect},
+    renderer::RenderQueue,
+    texture::GpuImage,
+};
+use uocf::geo::land_texture_2d::LandTextureSize;
+
+const TEXTURE_BYTES_PER_PIXEL: u32 = 4; // RGBA8888
+
+/// Pixel data of a single layer of a land texture array, to be written to the GPU texture.
+pub struct LandTextureLayerUpload {
+    pub image_id: AssetId<Image>,
+    pub layer: u32,
+    pub size: LandTextureSize,
+    pub bytes: Vec<u8>,
+    /// Pixel data of the smaller mip levels (1, 2...). Empty if the array has none.
+    pub mips: Vec<Vec<u8>>,
+}
+
+/// Render world: layer uploads extracted from the LandTextureCache, not written yet.
+#[derive(Resource, Default)]
+struct PendingLandTextureUploads(Vec<LandTextureLayerUpload>);
+
+pub struct LandTextureGpuUploadPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(LandTextureGpuUploadPlugin);
+
+impl Plugin for LandTextureGpuUploadPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .init_resource::<PendingLandTextureUploads>()
+            .add_systems(ExtractSchedule, extract_land_texture_uploads)
+            // PrepareResources runs after PrepareAssets, where the GpuImage of a new array is created.
+            .add_systems(Render, write_land_texture_uploads.in_set(RenderSet::PrepareResources));
+    }
+}
+
+fn extract_land_texture_uploads(mut main_world: ResMut<MainWorld>, mut pending_r: ResMut<PendingLandTextureUploads>) {
+    // Checked without taking the resource as mutated, which would happen on every frame.
+    if main_world
+        .get_resource::<LandTextureCache>()
+        .is_none_or(|cache| !cache.has_pending_gpu_uploads())
+    {
+        return;
+    }
+    let mut cache = main_world.resource_mut::<LandTextureCache>();
+    pending_r.0.extend(cache.take_pending_gpu_uploads());
+}
+
+fn write_land_texture_uploads(
+    mut pending_r: ResMut<PendingLandTextureUploads>,
+    gpu_images_r: Res<RenderAssets<GpuImage>>,
+    render_queue_r: Res<RenderQueue>,
+) {
+    if pending_r.0.is_empty() {
+        return;
+    }
+    profile_span!("write_land_texture_uploads", uploads = pending_r.0.len());
+    // Uploads are written in order, so if a layer was reused in the meantime the newest texture wins.
+    pending_r.0.retain(|upload| {
+        let Some(gpu_image) = gpu_images_r.get(upload.image_id) else {
+            return true;
+        };
+        let (width, height) = upload.size.dimensions();
+        let levels = std::iter::once(&upload.bytes).chain(&upload.mips);
+        let level_count = 1 + upload.mips.len() as u32;
+        let level_sizes_valid = levels.clone().enumerate().all(|(level, bytes)| {
+            let (w, h) = mipmaps::mip_level_size(width, height, level as u32);
+            bytes.len() == (w * h * TEXTURE_BYTES_PER_PIXEL) as usize
+        });
+        if upload.layer >= gpu_image.size.depth_or_array_layers
+            || level_count > gpu_image.mip_level_count
+            || !level_sizes_valid
+        {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::RenderWorldLand,
+                &format!(
+                    "Dropping invalid land texture upload: layer {}, {} bytes and {level_count} mip levels for a \
+                     {width}x{height} texture.",
+                    upload.layer,
+                    upload.bytes.len()
+                ),
+            );
+            return false;
+        }
+        for (level, bytes) in levels.enumerate() {
+            let (w, h) = mipmaps::mip_level_size(width, height, level as u32);
+            render_queue_r.write_texture(
+                TexelCopyTextureInfo {
+                    texture: &gpu_image.texture,
+                    mip_level: level as u32,
+                    origin: Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: upload.layer,
+                    },
+                    aspect: TextureAspect::All,
+                },
+                bytes,
+                TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(w * TEXTURE_BYTES_PER_PIXEL),
+                    rows_per_image: Some(h),
+                },
+                Extent3d {
+                    width: w,
+                    height: h,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+        false
+    });
+}
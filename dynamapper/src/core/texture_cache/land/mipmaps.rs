@@ -0,0 +1,114 @@
+// Mipmaps of the land textures and of their normal maps, with trilinear or anisotropic filtering
+//  (render.land_texture_filtering).
+// - Built on the CPU for each layer when it's loaded into the cache, and uploaded with it (see gpu_upload): each level
+//   halves the previous one, averaging 2x2 texels.
+// - The textures are sRGB: they're averaged in linear space, or the smaller levels would get darker. The normals are
+//   averaged as vectors, and normalized again.
+//
+
+use crate::external_data::settings::LandTextureFiltering;
+
+const BYTES_PER_PIXEL: usize = 4; // RGBA8888
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MipContent {
+    /// sRGB colors, linear alpha.
+    Color,
+    /// Tangent-space normals (see normal_map), with the height in the alpha.
+    NormalMap,
+}
+
+/// Mip levels of a texture array of that size: the whole chain down to 1x1, or just the full size with nearest
+///  filtering.
+pub fn mip_level_count(filtering: LandTextureFiltering, width: u32, height: u32) -> u32 {
+    match filtering {
+        LandTextureFiltering::Nearest => 1,
+        LandTextureFiltering::Trilinear | LandTextureFiltering::Anisotropic => 32 - width.max(height).leading_zeros(),
+    }
+}
+
+/// Size of a mip level of a texture.
+pub fn mip_level_size(width: u32, height: u32, level: u32) -> (u32, u32) {
+    ((width >> level).max(1), (height >> level).max(1))
+}
+
+fn srgb_to_linear(c: u8) -> f32 {
+    let c = c as f32 / 255.0;
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> u8 {
+    let c = c.clamp(0.0, 1.0);
+    let c = if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    };
+    (c * 255.0).round() as u8
+}
+
+/// Halves an RGBA8 image (at least 1x1), averaging each 2x2 block.
+fn downsample(rgba: &[u8], width: u32, height: u32, content: MipContent) -> Vec<u8> {
+    let (w, h) = (width as usize, height as usize);
+    let (dw, dh) = ((w / 2).max(1), (h / 2).max(1));
+    // To linear colors, or to vectors in -1..1. Alpha is linear in both.
+    let decode = |c: u8| match content {
+        MipContent::Color => srgb_to_linear(c),
+        MipContent::NormalMap => c as f32 / 255.0 * 2.0 - 1.0,
+    };
+    let mut out = vec![0u8; dw * dh * BYTES_PER_PIXEL];
+    for y in 0..dh {
+        for x in 0..dw {
+            let mut sum = [0.0f32; BYTES_PER_PIXEL];
+            let mut count = 0.0;
+            for sy in (2 * y)..(2 * y + 2).min(h) {
+                for sx in (2 * x)..(2 * x + 2).min(w) {
+                    let src = (sy * w + sx) * BYTES_PER_PIXEL;
+                    for (s, &c) in sum[..3].iter_mut().zip(&rgba[src..src + 3]) {
+                        *s += decode(c);
+                    }
+                    sum[3] += rgba[src + 3] as f32 / 255.0;
+                    count += 1.0;
+                }
+            }
+            let mut avg = sum.map(|s| s / count);
+            let dst = (y * dw + x) * BYTES_PER_PIXEL;
+            match content {
+                MipContent::Color => {
+                    for (o, &a) in out[dst..dst + 3].iter_mut().zip(&avg[..3]) {
+                        *o = linear_to_srgb(a);
+                    }
+                }
+                MipContent::NormalMap => {
+                    let len = (avg[0] * avg[0] + avg[1] * avg[1] + avg[2] * avg[2]).sqrt();
+                    if len > 1e-6 {
+                        avg[..3].iter_mut().for_each(|n| *n /= len);
+                    } else {
+                        avg[..3].copy_from_slice(&[0.0, 0.0, 1.0]);
+                    }
+                    for (o, &n) in out[dst..dst + 3].iter_mut().zip(&avg[..3]) {
+                        *o = ((n * 0.5 + 0.5) * 255.0).round() as u8;
+                    }
+                }
+            }
+            out[dst + 3] = (avg[3] * 255.0).round() as u8;
+        }
+    }
+    out
+}
+
+/// The mip levels after the full size one (1, 2...) of an RGBA8 image, up to level_count - 1 of them.
+pub fn mip_chain(rgba: &[u8], width: u32, height: u32, level_count: u32, content: MipContent) -> Vec<Vec<u8>> {
+    let mut levels: Vec<Vec<u8>> = Vec::with_capacity(level_count.saturating_sub(1) as usize);
+    for level in 1..level_count {
+        let (w, h) = mip_level_size(width, height, level - 1);
+        let prev = levels.last().map_or(rgba, Vec::as_slice);
+        let next = downsample(prev, w, h, content);
+        levels.push(next);
+    }
+    levels
+}
@@ -0,0 +1,52 @@
+// Normal maps of the land textures, for extra surface detail in the fragment shading modes.
+// - UO has none: they're derived from the texture itself, taking its luminance as a height map (brighter is higher),
+//   with a Sobel filter. Wrapping around the edges, since a texture repeats over the neighboring tiles.
+// - Baked on the CPU when a texture is loaded into the cache (see cache::LandTextureCache), into the layer of the normal
+//   map array with the same index as the texture's.
+// - Tangent space, encoded as [0..255] = [-1..1]: r along +u (world +x), g along +v (world +z), b away from the surface.
+//   The alpha holds the height.
+//
+
+const BYTES_PER_PIXEL: usize = 4; // RGBA8888
+/// Height difference between black and white texels, in texels: how bumpy the baked normals are. The shader scales
+///  the slopes further (see normal_map_params in LandEffectsUniform).
+const NORMAL_MAP_HEIGHT_SCALE: f32 = 4.0;
+
+/// Height (0..1) of each texel: its luminance.
+fn height_map(rgba: &[u8]) -> Vec<f32> {
+    rgba.chunks_exact(BYTES_PER_PIXEL)
+        .map(|px| (0.299 * px[0] as f32 + 0.587 * px[1] as f32 + 0.114 * px[2] as f32) / 255.0)
+        .collect()
+}
+
+/// Bakes the normal map of an RGBA8 texture, as RGBA8 of the same size.
+pub fn normal_map_from_texture(rgba: &[u8], width: u32, height: u32) -> Vec<u8> {
+    let (w, h) = (width as i32, height as i32);
+    debug_assert_eq!(rgba.len(), (w * h) as usize * BYTES_PER_PIXEL);
+    let heights = height_map(rgba);
+    let at = |x: i32, y: i32| heights[(y.rem_euclid(h) * w + x.rem_euclid(w)) as usize];
+
+    let mut normals = vec![0u8; rgba.len()];
+    for y in 0..h {
+        for x in 0..w {
+            // Sobel: slopes along u and v, weighted over the 3x3 neighborhood (/8 gives the slope per texel).
+            let du = (at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x - 1, y) + at(x - 1, y + 1));
+            let dv = (at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1))
+                - (at(x - 1, y - 1) + 2.0 * at(x, y - 1) + at(x + 1, y - 1));
+            let (nx, ny, nz) = (
+                -du / 8.0 * NORMAL_MAP_HEIGHT_SCALE,
+                -dv / 8.0 * NORMAL_MAP_HEIGHT_SCALE,
+                1.0,
+            );
+            let len = (nx * nx + ny * ny + nz * nz).sqrt();
+            let encode = |n: f32| ((n / len * 0.5 + 0.5) * 255.0).round() as u8;
+            let dst = (y * w + x) as usize * BYTES_PER_PIXEL;
+            normals[dst] = encode(nx);
+            normals[dst + 1] = encode(ny);
+            normals[dst + 2] = encode(nz);
+            normals[dst + 3] = (at(x, y) * 255.0).round() as u8;
+        }
+    }
+    normals
+}
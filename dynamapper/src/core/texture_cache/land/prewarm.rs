@@ -0,0 +1,153 @@
+// Land texture pre-warm
+// - While loading, before entering AppState::InGame, collects the land tiles of the map blocks around the player start
+//   position and makes their textures resident in the texture arrays, so that the first frames don't stall on
+//   texture uploads.
+// - Textures are uploaded in batches, a batch per frame, while the loading screen shows the progress.
+//
+
+use super::cache::LandTextureCache;
+use crate::{
+    core::{
+        loading::{LoadingProgress, LoadingStep, LoadingStepState},
+        uo_files_loader::{ArtRes, MapPlanesRes, TexMap2DRes},
+    },
+    prelude::*,
+};
+use bevy::prelude::*;
+use std::collections::{BTreeSet, HashSet};
+use uocf::geo::map::{MapBlockRelPos, MapCell};
+
+/// Map blocks scanned on each side of the block holding the player start position.
+const PREWARM_RADIUS_BLOCKS: u32 = 12;
+/// Textures made resident per frame. Their layers are written to the GPU during the next render world extraction.
+const PREWARM_TEXTURES_PER_FRAME: usize = 64;
+
+/// Land tile ids whose textures are still to be made resident.
+#[derive(Resource, Debug, Default)]
+pub struct LandTexturePrewarm {
+    pending: Vec<u16>,
+    pub total: usize,
+}
+impl LandTexturePrewarm {
+    pub fn done_count(&self) -> usize {
+        self.total - self.pending.len()
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    /// Completion, from 0.0 to 1.0.
+    pub fn progress(&self) -> f32 {
+        if self.total == 0 {
+            return 1.0;
+        }
+        self.done_count() as f32 / self.total as f32
+    }
+}
+
+pub struct LandTexturePrewarmPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(LandTexturePrewarmPlugin);
+
+impl Plugin for LandTexturePrewarmPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<LandTexturePrewarm>()
+            .add_systems(OnEnter(LoadingPhase::Textures), sys_collect_prewarm_textures)
+            .add_systems(
+                Update,
+                sys_prewarm_land_textures.run_if(in_state(LoadingPhase::Textures)),
+            );
+    }
+}
+
+fn sys_collect_prewarm_textures(
+    settings_r: Res<Settings>,
+    map_planes_r: Res<MapPlanesRes>,
+    loading_progress_r: Res<LoadingProgress>,
+    mut prewarm_r: ResMut<LandTexturePrewarm>,
+) {
+    log_system_add_onenter::<LandTexturePrewarmPlugin>(LoadingPhase::Textures.as_ref(), fname!());
+    loading_progress_r.start(LoadingStep::TexturePrewarm);
+
+    let start_p = settings_r.world.start_p;
+    let map_id = start_p.m as u32;
+    let Some(map_plane) = map_planes_r.get(map_id) else {
+        logger::one(
+            None,
+            LogSev::Warn,
+            LogAbout::RenderWorldLand,
+            &format!("Skipping land texture pre-warm: map plane {map_id} isn't loaded."),
+        );
+        return;
+    };
+
+    let center_x = MapCell::coords_of_parent_block_x(start_p.x as u32);
+    let center_y = MapCell::coords_of_parent_block_y(start_p.y as u32);
+    let bx0 = center_x.saturating_sub(PREWARM_RADIUS_BLOCKS);
+    let by0 = center_y.saturating_sub(PREWARM_RADIUS_BLOCKS);
+    let bx1 = (center_x + PREWARM_RADIUS_BLOCKS).min(map_plane.size_blocks().width.saturating_sub(1));
+    let by1 = (center_y + PREWARM_RADIUS_BLOCKS).min(map_plane.size_blocks().height.saturating_sub(1));
+    let mut blocks: Vec<MapBlockRelPos> = (bx0..=bx1)
+        .flat_map(|x| (by0..=by1).map(move |y| MapBlockRelPos { x, y }))
+        .collect();
+    // The blocks stay cached: the scene needs them right after.
+    if let Err(e) = map_plane.load_blocks(&mut blocks) {
+        logger::one(
+            None,
+            LogSev::Error,
+            LogAbout::RenderWorldLand,
+            &format!("Skipping land texture pre-warm: can't load the map blocks around the start position: {e}"),
+        );
+        return;
+    }
+
+    // Textures are cached by land tile id. Sorted, so that the upload order is stable between runs.
+    let map_plane = map_plane.read();
+    let tile_ids: BTreeSet<u16> = blocks
+        .iter()
+        .filter_map(|&pos| map_plane.block(pos))
+        .flat_map(|block| block.cells().iter().map(|cell| cell.id))
+        .collect();
+    prewarm_r.pending = tile_ids.into_iter().rev().collect();
+    prewarm_r.total = prewarm_r.pending.len();
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::RenderWorldLand,
+        &format!(
+            "Pre-warming {} land textures from {} map blocks around the start position.",
+            prewarm_r.total,
+            blocks.len()
+        ),
+    );
+}
+
+fn sys_prewarm_land_textures(
+    mut prewarm_r: ResMut<LandTexturePrewarm>,
+    mut land_texture_cache_r: ResMut<LandTextureCache>,
+    mut images_r: ResMut<Assets<Image>>,
+    texmap_2d_r: Res<TexMap2DRes>,
+    art_r: Res<ArtRes>,
+    loading_progress_r: Res<LoadingProgress>,
+) {
+    if loading_progress_r.get(LoadingStep::TexturePrewarm) == LoadingStepState::Done {
+        return;
+    }
+    let batch_start = prewarm_r.pending.len().saturating_sub(PREWARM_TEXTURES_PER_FRAME);
+    let batch: HashSet<u16> = prewarm_r.pending.drain(batch_start..).collect();
+    land_texture_cache_r.preload_textures(&mut images_r, texmap_2d_r.0.clone(), art_r.0.clone(), &batch);
+    loading_progress_r.set(LoadingStep::TexturePrewarm, LoadingStepState::Running(Some(prewarm_r.progress())));
+
+    if prewarm_r.is_done() {
+        loading_progress_r.finish(LoadingStep::TexturePrewarm);
+        logger::one(
+            None,
+            LogSev::Info,
+            LogAbout::RenderWorldLand,
+            &format!("Land texture pre-warm done ({} textures).", prewarm_r.total),
+        );
+    }
+}
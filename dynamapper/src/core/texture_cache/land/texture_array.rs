@@ -1,5 +1,6 @@
 #![allow(unused)]
 
+use super::mipmaps;
 use crate::{core::uo_files_loader::TexMap2DRes, prelude::*, util_lib::image::*};
 use bevy::{
     image::{ImageSampler, ImageSamplerDescriptor},
@@ -9,6 +10,7 @@
     },
 };
 use std::sync::OnceLock;
+use uocf::art::{Art, ArtElement};
 use uocf::geo::land_texture_2d::{LandTextureSize, TexMap2D};
 
 //pub const TEXTURE_UNUSED_ID: u32 = 0x007F;
@@ -27,20 +29,42 @@ fn max_layers_per_texture_size(tex_size: LandTextureSize) -> u32 {
     }
 }
 
+/// Sampler of the land texture arrays. Anisotropic filtering requires linear filters everywhere.
+fn texture_array_sampler(filtering: LandTextureFiltering, anisotropy: u16) -> ImageSamplerDescriptor {
+    let (mag_filter, min_filter, anisotropy_clamp) = match filtering {
+        LandTextureFiltering::Nearest => (FilterMode::Nearest, FilterMode::Nearest, 1),
+        LandTextureFiltering::Trilinear => (FilterMode::Nearest, FilterMode::Linear, 1),
+        LandTextureFiltering::Anisotropic => (FilterMode::Linear, FilterMode::Linear, anisotropy.clamp(1, 16)),
+    };
+    ImageSamplerDescriptor {
+        address_mode_u: AddressMode::ClampToEdge.into(),
+        address_mode_v: AddressMode::ClampToEdge.into(),
+        mag_filter: mag_filter.into(),
+        min_filter: min_filter.into(),
+        mipmap_filter: min_filter.into(),
+        anisotropy_clamp,
+        ..default()
+    }
+}
+
 /// Create a GPU texture array (array texture) resource for a given size.
+/// The textures are Rgba8UnormSrgb, their normal maps (see normal_map) Rgba8Unorm: they aren't colors.
+/// Without nearest filtering, it has room for the mipmaps (see mipmaps).
 pub fn create_gpu_texture_array(
     label: &'static str,
     image_assets: &mut Assets<Image>,
     tex_size: LandTextureSize,
+    format: TextureFormat,
+    filtering: LandTextureFiltering,
+    anisotropy: u16,
 ) -> Handle<Image> {
     let (width, height) = tex_size.dimensions();
     let layers = max_layers_per_texture_size(tex_size);
 
-    // Pre-allocate array data as RGBA8 (4 bytes/pixel)
-    let data_bytes = (width * height * layers * 4) as usize;
-
+    // No CPU-side data: the layers are written directly to the GPU texture (see gpu_upload), and the asset must not be
+    //  mutated afterwards, or bevy would recreate the GPU texture and lose them.
     let mut array = Image {
-        data: Some(vec![0u8; data_bytes]),
+        data: None,
         texture_descriptor: bevy::render::render_resource::TextureDescriptor {
             label: Some(label),
             size: Extent3d {
@@ -49,20 +73,13 @@ pub fn create_gpu_texture_array(
                 depth_or_array_layers: layers,
             },
             dimension: TextureDimension::D2,
-            format: TextureFormat::Rgba8UnormSrgb,
-            mip_level_count: 1,
+            format,
+            mip_level_count: mipmaps::mip_level_count(filtering, width, height),
             sample_count: 1,
             usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
             view_formats: &[],
         },
-        sampler: ImageSampler::Descriptor(ImageSamplerDescriptor {
-            address_mode_u: AddressMode::ClampToEdge.into(),
-            address_mode_v: AddressMode::ClampToEdge.into(),
-            mag_filter: FilterMode::Nearest.into(),
-            min_filter: FilterMode::Nearest.into(),
-            mipmap_filter: FilterMode::Nearest.into(),
-            ..default()
-        }),
+        sampler: ImageSampler::Descriptor(texture_array_sampler(filtering, anisotropy)),
         ..default()
     };
     // Make sure the image view is consistent with array sizing
@@ -141,12 +158,41 @@ fn get_error_texture(
     */
 }
 
+/// Convert the flat (isometric) art of a land tile to a top-down small texture.
+/// The art is a 44x44 diamond: its top, right, bottom and left corners are the top-left, top-right, bottom-right and
+///  bottom-left corners of the tile. We sample it (nearest) rotated by 45 degrees, so that it fills the whole texture.
+fn land_art_to_small_texture(art_ref: &ArtElement) -> Vec<u8> {
+    const BYTES_PER_PIXEL: usize = 4; // RGBA8888
+    let (tw, th) = LandTextureSize::Small.dimensions();
+    let art_size = Art::LAND_ART_SIZE;
+    let art_half = art_size as f32 / 2.0;
+    let art_pixels = art_ref.pixel_data();
+
+    let mut rgba = vec![0u8; (tw * th) as usize * BYTES_PER_PIXEL];
+    for ty in 0..th {
+        for tx in 0..tw {
+            // Tile-space coordinates (0..1) of the texel center.
+            let u = (tx as f32 + 0.5) / tw as f32;
+            let v = (ty as f32 + 0.5) / th as f32;
+            let ax = (art_half + (u - v) * art_half).floor().clamp(0.0, (art_size - 1) as f32) as u32;
+            let ay = ((u + v) * art_half).floor().clamp(0.0, (art_size - 1) as f32) as u32;
+
+            let src = (ay * art_size + ax) as usize * BYTES_PER_PIXEL;
+            let dst = (ty * tw + tx) as usize * BYTES_PER_PIXEL;
+            rgba[dst..dst + BYTES_PER_PIXEL].copy_from_slice(&art_pixels[src..src + BYTES_PER_PIXEL]);
+        }
+    }
+    rgba
+}
+
 /// Try to get actual texture for provided texture_id.
+/// If it's not in texmaps, fall back to the flat land art (like the classic client does).
 /// If invalid, return UNUSED texture.
 pub fn get_texmap_image(
     texture_id: u16,
     image_assets_resmut: &mut ResMut<Assets<Image>>,
     texmap_2d_res: &TexMap2D,
+    art_res: &Art,
 ) -> (LandTextureSize, Handle<Image>) {
     fn local_log_warn(msg: &str) {
         logger::one(None, LogSev::Warn, LogAbout::RenderWorldLand, msg);
@@ -155,7 +201,14 @@ fn local_log_warn(msg: &str) {
     let tex_size_and_rgba = {
         match texmap_2d_res.element(texture_id as usize) {
             Some(tex_ref) => Some((tex_ref.size().clone(), tex_ref.pixel_data().clone())),
-            None => None,
+            None => match art_res.land(texture_id) {
+                Ok(Some(art_ref)) => Some((LandTextureSize::Small, land_art_to_small_texture(&art_ref))),
+                Ok(None) => None,
+                Err(e) => {
+                    local_log_warn(&format!("Can't read land art {texture_id:#X}: {e}"));
+                    None
+                }
+            },
         }
     };
 
@@ -0,0 +1,146 @@
+// UO file fingerprints
+// - The loading task fingerprints every file of the UO installation (uocf::checksum::Fingerprint: the size and the
+//   checksum of some samples, so it's quick even for the map files), and logs them.
+// - They're compared with the ones of the last session, saved in the cache folder: the files that changed, appeared
+//   or disappeared since then are warned about. Not if the UO folder is another one.
+// - They key the caches built from the files (see texture_cache::land::disk_cache).
+//
+
+use crate::prelude::*;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use uocf::checksum::Fingerprint;
+use uocf::installation::UoInstallation;
+
+const LAST_SESSION_FILE_NAME: &str = "uo_file_fingerprints.txt";
+
+fn lg(sev: LogSev, msg: &str) {
+    logger::one(None, sev, LogAbout::UoFiles, msg);
+}
+
+/// Fingerprints of the files of a UO installation, by path.
+pub struct UoFileFingerprints {
+    folder: PathBuf,
+    by_path: BTreeMap<PathBuf, Fingerprint>,
+}
+impl UoFileFingerprints {
+    /// Fingerprints every file of the installation. The ones that can't be read are logged, and left out.
+    pub fn compute(installation: &UoInstallation) -> Self {
+        let mut by_path = BTreeMap::new();
+        for path in installation.files() {
+            match Fingerprint::of_file(path) {
+                Ok(fingerprint) => {
+                    by_path.insert(path.clone(), fingerprint);
+                }
+                Err(e) => lg(LogSev::Warn, &format!("Can't fingerprint {path:?}: {e}")),
+            }
+        }
+        Self {
+            folder: installation.folder.clone(),
+            by_path,
+        }
+    }
+
+    pub fn get(&self, path: &Path) -> Option<&Fingerprint> {
+        self.by_path.get(path)
+    }
+
+    /// Key of a cache built from these files: it changes when one of them does. None if one has no fingerprint.
+    pub fn key<'a>(&self, paths: impl IntoIterator<Item = &'a Path>) -> Option<u64> {
+        let fingerprints: Option<Vec<&Fingerprint>> = paths.into_iter().map(|path| self.get(path)).collect();
+        fingerprints.map(Fingerprint::combine)
+    }
+
+    /// Files by their name, lowercase: the installation matches them regardless of the case.
+    fn by_file_name(&self) -> BTreeMap<String, Fingerprint> {
+        self.by_path
+            .iter()
+            .filter_map(|(path, fingerprint)| {
+                let file_name = path.file_name()?.to_string_lossy().to_lowercase();
+                Some((file_name, *fingerprint))
+            })
+            .collect()
+    }
+
+    pub fn log(&self) {
+        for (file_name, fingerprint) in self.by_file_name() {
+            lg(LogSev::Debug, &format!("Fingerprint of {file_name}: {fingerprint}."));
+        }
+        lg(LogSev::Info, &format!("Fingerprinted {} UO files.", self.by_path.len()));
+    }
+
+    /// Warns about the files changed since the last session, then saves these fingerprints for the next one.
+    /// Nothing is done without a cache folder, or in the browser (no file system).
+    pub fn check_last_session(&self, cache_folder: &Path) {
+        if cfg!(target_arch = "wasm32") || cache_folder.as_os_str().is_empty() {
+            return;
+        }
+        let file_path = cache_folder.join(LAST_SESSION_FILE_NAME);
+        if let Ok(text) = std::fs::read_to_string(&file_path) {
+            self.compare(&text);
+        }
+        if let Err(e) = std::fs::create_dir_all(cache_folder).and_then(|()| std::fs::write(&file_path, self.to_text()))
+        {
+            lg(
+                LogSev::Warn,
+                &format!("Can't save the UO file fingerprints to {file_path:?}: {e}"),
+            );
+        }
+    }
+
+    /// The folder on the first line, then a line per file: name, size and hash.
+    fn to_text(&self) -> String {
+        let mut text = format!("{}\n", self.folder.display());
+        for (file_name, fingerprint) in self.by_file_name() {
+            text += &format!("{file_name} {} {:016x}\n", fingerprint.size, fingerprint.hash);
+        }
+        text
+    }
+
+    fn compare(&self, last_session_text: &str) {
+        let mut lines = last_session_text.lines();
+        if lines.next() != Some(self.folder.display().to_string().as_str()) {
+            lg(
+                LogSev::Info,
+                "The UO folder isn't the one of the last session: its files aren't compared.",
+            );
+            return;
+        }
+        let last: BTreeMap<&str, Fingerprint> = lines
+            .filter_map(|line| {
+                let mut fields = line.split(' ');
+                let (file_name, size, hash) = (fields.next()?, fields.next()?, fields.next()?);
+                let fingerprint = Fingerprint {
+                    size: size.parse().ok()?,
+                    hash: u64::from_str_radix(hash, 16).ok()?,
+                };
+                Some((file_name, fingerprint))
+            })
+            .collect();
+        let current = self.by_file_name();
+
+        let mut changed_count = 0;
+        for (file_name, fingerprint) in &current {
+            let what = match last.get(file_name.as_str()) {
+                Some(last_fingerprint) if last_fingerprint == fingerprint => continue,
+                Some(last_fingerprint) => format!("changed ({last_fingerprint} -> {fingerprint})"),
+                None => "appeared".to_owned(),
+            };
+            lg(
+                LogSev::Warn,
+                &format!("UO file {file_name} {what} since the last session."),
+            );
+            changed_count += 1;
+        }
+        for file_name in last.keys().filter(|file_name| !current.contains_key(**file_name)) {
+            lg(
+                LogSev::Warn,
+                &format!("UO file {file_name} disappeared since the last session."),
+            );
+            changed_count += 1;
+        }
+        if changed_count == 0 {
+            lg(LogSev::Info, "The UO files didn't change since the last session.");
+        }
+    }
+}
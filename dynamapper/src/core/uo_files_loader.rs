@@ -1,84 +1,580 @@
 #![allow(unused)]
 
+use crate::core::loading::{LoadingProgress, LoadingStep, LoadingStepState};
 use crate::core::system_sets::StartupSysSet;
+use crate::core::texture_cache::land::disk_cache;
+use crate::core::uo_file_fingerprints::UoFileFingerprints;
 use crate::external_data::settings::Settings;
 use crate::prelude::*;
 use bevy::prelude::*;
+use bevy::tasks::{AsyncComputeTaskPool, Task, block_on, poll_once};
+use color_eyre::eyre::{self, WrapErr};
 use dashmap::DashMap;
 //use parking_lot::RwLock;
-use uocf::eyre_imports;
-use uocf::geo::{land_texture_2d, map};
+use uocf::art;
+use uocf::cliloc;
+use uocf::geo::{land_texture_2d, map, map_def, statics};
+use uocf::hues;
+use uocf::installation::{FileFormat, UoInstallation};
+use uocf::radarcol;
 use uocf::tiledata;
-eyre_imports!();
+use uocf::verdata;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
+const CLILOC_DEFAULT_LANGUAGE: &str = "enu";
+/// Virtual folder where the files of the UO file server are added to the uocf vfs.
+#[cfg(not(target_arch = "wasm32"))]
+const REMOTE_UO_FOLDER: &str = "/uo_file_server";
+
 #[derive(Resource)]
 pub struct UoInterfaceSettingsRes(pub Arc<UoInterfaceSettings>);
 
 #[derive(Resource)]
-pub struct MapPlanesRes(pub Arc<DashMap<u32, map::MapPlane>>);
+pub struct MapPlanesRes(pub Arc<DashMap<u32, map::MapPlaneShared>>);
+impl MapPlanesRes {
+    /// Handle to a loaded map plane. It's a copy, so that the DashMap isn't kept locked while using the map plane.
+    pub fn get(&self, map_id: u32) -> Option<map::MapPlaneShared> {
+        self.0.get(&map_id).map(|map_plane| map_plane.clone())
+    }
+}
+
+#[derive(Resource)]
+pub struct StaticsPlanesRes(pub Arc<DashMap<u32, statics::StaticsPlane>>);
 
 #[derive(Resource)]
 pub struct TileDataRes(pub Arc<tiledata::TileData>);
 
+/// Localized strings, from the cliloc of the language in the settings.
+/// The cliloc is empty if the client has no (supported) cliloc file: names then come from tiledata only.
+#[derive(Resource)]
+pub struct LocalizationRes {
+    pub cliloc: Arc<cliloc::Cliloc>,
+    /// Language of the loaded cliloc (it's the default one if the requested one is missing).
+    pub language: String,
+}
+impl LocalizationRes {
+    /// The localized string, or a placeholder with its number if missing.
+    pub fn get(&self, id: u32) -> Cow<'_, str> {
+        match self.cliloc.get(id) {
+            Some(text) => Cow::Borrowed(text),
+            None => Cow::Owned(format!("<cliloc #{id}>")),
+        }
+    }
+
+    /// Localized name of a land tile, falling back to the tiledata one.
+    pub fn land_tile_name<'a>(&'a self, tiledata: &'a tiledata::TileData, tile_id: u16) -> Cow<'a, str> {
+        match self.cliloc.land_tile_name(tile_id) {
+            Some(name) => Cow::Borrowed(name),
+            None => tiledata
+                .land_tile(tile_id)
+                .map_or(Cow::Owned(format!("<land tile {tile_id:#06X}>")), |tile| Cow::Borrowed(tile.name_ascii())),
+        }
+    }
+
+    /// Localized name of an item (static), falling back to the tiledata one.
+    pub fn item_name<'a>(&'a self, tiledata: &'a tiledata::TileData, item_id: u16) -> Cow<'a, str> {
+        match self.cliloc.item_name(item_id) {
+            Some(name) => Cow::Borrowed(name),
+            None => tiledata
+                .item_tile(item_id)
+                .map_or(Cow::Owned(format!("<item {item_id:#06X}>")), |tile| Cow::Borrowed(tile.name_ascii())),
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct HuesRes(pub Arc<hues::Hues>);
+
+#[derive(Resource)]
+pub struct RadarColRes(pub Arc<radarcol::RadarColors>);
+
 #[derive(Resource)]
 pub struct TexMap2DRes(pub Arc<land_texture_2d::TexMap2D>);
 
+#[derive(Resource)]
+pub struct ArtRes(pub Arc<art::Art>);
+
 pub struct UoInterfaceSettings {
     pub base_folder: PathBuf,
+    /// "host:port" of the UO file server the files are read from, instead of base_folder. Empty if none.
+    pub server: String,
+    /// Files found in base_folder, scanned by the loading task.
+    pub installation: UoInstallation,
+    pub map_file_backend: map::FileBackend,
+    /// Decode the land textures when first requested, instead of while loading (see TexMap2D::load_lazy).
+    pub lazy_texmaps: bool,
+    /// Folder of the caches built from the UO files. Empty: no caches.
+    pub cache_folder: PathBuf,
+    /// Cliloc language (file extension).
+    pub language: String,
+    /// Sizes and names of the map planes: the standard ones, and the custom ones of uomap.def.
+    pub map_defs: map_def::MapDefinitions,
+}
+impl UoInterfaceSettings {
+    /// Map planes with both a definition and a map file, in ascending order.
+    pub fn map_plane_indices(&self) -> impl Iterator<Item = u32> + '_ {
+        self.map_defs
+            .indices()
+            .filter(|map_index| self.installation.map_planes.contains_key(map_index))
+    }
 }
 
 pub struct UOFilesPlugin {
     pub registered_by: &'static str,
 }
 impl_tracked_plugin!(UOFilesPlugin);
+
 impl Plugin for UOFilesPlugin {
     fn build(&self, app: &mut App) {
         log_plugin_build(self);
-        app.add_systems(
-            Startup,
-            sys_setup_uo_data.in_set(StartupSysSet::LoadStartupUOFiles),
-        );
+        app.add_event::<RetryUoDataLoadingEvent>()
+            .add_systems(
+                Startup,
+                sys_start_uo_data_loading.in_set(StartupSysSet::LoadStartupUOFiles),
+            )
+            .add_systems(
+                Update,
+                (sys_finish_uo_data_loading, sys_retry_uo_data_loading)
+                    .chain()
+                    .run_if(in_state(LoadingPhase::UoFiles)),
+            );
     }
 }
 
-pub fn sys_setup_uo_data(mut commands: Commands, settings: Res<Settings>) {
+/// Why loading the UO data failed. While it's there, the loading screen shows it instead of the progress, and lets the
+///  user fix uo_files.folder and retry (see render::loading_ui).
+#[derive(Resource, Clone, Debug)]
+pub struct UoDataLoadingError {
+    /// The UO folder the data was loaded from.
+    pub folder: String,
+    /// The error, then its causes.
+    pub messages: Vec<String>,
+}
+
+/// Request to load the UO data again, from the uo_files.folder in the settings.
+#[derive(Event, Debug, Clone, Copy)]
+pub struct RetryUoDataLoadingEvent;
+
+/// UO data read by the loading task, inserted as resources once it's done.
+pub struct LoadedUoData {
+    uo_settings: UoInterfaceSettings,
+    map_planes: DashMap<u32, map::MapPlaneShared>,
+    statics_planes: DashMap<u32, statics::StaticsPlane>,
+    tiledata: tiledata::TileData,
+    cliloc: cliloc::Cliloc,
+    language: String,
+    hues: hues::Hues,
+    radar_colors: radarcol::RadarColors,
+    texmap_2d: land_texture_2d::TexMap2D,
+    art: art::Art,
+}
+
+/// Background task reading the UO files needed to start.
+#[derive(Resource)]
+pub struct UoDataLoadingTask(Task<eyre::Result<LoadedUoData>>);
+
+pub fn sys_start_uo_data_loading(
+    mut commands: Commands,
+    settings: Res<Settings>,
+    loading_progress_r: Res<LoadingProgress>,
+) {
     log_system_add_startup::<UOFilesPlugin>(StartupSysSet::LoadStartupUOFiles, fname!());
+    start_uo_data_loading(&mut commands, &settings, &loading_progress_r);
+}
+
+fn sys_retry_uo_data_loading(
+    mut commands: Commands,
+    mut events: EventReader<RetryUoDataLoadingEvent>,
+    settings: Res<Settings>,
+    loading_progress_r: Res<LoadingProgress>,
+    task_r: Option<Res<UoDataLoadingTask>>,
+) {
+    if events.read().count() == 0 || task_r.is_some() {
+        return;
+    }
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::UoFiles,
+        &format!("Loading the UO data again, from {}.", settings.uo_files.source_label()),
+    );
+    commands.remove_resource::<UoDataLoadingError>();
+    loading_progress_r.reset();
+    start_uo_data_loading(&mut commands, &settings, &loading_progress_r);
+}
+
+fn start_uo_data_loading(commands: &mut Commands, settings: &Settings, loading_progress_r: &LoadingProgress) {
+    let uo_settings = UoInterfaceSettings {
+        base_folder: settings.uo_files.folder.clone().into(),
+        server: settings.uo_files.server.trim().to_owned(),
+        installation: UoInstallation::default(),
+        map_file_backend: if settings.uo_files.mmap_map_files {
+            map::FileBackend::Mmap
+        } else {
+            map::FileBackend::Read
+        },
+        lazy_texmaps: settings.uo_files.lazy_texmaps,
+        cache_folder: settings.uo_files.cache_folder.clone().into(),
+        language: settings.uo_files.language.to_lowercase(),
+        map_defs: map_def::MapDefinitions::standard(),
+    };
+    let map_defs_file: Option<PathBuf> =
+        (!settings.uo_files.map_definitions.is_empty()).then(|| settings.uo_files.map_definitions.clone().into());
+    // Other map planes are loaded on demand, when the player moves there (see MapPlaneManager).
+    let map_plane_index = settings.world.start_p.m as u32;
+    let progress = loading_progress_r.clone();
+
+    // Reading the files takes a while: do it in background, so that the loading screen stays responsive.
+    let task = AsyncComputeTaskPool::get()
+        .spawn(async move { load_uo_data(uo_settings, map_defs_file, map_plane_index, &progress) });
+    commands.insert_resource(UoDataLoadingTask(task));
+}
+
+fn load_uo_data(
+    mut uo_settings: UoInterfaceSettings,
+    map_defs_file: Option<PathBuf>,
+    map_plane_index: u32,
+    progress: &LoadingProgress,
+) -> eyre::Result<LoadedUoData> {
     let lg = |text: &str| logger::one(None, logger::LogSev::Info, logger::LogAbout::UoFiles, text);
-    let uo_path: PathBuf = settings.uo_files.folder.clone().into();
 
     lg("Start loading UO Data.");
+    #[cfg(not(target_arch = "wasm32"))]
+    if !uo_settings.server.is_empty() {
+        // The remote files are read through the vfs, as if they were in a local folder.
+        lg(&format!("Connecting to the UO file server {}.", uo_settings.server));
+        let remote = uocf::remote::RemoteFolder::connect(&uo_settings.server)
+            .wrap_err_with(|| format!("Connect to the UO file server {}", uo_settings.server))?;
+        uo_settings.base_folder = PathBuf::from(REMOTE_UO_FOLDER);
+        let file_count = remote.add_to_vfs(&uo_settings.base_folder);
+        lg(&format!("{file_count} files on the UO file server."));
+    }
+    uo_settings.installation = UoInstallation::scan(&uo_settings.base_folder).wrap_err("Scan the UO folder")?;
+    log_installation(&uo_settings.installation);
+    let fingerprints = UoFileFingerprints::compute(&uo_settings.installation);
+    fingerprints.log();
+    fingerprints.check_last_session(&uo_settings.cache_folder);
+    load_map_definitions(&mut uo_settings, map_defs_file.as_deref())?;
+    let installation = &uo_settings.installation;
 
-    let map_plane_index = 0_u32;
-    lg(
-        &format!("Loading map plane {map_plane_index} structure (map{map_plane_index}.mul)...")
-            .as_str(),
-    );
-    let map_plane = map::MapPlane::init(
-        uo_path.join(&format!("map{map_plane_index}.mul")),
-        map_plane_index,
-    )
-    .expect(&format!("Error initializing map plane {map_plane_index}"));
-    let mut map_planes = DashMap::<u32, map::MapPlane>::new();
-    map_planes.insert(map_plane_index, map_plane);
+    progress.start(LoadingStep::MapIndex);
+    let (map_plane, statics_plane) = load_map_plane_files(&uo_settings, map_plane_index)
+        .wrap_err_with(|| format!("Error initializing map plane {map_plane_index}"))?;
+    let map_planes = DashMap::<u32, map::MapPlaneShared>::new();
+    map_planes.insert(map_plane_index, map::MapPlaneShared::new(map_plane));
+    let statics_planes = DashMap::<u32, statics::StaticsPlane>::new();
+    statics_planes.insert(map_plane_index, statics_plane);
+    progress.finish(LoadingStep::MapIndex);
+
+    // Only old clients ship verdata.mul.
+    let verdata: Option<Arc<verdata::Verdata>> = match &installation.verdata {
+        Some(verdata_path) => {
+            lg("Loading Verdata patches");
+            Some(Arc::new(verdata::Verdata::load(verdata_path.clone()).wrap_err("Load verdata")?))
+        }
+        None => None,
+    };
 
     lg("Loading Tiledata");
-    let tiledata = tiledata::TileData::load(uo_path.join("tiledata.mul")).expect("Load tiledata");
+    progress.start(LoadingStep::Tiledata);
+    let tiledata = installation
+        .tiledata()
+        .and_then(|tiledata_path| tiledata::TileData::load(tiledata_path.clone(), verdata.as_deref()))
+        .wrap_err("Load tiledata")?;
+    // Optional: used only to show the localized names.
+    let (cliloc, language) = load_cliloc(installation, &uo_settings.language);
+    progress.finish(LoadingStep::Tiledata);
+
+    lg("Loading Hues");
+    progress.start(LoadingStep::Hues);
+    let hues = installation
+        .hues()
+        .and_then(|hues_path| hues::Hues::load(hues_path.clone()))
+        .wrap_err("Load hues")?;
+    let radar_colors = installation
+        .radarcol()
+        .and_then(|radarcol_path| radarcol::RadarColors::load(radarcol_path.clone()))
+        .wrap_err("Load radarcol")?;
+    progress.finish(LoadingStep::Hues);
 
     lg("Loading Texmaps...");
-    let texmap_2d =
-        land_texture_2d::TexMap2D::load(uo_path.join("texmaps.mul"), uo_path.join("texidx.mul"))
-            .expect("Load texmap");
+    progress.start(LoadingStep::Texmap);
+    let texmap_2d = installation
+        .texmaps()
+        .and_then(|texmaps| {
+            if uo_settings.lazy_texmaps {
+                return land_texture_2d::TexMap2D::load_lazy(
+                    texmaps.data.clone(),
+                    texmaps.index.clone(),
+                    verdata.as_deref(),
+                );
+            }
+            let cache_key = fingerprints.key(
+                [texmaps.data.as_path(), texmaps.index.as_path()]
+                    .into_iter()
+                    .chain(installation.verdata.as_deref()),
+            );
+            disk_cache::load_texmaps(
+                texmaps,
+                verdata.as_deref(),
+                &uo_settings.cache_folder,
+                cache_key,
+                |decoded, total| {
+                    let fraction = decoded as f32 / total.max(1) as f32;
+                    progress.set(LoadingStep::Texmap, LoadingStepState::Running(Some(fraction)));
+                },
+            )
+        })
+        .wrap_err("Load texmap")?;
+    progress.finish(LoadingStep::Texmap);
+
+    lg("Indexing Art...");
+    progress.start(LoadingStep::Art);
+    let art = installation
+        .art()
+        .and_then(|art| art::Art::load(art.data.clone(), art.index.clone(), verdata.clone()))
+        .wrap_err("Load art")?;
+    progress.finish(LoadingStep::Art);
 
     lg("Done loading UO Data.");
+    Ok(LoadedUoData {
+        uo_settings,
+        map_planes,
+        statics_planes,
+        tiledata,
+        cliloc,
+        language,
+        hues,
+        radar_colors,
+        texmap_2d,
+        art,
+    })
+}
+
+/// Logs what the scan of the UO folder found.
+fn log_installation(installation: &UoInstallation) {
+    let map_planes: Vec<String> = installation
+        .map_planes
+        .iter()
+        .map(|(map_index, map_files)| {
+            let format = match map_files.format {
+                FileFormat::Mul => "mul",
+                FileFormat::Uop => "uop",
+            };
+            let patched = if map_files.diff.is_some() { ", patched" } else { "" };
+            format!("{map_index} ({format}{patched})")
+        })
+        .collect();
+    let statics_planes: Vec<&u32> = installation.statics_planes.keys().collect();
+    let clilocs: Vec<&String> = installation.clilocs.keys().collect();
+    let yes_no = |found: bool| if found { "yes" } else { "no" };
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::UoFiles,
+        &format!(
+            "UO files in {}: map planes [{}], statics {statics_planes:?}, clilocs {clilocs:?}, verdata: {}, {}: {}.",
+            installation.folder.display(),
+            map_planes.join(", "),
+            yes_no(installation.verdata.is_some()),
+            map_def::UOMAP_DEF_FILE_NAME,
+            yes_no(installation.map_defs.is_some()),
+        ),
+    );
+}
+
+/// Loads the cliloc of the given language, or the default one if missing. Returns it with the language of the file
+/// picked; the cliloc is empty if there's no file or it can't be parsed.
+fn load_cliloc(installation: &UoInstallation, language: &str) -> (cliloc::Cliloc, String) {
+    let lg_warn = |text: &str| logger::one(None, LogSev::Warn, LogAbout::UoFiles, text);
+
+    let (cliloc_path, loaded_language) = match installation.cliloc(language) {
+        Some(path) => (path, language),
+        None => {
+            let fallback = installation.cliloc(CLILOC_DEFAULT_LANGUAGE);
+            if fallback.is_some() && language != CLILOC_DEFAULT_LANGUAGE {
+                lg_warn(&format!(
+                    "No cliloc for language '{language}', using '{CLILOC_DEFAULT_LANGUAGE}'."
+                ));
+            }
+            match fallback {
+                Some(path) => (path, CLILOC_DEFAULT_LANGUAGE),
+                None => return (cliloc::Cliloc::default(), language.to_owned()),
+            }
+        }
+    };
+
+    logger::one(
+        None,
+        LogSev::Info,
+        LogAbout::UoFiles,
+        &format!("Loading Cliloc ({loaded_language})"),
+    );
+    match cliloc::Cliloc::load(cliloc_path.clone()) {
+        Ok(cliloc) => (cliloc, loaded_language.to_owned()),
+        Err(e) => {
+            lg_warn(&format!(
+                "Can't load {}, names will come from tiledata: {e}",
+                cliloc_path.to_string_lossy()
+            ));
+            (cliloc::Cliloc::default(), loaded_language.to_owned())
+        }
+    }
+}
+
+/// Once the loading task is done, makes the UO data available as resources and moves to the next loading phase.
+fn sys_finish_uo_data_loading(
+    mut commands: Commands,
+    task_r: Option<ResMut<UoDataLoadingTask>>,
+    settings: Res<Settings>,
+    mut next_phase: ResMut<NextState<LoadingPhase>>,
+) {
+    let Some(mut task_r) = task_r else {
+        return;
+    };
+    let Some(result) = block_on(poll_once(&mut task_r.0)) else {
+        return;
+    };
+    commands.remove_resource::<UoDataLoadingTask>();
+    let data = match result {
+        Ok(data) => data,
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::UoFiles,
+                &format!("Error loading UO data: {e:?}"),
+            );
+            commands.insert_resource(UoDataLoadingError {
+                folder: settings.uo_files.folder.clone(),
+                messages: e.chain().map(|cause| cause.to_string()).collect(),
+            });
+            return;
+        }
+    };
+
+    commands.insert_resource(UoInterfaceSettingsRes(Arc::new(data.uo_settings)));
+    commands.insert_resource(MapPlanesRes(Arc::new(data.map_planes)));
+    commands.insert_resource(StaticsPlanesRes(Arc::new(data.statics_planes)));
+    commands.insert_resource(TileDataRes(Arc::new(data.tiledata)));
+    commands.insert_resource(LocalizationRes {
+        cliloc: Arc::new(data.cliloc),
+        language: data.language,
+    });
+    commands.insert_resource(HuesRes(Arc::new(data.hues)));
+    commands.insert_resource(RadarColRes(Arc::new(data.radar_colors)));
+    commands.insert_resource(TexMap2DRes(Arc::new(data.texmap_2d)));
+    commands.insert_resource(ArtRes(Arc::new(data.art)));
+    next_phase.set(LoadingPhase::Textures);
+}
+
+/// Adds the custom map definitions of a uomap.def to the standard ones: the one in the settings, or else the one in
+///  the UO folder, if any.
+fn load_map_definitions(uo_settings: &mut UoInterfaceSettings, map_defs_file: Option<&Path>) -> eyre::Result<()> {
+    let Some(map_defs_file) = map_defs_file
+        .map(Path::to_owned)
+        .or_else(|| uo_settings.installation.map_defs.clone())
+    else {
+        return Ok(());
+    };
+    let custom_map_defs = map_def::MapDefinitions::load(&map_defs_file)
+        .wrap_err_with(|| format!("Loading the map definitions in {}", map_defs_file.display()))?;
+    logger::one(
+        None,
+        logger::LogSev::Info,
+        logger::LogAbout::UoFiles,
+        &format!(
+            "Loaded the definitions of map planes {:?} from {}.",
+            custom_map_defs.indices().collect::<Vec<_>>(),
+            map_defs_file.display()
+        ),
+    );
+    uo_settings.map_defs.extend(custom_map_defs);
+    Ok(())
+}
+
+/// Opens the map and statics files of a map plane. Blocks are read later, on request.
+pub fn load_map_plane_files(
+    uo_settings: &UoInterfaceSettings,
+    map_plane_index: u32,
+) -> eyre::Result<(map::MapPlane, statics::StaticsPlane)> {
+    let lg = |text: &str| logger::one(None, logger::LogSev::Info, logger::LogAbout::UoFiles, text);
+    let installation = &uo_settings.installation;
+
+    let map_plane = load_map_plane_file(
+        installation,
+        map_plane_index,
+        uo_settings.map_file_backend,
+        &uo_settings.map_defs,
+        None,
+    )?;
+
+    let statics_files = installation
+        .statics_plane(map_plane_index)
+        .wrap_err_with(|| format!("Initializing statics for map plane {map_plane_index}"))?;
+    lg(&format!(
+        "Loading statics for map plane {map_plane_index} ({}, {})...",
+        file_name(&statics_files.statics),
+        file_name(&statics_files.staidx)
+    ));
+    let mut statics_plane = statics::StaticsPlane::init(
+        statics_files.statics.clone(),
+        statics_files.staidx.clone(),
+        map_plane_index,
+        map_plane.size_blocks,
+    )
+    .wrap_err_with(|| format!("Initializing statics for map plane {map_plane_index}"))?;
+
+    if let Some((stadifl_path, stadifi_path, stadif_path)) = &statics_files.diff {
+        let patched_blocks = statics_plane
+            .load_diffs(stadifl_path.clone(), stadifi_path.clone(), stadif_path.clone())
+            .wrap_err_with(|| format!("Loading statics diffs for map plane {map_plane_index}"))?;
+        lg(&format!("Applied {patched_blocks} statics block patches ({}).", file_name(stadif_path)));
+    }
+
+    Ok((map_plane, statics_plane))
+}
+
+/// Loads the map file of a map plane (and its patches) from an installation: the UO folder, or another folder holding
+///  a different version of the map (see maps::compare).
+/// The map size is map_size, if given, else the one of map_defs matching the file size.
+pub fn load_map_plane_file(
+    installation: &UoInstallation,
+    map_plane_index: u32,
+    file_backend: map::FileBackend,
+    map_defs: &map_def::MapDefinitions,
+    map_size: Option<map::MapSizeCells>,
+) -> eyre::Result<map::MapPlane> {
+    let lg = |text: &str| logger::one(None, logger::LogSev::Info, logger::LogAbout::UoFiles, text);
+
+    let map_files = installation
+        .map_plane(map_plane_index)
+        .wrap_err_with(|| format!("Initializing map plane {map_plane_index}"))?;
+    lg(&format!(
+        "Loading map plane {map_plane_index} structure ({})...",
+        file_name(&map_files.map)
+    ));
+    let map_file_path = map_files.map.clone();
+    let mut map_plane = match map_size {
+        Some(map_size) => map::MapPlane::init_with_size(map_file_path, map_plane_index, file_backend, map_size),
+        None => map::MapPlane::init(map_file_path, map_plane_index, file_backend, map_defs),
+    }
+    .wrap_err_with(|| format!("Initializing map plane {map_plane_index}"))?;
+
+    // Patch files, shipped only by old clients.
+    if let Some((mapdifl_path, mapdif_path)) = &map_files.diff {
+        let patched_blocks = map_plane
+            .load_diffs(mapdifl_path.clone(), mapdif_path.clone())
+            .wrap_err_with(|| format!("Loading map plane {map_plane_index} diffs"))?;
+        lg(&format!("Applied {patched_blocks} map block patches ({}).", file_name(mapdif_path)));
+    }
+    Ok(map_plane)
+}
 
-    commands.insert_resource(UoInterfaceSettingsRes(Arc::new(UoInterfaceSettings {
-        base_folder: uo_path,
-    })));
-    commands.insert_resource(MapPlanesRes(Arc::new(map_planes)));
-    commands.insert_resource(TileDataRes(Arc::new(tiledata)));
-    commands.insert_resource(TexMap2DRes(Arc::new(texmap_2d)));
+fn file_name(path: &Path) -> Cow<'_, str> {
+    path.file_name().unwrap_or(path.as_os_str()).to_string_lossy()
 }
@@ -1,8 +1,18 @@
+pub mod annotations;
+pub mod asset_files;
+pub mod bookmarks;
 pub mod settings;
+pub mod settings_writeback;
 pub mod shader_presets;
+pub mod shard_files;
+pub mod shard_regions;
+pub mod shard_spawns;
 
 use crate::{
-    external_data::{settings::SettingsPlugin, shader_presets::ShaderPresetsPlugin},
+    external_data::{
+        bookmarks::BookmarksPlugin, settings::SettingsPlugin, settings_writeback::SettingsWritebackPlugin,
+        shader_presets::ShaderPresetsPlugin,
+    },
     impl_tracked_plugin,
     util_lib::tracked_plugin::*,
 };
@@ -24,6 +34,12 @@ fn build(&self, app: &mut App) {
             ShaderPresetsPlugin {
                 registered_by: "ExternalDataPlugin",
             },
+            SettingsWritebackPlugin {
+                registered_by: "ExternalDataPlugin",
+            },
+            BookmarksPlugin {
+                registered_by: "ExternalDataPlugin",
+            },
         ));
     }
 }
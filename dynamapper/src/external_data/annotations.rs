@@ -0,0 +1,172 @@
+// Annotations: markers, pins and notes placed on a map by its reviewers, to point each other at locations.
+// - One file per map plane in the assets folder (annotations_map0.json...), so that the annotations of a map can be
+//   shared on their own. A missing file means no annotations yet. The file is rewritten on every change.
+// - Only the annotations of the map plane shown are loaded (see render::overlays::annotations), and drawn over the
+//   terrain. They're placed, listed and removed in the Annotations window (render::annotations_ui).
+//
+
+use crate::{core::render::scene::player::TeleportPlayerEvent, external_data::asset_files, prelude::*};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(strum_macros::AsRefStr, Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationKind {
+    /// A pin with a name next to it.
+    Marker,
+    /// A colored pin: the name is only shown in the list.
+    Pin,
+    /// A pin with a box of free text.
+    Note,
+}
+impl AnnotationKind {
+    pub const ALL: [AnnotationKind; 3] = [AnnotationKind::Marker, AnnotationKind::Pin, AnnotationKind::Note];
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub kind: AnnotationKind,
+    /// Name of a marker or a pin, text of a note (can be multiline).
+    pub text: String,
+    pub x: u16,
+    pub y: u16,
+    pub z: i8,
+    /// sRGB.
+    pub color: [u8; 3],
+}
+impl Annotation {
+    pub fn teleport_event(&self, map_id: u32) -> TeleportPlayerEvent {
+        TeleportPlayerEvent {
+            x: self.x,
+            y: self.y,
+            z: Some(self.z),
+            map_id,
+            from_history: false,
+        }
+    }
+}
+
+/// Contents of an annotations file.
+#[derive(Serialize, Deserialize)]
+struct AnnotationsFile {
+    map: u32,
+    #[serde(default)]
+    annotations: Vec<Annotation>,
+}
+
+/// The annotations of a map plane.
+#[derive(Resource, Debug)]
+pub struct Annotations {
+    /// Map plane of the list. None until loaded.
+    pub map_id: Option<u32>,
+    pub list: Vec<Annotation>,
+    pub visible: bool,
+    /// The file couldn't be read: don't overwrite it with what we have.
+    read_only: bool,
+}
+impl Default for Annotations {
+    fn default() -> Self {
+        Self {
+            map_id: None,
+            list: Vec::new(),
+            visible: true,
+            read_only: false,
+        }
+    }
+}
+impl Annotations {
+    pub fn file_name(map_id: u32) -> String {
+        format!("annotations_map{map_id}.json")
+    }
+
+    /// Replaces the list with the annotations of another map plane.
+    pub fn load_map(&mut self, map_id: u32) {
+        self.map_id = Some(map_id);
+        (self.list, self.read_only) = match load_from_file(map_id) {
+            Some(list) => (list, false),
+            None => (Vec::new(), true),
+        };
+    }
+
+    pub fn add(&mut self, annotation: Annotation) {
+        self.list.push(annotation);
+        self.save_to_file();
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.list.len() {
+            self.list.remove(index);
+            self.save_to_file();
+        }
+    }
+
+    fn save_to_file(&self) {
+        let Some(map_id) = self.map_id else {
+            return;
+        };
+        let file_name = Self::file_name(map_id);
+        if self.read_only {
+            logger::one(
+                None,
+                LogSev::Warn,
+                LogAbout::General,
+                &format!("Not saving the annotations: {file_name} couldn't be read."),
+            );
+            return;
+        }
+        let file = AnnotationsFile {
+            map: map_id,
+            annotations: self.list.clone(),
+        };
+        let result = serde_json::to_string_pretty(&file)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| asset_files::write(&file_name, contents).map_err(anyhow::Error::from));
+        if let Err(e) = result {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::General,
+                &format!("Failed to save the annotations to {file_name}: {e}"),
+            );
+        }
+    }
+}
+
+/// The annotations of a map plane, or None if its file can't be read. No file means no annotations yet.
+fn load_from_file(map_id: u32) -> Option<Vec<Annotation>> {
+    let file_name = Annotations::file_name(map_id);
+    let contents = match asset_files::read_to_string(&file_name) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Some(Vec::new()),
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::General,
+                &format!("Failed to read {file_name}: {e}"),
+            );
+            return None;
+        }
+    };
+    match serde_json::from_str::<AnnotationsFile>(&contents) {
+        Ok(file) if file.map == map_id => Some(file.annotations),
+        Ok(file) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::General,
+                &format!("{file_name} holds the annotations of map {}, not {map_id}.", file.map),
+            );
+            None
+        }
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::General,
+                &format!("Failed to parse {file_name}: {e}"),
+            );
+            None
+        }
+    }
+}
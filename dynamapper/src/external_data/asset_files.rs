@@ -0,0 +1,38 @@
+// Files of the assets folder read and written directly (settings.toml, shader_presets.toml, bookmarks.toml,
+//  annotations_map*.json): they're needed before the asset server is up, and written back by the app.
+// In the browser (wasm32) there's no file system: the copies of the files embedded at build time are read, and
+//  writing fails (the callers log it, like any other write error).
+
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn file_path(file_name: &str) -> PathBuf {
+    PathBuf::from(crate::core::constants::ASSET_FOLDER.to_string() + file_name)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_to_string(file_name: &str) -> io::Result<String> {
+    std::fs::read_to_string(file_path(file_name))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write(file_name: &str, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    std::fs::write(file_path(file_name), contents)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn read_to_string(file_name: &str) -> io::Result<String> {
+    let contents = match file_name {
+        "settings.toml" => include_str!("../../../assets/settings.toml"),
+        "shader_presets.toml" => include_str!("../../../assets/shader_presets.toml"),
+        _ => return Err(io::ErrorKind::NotFound.into()),
+    };
+    Ok(contents.to_owned())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write(_file_name: &str, _contents: impl AsRef<[u8]>) -> io::Result<()> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "no file system in the browser"))
+}
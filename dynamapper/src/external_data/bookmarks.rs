@@ -0,0 +1,124 @@
+// Bookmarks: named locations (map plane included), to go back quickly to the points of interest of a map review.
+// - Stored in assets/bookmarks.toml, as a list of [[bookmark]] tables. The file is optional, and rewritten on every
+//   change: comments in it aren't kept.
+// - Listed, added, removed and jumped to in the Go to window (render::go_to_ui). The first ones can be jumped to with
+//   a key too (InputAction::GO_TO_BOOKMARK).
+//
+
+use crate::{core::render::scene::player::TeleportPlayerEvent, external_data::asset_files, prelude::*};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+const BOOKMARKS_FILE_NAME: &str = "bookmarks.toml";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub name: String,
+    pub x: u16,
+    pub y: u16,
+    pub z: i8,
+    pub map: u8,
+}
+impl Bookmark {
+    pub fn teleport_event(&self) -> TeleportPlayerEvent {
+        TeleportPlayerEvent {
+            x: self.x,
+            y: self.y,
+            z: Some(self.z),
+            map_id: self.map as u32,
+            from_history: false,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct Bookmarks {
+    #[serde(default, rename = "bookmark")]
+    pub list: Vec<Bookmark>,
+    /// The file couldn't be parsed: don't overwrite it with what we have.
+    #[serde(skip)]
+    read_only: bool,
+}
+impl Bookmarks {
+    pub fn add(&mut self, bookmark: Bookmark) {
+        self.list.push(bookmark);
+        self.save_to_file();
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.list.len() {
+            self.list.remove(index);
+            self.save_to_file();
+        }
+    }
+
+    fn save_to_file(&self) {
+        if self.read_only {
+            logger::one(
+                None,
+                LogSev::Warn,
+                LogAbout::General,
+                &format!("Not saving the bookmarks: {BOOKMARKS_FILE_NAME} couldn't be read at startup."),
+            );
+            return;
+        }
+        let result = toml::to_string_pretty(self)
+            .map_err(anyhow::Error::from)
+            .and_then(|contents| asset_files::write(BOOKMARKS_FILE_NAME, contents).map_err(anyhow::Error::from));
+        if let Err(e) = result {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::General,
+                &format!("Failed to save the bookmarks to {BOOKMARKS_FILE_NAME}: {e}"),
+            );
+        }
+    }
+}
+
+pub struct BookmarksPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(BookmarksPlugin);
+
+impl Plugin for BookmarksPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.insert_resource(load_from_file());
+    }
+}
+
+/// No file means no bookmarks yet.
+pub fn load_from_file() -> Bookmarks {
+    let contents = match asset_files::read_to_string(BOOKMARKS_FILE_NAME) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Bookmarks::default(),
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::General,
+                &format!("Failed to read {BOOKMARKS_FILE_NAME}: {e}"),
+            );
+            return Bookmarks {
+                read_only: true,
+                ..default()
+            };
+        }
+    };
+    match toml::from_str(&contents) {
+        Ok(bookmarks) => bookmarks,
+        Err(e) => {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::General,
+                &format!("Failed to parse {BOOKMARKS_FILE_NAME}: {}", e.message()),
+            );
+            Bookmarks {
+                read_only: true,
+                ..default()
+            }
+        }
+    }
+}
@@ -1,11 +1,14 @@
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 
 use crate::prelude::*;
-use crate::core::render::scene::camera::RenderZoom;
+use crate::core::render::scene::camera::{RenderZoom, ZoomTarget};
+use crate::core::system_sets::StartupSysSet;
+use crate::external_data::asset_files;
 use crate::logger::{self, LogAbout, LogSev};
 use crate::util_lib::uo_coords::*;
 use bevy::{
-    //asset::{AssetLoader, LoadContext, io::Reader},
+    asset::{AssetLoader, LoadContext, io::Reader},
     pbr::wireframe::WireframeConfig,
     prelude::*,
     window::WindowResolution
@@ -14,41 +17,344 @@
 
 const CONFIG_FILE_NAME: &'static str = "settings.toml";
 
-#[derive(Asset, Clone, Debug, Deserialize, Resource, TypePath)]
+#[derive(Asset, Clone, Debug, Deserialize, PartialEq, Resource, TypePath)]
 pub struct Settings {
     pub uo_files: SectUoFiles,
     pub input: SectInput,
     pub window: SectWindow,
+    #[serde(default)]
+    pub frame_rate: SectFrameRate,
     pub world: SectWorld,
+    #[serde(default)]
+    pub render: SectRender,
+    #[serde(default)]
+    pub day_night: SectDayNight,
+    #[serde(default)]
+    pub editor: SectEditor,
+    #[serde(default)]
+    pub live_shard: SectLiveShard,
+    #[serde(default)]
+    pub shard_overlays: SectShardOverlays,
     pub debug: SectDebug,
-    // pub logger: Option<Logger>, // For the commented section
+    #[serde(default)]
+    pub log: SectLog,
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct SectUoFiles {
     pub folder: String, // or PathBuf for extra fanciness
+    // Memory-map the map files instead of reading the blocks from them.
+    #[serde(default)]
+    pub mmap_map_files: bool,
+    // Cliloc language, as the file extension: enu, deu, chs, ... (Cliloc.enu is used if the file is missing).
+    #[serde(default = "SectUoFiles::default_language")]
+    pub language: String,
+    // Folder with another version of the map files (map*.mul), compared with the ones of the UO folder in the Map
+    //  Compare window. Empty if none.
+    #[serde(default)]
+    pub compare_folder: String,
+    // uomap.def with the sizes of custom map planes (resized maps, map6 and beyond). Empty: the uomap.def in the UO
+    //  folder, if any.
+    #[serde(default)]
+    pub map_definitions: String,
+    // "host:port" of a UO file server (uocf example uo_file_server) to read the UO files from, instead of folder. Empty:
+    //  the local folder. Not in the browser.
+    #[serde(default)]
+    pub server: String,
+    // Decode each land texture of texmaps.mul the first time it's drawn, instead of all of them while loading.
+    #[serde(default)]
+    pub lazy_texmaps: bool,
+    // Folder of the caches built from the UO files, like the decoded land textures. Empty: no caches.
+    #[serde(default = "SectUoFiles::default_cache_folder")]
+    pub cache_folder: String,
+}
+impl SectUoFiles {
+    fn default_language() -> String {
+        "enu".to_owned()
+    }
+    fn default_cache_folder() -> String {
+        "cache".to_owned()
+    }
+
+    /// Where the UO files are read from, for the messages: the folder, or the file server.
+    pub fn source_label(&self) -> String {
+        if self.server.is_empty() {
+            self.folder.clone()
+        } else {
+            format!("the file server {}", self.server)
+        }
+    }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct SectInput {
     pub movement_speed_multiplier: f32,
+    // Panning speed of the free camera, in tiles per second at zoom 1.
+    #[serde(default = "SectInput::default_free_camera_speed")]
+    pub free_camera_speed: f32,
+    // Input action name -> key name (e.g. move_north = "W"). Missing actions use their default key.
+    #[serde(default)]
+    pub key_bindings: BTreeMap<String, String>,
+    // Input action name -> gamepad button name (e.g. move_north = "DPadUp", or "None"). Missing actions use their
+    //  default button.
+    #[serde(default)]
+    pub gamepad_bindings: BTreeMap<String, String>,
+}
+impl SectInput {
+    fn default_free_camera_speed() -> f32 {
+        40.0
+    }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct SectWindow {
     pub height: f32,
     pub width: f32,
     pub zoom: f32,
+    // The mouse wheel zooms at the cursor (the world point under it stays there) instead of the view center.
+    #[serde(default = "SectWindow::default_zoom_to_cursor")]
+    pub zoom_to_cursor: bool,
+    // How fast the zoom reaches the wanted one (exponential rate, per second). 0 = instantly.
+    #[serde(default = "SectWindow::default_zoom_smoothing")]
+    pub zoom_smoothing: f32,
+    // Write the window size, zoom and shader preset in use back to this file on exit.
+    #[serde(default = "SectWindow::default_save_on_exit")]
+    pub save_on_exit: bool,
+}
+impl SectWindow {
+    fn default_zoom_to_cursor() -> bool {
+        true
+    }
+    fn default_zoom_smoothing() -> f32 {
+        12.0
+    }
+    fn default_save_on_exit() -> bool {
+        true
+    }
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SectFrameRate {
+    // Max frames per second. 0 = the monitor refresh rate, -1 = no limit.
+    pub fps_limit: f32,
+    // Wait for the monitor refresh to show a frame (no tearing).
+    pub vsync: bool,
+    // Update at background_fps, and only on window events, while the window isn't focused.
+    pub background_throttle: bool,
+    pub background_fps: f32,
+    // Stop the world (walking, animations, day/night cycle, chunk streaming) while the window isn't focused.
+    pub pause_in_background: bool,
+}
+impl Default for SectFrameRate {
+    fn default() -> Self {
+        Self {
+            fps_limit: 0.0,
+            vsync: true,
+            background_throttle: true,
+            background_fps: 30.0,
+            pause_in_background: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct SectWorld {
     pub start_p: UOVec4, //[i32; 4], // or [f32;4].
 }
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SectRender {
+    // Max distance (in chunks) of a drawn chunk from the player chunk, even if the window is bigger.
+    pub draw_distance_chunks: u32,
+    // Extra chunks drawn on each side of the visible area, so that they're ready before scrolling into view.
+    pub chunk_padding: u32,
+    // Tiles per land chunk row/column: 8, 16 or 32. Read only at startup.
+    pub chunk_size_tiles: u32,
+    // Terrain shader preset applied at startup, as "<mode>.<preset>" (see shader_presets.toml).
+    pub shader_preset: String,
+    // Memory budget (MB) for the resident land textures and their normal maps: over it, the least recently used
+    //  ones are evicted. 0 = none.
+    pub land_texture_budget_mb: u32,
+    // Zoomed out, land chunks use coarser meshes whose quads are at most this size on screen (pixels). 0 = full detail.
+    pub lod_max_quad_pixels: f32,
+    // Zoom where the far view (a radar colors map of the whole plane) replaces the land chunks of the main view. It
+    //  fades in from 70% of it, and allows zooming out past the chunk zoom limit. 0 = no far view.
+    pub far_view_zoom: f32,
+    // Land chunks share a single material, with their data in storage buffers, so that they're drawn in a few batches.
+    //  Off, or without GPU support for it, every chunk has its own material. Read when entering the game.
+    pub land_batching: bool,
+    // Filtering of the land textures. Read only at startup.
+    pub land_texture_filtering: LandTextureFiltering,
+    // Max anisotropy with the anisotropic filtering: 1..16.
+    pub land_texture_anisotropy: u16,
+    // How the main view draws the land: the 3D meshes, or the classic 2D art sprites. Read only at startup.
+    pub render_path: RenderPath,
+}
+impl Default for SectRender {
+    fn default() -> Self {
+        Self {
+            draw_distance_chunks: crate::core::constants::RENDER_DISTANCE_FROM_PLAYER,
+            chunk_padding: crate::core::constants::RENDER_CHUNK_PADDING,
+            chunk_size_tiles: crate::core::render::scene::world::land::DEFAULT_TILE_NUM_PER_CHUNK_DIM,
+            shader_preset: "classic.morning".to_owned(),
+            land_texture_budget_mb: 0,
+            lod_max_quad_pixels: 32.0,
+            far_view_zoom: 6.0,
+            land_batching: true,
+            land_texture_filtering: LandTextureFiltering::Nearest,
+            land_texture_anisotropy: 8,
+            render_path: RenderPath::Mesh3d,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum LandTextureFiltering {
+    /// The classic look: sharp texels up close, but shimmering when zoomed out.
+    #[default]
+    Nearest,
+    /// Mipmaps (generated when a texture is loaded), blended between levels. Texels stay sharp up close.
+    Trilinear,
+    /// Mipmaps, and sharper at grazing angles. Texels are smoothed up close too.
+    Anisotropic,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RenderPath {
+    /// Land chunk meshes, textured and lit by the land shader.
+    #[default]
+    Mesh3d,
+    /// The land art (art.mul) drawn on a 2D canvas, like the classic client (see scene::classic_2d).
+    Classic2d,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SectDayNight {
+    // Drive the lighting with the world clock at startup.
+    pub enabled: bool,
+    // Real seconds for a full in-game day.
+    pub day_length_secs: f32,
+    // In-game hour (0-24) at startup.
+    pub start_hour: f32,
+}
+impl Default for SectDayNight {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            day_length_secs: 24.0 * 60.0,
+            start_hour: 9.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SectEditor {
+    // Map edits kept in the undo history: the oldest ones are dropped. 0 disables undo.
+    pub history_size: usize,
+}
+impl Default for SectEditor {
+    fn default() -> Self {
+        Self { history_size: 100 }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SectLiveShard {
+    // "host:port" of the live data feed of the shard (see core::live_world::shard_link). Empty: none.
+    pub server: String,
+    // Connect when entering the game. Otherwise, from the Live Shard window.
+    pub connect_at_startup: bool,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SectShardOverlays {
+    // Regions of the shard (a Sphere map script or a ServUO Regions.xml, see external_data::shard_regions), drawn over
+    //  the map from when the game starts. Empty: none, until loaded in the Shard Overlays window.
+    pub regions_file: String,
+    // Spawns of the shard (a Sphere world save or an XmlSpawner file, see external_data::shard_spawns), likewise.
+    pub spawns_file: String,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
 pub struct SectDebug {
     pub map_render_wireframe: bool,
+    // Record the CPU/GPU time of the render passes (Bevy's RenderDiagnosticsPlugin), shown in the diagnostics overlay.
+    //  Read only at startup.
+    #[serde(default)]
+    pub gpu_timings: bool,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+pub struct SectLog {
+    // Lowest severity of the shown messages (see logger::LogSev).
+    #[serde(default = "SectLog::default_min_severity")]
+    pub min_severity: String,
+    // Also write the log to a file in file_folder, rotated when it reaches file_max_size_kb.
+    #[serde(default)]
+    pub file_enabled: bool,
+    #[serde(default = "SectLog::default_file_folder")]
+    pub file_folder: String,
+    #[serde(default = "SectLog::default_file_max_size_kb")]
+    pub file_max_size_kb: u64,
+    // Rotated files kept, besides the current one.
+    #[serde(default = "SectLog::default_file_max_rotated")]
+    pub file_max_rotated: u32,
+    // LogAbout name -> lowest severity of the shown messages about it, overriding min_severity.
+    #[serde(flatten)]
+    pub min_severity_by_about: BTreeMap<String, String>,
+}
+impl SectLog {
+    fn default_min_severity() -> String {
+        "DebugVerbose".to_owned()
+    }
+    fn default_file_folder() -> String {
+        "logs".to_owned()
+    }
+    fn default_file_max_size_kb() -> u64 {
+        1024
+    }
+    fn default_file_max_rotated() -> u32 {
+        5
+    }
+
+    /// Sets the logger filters and the file output from this section.
+    pub fn apply(&self) {
+        logger::configure_filters(&self.min_severity, &self.min_severity_by_about);
+
+        let file_config = self.file_enabled.then(|| logger::file_output::LogFileConfig {
+            folder: PathBuf::from(&self.file_folder),
+            max_size_bytes: self.file_max_size_kb.max(1) * 1024,
+            max_rotated_files: self.file_max_rotated,
+        });
+        if let Err(e) = logger::file_output::configure(file_config) {
+            logger::one(
+                None,
+                LogSev::Error,
+                LogAbout::General,
+                &format!("Can't open the log file in '{}': {e}", self.file_folder),
+            );
+        }
+    }
+}
+impl Default for SectLog {
+    fn default() -> Self {
+        Self {
+            min_severity: Self::default_min_severity(),
+            file_enabled: false,
+            file_folder: Self::default_file_folder(),
+            file_max_size_kb: Self::default_file_max_size_kb(),
+            file_max_rotated: Self::default_file_max_rotated(),
+            min_severity_by_about: BTreeMap::new(),
+        }
+    }
 }
 
 // ----
@@ -56,19 +362,121 @@ pub struct SectDebug {
 #[derive(Event)]
 pub struct ToggleWireframe;
 
+// Sent when settings.toml is hot reloaded, for each group of values that changed.
+
+#[derive(Event)]
+pub struct WindowSizeSettingsChangedEvent;
+
+#[derive(Event)]
+pub struct ZoomSettingsChangedEvent;
+
+/// Draw distance and chunk padding.
+#[derive(Event)]
+pub struct RenderDistanceSettingsChangedEvent;
+
+#[derive(Event)]
+pub struct WireframeSettingsChangedEvent;
+
+#[derive(Event)]
+pub struct KeyBindingsSettingsChangedEvent;
+
+/// Also sent by the Frame Rate window.
+#[derive(Event)]
+pub struct FrameRateSettingsChangedEvent;
+
 // ----
 
 pub fn load_from_file() -> Settings {
-    let settings_with_rel_path: PathBuf =
-        PathBuf::from(crate::core::constants::ASSET_FOLDER.to_string() + CONFIG_FILE_NAME);
-
-    let contents =
-        std::fs::read_to_string(&settings_with_rel_path).expect("Failed to read settings file");
+    let contents = asset_files::read_to_string(CONFIG_FILE_NAME).expect("Failed to read settings file");
     let settings: Settings = toml::from_str(&contents).expect("Failed to parse settings TOML");
 
     settings
 }
 
+/// Edits settings.toml in place: what isn't touched by `edit` (comments included) stays as it is.
+pub fn edit_settings_file(
+    edit: impl FnOnce(&mut toml_edit::DocumentMut) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let contents = asset_files::read_to_string(CONFIG_FILE_NAME)?;
+    let mut doc: toml_edit::DocumentMut = contents.parse()?;
+    edit(&mut doc)?;
+    asset_files::write(CONFIG_FILE_NAME, doc.to_string())?;
+    Ok(())
+}
+
+/// Table of settings.toml with the given (dotted) name, e.g. "input.key_bindings". Created if missing.
+pub fn settings_file_section<'a>(
+    doc: &'a mut toml_edit::DocumentMut,
+    name: &str,
+) -> anyhow::Result<&'a mut toml_edit::Table> {
+    let mut table = doc.as_table_mut();
+    for part in name.split('.') {
+        table = table
+            .entry(part)
+            .or_insert_with(toml_edit::table)
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("[{name}] in {CONFIG_FILE_NAME} isn't a table"))?;
+    }
+    Ok(table)
+}
+
+/// Sets a value in a table of settings.toml, keeping the formatting and the comment of the value it replaces.
+pub fn set_settings_file_value(table: &mut toml_edit::Table, key: &str, value: impl Into<toml_edit::Value>) {
+    let mut value = value.into();
+    match table.get_mut(key).and_then(|item| item.as_value_mut()) {
+        Some(old) => {
+            *value.decor_mut() = old.decor().clone();
+            *old = value;
+        }
+        None => {
+            table.insert(key, toml_edit::Item::Value(value));
+        }
+    }
+}
+
+/// Writes the [input.key_bindings] and [input.gamepad_bindings] tables to settings.toml.
+pub fn save_key_bindings(
+    key_bindings: &BTreeMap<String, String>,
+    gamepad_bindings: &BTreeMap<String, String>,
+) -> anyhow::Result<()> {
+    edit_settings_file(|doc| {
+        for (name, bindings) in [
+            ("input.key_bindings", key_bindings),
+            ("input.gamepad_bindings", gamepad_bindings),
+        ] {
+            // Updated in place, so that the table keeps its position and formatting in the file.
+            let table = settings_file_section(doc, name)?;
+            table.retain(|action, _| bindings.contains_key(action));
+            for (action, value) in bindings {
+                set_settings_file_value(table, action, value.as_str());
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Writes the [frame_rate] section to settings.toml.
+pub fn save_frame_rate(frame_rate: &SectFrameRate) -> anyhow::Result<()> {
+    edit_settings_file(|doc| {
+        let table = settings_file_section(doc, "frame_rate")?;
+        set_settings_file_value(table, "fps_limit", f64::from(frame_rate.fps_limit));
+        set_settings_file_value(table, "vsync", frame_rate.vsync);
+        set_settings_file_value(table, "background_throttle", frame_rate.background_throttle);
+        set_settings_file_value(table, "background_fps", f64::from(frame_rate.background_fps));
+        set_settings_file_value(table, "pause_in_background", frame_rate.pause_in_background);
+        Ok(())
+    })
+}
+
+/// Writes uo_files.folder to settings.toml.
+pub fn save_uo_folder(folder: &str) -> anyhow::Result<()> {
+    edit_settings_file(|doc| {
+        let table = settings_file_section(doc, "uo_files")?;
+        set_settings_file_value(table, "folder", folder);
+        Ok(())
+    })
+}
+
 // ----
 
 pub struct SettingsPlugin {
@@ -79,11 +487,28 @@ impl Plugin for SettingsPlugin {
     fn build(&self, app: &mut App) {
         log_plugin_build(self);
         app.init_asset::<Settings>()
-            //.init_asset::<SettingsAsset>()
-            //.init_asset_loader::<SettingsAssetLoader>() // Register custom loader
+            .init_asset_loader::<SettingsAssetLoader>()
             .add_event::<ToggleWireframe>()
+            .add_event::<WindowSizeSettingsChangedEvent>()
+            .add_event::<ZoomSettingsChangedEvent>()
+            .add_event::<RenderDistanceSettingsChangedEvent>()
+            .add_event::<WireframeSettingsChangedEvent>()
+            .add_event::<KeyBindingsSettingsChangedEvent>()
+            .add_event::<FrameRateSettingsChangedEvent>()
             .add_systems(PreStartup, sys_startup_load_file)
-            .add_systems(Startup, sys_apply)
+            .add_systems(Startup, (sys_apply, sys_settings_watcher_loader))
+            .add_systems(
+                Update,
+                (
+                    sys_settings_reloaded,
+                    (
+                        sys_evlisten_apply_window_size,
+                        sys_evlisten_apply_zoom,
+                        sys_evlisten_apply_wireframe,
+                    ),
+                )
+                    .chain(),
+            )
             .add_systems(Update, sys_evlisten_switch_wireframe)
             ;
     }
@@ -104,20 +529,53 @@ fn sys_apply(
     settings_res: Res<Settings>,
     mut windows_q: Query<&mut Window>,
     mut zoom_res: ResMut<RenderZoom>,
+    mut zoom_target_res: ResMut<ZoomTarget>,
 ){
     let mut w = windows_q.single_mut().unwrap();
     w.resolution = WindowResolution::new(settings_res.window.width, settings_res.window.height);
 
+    // No smooth zoom at startup.
     zoom_res.write_val(settings_res.window.zoom);
+    zoom_target_res.write_val(settings_res.window.zoom, None);
 }
 
-// ----
+fn sys_evlisten_apply_window_size(
+    mut events: EventReader<WindowSizeSettingsChangedEvent>,
+    settings_res: Res<Settings>,
+    mut windows_q: Query<&mut Window>,
+) {
+    if events.read().last().is_none() {
+        return;
+    }
+    if let Ok(mut w) = windows_q.single_mut() {
+        w.resolution.set(settings_res.window.width, settings_res.window.height);
+    }
+}
 
-/*
+fn sys_evlisten_apply_zoom(
+    mut events: EventReader<ZoomSettingsChangedEvent>,
+    settings_res: Res<Settings>,
+    mut zoom_target_res: ResMut<ZoomTarget>,
+) {
+    if events.read().last().is_some() {
+        zoom_target_res.write_val(settings_res.window.zoom, None);
+    }
+}
 
-// Wrappers
-#[derive(Asset, TypePath, Debug, Clone)]
-pub struct SettingsAsset(pub Settings);
+fn sys_evlisten_apply_wireframe(
+    mut events: EventReader<WireframeSettingsChangedEvent>,
+    settings_res: Res<Settings>,
+    mut config: ResMut<WireframeConfig>,
+) {
+    if events.read().last().is_some() {
+        config.global = settings_res.debug.map_render_wireframe;
+    }
+}
+
+// ----
+
+// Hot reload: settings.toml is loaded again as an asset, so that the asset server watches it for changes.
+// The initial values come from load_from_file, which is needed before the asset server is up.
 
 #[derive(Resource, Clone)]
 pub struct SettingsHandle(pub Handle<Settings>);
@@ -126,9 +584,8 @@ fn sys_settings_watcher_loader(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
 ) {
-    // TODO: disable hot reloading for now. We would need every system to fetch the updated settings and react.
-
-    // Ttrack for changes and update it asynchronously.
+    log_system_add_startup::<SettingsPlugin>(StartupSysSet::First, fname!());
+    // Track for changes and update it asynchronously.
     let handle: Handle<Settings> = asset_server.load(CONFIG_FILE_NAME);
     commands.insert_resource(SettingsHandle(handle));
 }
@@ -159,32 +616,85 @@ fn extensions(&self) -> &[&str] {
     }
 }
 
+/// Replaces the Settings resource with the reloaded file, and tells which values changed.
+/// Values read only at startup keep working with the old value, until the app is restarted.
 fn sys_settings_reloaded(
-    mut commands: Commands,
     mut events: EventReader<AssetEvent<Settings>>,
-    handles: Res<SettingsHandle>,
+    handle_res: Option<Res<SettingsHandle>>,
     assets: Res<Assets<Settings>>,
+    mut settings_res: ResMut<Settings>,
+    mut window_size_writer: EventWriter<WindowSizeSettingsChangedEvent>,
+    mut zoom_writer: EventWriter<ZoomSettingsChangedEvent>,
+    mut render_distance_writer: EventWriter<RenderDistanceSettingsChangedEvent>,
+    mut wireframe_writer: EventWriter<WireframeSettingsChangedEvent>,
+    mut key_bindings_writer: EventWriter<KeyBindingsSettingsChangedEvent>,
+    mut frame_rate_writer: EventWriter<FrameRateSettingsChangedEvent>,
 ) {
-    for event in events.read() {
-        match event {
-            AssetEvent::LoadedWithDependencies { id } if id == &handles.0.id() => {
-                if let Some(settings) = assets.get(&handles.0) {
-                    println!("Settings loaded (or hot reloaded): {settings:#?}");
-                    commands.insert_resource(settings.clone());
-                }
-            }
-            AssetEvent::Modified { id } if id == &handles.0.id() => {
-                if let Some(settings) = assets.get(&handles.0) {
-                    println!("Settings hot reloaded: {settings:#?}");
-                    commands.insert_resource(settings.clone());
-                }
-            }
-            _ => {}
-        }
+    let Some(handle_res) = handle_res else {
+        return;
+    };
+    // The first load (AssetEvent::LoadedWithDependencies) reads the same values we already have.
+    let modified = events
+        .read()
+        .any(|event| matches!(event, AssetEvent::Modified { id } if *id == handle_res.0.id()));
+    if !modified {
+        return;
     }
-}
+    let Some(new) = assets.get(&handle_res.0) else {
+        return;
+    };
+    if *new == *settings_res {
+        return;
+    }
+    let old = settings_res.clone();
+    *settings_res = new.clone();
+    logger::one(None, LogSev::Info, LogAbout::General, "Reloaded settings file.");
 
-*/
+    if (old.window.width, old.window.height) != (new.window.width, new.window.height) {
+        window_size_writer.write(WindowSizeSettingsChangedEvent);
+    }
+    if old.window.zoom != new.window.zoom {
+        zoom_writer.write(ZoomSettingsChangedEvent);
+    }
+    if (old.render.draw_distance_chunks, old.render.chunk_padding)
+        != (new.render.draw_distance_chunks, new.render.chunk_padding)
+    {
+        render_distance_writer.write(RenderDistanceSettingsChangedEvent);
+    }
+    if old.log != new.log {
+        new.log.apply();
+    }
+    if old.debug.map_render_wireframe != new.debug.map_render_wireframe {
+        wireframe_writer.write(WireframeSettingsChangedEvent);
+    }
+    if old.input.key_bindings != new.input.key_bindings || old.input.gamepad_bindings != new.input.gamepad_bindings {
+        key_bindings_writer.write(KeyBindingsSettingsChangedEvent);
+    }
+    if old.frame_rate != new.frame_rate {
+        frame_rate_writer.write(FrameRateSettingsChangedEvent);
+    }
+
+    let restart_needed: Vec<&str> = [
+        ("uo_files", old.uo_files != new.uo_files),
+        ("world", old.world != new.world),
+        ("render.chunk_size_tiles", old.render.chunk_size_tiles != new.render.chunk_size_tiles),
+        ("render.shader_preset", old.render.shader_preset != new.render.shader_preset),
+        ("render.land_batching", old.render.land_batching != new.render.land_batching),
+        ("render.render_path", old.render.render_path != new.render.render_path),
+        ("debug.gpu_timings", old.debug.gpu_timings != new.debug.gpu_timings),
+    ]
+    .into_iter()
+    .filter_map(|(name, changed)| changed.then_some(name))
+    .collect();
+    if !restart_needed.is_empty() {
+        logger::one(
+            None,
+            LogSev::Warn,
+            LogAbout::General,
+            &format!("Changed settings which are applied only at startup: {}.", restart_needed.join(", ")),
+        );
+    }
+}
 
 // ----
 
@@ -0,0 +1,109 @@
+// Settings write-back
+// - On exit (if window.save_on_exit) or on a SaveViewSettingsEvent, writes the window size, the zoom and the active
+//   terrain shader preset back to settings.toml, so that the next launch starts with the same view.
+// - Only those values are rewritten: the rest of the file, comments included, is kept.
+//
+
+use crate::{
+    core::render::scene::camera::ZoomTarget,
+    external_data::shader_presets::ActiveShaderPreset,
+    prelude::*,
+};
+use bevy::{app::AppExit, prelude::*, window::PrimaryWindow};
+
+/// Saves the current view to settings.toml right away.
+#[derive(Event)]
+pub struct SaveViewSettingsEvent;
+
+/// Values written back to settings.toml.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct ViewSettings {
+    /// Physical size of the primary window. Tracked while running, since the window is gone when the app exits.
+    pub window_size: Option<UVec2>,
+    pub zoom: f32,
+    pub shader_preset: String,
+}
+
+pub struct SettingsWritebackPlugin {
+    pub registered_by: &'static str,
+}
+impl_tracked_plugin!(SettingsWritebackPlugin);
+impl Plugin for SettingsWritebackPlugin {
+    fn build(&self, app: &mut App) {
+        log_plugin_build(self);
+        app.init_resource::<ViewSettings>()
+            .add_event::<SaveViewSettingsEvent>()
+            .add_systems(Update, sys_track_view_settings)
+            .add_systems(Last, sys_write_back_view_settings);
+    }
+}
+
+fn sys_track_view_settings(
+    windows_q: Query<&Window, With<PrimaryWindow>>,
+    zoom_target_r: Res<ZoomTarget>,
+    active_preset_r: Option<Res<ActiveShaderPreset>>,
+    mut view_r: ResMut<ViewSettings>,
+) {
+    if let Ok(window) = windows_q.single() {
+        let size = UVec2::new(window.resolution.physical_width(), window.resolution.physical_height());
+        if view_r.window_size != Some(size) {
+            view_r.window_size = Some(size);
+        }
+    }
+    // The zoom being reached, if the smooth zoom is still going on.
+    if view_r.zoom != zoom_target_r.zoom {
+        view_r.zoom = zoom_target_r.zoom;
+    }
+    if let Some(active_preset_r) = active_preset_r
+        && active_preset_r.is_changed()
+    {
+        view_r.shader_preset = active_preset_r.0.name();
+    }
+}
+
+fn sys_write_back_view_settings(
+    mut exit_events: EventReader<AppExit>,
+    mut save_events: EventReader<SaveViewSettingsEvent>,
+    view_r: Res<ViewSettings>,
+    mut settings_r: ResMut<Settings>,
+) {
+    let exiting = exit_events.read().last().is_some();
+    let save_requested = save_events.read().last().is_some();
+    if !(save_requested || (exiting && settings_r.window.save_on_exit)) {
+        return;
+    }
+
+    // Keep the resource in sync, so that the hot reload of the written file doesn't see any change.
+    if let Some(size) = view_r.window_size {
+        settings_r.window.width = size.x as f32;
+        settings_r.window.height = size.y as f32;
+    }
+    settings_r.window.zoom = view_r.zoom;
+    if !view_r.shader_preset.is_empty() {
+        settings_r.render.shader_preset = view_r.shader_preset.clone();
+    }
+
+    let result = edit_settings_file(|doc| {
+        let window = settings_file_section(doc, "window")?;
+        set_settings_file_value(window, "width", f64::from(settings_r.window.width));
+        set_settings_file_value(window, "height", f64::from(settings_r.window.height));
+        set_settings_file_value(window, "zoom", round_for_file(settings_r.window.zoom));
+        let render = settings_file_section(doc, "render")?;
+        set_settings_file_value(render, "shader_preset", settings_r.render.shader_preset.as_str());
+        Ok(())
+    });
+    match result {
+        Ok(()) => logger::one(None, LogSev::Info, LogAbout::General, "Saved the current view to the settings file."),
+        Err(e) => logger::one(
+            None,
+            LogSev::Error,
+            LogAbout::General,
+            &format!("Can't save the current view to the settings file: {e}"),
+        ),
+    }
+}
+
+/// f32 values widened to f64 get noise digits (1.1 -> 1.100000023841858): keep 3 decimals.
+fn round_for_file(val: f32) -> f64 {
+    (f64::from(val) * 1000.0).round() / 1000.0
+}
@@ -1,18 +1,18 @@
 use crate::{
     core::render::scene::world::land::mesh_material::{
-        LandEffectsUniform, LandLightingUniforms, LandShaderModePresets,
+        LandEffectsUniform, LandLightingUniforms, LandMaterialUniformsPresets, LandShaderModePresets,
     },
     core::system_sets::StartupSysSet,
+    external_data::asset_files,
     prelude::*,
     util_lib::tracked_plugin::*,
 };
 use bevy::prelude::*;
-use std::path::PathBuf;
 
 const SHADER_PRESETS_FILE_NAME: &str = "shader_presets.toml";
 
 // Holds current values and a dirty flag.
-// Bevy detects asset changes and re-uploads uniforms automatically.
+// push_uniforms_if_dirty copies the changed values to the uniforms shared by the land materials.
 #[derive(Resource, Clone, Copy)]
 pub struct UniformState {
     pub effects: LandEffectsUniform,    // modes/toggles + intensities
@@ -21,6 +21,91 @@ pub struct UniformState {
     pub dirty: bool,          // when true, push to GPU materials this frame
 }
 
+/// Time of day (or place) of a preset, for each shading mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShaderPresetKind {
+    Morning,
+    Afternoon,
+    Night,
+    Cave,
+}
+impl ShaderPresetKind {
+    pub const ALL: [ShaderPresetKind; 4] = [
+        ShaderPresetKind::Morning,
+        ShaderPresetKind::Afternoon,
+        ShaderPresetKind::Night,
+        ShaderPresetKind::Cave,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ShaderPresetKind::Morning => "Morning",
+            ShaderPresetKind::Afternoon => "Afternoon",
+            ShaderPresetKind::Night => "Night",
+            ShaderPresetKind::Cave => "Cave",
+        }
+    }
+
+    /// Name of the preset table in shader_presets.toml.
+    pub fn table_name(self) -> &'static str {
+        match self {
+            ShaderPresetKind::Morning => "morning",
+            ShaderPresetKind::Afternoon => "afternoon",
+            ShaderPresetKind::Night => "night",
+            ShaderPresetKind::Cave => "cave",
+        }
+    }
+}
+
+const SHADING_MODE_TABLE_NAMES: [&str; 3] = ["classic", "enhanced", "kr"];
+
+/// A preset of shader_presets.toml, named in settings.toml as "<mode>.<kind>" (e.g. "classic.morning").
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ShaderPresetId {
+    pub shading_mode: u32,
+    pub kind: ShaderPresetKind,
+}
+impl Default for ShaderPresetId {
+    fn default() -> Self {
+        Self {
+            shading_mode: 0,
+            kind: ShaderPresetKind::Morning,
+        }
+    }
+}
+impl ShaderPresetId {
+    pub fn parse(name: &str) -> Option<Self> {
+        let (mode_name, kind_name) = name.trim().split_once('.')?;
+        let shading_mode = SHADING_MODE_TABLE_NAMES
+            .iter()
+            .position(|mode| mode.eq_ignore_ascii_case(mode_name))? as u32;
+        let kind = ShaderPresetKind::ALL
+            .into_iter()
+            .find(|kind| kind.table_name().eq_ignore_ascii_case(kind_name))?;
+        Some(Self { shading_mode, kind })
+    }
+
+    pub fn name(&self) -> String {
+        // Like LandShaderModePresets::for_mode, higher modes use the KR presets.
+        let mode_idx = (self.shading_mode as usize).min(SHADING_MODE_TABLE_NAMES.len() - 1);
+        format!("{}.{}", SHADING_MODE_TABLE_NAMES[mode_idx], self.kind.table_name())
+    }
+
+    pub fn preset<'a>(&self, presets: &'a LandShaderModePresets) -> &'a LandMaterialUniformsPresets {
+        let mode_presets = presets.for_mode(self.shading_mode);
+        match self.kind {
+            ShaderPresetKind::Morning => &mode_presets.morning,
+            ShaderPresetKind::Afternoon => &mode_presets.afternoon,
+            ShaderPresetKind::Night => &mode_presets.night,
+            ShaderPresetKind::Cave => &mode_presets.cave,
+        }
+    }
+}
+
+/// Last preset applied to UniformState (at startup or from the terrain shader window).
+#[derive(Resource, Clone, Copy, Debug, Default)]
+pub struct ActiveShaderPreset(pub ShaderPresetId);
+
 pub struct ShaderPresetsPlugin {
     pub registered_by: &'static str,
 }
@@ -36,11 +121,8 @@ fn build(&self, app: &mut App) {
 }
 
 pub fn load_from_file() -> LandShaderModePresets {
-    let presets_with_rel_path: PathBuf =
-        PathBuf::from(crate::core::constants::ASSET_FOLDER.to_string() + SHADER_PRESETS_FILE_NAME);
-
-    let contents = std::fs::read_to_string(&presets_with_rel_path)
-        .expect("Failed to read shader presets file");
+    let contents =
+        asset_files::read_to_string(SHADER_PRESETS_FILE_NAME).expect("Failed to read shader presets file");
     let presets: LandShaderModePresets = match toml::from_str(&contents) {
         Ok(cont) => cont,
         Err(e) => {
@@ -52,9 +134,24 @@ pub fn load_from_file() -> LandShaderModePresets {
     presets
 }
 
-fn setup_uniform_state(mut commands: Commands, shader_presets: Res<LandShaderModePresets>) {
+fn setup_uniform_state(
+    mut commands: Commands,
+    settings_r: Res<Settings>,
+    shader_presets: Res<LandShaderModePresets>,
+) {
     log_system_add_startup::<ShaderPresetsPlugin>(StartupSysSet::LoadStartupUOFiles, fname!());
-    let preset = &shader_presets.classic.morning; // TODO: move this in the presets file?
+    let preset_name = &settings_r.render.shader_preset;
+    let preset_id = ShaderPresetId::parse(preset_name).unwrap_or_else(|| {
+        logger::one(
+            None,
+            LogSev::Warn,
+            LogAbout::Renderer,
+            &format!("Unknown shader preset '{preset_name}': using '{}'.", ShaderPresetId::default().name()),
+        );
+        ShaderPresetId::default()
+    });
+    let preset = preset_id.preset(&shader_presets);
+    commands.insert_resource(ActiveShaderPreset(preset_id));
     commands.insert_resource(UniformState {
         effects: preset.effects,
         lighting: preset.lighting,
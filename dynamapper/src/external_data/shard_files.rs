@@ -0,0 +1,194 @@
+// Readers of the shard definition files (the scripts and data of the emulators, not the UO client files): the
+//  regions and the spawns shown over the map come from them (see shard_regions).
+// - SphereServer scripts (*.scp): [TYPE arg] sections of KEY=value lines, "//" comments.
+// - ServUO XML files: only what their simple layout needs. Elements, attributes and text; no DTD, no namespaces,
+//   no CDATA. The standard entities are decoded in the attributes and the text.
+//
+
+use color_eyre::eyre;
+
+/// A [TYPE arg] section of a Sphere script.
+#[derive(Clone, Debug)]
+pub struct ScpSection {
+    /// Uppercase, e.g. "AREADEF".
+    pub kind: String,
+    pub arg: String,
+    /// Keys uppercase, in file order. A key can be repeated (e.g. RECT).
+    pub keys: Vec<(String, String)>,
+    /// 1-based line of the header, for the messages.
+    pub line: usize,
+}
+impl ScpSection {
+    /// Value of the first occurrence of a key (uppercase).
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.keys.iter().find(|(k, _)| k == key).map(|(_, value)| value.as_str())
+    }
+
+    pub fn values<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a str> {
+        self.keys.iter().filter(move |(k, _)| k == key).map(|(_, value)| value.as_str())
+    }
+}
+
+/// Sections of a Sphere script. The lines before the first section are skipped, like [EOF] and what follows it.
+pub fn read_scp_sections(text: &str) -> Vec<ScpSection> {
+    let mut sections = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[')
+            && let Some(header) = header.strip_suffix(']')
+        {
+            let (kind, arg) = header.trim().split_once(char::is_whitespace).unwrap_or((header.trim(), ""));
+            if kind.eq_ignore_ascii_case("EOF") {
+                break;
+            }
+            sections.push(ScpSection {
+                kind: kind.to_ascii_uppercase(),
+                arg: arg.trim().to_owned(),
+                keys: Vec::new(),
+                line: index + 1,
+            });
+            continue;
+        }
+        let Some(section) = sections.last_mut() else {
+            continue;
+        };
+        let (key, value) = line.split_once('=').unwrap_or((line, ""));
+        section.keys.push((key.trim().to_ascii_uppercase(), value.trim().to_owned()));
+    }
+    sections
+}
+
+/// A number as Sphere reads it: hexadecimal if it starts with 0 (e.g. 04000) or 0x, else decimal.
+pub fn sphere_number(text: &str) -> Option<i64> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, text),
+    };
+    let value = if let Some(hex) = digits.strip_prefix("0x").or_else(|| digits.strip_prefix("0X")) {
+        i64::from_str_radix(hex, 16).ok()?
+    } else if digits.len() > 1 && digits.starts_with('0') {
+        i64::from_str_radix(digits, 16).ok()?
+    } else {
+        digits.parse().ok()?
+    };
+    Some(if negative { -value } else { value })
+}
+
+/// A piece of an XML file, in file order.
+#[derive(Clone, Debug, PartialEq)]
+pub enum XmlItem {
+    /// A start tag. A self-closing one (<a/>) is followed by its End.
+    Start { name: String, attrs: Vec<(String, String)> },
+    End { name: String },
+    /// Trimmed, not empty.
+    Text(String),
+}
+
+/// Attribute of a Start, by name (case insensitive).
+pub fn xml_attr<'a>(attrs: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    attrs
+        .iter()
+        .find(|(attr, _)| attr.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+pub fn read_xml(text: &str) -> eyre::Result<Vec<XmlItem>> {
+    let mut items = Vec::new();
+    let mut rest = text;
+    while let Some(tag_start) = rest.find('<') {
+        let before = rest[..tag_start].trim();
+        if !before.is_empty() {
+            items.push(XmlItem::Text(decode_xml_entities(before)));
+        }
+        rest = &rest[tag_start..];
+        // Comments, declarations (<?xml ...?>) and DTDs are skipped.
+        let skipped_end = if rest.starts_with("<!--") {
+            Some("-->")
+        } else if rest.starts_with("<?") {
+            Some("?>")
+        } else if rest.starts_with("<!") {
+            Some(">")
+        } else {
+            None
+        };
+        if let Some(skipped_end) = skipped_end {
+            let Some(end) = rest.find(skipped_end) else {
+                eyre::bail!("Unterminated '{}' in the XML.", &rest[..rest.len().min(4)]);
+            };
+            rest = &rest[end + skipped_end.len()..];
+            continue;
+        }
+        let Some(tag_end) = rest.find('>') else {
+            eyre::bail!("Unterminated tag in the XML.");
+        };
+        let tag = &rest[1..tag_end];
+        rest = &rest[tag_end + 1..];
+        if let Some(name) = tag.strip_prefix('/') {
+            items.push(XmlItem::End {
+                name: name.trim().to_owned(),
+            });
+            continue;
+        }
+        let (tag, self_closing) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let (name, attrs_text) = tag.trim().split_once(char::is_whitespace).unwrap_or((tag.trim(), ""));
+        if name.is_empty() {
+            eyre::bail!("Tag without a name in the XML.");
+        }
+        items.push(XmlItem::Start {
+            name: name.to_owned(),
+            attrs: read_xml_attrs(attrs_text)?,
+        });
+        if self_closing {
+            items.push(XmlItem::End { name: name.to_owned() });
+        }
+    }
+    Ok(items)
+}
+
+/// name="value" or name='value' pairs.
+fn read_xml_attrs(text: &str) -> eyre::Result<Vec<(String, String)>> {
+    let mut attrs = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let Some((name, value_part)) = rest.split_once('=') else {
+            eyre::bail!("Attribute without a value in the XML: '{}'.", rest.trim());
+        };
+        let value_part = value_part.trim_start();
+        let Some(quote) = value_part.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            eyre::bail!("Unquoted value of attribute '{}' in the XML.", name.trim());
+        };
+        let Some(value_end) = value_part[1..].find(quote) else {
+            eyre::bail!("Unterminated value of attribute '{}' in the XML.", name.trim());
+        };
+        attrs.push((name.trim().to_owned(), decode_xml_entities(&value_part[1..1 + value_end])));
+        rest = value_part[value_end + 2..].trim_start();
+    }
+    Ok(attrs)
+}
+
+fn decode_xml_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_owned();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Map plane of a ServUO facet name (Map.Parse), or None if it isn't a standard one.
+pub fn facet_map_id(facet: &str) -> Option<u8> {
+    const FACETS: [&str; 6] = ["Felucca", "Trammel", "Ilshenar", "Malas", "Tokuno", "TerMur"];
+    FACETS
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(facet.trim()))
+        .map(|map_id| map_id as u8)
+}
@@ -0,0 +1,204 @@
+// Shard regions: the areas defined by a shard (towns, dungeons, guarded zones...), read from its files to be drawn
+//  over the map (see render::overlays::shard_regions).
+// - SphereServer: the [AREADEF] and [ROOMDEF] sections of its map scripts (e.g. sphere_map0.scp), with
+//   RECT=x1,y1,x2,y2[,map] lines. Rects without a map are on the one of P=x,y,z,map.
+// - ServUO (and RunUO): Data/Regions.xml, <region> elements (nested ones included) in <Facet> ones, with
+//   <rect x="" y="" width="" height=""/> or <rect x1="" y1="" x2="" y2=""/> elements.
+// - The kind comes from the ServUO region type, or from the Sphere GROUP, name and FLAGS (REGION_FLAG_GUARDED).
+// - The rects end before x2 and y2, like in both emulators.
+//
+
+use crate::external_data::shard_files::{self, XmlItem, read_scp_sections, read_xml, sphere_number, xml_attr};
+use color_eyre::eyre::{self, WrapErr};
+use std::path::Path;
+
+/// Sphere's REGION_FLAG_GUARDED.
+const SPHERE_REGION_FLAG_GUARDED: i64 = 0x4000;
+
+#[derive(strum_macros::AsRefStr, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RegionKind {
+    Town,
+    Dungeon,
+    Guarded,
+    Other,
+}
+impl RegionKind {
+    pub const ALL: [RegionKind; 4] = [
+        RegionKind::Town,
+        RegionKind::Dungeon,
+        RegionKind::Guarded,
+        RegionKind::Other,
+    ];
+
+    /// The kind a region type, group or name tells, if any.
+    fn from_text(text: &str) -> Option<Self> {
+        let text = text.to_ascii_lowercase();
+        if text.contains("town") || text.contains("city") {
+            Some(RegionKind::Town)
+        } else if text.contains("dungeon") {
+            Some(RegionKind::Dungeon)
+        } else if text.contains("guard") {
+            Some(RegionKind::Guarded)
+        } else {
+            None
+        }
+    }
+}
+
+/// Tiles x1 <= x < x2, y1 <= y < y2.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RegionRect {
+    pub x1: u16,
+    pub y1: u16,
+    pub x2: u16,
+    pub y2: u16,
+}
+impl RegionRect {
+    /// From two opposite corners, in any order.
+    fn from_corners(x1: i64, y1: i64, x2: i64, y2: i64) -> Self {
+        let clamp = |value: i64| value.clamp(0, u16::MAX as i64) as u16;
+        Self {
+            x1: clamp(x1.min(x2)),
+            y1: clamp(y1.min(y2)),
+            x2: clamp(x1.max(x2)),
+            y2: clamp(y1.max(y2)),
+        }
+    }
+
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        (self.x1..self.x2).contains(&x) && (self.y1..self.y2).contains(&y)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ShardRegion {
+    pub name: String,
+    pub kind: RegionKind,
+    /// How the shard defines it, e.g. "TownRegion", or "AREADEF a_britain (Towns)".
+    pub definition: String,
+    pub map: u8,
+    pub rects: Vec<RegionRect>,
+}
+
+/// The regions of a Sphere script, or of a ServUO XML file (.xml). Regions without rects are skipped.
+pub fn load(path: &Path) -> eyre::Result<Vec<ShardRegion>> {
+    let text = std::fs::read(path).wrap_err_with(|| format!("Read regions file '{}'", path.display()))?;
+    let text = String::from_utf8_lossy(&text);
+    let is_xml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml"));
+    let regions = if is_xml {
+        read_servuo_regions(&text)
+    } else {
+        read_sphere_regions(&text)
+    };
+    regions.wrap_err_with(|| format!("Parse regions file '{}'", path.display()))
+}
+
+fn read_sphere_regions(text: &str) -> eyre::Result<Vec<ShardRegion>> {
+    let mut regions = Vec::new();
+    for section in read_scp_sections(text) {
+        if section.kind != "AREADEF" && section.kind != "ROOMDEF" {
+            continue;
+        }
+        let numbers = |value: &str| value.split(',').map(sphere_number).collect::<Option<Vec<i64>>>();
+        let default_map = match section.value("P").map(numbers) {
+            Some(Some(p)) => p.get(3).copied().unwrap_or(0),
+            Some(None) => eyre::bail!("Bad P in [{} {}] (line {}).", section.kind, section.arg, section.line),
+            None => 0,
+        };
+        let mut rects_by_map: Vec<(u8, RegionRect)> = Vec::new();
+        for value in section.values("RECT") {
+            let Some(rect) = numbers(value).filter(|rect| rect.len() == 4 || rect.len() == 5) else {
+                eyre::bail!("Bad RECT '{value}' in [{} {}] (line {}).", section.kind, section.arg, section.line);
+            };
+            let map = rect.get(4).copied().unwrap_or(default_map).clamp(0, u8::MAX as i64) as u8;
+            rects_by_map.push((map, RegionRect::from_corners(rect[0], rect[1], rect[2], rect[3])));
+        }
+        let guarded = section.values("FLAGS").flat_map(|flags| flags.split('|')).any(|flag| {
+            let flag = flag.trim();
+            flag.to_ascii_uppercase().contains("GUARDED")
+                || sphere_number(flag).is_some_and(|flags| flags & SPHERE_REGION_FLAG_GUARDED != 0)
+        });
+        let name = section.value("NAME").filter(|name| !name.is_empty()).unwrap_or(&section.arg);
+        let group = section.value("GROUP").unwrap_or_default();
+        let kind = RegionKind::from_text(group)
+            .or_else(|| RegionKind::from_text(name))
+            .or_else(|| RegionKind::from_text(&section.arg))
+            .unwrap_or(if guarded { RegionKind::Guarded } else { RegionKind::Other });
+        let definition = if group.is_empty() {
+            format!("{} {}", section.kind, section.arg)
+        } else {
+            format!("{} {} ({group})", section.kind, section.arg)
+        };
+        // A region can span several map planes: one ShardRegion for each.
+        let mut maps: Vec<u8> = rects_by_map.iter().map(|(map, _)| *map).collect();
+        maps.sort_unstable();
+        maps.dedup();
+        for map in maps {
+            regions.push(ShardRegion {
+                name: name.to_owned(),
+                kind,
+                definition: definition.clone(),
+                map,
+                rects: rects_by_map.iter().filter(|(m, _)| *m == map).map(|(_, rect)| *rect).collect(),
+            });
+        }
+    }
+    Ok(regions)
+}
+
+fn read_servuo_regions(text: &str) -> eyre::Result<Vec<ShardRegion>> {
+    let mut regions = Vec::new();
+    let mut map: Option<u8> = None;
+    // The <region> elements being read, the innermost last.
+    let mut open_regions: Vec<ShardRegion> = Vec::new();
+    for item in read_xml(text)? {
+        match item {
+            XmlItem::Start { name, attrs } if name.eq_ignore_ascii_case("Facet") => {
+                map = xml_attr(&attrs, "name").and_then(shard_files::facet_map_id);
+            }
+            XmlItem::Start { name, attrs } if name.eq_ignore_ascii_case("region") => {
+                let region_type = xml_attr(&attrs, "type").unwrap_or("Region");
+                let region_name = xml_attr(&attrs, "name").unwrap_or_default();
+                open_regions.push(ShardRegion {
+                    name: if region_name.is_empty() { region_type } else { region_name }.to_owned(),
+                    kind: RegionKind::from_text(region_type)
+                        .or_else(|| RegionKind::from_text(region_name))
+                        .unwrap_or(RegionKind::Other),
+                    definition: region_type.to_owned(),
+                    map: map.unwrap_or(0),
+                    rects: Vec::new(),
+                });
+            }
+            XmlItem::Start { name, attrs } if name.eq_ignore_ascii_case("rect") => {
+                let Some(region) = open_regions.last_mut() else {
+                    continue;
+                };
+                let number = |attr: &str| xml_attr(&attrs, attr).and_then(|value| value.trim().parse::<i64>().ok());
+                let rect = if let (Some(x), Some(y), Some(width), Some(height)) =
+                    (number("x"), number("y"), number("width"), number("height"))
+                {
+                    RegionRect::from_corners(x, y, x + width, y + height)
+                } else if let (Some(x1), Some(y1), Some(x2), Some(y2)) =
+                    (number("x1"), number("y1"), number("x2"), number("y2"))
+                {
+                    RegionRect::from_corners(x1, y1, x2, y2)
+                } else {
+                    eyre::bail!("Bad rect in region '{}'.", region.name);
+                };
+                region.rects.push(rect);
+            }
+            XmlItem::End { name } if name.eq_ignore_ascii_case("region") => {
+                // The regions of the unknown facets are skipped.
+                if let Some(region) = open_regions.pop()
+                    && map.is_some()
+                    && !region.rects.is_empty()
+                {
+                    regions.push(region);
+                }
+            }
+            XmlItem::End { name } if name.eq_ignore_ascii_case("Facet") => map = None,
+            _ => {}
+        }
+    }
+    Ok(regions)
+}
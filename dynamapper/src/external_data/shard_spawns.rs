@@ -0,0 +1,163 @@
+// Shard spawns: the spawn points of a shard, read from its files to be drawn over the map (see
+//  render::overlays::shard_spawns).
+// - SphereServer: the spawn gems of its world save (sphereworld.scp), [WORLDITEM i_worldgem_bit] sections (or with
+//   TYPE=t_spawn_char/t_spawn_item). MORE1 is what's spawned (a character, an item or a spawn group), AMOUNT the
+//   max count, MOREZ (or the z of MOREP) the max distance.
+// - ServUO (and RunUO): XmlSpawner save files, a <Points> element for each spawner, with <Map>, <X>, <Y>, <Width>,
+//   <Height>, <CentreX>, <CentreY>, <CentreZ>, <Range>, <MaxCount> and the spawned types in <Objects2> (or the older
+//   <Objects>).
+// - The radius is the max distance of the spawned ones from the spawn point: half the largest side of the spawn area
+//   of the XmlSpawners, or their home range if they don't have one.
+//
+
+use crate::external_data::shard_files::{self, XmlItem, read_scp_sections, read_xml, sphere_number};
+use color_eyre::eyre::{self, WrapErr};
+use std::collections::HashMap;
+use std::path::Path;
+
+#[derive(Clone, Debug)]
+pub struct ShardSpawn {
+    /// Spawner name, or definition (e.g. "WORLDITEM i_worldgem_bit 040001234").
+    pub name: String,
+    pub map: u8,
+    pub x: u16,
+    pub y: u16,
+    pub z: i8,
+    /// Tiles.
+    pub radius: u16,
+    pub max_count: u32,
+    /// Names of what's spawned, as the shard defines them: Sphere defnames, ServUO type names.
+    pub creatures: Vec<String>,
+}
+
+/// The spawns of a Sphere world save, or of an XmlSpawner file (.xml).
+pub fn load(path: &Path) -> eyre::Result<Vec<ShardSpawn>> {
+    let text = std::fs::read(path).wrap_err_with(|| format!("Read spawns file '{}'", path.display()))?;
+    let text = String::from_utf8_lossy(&text);
+    let is_xml = path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("xml"));
+    let spawns = if is_xml {
+        read_xml_spawners(&text)
+    } else {
+        read_sphere_spawns(&text)
+    };
+    spawns.wrap_err_with(|| format!("Parse spawns file '{}'", path.display()))
+}
+
+fn to_u16(value: i64) -> u16 {
+    value.clamp(0, u16::MAX as i64) as u16
+}
+
+fn read_sphere_spawns(text: &str) -> eyre::Result<Vec<ShardSpawn>> {
+    let mut spawns = Vec::new();
+    for section in read_scp_sections(text) {
+        let is_spawn_type = section
+            .value("TYPE")
+            .is_some_and(|item_type| item_type.to_ascii_lowercase().starts_with("t_spawn"));
+        let is_worldgem = section.arg.to_ascii_lowercase().starts_with("i_worldgem");
+        if section.kind != "WORLDITEM" || !(is_spawn_type || is_worldgem) {
+            continue;
+        }
+        let numbers = |value: &str| value.split(',').map(sphere_number).collect::<Option<Vec<i64>>>();
+        let Some(p) = section.value("P").and_then(numbers).filter(|p| p.len() >= 2) else {
+            eyre::bail!("Bad or missing P in [{} {}] (line {}).", section.kind, section.arg, section.line);
+        };
+        let radius = match section.value("MOREZ") {
+            Some(more_z) => sphere_number(more_z),
+            None => section.value("MOREP").and_then(numbers).and_then(|more_p| more_p.get(2).copied()),
+        };
+        let creature = section.value("MORE1").or_else(|| section.value("MORE")).unwrap_or_default();
+        let name = match section.value("SERIAL") {
+            Some(serial) => format!("{} {} {serial}", section.kind, section.arg),
+            None => format!("{} {}", section.kind, section.arg),
+        };
+        spawns.push(ShardSpawn {
+            name,
+            map: p.get(3).copied().unwrap_or(0).clamp(0, u8::MAX as i64) as u8,
+            x: to_u16(p[0]),
+            y: to_u16(p[1]),
+            z: p.get(2).copied().unwrap_or(0).clamp(i8::MIN as i64, i8::MAX as i64) as i8,
+            radius: to_u16(radius.unwrap_or(0)),
+            max_count: section
+                .value("AMOUNT")
+                .and_then(sphere_number)
+                .unwrap_or(1)
+                .clamp(0, u32::MAX as i64) as u32,
+            creatures: if creature.is_empty() { Vec::new() } else { vec![creature.to_owned()] },
+        });
+    }
+    Ok(spawns)
+}
+
+/// Names of the spawned types of an XmlSpawner: "OBJ=Orc:MX=3:SB=0...:OBJ=Ogre:..." (Objects2), or
+///  "Orc:3:Ogre:1" (Objects).
+fn xml_spawner_creatures(objects2: Option<&str>, objects: Option<&str>) -> Vec<String> {
+    let names: Vec<&str> = if let Some(objects2) = objects2 {
+        objects2
+            .split("OBJ=")
+            .filter_map(|object| object.split(':').next())
+            .collect()
+    } else if let Some(objects) = objects {
+        objects.split(':').step_by(2).collect()
+    } else {
+        Vec::new()
+    };
+    names
+        .into_iter()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+fn read_xml_spawners(text: &str) -> eyre::Result<Vec<ShardSpawn>> {
+    let mut spawns = Vec::new();
+    // Child element -> text, of the <Points> element being read.
+    let mut fields: Option<HashMap<String, String>> = None;
+    let mut field = String::new();
+    for item in read_xml(text)? {
+        match item {
+            XmlItem::Start { name, .. } if name.eq_ignore_ascii_case("Points") => fields = Some(HashMap::new()),
+            XmlItem::Start { name, .. } => field = name.to_ascii_lowercase(),
+            XmlItem::Text(text) => {
+                if let Some(fields) = fields.as_mut() {
+                    fields.insert(field.clone(), text);
+                }
+            }
+            XmlItem::End { name } if name.eq_ignore_ascii_case("Points") => {
+                let Some(fields) = fields.take() else {
+                    continue;
+                };
+                let number = |name: &str| fields.get(name).and_then(|value| value.trim().parse::<i64>().ok());
+                let spawner_name = fields.get("name").cloned().unwrap_or_default();
+                // The spawners of the unknown maps (Internal...) are skipped.
+                let Some(map) = fields.get("map").and_then(|map| shard_files::facet_map_id(map)) else {
+                    continue;
+                };
+                let (Some(x), Some(y)) = (number("x"), number("y")) else {
+                    eyre::bail!("Spawner '{spawner_name}' without X or Y.");
+                };
+                let (width, height) = (number("width").unwrap_or(0), number("height").unwrap_or(0));
+                let radius = if width > 0 || height > 0 {
+                    width.max(height) / 2
+                } else {
+                    number("range").unwrap_or(0)
+                };
+                spawns.push(ShardSpawn {
+                    name: spawner_name,
+                    map,
+                    x: to_u16(number("centrex").unwrap_or(x + width / 2)),
+                    y: to_u16(number("centrey").unwrap_or(y + height / 2)),
+                    z: number("centrez").unwrap_or(0).clamp(i8::MIN as i64, i8::MAX as i64) as i8,
+                    radius: to_u16(radius),
+                    max_count: number("maxcount").unwrap_or(0).clamp(0, u32::MAX as i64) as u32,
+                    creatures: xml_spawner_creatures(
+                        fields.get("objects2").map(String::as_str),
+                        fields.get("objects").map(String::as_str),
+                    ),
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(spawns)
+}
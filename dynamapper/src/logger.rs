@@ -1,13 +1,20 @@
+pub mod file_output;
+
 use chrono::Timelike;
 //use pad::PadStr;
 use regex::Regex;
-use strum::VariantNames; // For the trait.
-use strum_macros::{Display, EnumString, VariantNames};
+use strum::{EnumCount, IntoEnumIterator, VariantNames}; // For the traits.
+use strum_macros::{Display, EnumCount, EnumIter, EnumString, VariantNames};
 //use std::io::Write; // for flush().
-use std::sync::OnceLock;
+use std::collections::{BTreeMap, VecDeque};
+use std::str::FromStr;
+use std::sync::{
+    Mutex, OnceLock,
+    atomic::{AtomicU8, Ordering},
+};
 
 // Event severity.
-#[derive(Display, EnumString, VariantNames, PartialEq)]
+#[derive(Display, EnumString, VariantNames, EnumIter, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum LogSev {
     Debug,
     DebugVerbose,
@@ -16,20 +23,42 @@ pub enum LogSev {
     Info,
     Warn,
 }
+impl LogSev {
+    /// Severities from the least to the most important, for the filtering.
+    pub const BY_RANK: [LogSev; 6] = [
+        LogSev::DebugVerbose,
+        LogSev::Debug,
+        LogSev::Diagnostics,
+        LogSev::Info,
+        LogSev::Warn,
+        LogSev::Error,
+    ];
+
+    pub fn rank(self) -> u8 {
+        Self::BY_RANK.iter().position(|sev| *sev == self).unwrap() as u8
+    }
+
+    fn from_rank(rank: u8) -> LogSev {
+        Self::BY_RANK[rank as usize]
+    }
+}
 
 // Event context.
-#[derive(Display, EnumString, VariantNames, PartialEq)]
+#[derive(Display, EnumString, VariantNames, EnumIter, EnumCount, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum LogAbout {
     AppState,
     Camera,
     General,
     Input,
     InternalAssets,
+    LiveWorld,
+    MapEditor,
     Player,
     Plugins,
     Renderer,
     RenderWorldArt,
     RenderWorldLand,
+    ShardData,
     Startup,
     SystemsGeneral,
     UoFiles,
@@ -76,11 +105,102 @@ fn enum_about_variant_name_validate(val: &str) -> bool {
     true
 }
 
-#[allow(unused)]
-fn can_show_msg(severity: LogSev, about: LogAbout) -> bool {
-    true
+// ---- Filtering ----
+
+// Lowest severity shown for each LogAbout (as LogSev::rank), indexed by LogAbout. Everything is shown by default.
+static MIN_SEVERITY_RANKS: [AtomicU8; LogAbout::COUNT] = [const { AtomicU8::new(0) }; LogAbout::COUNT];
+
+/// Lowest severity of the messages shown for the given LogAbout.
+pub fn min_severity(about: LogAbout) -> LogSev {
+    LogSev::from_rank(MIN_SEVERITY_RANKS[about as usize].load(Ordering::Relaxed))
+}
+
+/// Changes at runtime the lowest severity of the messages shown for the given LogAbout.
+pub fn set_min_severity(about: LogAbout, severity: LogSev) {
+    MIN_SEVERITY_RANKS[about as usize].store(severity.rank(), Ordering::Relaxed);
+}
+
+pub fn set_min_severity_all(severity: LogSev) {
+    for about in LogAbout::iter() {
+        set_min_severity(about, severity);
+    }
+}
+
+/// Sets the filters from the settings: a default severity, then the ones for specific LogAbouts (by name).
+/// Unknown names are reported and skipped.
+pub fn configure_filters(default_min_severity: &str, min_severity_by_about: &BTreeMap<String, String>) {
+    let parse_severity = |name: &str| {
+        let severity = LogSev::from_str(name.trim()).ok();
+        if severity.is_none() {
+            one(
+                None,
+                LogSev::Warn,
+                LogAbout::General,
+                &format!("Unknown log severity '{name}'. Valid ones: {}.", LogSev::VARIANTS.join(", ")),
+            );
+        }
+        severity
+    };
+
+    set_min_severity_all(parse_severity(default_min_severity).unwrap_or(LogSev::DebugVerbose));
+    for (about_name, severity_name) in min_severity_by_about {
+        let Ok(about) = LogAbout::from_str(about_name.trim()) else {
+            one(
+                None,
+                LogSev::Warn,
+                LogAbout::General,
+                &format!("Unknown log About '{about_name}'. Valid ones: {}.", LogAbout::VARIANTS.join(", ")),
+            );
+            continue;
+        };
+        if let Some(severity) = parse_severity(severity_name) {
+            set_min_severity(about, severity);
+        }
+    }
+}
+
+pub fn can_show_msg(severity: LogSev, about: LogAbout) -> bool {
+    severity.rank() >= min_severity(about).rank()
 }
 
+// ---- Recent messages, for the log console ----
+
+const RECENT_MSGS_MAX: usize = 1000;
+
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+    /// Local time, as hh:mm:ss.
+    pub time: String,
+    pub severity: LogSev,
+    pub about: LogAbout,
+    pub location: String,
+    pub msg: String,
+}
+
+fn recent_msgs() -> &'static Mutex<VecDeque<LogRecord>> {
+    static RECENT_MSGS: OnceLock<Mutex<VecDeque<LogRecord>>> = OnceLock::new();
+    RECENT_MSGS.get_or_init(|| Mutex::new(VecDeque::with_capacity(RECENT_MSGS_MAX)))
+}
+
+fn push_recent_msg(record: LogRecord) {
+    let mut recent = recent_msgs().lock().unwrap();
+    if recent.len() == RECENT_MSGS_MAX {
+        recent.pop_front();
+    }
+    recent.push_back(record);
+}
+
+/// Copy of the last shown messages, oldest first.
+pub fn recent_messages() -> Vec<LogRecord> {
+    recent_msgs().lock().unwrap().iter().cloned().collect()
+}
+
+pub fn clear_recent_messages() {
+    recent_msgs().lock().unwrap().clear();
+}
+
+// ----
+
 #[track_caller]
 pub fn one(
     show_caller_location_override: Option<bool>,
@@ -89,6 +209,9 @@ pub fn one(
     msg: &str,
 ) {
     use std::fmt::Write;
+    if !can_show_msg(severity, about) {
+        return;
+    }
     let show_location = show_caller_location_override.unwrap_or(true);
 
     //let now: OffsetDateTime = SystemTime::now().into(); // not adjusted by time zone
@@ -99,17 +222,18 @@ pub fn one(
     let mut full_msg = String::with_capacity(256);
     write!(full_msg, "<d>{h:02}:{m:02}:{s:02} {{ ").unwrap();
 
+    let caller = std::panic::Location::caller();
+    let loc_str: String = format!("{}:{}", caller.file(), caller.line());
+
     // Add file:line if enabled
     if show_location {
-        let caller = std::panic::Location::caller();
-        let loc_str: String = format!("{}:{}", caller.file(), caller.line());
 
         const PAD_WIDTH: usize = 46;
         let loc_trimmed: String = if loc_str.len() > PAD_WIDTH {
             let slice: &str = &loc_str[loc_str.len() - (PAD_WIDTH - 2)..];
             format!("..{}", slice)
         } else {
-            loc_str
+            loc_str.clone()
         };
 
         // Right-pad or truncate to PAD_WIDTH
@@ -144,8 +268,32 @@ pub fn one(
     }
 
     paris::log!("{full_msg}");
+
+    if file_output::is_enabled() {
+        let date_time = now.format("%Y-%m-%d %H:%M:%S");
+        write_to_file(&format!("{date_time} {{ {loc_str} }} [{about}] {severity}: {msg}"));
+    }
+
+    push_recent_msg(LogRecord {
+        time: format!("{h:02}:{m:02}:{s:02}"),
+        severity,
+        about,
+        location: loc_str,
+        msg: msg.to_owned(),
+    });
 }
 
 pub fn system(msg: &str) {
     paris::log!("<dark-green>{msg}</>");
+    if file_output::is_enabled() {
+        let date_time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+        write_to_file(&format!("{date_time} [System] {msg}"));
+    }
+}
+
+fn write_to_file(line: &str) {
+    if let Err(e) = file_output::write_line(line) {
+        // Not through one(): file logging is disabled now, but let's not go through the filters for this.
+        paris::log!("<red><bold>Can't write to the log file, file logging disabled: {e}</></bold>");
+    }
 }
@@ -0,0 +1,118 @@
+// Log file output
+// - Plain text copy of the logged messages (no color tags), for attaching to bug reports.
+// - Size-based rotation: when the file would exceed the max size, dynamapper.log becomes dynamapper.log.1, the older
+//   ones are shifted (.1 -> .2, ...) and the ones beyond the max count are deleted.
+//
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const LOG_FILE_NAME: &str = "dynamapper.log";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogFileConfig {
+    pub folder: PathBuf,
+    /// A file is rotated before it grows over this size.
+    pub max_size_bytes: u64,
+    /// Rotated files kept, besides the one being written.
+    pub max_rotated_files: u32,
+}
+
+struct LogFileWriter {
+    config: LogFileConfig,
+    // None only while rotating: the file is closed before being renamed.
+    file: Option<LineWriter<File>>,
+    size_bytes: u64,
+}
+impl LogFileWriter {
+    fn open(config: LogFileConfig) -> std::io::Result<Self> {
+        fs::create_dir_all(&config.folder)?;
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(config.folder.join(LOG_FILE_NAME))?;
+        let size_bytes = file.metadata()?.len();
+        Ok(Self {
+            config,
+            file: Some(LineWriter::new(file)),
+            size_bytes,
+        })
+    }
+
+    fn rotated_file_path(folder: &Path, idx: u32) -> PathBuf {
+        folder.join(format!("{LOG_FILE_NAME}.{idx}"))
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+        let folder = &self.config.folder;
+        let max_rotated = self.config.max_rotated_files;
+        let current_path = folder.join(LOG_FILE_NAME);
+        if max_rotated > 0 {
+            // Missing files are fine: there aren't that many rotated files yet.
+            let _ = fs::remove_file(Self::rotated_file_path(folder, max_rotated));
+            for idx in (1..max_rotated).rev() {
+                let _ = fs::rename(Self::rotated_file_path(folder, idx), Self::rotated_file_path(folder, idx + 1));
+            }
+            fs::rename(&current_path, Self::rotated_file_path(folder, 1))?;
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&current_path)?;
+        self.file = Some(LineWriter::new(file));
+        self.size_bytes = 0;
+        Ok(())
+    }
+
+    fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        let line_size = line.len() as u64 + 1;
+        if self.size_bytes > 0 && self.size_bytes + line_size > self.config.max_size_bytes {
+            self.rotate()?;
+        }
+        let Some(file) = self.file.as_mut() else {
+            return Err(std::io::Error::other("log file isn't open"));
+        };
+        writeln!(file, "{line}")?;
+        self.size_bytes += line_size;
+        Ok(())
+    }
+}
+
+static LOG_FILE: Mutex<Option<LogFileWriter>> = Mutex::new(None);
+
+/// Starts (or stops, with None) writing the log to file. The file is kept open if the configuration didn't change.
+pub fn configure(config: Option<LogFileConfig>) -> std::io::Result<()> {
+    let mut log_file = LOG_FILE.lock().unwrap();
+    match config {
+        None => *log_file = None,
+        Some(config) => {
+            if log_file.as_ref().is_none_or(|writer| writer.config != config) {
+                *log_file = Some(LogFileWriter::open(config)?);
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn is_enabled() -> bool {
+    LOG_FILE.lock().unwrap().is_some()
+}
+
+/// Appends a line to the log file, if enabled. On write errors, file logging is disabled and the error returned.
+pub(super) fn write_line(line: &str) -> std::io::Result<()> {
+    let mut log_file = LOG_FILE.lock().unwrap();
+    let Some(writer) = log_file.as_mut() else {
+        return Ok(());
+    };
+    let result = writer.write_line(line);
+    if result.is_err() {
+        *log_file = None;
+    }
+    result
+}
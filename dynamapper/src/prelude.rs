@@ -4,7 +4,7 @@
 pub use crate::{core::app_states::*, logger::{self, LogSev, LogAbout}, external_data::settings::*};
 
 #[doc(hidden)]
-pub use crate::{fname, impl_tracked_plugin, util_lib::tracked_plugin::*};
+pub use crate::{fname, impl_tracked_plugin, profile_span, util_lib::tracked_plugin::*};
 
 #[doc(hidden)]
 pub use crate::util_lib::uo_coords::*;
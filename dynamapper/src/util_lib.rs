@@ -1,6 +1,7 @@
 pub mod array;
 pub mod math;
 pub mod image;
+pub mod profiling;
 //pub mod rect;
 pub mod uo_coords;
 
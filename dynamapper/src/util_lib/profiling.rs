@@ -0,0 +1,30 @@
+// Profiling spans, for the Tracy profiler (https://github.com/wolfpld/tracy).
+// - Recorded only in builds with the `profiling` cargo feature (cargo run --release --features profiling), which
+//   enables Bevy's trace_tracy: Bevy records its systems and render passes, and profile_span! adds spans for the land
+//   pipeline (chunk building, map block loading, land texture cache, shader uniform pushes). Connect the Tracy
+//   profiler to the running app to see them.
+// - Without the feature, profile_span! expands to nothing.
+// - The profile_span! spans can be turned off at runtime (Diagnostics window), to unclutter the timeline: Bevy's own
+//   spans are always recorded.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static SPANS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+pub fn spans_enabled() -> bool {
+    SPANS_ENABLED.load(Ordering::Relaxed)
+}
+
+pub fn set_spans_enabled(enabled: bool) {
+    SPANS_ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Opens a profiling span named $name (with optional tracing fields), closed at the end of the enclosing scope.
+#[macro_export]
+macro_rules! profile_span {
+    ($name:literal $(, $($fields:tt)+)?) => {
+        #[cfg(feature = "profiling")]
+        let _profile_span = $crate::util_lib::profiling::spans_enabled()
+            .then(|| bevy::log::info_span!($name $(, $($fields)+)?).entered());
+    };
+}
@@ -27,6 +27,9 @@ fn log_system_add_base<'a>(myname: &'static str, plugname: &str, schedule: &'sta
 pub fn log_system_add_startup<T: TrackedPlugin>(sys_set: StartupSysSet, _myname: &'static str) {
     log_system_add_base(_myname, std::any::type_name::<T>(), "Startup", sys_set.as_ref())
 }
+pub fn log_system_add_onenter<T: TrackedPlugin>(state: &'static str, _myname: &'static str) {
+    log_system_add_base(_myname, std::any::type_name::<T>(), "OnEnter", state)
+}
 pub fn log_system_add_update<T: TrackedPlugin>(_myname: &'static str) {
     () // do nothing for now, it can be too cluttering.
     //log_system_add_base(_myname, std::any::type_name::<T>(), "Update")
@@ -0,0 +1,32 @@
+// Render regression test of the land pipeline, as a test target: runs `dynamapper --render-test` on
+//  render_tests/land_fixture.toml (see render/render_test.rs) and checks its exit code.
+// It isn't headless: the app opens a hidden window, so it needs a display (e.g. xvfb-run on a Linux CI) and a GPU
+//  adapter (a software one, like lavapipe, works). It also needs the UO folder set in settings.toml, and the reference
+//  images blessed with that installation. So it's ignored by default:
+//  cargo test -p dynamapper --test render_regression -- --ignored
+
+use std::path::Path;
+use std::process::Command;
+
+/// Folder of the assets, settings.toml and render_tests: the app is run from there.
+fn workspace_folder() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("The crate isn't in a workspace")
+}
+
+#[test]
+#[ignore = "needs a display, a GPU adapter, a UO installation and blessed references"]
+fn land_fixture_matches_references() {
+    let case_path = workspace_folder().join("render_tests/land_fixture.toml");
+    let status = Command::new(env!("CARGO_BIN_EXE_dynamapper"))
+        .current_dir(workspace_folder())
+        .arg("--render-test")
+        .arg(&case_path)
+        .status()
+        .expect("Can't run dynamapper");
+    assert!(
+        status.success(),
+        "Render test {case_path:?} failed ({status}): see the .actual.png and .diff.png next to the references."
+    );
+}
@@ -0,0 +1,216 @@
+// Benchmarks of the uocf readers, to track the performance of parser changes over time.
+//   cargo bench -p uocf --bench readers
+//   cargo bench -p uocf --bench readers -- map_blocks      (only the benchmarks whose name contains map_blocks)
+// The files are synthetic, written to a temporary folder at the start: the results don't depend on the UO client
+//  installed, and are comparable between machines running the same uocf version. Criterion compares each run with
+//  the previous one (target/criterion), and reports the changes.
+
+use std::path::{Path, PathBuf};
+
+use criterion::{BatchSize, BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use uocf::geo::land_texture_2d::{self, LandTextureSize, TexMap2D};
+use uocf::geo::map::{FileBackend, MapBlock, MapBlockRelPos, MapPlane, MapSizeCells};
+use uocf::tiledata::TileData;
+
+/// Size of the synthetic map plane: the one of Ilshenar, 288x200 blocks (11 MB).
+const MAP_SIZE: MapSizeCells = MapSizeCells {
+    width: 2304,
+    height: 1600,
+};
+/// Blocks loaded by each map_blocks benchmark: 8 whole block columns.
+const MAP_BLOCKS_PER_LOAD: usize = 8 * 200;
+/// Texture slots of texmaps.mul (TEXMAP_MAX_ID in land_texture_2d).
+const TEXMAP_SLOTS: u32 = 0x1388;
+
+/// Deterministic pseudo random numbers (xorshift32), so that every run reads the same data.
+struct XorShift(u32);
+impl XorShift {
+    fn next(&mut self) -> u32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0
+    }
+}
+
+fn bench_folder() -> PathBuf {
+    let folder = std::env::temp_dir().join("uocf_bench");
+    std::fs::create_dir_all(&folder).expect("Can't create the benchmark files folder");
+    folder
+}
+
+fn write_tiledata(folder: &Path, revision: u8) -> PathBuf {
+    let file_path = folder.join(format!("tiledata_rev{revision}.mul"));
+    TileData::new_empty(revision)
+        .and_then(|tiledata| tiledata.save(&file_path))
+        .expect("Can't write the benchmark tiledata");
+    file_path
+}
+
+/// Writes texmaps.mul and texidx.mul, with a texture every other slot: one big every 8, the others small.
+fn write_texmaps(folder: &Path) -> (PathBuf, PathBuf) {
+    let mut rng = XorShift(0x7E4A_0001);
+    let mut texmaps = Vec::new();
+    let mut texidx = Vec::new();
+    for id in 0..TEXMAP_SLOTS {
+        let (lookup, len) = if id % 2 == 0 {
+            let pixels = if id % 16 == 0 { 128 * 128 } else { 64 * 64 };
+            let lookup = texmaps.len() as u32;
+            for _ in 0..pixels {
+                texmaps.extend_from_slice(&(rng.next() as u16).to_le_bytes());
+            }
+            (lookup, pixels * 2)
+        } else {
+            (u32::MAX, 0)
+        };
+        for value in [lookup, len, 0] {
+            texidx.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    let texmaps_path = folder.join("texmaps.mul");
+    let texidx_path = folder.join("texidx.mul");
+    std::fs::write(&texmaps_path, texmaps).expect("Can't write the benchmark texmaps.mul");
+    std::fs::write(&texidx_path, texidx).expect("Can't write the benchmark texidx.mul");
+    (texmaps_path, texidx_path)
+}
+
+fn write_map(folder: &Path) -> PathBuf {
+    let mut rng = XorShift(0x3A9D_0002);
+    let block_count = MAP_SIZE.to_blocks().width * MAP_SIZE.to_blocks().height;
+    let mut map = Vec::with_capacity(MAP_SIZE.map_file_len() as usize);
+    for _ in 0..block_count {
+        map.extend_from_slice(&0u32.to_le_bytes()); // Block header.
+        for _ in 0..MapBlock::CELLS_PER_BLOCK {
+            let random = rng.next();
+            map.extend_from_slice(&((random & 0x3FFF) as u16).to_le_bytes());
+            map.push((random >> 16) as u8); // Z.
+        }
+    }
+    let map_path = folder.join("map0.mul");
+    std::fs::write(&map_path, map).expect("Can't write the benchmark map0.mul");
+    map_path
+}
+
+fn bench_tiledata(c: &mut Criterion) {
+    let folder = bench_folder();
+    let mut group = c.benchmark_group("tiledata_load");
+    // Revision 1 is the classic format, revision 3 the High Seas one with the most items.
+    for revision in [1, 3] {
+        let file_path = write_tiledata(&folder, revision);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("rev{revision}")),
+            &file_path,
+            |b, file_path| {
+                b.iter(|| {
+                    TileData::load(file_path.clone(), None)
+                        .expect("Can't load the benchmark tiledata")
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_texmap(c: &mut Criterion) {
+    let (texmaps_path, texidx_path) = write_texmaps(&bench_folder());
+    let mut group = c.benchmark_group("texmap");
+    group.sample_size(20);
+    group.bench_function("full_load", |b| {
+        b.iter(|| {
+            TexMap2D::load(texmaps_path.clone(), texidx_path.clone(), None)
+                .expect("Can't load the benchmark texmaps")
+        });
+    });
+    group.bench_function("lazy_load", |b| {
+        b.iter(|| {
+            TexMap2D::load_lazy(texmaps_path.clone(), texidx_path.clone(), None)
+                .expect("Can't load the benchmark texmaps")
+        });
+    });
+    group.finish();
+}
+
+fn bench_map_blocks(c: &mut Criterion) {
+    let map_path = write_map(&bench_folder());
+    let size_blocks = MAP_SIZE.to_blocks();
+    // Whole block columns are contiguous in the file: one read each. The same number of blocks scattered on the map
+    //  needs a seek for each of them.
+    let sequential_blocks: Vec<MapBlockRelPos> = (0..MAP_BLOCKS_PER_LOAD as u32)
+        .map(|i| MapBlockRelPos {
+            x: 100 + i / size_blocks.height,
+            y: i % size_blocks.height,
+        })
+        .collect();
+    let mut rng = XorShift(0x51C4_0003);
+    let mut random_blocks: Vec<MapBlockRelPos> = Vec::with_capacity(MAP_BLOCKS_PER_LOAD);
+    while random_blocks.len() < MAP_BLOCKS_PER_LOAD {
+        let pos = MapBlockRelPos {
+            x: rng.next() % size_blocks.width,
+            y: rng.next() % size_blocks.height,
+        };
+        if !random_blocks.contains(&pos) {
+            random_blocks.push(pos);
+        }
+    }
+
+    let mut group = c.benchmark_group("map_blocks");
+    group.throughput(Throughput::Elements(MAP_BLOCKS_PER_LOAD as u64));
+    for (backend_name, backend) in [("read", FileBackend::Read), ("mmap", FileBackend::Mmap)] {
+        for (order_name, blocks) in [
+            ("sequential", &sequential_blocks),
+            ("random", &random_blocks),
+        ] {
+            // A new map plane for every load, so that the blocks aren't cached yet.
+            group.bench_function(BenchmarkId::new(order_name, backend_name), |b| {
+                b.iter_batched(
+                    || {
+                        let map_plane =
+                            MapPlane::init_with_size(map_path.clone(), 0, backend, MAP_SIZE)
+                                .expect("Can't open the benchmark map");
+                        (map_plane, blocks.clone())
+                    },
+                    |(mut map_plane, mut blocks)| {
+                        map_plane
+                            .load_blocks(&mut blocks)
+                            .expect("Can't load the benchmark map blocks");
+                        map_plane
+                    },
+                    BatchSize::PerIteration,
+                );
+            });
+        }
+    }
+    group.finish();
+}
+
+fn bench_bgra5551_to_rgba8888(c: &mut Criterion) {
+    let mut rng = XorShift(0xC010_0004);
+    let mut group = c.benchmark_group("bgra5551_to_rgba8888");
+    for size in [LandTextureSize::Small, LandTextureSize::Big] {
+        let (width, height) = size.dimensions();
+        let pixels: Vec<u16> = (0..width * height).map(|_| rng.next() as u16).collect();
+        group.throughput(Throughput::Elements(pixels.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{width}x{height}")),
+            &pixels,
+            |b, pixels| {
+                let mut rgba_bytes = Vec::with_capacity(pixels.len() * 4);
+                b.iter(|| {
+                    rgba_bytes.clear();
+                    land_texture_2d::bgra5551_to_rgba8888(pixels, &mut rgba_bytes);
+                    rgba_bytes.len()
+                });
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    readers,
+    bench_tiledata,
+    bench_texmap,
+    bench_map_blocks,
+    bench_bgra5551_to_rgba8888
+);
+criterion_main!(readers);
@@ -0,0 +1,57 @@
+// Converts tiledata.mul to a JSON or TOML document and back (the format is chosen by the file extension).
+//   cargo run -p uocf --features serde --example tiledata_text -- export <tiledata.mul> <tiledata.toml|.json>
+//   cargo run -p uocf --features serde --example tiledata_text -- import <tiledata.toml|.json> <new tiledata.mul>
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use uocf::tiledata::TileData;
+
+const USAGE: &str = "Usage:\n  tiledata_text export <tiledata.mul> <output .json or .toml>\n  \
+                     tiledata_text import <input .json or .toml> <output tiledata.mul>";
+
+fn is_toml(file_path: &Path) -> Result<bool, String> {
+    match file_path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) if ext.eq_ignore_ascii_case("toml") => Ok(true),
+        Some(ext) if ext.eq_ignore_ascii_case("json") => Ok(false),
+        _ => Err(format!("'{}': use a .json or .toml file.", file_path.display())),
+    }
+}
+
+fn export(mul_path: &Path, doc_path: &Path) -> Result<(), String> {
+    let tiledata = TileData::load(mul_path.to_path_buf(), None).map_err(|e| e.to_string())?;
+    let text = if is_toml(doc_path)? {
+        tiledata.to_toml()
+    } else {
+        tiledata.to_json()
+    }
+    .map_err(|e| e.to_string())?;
+    std::fs::write(doc_path, text).map_err(|e| format!("Writing '{}': {e}", doc_path.display()))
+}
+
+fn import(doc_path: &Path, mul_path: &Path) -> Result<(), String> {
+    let text = std::fs::read_to_string(doc_path).map_err(|e| format!("Reading '{}': {e}", doc_path.display()))?;
+    let tiledata = if is_toml(doc_path)? {
+        TileData::from_toml(&text)
+    } else {
+        TileData::from_json(&text)
+    }
+    .map_err(|e| e.to_string())?;
+    tiledata.save(mul_path).map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.as_slice() {
+        [command, from, to] if command == "export" => export(&PathBuf::from(from), &PathBuf::from(to)),
+        [command, from, to] if command == "import" => import(&PathBuf::from(from), &PathBuf::from(to)),
+        _ => Err(USAGE.to_owned()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
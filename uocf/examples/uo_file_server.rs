@@ -0,0 +1,40 @@
+// Serves the files of a UO folder to the clients of uocf::remote (e.g. dynamapper, with uo_files.server set).
+//   cargo run -p uocf --example uo_file_server -- <UO folder> [address, default 0.0.0.0:2594]
+
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use uocf::remote;
+
+const USAGE: &str =
+    "Usage:\n  uo_file_server <UO folder> [address to listen on, default 0.0.0.0:2594]";
+
+fn run(folder: PathBuf, address: String) -> Result<(), String> {
+    if !folder.is_dir() {
+        return Err(format!("'{}' isn't a folder.", folder.display()));
+    }
+    let listener =
+        TcpListener::bind(&address).map_err(|e| format!("Listening on {address}: {e}"))?;
+    println!("Serving '{}' on {address}.", folder.display());
+    remote::serve(listener, folder).map_err(|e| e.to_string())
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let result = match args.as_slice() {
+        [folder] => run(
+            PathBuf::from(folder),
+            format!("0.0.0.0:{}", remote::DEFAULT_PORT),
+        ),
+        [folder, address] => run(PathBuf::from(folder), address.clone()),
+        _ => Err(USAGE.to_owned()),
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,278 @@
+#![allow(dead_code)]
+
+// Reader for art.mul/artidx.mul: the isometric art of land tiles and items (statics).
+// The first 0x4000 entries are land tiles: 44x44 diamonds stored raw, row by row (only the pixels inside the diamond).
+// The following entries are items (entry index = 0x4000 + item id): variable sized images, run-length encoded.
+// art.mul is big, so entries are read and decoded on request instead of being loaded all at once.
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use getset::Getters;
+use image::{DynamicImage, ImageBuffer};
+use std::io::{BufReader, Cursor, SeekFrom, prelude::*};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use crate::errors::{CursorReadExt, IoResultExt, Result, UocfError, file_name_of};
+use crate::vfs::{self, VfsFile};
+use crate::generic_index;
+use crate::utils::color::*;
+use crate::verdata::Verdata;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ArtKind {
+    #[default]
+    Land,
+    Item,
+}
+
+#[derive(Clone, Debug, Default, Getters)]
+pub struct ArtElement {
+    // Pixel data in art.mul is stored as bgra5551 (u16), but we convert it to rgba8888 before storing it.
+    // Pixels not covered by the art (outside the land diamond, or skipped by the item runs) are fully transparent.
+    #[get = "pub"]
+    id: u32,
+    #[get = "pub"]
+    kind: ArtKind,
+    #[get = "pub"]
+    width: u32,
+    #[get = "pub"]
+    height: u32,
+    #[get = "pub"]
+    pixel_data: Vec<u8>,
+}
+impl ArtElement {
+    const PIXEL_DATA_CHANNELS: usize = 4; // R, G, B, A
+
+    pub fn to_image(&self) -> Result<DynamicImage> {
+        let img: image::ImageBuffer<image::Rgba<u8>, _> =
+            ImageBuffer::from_vec(self.width, self.height, self.pixel_data.clone())
+                .ok_or_else(|| {
+                    UocfError::malformed("art", 0, format!("art 0x{:x}: pixel data doesn't match the size", self.id))
+                })?;
+        Ok(DynamicImage::ImageRgba8(img))
+    }
+
+    #[inline(always)]
+    fn put_pixel(pixel_data: &mut [u8], width: u32, x: u32, y: u32, color_16: u16) {
+        let mut pixel_16 = Bgra5551::new_from_val(color_16);
+        pixel_16.set_a(1);
+        let start = ((y * width + x) as usize) * Self::PIXEL_DATA_CHANNELS;
+        pixel_data[start..start + Self::PIXEL_DATA_CHANNELS]
+            .copy_from_slice(&pixel_16.as_rgba8888().value().to_le_bytes());
+    }
+
+    /// Decodes raw land art: a 44x44 diamond, the top half with rows growing by 2 pixels each, then the bottom half
+    ///  with rows shrinking by 2 pixels each.
+    fn decode_land(id: u32, data: &[u8]) -> Result<ArtElement> {
+        const SIZE: u32 = Art::LAND_ART_SIZE;
+        const HALF: u32 = SIZE / 2;
+
+        let mut element = ArtElement {
+            id,
+            kind: ArtKind::Land,
+            width: SIZE,
+            height: SIZE,
+            pixel_data: vec![0; (SIZE * SIZE) as usize * Self::PIXEL_DATA_CHANNELS],
+        };
+
+        let mut rdr = Cursor::new(data);
+        for y in 0..SIZE {
+            let (x_start, run) = if y < HALF {
+                (HALF - 1 - y, (y + 1) * 2)
+            } else {
+                (y - HALF, (SIZE - y) * 2)
+            };
+            for x in x_start..x_start + run {
+                let color_16 = rdr
+                    .read_field(Art::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || {
+                        format!("land art 0x{id:x}: pixel at row {y}, column {x}")
+                    })?;
+                Self::put_pixel(&mut element.pixel_data, SIZE, x, y, color_16);
+            }
+        }
+        Ok(element)
+    }
+
+    /// Decodes run-length encoded item art.
+    /// Layout: u32 header, u16 width, u16 height, a table with the start of each row (u16 offsets, counted in u16
+    ///  words from the end of the table), then for each row a list of (x offset, run length, run pixels) terminated
+    ///  by a (0, 0) pair.
+    fn decode_item(id: u32, data: &[u8]) -> Result<ArtElement> {
+        const HEADER_SIZE: u64 = 4 + 2 + 2;
+
+        let mut rdr = Cursor::new(data);
+        let strerr_base = format!("item art 0x{id:x}: ");
+        let _header = rdr
+            .read_field(Art::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || format!("{strerr_base}header"))?;
+        let width = rdr
+            .read_field(Art::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || format!("{strerr_base}width"))? as u32;
+        let height = rdr
+            .read_field(Art::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || format!("{strerr_base}height"))? as u32;
+        if width == 0 || height == 0 || width > Art::ITEM_ART_MAX_SIZE || height > Art::ITEM_ART_MAX_SIZE {
+            return Err(UocfError::malformed(
+                Art::FILE_NAME,
+                rdr.position(),
+                format!("{strerr_base}invalid size {width}x{height}."),
+            ));
+        }
+
+        let mut row_lookups = vec![0u16; height as usize];
+        rdr.read_field(Art::FILE_NAME, |rdr| rdr.read_u16_into::<LittleEndian>(&mut row_lookups), || format!("{strerr_base}row lookup table"))?;
+        let data_start = HEADER_SIZE + (height as u64 * 2);
+
+        let mut element = ArtElement {
+            id,
+            kind: ArtKind::Item,
+            width,
+            height,
+            pixel_data: vec![0; (width * height) as usize * Self::PIXEL_DATA_CHANNELS],
+        };
+
+        for (y, &row_lookup) in row_lookups.iter().enumerate() {
+            let y = y as u32;
+            rdr.set_position(data_start + (row_lookup as u64 * 2));
+            let mut x: u32 = 0;
+            loop {
+                let x_offset = rdr
+                    .read_field(Art::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || format!("{strerr_base}row {y} run offset"))? as u32;
+                let x_run = rdr
+                    .read_field(Art::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || format!("{strerr_base}row {y} run length"))? as u32;
+                if x_offset + x_run == 0 {
+                    break;
+                }
+                x += x_offset;
+                if x + x_run > width {
+                    return Err(UocfError::malformed(
+                        Art::FILE_NAME,
+                        rdr.position(),
+                        format!("{strerr_base}row {y} run ({x}..{}) exceeds the art width ({width}).", x + x_run),
+                    ));
+                }
+                for _ in 0..x_run {
+                    let color_16 = rdr
+                        .read_field(Art::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || format!("{strerr_base}pixel at row {y}, column {x}"))?;
+                    Self::put_pixel(&mut element.pixel_data, width, x, y, color_16);
+                    x += 1;
+                }
+            }
+        }
+        Ok(element)
+    }
+}
+
+pub struct Art {
+    artidx: generic_index::IndexFile,
+    // Entries are read on request: the reader is shared, so that the Art struct can be used through an immutable ref.
+    art_file_rdr: Mutex<BufReader<VfsFile>>,
+    art_file_size: u64,
+    // Patches in verdata.mul replace the art.mul entries with the same index.
+    verdata: Option<Arc<Verdata>>,
+}
+
+impl Art {
+    const FILE_NAME: &'static str = "art.mul";
+    pub const LAND_ART_QTY: u32 = 0x4000;
+    pub const LAND_ART_SIZE: u32 = 44;
+    // Sanity check for corrupted entries: no item art in the game data is this big.
+    const ITEM_ART_MAX_SIZE: u32 = 1024;
+
+    pub fn element_count(&self) -> usize {
+        self.artidx.element_count()
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.element_count()
+            .saturating_sub(Self::LAND_ART_QTY as usize)
+    }
+
+    /// Art of a land tile (44x44 diamond). Returns Ok(None) if there's no art for the tile.
+    pub fn land(&self, tile_id: u16) -> Result<Option<ArtElement>> {
+        if tile_id as u32 >= Self::LAND_ART_QTY {
+            return Ok(None);
+        }
+        self.element(tile_id as usize)
+    }
+
+    /// Art of an item (static). Returns Ok(None) if there's no art for the item.
+    pub fn item(&self, item_id: u16) -> Result<Option<ArtElement>> {
+        self.element(Self::LAND_ART_QTY as usize + item_id as usize)
+    }
+
+    /// Reads and decodes the entry at the given index of artidx.mul. Returns Ok(None) for unused entries.
+    pub fn element(&self, element_index: usize) -> Result<Option<ArtElement>> {
+        if let Some(patch_data) = self
+            .verdata
+            .as_ref()
+            .and_then(|v| v.patch_data(Verdata::FILE_ID_ART, element_index as u32))
+        {
+            return Self::decode_element(element_index, patch_data).map(Some);
+        }
+
+        if element_index >= self.artidx.element_count() {
+            return Ok(None);
+        }
+        let idx_elem = self.artidx.element(element_index)?;
+        let (Some(lookup), Some(len)) = (idx_elem.lookup(), idx_elem.len()) else {
+            return Ok(None);
+        };
+        if len == 0 || lookup as u64 + len as u64 > self.art_file_size {
+            return Ok(None);
+        }
+
+        let mut data = vec![0u8; len as usize];
+        {
+            let mut rdr = self
+                .art_file_rdr
+                .lock()
+                .map_err(|_| UocfError::io("Reading art.mul", std::io::Error::other("reader lock poisoned")))?;
+            rdr.seek(SeekFrom::Start(lookup as u64))
+                .io_context(|| format!("Seek to art entry 0x{element_index:x}"))?;
+            rdr.read_exact(&mut data)
+                .read_context(Self::FILE_NAME, lookup as u64, || format!("art entry 0x{element_index:x}"))?;
+        }
+
+        Self::decode_element(element_index, &data).map(Some)
+    }
+
+    fn decode_element(element_index: usize, data: &[u8]) -> Result<ArtElement> {
+        if (element_index as u32) < Self::LAND_ART_QTY {
+            ArtElement::decode_land(element_index as u32, data)
+        } else {
+            ArtElement::decode_item(element_index as u32 - Self::LAND_ART_QTY, data)
+        }
+    }
+
+    pub fn load(
+        art_file_path: PathBuf,
+        artidx_file_path: PathBuf,
+        verdata: Option<Arc<Verdata>>,
+    ) -> Result<Art> {
+        /* Open art.mul */
+        let art_file_name = file_name_of(&art_file_path)?;
+        let art_file_path = vfs::canonicalize(&art_file_path)
+            .io_context(|| format!("Check {art_file_name} path"))?;
+
+        let art_file_handle = vfs::open(&art_file_path)
+            .io_context(|| format!("Open art mul file at '{art_file_name}'"))?;
+        let art_file_size = art_file_handle
+            .len()
+            .io_context(|| format!("Get {art_file_name} size"))?;
+
+        /* Open artidx.mul */
+        let artidx = generic_index::IndexFile::load(artidx_file_path)?;
+
+        let art = Art {
+            artidx,
+            art_file_rdr: Mutex::new(BufReader::new(art_file_handle)),
+            art_file_size,
+            verdata,
+        };
+        println!(
+            "Indexed {} (0x{:x}) Art entries ({} items) from '{art_file_name}'.",
+            art.element_count(),
+            art.element_count(),
+            art.item_count()
+        );
+        Ok(art)
+    }
+}
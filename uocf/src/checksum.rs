@@ -0,0 +1,162 @@
+// Checksums of file contents, to tell whether a file changed (e.g. to invalidate a cache built from it).
+// - Checksum: not cryptographic, FNV-1a on 64 bit little endian words, then a final mix. The result is the same on
+//   every platform and uocf version, and doesn't depend on how the bytes are split between the update calls.
+// - Fingerprint: the size of a file and the checksum of some samples of it, instead of the whole content. It takes a
+//   few reads even for the big map files, and the client patches change the samples (a new size, new data in the
+//   index files, ...), but a change between the samples goes unnoticed.
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::errors::{IoResultExt, Result};
+use crate::vfs;
+
+const FNV_OFFSET_BASIS: u64 = 0xCBF2_9CE4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+#[derive(Clone, Debug)]
+pub struct Checksum {
+    hash: u64,
+    /// Bytes of the word not complete yet.
+    pending: [u8; 8],
+    pending_len: usize,
+    total_len: u64,
+}
+impl Default for Checksum {
+    fn default() -> Self {
+        Self {
+            hash: FNV_OFFSET_BASIS,
+            pending: [0; 8],
+            pending_len: 0,
+            total_len: 0,
+        }
+    }
+}
+impl Checksum {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add_word(&mut self, word: [u8; 8]) {
+        self.hash ^= u64::from_le_bytes(word);
+        self.hash = self.hash.wrapping_mul(FNV_PRIME);
+    }
+
+    pub fn update(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+        if self.pending_len > 0 {
+            let taken = bytes.len().min(8 - self.pending_len);
+            self.pending[self.pending_len..self.pending_len + taken]
+                .copy_from_slice(&bytes[..taken]);
+            self.pending_len += taken;
+            bytes = &bytes[taken..];
+            if self.pending_len < 8 {
+                return;
+            }
+            self.add_word(self.pending);
+            self.pending_len = 0;
+        }
+        let (words, rest) = bytes.as_chunks::<8>();
+        for &word in words {
+            self.add_word(word);
+        }
+        self.pending[..rest.len()].copy_from_slice(rest);
+        self.pending_len = rest.len();
+    }
+
+    /// The checksum of the bytes so far. The length is mixed in, so that trailing zeros change it.
+    pub fn finish(&self) -> u64 {
+        let mut hash = self.hash;
+        if self.pending_len > 0 {
+            let mut word = [0; 8];
+            word[..self.pending_len].copy_from_slice(&self.pending[..self.pending_len]);
+            hash = (hash ^ u64::from_le_bytes(word)).wrapping_mul(FNV_PRIME);
+        }
+        hash = (hash ^ self.total_len).wrapping_mul(FNV_PRIME);
+        // Final mix (the one of splitmix64): every input bit affects every output bit.
+        hash = (hash ^ (hash >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        hash = (hash ^ (hash >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        hash ^ (hash >> 31)
+    }
+
+    /// Adds everything rdr gives, to its end.
+    pub fn update_from_reader(&mut self, mut rdr: impl Read) -> io::Result<()> {
+        let mut buf = vec![0u8; 64 * 1024];
+        loop {
+            match rdr.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(len) => self.update(&buf[..len]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Fast fingerprint of a file: its size, and the checksum of its start, its end and samples spread in between.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Fingerprint {
+    pub size: u64,
+    pub hash: u64,
+}
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes, {:016x}", self.size, self.hash)
+    }
+}
+impl Fingerprint {
+    /// Bytes read at the start and at the end.
+    const EDGE_LEN: u64 = 64 * 1024;
+    /// Samples read in between, and their length.
+    const SAMPLE_COUNT: u64 = 16;
+    const SAMPLE_LEN: u64 = 4 * 1024;
+
+    pub fn of_reader(mut rdr: impl Read + Seek) -> io::Result<Fingerprint> {
+        let size = rdr.seek(SeekFrom::End(0))?;
+        let mut checksum = Checksum::new();
+        checksum.update(&size.to_le_bytes());
+        if size <= 2 * Self::EDGE_LEN + Self::SAMPLE_COUNT * Self::SAMPLE_LEN {
+            rdr.seek(SeekFrom::Start(0))?;
+            checksum.update_from_reader(&mut rdr)?;
+        } else {
+            let middle_len = size - 2 * Self::EDGE_LEN;
+            let samples = (0..Self::SAMPLE_COUNT).map(|i| {
+                let start =
+                    Self::EDGE_LEN + (middle_len - Self::SAMPLE_LEN) * i / (Self::SAMPLE_COUNT - 1);
+                (start, Self::SAMPLE_LEN)
+            });
+            let ranges = std::iter::once((0, Self::EDGE_LEN))
+                .chain(samples)
+                .chain(std::iter::once((size - Self::EDGE_LEN, Self::EDGE_LEN)));
+            let mut buf = Vec::new();
+            for (start, len) in ranges {
+                buf.resize(len as usize, 0);
+                rdr.seek(SeekFrom::Start(start))?;
+                rdr.read_exact(&mut buf)?;
+                checksum.update(&buf);
+            }
+        }
+        Ok(Fingerprint {
+            size,
+            hash: checksum.finish(),
+        })
+    }
+
+    /// The fingerprint of a file (or of a vfs file).
+    pub fn of_file(path: &Path) -> Result<Fingerprint> {
+        let context = || format!("Fingerprint '{}'", path.display());
+        let file = vfs::open(path).io_context(context)?;
+        Self::of_reader(io::BufReader::new(file)).io_context(context)
+    }
+
+    /// A single value for several fingerprints, in order: e.g. the key of a cache built from these files.
+    pub fn combine<'a>(fingerprints: impl IntoIterator<Item = &'a Fingerprint>) -> u64 {
+        let mut checksum = Checksum::new();
+        for fingerprint in fingerprints {
+            checksum.update(&fingerprint.size.to_le_bytes());
+            checksum.update(&fingerprint.hash.to_le_bytes());
+        }
+        checksum.finish()
+    }
+}
@@ -0,0 +1,125 @@
+#![allow(dead_code)]
+
+// Cliloc.<lang> (e.g. Cliloc.enu) holds the localized strings of the client, each one identified by a number.
+//  Tile names longer (and localized) than the 20 ASCII chars of tiledata are stored there too.
+// Layout: a header (u32, u16), then a list of entries: number (u32), flag (u8), text length (u16), text (UTF-8, not
+//  null-terminated).
+// Newer clients (since 7.0.100 or so) compress the file: that format isn't supported.
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::HashMap;
+use std::io::{Cursor, prelude::*};
+use std::path::PathBuf;
+
+use crate::errors::{CursorReadExt, IoResultExt, Result, UocfError, file_name_of};
+use crate::vfs;
+
+#[derive(Default)]
+pub struct Cliloc {
+    strings: HashMap<u32, String>,
+}
+
+impl Cliloc {
+    const HEADER_PACKED_SIZE: usize = 4 + 2;
+    const ENTRY_HEADER_PACKED_SIZE: usize = 4 + 1 + 2;
+    /// Names of the land tiles: cliloc number = LAND_NAMES_START + tile id.
+    pub const LAND_NAMES_START: u32 = 500_000;
+    /// Names of the items (statics) with id below ITEM_NAMES_HIGH_ID: cliloc number = ITEM_NAMES_START + item id.
+    pub const ITEM_NAMES_START: u32 = 1_020_000;
+    /// Names of the items with id from ITEM_NAMES_HIGH_ID up: cliloc number = ITEM_NAMES_HIGH_START + item id.
+    pub const ITEM_NAMES_HIGH_START: u32 = 1_078_872;
+    pub const ITEM_NAMES_HIGH_ID: u16 = 0x4000;
+
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub fn get(&self, number: u32) -> Option<&str> {
+        self.strings.get(&number).map(String::as_str)
+    }
+
+    /// Localized name of a land tile, if the cliloc has one.
+    pub fn land_tile_name(&self, tile_id: u16) -> Option<&str> {
+        self.get(Self::LAND_NAMES_START + tile_id as u32)
+            .filter(|name| !name.is_empty())
+    }
+
+    /// Localized name of an item (static), if the cliloc has one.
+    pub fn item_name(&self, item_id: u16) -> Option<&str> {
+        let number = if item_id < Self::ITEM_NAMES_HIGH_ID {
+            Self::ITEM_NAMES_START + item_id as u32
+        } else {
+            Self::ITEM_NAMES_HIGH_START + item_id as u32
+        };
+        self.get(number).filter(|name| !name.is_empty())
+    }
+
+    pub fn load(file_path: PathBuf) -> Result<Cliloc> {
+        let file_name = file_name_of(&file_path)?;
+        let file_path = vfs::canonicalize(&file_path)
+            .io_context(|| format!("Check {file_name} path"))?;
+
+        let mut file_handle = vfs::open(&file_path)
+            .io_context(|| format!("Open cliloc at '{}'", file_path.to_string_lossy()))?;
+        let file_size = file_handle
+            .len()
+            .io_context(|| format!("Get {file_name} size"))? as usize;
+
+        if file_size < Self::HEADER_PACKED_SIZE {
+            return Err(UocfError::malformed(
+                &file_name,
+                0,
+                format!("too short: {file_size} bytes, not enough for the header."),
+            ));
+        }
+
+        let mut cliloc_file_rdr = {
+            let mut buf = vec![0; file_size];
+            file_handle
+                .read_exact(buf.as_mut())
+                .io_context(|| format!("Read {file_name}"))?;
+            Cursor::new(buf)
+        };
+
+        let _header_1 =
+            cliloc_file_rdr.read_field(&file_name, |rdr| rdr.read_u32::<LittleEndian>(), || "header")?;
+        let _header_2 =
+            cliloc_file_rdr.read_field(&file_name, |rdr| rdr.read_u16::<LittleEndian>(), || "header")?;
+
+        // A compressed file doesn't parse as a list of entries.
+        let unsupported = |what: String| {
+            UocfError::unsupported_revision(&file_name, format!("{what} (compressed cliloc of a newer client?)"))
+        };
+        let mut cliloc = Cliloc::default();
+        while (cliloc_file_rdr.position() as usize) < file_size {
+            let entry_offset = cliloc_file_rdr.position();
+            if file_size - (entry_offset as usize) < Self::ENTRY_HEADER_PACKED_SIZE {
+                return Err(unsupported(format!("truncated entry header at 0x{entry_offset:X}")));
+            }
+            let number = cliloc_file_rdr
+                .read_u32::<LittleEndian>()
+                .read_context(&file_name, entry_offset, || "entry number")?;
+            let _flag = cliloc_file_rdr
+                .read_u8()
+                .read_context(&file_name, entry_offset, || "entry flag")?;
+            let text_len = cliloc_file_rdr
+                .read_u16::<LittleEndian>()
+                .read_context(&file_name, entry_offset, || "entry text length")? as usize;
+
+            let mut text = vec![0; text_len];
+            cliloc_file_rdr
+                .read_exact(&mut text)
+                .map_err(|_| unsupported(format!("truncated text of entry {number} at 0x{entry_offset:X}")))?;
+            let text = String::from_utf8(text)
+                .map_err(|_| unsupported(format!("text of entry {number} at 0x{entry_offset:X} isn't UTF-8")))?;
+            cliloc.strings.insert(number, text);
+        }
+        println!("Loaded {} Cliloc strings from '{file_name}'.", cliloc.len());
+
+        Ok(cliloc)
+    }
+}
@@ -1,10 +1,137 @@
-#[macro_export]
-macro_rules! eyre_imports {
-    () => {
-        use color_eyre::eyre::{
-            self,    // for eyre::Result
-            eyre,    // for eyre! macro
-            WrapErr, // for wrap_err* methods
-        };
-    };
+// Errors returned by uocf.
+// Every fallible function returns a UocfError, so that the caller can tell the error kinds apart (e.g. fall back to
+//  another file format if one is missing, skip a malformed entry) instead of getting just a message.
+// Context (the file, the offset and what was being read) is stored in the variants; wrapping the errors with more
+//  context, eyre style, is left to the application.
+
+use std::fmt;
+use std::io::{self, Cursor};
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum UocfError {
+    /// A file operation (open, read, seek, ...) failed.
+    Io { context: String, source: io::Error },
+    /// The file content isn't valid: truncated data, values out of the expected bounds, ...
+    /// The offset is the position, in the file or in the entry being decoded, of the data being read.
+    Malformed { file: String, offset: u64, what: String },
+    /// The requested element (id, index, coordinates) isn't in the data.
+    OutOfRange { what: String },
+    /// The file is in a format or revision that isn't supported (e.g. a compressed one).
+    UnsupportedRevision { file: String, what: String },
+}
+
+pub type Result<T> = std::result::Result<T, UocfError>;
+
+impl UocfError {
+    pub(crate) fn io(context: impl Into<String>, source: io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn malformed(file: impl Into<String>, offset: u64, what: impl Into<String>) -> Self {
+        Self::Malformed {
+            file: file.into(),
+            offset,
+            what: what.into(),
+        }
+    }
+
+    pub(crate) fn out_of_range(what: impl Into<String>) -> Self {
+        Self::OutOfRange { what: what.into() }
+    }
+
+    pub(crate) fn unsupported_revision(file: impl Into<String>, what: impl Into<String>) -> Self {
+        Self::UnsupportedRevision {
+            file: file.into(),
+            what: what.into(),
+        }
+    }
+
+    /// Is it caused by a missing file?
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::Io { source, .. } if source.kind() == io::ErrorKind::NotFound)
+    }
+}
+
+impl fmt::Display for UocfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io { context, source } => write!(f, "{context}: {source}"),
+            Self::Malformed { file, offset, what } => write!(f, "Malformed {file} (at 0x{offset:X}): {what}"),
+            Self::OutOfRange { what } => write!(f, "Out of range: {what}"),
+            Self::UnsupportedRevision { file, what } => write!(f, "Unsupported {file}: {what}"),
+        }
+    }
+}
+
+impl std::error::Error for UocfError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Name of the file at file_path, for the error messages. A path without one (e.g. "..") is an error of the caller.
+pub(crate) fn file_name_of(file_path: &Path) -> Result<String> {
+    match file_path.file_name() {
+        Some(file_name) => Ok(file_name.to_string_lossy().into_owned()),
+        None => Err(UocfError::io(
+            format!("Open {file_path:?}"),
+            io::Error::new(io::ErrorKind::InvalidInput, "the path has no file name"),
+        )),
+    }
+}
+
+/// Conversion of the std::io errors to UocfError, with their context.
+pub(crate) trait IoResultExt<T> {
+    /// For file operations: the error becomes UocfError::Io.
+    fn io_context<S: Into<String>>(self, context: impl FnOnce() -> S) -> Result<T>;
+    /// For reads of the file content: running out of data means that the file is malformed (truncated), other
+    ///  errors become UocfError::Io.
+    fn read_context<S: Into<String>>(self, file: &str, offset: u64, what: impl FnOnce() -> S) -> Result<T>;
+}
+
+impl<T> IoResultExt<T> for io::Result<T> {
+    fn io_context<S: Into<String>>(self, context: impl FnOnce() -> S) -> Result<T> {
+        self.map_err(|e| UocfError::io(context(), e))
+    }
+
+    fn read_context<S: Into<String>>(self, file: &str, offset: u64, what: impl FnOnce() -> S) -> Result<T> {
+        self.map_err(|e| {
+            let what = what().into();
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                UocfError::malformed(file, offset, format!("{what}: unexpected end of data"))
+            } else {
+                UocfError::io(format!("Reading {file} at 0x{offset:X}: {what}"), e)
+            }
+        })
+    }
+}
+
+/// Reads of the content of a file loaded in memory.
+pub(crate) trait CursorReadExt: Sized {
+    /// Reads with read, like IoResultExt::read_context; the offset of a failure is the one where the read started.
+    fn read_field<T, S: Into<String>>(
+        &mut self,
+        file: &str,
+        read: impl FnOnce(&mut Self) -> io::Result<T>,
+        what: impl FnOnce() -> S,
+    ) -> Result<T>;
+}
+
+impl<B: AsRef<[u8]>> CursorReadExt for Cursor<B> {
+    fn read_field<T, S: Into<String>>(
+        &mut self,
+        file: &str,
+        read: impl FnOnce(&mut Self) -> io::Result<T>,
+        what: impl FnOnce() -> S,
+    ) -> Result<T> {
+        let offset = self.position();
+        read(self).read_context(file, offset, what)
+    }
 }
@@ -0,0 +1,135 @@
+// Parser of the .def text files (body.def, bodyconv.def, gump.def, art.def, ... and uomap.def).
+// - An entry per line, made of fields separated by spaces or tabs.
+// - A field between braces is a group of values, separated by commas and/or spaces: "400 {402, 403} 0".
+// - '#' and "//" start a comment, up to the end of the line. Empty lines are skipped.
+// The meaning of the fields is left to the parsers of the single files (e.g. geo::map_def).
+
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::errors::{IoResultExt, Result, UocfError};
+use crate::vfs;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DefField {
+    Value(String),
+    /// Values between braces.
+    Group(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+pub struct DefEntry {
+    /// Offset of the line in the file, for the error messages.
+    pub offset: u64,
+    pub fields: Vec<DefField>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DefFile {
+    pub file_name: String,
+    pub entries: Vec<DefEntry>,
+}
+
+impl DefFile {
+    pub fn load(file_path: &Path) -> Result<DefFile> {
+        let file_name = file_path
+            .file_name()
+            .map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        let text = vfs::read_to_string(file_path).io_context(|| format!("Read {file_name}"))?;
+        Self::parse(&file_name, &text)
+    }
+
+    /// file_name is used only in the error messages.
+    pub fn parse(file_name: &str, text: &str) -> Result<DefFile> {
+        let mut entries = Vec::new();
+        let mut offset: u64 = 0;
+        for line in text.split_inclusive('\n') {
+            let line_offset = offset;
+            offset += line.len() as u64;
+
+            let content = match (line.find('#'), line.find("//")) {
+                (Some(a), Some(b)) => &line[..a.min(b)],
+                (Some(a), None) | (None, Some(a)) => &line[..a],
+                (None, None) => line,
+            };
+            let fields = Self::parse_fields(content)
+                .map_err(|what| UocfError::malformed(file_name, line_offset, what))?;
+            if !fields.is_empty() {
+                entries.push(DefEntry {
+                    offset: line_offset,
+                    fields,
+                });
+            }
+        }
+        Ok(DefFile {
+            file_name: file_name.to_owned(),
+            entries,
+        })
+    }
+
+    fn parse_fields(content: &str) -> std::result::Result<Vec<DefField>, String> {
+        let mut fields = Vec::new();
+        let mut rest = content.trim_start();
+        while !rest.is_empty() {
+            if let Some(group) = rest.strip_prefix('{') {
+                let Some(group_end) = group.find('}') else {
+                    return Err("unclosed '{'".to_owned());
+                };
+                let values = group[..group_end]
+                    .split(|c: char| c == ',' || c.is_whitespace())
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_owned)
+                    .collect();
+                fields.push(DefField::Group(values));
+                rest = &group[group_end + 1..];
+            } else {
+                let value_end = rest
+                    .find(|c: char| c.is_whitespace() || c == '{')
+                    .unwrap_or(rest.len());
+                let value = &rest[..value_end];
+                if value.contains('}') {
+                    return Err("'}' without '{'".to_owned());
+                }
+                fields.push(DefField::Value(value.to_owned()));
+                rest = &rest[value_end..];
+            }
+            rest = rest.trim_start();
+        }
+        Ok(fields)
+    }
+}
+
+impl DefEntry {
+    /// The values of a field: the single one, or the ones of a group. Empty if there's no such field.
+    pub fn values(&self, field_index: usize) -> &[String] {
+        match self.fields.get(field_index) {
+            Some(DefField::Value(value)) => std::slice::from_ref(value),
+            Some(DefField::Group(values)) => values,
+            None => &[],
+        }
+    }
+
+    /// A single value field, parsed. what names the field in the error message.
+    pub fn parse_value<T: FromStr>(
+        &self,
+        file_name: &str,
+        field_index: usize,
+        what: &str,
+    ) -> Result<T> {
+        match self.fields.get(field_index) {
+            Some(DefField::Value(value)) => value.parse().map_err(|_| {
+                UocfError::malformed(file_name, self.offset, format!("invalid {what}: '{value}'"))
+            }),
+            Some(DefField::Group(_)) => Err(UocfError::malformed(
+                file_name,
+                self.offset,
+                format!("{what} can't be a group"),
+            )),
+            None => Err(UocfError::malformed(
+                file_name,
+                self.offset,
+                format!("missing {what}"),
+            )),
+        }
+    }
+}
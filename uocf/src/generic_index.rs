@@ -1,12 +1,11 @@
 #![allow(dead_code)]
 
-crate::eyre_imports!();
 use byteorder::{LittleEndian, ReadBytesExt};
-use std::fs::File;
 use std::io::{prelude::*, Cursor};
 use std::path::PathBuf;
 
-use super::utils::math::*;
+use crate::errors::{CursorReadExt, IoResultExt, Result, UocfError, file_name_of};
+use crate::vfs;
 
 #[derive(Clone, Debug, Default)]
 pub struct IndexElement {
@@ -16,7 +15,7 @@ pub struct IndexElement {
 }
 impl IndexElement {
     const INVALID_LOOKUP: u32 = 0xFFFFFFFF;
-    const PACKED_SIZE: u32 = 4 + 4 + 4;
+    pub(crate) const PACKED_SIZE: u32 = 4 + 4 + 4;
 
     pub fn lookup(&self) -> Option<u32> {
         if self.lookup == Self::INVALID_LOOKUP || self.extra == Self::INVALID_LOOKUP {
@@ -49,59 +48,57 @@ pub fn element_count(&self) -> usize {
         self.file_data.len()
     }
 
-    pub fn element(&self, element_index: usize) -> eyre::Result<&IndexElement> {
+    pub fn element(&self, element_index: usize) -> Result<&IndexElement> {
         if element_index >= self.file_data.len() {
-            return Err(eyre!(
-                "IndexFile: requested element with out of range index ({element_index})."
-                    .to_owned()
-            ));
+            return Err(UocfError::out_of_range(format!(
+                "index file element {element_index} (the file has {} elements)",
+                self.file_data.len()
+            )));
         }
         Ok(&self.file_data[element_index])
     }
 
-    pub fn load(file_path: PathBuf) -> eyre::Result<IndexFile> {
-        let file_name = file_path
-            .file_name()
-            .expect("Provided file path without filename.")
-            .to_string_lossy();
-        let file_path = file_path
-            .canonicalize()
-            .wrap_err_with(|| format!("Check {file_name} path"))?;
-
-        let mut file_handle = File::open(&file_path)
-            .wrap_err_with(|| format!("Open index mul file at '{file_name}'"))?;
-        let file_metadata = file_handle
-            .metadata()
-            .wrap_err("Get {file_name} metadata")?;
-        let file_size = downcast_ceil_usize(file_metadata.len());
+    pub fn load(file_path: PathBuf) -> Result<IndexFile> {
+        let file_name = file_name_of(&file_path)?;
+        let file_path = vfs::canonicalize(&file_path)
+            .io_context(|| format!("Check {file_name} path"))?;
+
+        let file_handle = vfs::open(&file_path)
+            .io_context(|| format!("Open index mul file at '{file_name}'"))?;
+        Self::from_reader(file_handle, &file_name)
+    }
+
+    /// Like load, reading the whole index file from rdr: a file, a byte slice (std::io::Cursor), an archive entry...
+    ///  file_name is used in the error messages.
+    pub fn from_reader(mut rdr: impl Read, file_name: &str) -> Result<IndexFile> {
+        let mut rdr_buf = Vec::new();
+        rdr.read_to_end(&mut rdr_buf)
+            .io_context(|| format!("Read {file_name}"))?;
+        let file_size = rdr_buf.len();
 
         let index_element_qty = file_size / IndexElement::PACKED_SIZE as usize;
         let mut index_file = IndexFile {
             file_data: vec![IndexElement::default(); index_element_qty],
         };
+        let mut index_file_rdr = Cursor::new(rdr_buf);
 
-        let mut index_file_rdr = {
-            let mut rdr_buf = vec![0; file_size];
-            file_handle
-                .read_exact(rdr_buf.as_mut())
-                .wrap_err("Read index file")?;
-            Cursor::new(rdr_buf)
-        };
-
-        let strerr_base = "Reading index data for element ";
+        let strerr_base = "index data for element ";
         let mut i_elem = 0;
         for elem in index_file.file_data.iter_mut() {
             elem.lookup = index_file_rdr
-                .read_u32::<LittleEndian>()
-                .wrap_err_with(|| format!("{}0x{:x}: Reading {}", strerr_base, i_elem, "lookup"))?;
+                .read_field(file_name, |rdr| rdr.read_u32::<LittleEndian>(), || {
+                    format!("{}0x{:x}: {}", strerr_base, i_elem, "lookup")
+                })?;
 
             elem.size = index_file_rdr
-                .read_u32::<LittleEndian>()
-                .wrap_err_with(|| format!("{}0x{:x}: Reading {}", strerr_base, i_elem, "size"))?;
+                .read_field(file_name, |rdr| rdr.read_u32::<LittleEndian>(), || {
+                    format!("{}0x{:x}: {}", strerr_base, i_elem, "size")
+                })?;
 
             elem.extra = index_file_rdr
-                .read_u32::<LittleEndian>()
-                .wrap_err_with(|| format!("{}0x{:x}: Reading {}", strerr_base, i_elem, "extra"))?;
+                .read_field(file_name, |rdr| rdr.read_u32::<LittleEndian>(), || {
+                    format!("{}0x{:x}: {}", strerr_base, i_elem, "extra")
+                })?;
             i_elem += 1;
         }
         println!(
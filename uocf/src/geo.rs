@@ -3,3 +3,7 @@
 
 pub mod land_texture_2d;
 pub mod map;
+pub mod map_def;
+pub mod sextant;
+pub mod statics;
+pub mod surface;
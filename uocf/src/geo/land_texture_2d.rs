@@ -1,16 +1,19 @@
 #![allow(dead_code)]
 
-crate::eyre_imports!();
 use byteorder::{LittleEndian, ReadBytesExt};
 use getset::Getters;
 use image::{DynamicImage, ImageBuffer, RgbaImage};
 use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fs::File;
 
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Mutex, OnceLock, PoisonError};
 
+use crate::errors::{IoResultExt, Result, UocfError, file_name_of};
+use crate::vfs::{self, VfsRead};
 use crate::generic_index;
+use crate::verdata::Verdata;
 use crate::utils::color::*;
 use crate::utils::math::*;
 use bytemuck;
@@ -88,7 +91,7 @@ pub fn size_y(&self) -> u32 {
     }
 
     #[must_use]
-    pub fn to_image(&self) -> eyre::Result<DynamicImage> {
+    pub fn to_image(&self) -> Result<DynamicImage> {
         /*  // Less efficient way?
         let mut built_img = RgbImage::new(size.0, size.1);
         for y in 0..size.1 {
@@ -105,7 +108,9 @@ pub fn to_image(&self) -> eyre::Result<DynamicImage> {
 
         let img: image::ImageBuffer<image::Rgba<u8>, _> =
             ImageBuffer::from_vec(self.size_x(), self.size_y(), self.pixel_data.clone())
-                .ok_or(eyre!("Invalid Texture Data"))?;
+                .ok_or_else(|| {
+                    UocfError::malformed("texmaps.mul", 0, format!("texture 0x{:x}: pixel data doesn't match the size", self.id))
+                })?;
         //image::save_buffer("./test.png", &buf, size.0, size.1, image::ColorType::Rgba8);
         let img = DynamicImage::ImageRgba8(img);
         //Image::from_dynamic
@@ -113,16 +118,74 @@ pub fn to_image(&self) -> eyre::Result<DynamicImage> {
     }
 }
 
-#[derive(Debug)]
+/// Converts texmaps.mul pixels (bgra5551, little endian) to rgba8888 bytes, appended to rgba_bytes. The alpha is
+///  always opaque. The pixels are converted 16 at a time with SIMD, the remainder one by one.
+pub fn bgra5551_to_rgba8888(pixels_5551: &[u16], rgba_bytes: &mut Vec<u8>) {
+    let (pixel_data_u16_prefix, pixel_data_u16_suffix) = pixels_5551.as_chunks::<16>();
+
+    for &chunk_array in pixel_data_u16_prefix {
+        #[allow(unused_mut)]
+        let mut chunk = u16x16::new(chunk_array);
+
+        #[cfg(target_endian = "big")]
+        {
+            chunk = chunk.swap_bytes();
+        }
+
+        let b_u16: u16x16 = (chunk & u16x16::splat(0x1F)) << 3;
+        let g_u16: u16x16 = ((chunk >> 5) & u16x16::splat(0x1F)) << 3;
+        let r_u16: u16x16 = ((chunk >> 10) & u16x16::splat(0x1F)) << 3;
+        let a_u16: u16x16 = u16x16::splat(0xFF); // Alpha is set to 255
+
+        // Now convert u16x16 to [u32; 16]
+        let mut rgba_u32_array = [0u32; 16];
+        for i in 0..16 {
+            let r_val = r_u16.as_array_ref()[i] as u32;
+            let g_val = g_u16.as_array_ref()[i] as u32;
+            let b_val = b_u16.as_array_ref()[i] as u32;
+            let a_val = a_u16.as_array_ref()[i] as u32;
+            rgba_u32_array[i] = (a_val << 24) | (b_val << 16) | (g_val << 8) | r_val;
+        }
+        rgba_bytes.extend_from_slice(bytemuck::cast_slice(&rgba_u32_array));
+    }
+
+    for &pixel_16_val in pixel_data_u16_suffix {
+        #[allow(unused_mut)]
+        let mut pixel_16 = Bgra5551::new_from_val(pixel_16_val);
+        pixel_16.set_a(1);
+        rgba_bytes.extend_from_slice(pixel_16.as_rgba8888().value().to_le_bytes().as_ref());
+    }
+}
+
+/// Texture slots read from texidx.mul.
+const TEXMAP_MAX_ID: u32 = 0x1388;
+
 pub struct TexMap2D {
-    file_data: Vec<Texture2DElement>, //HashMap<u32, Texture2DElement>,
+    file_data: Vec<OnceLock<Texture2DElement>>, //HashMap<u32, Texture2DElement>,
+    /// In the lazy mode, where the textures not requested yet are read from.
+    lazy: Option<LazyTexMaps>,
+}
+impl std::fmt::Debug for TexMap2D {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TexMap2D")
+            .field("len", &self.file_data.len())
+            .field("lazy", &self.lazy.is_some())
+            .finish()
+    }
 }
 
 impl TexMap2D {
+    const FILE_NAME: &'static str = "texmaps.mul";
+
     pub fn len(&self) -> usize {
         self.file_data.len()
     }
 
+    /// Whether the textures are decoded on the first request (load_lazy), instead of all at once.
+    pub fn is_lazy(&self) -> bool {
+        self.lazy.is_some()
+    }
+
     pub fn element(&self, element_index: usize) -> Option<&Texture2DElement> {
         if element_index >= self.file_data.len() {
             /*return Err(eyre!(
@@ -131,7 +194,13 @@ pub fn element(&self, element_index: usize) -> Option<&Texture2DElement> {
             return None;
         }
         //println!("Requested element {element_index} from texmap.mul.");
-        let element: &Texture2DElement = &self.file_data[element_index];
+        // Loaded whole, every slot is set already. Lazily, the texture is read the first time it's requested.
+        let element: &Texture2DElement = self.file_data[element_index].get_or_init(|| {
+            self.lazy
+                .as_ref()
+                .map(|lazy| lazy.decode(element_index))
+                .unwrap_or_default()
+        });
         if !element.valid {
             /*return Err(eyre!(
                 "TexMap2d: requested invalid/uninitialized element ({element_index})."
@@ -145,43 +214,297 @@ pub fn element(&self, element_index: usize) -> Option<&Texture2DElement> {
     pub fn load(
         texmap_file_path: PathBuf,
         texmap_idx_file_path: PathBuf,
-    ) -> eyre::Result<TexMap2D> {
+        verdata: Option<&Verdata>,
+    ) -> Result<TexMap2D> {
+        Self::load_with_progress(texmap_file_path, texmap_idx_file_path, verdata, |_, _| {})
+    }
+
+    /// Like load, calling progress(decoded, total) each time a texture is decoded. It's called from the decoding
+    ///  threads: two calls may come out of order.
+    pub fn load_with_progress(
+        texmap_file_path: PathBuf,
+        texmap_idx_file_path: PathBuf,
+        verdata: Option<&Verdata>,
+        progress: impl Fn(usize, usize) + Sync,
+    ) -> Result<TexMap2D> {
+        let (texmap_file_handle, texidx) = Self::open_files(texmap_file_path, texmap_idx_file_path)?;
+        Self::from_reader_with_progress(BufReader::new(texmap_file_handle), &texidx, verdata, progress)
+    }
+
+    /// Lazy mode: only texidx.mul is read here. Each texture is read from texmaps.mul and decoded the first time it's
+    ///  requested (element), then kept. Starts faster, and takes less memory if only a few textures are used.
+    pub fn load_lazy(
+        texmap_file_path: PathBuf,
+        texmap_idx_file_path: PathBuf,
+        verdata: Option<&Verdata>,
+    ) -> Result<TexMap2D> {
+        let (texmap_file_handle, texidx) = Self::open_files(texmap_file_path, texmap_idx_file_path)?;
+        Self::from_reader_lazy(texmap_file_handle, &texidx, verdata)
+    }
+
+    /// Opens texmaps.mul and reads texidx.mul.
+    fn open_files(
+        texmap_file_path: PathBuf,
+        texmap_idx_file_path: PathBuf,
+    ) -> Result<(vfs::VfsFile, generic_index::IndexFile)> {
         /* Open texmap.mul */
-        let texmap_file_name = texmap_file_path
-            .file_name()
-            .expect("Provided file path without filename.")
-            .to_string_lossy();
-        let texmap_file_path = texmap_file_path
-            .canonicalize()
-            .wrap_err_with(|| format!("Check {texmap_file_name} path"))?;
-
-        let texmap_file_handle = File::open(&texmap_file_path)
-            .wrap_err_with(|| format!("Open map textures mul file at '{texmap_file_name}'"))?;
-        let texmap_file_metadata = texmap_file_handle
-            .metadata()
-            .wrap_err_with(|| format!("Get {texmap_file_name} metadata"))?;
-        let texmap_file_size = downcast_ceil_usize(texmap_file_metadata.len());
-        let mut texmap_file_rdr = BufReader::new(texmap_file_handle);
+        let texmap_file_name = file_name_of(&texmap_file_path)?;
+        let texmap_file_path = vfs::canonicalize(&texmap_file_path)
+            .io_context(|| format!("Check {texmap_file_name} path"))?;
+
+        let texmap_file_handle = vfs::open(&texmap_file_path)
+            .io_context(|| format!("Open map textures mul file at '{texmap_file_name}'"))?;
 
         /* Open texidx.mul */
         let texidx: generic_index::IndexFile =
             generic_index::IndexFile::load(texmap_idx_file_path)?;
+        Ok((texmap_file_handle, texidx))
+    }
+
+    /// Like load, reading the textures from texmap_rdr (the whole texmaps.mul: a file, a byte slice with
+    ///  std::io::Cursor, an archive entry...), at the positions given by texidx.
+    pub fn from_reader(
+        texmap_file_rdr: impl Read + Seek,
+        texidx: &generic_index::IndexFile,
+        verdata: Option<&Verdata>,
+    ) -> Result<TexMap2D> {
+        Self::from_reader_with_progress(texmap_file_rdr, texidx, verdata, |_, _| {})
+    }
 
-        /* Read whole texidx.mul to get texmap index data */
-        const TEXMAP_MAX_ID: u32 = 0x1388;
-        let mut texmap = TexMap2D {
+    /// Like from_reader, with the progress callback of load_with_progress.
+    /// The raw pixels of every texture are read first, in file order. Then the textures are decoded in parallel.
+    pub fn from_reader_with_progress(
+        mut texmap_file_rdr: impl Read + Seek,
+        texidx: &generic_index::IndexFile,
+        verdata: Option<&Verdata>,
+        progress: impl Fn(usize, usize) + Sync,
+    ) -> Result<TexMap2D> {
+        let texmap_file_name = Self::FILE_NAME;
+        let texmap_file_size = texmap_file_rdr
+            .seek(SeekFrom::End(0))
+            .io_context(|| format!("Get {texmap_file_name} size"))?;
+        let entries = texture_entries(texidx, verdata, downcast_ceil_usize(texmap_file_size))?;
+        let raw_textures = entries
+            .iter()
+            .map(|entry| entry.read_raw(&mut texmap_file_rdr))
+            .collect::<Result<Vec<RawTexture>>>()?;
+
+        let mut file_data = vec![Texture2DElement::default(); TEXMAP_MAX_ID as usize];
+        for texture in decode_textures(&raw_textures, &progress) {
+            let id = texture.id as usize;
+            file_data[id] = texture;
+        }
+        let texmap = TexMap2D {
             //file_data: vec![Texture2DElement::default(); texidx.element_count()],
-            file_data: vec![Texture2DElement::default(); TEXMAP_MAX_ID as usize],
+            file_data: file_data.into_iter().map(OnceLock::from).collect(),
+            lazy: None,
+        };
+
+        println!(
+            "Parsed {} (0x{:x}) Map Tile texture slots, loaded {} (0x{:x}) valid.",
+            texidx.element_count(),
+            texidx.element_count(),
+            entries.len(),
+            entries.len()
+        );
+        log_patched(&entries);
+
+        Ok(texmap)
+    }
+
+    /// Like load_lazy, reading the textures from texmap_rdr when they're requested.
+    pub fn from_reader_lazy(
+        mut texmap_file_rdr: impl VfsRead + 'static,
+        texidx: &generic_index::IndexFile,
+        verdata: Option<&Verdata>,
+    ) -> Result<TexMap2D> {
+        let texmap_file_name = Self::FILE_NAME;
+        let texmap_file_size = texmap_file_rdr
+            .seek(SeekFrom::End(0))
+            .io_context(|| format!("Get {texmap_file_name} size"))?;
+        let entries = texture_entries(texidx, verdata, downcast_ceil_usize(texmap_file_size))?;
+
+        println!(
+            "Parsed {} (0x{:x}) Map Tile texture slots, {} (0x{:x}) valid, decoded on request.",
+            texidx.element_count(),
+            texidx.element_count(),
+            entries.len(),
+            entries.len()
+        );
+        log_patched(&entries);
+
+        // The patches are copied: the Verdata is only borrowed here.
+        let mut entries_by_id: Vec<Option<TextureEntry<'static>>> = (0..TEXMAP_MAX_ID).map(|_| None).collect();
+        for entry in entries {
+            let id = entry.id as usize;
+            entries_by_id[id] = Some(entry.into_owned());
+        }
+        Ok(TexMap2D {
+            file_data: (0..TEXMAP_MAX_ID).map(|_| OnceLock::new()).collect(),
+            lazy: Some(LazyTexMaps {
+                rdr: Mutex::new(Box::new(texmap_file_rdr)),
+                entries: entries_by_id,
+            }),
+        })
+    }
+}
+
+/// Start of a texture cache file (write_rgba_cache), and the version of its layout.
+const RGBA_CACHE_MAGIC: &[u8; 8] = b"UOCFTEX\0";
+const RGBA_CACHE_VERSION: u32 = 1;
+
+impl TexMap2D {
+    const RGBA_CACHE_NAME: &'static str = "texture cache";
+
+    /// Writes the decoded (rgba8888) textures to wtr, tagged with key: e.g. a checksum of the files they come from.
+    ///  read_rgba_cache reads them back without decoding texmaps.mul again. In the lazy mode, every texture is decoded
+    ///  first.
+    /// Layout: magic, version (u32), key (u64), texture count (u32), then each texture: id (u32), size (u8: 0 small,
+    ///  1 big) and its pixels. Little endian.
+    pub fn write_rgba_cache(&self, mut wtr: impl Write, key: u64) -> Result<()> {
+        let textures: Vec<&Texture2DElement> = (0..self.len()).filter_map(|id| self.element(id)).collect();
+        let mut header = Vec::with_capacity(24);
+        header.extend_from_slice(RGBA_CACHE_MAGIC);
+        header.extend_from_slice(&RGBA_CACHE_VERSION.to_le_bytes());
+        header.extend_from_slice(&key.to_le_bytes());
+        header.extend_from_slice(&(textures.len() as u32).to_le_bytes());
+        let context = || format!("Write the {}", Self::RGBA_CACHE_NAME);
+        wtr.write_all(&header).io_context(context)?;
+        for texture in textures {
+            let size_byte: u8 = match texture.size {
+                LandTextureSize::Small => 0,
+                LandTextureSize::Big => 1,
+            };
+            wtr.write_all(&texture.id.to_le_bytes()).io_context(context)?;
+            wtr.write_all(&[size_byte]).io_context(context)?;
+            wtr.write_all(&texture.pixel_data).io_context(context)?;
+        }
+        wtr.flush().io_context(context)
+    }
+
+    /// Reads the textures written by write_rgba_cache. None if the cache was written with another key, or with
+    ///  another version of the layout: it's stale, and texmaps.mul must be loaded again.
+    pub fn read_rgba_cache(mut rdr: impl Read, key: u64) -> Result<Option<TexMap2D>> {
+        let file_name = Self::RGBA_CACHE_NAME;
+        let mut header = [0u8; 24];
+        match rdr.read_exact(&mut header) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(UocfError::io(format!("Read the {file_name}"), e)),
+        }
+        let cached_version = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        let cached_key = u64::from_le_bytes(header[12..20].try_into().unwrap());
+        if &header[..8] != RGBA_CACHE_MAGIC || cached_version != RGBA_CACHE_VERSION || cached_key != key {
+            return Ok(None);
+        }
+        let count = u32::from_le_bytes(header[20..24].try_into().unwrap());
+
+        let mut file_data = vec![Texture2DElement::default(); TEXMAP_MAX_ID as usize];
+        let mut offset = header.len() as u64;
+        for _ in 0..count {
+            let id = rdr
+                .read_u32::<LittleEndian>()
+                .read_context(file_name, offset, || "texture id".to_owned())?;
+            let size = match rdr.read_u8().read_context(file_name, offset + 4, || format!("texture 0x{id:x} size"))? {
+                0 => LandTextureSize::Small,
+                1 => LandTextureSize::Big,
+                other => {
+                    return Err(UocfError::malformed(file_name, offset + 4, format!("texture size {other}")));
+                }
+            };
+            if id >= TEXMAP_MAX_ID {
+                return Err(UocfError::malformed(file_name, offset, format!("texture id 0x{id:x}")));
+            }
+            let (size_x, size_y) = size.dimensions();
+            let mut pixel_data = vec![0u8; (size_x * size_y) as usize * Texture2DElement::PIXEL_DATA_CHANNELS];
+            rdr.read_exact(&mut pixel_data)
+                .read_context(file_name, offset + 5, || format!("texture 0x{id:x} pixels"))?;
+            offset += 5 + pixel_data.len() as u64;
+            file_data[id as usize] = Texture2DElement {
+                valid: true,
+                id,
+                size,
+                pixel_data,
+            };
+        }
+        Ok(Some(TexMap2D {
+            file_data: file_data.into_iter().map(OnceLock::from).collect(),
+            lazy: None,
+        }))
+    }
+}
+
+fn log_patched(entries: &[TextureEntry]) {
+    let i_idx_patched = entries.iter().filter(|entry| entry.patch.is_some()).count();
+    if i_idx_patched > 0 {
+        println!("Applied {i_idx_patched} Verdata patches to Map Tile textures.");
+    }
+}
+
+/// A texture listed in texidx.mul, or patched by Verdata.
+struct TextureEntry<'a> {
+    id: u32,
+    size: LandTextureSize,
+    /// Position of the pixels in texmaps.mul, if not patched.
+    lookup: u64,
+    /// Verdata patches take precedence over the texmaps.mul content.
+    patch: Option<Cow<'a, [u8]>>,
+}
+impl TextureEntry<'_> {
+    fn into_owned(self) -> TextureEntry<'static> {
+        TextureEntry {
+            id: self.id,
+            size: self.size,
+            lookup: self.lookup,
+            patch: self.patch.map(|patch| Cow::Owned(patch.into_owned())),
+        }
+    }
+
+    /// Reads the bgra5551 pixels, from the patch or from texmap_file_rdr.
+    fn read_raw(&self, texmap_file_rdr: &mut (impl Read + Seek)) -> Result<RawTexture<'_>> {
+        let texmap_file_name = TexMap2D::FILE_NAME;
+        let (size_x, size_y) = self.size.dimensions();
+        let pixel_qty_bytes = (size_x * size_y) as usize * 2; // Each u16 is 2 bytes
+        let i_idx_raw = self.id;
+        let pixel_data_bytes: Cow<[u8]> = if let Some(patch_data) = &self.patch {
+            Cow::Borrowed(&patch_data[..pixel_qty_bytes])
+        } else {
+            let mut pixel_data_bytes = vec![0u8; pixel_qty_bytes];
+            texmap_file_rdr
+                .seek(SeekFrom::Start(self.lookup))
+                .io_context(|| format!("Seek to texture 0x{i_idx_raw:x} in {texmap_file_name}"))?;
+            texmap_file_rdr
+                .read_exact(&mut pixel_data_bytes)
+                .read_context(texmap_file_name, self.lookup, || format!("texture 0x{i_idx_raw:x}"))?;
+            Cow::Owned(pixel_data_bytes)
         };
+        Ok(RawTexture {
+            id: self.id,
+            size: self.size,
+            pixel_data_bytes,
+        })
+    }
+}
 
-        // Loop on each entry of texidx
-        let mut i_idx_valid: usize = 0;
-        for i_idx_raw in 0..TEXMAP_MAX_ID {
-            // 0..texidx.element_count() {
-            // Fill texmap
-            let cur_idx_elem: &generic_index::IndexElement = texidx
-                .element(i_idx_raw as usize)
-                .expect("Reading lookup value for element {i_idx}");
+/// The textures of texidx.mul and of the Verdata patches, by id.
+/// A texidx.mul too short for a texture id without patch is an OutOfRange error.
+fn texture_entries<'a>(
+    texidx: &generic_index::IndexFile,
+    verdata: Option<&'a Verdata>,
+    texmap_file_size: usize,
+) -> Result<Vec<TextureEntry<'a>>> {
+    // Loop on each entry of texidx
+    let mut entries = Vec::new();
+    for i_idx_raw in 0..TEXMAP_MAX_ID {
+        // 0..texidx.element_count() {
+        // Verdata patches take precedence over the texmaps.mul content.
+        let patch_data: Option<&[u8]> =
+            verdata.and_then(|v| v.patch_data(Verdata::FILE_ID_TEXMAPS, i_idx_raw));
+
+        let (tex_lookup, tex_len) = if let Some(patch_data) = patch_data {
+            (0, patch_data.len() as u32)
+        } else {
+            let cur_idx_elem: &generic_index::IndexElement = texidx.element(i_idx_raw as usize)?;
 
             let tex_lookup = match cur_idx_elem.lookup() {
                 None => continue,
@@ -197,100 +520,118 @@ pub fn load(
                 None => continue,
                 Some(val) => val,
             };
+            (tex_lookup, tex_len)
+        };
 
-            let tex_size_type: LandTextureSize = match tex_len {
-                0x2000 => {
-                    // 0x2000 comes from 64*64 pixels = 0x1000. A single pixel is coded with a 16 bit (2 bytes) color value,
-                    //  thus 0x1000 * 2 = 0x2000.
-                    LandTextureSize::Small
-                }
-                0x8000 => {
-                    // 0x8000 comes from 128*128 pixels * 2.
-                    LandTextureSize::Big
-                }
-                _ => {
-                    /*println!(
-                        "Unknown texture size: {tex_len} (0x{:x}) for texture {i_idx} (0x{:x})",
-                        tex_len, i_idx
-                    );*/
-                    continue;
-                }
-            };
-
-            let cur_texture: &mut Texture2DElement = &mut texmap.file_data[i_idx_raw as usize];
-            cur_texture.id = i_idx_raw as u32; //i_idx_valid as u32;
-            cur_texture.size = tex_size_type.clone();
-
-            let pixel_qty = match tex_size_type {
-                LandTextureSize::Small => {
-                    LandTextureSize::SMALL_X as usize * LandTextureSize::SMALL_Y as usize
-                }
-                LandTextureSize::Big => {
-                    LandTextureSize::BIG_X as usize * LandTextureSize::BIG_Y as usize
-                }
-            };
-
-            texmap_file_rdr.seek(SeekFrom::Start(tex_lookup as u64))?;
-            let pixel_qty_bytes = pixel_qty * 2; // Each u16 is 2 bytes
-            let mut pixel_data_bytes = vec![0u8; pixel_qty_bytes];
-            texmap_file_rdr.read_exact(&mut pixel_data_bytes)?;
-
-            cur_texture.pixel_data = Vec::with_capacity(pixel_qty * 4);
-
-            let (pixel_data_u16_prefix, pixel_data_u16_suffix) =
-                bytemuck::cast_slice(&pixel_data_bytes).as_chunks::<16>();
-
-            for &chunk_array in pixel_data_u16_prefix {
-                #[allow(unused_mut)]
-                let mut chunk = u16x16::new(chunk_array);
-
-                #[cfg(target_endian = "big")]
-                {
-                    chunk = chunk.swap_bytes();
-                }
-
-                let b_u16: u16x16 = (chunk & u16x16::splat(0x1F)) << 3;
-                let g_u16: u16x16 = ((chunk >> 5) & u16x16::splat(0x1F)) << 3;
-                let r_u16: u16x16 = ((chunk >> 10) & u16x16::splat(0x1F)) << 3;
-                let a_u16: u16x16 = u16x16::splat(0xFF); // Alpha is set to 255
-
-                // Now convert u16x16 to [u32; 16]
-                let mut rgba_u32_array = [0u32; 16];
-                for i in 0..16 {
-                    let r_val = r_u16.as_array_ref()[i] as u32;
-                    let g_val = g_u16.as_array_ref()[i] as u32;
-                    let b_val = b_u16.as_array_ref()[i] as u32;
-                    let a_val = a_u16.as_array_ref()[i] as u32;
-                    rgba_u32_array[i] = (a_val << 24) | (b_val << 16) | (g_val << 8) | r_val;
-                }
-                cur_texture
-                    .pixel_data
-                    .extend_from_slice(bytemuck::cast_slice(&rgba_u32_array));
+        let tex_size_type: LandTextureSize = match tex_len {
+            0x2000 => {
+                // 0x2000 comes from 64*64 pixels = 0x1000. A single pixel is coded with a 16 bit (2 bytes) color value,
+                //  thus 0x1000 * 2 = 0x2000.
+                LandTextureSize::Small
             }
-
-            for &pixel_16_val in pixel_data_u16_suffix {
-                #[allow(unused_mut)]
-                let mut pixel_16 = Bgra5551::new_from_val(pixel_16_val);
-                pixel_16.set_a(1);
-                cur_texture
-                    .pixel_data
-                    .extend_from_slice(pixel_16.as_rgba8888().value().to_le_bytes().as_ref());
+            0x8000 => {
+                // 0x8000 comes from 128*128 pixels * 2.
+                LandTextureSize::Big
             }
+            _ => {
+                /*println!(
+                    "Unknown texture size: {tex_len} (0x{:x}) for texture {i_idx} (0x{:x})",
+                    tex_len, i_idx
+                );*/
+                continue;
+            }
+        };
 
-            cur_texture.valid = true;
-            i_idx_valid += 1;
-        }
-
-        texmap.file_data.shrink_to_fit();
+        entries.push(TextureEntry {
+            id: i_idx_raw,
+            size: tex_size_type,
+            lookup: tex_lookup as u64,
+            patch: patch_data.map(Cow::Borrowed),
+        });
+    }
+    Ok(entries)
+}
 
-        println!(
-            "Parsed {} (0x{:x}) Map Tile texture slots, loaded {} (0x{:x}) valid.",
-            texidx.element_count(),
-            texidx.element_count(),
-            i_idx_valid,
-            i_idx_valid
-        );
+/// The texmaps.mul reader of the lazy mode, and where each texture is.
+struct LazyTexMaps {
+    rdr: Mutex<Box<dyn VfsRead>>,
+    entries: Vec<Option<TextureEntry<'static>>>,
+}
+impl LazyTexMaps {
+    /// Reads and decodes a texture. An empty slot, or one that can't be read, gives an invalid element.
+    fn decode(&self, id: usize) -> Texture2DElement {
+        let Some(Some(entry)) = self.entries.get(id) else {
+            return Texture2DElement::default();
+        };
+        // Only the read is under the lock: other threads can read their texture while this one is decoded.
+        let raw_texture = {
+            let mut rdr = self.rdr.lock().unwrap_or_else(PoisonError::into_inner);
+            entry.read_raw(&mut *rdr)
+        };
+        match raw_texture {
+            Ok(raw_texture) => raw_texture.decode(),
+            Err(e) => {
+                println!("Can't read Map Tile texture 0x{id:x}: {e}");
+                Texture2DElement::default()
+            }
+        }
+    }
+}
 
-        Ok(texmap)
+/// A texture as read from texmaps.mul (or a Verdata patch), still bgra5551.
+struct RawTexture<'a> {
+    id: u32,
+    size: LandTextureSize,
+    pixel_data_bytes: Cow<'a, [u8]>,
+}
+impl RawTexture<'_> {
+    fn decode(&self) -> Texture2DElement {
+        let mut pixel_data = Vec::with_capacity(self.pixel_data_bytes.len() * 2);
+        // The bytes may not be aligned for u16: copy them if needed.
+        let pixels_5551: Cow<[u16]> = match bytemuck::try_cast_slice(&self.pixel_data_bytes) {
+            Ok(pixels) => Cow::Borrowed(pixels),
+            Err(_) => Cow::Owned(bytemuck::pod_collect_to_vec(&self.pixel_data_bytes)),
+        };
+        bgra5551_to_rgba8888(&pixels_5551, &mut pixel_data);
+        Texture2DElement {
+            valid: true,
+            id: self.id,
+            size: self.size,
+            pixel_data,
+        }
     }
 }
+
+/// Decodes the textures, split among the available cores (serially on wasm32, which has no threads), in the same
+///  order. Calls progress(decoded, total) after each one.
+fn decode_textures(raw_textures: &[RawTexture], progress: &(impl Fn(usize, usize) + Sync)) -> Vec<Texture2DElement> {
+    let total = raw_textures.len();
+    let decoded = AtomicUsize::new(0);
+    let decode = |raw_texture: &RawTexture| {
+        let texture = raw_texture.decode();
+        progress(decoded.fetch_add(1, Ordering::Relaxed) + 1, total);
+        texture
+    };
+
+    #[cfg(target_arch = "wasm32")]
+    let textures = raw_textures.iter().map(decode).collect();
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let textures = {
+        let thread_count = std::thread::available_parallelism().map_or(1, |count| count.get());
+        let chunk_len = total.div_ceil(thread_count).max(1);
+        std::thread::scope(|scope| {
+            let decode = &decode;
+            let handles: Vec<_> = raw_textures
+                .chunks(chunk_len)
+                .map(|chunk| scope.spawn(move || chunk.iter().map(decode).collect::<Vec<_>>()))
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("Texture decoding thread panicked."))
+                .collect()
+        })
+    };
+
+    textures
+}
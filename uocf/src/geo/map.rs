@@ -1,14 +1,20 @@
 #![allow(dead_code)]
 
-crate::eyre_imports!();
 use byteorder::{LittleEndian, ReadBytesExt};
-use color_eyre::Section;
 use glam::Vec3; // Bevy uses glam::Vec3 under the hood.
-use std::collections::BTreeMap;
-use std::fs::File;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{File, OpenOptions};
 use std::io::{BufReader, Cursor, SeekFrom, prelude::*};
 use bytemuck::{Pod, Zeroable};
-use std::path::PathBuf;
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use super::map_def::MapDefinitions;
+use crate::errors::{IoResultExt, Result, UocfError, file_name_of};
+use crate::uop::UopFile;
+use crate::vfs::{self, VfsFile};
 
 #[derive(Clone, Copy, Default)]
 pub struct MapCell {
@@ -112,33 +118,55 @@ fn coords_from_idx(block_idx: u32, map_height_blocks: u32) -> MapBlockRelPos {
         }
     }
     #[inline(always)]
-    fn idx_from_coords(block_coords: &MapBlockRelPos, map_height_blocks: u32) -> u32 {
+    pub(crate) fn idx_from_coords(block_coords: &MapBlockRelPos, map_height_blocks: u32) -> u32 {
         (block_coords.x * map_height_blocks) + block_coords.y
     }
 
     // Cells are loaded from blocks left-to-right then top-to-bottom.
-    const ERR_CELL_OUT_RANGE: &'static str = "Map Cell out of range";
-    pub fn cell(&self, x: u32, y: u32) -> eyre::Result<&MapCell> {
+    pub fn cell(&self, x: u32, y: u32) -> Result<&MapCell> {
         if x >= Self::CELLS_PER_ROW || y >= Self::CELLS_PER_COLUMN {
-            Err(eyre!(Self::ERR_CELL_OUT_RANGE.to_owned()))
+            Err(UocfError::out_of_range(format!("map cell ({x}, {y}) in block")))
         } else {
             Ok(&self.cells[((Self::CELLS_PER_COLUMN * y) + x) as usize])
         }
     }
-    fn cell_as_mut(&mut self, x: u32, y: u32) -> eyre::Result<&mut MapCell> {
+    /// All the cells of the block, row by row.
+    pub fn cells(&self) -> &[MapCell] {
+        self.cells.as_slice()
+    }
+    /// Lowest and highest z of the cells in the block.
+    pub fn z_range(&self) -> (i8, i8) {
+        self.cells
+            .iter()
+            .fold((i8::MAX, i8::MIN), |(lo, hi), cell| (lo.min(cell.z), hi.max(cell.z)))
+    }
+    pub fn cell_as_mut(&mut self, x: u32, y: u32) -> Result<&mut MapCell> {
         if x >= Self::CELLS_PER_ROW || y >= Self::CELLS_PER_COLUMN {
-            Err(eyre!(Self::ERR_CELL_OUT_RANGE.to_owned()))
+            Err(UocfError::out_of_range(format!("map cell ({x}, {y}) in block")))
         } else {
             Ok(&mut self.cells[((Self::CELLS_PER_COLUMN * y) + x) as usize])
         }
     }
 
-    pub fn from_reader(rdr: &mut Cursor<&[u8]>) -> eyre::Result<MapBlock> {
+    /// The cells, packed as in the map files, without the block header.
+    fn packed_cells(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::CELLS_PER_BLOCK as usize * MapCell::PACKED_SIZE);
+        for cell in self.cells.iter() {
+            bytes.extend_from_slice(&cell.id.to_le_bytes());
+            bytes.push(cell.z as u8);
+        }
+        bytes
+    }
+
+    /// Reads a block from a buffer holding data of the given file, starting at base_offset in the file.
+    pub fn from_reader(rdr: &mut Cursor<&[u8]>, file_name: &str, base_offset: u64) -> Result<MapBlock> {
         let bytes = rdr.get_ref(); // Get the underlying byte slice
         let offset = rdr.position() as usize; // Get the current position of the cursor
 
         // Read the raw block as a byte slice
-        let raw_block_bytes = &bytes[offset..offset + MapBlock::PACKED_SIZE];
+        let raw_block_bytes = bytes.get(offset..offset + MapBlock::PACKED_SIZE).ok_or_else(|| {
+            UocfError::malformed(file_name, base_offset + offset as u64, "map block: unexpected end of data")
+        })?;
 
         // Cast the byte slice to RawMapBlock. This is where endianness needs to be handled for fields.
         let raw_block: &RawMapBlock = bytemuck::from_bytes(raw_block_bytes);
@@ -159,20 +187,414 @@ pub fn from_reader(rdr: &mut Cursor<&[u8]>) -> eyre::Result<MapBlock> {
             }
         }
         // Advance the cursor by the size of the block
-        rdr.seek(SeekFrom::Current(MapBlock::PACKED_SIZE as i64))?;
+        rdr.set_position((offset + MapBlock::PACKED_SIZE) as u64);
         Ok(new_block)
     }
 }
 
+/// How the map file data is accessed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FileBackend {
+    /// Seek and read the requested blocks from the file.
+    #[default]
+    Read,
+    /// Memory-map the whole file: blocks are parsed straight from the mapped memory, without reading them into a
+    ///  buffer first. Faster when loading many blocks, but the file must not be modified by other programs while
+    ///  it's mapped (writes from MapPlane::write_blocks are fine).
+    /// On wasm32 there's no memory mapping: the file is read like with Read.
+    Mmap,
+}
+
+/// Map data read sequentially: a buffered file, or any other Read + Seek source (see MapPlane::from_reader).
+trait MapDataReader: Read + Seek + Send + Sync {}
+impl<T: Read + Seek + Send + Sync> MapDataReader for T {}
+
+// Physical access to the map file (either .mul or .uop).
+enum MapFileReader {
+    Stream(Box<dyn MapDataReader>),
+    #[cfg(not(target_arch = "wasm32"))]
+    Mapped(Mmap),
+    // The whole data, in memory (see MapPlane::from_bytes).
+    Memory(Arc<[u8]>),
+}
+impl MapFileReader {
+    fn open(file_handle: VfsFile, backend: FileBackend) -> Result<MapFileReader> {
+        Ok(match (file_handle, backend) {
+            // Files of the vfs in memory are read from there, whatever the backend.
+            (VfsFile::Memory(cursor), _) => Self::Memory(cursor.into_inner()),
+            #[cfg(not(target_arch = "wasm32"))]
+            (VfsFile::Disk(file_handle), FileBackend::Mmap) => {
+                // SAFETY: the mapping is read-only. Modifying the file while it's mapped (which the client and the
+                //  other UO tools don't do while running) is undefined behavior.
+                let mmap = unsafe { Mmap::map(&file_handle) }.io_context(|| "Memory-map the file")?;
+                Self::Mapped(mmap)
+            }
+            // Also for Mmap where there's nothing to map: in the browser, or for the vfs sources.
+            (file_handle, _) => Self::Stream(Box::new(BufReader::new(file_handle))),
+        })
+    }
+
+    // The whole data, if it's in memory (mapped or not).
+    fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Stream(_) => None,
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Mapped(mmap) => Some(mmap),
+            Self::Memory(bytes) => Some(bytes),
+        }
+    }
+
+    fn len(&mut self, file_name: &str) -> Result<u64> {
+        match self {
+            Self::Stream(rdr) => rdr
+                .seek(SeekFrom::End(0))
+                .io_context(|| format!("Get {file_name} size")),
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Mapped(mmap) => Ok(mmap.len() as u64),
+            Self::Memory(bytes) => Ok(bytes.len() as u64),
+        }
+    }
+
+    // Runs f with a Read + Seek view of the data, positioned at the start: for the parsers reading it sequentially.
+    fn with_reader<T>(&mut self, f: impl FnOnce(&mut dyn MapDataReader) -> T) -> T {
+        match self {
+            Self::Stream(rdr) => {
+                // If the seek fails, so does the first read.
+                let _ = rdr.seek(SeekFrom::Start(0));
+                f(rdr.as_mut())
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            Self::Mapped(mmap) => f(&mut Cursor::new(&mmap[..])),
+            Self::Memory(bytes) => f(&mut Cursor::new(&bytes[..])),
+        }
+    }
+
+    // Fill the whole buffer with data starting at the given file offset.
+    fn read_exact_at(&mut self, file_name: &str, offset: u64, buf: &mut [u8]) -> Result<()> {
+        match self {
+            Self::Stream(rdr) => {
+                rdr.seek(SeekFrom::Start(offset))
+                    .io_context(|| format!("Seek to {offset} in {file_name}"))?;
+                rdr.read_exact(buf).read_context(file_name, offset, || "map chunk")?;
+            }
+            // Mapped or in memory.
+            _ => {
+                let src = self
+                    .slice_at(offset, buf.len())
+                    .ok_or_else(|| UocfError::malformed(file_name, offset, "map chunk: unexpected end of file"))?;
+                buf.copy_from_slice(src);
+            }
+        }
+        Ok(())
+    }
+
+    // Data at the given file offset, without copying it. Only for mapped files and data in memory.
+    fn slice_at(&self, offset: u64, len: usize) -> Option<&[u8]> {
+        self.bytes()?.get(offset as usize..offset as usize + len)
+    }
+}
+
+// A region of the UOP file containing a slice of the map data (as it would be stored in the .mul file).
+#[derive(Clone, Copy, Debug)]
+struct UopDataChunk {
+    data_offset: u64,
+    len: u64,
+}
+
+// Where the map data is read from. The .uop file is a container of .mul file slices (each holding a fixed
+//  amount of blocks), so we translate .mul file offsets to the position of the data in the .uop file.
+enum MapFileSource {
+    Mul {
+        rdr: MapFileReader,
+        file_name: String,
+        len: u64,
+    },
+    Uop {
+        rdr: MapFileReader,
+        file_name: String,
+        // Sorted by their position in the original .mul file.
+        chunks: Vec<UopDataChunk>,
+        len: u64,
+    },
+}
+impl MapFileSource {
+    // Size of the map data, as if it was stored in a .mul file.
+    fn len(&self) -> u64 {
+        match self {
+            Self::Mul { len, .. } | Self::Uop { len, .. } => *len,
+        }
+    }
+
+    fn file_name(&self) -> &str {
+        match self {
+            Self::Mul { file_name, .. } | Self::Uop { file_name, .. } => file_name,
+        }
+    }
+
+    // The map data of rdr, either a .mul file or a .uop one (detected from the content).
+    fn new(mut rdr: MapFileReader, map_index: u32) -> Result<MapFileSource> {
+        if rdr.with_reader(|uop_rdr| UopFile::is_uop_reader(uop_rdr)) {
+            Self::new_uop(rdr, map_index)
+        } else {
+            Self::new_mul(rdr, map_index)
+        }
+    }
+
+    fn new_mul(mut rdr: MapFileReader, map_index: u32) -> Result<MapFileSource> {
+        let file_name = format!("map{map_index}.mul");
+        let len = rdr.len(&file_name)?;
+        Ok(Self::Mul { rdr, file_name, len })
+    }
+
+    fn new_uop(mut rdr: MapFileReader, map_index: u32) -> Result<MapFileSource> {
+        let file_name = MapPlane::uop_file_name(map_index);
+        let uop = rdr.with_reader(|uop_rdr| UopFile::from_reader(uop_rdr, &file_name))?;
+
+        // Entries are named after the slice of the .mul file they contain, numbered from 0.
+        let mut chunks: Vec<UopDataChunk> = Vec::with_capacity(uop.entry_count());
+        let mut len: u64 = 0;
+        for i_entry in 0..uop.entry_count() {
+            let entry_name = format!("build/map{map_index}legacymul/{i_entry:08}.dat");
+            let Some(entry) = uop.entry_by_name(&entry_name) else {
+                break;
+            };
+            if entry.is_compressed() {
+                return Err(UocfError::unsupported_revision(
+                    &file_name,
+                    format!("compressed entry '{entry_name}'."),
+                ));
+            }
+            chunks.push(UopDataChunk {
+                data_offset: entry.data_offset(),
+                len: entry.decompressed_len as u64,
+            });
+            len += entry.decompressed_len as u64;
+        }
+        if chunks.is_empty() {
+            return Err(UocfError::malformed(&file_name, 0, format!("no map{map_index} data entries.")));
+        }
+
+        Ok(Self::Uop {
+            rdr,
+            file_name,
+            chunks,
+            len,
+        })
+    }
+
+    // Fill the whole buffer with data starting at the given .mul file offset.
+    fn read_exact_at(&mut self, offset: u64, buf: &mut [u8]) -> Result<()> {
+        if offset + buf.len() as u64 > self.len() {
+            return Err(UocfError::malformed(self.file_name(), offset, "map chunk: unexpected end of file"));
+        }
+        match self {
+            Self::Mul { rdr, file_name, .. } => rdr.read_exact_at(file_name, offset, buf)?,
+            Self::Uop {
+                rdr, chunks, file_name, ..
+            } => {
+                // The requested data may span over more than one uop entry.
+                let mut chunk_start: u64 = 0;
+                let mut buf_pos: usize = 0;
+                for chunk in chunks.iter() {
+                    if buf_pos == buf.len() {
+                        break;
+                    }
+                    let chunk_end = chunk_start + chunk.len;
+                    let cur_offset = offset + buf_pos as u64;
+                    if cur_offset < chunk_end {
+                        let offset_in_chunk = cur_offset - chunk_start;
+                        let to_read = ((chunk_end - cur_offset) as usize).min(buf.len() - buf_pos);
+                        let physical_offset = chunk.data_offset + offset_in_chunk;
+                        rdr.read_exact_at(file_name, physical_offset, &mut buf[buf_pos..buf_pos + to_read])?;
+                        buf_pos += to_read;
+                    }
+                    chunk_start = chunk_end;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Data starting at the given .mul file offset, straight from the mapped file. None if the file isn't mapped, or
+    //  if the data isn't contiguous in the file (it spans over more than one uop entry).
+    fn mapped_slice_at(&self, offset: u64, len: usize) -> Option<&[u8]> {
+        if offset + len as u64 > self.len() {
+            return None;
+        }
+        match self {
+            Self::Mul { rdr, .. } => rdr.slice_at(offset, len),
+            Self::Uop { rdr, chunks, .. } => {
+                let mut chunk_start: u64 = 0;
+                for chunk in chunks.iter() {
+                    let chunk_end = chunk_start + chunk.len;
+                    if offset < chunk_end {
+                        if offset + len as u64 > chunk_end {
+                            return None;
+                        }
+                        return rdr.slice_at(chunk.data_offset + (offset - chunk_start), len);
+                    }
+                    chunk_start = chunk_end;
+                }
+                None
+            }
+        }
+    }
+}
+
+// Map patches used by pre-UOP clients: mapdifl*.mul lists the indices of the patched blocks, mapdif*.mul holds
+//  the replacement blocks, in the same order.
+struct MapDiff {
+    // Block index -> offset of the replacement block in data.
+    block_offsets: HashMap<u32, usize>,
+    data: Vec<u8>,
+    data_file_name: String,
+    data_file_path: PathBuf,
+}
+impl MapDiff {
+    fn load(mapdifl_file_path: PathBuf, mapdif_file_path: PathBuf) -> Result<MapDiff> {
+        let data_file_name = mapdif_file_path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        let data_file_path = mapdif_file_path.clone();
+        let block_list = load_diff_block_list(mapdifl_file_path)?;
+        let data = load_diff_file(mapdif_file_path)?;
+        if block_list.len() * MapBlock::PACKED_SIZE > data.len() {
+            return Err(UocfError::malformed(
+                data_file_name,
+                0,
+                format!(
+                    "{} patched blocks listed, but the file holds {} bytes.",
+                    block_list.len(),
+                    data.len()
+                ),
+            ));
+        }
+
+        // Later entries override earlier ones for the same block.
+        let block_offsets = block_list
+            .into_iter()
+            .enumerate()
+            .map(|(i_block, block_idx)| (block_idx, i_block * MapBlock::PACKED_SIZE))
+            .collect();
+        Ok(MapDiff {
+            block_offsets,
+            data,
+            data_file_name,
+            data_file_path,
+        })
+    }
+
+    fn block(&self, block_idx: u32) -> Result<Option<MapBlock>> {
+        let Some(&offset) = self.block_offsets.get(&block_idx) else {
+            return Ok(None);
+        };
+        let mut rdr = Cursor::new(self.data.as_slice());
+        rdr.set_position(offset as u64);
+        MapBlock::from_reader(&mut rdr, &self.data_file_name, 0).map(Some)
+    }
+}
+
 pub struct MapPlane {
     pub index: u32,
     pub size_blocks: MapSizeBlocks,
-    map_file_mul_rdr: BufReader<File>,
+    file_backend: FileBackend,
+    // None if the map data doesn't come from a file (see from_reader, vfs): it can't be written then.
+    map_file_path: Option<PathBuf>,
+    map_file_src: MapFileSource,
     cached_blocks: BTreeMap<MapBlockRelPos, MapBlock>,
+    // If set, after loading new blocks the cache is trimmed to this size, dropping the blocks farthest from the
+    //  ones just requested.
+    max_cached_blocks: Option<usize>,
+    diff: Option<MapDiff>,
+    // If false, the diff is kept loaded but the original blocks are returned.
+    apply_diffs: bool,
+    // Copies of the blocks changed with set_cell. They aren't evicted with the cache, and loading one of these blocks
+    //  returns the edited copy instead of the file (or diff) data.
+    edited_blocks: BTreeMap<MapBlockRelPos, MapBlock>,
 }
 impl MapPlane {
     pub const EXTRA_BLOCKS_TO_CACHE_PER_SIDE: u32 = 8;
 
+    /// Names of the (list, data) diff files of pre-UOP clients.
+    pub fn diff_file_names(map_index: u32) -> (String, String) {
+        (format!("mapdifl{map_index}.mul"), format!("mapdif{map_index}.mul"))
+    }
+
+    /// Load the map patches. Cached blocks are dropped, so that they are read again with the patches.
+    /// Returns the number of patched blocks.
+    pub fn load_diffs(
+        &mut self,
+        mapdifl_file_path: PathBuf,
+        mapdif_file_path: PathBuf,
+    ) -> Result<usize> {
+        let diff = MapDiff::load(mapdifl_file_path, mapdif_file_path)?;
+        let patched_blocks = diff.block_offsets.len();
+        self.diff = Some(diff);
+        self.apply_diffs = true;
+        self.cached_blocks.clear();
+        Ok(patched_blocks)
+    }
+
+    pub fn has_diffs(&self) -> bool {
+        self.diff.is_some()
+    }
+
+    pub fn diff_block_count(&self) -> usize {
+        self.diff
+            .as_ref()
+            .map_or(0, |diff| diff.block_offsets.len())
+    }
+
+    pub fn apply_diffs(&self) -> bool {
+        self.apply_diffs
+    }
+
+    /// Toggle the use of the loaded patches. Cached blocks are dropped if the setting changes, so the caller has to
+    ///  load again the blocks it needs.
+    pub fn set_apply_diffs(&mut self, apply_diffs: bool) {
+        if self.apply_diffs == apply_diffs {
+            return;
+        }
+        self.apply_diffs = apply_diffs;
+        if self.diff.is_some() {
+            self.cached_blocks.clear();
+        }
+    }
+
+    /// Is the block replaced by a patch (regardless of the patches being applied or not)?
+    pub fn is_block_patched(&self, pos: MapBlockRelPos) -> bool {
+        let block_idx = MapBlock::idx_from_coords(&pos, self.size_blocks.height);
+        self.diff
+            .as_ref()
+            .is_some_and(|diff| diff.block_offsets.contains_key(&block_idx))
+    }
+
+    pub fn file_backend(&self) -> FileBackend {
+        self.file_backend
+    }
+
+    pub fn cached_block_count(&self) -> usize {
+        self.cached_blocks.len()
+    }
+
+    pub fn max_cached_blocks(&self) -> Option<usize> {
+        self.max_cached_blocks
+    }
+    pub fn set_max_cached_blocks(&mut self, max_cached_blocks: Option<usize>) {
+        self.max_cached_blocks = max_cached_blocks;
+    }
+
+    /// Drop from the cache every block outside the given rectangle. Returns the number of evicted blocks.
+    pub fn evict_blocks_outside(&mut self, rect: &MapRectBlocks) -> usize {
+        let count_before = self.cached_blocks.len();
+        self.cached_blocks.retain(|pos, _| rect.contains(pos));
+        count_before - self.cached_blocks.len()
+    }
+
+    /// Drop from the cache the blocks farthest from the given position, until the cache size is within the limit.
+    /// Returns the number of evicted blocks.
+    pub fn evict_farthest_blocks(&mut self, center: MapBlockRelPos, max_blocks: usize) -> usize {
+        evict_farthest_blocks(&mut self.cached_blocks, center, max_blocks)
+    }
+
     //pub fn block(&self, x: u32, y: u32) -> Option<&MapBlock> {
     //    self.cached_blocks.get(&MapBlockRelPos { x, y })
     //}
@@ -185,6 +607,178 @@ pub fn block(&self, pos: MapBlockRelPos) -> Option<&MapBlock> {
     pub fn block_as_mut(&mut self, pos: MapBlockRelPos) -> Option<&mut MapBlock> {
         self.cached_blocks.get_mut(&pos)
     }
+
+    /// Changes a cell, loading its block if it isn't cached. Returns the previous cell.
+    /// The edited block is kept apart from the cache, so the change isn't lost when the block is evicted.
+    pub fn set_cell(&mut self, x: u32, y: u32, cell: MapCell) -> Result<MapCell> {
+        let block_pos = MapBlockRelPos {
+            x: MapCell::coords_of_parent_block_x(x),
+            y: MapCell::coords_of_parent_block_y(y),
+        };
+        if block_pos.x >= self.size_blocks.width || block_pos.y >= self.size_blocks.height {
+            return Err(UocfError::out_of_range(format!("map cell ({x}, {y})")));
+        }
+        self.load_blocks(&mut vec![block_pos])?;
+        let block = self
+            .cached_blocks
+            .get_mut(&block_pos)
+            .ok_or_else(|| UocfError::out_of_range(format!("map block {block_pos:?}, not cached after loading it")))?;
+        let block_cell = block.cell_as_mut(MapCell::coords_in_block_x(x), MapCell::coords_in_block_y(y))?;
+        let prev_cell = std::mem::replace(block_cell, cell);
+        self.edited_blocks.insert(block_pos, block.clone());
+        Ok(prev_cell)
+    }
+
+    pub fn has_edits(&self) -> bool {
+        !self.edited_blocks.is_empty()
+    }
+
+    pub fn edited_block_count(&self) -> usize {
+        self.edited_blocks.len()
+    }
+
+    pub fn is_block_edited(&self, pos: MapBlockRelPos) -> bool {
+        self.edited_blocks.contains_key(&pos)
+    }
+
+    /// The blocks changed with set_cell, sorted by position.
+    pub fn edited_blocks(&self) -> impl Iterator<Item = &MapBlock> {
+        self.edited_blocks.values()
+    }
+
+    /// Drops every change made with set_cell: the edited blocks are dropped from the cache too, so that they're read
+    ///  again from the file. Returns the positions of the blocks which were edited.
+    pub fn discard_edits(&mut self) -> Vec<MapBlockRelPos> {
+        let edited = std::mem::take(&mut self.edited_blocks);
+        for pos in edited.keys() {
+            self.cached_blocks.remove(pos);
+        }
+        edited.into_keys().collect()
+    }
+
+    /// Writes a block to the map file, at its position (internal_coords). See write_blocks.
+    pub fn write_block(&mut self, block: &MapBlock) -> Result<()> {
+        self.write_blocks([block]).map(|_| ())
+    }
+
+    /// Writes blocks to the map file, at their position (internal_coords). The block headers in the file are kept.
+    /// A block replaced by an applied patch is written to the diff data file instead: the patch would hide the change
+    ///  otherwise. Only mul files can be written.
+    /// The written blocks replace the cached ones, and aren't edited blocks anymore. Returns how many were written.
+    pub fn write_blocks<'a>(&mut self, blocks: impl IntoIterator<Item = &'a MapBlock>) -> Result<usize> {
+        let map_file_name = match &self.map_file_src {
+            MapFileSource::Mul { file_name, .. } => file_name.clone(),
+            MapFileSource::Uop { file_name, .. } => {
+                return Err(UocfError::unsupported_revision(file_name, "writing uop map files isn't supported."));
+            }
+        };
+        let mut map_file: Option<File> = None;
+        let mut diff_file: Option<File> = None;
+        let mut written: usize = 0;
+        for block in blocks {
+            let pos = block.internal_coords;
+            if pos.x >= self.size_blocks.width || pos.y >= self.size_blocks.height {
+                return Err(UocfError::out_of_range(format!("map block {pos:?}")));
+            }
+            let block_idx = MapBlock::idx_from_coords(&pos, self.size_blocks.height);
+            let cells = block.packed_cells();
+            let patch_offset = match (&self.diff, self.apply_diffs) {
+                (Some(diff), true) => diff.block_offsets.get(&block_idx).copied(),
+                _ => None,
+            };
+            match (patch_offset, self.diff.as_mut()) {
+                (Some(offset), Some(diff)) => {
+                    let file = open_for_writing(&mut diff_file, &diff.data_file_path, &diff.data_file_name)?;
+                    let cells_offset = offset + 4 /* u32 header */;
+                    write_at(file, &diff.data_file_name, cells_offset as u64, &cells)?;
+                    diff.data[cells_offset..cells_offset + cells.len()].copy_from_slice(&cells);
+                }
+                _ => {
+                    let Some(map_file_path) = &self.map_file_path else {
+                        return Err(UocfError::unsupported_revision(
+                            &map_file_name,
+                            "writing map data not read from a file isn't supported.",
+                        ));
+                    };
+                    let file = open_for_writing(&mut map_file, map_file_path, &map_file_name)?;
+                    let cells_offset = (MapBlock::PACKED_SIZE * block_idx as usize) as u64 + 4 /* u32 header */;
+                    write_at(file, &map_file_name, cells_offset, &cells)?;
+                }
+            }
+            if let Some(cached_block) = self.cached_blocks.get_mut(&pos) {
+                *cached_block = block.clone();
+            }
+            self.edited_blocks.remove(&pos);
+            written += 1;
+        }
+        for file in map_file.iter_mut().chain(diff_file.iter_mut()) {
+            file.flush().io_context(|| "Flush map file")?;
+        }
+        Ok(written)
+    }
+
+    /// Writes the edited blocks (see set_cell) to the files, with write_blocks. Returns how many were written.
+    /// If make_backup, each file is copied to <file name>.bak before writing it, unless that backup exists already:
+    ///  the oldest backup, with the original data, is kept.
+    pub fn save_edits(&mut self, make_backup: bool) -> Result<usize> {
+        if self.edited_blocks.is_empty() {
+            return Ok(0);
+        }
+        if let MapFileSource::Uop { file_name, .. } = &self.map_file_src {
+            return Err(UocfError::unsupported_revision(file_name, "writing uop map files isn't supported."));
+        }
+        if make_backup {
+            if let Some(map_file_path) = &self.map_file_path {
+                backup_file(map_file_path)?;
+            }
+            if self.apply_diffs
+                && let Some(diff) = &self.diff
+            {
+                let height = self.size_blocks.height;
+                let patched = self
+                    .edited_blocks
+                    .keys()
+                    .any(|pos| diff.block_offsets.contains_key(&MapBlock::idx_from_coords(pos, height)));
+                if patched {
+                    backup_file(&diff.data_file_path)?;
+                }
+            }
+        }
+        let blocks: Vec<MapBlock> = self.edited_blocks.values().cloned().collect();
+        self.write_blocks(&blocks)
+    }
+}
+
+/// Opens the file for writing, if it isn't open yet.
+fn open_for_writing<'a>(file: &'a mut Option<File>, file_path: &Path, file_name: &str) -> Result<&'a mut File> {
+    if file.is_none() {
+        let handle = OpenOptions::new()
+            .write(true)
+            .open(file_path)
+            .io_context(|| format!("Open {file_name} for writing"))?;
+        *file = Some(handle);
+    }
+    Ok(file.as_mut().unwrap())
+}
+
+fn write_at(file: &mut File, file_name: &str, offset: u64, bytes: &[u8]) -> Result<()> {
+    file.seek(SeekFrom::Start(offset))
+        .io_context(|| format!("Seek to {offset} in {file_name}"))?;
+    file.write_all(bytes)
+        .io_context(|| format!("Write {file_name} at 0x{offset:X}"))
+}
+
+/// Copies the file to <file name>.bak, if there's no such file yet.
+fn backup_file(file_path: &Path) -> Result<()> {
+    let mut backup_path = file_path.as_os_str().to_owned();
+    backup_path.push(".bak");
+    let backup_path = PathBuf::from(backup_path);
+    if backup_path.exists() {
+        return Ok(());
+    }
+    std::fs::copy(file_path, &backup_path)
+        .io_context(|| format!("Back up '{}'", file_path.to_string_lossy()))?;
+    Ok(())
 }
 
 // Position of a cell in the map plane
@@ -221,12 +815,42 @@ pub struct MapSizeCells {
     pub width: u32,
     pub height: u32,
 }
+impl MapSizeCells {
+    /// Whole blocks: the size must be a (non zero) multiple of the block size.
+    pub fn is_valid(&self) -> bool {
+        self.width > 0
+            && self.height > 0
+            && self.width.is_multiple_of(MapBlock::CELLS_PER_ROW)
+            && self.height.is_multiple_of(MapBlock::CELLS_PER_COLUMN)
+    }
+
+    pub fn to_blocks(&self) -> MapSizeBlocks {
+        MapSizeBlocks {
+            width: self.width / MapBlock::CELLS_PER_ROW,
+            height: self.height / MapBlock::CELLS_PER_COLUMN,
+        }
+    }
+
+    /// Length of a map*.mul file of this size.
+    pub fn map_file_len(&self) -> u64 {
+        let size_blocks = self.to_blocks();
+        MapBlock::PACKED_SIZE as u64 * size_blocks.width as u64 * size_blocks.height as u64
+    }
+}
 // Size of a map plane, expressed in blocks.
 #[derive(Clone, Copy, Debug)]
 pub struct MapSizeBlocks {
     pub width: u32,
     pub height: u32,
 }
+impl MapSizeBlocks {
+    pub fn to_cells(&self) -> MapSizeCells {
+        MapSizeCells {
+            width: self.width * MapBlock::CELLS_PER_ROW,
+            height: self.height * MapBlock::CELLS_PER_COLUMN,
+        }
+    }
+}
 
 // A rectangle in the map; always in tiles/cells.
 #[derive(Clone, Copy, Debug)]
@@ -261,81 +885,204 @@ pub struct MapRectBlocks {
     pub width: u32,
     pub height: u32,
 }
+impl MapRectBlocks {
+    #[inline(always)]
+    pub fn contains(&self, pos: &MapBlockRelPos) -> bool {
+        pos.x >= self.x0
+            && pos.x < self.x0 + self.width
+            && pos.y >= self.y0
+            && pos.y < self.y0 + self.height
+    }
+}
+
+/// Shared helper for the diff files (map and statics): read the whole file.
+pub(crate) fn load_diff_file(file_path: PathBuf) -> Result<Vec<u8>> {
+    let file_name = file_name_of(&file_path)?;
+    let file_path = vfs::canonicalize(&file_path)
+        .io_context(|| format!("Check {file_name} path"))?;
+    let mut file_handle =
+        vfs::open(&file_path).io_context(|| format!("Open diff file at '{file_name}'"))?;
+    let mut data = Vec::new();
+    file_handle
+        .read_to_end(&mut data)
+        .io_context(|| format!("Read {file_name}"))?;
+    Ok(data)
+}
+
+/// Shared helper for the diff files (map and statics): the list file is a plain array of u32 block indices.
+pub(crate) fn load_diff_block_list(file_path: PathBuf) -> Result<Vec<u32>> {
+    let file_name = file_path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+    let data = load_diff_file(file_path)?;
+    let mut block_list = vec![0_u32; data.len() / size_of::<u32>()];
+    Cursor::new(data)
+        .read_u32_into::<LittleEndian>(&mut block_list)
+        .read_context(&file_name, 0, || "block list")?;
+    Ok(block_list)
+}
+
+/// Shared helper for the block caches (map and statics): drop the blocks farthest (Chebyshev distance) from the
+///  given center, until the cache holds no more than max_blocks.
+pub(crate) fn evict_farthest_blocks<T>(
+    cached_blocks: &mut BTreeMap<MapBlockRelPos, T>,
+    center: MapBlockRelPos,
+    max_blocks: usize,
+) -> usize {
+    if cached_blocks.len() <= max_blocks {
+        return 0;
+    }
+    let distance = |pos: &MapBlockRelPos| pos.x.abs_diff(center.x).max(pos.y.abs_diff(center.y));
+
+    let mut by_distance: Vec<(u32, MapBlockRelPos)> = cached_blocks
+        .keys()
+        .map(|pos| (distance(pos), *pos))
+        .collect();
+    // Farthest first.
+    by_distance.sort_unstable_by_key(|&(dist, _)| std::cmp::Reverse(dist));
+
+    let to_evict = cached_blocks.len() - max_blocks;
+    for (_, pos) in by_distance.iter().take(to_evict) {
+        cached_blocks.remove(pos);
+    }
+    to_evict
+}
 
 impl MapPlane {
-    pub fn init(map_file_mul_path: PathBuf, map_index: u32) -> eyre::Result<MapPlane> {
-        // We need to use PathBuf instead of String, because the latter has a UTF-8 encoding, while the former
-        //  can have different encodings, even not valid UTF-*, which can be valid for the used OS.
-        let map_file_mul_path = map_file_mul_path
-            .canonicalize()
-            .wrap_err_with(|| format!("Check map{map_index}.mul path"))?;
-
-        let map_file_mul_handle = File::open(&map_file_mul_path).wrap_err_with(|| {
-            format!(
-                "Open map{map_index}.mul at '{}'",
-                map_file_mul_path.to_string_lossy()
-            )
-        })?;
-        let map_file_mul_metadata = map_file_mul_handle
-            .metadata()
-            .wrap_err_with(|| format!("Get map{map_index}.mul metadata"))?;
-
-        let map_file_mul_rdr = BufReader::new(map_file_mul_handle);
-
-        let map_size_tiles = match map_index {
-            0..=1 => {
-                if map_file_mul_metadata.len() < 77070336 {
-                    Ok(MapSizeCells {
-                        width: 6144,
-                        height: 4096,
-                    }) // pre-ML
-                } else {
-                    Ok(MapSizeCells {
-                        width: 7168,
-                        height: 4096,
-                    })
-                }
+    /// Name of the .uop file shipped by newer clients in place of map*.mul.
+    pub fn uop_file_name(map_index: u32) -> String {
+        format!("map{map_index}LegacyMUL.uop")
+    }
+
+    /// Accepts either a map*.mul or a map*LegacyMUL.uop file path: the format is detected by the file content.
+    /// If the requested file doesn't exist, the .uop file in the same folder is tried.
+    /// file_backend selects how the blocks are read from the file.
+    /// The map size is the one of map_defs matching the file size (see map_def).
+    pub fn init(
+        map_file_path: PathBuf,
+        map_index: u32,
+        file_backend: FileBackend,
+        map_defs: &MapDefinitions,
+    ) -> Result<MapPlane> {
+        let (map_file_path, map_file_src) = Self::open(map_file_path, map_index, file_backend)?;
+        let map_size_tiles =
+            map_defs.size_for_file(map_index, map_file_src.file_name(), map_file_src.len())?;
+        Ok(Self::from_source(map_index, file_backend, map_file_path, map_file_src, map_size_tiles))
+    }
+
+    /// Like init, with a map size given by the caller instead of the map definitions, e.g. for the resized maps of
+    ///  private shards. The file size must match it.
+    pub fn init_with_size(
+        map_file_path: PathBuf,
+        map_index: u32,
+        file_backend: FileBackend,
+        map_size_tiles: MapSizeCells,
+    ) -> Result<MapPlane> {
+        let (map_file_path, map_file_src) = Self::open(map_file_path, map_index, file_backend)?;
+        Self::from_source_with_size(map_index, file_backend, map_file_path, map_file_src, map_size_tiles)
+    }
+
+    /// Like init_with_size, reading the map data from rdr instead of a file: a byte slice (std::io::Cursor), an
+    ///  archive entry, a network stream... The data can be either the .mul or the .uop one. Blocks are read with
+    ///  seeks, like with FileBackend::Read.
+    /// The map plane can't be written (write_blocks, save_edits) but in the diff data.
+    pub fn from_reader(
+        rdr: impl Read + Seek + Send + Sync + 'static,
+        map_index: u32,
+        map_size_tiles: MapSizeCells,
+    ) -> Result<MapPlane> {
+        let map_file_src = MapFileSource::new(MapFileReader::Stream(Box::new(rdr)), map_index)?;
+        Self::from_source_with_size(map_index, FileBackend::Read, None, map_file_src, map_size_tiles)
+    }
+
+    /// Like from_reader, with the whole map data already in memory: blocks are parsed straight from it, without
+    ///  copying, like with FileBackend::Mmap (which is the reported file_backend).
+    pub fn from_bytes(bytes: impl Into<Arc<[u8]>>, map_index: u32, map_size_tiles: MapSizeCells) -> Result<MapPlane> {
+        let map_file_src = MapFileSource::new(MapFileReader::Memory(bytes.into()), map_index)?;
+        Self::from_source_with_size(map_index, FileBackend::Mmap, None, map_file_src, map_size_tiles)
+    }
+
+    /// Opens the map file, see init.
+    fn open(
+        map_file_path: PathBuf,
+        map_index: u32,
+        file_backend: FileBackend,
+    ) -> Result<(Option<PathBuf>, MapFileSource)> {
+        let map_file_path = if vfs::exists(&map_file_path) {
+            map_file_path
+        } else {
+            let uop_file_path = map_file_path.with_file_name(Self::uop_file_name(map_index));
+            if vfs::exists(&uop_file_path) {
+                uop_file_path
+            } else {
+                map_file_path
             }
-            2 => Ok(MapSizeCells {
-                width: 2304,
-                height: 1600,
-            }),
-            3 => Ok(MapSizeCells {
-                width: 2560,
-                height: 2048,
-            }),
-            4 => Ok(MapSizeCells {
-                width: 1448,
-                height: 1448,
-            }),
-            5 => Ok(MapSizeCells {
-                width: 1280,
-                height: 4096,
-            }),
-            _ => Err(eyre!("Invalid map number")),
-        }?;
-
-        let map_size_blocks = MapSizeBlocks {
-            width: map_size_tiles.width / MapBlock::CELLS_PER_ROW,
-            height: map_size_tiles.height / MapBlock::CELLS_PER_COLUMN,
         };
 
-        let map_file_expected_size = MapBlock::PACKED_SIZE as u64
-            * map_size_blocks.width as u64
-            * map_size_blocks.height as u64;
-        if map_file_mul_metadata.len() != map_file_expected_size {
-            return Err(eyre!(
-                "Malformed map file: expected size doesn't match the real file size".to_owned()
+        // We need to use PathBuf instead of String, because the latter has a UTF-8 encoding, while the former
+        //  can have different encodings, even not valid UTF-*, which can be valid for the used OS.
+        let map_file_path = vfs::canonicalize(&map_file_path)
+            .io_context(|| format!("Check map{map_index} file path"))?;
+
+        let map_file_name = map_file_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let map_file_handle = vfs::open(&map_file_path)
+            .io_context(|| format!("Open map{map_index} file at '{map_file_name}'"))?;
+        // Only files on disk can be written, not the other files of the vfs.
+        let map_file_path = matches!(map_file_handle, VfsFile::Disk(_)).then_some(map_file_path);
+        let map_file_rdr = MapFileReader::open(map_file_handle, file_backend)?;
+        Ok((map_file_path, MapFileSource::new(map_file_rdr, map_index)?))
+    }
+
+    /// Checks the map data size against map_size_tiles, then see from_source.
+    fn from_source_with_size(
+        map_index: u32,
+        file_backend: FileBackend,
+        map_file_path: Option<PathBuf>,
+        map_file_src: MapFileSource,
+        map_size_tiles: MapSizeCells,
+    ) -> Result<MapPlane> {
+        if !map_size_tiles.is_valid() {
+            return Err(UocfError::out_of_range(format!(
+                "map size {}x{} (it must be a multiple of {}x{})",
+                map_size_tiles.width,
+                map_size_tiles.height,
+                MapBlock::CELLS_PER_ROW,
+                MapBlock::CELLS_PER_COLUMN
+            )));
+        }
+        let map_file_expected_size = map_size_tiles.map_file_len();
+        let map_file_len = map_file_src.len();
+        if map_file_len != map_file_expected_size {
+            return Err(UocfError::malformed(
+                map_file_src.file_name(),
+                0,
+                format!(
+                    "expected size {map_file_expected_size} ({}x{}) doesn't match the real size {map_file_len}.",
+                    map_size_tiles.width, map_size_tiles.height
+                ),
             ));
         }
+        Ok(Self::from_source(map_index, file_backend, map_file_path, map_file_src, map_size_tiles))
+    }
 
-        let map_plane = MapPlane {
+    /// The file size must have been checked against map_size_tiles.
+    fn from_source(
+        map_index: u32,
+        file_backend: FileBackend,
+        map_file_path: Option<PathBuf>,
+        map_file_src: MapFileSource,
+        map_size_tiles: MapSizeCells,
+    ) -> MapPlane {
+        MapPlane {
             index: map_index,
-            size_blocks: map_size_blocks,
-            map_file_mul_rdr,
+            size_blocks: map_size_tiles.to_blocks(),
+            file_backend,
+            map_file_path,
+            map_file_src,
             cached_blocks: BTreeMap::new(),
-        };
-        Ok(map_plane)
+            max_cached_blocks: None,
+            diff: None,
+            apply_diffs: false,
+            edited_blocks: BTreeMap::new(),
+        }
     }
 
     pub fn calc_blocks_to_load(&self, map_rect_to_show: &MapRectCells) -> Vec<MapBlockRelPos> {
@@ -370,9 +1117,8 @@ pub fn calc_blocks_to_load(&self, map_rect_to_show: &MapRectCells) -> Vec<MapBlo
         ret
     }
 
-    pub fn load_blocks(&mut self,   blocks_to_load: &mut Vec<MapBlockRelPos>) -> eyre::Result<()> {
+    pub fn load_blocks(&mut self,   blocks_to_load: &mut Vec<MapBlockRelPos>) -> Result<()> {
         const MAP_FILE_MAX_SEQ_BLOCKS: usize = 10_000; // Cap of blocks to be read sequentially.
-        const MAP_FILE_MAX_CHUNK_SIZE: usize = MapBlock::PACKED_SIZE * MAP_FILE_MAX_SEQ_BLOCKS;
 
         if blocks_to_load.is_empty() {
             //println!("Received empty load request (no blocks).");
@@ -399,8 +1145,8 @@ pub fn load_blocks(&mut self,   blocks_to_load: &mut Vec<MapBlockRelPos>) -> eyr
         // Having it sorted allows us to perform less file reads by acquiring blocks stored sequentially in the map file.
         blocks_to_load.sort(); // Sort first by x, then by y.
 
-        // Start reading blocks.
-        let mut blocks_buffer: Vec<u8> = vec![0; MAP_FILE_MAX_CHUNK_SIZE];
+        // Start reading blocks. The buffer isn't needed for the data we can get from a memory-mapped file.
+        let mut blocks_buffer: Vec<u8> = Vec::new();
         let mut blocks_read: usize = 0;
         let mut chunk_blocks_to_read_seq_count: usize;
         'read_chunks: while blocks_read < blocks_to_load.len() {
@@ -448,42 +1194,46 @@ pub fn load_blocks(&mut self,   blocks_to_load: &mut Vec<MapBlockRelPos>) -> eyr
             if block_to_seek.x >= self.size_blocks.width
                 || block_to_seek.y >= self.size_blocks.height
             {
-                Err(eyre!(format!(
-                    "Requested map block out of bounds {block_to_seek:?}.")
-                ))?;
+                return Err(UocfError::out_of_range(format!("map block {block_to_seek:?}")));
             }
 
             let block_idx = MapBlock::idx_from_coords(&block_to_seek, self.size_blocks.height);
             let off = (MapBlock::PACKED_SIZE * block_idx as usize) as u64;
-            self.map_file_mul_rdr
-                .seek(SeekFrom::Start(off))
-                .wrap_err(format!("Failed to seek to {off} for block {block_idx}."))?;
-
-            blocks_buffer.resize(chunk_blocks_to_read_seq_count * MapBlock::PACKED_SIZE, 0);
-            let read_result = self.map_file_mul_rdr
-                .read(blocks_buffer.as_mut())
-                .wrap_err("Read map chunk")?;
-            if 0 == read_result {
-                // EOF
-                return Err(eyre!("Encountered unexpected End Of File.".to_owned()));
-            }
 
-            let mut rdr = Cursor::new(blocks_buffer.as_slice());
+            let chunk_len = chunk_blocks_to_read_seq_count * MapBlock::PACKED_SIZE;
+            let chunk_bytes: &[u8] = match self.map_file_src.mapped_slice_at(off, chunk_len) {
+                Some(mapped_bytes) => mapped_bytes,
+                None => {
+                    blocks_buffer.resize(chunk_len, 0);
+                    self.map_file_src.read_exact_at(off, blocks_buffer.as_mut())?;
+                    blocks_buffer.as_slice()
+                }
+            };
+            let file_name = self.map_file_src.file_name();
+
+            let mut rdr = Cursor::new(chunk_bytes);
             let chunk_slice_to_loop =
                 &blocks_to_load[blocks_read..blocks_read + chunk_blocks_to_read_seq_count];
 
             'block_store: for block_pos in chunk_slice_to_loop.iter() {
                 if self.cached_blocks.contains_key(block_pos) {
-                    rdr.seek(SeekFrom::Current(MapBlock::PACKED_SIZE as i64))
-                        .wrap_err(format!(
-                            "Failed to seek after already cached block {:?}.",
-                            block_pos
-                        ))?;
+                    rdr.set_position(rdr.position() + MapBlock::PACKED_SIZE as u64);
                     blocks_read += 1;
                     continue 'block_store;
                 }
 
-                let mut new_block = MapBlock::from_reader(&mut rdr)?;
+                let mut new_block = MapBlock::from_reader(&mut rdr, file_name, off)?;
+                if self.apply_diffs
+                    && let Some(diff) = &self.diff
+                {
+                    let block_idx = MapBlock::idx_from_coords(block_pos, self.size_blocks.height);
+                    if let Some(patched_block) = diff.block(block_idx)? {
+                        new_block = patched_block;
+                    }
+                }
+                if let Some(edited_block) = self.edited_blocks.get(block_pos) {
+                    new_block = edited_block.clone();
+                }
                 new_block.internal_coords = block_pos.clone();
                 self.cached_blocks.insert(*block_pos, new_block);
                 blocks_read += 1;
@@ -492,6 +1242,192 @@ pub fn load_blocks(&mut self,   blocks_to_load: &mut Vec<MapBlockRelPos>) -> eyr
 
         //println!("Done reading block.");
 
+        if let Some(max_cached_blocks) = self.max_cached_blocks {
+            // Keep the blocks around the ones just requested (their bounding box center).
+            let (min_x, max_x) = blocks_to_load.iter().fold((u32::MAX, 0), |(lo, hi), p| (lo.min(p.x), hi.max(p.x)));
+            let (min_y, max_y) = blocks_to_load.iter().fold((u32::MAX, 0), |(lo, hi), p| (lo.min(p.y), hi.max(p.y)));
+            let center = MapBlockRelPos {
+                x: min_x + (max_x - min_x) / 2,
+                y: min_y + (max_y - min_y) / 2,
+            };
+            // Never evict what was just requested.
+            let max_cached_blocks = max_cached_blocks.max(blocks_to_load.len());
+            self.evict_farthest_blocks(center, max_cached_blocks);
+        }
+
         Ok(())
     }
+
+    /// Reads a column of blocks (the ones with the given x, top to bottom) straight from the file, without caching
+    ///  them: for scans of the whole map plane, which would fill the cache. Patches and edits are applied like in
+    ///  load_blocks.
+    pub fn read_block_column(&mut self, x: u32) -> Result<Vec<MapBlock>> {
+        if x >= self.size_blocks.width {
+            return Err(UocfError::out_of_range(format!("map block column {x}")));
+        }
+        // Blocks are stored top to bottom, then left to right: a column is contiguous in the file.
+        let height = self.size_blocks.height;
+        let first_block_idx = MapBlock::idx_from_coords(&MapBlockRelPos { x, y: 0 }, height);
+        let off = (MapBlock::PACKED_SIZE * first_block_idx as usize) as u64;
+        let column_len = height as usize * MapBlock::PACKED_SIZE;
+        let mut column_buffer: Vec<u8> = Vec::new();
+        let column_bytes: &[u8] = match self.map_file_src.mapped_slice_at(off, column_len) {
+            Some(mapped_bytes) => mapped_bytes,
+            None => {
+                column_buffer.resize(column_len, 0);
+                self.map_file_src.read_exact_at(off, column_buffer.as_mut())?;
+                column_buffer.as_slice()
+            }
+        };
+        let file_name = self.map_file_src.file_name();
+
+        let mut rdr = Cursor::new(column_bytes);
+        (0..height)
+            .map(|y| {
+                let block_pos = MapBlockRelPos { x, y };
+                let mut block = MapBlock::from_reader(&mut rdr, file_name, off)?;
+                if self.apply_diffs
+                    && let Some(diff) = &self.diff
+                    && let Some(patched_block) = diff.block(first_block_idx + y)?
+                {
+                    block = patched_block;
+                }
+                if let Some(edited_block) = self.edited_blocks.get(&block_pos) {
+                    block = edited_block.clone();
+                }
+                block.internal_coords = block_pos;
+                Ok(block)
+            })
+            .collect()
+    }
+}
+
+/// A MapPlane shared between threads. Cloning it gives another handle to the same map plane.
+/// Loading blocks takes the write lock, but only if some of them aren't cached yet: reads of cached blocks from
+///  different threads don't block each other.
+#[derive(Clone)]
+pub struct MapPlaneShared {
+    // Copies of the immutable MapPlane fields, readable without locking.
+    index: u32,
+    size_blocks: MapSizeBlocks,
+    map_plane: Arc<RwLock<MapPlane>>,
+}
+impl MapPlaneShared {
+    const ERR_LOCK_POISONED: &'static str = "MapPlane lock poisoned.";
+
+    pub fn new(map_plane: MapPlane) -> MapPlaneShared {
+        MapPlaneShared {
+            index: map_plane.index,
+            size_blocks: map_plane.size_blocks,
+            map_plane: Arc::new(RwLock::new(map_plane)),
+        }
+    }
+
+    pub fn index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn size_blocks(&self) -> MapSizeBlocks {
+        self.size_blocks
+    }
+
+    #[inline(always)]
+    pub fn contains_block(&self, pos: MapBlockRelPos) -> bool {
+        pos.x < self.size_blocks.width && pos.y < self.size_blocks.height
+    }
+
+    /// Shared access to the map plane, to read many cached blocks with a single lock.
+    pub fn read(&self) -> RwLockReadGuard<'_, MapPlane> {
+        self.map_plane.read().expect(Self::ERR_LOCK_POISONED)
+    }
+
+    /// Exclusive access to the map plane, to change its settings or its cache.
+    pub fn write(&self) -> RwLockWriteGuard<'_, MapPlane> {
+        self.map_plane.write().expect(Self::ERR_LOCK_POISONED)
+    }
+
+    /// Loads the blocks which aren't cached yet.
+    pub fn load_blocks(&self, blocks_to_load: &mut Vec<MapBlockRelPos>) -> Result<()> {
+        {
+            let map_plane = self.read();
+            if blocks_to_load.iter().all(|pos| map_plane.block(*pos).is_some()) {
+                return Ok(());
+            }
+        }
+        self.write().load_blocks(blocks_to_load)
+    }
+
+    /// Loads the blocks which aren't cached yet, and returns a copy of all the requested blocks.
+    /// The copies are taken under the same lock as the load, so they can't be evicted in the meanwhile.
+    pub fn load_blocks_cloned(
+        &self,
+        blocks_to_load: &mut Vec<MapBlockRelPos>,
+    ) -> Result<BTreeMap<MapBlockRelPos, MapBlock>> {
+        let mut map_plane = self.write();
+        map_plane.load_blocks(blocks_to_load)?;
+        blocks_to_load
+            .iter()
+            .map(|pos| {
+                let block = map_plane
+                    .block(*pos)
+                    .ok_or_else(|| UocfError::out_of_range(format!("map block {pos:?}, not cached after loading it")))?;
+                Ok((*pos, block.clone()))
+            })
+            .collect()
+    }
+
+    /// Copy of a block, loading it if it isn't cached.
+    pub fn load_block(&self, pos: MapBlockRelPos) -> Result<MapBlock> {
+        let mut blocks = self.load_blocks_cloned(&mut vec![pos])?;
+        Ok(blocks.remove(&pos).unwrap())
+    }
+
+    /// Copy of a block, only if it's cached.
+    pub fn block(&self, pos: MapBlockRelPos) -> Option<MapBlock> {
+        self.read().block(pos).cloned()
+    }
+
+    /// A cell, loading its block if it isn't cached.
+    pub fn cell(&self, x: u32, y: u32) -> Result<MapCell> {
+        let block_pos = MapBlockRelPos {
+            x: MapCell::coords_of_parent_block_x(x),
+            y: MapCell::coords_of_parent_block_y(y),
+        };
+        if !self.contains_block(block_pos) {
+            return Err(UocfError::out_of_range(format!("map cell ({x}, {y})")));
+        }
+        self.load_blocks(&mut vec![block_pos])?;
+        let map_plane = self.read();
+        let block = map_plane
+            .block(block_pos)
+            .ok_or_else(|| UocfError::out_of_range(format!("map block {block_pos:?}, not cached after loading it")))?;
+        block
+            .cell(MapCell::coords_in_block_x(x), MapCell::coords_in_block_y(y))
+            .copied()
+    }
+
+    /// Changes many cells with a single lock (see MapPlane::set_cell). Returns the previous cells, in the same order.
+    /// If a cell can't be changed, the ones before it stay changed.
+    pub fn set_cells(&self, cells: impl IntoIterator<Item = (u32, u32, MapCell)>) -> Result<Vec<(u32, u32, MapCell)>> {
+        let mut map_plane = self.write();
+        cells
+            .into_iter()
+            .map(|(x, y, cell)| Ok((x, y, map_plane.set_cell(x, y, cell)?)))
+            .collect()
+    }
+
+    /// See MapPlane::save_edits.
+    pub fn save_edits(&self, make_backup: bool) -> Result<usize> {
+        self.write().save_edits(make_backup)
+    }
+
+    /// See MapPlane::read_block_column. The map plane is locked for a single column.
+    pub fn read_block_column(&self, x: u32) -> Result<Vec<MapBlock>> {
+        self.write().read_block_column(x)
+    }
+
+    /// See MapPlane::evict_blocks_outside.
+    pub fn evict_blocks_outside(&self, rect: &MapRectBlocks) -> usize {
+        self.write().evict_blocks_outside(rect)
+    }
 }
@@ -0,0 +1,161 @@
+// Map plane definitions: the size (and name) of each map plane, by index.
+// - MapDefinitions::standard holds the maps of the official clients. Felucca and Trammel have two sizes: the
+//   pre-Mondain's Legacy one (6144x4096) and the later one (7168x4096).
+// - uomap.def (UOMAP_DEF_FILE_NAME, see generic_def) defines custom ones, for shards with resized maps or more map
+//   planes (map6 and beyond). An entry per line: "<index> <width> <height> [name]", sizes in tiles, multiples of 8.
+//   Alternative sizes go between braces, e.g. "0 {7168 6144} 4096 Felucca": every combination is a candidate.
+//   The entries of an index replace the standard ones for that index.
+// - A map file can have more than one candidate size: MapPlane::init picks the one matching the file size.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use super::map::{MapBlock, MapSizeCells};
+use crate::errors::{Result, UocfError};
+use crate::generic_def::DefFile;
+
+pub const UOMAP_DEF_FILE_NAME: &str = "uomap.def";
+
+#[derive(Clone, Debug, Default)]
+pub struct MapDefinition {
+    pub name: Option<String>,
+    /// Candidate sizes, in order of preference.
+    pub sizes: Vec<MapSizeCells>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MapDefinitions {
+    definitions: BTreeMap<u32, MapDefinition>,
+}
+
+impl MapDefinitions {
+    pub fn standard() -> Self {
+        let sizes = [
+            (0, "Felucca", &[(7168, 4096), (6144, 4096)][..]),
+            (1, "Trammel", &[(7168, 4096), (6144, 4096)]),
+            (2, "Ilshenar", &[(2304, 1600)]),
+            (3, "Malas", &[(2560, 2048)]),
+            (4, "Tokuno", &[(1448, 1448)]),
+            (5, "Ter Mur", &[(1280, 4096)]),
+        ];
+        let definitions = sizes
+            .into_iter()
+            .map(|(index, name, sizes)| {
+                let definition = MapDefinition {
+                    name: Some(name.to_owned()),
+                    sizes: sizes
+                        .iter()
+                        .map(|&(width, height)| MapSizeCells { width, height })
+                        .collect(),
+                };
+                (index, definition)
+            })
+            .collect();
+        MapDefinitions { definitions }
+    }
+
+    /// Definitions from a uomap.def file.
+    pub fn load(file_path: &Path) -> Result<Self> {
+        Self::from_def(&DefFile::load(file_path)?)
+    }
+
+    pub fn from_def(def: &DefFile) -> Result<Self> {
+        let file_name = def.file_name.as_str();
+        let mut definitions = BTreeMap::<u32, MapDefinition>::new();
+        for entry in &def.entries {
+            let index: u32 = entry.parse_value(file_name, 0, "map index")?;
+            let parse_sizes =
+                |field_index: usize, what: &str, multiple_of: u32| -> Result<Vec<u32>> {
+                    let values = entry.values(field_index);
+                    if values.is_empty() {
+                        return Err(UocfError::malformed(
+                            file_name,
+                            entry.offset,
+                            format!("missing {what}"),
+                        ));
+                    }
+                    values
+                        .iter()
+                        .map(|value| match value.parse::<u32>() {
+                            Ok(size) if size > 0 && size % multiple_of == 0 => Ok(size),
+                            _ => Err(UocfError::malformed(
+                                file_name,
+                                entry.offset,
+                                format!("invalid {what}: '{value}' (a multiple of {multiple_of})"),
+                            )),
+                        })
+                        .collect()
+                };
+            let widths = parse_sizes(1, "map width", MapBlock::CELLS_PER_ROW)?;
+            let heights = parse_sizes(2, "map height", MapBlock::CELLS_PER_COLUMN)?;
+            let name_words: Vec<&str> = (3..entry.fields.len())
+                .flat_map(|field_index| entry.values(field_index))
+                .map(String::as_str)
+                .collect();
+
+            // An index can be on more lines: their sizes add up.
+            let definition = definitions.entry(index).or_default();
+            for &width in &widths {
+                for &height in &heights {
+                    definition.sizes.push(MapSizeCells { width, height });
+                }
+            }
+            if !name_words.is_empty() {
+                definition.name = Some(name_words.join(" "));
+            }
+        }
+        Ok(MapDefinitions { definitions })
+    }
+
+    /// Adds the definitions of other, which replace the ones of the same index.
+    pub fn extend(&mut self, other: MapDefinitions) {
+        self.definitions.extend(other.definitions);
+    }
+
+    /// Defined map plane indices, in ascending order.
+    pub fn indices(&self) -> impl Iterator<Item = u32> + '_ {
+        self.definitions.keys().copied()
+    }
+
+    pub fn get(&self, map_index: u32) -> Option<&MapDefinition> {
+        self.definitions.get(&map_index)
+    }
+
+    pub fn name(&self, map_index: u32) -> Option<&str> {
+        self.get(map_index)?.name.as_deref()
+    }
+
+    /// The size of the map plane whose map file is file_len bytes long.
+    pub fn size_for_file(
+        &self,
+        map_index: u32,
+        file_name: &str,
+        file_len: u64,
+    ) -> Result<MapSizeCells> {
+        let Some(definition) = self.get(map_index) else {
+            return Err(UocfError::out_of_range(format!(
+                "map index {map_index} (no known map size, define it in {UOMAP_DEF_FILE_NAME})"
+            )));
+        };
+        definition
+            .sizes
+            .iter()
+            .find(|size| size.map_file_len() == file_len)
+            .copied()
+            .ok_or_else(|| {
+                let expected: Vec<String> = definition
+                    .sizes
+                    .iter()
+                    .map(|size| format!("{} ({}x{})", size.map_file_len(), size.width, size.height))
+                    .collect();
+                UocfError::malformed(
+                    file_name,
+                    0,
+                    format!(
+                        "expected size {} doesn't match the real size {file_len}.",
+                        expected.join(" or ")
+                    ),
+                )
+            })
+    }
+}
@@ -0,0 +1,86 @@
+// Sextant coordinates: the latitude and longitude shown by the in-game sextant, as the emulators compute them
+//  (RunUO/ServUO Sextant.Format).
+// - The world is 5120x4096 tiles around the throne of Lord British (1323, 1624): 360 degrees each way, a degree is
+//   60 minutes. Past 180 degrees it wraps to the other side.
+// - Felucca and Trammel: the dungeons and T2A (x >= 5120, y >= 2304) are around (5936, 3112) instead, and the rest of
+//   the area past 5120 has no sextant coordinates. The other maps use the same center as Britannia, within their
+//   size.
+
+use std::fmt;
+
+const SEXTANT_WIDTH: i32 = 5120;
+const SEXTANT_HEIGHT: i32 = 4096;
+const BRITANNIA_CENTER: (i32, i32) = (1323, 1624);
+const LOST_LANDS_CENTER: (i32, i32) = (5936, 3112);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Sextant {
+    pub lat_degrees: u32,
+    pub lat_minutes: u32,
+    pub south: bool,
+    pub long_degrees: u32,
+    pub long_minutes: u32,
+    pub east: bool,
+}
+
+impl Sextant {
+    /// Sextant coordinates of a tile of a map plane of the given size, or None where the sextant doesn't work.
+    pub fn from_coords(
+        map_index: u32,
+        x: u32,
+        y: u32,
+        map_width: u32,
+        map_height: u32,
+    ) -> Option<Self> {
+        let center = if map_index <= 1 {
+            if x < SEXTANT_WIDTH as u32 && y < SEXTANT_HEIGHT as u32 {
+                BRITANNIA_CENTER
+            } else if (2304..SEXTANT_HEIGHT as u32).contains(&y) && (5120..6144).contains(&x) {
+                LOST_LANDS_CENTER
+            } else {
+                return None;
+            }
+        } else if x < map_width && y < map_height {
+            BRITANNIA_CENTER
+        } else {
+            return None;
+        };
+        let (long_degrees, long_minutes, east) = angle(x as i32 - center.0, SEXTANT_WIDTH);
+        let (lat_degrees, lat_minutes, south) = angle(y as i32 - center.1, SEXTANT_HEIGHT);
+        Some(Self {
+            lat_degrees,
+            lat_minutes,
+            south,
+            long_degrees,
+            long_minutes,
+            east,
+        })
+    }
+}
+
+/// Degrees, minutes and whether it's positive (south or east) of a distance from the center.
+fn angle(distance: i32, size: i32) -> (u32, u32, bool) {
+    let mut angle = (distance * 360) as f64 / size as f64;
+    if angle > 180.0 {
+        angle = -180.0 + angle % 180.0;
+    }
+    let positive = angle >= 0.0;
+    let angle = angle.abs();
+    (angle as u32, (angle.fract() * 60.0) as u32, positive)
+}
+
+/// As the client shows it, e.g. "6°35'S 7°48'E".
+impl fmt::Display for Sextant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}°{}'{} {}°{}'{}",
+            self.lat_degrees,
+            self.lat_minutes,
+            if self.south { 'S' } else { 'N' },
+            self.long_degrees,
+            self.long_minutes,
+            if self.east { 'E' } else { 'W' }
+        )
+    }
+}
@@ -0,0 +1,339 @@
+#![allow(dead_code)]
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::{BTreeMap, HashMap};
+use std::io::{BufReader, Cursor, SeekFrom, prelude::*};
+use std::path::PathBuf;
+
+use super::map::{
+    MapBlock, MapBlockRelPos, MapRectBlocks, MapSizeBlocks, evict_farthest_blocks, load_diff_block_list,
+    load_diff_file,
+};
+use crate::errors::{IoResultExt, Result, UocfError};
+use crate::vfs::{self, VfsFile};
+use crate::generic_index;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct StaticItem {
+    // Static items are stored in statics*.mul, grouped by map block. The block they belong to is
+    //  found by looking up the block index in staidx*.mul.
+    pub id: u16,
+    // Position of the item inside the block (0..8).
+    pub x: u8,
+    pub y: u8,
+    pub z: i8,
+    pub hue: u16,
+}
+impl StaticItem {
+    pub const PACKED_SIZE: usize = 2 + 1 + 1 + 1 + 2;
+
+    /// Reads an item from a buffer holding data of the given file, starting at base_offset in the file.
+    pub fn from_reader(rdr: &mut Cursor<&[u8]>, file_name: &str, base_offset: u64) -> Result<StaticItem> {
+        let offset = base_offset + rdr.position();
+        let strerr_base = "static item: ";
+        Ok(StaticItem {
+            id: rdr
+                .read_u16::<LittleEndian>()
+                .read_context(file_name, offset, || format!("{strerr_base}id"))?,
+            x: rdr.read_u8().read_context(file_name, offset, || format!("{strerr_base}x"))?,
+            y: rdr.read_u8().read_context(file_name, offset, || format!("{strerr_base}y"))?,
+            z: rdr.read_i8().read_context(file_name, offset, || format!("{strerr_base}z"))?,
+            hue: rdr
+                .read_u16::<LittleEndian>()
+                .read_context(file_name, offset, || format!("{strerr_base}hue"))?,
+        })
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StaticsBlock {
+    pub internal_coords: MapBlockRelPos,
+    // Items are kept in the same order as they are stored in the file, which is also the order
+    //  the classic client uses to draw items sharing the same cell and z.
+    items: Vec<StaticItem>,
+}
+impl StaticsBlock {
+    pub fn items(&self) -> &[StaticItem] {
+        &self.items
+    }
+
+    /// Iterate over the items placed in the given cell of this block.
+    pub fn items_at(&self, x: u32, y: u32) -> impl Iterator<Item = &StaticItem> {
+        self.items
+            .iter()
+            .filter(move |item| item.x as u32 == x && item.y as u32 == y)
+    }
+}
+
+// Statics patches used by pre-UOP clients: stadifl*.mul lists the indices of the patched blocks, stadifi*.mul has
+//  an index entry for each of them (in the same order), pointing to the items in stadif*.mul.
+struct StaticsDiff {
+    // Block index -> (lookup, len) of the items in data. None if the patch empties the block.
+    blocks: HashMap<u32, Option<(u32, u32)>>,
+    data: Vec<u8>,
+    data_file_name: String,
+}
+impl StaticsDiff {
+    fn load(
+        stadifl_file_path: PathBuf,
+        stadifi_file_path: PathBuf,
+        stadif_file_path: PathBuf,
+    ) -> Result<StaticsDiff> {
+        let stadifi_file_name = stadifi_file_path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        let data_file_name = stadif_file_path.file_name().map_or(String::new(), |name| name.to_string_lossy().into_owned());
+        let block_list = load_diff_block_list(stadifl_file_path)?;
+        let stadifi = generic_index::IndexFile::load(stadifi_file_path)?;
+        let data = load_diff_file(stadif_file_path)?;
+        if stadifi.element_count() < block_list.len() {
+            return Err(UocfError::malformed(
+                stadifi_file_name,
+                0,
+                format!(
+                    "{} patched blocks listed, but only {} index entries.",
+                    block_list.len(),
+                    stadifi.element_count()
+                ),
+            ));
+        }
+
+        let mut blocks = HashMap::with_capacity(block_list.len());
+        for (i_block, block_idx) in block_list.into_iter().enumerate() {
+            let idx_elem = stadifi.element(i_block)?;
+            let items_pos = match (idx_elem.lookup(), idx_elem.len()) {
+                (Some(lookup), Some(len)) => {
+                    if lookup as u64 + len as u64 > data.len() as u64 {
+                        return Err(UocfError::malformed(
+                            &data_file_name,
+                            lookup as u64,
+                            format!("patched statics block {block_idx} points outside of the file."),
+                        ));
+                    }
+                    Some((lookup, len))
+                }
+                _ => None,
+            };
+            // Later entries override earlier ones for the same block.
+            blocks.insert(block_idx, items_pos);
+        }
+        Ok(StaticsDiff {
+            blocks,
+            data,
+            data_file_name,
+        })
+    }
+
+    fn block_items(&self, block_idx: u32) -> Result<Option<Vec<StaticItem>>> {
+        let Some(&items_pos) = self.blocks.get(&block_idx) else {
+            return Ok(None);
+        };
+        let Some((lookup, len)) = items_pos else {
+            return Ok(Some(Vec::new()));
+        };
+        let item_qty = len as usize / StaticItem::PACKED_SIZE;
+        let mut rdr = Cursor::new(&self.data[lookup as usize..(lookup + len) as usize]);
+        let mut items = Vec::with_capacity(item_qty);
+        for _ in 0..item_qty {
+            items.push(StaticItem::from_reader(&mut rdr, &self.data_file_name, lookup as u64)?);
+        }
+        Ok(Some(items))
+    }
+}
+
+pub struct StaticsPlane {
+    pub index: u32,
+    pub size_blocks: MapSizeBlocks,
+    staidx: generic_index::IndexFile,
+    statics_file_mul_rdr: BufReader<VfsFile>,
+    statics_file_size: u64,
+    cached_blocks: BTreeMap<MapBlockRelPos, StaticsBlock>,
+    diff: Option<StaticsDiff>,
+    // If false, the diff is kept loaded but the original blocks are returned.
+    apply_diffs: bool,
+}
+impl StaticsPlane {
+    /// Names of the (list, index, data) diff files of pre-UOP clients.
+    pub fn diff_file_names(map_index: u32) -> (String, String, String) {
+        (
+            format!("stadifl{map_index}.mul"),
+            format!("stadifi{map_index}.mul"),
+            format!("stadif{map_index}.mul"),
+        )
+    }
+
+    /// Load the statics patches. Cached blocks are dropped, so that they are read again with the patches.
+    /// Returns the number of patched blocks.
+    pub fn load_diffs(
+        &mut self,
+        stadifl_file_path: PathBuf,
+        stadifi_file_path: PathBuf,
+        stadif_file_path: PathBuf,
+    ) -> Result<usize> {
+        let diff = StaticsDiff::load(stadifl_file_path, stadifi_file_path, stadif_file_path)?;
+        let patched_blocks = diff.blocks.len();
+        self.diff = Some(diff);
+        self.apply_diffs = true;
+        self.cached_blocks.clear();
+        Ok(patched_blocks)
+    }
+
+    pub fn has_diffs(&self) -> bool {
+        self.diff.is_some()
+    }
+
+    pub fn diff_block_count(&self) -> usize {
+        self.diff.as_ref().map_or(0, |diff| diff.blocks.len())
+    }
+
+    pub fn apply_diffs(&self) -> bool {
+        self.apply_diffs
+    }
+
+    /// Toggle the use of the loaded patches. Cached blocks are dropped if the setting changes, so the caller has to
+    ///  load again the blocks it needs.
+    pub fn set_apply_diffs(&mut self, apply_diffs: bool) {
+        if self.apply_diffs == apply_diffs {
+            return;
+        }
+        self.apply_diffs = apply_diffs;
+        if self.diff.is_some() {
+            self.cached_blocks.clear();
+        }
+    }
+
+    pub fn block(&self, pos: MapBlockRelPos) -> Option<&StaticsBlock> {
+        self.cached_blocks.get(&pos)
+    }
+
+    pub fn cached_block_count(&self) -> usize {
+        self.cached_blocks.len()
+    }
+
+    /// Drop from the cache every block outside the given rectangle. Returns the number of evicted blocks.
+    pub fn evict_blocks_outside(&mut self, rect: &MapRectBlocks) -> usize {
+        let count_before = self.cached_blocks.len();
+        self.cached_blocks.retain(|pos, _| rect.contains(pos));
+        count_before - self.cached_blocks.len()
+    }
+
+    /// Drop from the cache the blocks farthest from the given position, until the cache size is within the limit.
+    /// Returns the number of evicted blocks.
+    pub fn evict_farthest_blocks(&mut self, center: MapBlockRelPos, max_blocks: usize) -> usize {
+        evict_farthest_blocks(&mut self.cached_blocks, center, max_blocks)
+    }
+
+    /// Statics share the block grid of the map plane with the same index, so the caller has to provide
+    ///  the map size (in blocks) it got from MapPlane::init.
+    pub fn init(
+        statics_file_mul_path: PathBuf,
+        staidx_file_mul_path: PathBuf,
+        map_index: u32,
+        size_blocks: MapSizeBlocks,
+    ) -> Result<StaticsPlane> {
+        let statics_file_mul_path = vfs::canonicalize(&statics_file_mul_path)
+            .io_context(|| format!("Check statics{map_index}.mul path"))?;
+
+        let statics_file_mul_handle = vfs::open(&statics_file_mul_path).io_context(|| {
+            format!(
+                "Open statics{map_index}.mul at '{}'",
+                statics_file_mul_path.to_string_lossy()
+            )
+        })?;
+        let statics_file_size = statics_file_mul_handle
+            .len()
+            .io_context(|| format!("Get statics{map_index}.mul size"))?;
+
+        let staidx = generic_index::IndexFile::load(staidx_file_mul_path)?;
+
+        let expected_index_elements = size_blocks.width as usize * size_blocks.height as usize;
+        if staidx.element_count() < expected_index_elements {
+            return Err(UocfError::malformed(
+                format!("staidx{map_index}.mul"),
+                0,
+                format!(
+                    "expected {expected_index_elements} entries, found {}",
+                    staidx.element_count()
+                ),
+            ));
+        }
+
+        let statics_plane = StaticsPlane {
+            index: map_index,
+            size_blocks,
+            staidx,
+            statics_file_mul_rdr: BufReader::new(statics_file_mul_handle),
+            statics_file_size,
+            cached_blocks: BTreeMap::new(),
+            diff: None,
+            apply_diffs: false,
+        };
+        Ok(statics_plane)
+    }
+
+    pub fn load_blocks(&mut self, blocks_to_load: &mut [MapBlockRelPos]) -> Result<()> {
+        // Having it sorted allows the buffered reader to mostly move forward in the file.
+        blocks_to_load.sort(); // Sort first by x, then by y.
+
+        let mut block_buffer: Vec<u8> = Vec::new();
+        for block_pos in blocks_to_load.iter() {
+            if self.cached_blocks.contains_key(block_pos) {
+                continue;
+            }
+            if block_pos.x >= self.size_blocks.width || block_pos.y >= self.size_blocks.height {
+                return Err(UocfError::out_of_range(format!("statics block {block_pos:?}")));
+            }
+
+            let block_idx = MapBlock::idx_from_coords(block_pos, self.size_blocks.height);
+
+            let mut new_block = StaticsBlock {
+                internal_coords: *block_pos,
+                items: Vec::new(),
+            };
+
+            if self.apply_diffs
+                && let Some(diff) = &self.diff
+                && let Some(patched_items) = diff.block_items(block_idx)?
+            {
+                new_block.items = patched_items;
+                self.cached_blocks.insert(*block_pos, new_block);
+                continue;
+            }
+
+            let idx_elem = self.staidx.element(block_idx as usize)?;
+
+            // Blocks without statics have an invalid lookup (or a zero length) in the index file.
+            let (lookup, len) = match (idx_elem.lookup(), idx_elem.len()) {
+                (Some(lookup), Some(len)) => (lookup, len),
+                _ => {
+                    self.cached_blocks.insert(*block_pos, new_block);
+                    continue;
+                }
+            };
+            if lookup as u64 + len as u64 > self.statics_file_size {
+                return Err(UocfError::malformed(
+                    format!("staidx{}.mul", self.index),
+                    block_idx as u64 * generic_index::IndexElement::PACKED_SIZE as u64,
+                    format!("statics block {block_pos:?} points outside of statics{}.mul.", self.index),
+                ));
+            }
+
+            let item_qty = len as usize / StaticItem::PACKED_SIZE;
+            block_buffer.resize(item_qty * StaticItem::PACKED_SIZE, 0);
+            self.statics_file_mul_rdr
+                .seek(SeekFrom::Start(lookup as u64))
+                .io_context(|| format!("Seek to {lookup} for statics block {block_idx}"))?;
+            let statics_file_name = format!("statics{}.mul", self.index);
+            self.statics_file_mul_rdr
+                .read_exact(block_buffer.as_mut())
+                .read_context(&statics_file_name, lookup as u64, || format!("statics block {block_idx}"))?;
+
+            let mut rdr = Cursor::new(block_buffer.as_slice());
+            new_block.items.reserve_exact(item_qty);
+            for _ in 0..item_qty {
+                new_block.items.push(StaticItem::from_reader(&mut rdr, &statics_file_name, lookup as u64)?);
+            }
+            self.cached_blocks.insert(*block_pos, new_block);
+        }
+
+        Ok(())
+    }
+}
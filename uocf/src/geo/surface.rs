@@ -0,0 +1,141 @@
+// Where a walker can stand in a map cell: on the land, or on top of a static item, following the tiledata flags. The
+//  rules are a simplified version of the server ones (RunUO/ServUO Map.CanFit and MovementImpl):
+// - the land can be stood on at its z, unless its tile is impassable or wet (water);
+// - a static item flagged as surface or bridge can be stood on at its top (a bridge counts half its height);
+// - a spot is blocked if an impassable or surface item overlaps the body height above it: the ground under a floor, or
+//   under a table, can't be stood on;
+// - stepping into a cell from a z ends on the highest spot not more than MAX_STEP_UP_Z above it. Going down is always
+//   allowed.
+// WorldQuery applies them to the cells of a map plane and its statics.
+
+use super::map::{MapBlockRelPos, MapCell, MapPlaneShared};
+use super::statics::{StaticItem, StaticsPlane};
+use crate::errors::Result;
+use crate::tiledata::TileData;
+
+/// Height of a walking body, in z units: items overlapping it block a standing spot.
+pub const BODY_HEIGHT_Z: i32 = 16;
+/// Highest climb allowed by a single step, like the classic client and the servers.
+pub const MAX_STEP_UP_Z: i32 = 14;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SurfaceSource {
+    Land,
+    /// A static item, by tile id.
+    Static(u16),
+}
+
+/// A z a walker can stand at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SurfaceSpot {
+    pub z: i32,
+    pub source: SurfaceSource,
+}
+
+/// Standing spots of a cell, from its land tile and its static items, lowest first. Tiles missing from the tiledata
+///  have no flags.
+pub fn standing_spots<'a>(
+    tiledata: &TileData,
+    land: MapCell,
+    statics: impl IntoIterator<Item = &'a StaticItem>,
+) -> Vec<SurfaceSpot> {
+    let mut spots = Vec::new();
+    let land_walkable = tiledata
+        .land_tile(land.id)
+        .is_none_or(|land_tile| !land_tile.flags.impassable() && !land_tile.flags.wet());
+    if land_walkable {
+        spots.push(SurfaceSpot {
+            z: land.z as i32,
+            source: SurfaceSource::Land,
+        });
+    }
+
+    // (bottom z, top z) of the items blocking the spots they overlap.
+    let mut obstacles: Vec<(i32, i32)> = Vec::new();
+    for item in statics {
+        let Some(item_tile) = tiledata.item_tile(item.id) else {
+            continue;
+        };
+        let bottom = item.z as i32;
+        let top = bottom + item_tile.height() as i32;
+        let surface = item_tile.flags.surface() || item_tile.flags.bridge();
+        if surface {
+            spots.push(SurfaceSpot {
+                z: top,
+                source: SurfaceSource::Static(item.id),
+            });
+        }
+        if surface || item_tile.flags.impassable() {
+            obstacles.push((bottom, top));
+        }
+    }
+
+    spots.retain(|spot| {
+        !obstacles
+            .iter()
+            .any(|&(bottom, top)| top > spot.z && bottom < spot.z + BODY_HEIGHT_Z)
+    });
+    spots.sort_by_key(|spot| spot.z);
+    spots
+}
+
+/// The spot reached stepping into a cell from from_z: the highest one not more than MAX_STEP_UP_Z above it.
+pub fn step_spot(spots: &[SurfaceSpot], from_z: i32) -> Option<SurfaceSpot> {
+    spots
+        .iter()
+        .rev()
+        .find(|spot| spot.z <= from_z + MAX_STEP_UP_Z)
+        .copied()
+}
+
+/// Height queries on a map plane and its statics.
+/// Without a statics plane, only the land counts.
+pub struct WorldQuery<'a> {
+    tiledata: &'a TileData,
+    map_plane: &'a MapPlaneShared,
+    statics_plane: Option<&'a mut StaticsPlane>,
+}
+impl<'a> WorldQuery<'a> {
+    pub fn new(
+        tiledata: &'a TileData,
+        map_plane: &'a MapPlaneShared,
+        statics_plane: Option<&'a mut StaticsPlane>,
+    ) -> Self {
+        Self {
+            tiledata,
+            map_plane,
+            statics_plane,
+        }
+    }
+
+    /// Standing spots of a cell, lowest first (see standing_spots). Loads the blocks of the cell if they aren't
+    ///  cached. Err if the cell is outside of the map plane.
+    pub fn standing_spots(&mut self, x: u32, y: u32) -> Result<Vec<SurfaceSpot>> {
+        let land = self.map_plane.cell(x, y)?;
+        let Some(statics_plane) = self.statics_plane.as_deref_mut() else {
+            return Ok(standing_spots(self.tiledata, land, []));
+        };
+        let block_pos = MapBlockRelPos {
+            x: MapCell::coords_of_parent_block_x(x),
+            y: MapCell::coords_of_parent_block_y(y),
+        };
+        statics_plane.load_blocks(&mut [block_pos])?;
+        let items = statics_plane
+            .block(block_pos)
+            .into_iter()
+            .flat_map(|block| {
+                block.items_at(MapCell::coords_in_block_x(x), MapCell::coords_in_block_y(y))
+            });
+        Ok(standing_spots(self.tiledata, land, items))
+    }
+
+    /// The highest z a walker can stand at in a cell, if any.
+    pub fn surface_top_z(&mut self, x: u32, y: u32) -> Result<Option<i32>> {
+        Ok(self.standing_spots(x, y)?.last().map(|spot| spot.z))
+    }
+
+    /// The z reached stepping into a cell from from_z (see step_spot), if it can be entered.
+    pub fn step_z(&mut self, x: u32, y: u32, from_z: i32) -> Result<Option<i32>> {
+        Ok(step_spot(&self.standing_spots(x, y)?, from_z).map(|spot| spot.z))
+    }
+}
@@ -0,0 +1,159 @@
+#![allow(dead_code)]
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{Cursor, prelude::*};
+use std::path::PathBuf;
+
+use crate::errors::{CursorReadExt, IoResultExt, Result, UocfError};
+use crate::vfs;
+use crate::utils::color::*;
+
+/* Start of HueEntry struct */
+
+#[derive(Clone, Debug)]
+pub struct HueEntry {
+    /* Internal, utility properties */
+    pub hue_id: u16,
+
+    /* File properties */
+    // Color ramp, from the darkest to the brightest color. Stored as bgra5551 (u16), like in the file.
+    pub colors: [u16; Self::COLORS_QTY],
+    pub table_start: u16,
+    pub table_end: u16,
+    name: [u8; Self::NAME_LEN],
+}
+
+impl Default for HueEntry {
+    fn default() -> Self {
+        Self {
+            hue_id: 0,
+            colors: [0; Self::COLORS_QTY],
+            table_start: 0,
+            table_end: 0,
+            name: [0; Self::NAME_LEN],
+        }
+    }
+}
+
+impl HueEntry {
+    pub const COLORS_QTY: usize = 32;
+    const NAME_LEN: usize = 20;
+    pub const PACKED_SIZE: usize = (Self::COLORS_QTY * 2) + 2 + 2 + Self::NAME_LEN;
+
+    pub fn name_ascii(&self) -> &str {
+        // Names are null-terminated ASCII strings. Find the null terminator
+        // and convert the slice up to that point to a &str.
+        let null_pos = self.name.iter().position(|&c| c == 0).unwrap_or(Self::NAME_LEN);
+        std::str::from_utf8(&self.name[..null_pos]).unwrap_or("")
+    }
+
+    /// Color ramp converted to rgba8888 (alpha is always set to 255), as little endian bytes (R, G, B, A).
+    pub fn colors_rgba8888(&self) -> [u8; Self::COLORS_QTY * 4] {
+        let mut out = [0u8; Self::COLORS_QTY * 4];
+        for (i_color, &color_16) in self.colors.iter().enumerate() {
+            let mut pixel_16 = Bgra5551::new_from_val(color_16);
+            pixel_16.set_a(1);
+            out[i_color * 4..(i_color + 1) * 4]
+                .copy_from_slice(&pixel_16.as_rgba8888().value().to_le_bytes());
+        }
+        out
+    }
+}
+/* End of HueEntry struct */
+
+/* Start of Hues struct */
+
+pub struct Hues {
+    hue_data: Vec<HueEntry>,
+}
+
+impl Hues {
+    const FILE_NAME: &'static str = "hues.mul";
+    const ENTRIES_PER_BLOCK: usize = 8;
+    const BLOCK_PACKED_SIZE: usize = 4 /* u32 header */ + (HueEntry::PACKED_SIZE * Self::ENTRIES_PER_BLOCK);
+    // Hue 0 means "not hued": hue ids stored in the game data are 1-based.
+    pub const HUE_NONE: u16 = 0;
+
+    pub fn len(&self) -> usize {
+        self.hue_data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hue_data.is_empty()
+    }
+
+    /// Get a hue by its 1-based id, as it's stored in statics or sent by the server.
+    /// The highest bit of the hue value (partial hue flag) is ignored.
+    pub fn hue(&self, hue_id: u16) -> Option<&HueEntry> {
+        let hue_id = hue_id & 0x7FFF;
+        if hue_id == Self::HUE_NONE {
+            return None;
+        }
+        self.hue_data.get(hue_id as usize - 1)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &HueEntry> {
+        self.hue_data.iter()
+    }
+
+    pub fn load(file_path: PathBuf) -> Result<Hues> {
+        let file_path = vfs::canonicalize(&file_path).io_context(|| "Check hues.mul path")?;
+
+        let mut file_handle = vfs::open(&file_path)
+            .io_context(|| format!("Open hues.mul at '{}'", file_path.to_string_lossy()))?;
+        let file_size = file_handle.len().io_context(|| "Get hues.mul size")? as usize;
+
+        // Some files have trailing data: ignore incomplete blocks.
+        let block_qty = file_size / Self::BLOCK_PACKED_SIZE;
+        if block_qty == 0 {
+            return Err(UocfError::malformed(
+                Self::FILE_NAME,
+                0,
+                format!("too short: {file_size} bytes, not enough for a single block."),
+            ));
+        }
+
+        let mut hues_file_rdr = {
+            let mut buf = vec![0; file_size];
+            file_handle
+                .read_exact(buf.as_mut())
+                .io_context(|| "Read hues.mul")?;
+            Cursor::new(buf)
+        };
+
+        let mut hues = Hues {
+            hue_data: Vec::with_capacity(block_qty * Self::ENTRIES_PER_BLOCK),
+        };
+
+        let mut i_hue: u32 = 0;
+        for _i_block in 0..block_qty {
+            let err_buf = format!("hue {} (0x{:x}): reading ", i_hue + 1, i_hue + 1);
+            let _header = hues_file_rdr
+                .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "header")?;
+
+            for _i_entry_in_block in 0..Self::ENTRIES_PER_BLOCK {
+                let err_buf = format!("hue {} (0x{:x}): reading ", i_hue + 1, i_hue + 1);
+                let mut hue = HueEntry {
+                    hue_id: (i_hue + 1) as u16,
+                    ..HueEntry::default()
+                };
+                hues_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u16_into::<LittleEndian>(&mut hue.colors), || err_buf.clone() + "colors")?;
+                hue.table_start = hues_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || err_buf.clone() + "table start")?;
+                hue.table_end = hues_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || err_buf.clone() + "table end")?;
+                hues_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_exact(&mut hue.name), || err_buf.clone() + "name")?;
+
+                hues.hue_data.push(hue);
+                i_hue += 1;
+            }
+        }
+        println!("Loaded {i_hue} (0x{:x}) Hues.", i_hue);
+
+        Ok(hues)
+    }
+}
+
+/* End of Hues struct */
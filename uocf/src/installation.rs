@@ -0,0 +1,297 @@
+// UO installation: the client files found in a folder, scanned once, so that the application doesn't have to build
+//  and probe the file paths by itself.
+// - File names are matched case-insensitively: their case differs between client versions (Cliloc.enu, cliloc.enu)
+//   and on case-sensitive file systems a copied client folder can have any.
+// - A map plane is found by its map file: map<N>.mul, or map<N>LegacyMUL.uop in the newer clients. If both are
+//   there the .mul one is used, like MapPlane::init does.
+// - Statics, tiledata, hues, radarcol, texmaps and art are read from the .mul files only (the .uop versions of art and
+//   the other files aren't supported yet).
+// - The optional files (verdata, the map and statics patches, clilocs, uomap.def) are None/empty if missing; the
+//   accessors of the required ones return a "not found" error (UocfError::is_not_found).
+// - Files added to the vfs under the folder path are found too, even if the folder isn't on disk.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::errors::{IoResultExt, Result, UocfError};
+use crate::geo::map::MapPlane;
+use crate::geo::map_def::UOMAP_DEF_FILE_NAME;
+use crate::geo::statics::StaticsPlane;
+use crate::vfs;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FileFormat {
+    Mul,
+    Uop,
+}
+
+#[derive(Clone, Debug)]
+pub struct MapPlaneFiles {
+    pub map: PathBuf,
+    pub format: FileFormat,
+    /// mapdifl<N>.mul and mapdif<N>.mul, shipped only by old clients.
+    pub diff: Option<(PathBuf, PathBuf)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct StaticsPlaneFiles {
+    pub statics: PathBuf,
+    pub staidx: PathBuf,
+    /// stadifl<N>.mul, stadifi<N>.mul and stadif<N>.mul, shipped only by old clients.
+    pub diff: Option<(PathBuf, PathBuf, PathBuf)>,
+}
+
+/// A data file with its index file (e.g. art.mul and artidx.mul).
+#[derive(Clone, Debug)]
+pub struct IndexedFiles {
+    pub data: PathBuf,
+    pub index: PathBuf,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct UoInstallation {
+    pub folder: PathBuf,
+    /// By map plane index.
+    pub map_planes: BTreeMap<u32, MapPlaneFiles>,
+    /// By map plane index.
+    pub statics_planes: BTreeMap<u32, StaticsPlaneFiles>,
+    pub tiledata: Option<PathBuf>,
+    pub hues: Option<PathBuf>,
+    pub radarcol: Option<PathBuf>,
+    pub texmaps: Option<IndexedFiles>,
+    pub art: Option<IndexedFiles>,
+    pub verdata: Option<PathBuf>,
+    /// By language (the lowercase file extension, e.g. "enu").
+    pub clilocs: BTreeMap<String, PathBuf>,
+    pub map_defs: Option<PathBuf>,
+}
+
+impl UoInstallation {
+    pub fn scan(folder: &Path) -> Result<UoInstallation> {
+        let mut files = HashMap::<String, PathBuf>::new();
+        // Files added to the vfs (in a browser, the only ones): the folder may not exist on disk then.
+        let vfs_files = vfs::files_in(folder);
+        for path in &vfs_files {
+            if let Some(file_name) = path.file_name() {
+                files.insert(file_name.to_string_lossy().to_lowercase(), path.clone());
+            }
+        }
+        let dir = match fs::read_dir(folder) {
+            Err(_) if !vfs_files.is_empty() => None,
+            dir => Some(dir.io_context(|| format!("Read the UO folder '{}'", folder.display()))?),
+        };
+        for dir_entry in dir.into_iter().flatten() {
+            let dir_entry =
+                dir_entry.io_context(|| format!("Read the UO folder '{}'", folder.display()))?;
+            if dir_entry
+                .file_type()
+                .is_ok_and(|file_type| file_type.is_dir())
+            {
+                continue;
+            }
+            // The vfs files win over the ones on disk.
+            let file_name = dir_entry.file_name().to_string_lossy().to_lowercase();
+            files.entry(file_name).or_insert_with(|| dir_entry.path());
+        }
+        let find = |file_name: &str| files.get(&file_name.to_lowercase()).cloned();
+        let find_pair = |data_file_name: &str, index_file_name: &str| {
+            Some(IndexedFiles {
+                data: find(data_file_name)?,
+                index: find(index_file_name)?,
+            })
+        };
+
+        let mut map_planes = BTreeMap::new();
+        let mut statics_planes = BTreeMap::new();
+        for map_index in Self::map_indices_in(files.keys()) {
+            let map = find(&format!("map{map_index}.mul"))
+                .map(|path| (path, FileFormat::Mul))
+                .or_else(|| {
+                    find(&MapPlane::uop_file_name(map_index)).map(|path| (path, FileFormat::Uop))
+                });
+            if let Some((map, format)) = map {
+                let (mapdifl_file_name, mapdif_file_name) = MapPlane::diff_file_names(map_index);
+                let diff = find(&mapdifl_file_name).zip(find(&mapdif_file_name));
+                map_planes.insert(map_index, MapPlaneFiles { map, format, diff });
+            }
+
+            let (stadifl_file_name, stadifi_file_name, stadif_file_name) =
+                StaticsPlane::diff_file_names(map_index);
+            let diff = match (
+                find(&stadifl_file_name),
+                find(&stadifi_file_name),
+                find(&stadif_file_name),
+            ) {
+                (Some(stadifl), Some(stadifi), Some(stadif)) => Some((stadifl, stadifi, stadif)),
+                _ => None,
+            };
+            if let Some(statics) = find(&format!("statics{map_index}.mul"))
+                && let Some(staidx) = find(&format!("staidx{map_index}.mul"))
+            {
+                statics_planes.insert(
+                    map_index,
+                    StaticsPlaneFiles {
+                        statics,
+                        staidx,
+                        diff,
+                    },
+                );
+            }
+        }
+
+        let clilocs = files
+            .iter()
+            .filter_map(|(file_name, path)| {
+                let language = file_name.strip_prefix("cliloc.")?;
+                (!language.is_empty()).then(|| (language.to_owned(), path.clone()))
+            })
+            .collect();
+
+        Ok(UoInstallation {
+            folder: folder.to_owned(),
+            map_planes,
+            statics_planes,
+            tiledata: find("tiledata.mul"),
+            hues: find("hues.mul"),
+            radarcol: find("radarcol.mul"),
+            texmaps: find_pair("texmaps.mul", "texidx.mul"),
+            art: find_pair("art.mul", "artidx.mul"),
+            verdata: find("verdata.mul"),
+            clilocs,
+            map_defs: find(UOMAP_DEF_FILE_NAME),
+        })
+    }
+
+    /// Map plane indices in the (lowercase) file names of the map and statics files.
+    fn map_indices_in<'a>(file_names: impl Iterator<Item = &'a String>) -> Vec<u32> {
+        let mut map_indices: Vec<u32> = file_names
+            .filter_map(|file_name| {
+                let digits = file_name
+                    .strip_suffix(".mul")
+                    .and_then(|name| {
+                        name.strip_prefix("map")
+                            .or_else(|| name.strip_prefix("statics"))
+                    })
+                    .or_else(|| file_name.strip_suffix("legacymul.uop")?.strip_prefix("map"))?;
+                if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+                    return None;
+                }
+                digits.parse().ok()
+            })
+            .collect();
+        map_indices.sort_unstable();
+        map_indices.dedup();
+        map_indices
+    }
+
+    fn not_found(&self, what: &str) -> UocfError {
+        UocfError::io(
+            format!("Find {what} in '{}'", self.folder.display()),
+            io::Error::from(io::ErrorKind::NotFound),
+        )
+    }
+
+    fn required<'a, T>(&self, file: &'a Option<T>, what: &str) -> Result<&'a T> {
+        file.as_ref().ok_or_else(|| self.not_found(what))
+    }
+
+    pub fn map_plane(&self, map_index: u32) -> Result<&MapPlaneFiles> {
+        self.map_planes.get(&map_index).ok_or_else(|| {
+            self.not_found(&format!(
+                "map{map_index}.mul or {}",
+                MapPlane::uop_file_name(map_index)
+            ))
+        })
+    }
+
+    pub fn statics_plane(&self, map_index: u32) -> Result<&StaticsPlaneFiles> {
+        self.statics_planes.get(&map_index).ok_or_else(|| {
+            self.not_found(&format!("statics{map_index}.mul and staidx{map_index}.mul"))
+        })
+    }
+
+    pub fn tiledata(&self) -> Result<&PathBuf> {
+        self.required(&self.tiledata, "tiledata.mul")
+    }
+
+    pub fn hues(&self) -> Result<&PathBuf> {
+        self.required(&self.hues, "hues.mul")
+    }
+
+    pub fn radarcol(&self) -> Result<&PathBuf> {
+        self.required(&self.radarcol, "radarcol.mul")
+    }
+
+    pub fn texmaps(&self) -> Result<&IndexedFiles> {
+        self.required(&self.texmaps, "texmaps.mul and texidx.mul")
+    }
+
+    pub fn art(&self) -> Result<&IndexedFiles> {
+        self.required(&self.art, "art.mul and artidx.mul")
+    }
+
+    /// The required files which weren't found: empty if the installation can be loaded. Any map plane will do, the
+    ///  files of a specific one are checked when loading it (see map_plane and statics_plane).
+    pub fn missing_files(&self) -> Vec<&'static str> {
+        let required = [
+            (
+                !self.map_planes.is_empty(),
+                "map files (map<N>.mul or map<N>LegacyMUL.uop)",
+            ),
+            (
+                !self.statics_planes.is_empty(),
+                "statics files (statics<N>.mul and staidx<N>.mul)",
+            ),
+            (self.tiledata.is_some(), "tiledata.mul"),
+            (self.hues.is_some(), "hues.mul"),
+            (self.radarcol.is_some(), "radarcol.mul"),
+            (self.texmaps.is_some(), "texmaps.mul and texidx.mul"),
+            (self.art.is_some(), "art.mul and artidx.mul"),
+        ];
+        required
+            .into_iter()
+            .filter(|(found, _)| !found)
+            .map(|(_, what)| what)
+            .collect()
+    }
+
+    pub fn cliloc(&self, language: &str) -> Option<&PathBuf> {
+        self.clilocs.get(&language.to_lowercase())
+    }
+
+    /// Every file found, required or optional, sorted by path.
+    pub fn files(&self) -> Vec<&PathBuf> {
+        let mut files = Vec::new();
+        for map_plane in self.map_planes.values() {
+            files.push(&map_plane.map);
+            if let Some((mapdifl, mapdif)) = &map_plane.diff {
+                files.extend([mapdifl, mapdif]);
+            }
+        }
+        for statics_plane in self.statics_planes.values() {
+            files.extend([&statics_plane.statics, &statics_plane.staidx]);
+            if let Some((stadifl, stadifi, stadif)) = &statics_plane.diff {
+                files.extend([stadifl, stadifi, stadif]);
+            }
+        }
+        for indexed_files in [&self.texmaps, &self.art].into_iter().flatten() {
+            files.extend([&indexed_files.data, &indexed_files.index]);
+        }
+        files.extend(
+            [
+                &self.tiledata,
+                &self.hues,
+                &self.radarcol,
+                &self.verdata,
+                &self.map_defs,
+            ]
+            .into_iter()
+            .flatten(),
+        );
+        files.extend(self.clilocs.values());
+        files.sort();
+        files
+    }
+}
@@ -1,12 +1,22 @@
-//#[macro_use]
-extern crate color_eyre;
-
 //#[macro_use]
 extern crate derive_new;
 
-mod errors;
+pub mod art;
+pub mod checksum;
+pub mod cliloc;
+pub mod errors;
 pub mod generic_def;
 pub mod generic_index;
 pub mod geo;
+pub mod hues;
+pub mod installation;
+pub mod radarcol;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
 pub mod tiledata;
+pub mod uop;
 mod utils;
+pub mod verdata;
+pub mod vfs;
+
+pub use errors::UocfError;
@@ -0,0 +1,112 @@
+#![allow(dead_code)]
+
+// radarcol.mul holds a single color for each land tile and each item (static) id, used by the client to draw the
+//  radar (minimap). It's a flat array of bgra5551 (u16) colors: the first 0x4000 entries are for the land tiles,
+//  the following ones for the items (entry index = 0x4000 + item id).
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::io::{Cursor, prelude::*};
+use std::path::PathBuf;
+
+use crate::errors::{IoResultExt, Result, UocfError};
+use crate::vfs;
+use crate::utils::color::*;
+
+pub struct RadarColors {
+    colors: Vec<u16>,
+}
+
+impl RadarColors {
+    const FILE_NAME: &'static str = "radarcol.mul";
+    pub const LAND_COLORS_QTY: usize = 0x4000;
+    const COLOR_PACKED_SIZE: usize = 2;
+    // Returned for ids not in the file (black).
+    pub const COLOR_NONE: u16 = 0;
+
+    pub fn len(&self) -> usize {
+        self.colors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.colors.is_empty()
+    }
+
+    pub fn item_count(&self) -> usize {
+        self.colors.len().saturating_sub(Self::LAND_COLORS_QTY)
+    }
+
+    /// Radar color of a land tile, as bgra5551 (like it's stored in the file).
+    pub fn land_color(&self, tile_id: u16) -> u16 {
+        if tile_id as usize >= Self::LAND_COLORS_QTY {
+            return Self::COLOR_NONE;
+        }
+        self.colors
+            .get(tile_id as usize)
+            .copied()
+            .unwrap_or(Self::COLOR_NONE)
+    }
+
+    /// Radar color of an item (static), as bgra5551 (like it's stored in the file).
+    pub fn item_color(&self, item_id: u16) -> u16 {
+        self.colors
+            .get(Self::LAND_COLORS_QTY + item_id as usize)
+            .copied()
+            .unwrap_or(Self::COLOR_NONE)
+    }
+
+    /// Converts a radar color to rgba8888 (alpha is always set to 255), as little endian bytes (R, G, B, A).
+    pub fn color_rgba8888(color_16: u16) -> [u8; 4] {
+        let mut pixel_16 = Bgra5551::new_from_val(color_16);
+        pixel_16.set_a(1);
+        pixel_16.as_rgba8888().value().to_le_bytes()
+    }
+
+    pub fn land_color_rgba8888(&self, tile_id: u16) -> [u8; 4] {
+        Self::color_rgba8888(self.land_color(tile_id))
+    }
+
+    pub fn item_color_rgba8888(&self, item_id: u16) -> [u8; 4] {
+        Self::color_rgba8888(self.item_color(item_id))
+    }
+
+    pub fn load(file_path: PathBuf) -> Result<RadarColors> {
+        let file_path = vfs::canonicalize(&file_path)
+            .io_context(|| "Check radarcol.mul path")?;
+
+        let mut file_handle = vfs::open(&file_path).io_context(|| {
+            format!("Open radarcol.mul at '{}'", file_path.to_string_lossy())
+        })?;
+        let file_size = file_handle.len().io_context(|| "Get radarcol.mul size")? as usize;
+
+        let color_qty = file_size / Self::COLOR_PACKED_SIZE;
+        if color_qty < Self::LAND_COLORS_QTY {
+            return Err(UocfError::malformed(
+                Self::FILE_NAME,
+                0,
+                format!("too short: {file_size} bytes, not enough for the land tile colors."),
+            ));
+        }
+
+        let mut radarcol_file_rdr = {
+            let mut buf = vec![0; file_size];
+            file_handle
+                .read_exact(buf.as_mut())
+                .io_context(|| "Read radarcol.mul")?;
+            Cursor::new(buf)
+        };
+
+        let mut colors = vec![0_u16; color_qty];
+        radarcol_file_rdr
+            .read_u16_into::<LittleEndian>(&mut colors)
+            .read_context(Self::FILE_NAME, 0, || "colors")?;
+
+        let radar_colors = RadarColors { colors };
+        println!(
+            "Loaded {} (0x{:x}) Radar colors ({} items).",
+            radar_colors.len(),
+            radar_colors.len(),
+            radar_colors.item_count()
+        );
+        Ok(radar_colors)
+    }
+}
@@ -0,0 +1,399 @@
+// Remote UO folder: a small file server streams the files of a UO folder over TCP (serve, see
+//  examples/uo_file_server.rs), and RemoteFolder reads them on the other side. Added to the vfs (add_to_vfs), the remote
+//  files are found by UoInstallation::scan and read by every loader as if they were local: tiledata, hues, textures...
+//  are read whole while loading, map and statics blocks only when they're requested, with a range request each (and
+//  read-ahead, see RemoteFile).
+// Protocol, little endian, one request at a time on a connection:
+//   request:  u8 kind: REQ_LIST, or REQ_READ followed by u16 name length, name (UTF-8), u64 offset, u32 length.
+//   response: u8 status (STATUS_OK or STATUS_ERROR), u32 payload length, then the payload:
+//             REQ_LIST: u32 file count, then for each file u16 name length, name, u64 size;
+//             REQ_READ: the bytes read, fewer than asked only at the end of the file;
+//             error: the message (UTF-8).
+// Only the files directly in the served folder are listed and can be read.
+// Not in the browser build: there are no TCP sockets there.
+
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, ErrorKind, SeekFrom, prelude::*};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::vfs::{self, VfsRead, VfsSource};
+
+/// Port of the file server, if not given.
+pub const DEFAULT_PORT: u16 = 2594;
+
+const REQ_LIST: u8 = 1;
+const REQ_READ: u8 = 2;
+const STATUS_OK: u8 = 0;
+const STATUS_ERROR: u8 = 1;
+/// Largest read served at once: bigger ones are split by RemoteFile.
+const MAX_READ_LEN: u32 = 8 * 1024 * 1024;
+/// Largest response accepted by the client (the file list of a folder is well below it).
+const MAX_PAYLOAD_LEN: u32 = MAX_READ_LEN + 1024;
+
+fn invalid_data(what: impl Into<String>) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, what.into())
+}
+
+fn read_name(rdr: &mut impl Read) -> io::Result<String> {
+    let len = rdr.read_u16::<LittleEndian>()?;
+    let mut name = vec![0; len as usize];
+    rdr.read_exact(&mut name)?;
+    String::from_utf8(name).map_err(|_| invalid_data("file name isn't UTF-8"))
+}
+
+fn write_name(wtr: &mut impl Write, name: &str) -> io::Result<()> {
+    let len = u16::try_from(name.len())
+        .map_err(|_| invalid_data(format!("file name too long: '{name}'")))?;
+    wtr.write_u16::<LittleEndian>(len)?;
+    wtr.write_all(name.as_bytes())
+}
+
+/* Server */
+
+/// Serves the files of folder to the clients connecting to listener, each in its own thread. Returns only if
+///  listener fails.
+pub fn serve(listener: TcpListener, folder: PathBuf) -> io::Result<()> {
+    let folder = Arc::new(folder);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let folder = Arc::clone(&folder);
+        std::thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map_or_else(|_| "?".to_owned(), |addr| addr.to_string());
+            println!("Client {peer} connected.");
+            match serve_connection(stream, &folder) {
+                Ok(()) => println!("Client {peer} disconnected."),
+                Err(e) => println!("Client {peer} dropped: {e}"),
+            }
+        });
+    }
+    Ok(())
+}
+
+fn serve_connection(stream: TcpStream, folder: &Path) -> io::Result<()> {
+    stream.set_nodelay(true)?;
+    let mut rdr = BufReader::new(stream.try_clone()?);
+    let mut wtr = BufWriter::new(stream);
+    // Files already read by this client: they're read again and again, a block at a time.
+    let mut open_files = HashMap::<String, File>::new();
+    loop {
+        let kind = match rdr.read_u8() {
+            Ok(kind) => kind,
+            Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        let response = match kind {
+            REQ_LIST => list_folder(folder),
+            REQ_READ => {
+                let name = read_name(&mut rdr)?;
+                let offset = rdr.read_u64::<LittleEndian>()?;
+                let len = rdr.read_u32::<LittleEndian>()?;
+                read_file_range(folder, &mut open_files, &name, offset, len)
+            }
+            _ => return Err(invalid_data(format!("unknown request {kind}"))),
+        };
+        let (status, payload) = match response {
+            Ok(payload) => (STATUS_OK, payload),
+            Err(e) => (STATUS_ERROR, e.to_string().into_bytes()),
+        };
+        wtr.write_u8(status)?;
+        wtr.write_u32::<LittleEndian>(payload.len() as u32)?;
+        wtr.write_all(&payload)?;
+        wtr.flush()?;
+    }
+}
+
+fn list_folder(folder: &Path) -> io::Result<Vec<u8>> {
+    let mut files: Vec<(String, u64)> = Vec::new();
+    for dir_entry in fs::read_dir(folder)? {
+        let dir_entry = dir_entry?;
+        let metadata = dir_entry.metadata()?;
+        if metadata.is_file()
+            && let Some(name) = dir_entry.file_name().to_str()
+        {
+            files.push((name.to_owned(), metadata.len()));
+        }
+    }
+    let mut payload = Vec::new();
+    payload.write_u32::<LittleEndian>(files.len() as u32)?;
+    for (name, len) in files {
+        write_name(&mut payload, &name)?;
+        payload.write_u64::<LittleEndian>(len)?;
+    }
+    Ok(payload)
+}
+
+fn read_file_range(
+    folder: &Path,
+    open_files: &mut HashMap<String, File>,
+    name: &str,
+    offset: u64,
+    len: u32,
+) -> io::Result<Vec<u8>> {
+    if len > MAX_READ_LEN {
+        return Err(invalid_data(format!(
+            "read of {len} bytes, the most is {MAX_READ_LEN}"
+        )));
+    }
+    let file = match open_files.get_mut(name) {
+        Some(file) => file,
+        None => {
+            // Only a file name: no way out of the folder (no "..", root, or drive prefix like "C:secret").
+            let mut components = Path::new(name).components();
+            let is_file_name = matches!(components.next(), Some(Component::Normal(_)))
+                && components.next().is_none()
+                && !name.contains(['/', '\\']);
+            if !is_file_name {
+                return Err(io::Error::new(
+                    ErrorKind::NotFound,
+                    format!("no file '{name}'"),
+                ));
+            }
+            let file = File::open(folder.join(name))?;
+            open_files.entry(name.to_owned()).or_insert(file)
+        }
+    };
+    file.seek(SeekFrom::Start(offset))?;
+    let mut data = Vec::with_capacity(len as usize);
+    file.take(len as u64).read_to_end(&mut data)?;
+    Ok(data)
+}
+
+/* Client */
+
+#[derive(Clone, Debug)]
+pub struct RemoteFileInfo {
+    pub name: String,
+    pub len: u64,
+}
+
+struct Connection {
+    rdr: BufReader<TcpStream>,
+    wtr: BufWriter<TcpStream>,
+}
+impl Connection {
+    fn open(server: &str) -> io::Result<Self> {
+        let stream = if server.contains(':') {
+            TcpStream::connect(server)
+        } else {
+            TcpStream::connect((server, DEFAULT_PORT))
+        }?;
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            rdr: BufReader::new(stream.try_clone()?),
+            wtr: BufWriter::new(stream),
+        })
+    }
+
+    /// Sends a request, and reads the whole response: (status, payload).
+    fn exchange(
+        &mut self,
+        write_request: impl FnOnce(&mut BufWriter<TcpStream>) -> io::Result<()>,
+        server: &str,
+    ) -> io::Result<(u8, Vec<u8>)> {
+        write_request(&mut self.wtr)?;
+        self.wtr.flush()?;
+        let status = self.rdr.read_u8()?;
+        let len = self.rdr.read_u32::<LittleEndian>()?;
+        if len > MAX_PAYLOAD_LEN {
+            return Err(invalid_data(format!(
+                "response of {len} bytes from {server}"
+            )));
+        }
+        let mut payload = vec![0; len as usize];
+        self.rdr.read_exact(&mut payload)?;
+        Ok((status, payload))
+    }
+}
+
+/// A UO folder served by a file server. The requests of every reader go through a single connection.
+pub struct RemoteFolder {
+    server: String,
+    /// None once broken: the next request connects again.
+    connection: Mutex<Option<Connection>>,
+    files: Vec<RemoteFileInfo>,
+}
+impl RemoteFolder {
+    /// Connects to the server at "host:port" (or "host", on DEFAULT_PORT), and gets the list of its files.
+    pub fn connect(server: &str) -> io::Result<Arc<RemoteFolder>> {
+        let mut remote = RemoteFolder {
+            server: server.to_owned(),
+            connection: Mutex::new(Some(Connection::open(server)?)),
+            files: Vec::new(),
+        };
+        let payload = remote.request(|wtr| wtr.write_u8(REQ_LIST))?;
+        let mut rdr = payload.as_slice();
+        let count = rdr.read_u32::<LittleEndian>()?;
+        for _ in 0..count {
+            let name = read_name(&mut rdr)?;
+            let len = rdr.read_u64::<LittleEndian>()?;
+            remote.files.push(RemoteFileInfo { name, len });
+        }
+        Ok(Arc::new(remote))
+    }
+
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    pub fn files(&self) -> &[RemoteFileInfo] {
+        &self.files
+    }
+
+    /// Reads up to len bytes of a file, from offset: fewer only at the end of the file.
+    /// Fails with ErrorKind::InvalidData if the server sends more than asked.
+    pub fn read_at(&self, name: &str, offset: u64, len: u32) -> io::Result<Vec<u8>> {
+        let len = len.min(MAX_READ_LEN);
+        let data = self.request(|wtr| {
+            wtr.write_u8(REQ_READ)?;
+            write_name(wtr, name)?;
+            wtr.write_u64::<LittleEndian>(offset)?;
+            wtr.write_u32::<LittleEndian>(len)
+        })?;
+        if data.len() > len as usize {
+            return Err(invalid_data(format!(
+                "{} sent {} bytes of '{name}', {len} were asked",
+                self.server,
+                data.len()
+            )));
+        }
+        Ok(data)
+    }
+
+    /// Sends a request written by write_request, and returns the payload of the response.
+    fn request(
+        &self,
+        write_request: impl FnOnce(&mut BufWriter<TcpStream>) -> io::Result<()>,
+    ) -> io::Result<Vec<u8>> {
+        let mut connection_slot = self
+            .connection
+            .lock()
+            .map_err(|_| io::Error::other("connection poisoned by a panic"))?;
+        let connection = match connection_slot.as_mut() {
+            Some(connection) => connection,
+            None => connection_slot.insert(Connection::open(&self.server)?),
+        };
+        // After a failed exchange, the rest of the response could still be on the way: the next request would read
+        //  it as its own. The connection isn't used anymore.
+        let (status, payload) = match connection.exchange(write_request, &self.server) {
+            Ok(response) => response,
+            Err(e) => {
+                *connection_slot = None;
+                return Err(e);
+            }
+        };
+        match status {
+            STATUS_OK => Ok(payload),
+            _ => Err(io::Error::other(format!(
+                "{}: {}",
+                self.server,
+                String::from_utf8_lossy(&payload)
+            ))),
+        }
+    }
+
+    /// Adds every file of the remote folder to the vfs, in folder (replacing the ones added there before). Returns
+    ///  how many were added.
+    pub fn add_to_vfs(self: &Arc<Self>, folder: &Path) -> usize {
+        vfs::remove_folder(folder);
+        for file in &self.files {
+            let source = RemoteFileSource {
+                remote: Arc::clone(self),
+                file: file.clone(),
+            };
+            vfs::add_source(folder.join(&file.name), Arc::new(source));
+        }
+        self.files.len()
+    }
+}
+
+struct RemoteFileSource {
+    remote: Arc<RemoteFolder>,
+    file: RemoteFileInfo,
+}
+impl VfsSource for RemoteFileSource {
+    fn len(&self) -> u64 {
+        self.file.len
+    }
+
+    fn open(&self) -> io::Result<Box<dyn VfsRead>> {
+        Ok(Box::new(RemoteFile::new(
+            Arc::clone(&self.remote),
+            self.file.clone(),
+        )))
+    }
+}
+
+/// Reader of a remote file. Small reads are served from a read-ahead buffer, so that reading the headers and the
+///  blocks of a map column one at a time doesn't cost a request each.
+pub struct RemoteFile {
+    remote: Arc<RemoteFolder>,
+    file: RemoteFileInfo,
+    pos: u64,
+    buf: Vec<u8>,
+    buf_start: u64,
+}
+impl RemoteFile {
+    const READ_AHEAD: usize = 64 * 1024;
+
+    pub fn new(remote: Arc<RemoteFolder>, file: RemoteFileInfo) -> Self {
+        Self {
+            remote,
+            file,
+            pos: 0,
+            buf: Vec::new(),
+            buf_start: 0,
+        }
+    }
+}
+impl Read for RemoteFile {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if out.is_empty() || self.pos >= self.file.len {
+            return Ok(0);
+        }
+        let in_buf =
+            self.pos >= self.buf_start && self.pos < self.buf_start + self.buf.len() as u64;
+        if !in_buf {
+            if out.len() >= Self::READ_AHEAD {
+                // Big reads go straight to the caller.
+                let data = self.remote.read_at(
+                    &self.file.name,
+                    self.pos,
+                    out.len().min(u32::MAX as usize) as u32,
+                )?;
+                out[..data.len()].copy_from_slice(&data);
+                self.pos += data.len() as u64;
+                return Ok(data.len());
+            }
+            self.buf = self
+                .remote
+                .read_at(&self.file.name, self.pos, Self::READ_AHEAD as u32)?;
+            self.buf_start = self.pos;
+            if self.buf.is_empty() {
+                return Ok(0);
+            }
+        }
+        let buf_pos = (self.pos - self.buf_start) as usize;
+        let len = out.len().min(self.buf.len() - buf_pos);
+        out[..len].copy_from_slice(&self.buf[buf_pos..buf_pos + len]);
+        self.pos += len as u64;
+        Ok(len)
+    }
+}
+impl Seek for RemoteFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(delta) => self.file.len.checked_add_signed(delta),
+            SeekFrom::Current(delta) => self.pos.checked_add_signed(delta),
+        };
+        self.pos = new_pos
+            .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "seek before the start"))?;
+        Ok(self.pos)
+    }
+}
@@ -1,11 +1,18 @@
 #![allow(dead_code)]
 
-crate::eyre_imports!();
 use byteorder::{LittleEndian, ReadBytesExt};
 use derive_new::new;
-use std::fs::File;
 use std::io::{prelude::*, Cursor};
-use std::path::PathBuf;
+use std::ops::{Bound, Range, RangeBounds};
+use std::path::{Path, PathBuf};
+
+use crate::errors::{CursorReadExt, IoResultExt, Result, UocfError};
+use crate::verdata::Verdata;
+use crate::vfs;
+
+// Conversion to and from human-editable documents (JSON, TOML).
+#[cfg(feature = "serde")]
+pub mod text;
 
 /* Struct to manage Flags for LandTile and ItemTile */
 
@@ -16,10 +23,64 @@ pub struct Flags {
 
 #[allow(unused)]
 impl Flags {
-    fn value(&self) -> u32 {
+    /// Name and bit of every flag, in bit order. The names match the accessors; unknown bits are named too, so
+    /// that every bit can be round-tripped.
+    pub const NAMED_BITS: [(&'static str, u32); 32] = [
+        ("background", 0x01),
+        ("weapon", 0x02),
+        ("transparent", 0x04),
+        ("translucent", 0x08),
+        ("wall", 0x10),
+        ("damaging", 0x20),
+        ("impassable", 0x40),
+        ("wet", 0x80),
+        ("unknown", 0x100),
+        ("surface", 0x200),
+        ("bridge", 0x400),
+        ("generic", 0x800),
+        ("window", 0x1000),
+        ("noshoot", 0x2000),
+        ("prefixa", 0x4000),
+        ("prefixan", 0x8000),
+        ("internal", 0x10000),
+        ("foliage", 0x20000),
+        ("partialhue", 0x40000),
+        ("unknown1", 0x80000),
+        ("map", 0x100000),
+        ("container", 0x200000),
+        ("wearable", 0x400000),
+        ("lightsource", 0x800000),
+        ("animated", 0x1000000),
+        ("nodiagonal", 0x2000000),
+        ("unknown2", 0x4000000),
+        ("armor", 0x8000000),
+        ("roof", 0x10000000),
+        ("door", 0x20000000),
+        ("stairback", 0x40000000),
+        ("stairright", 0x80000000),
+    ];
+
+    pub fn from_value(value: u32) -> Self {
+        Self { internal_flags: value }
+    }
+
+    pub fn value(&self) -> u32 {
         self.internal_flags
     }
 
+    /// Names of the flags which are set, in bit order.
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        Self::NAMED_BITS
+            .iter()
+            .filter(|(_, bit)| self.internal_flags & bit != 0)
+            .map(|&(name, _)| name)
+    }
+
+    /// Bit of a flag, by name (see NAMED_BITS).
+    pub fn bit_of(name: &str) -> Option<u32> {
+        Self::NAMED_BITS.iter().find(|(flag_name, _)| *flag_name == name).map(|&(_, bit)| bit)
+    }
+
     pub fn background(&self) -> bool {
         0 != (self.internal_flags & 0x01)
     }
@@ -133,7 +194,9 @@ pub struct LandTile {
     #[new(default)]
     pub flags: Flags,
 
-    //pub unk1: i32,    // added with HS
+    #[new(default)]
+    pub unk_hs: u32, // Added with HS: only in the HS layout.
+
     #[new(default)]
     pub texture_id: u16,
 
@@ -146,6 +209,7 @@ fn default() -> Self {
         Self {
             tile_id: Self::TILE_ID_UNUSED,
             flags: Flags::default(),
+            unk_hs: 0,
             texture_id: 0,
             name: [0; Self::NAME_LEN],
         }
@@ -166,6 +230,12 @@ pub fn name_ascii(&self) -> &str {
         std::str::from_utf8(&self.name[..null_pos]).unwrap_or("")
     }
 
+    /// Sets the name: ASCII, up to 20 chars.
+    pub fn set_name(&mut self, name: &str) -> Result<()> {
+        self.name = name_to_bytes(name)?;
+        Ok(())
+    }
+
     fn is_nodraw(&self) -> Option<bool> {
         match self.tile_id {
             Self::TILE_ID_UNUSED => None,
@@ -188,24 +258,32 @@ pub struct ItemTile {
     #[new(default)]
     pub flags: Flags,
 
-    //pub unknown: u32, // Added with HS
+    #[new(default)]
+    pub unk_hs: u32, // Added with HS: only in the HS layout.
+
     #[new(default)]
     pub weight: u8, // Stratics: 255 means not movable
 
     #[new(default)]
     pub quality: u8, // Stratics: If Wearable, this is a Layer. If Light Source, this is Light ID
 
-    //pub unknown0: u16,
-    //pub unknown1: u8,
+    #[new(default)]
+    pub unk0: u16,
+
+    #[new(default)]
+    pub unk1: u8,
+
     #[new(default)]
     pub quantity: u8, // Stratics: If Weapon, this is Weapon Struct. If Armor, Armor Struct
 
     #[new(default)]
     pub anim_id: u16, // Stratics: The Body ID the animatation. Add 50,000 and 60,000 respectivefully to get the two gump indicies assocaited with this tile
 
-    //pub unknown2: u8,
     #[new(default)]
-    pub hue_extra: u8, // For colored light sources? or forms a u16 with unknown2 ?
+    pub unk2: u8,
+
+    #[new(default)]
+    pub hue_extra: u8, // For colored light sources? or forms a u16 with unk2 ?
 
     #[new(default)]
     pub stacking_offset: u8,
@@ -225,10 +303,14 @@ fn default() -> Self {
         Self {
             tile_id: Self::TILE_ID_UNUSED,
             flags: Flags::default(),
+            unk_hs: 0,
             weight: 0,
             quality: 0,
+            unk0: 0,
+            unk1: 0,
             quantity: 0,
             anim_id: 0,
+            unk2: 0,
             hue_extra: 0,
             stacking_offset: 0,
             value: 0,
@@ -244,16 +326,20 @@ impl ItemTile {
 
     const NAME_LEN: usize = 20;
 
-    fn height(&self) -> i8 {
+    pub fn height(&self) -> i8 {
         if self.flags.bridge() {
             self.height / 2
         } else {
             self.height
         }
     }
-    fn height_raw(&self) -> i8 {
+    /// Height as stored in the file (height() halves it for bridges).
+    pub fn height_raw(&self) -> i8 {
         self.height
     }
+    pub fn set_height_raw(&mut self, height: i8) {
+        self.height = height;
+    }
 
     fn gump_id_male(&self) -> u32 {
         self.anim_id as u32 + 50_000
@@ -269,7 +355,13 @@ pub fn name_ascii(&self) -> &str {
         std::str::from_utf8(&self.name[..null_pos]).unwrap_or("")
     }
 
-    fn is_nodraw(&self) -> Option<bool> {
+    /// Sets the name: ASCII, up to 20 chars.
+    pub fn set_name(&mut self, name: &str) -> Result<()> {
+        self.name = name_to_bytes(name)?;
+        Ok(())
+    }
+
+    pub fn is_nodraw(&self) -> Option<bool> {
         let tid = self.tile_id;
         match tid {
             Self::TILE_ID_UNUSED => None,
@@ -294,6 +386,18 @@ fn is_nodraw(&self) -> Option<bool> {
 }
 /* End of ItemTile struct */
 
+/// Tile name as stored in the file: ASCII, null-padded to 20 bytes.
+fn name_to_bytes(name: &str) -> Result<[u8; 20]> {
+    if !name.is_ascii() || name.len() > 20 {
+        return Err(UocfError::out_of_range(format!(
+            "tile name '{name}': it must be ASCII, up to 20 chars."
+        )));
+    }
+    let mut bytes = [0; 20];
+    bytes[..name.len()].copy_from_slice(name.as_bytes());
+    Ok(bytes)
+}
+
 /* Enums for Tiledata file structure */
 
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
@@ -326,25 +430,161 @@ pub struct TileData {
     land_tile_binary_size: LandTileBinSize,
     item_tile_binary_size: ItemTileBinSize,
     max_item_rev: ItemTileMaxIdxRev,
+    // The u32 header of each block of 32 tiles (unknown meaning), kept to write it back.
+    land_block_headers: Vec<u32>,
+    item_block_headers: Vec<u32>,
     land_data: Vec<LandTile>,
     item_data: Vec<ItemTile>,
 }
 impl TileData {
+    const FILE_NAME: &'static str = "tiledata.mul";
     const LAND_TILE_MAX: usize = 0x4000;
     //const ITEM_TILE_MAX: usize = ItemTileMaxIdxRev::Revision3 as usize;
 
     /* Methods */
 
-    pub fn load(file_path: PathBuf) -> eyre::Result<TileData> {
-        let file_path = file_path
-            .canonicalize()
-            .wrap_err("Check tiledata.mul path")?;
+    pub fn land_tile(&self, tile_id: u16) -> Option<&LandTile> {
+        self.land_data.get(tile_id as usize)
+    }
+    pub fn item_tile(&self, tile_id: u16) -> Option<&ItemTile> {
+        self.item_data.get(tile_id as usize)
+    }
+    pub fn land_tile_mut(&mut self, tile_id: u16) -> Option<&mut LandTile> {
+        self.land_data.get_mut(tile_id as usize)
+    }
+    pub fn item_tile_mut(&mut self, tile_id: u16) -> Option<&mut ItemTile> {
+        self.item_data.get_mut(tile_id as usize)
+    }
 
-        let mut file_handle = File::open(&file_path)
-            .wrap_err_with(|| format!("Open tiledata.mul at '{}'", file_path.to_string_lossy()))?;
-        let file_metadata = file_handle
-            .metadata()
-            .wrap_err("Get tiledata.mul metadata")?;
+    /// File layout revision: 1 (classic), 2 (High Seas, 0x8000 items) or 3 (High Seas, 0x10000 items).
+    pub fn revision(&self) -> u8 {
+        match self.max_item_rev {
+            ItemTileMaxIdxRev::Revision1 => 1,
+            ItemTileMaxIdxRev::Revision2 => 2,
+            ItemTileMaxIdxRev::Revision3 => 3,
+        }
+    }
+
+    /// A tiledata with every tile zeroed, in the layout of the given revision (see revision()).
+    pub fn new_empty(revision: u8) -> Result<TileData> {
+        let (land_tile_binary_size, item_tile_binary_size, max_item_rev) = match revision {
+            1 => (LandTileBinSize::Classic, ItemTileBinSize::Classic, ItemTileMaxIdxRev::Revision1),
+            2 => (LandTileBinSize::HS, ItemTileBinSize::HS, ItemTileMaxIdxRev::Revision2),
+            3 => (LandTileBinSize::HS, ItemTileBinSize::HS, ItemTileMaxIdxRev::Revision3),
+            _ => {
+                return Err(UocfError::unsupported_revision(
+                    Self::FILE_NAME,
+                    format!("revision {revision}: use 1, 2 or 3."),
+                ));
+            }
+        };
+        let item_count = 1 + max_item_rev as usize;
+        Ok(TileData {
+            land_tile_binary_size,
+            item_tile_binary_size,
+            max_item_rev,
+            land_block_headers: vec![0; LandTile::BLOCK_QTY],
+            item_block_headers: vec![0; item_count / ItemTile::TILES_PER_BLOCK],
+            land_data: (0..Self::LAND_TILE_MAX).map(|id| LandTile::new(id as i32)).collect(),
+            item_data: (0..item_count).map(|id| ItemTile::new(id as i32)).collect(),
+        })
+    }
+
+    /// Number of land tile slots (0x4000).
+    pub fn land_tile_count(&self) -> usize {
+        self.land_data.len()
+    }
+    /// Number of item tile slots, depending on the file revision (0x4000, 0x8000 or 0x10000).
+    pub fn item_tile_count(&self) -> usize {
+        self.item_data.len()
+    }
+
+    /// All the land tiles, with their id.
+    pub fn land_tiles(&self) -> impl Iterator<Item = (u16, &LandTile)> {
+        self.land_tiles_in(..)
+    }
+    /// All the item tiles, with their id.
+    pub fn item_tiles(&self) -> impl Iterator<Item = (u16, &ItemTile)> {
+        self.item_tiles_in(..)
+    }
+
+    /// The land tiles with id in the given range (clamped to the existing ones), with their id.
+    pub fn land_tiles_in(&self, ids: impl RangeBounds<u16>) -> impl Iterator<Item = (u16, &LandTile)> {
+        let ids = Self::id_range(ids, self.land_data.len());
+        let first_id = ids.start;
+        self.land_data[ids]
+            .iter()
+            .enumerate()
+            .map(move |(i, tile)| ((first_id + i) as u16, tile))
+    }
+    /// The item tiles with id in the given range (clamped to the existing ones), with their id.
+    pub fn item_tiles_in(&self, ids: impl RangeBounds<u16>) -> impl Iterator<Item = (u16, &ItemTile)> {
+        let ids = Self::id_range(ids, self.item_data.len());
+        let first_id = ids.start;
+        self.item_data[ids]
+            .iter()
+            .enumerate()
+            .map(move |(i, tile)| ((first_id + i) as u16, tile))
+    }
+
+    /// Converts a range of tile ids to a range of indices of a tile vector of the given length.
+    fn id_range(ids: impl RangeBounds<u16>, len: usize) -> Range<usize> {
+        let start = match ids.start_bound() {
+            Bound::Included(&id) => id as usize,
+            Bound::Excluded(&id) => id as usize + 1,
+            Bound::Unbounded => 0,
+        };
+        let end = match ids.end_bound() {
+            Bound::Included(&id) => id as usize + 1,
+            Bound::Excluded(&id) => id as usize,
+            Bound::Unbounded => len,
+        };
+        let end = end.min(len);
+        start.min(end)..end
+    }
+
+    /// Patches the raw tiledata.mul content with the tiledata blocks found in verdata.mul.
+    /// Verdata block ids count the land blocks first, then the item blocks. Returns the number of applied patches.
+    fn apply_verdata_patches(&self, file_buf: &mut [u8], verdata: &Verdata) -> usize {
+        let land_block_size = 4 /* u32 header */ + (self.land_tile_binary_size as usize * LandTile::TILES_PER_BLOCK);
+        let item_block_size = 4 /* u32 header */ + (self.item_tile_binary_size as usize * ItemTile::TILES_PER_BLOCK);
+        let land_section_size = land_block_size * LandTile::BLOCK_QTY;
+
+        let mut applied: usize = 0;
+        for patch in verdata.patches_for_file(Verdata::FILE_ID_TILEDATA) {
+            let block_id = patch.block_id as usize;
+            let (block_offset, block_size) = if block_id < LandTile::BLOCK_QTY {
+                (block_id * land_block_size, land_block_size)
+            } else {
+                (land_section_size + (block_id - LandTile::BLOCK_QTY) * item_block_size, item_block_size)
+            };
+            let Some(patch_data) = verdata.data_of(patch) else {
+                continue;
+            };
+            // Patches made for a different tiledata format (block size) can't be applied.
+            if patch_data.len() != block_size || block_offset + block_size > file_buf.len() {
+                continue;
+            }
+            file_buf[block_offset..block_offset + block_size].copy_from_slice(patch_data);
+            applied += 1;
+        }
+        applied
+    }
+
+    pub fn load(file_path: PathBuf, verdata: Option<&Verdata>) -> Result<TileData> {
+        let file_path = vfs::canonicalize(&file_path)
+            .io_context(|| "Check tiledata.mul path")?;
+
+        let file_handle = vfs::open(&file_path)
+            .io_context(|| format!("Open tiledata.mul at '{}'", file_path.to_string_lossy()))?;
+        Self::from_reader(file_handle, verdata)
+    }
+
+    /// Like load, reading the whole tiledata.mul from rdr: a file, a byte slice (std::io::Cursor), an archive
+    ///  entry...
+    pub fn from_reader(mut rdr: impl Read, verdata: Option<&Verdata>) -> Result<TileData> {
+        let mut buf = Vec::new();
+        rdr.read_to_end(&mut buf).io_context(|| "Read tiledata.mul")?;
 
         const FILE_SIZE_REV1: u64 = {
             const LAND_SECTION_SIZE: u64 = {
@@ -391,10 +631,12 @@ pub fn load(file_path: PathBuf) -> eyre::Result<TileData> {
             LAND_SECTION_SIZE + ITEM_SECTION_SIZE
         };
 
-        let file_size = file_metadata.len();
+        let file_size = buf.len() as u64;
         if file_size < FILE_SIZE_REV1 {
-            return Err(eyre!(
-                "Tiledata.mul too short: it doesn't have room for land tile data.".to_owned()
+            return Err(UocfError::malformed(
+                Self::FILE_NAME,
+                0,
+                "too short: it doesn't have room for land tile data.",
             ));
         }
 
@@ -402,6 +644,8 @@ pub fn load(file_path: PathBuf) -> eyre::Result<TileData> {
             land_tile_binary_size: LandTileBinSize::Classic,
             item_tile_binary_size: ItemTileBinSize::Classic,
             max_item_rev: ItemTileMaxIdxRev::Revision1,
+            land_block_headers: Vec::with_capacity(LandTile::BLOCK_QTY),
+            item_block_headers: Vec::new(),
             land_data: vec![LandTile::default(); TileData::LAND_TILE_MAX],
             item_data: vec![],
         };
@@ -428,8 +672,9 @@ pub fn load(file_path: PathBuf) -> eyre::Result<TileData> {
                 ..tiledata
             };
         } else {
-            return Err(eyre!(
-                format!("Malformed tiledata.mul? Size: {file_size}").to_owned()
+            return Err(UocfError::unsupported_revision(
+                Self::FILE_NAME,
+                format!("unknown layout, size {file_size} doesn't match any known revision."),
             ));
         }
         tiledata.item_data = vec![ItemTile::default(); 1 + tiledata.max_item_rev as usize];
@@ -444,10 +689,10 @@ pub fn load(file_path: PathBuf) -> eyre::Result<TileData> {
         );
 
         let mut tiledata_file_rdr = {
-            let mut buf = vec![0; file_size as usize];
-            file_handle
-                .read_exact(buf.as_mut())
-                .wrap_err("Read tiledata.mul")?;
+            if let Some(verdata) = verdata {
+                let applied = tiledata.apply_verdata_patches(&mut buf, verdata);
+                println!("Applied {applied} Verdata patches to Tiledata.");
+            }
             Cursor::new(buf)
         };
 
@@ -456,36 +701,29 @@ pub fn load(file_path: PathBuf) -> eyre::Result<TileData> {
         // Read LandTiles
         let mut i_tile: u32 = 0;
         for _i_land_block in 0..LandTile::BLOCK_QTY {
-            err_buf = format!(
-                "Reading tiledata info for item tile {i_tile} (0x{:x}): reading ",
-                i_tile
-            );
+            err_buf = format!("land tile {i_tile} (0x{:x}): reading ", i_tile);
 
-            let _header = tiledata_file_rdr
-                .read_u32::<LittleEndian>()
-                .wrap_err(err_buf.clone() + "header")?;
+            let header = tiledata_file_rdr
+                .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "header")?;
+            tiledata.land_block_headers.push(header);
 
             for _i_tile_in_block in 0..LandTile::TILES_PER_BLOCK {
                 let land_tile = &mut tiledata.land_data[i_tile as usize];
                 land_tile.tile_id = i_tile as i32;
 
                 land_tile.flags.internal_flags = tiledata_file_rdr
-                    .read_u32::<LittleEndian>()
-                    .wrap_err(err_buf.clone() + "flags")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "flags")?;
 
                 if tiledata.land_tile_binary_size == LandTileBinSize::HS {
-                    let _unk = tiledata_file_rdr
-                        .read_i32::<LittleEndian>()
-                        .wrap_err(err_buf.clone() + "unk field")?;
+                    land_tile.unk_hs = tiledata_file_rdr
+                        .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "unk field")?;
                 }
 
                 land_tile.texture_id = tiledata_file_rdr
-                    .read_u16::<LittleEndian>()
-                    .wrap_err(err_buf.clone() + "texture id")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || err_buf.clone() + "texture id")?;
 
                 tiledata_file_rdr
-                    .read_exact(&mut land_tile.name)
-                    .wrap_err(err_buf.clone() + "name")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_exact(&mut land_tile.name), || err_buf.clone() + "name")?;
 
                 i_tile = i_tile.saturating_add(1);
             }
@@ -496,76 +734,59 @@ pub fn load(file_path: PathBuf) -> eyre::Result<TileData> {
         i_tile = 0_u32;
         let block_qty: usize = (1 + tiledata.max_item_rev as usize) / ItemTile::TILES_PER_BLOCK;
         for _i_item_block in 0..block_qty as u32 {
-            err_buf = format!(
-                "Reading tiledata info for item tile {i_tile} (0x{:x}): reading ",
-                i_tile
-            );
+            err_buf = format!("item tile {i_tile} (0x{:x}): reading ", i_tile);
 
-            let _header = tiledata_file_rdr
-                .read_u32::<LittleEndian>()
-                .wrap_err(err_buf.clone() + "header")?;
+            let header = tiledata_file_rdr
+                .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "header")?;
+            tiledata.item_block_headers.push(header);
 
             for _i_tile_in_block in 0..ItemTile::TILES_PER_BLOCK {
                 let item_tile = &mut tiledata.item_data[i_tile as usize];
                 item_tile.tile_id = i_tile as i32;
 
                 item_tile.flags.internal_flags = tiledata_file_rdr
-                    .read_u32::<LittleEndian>()
-                    .wrap_err(err_buf.clone() + "flags")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "flags")?;
 
                 if tiledata.item_tile_binary_size == ItemTileBinSize::HS {
-                    let _unk = tiledata_file_rdr
-                        .read_i32::<LittleEndian>()
-                        .wrap_err(err_buf.clone() + "unk field HS")?;
+                    item_tile.unk_hs = tiledata_file_rdr
+                        .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "unk field HS")?;
                 }
 
                 item_tile.weight = tiledata_file_rdr
-                    .read_u8()
-                    .wrap_err(err_buf.clone() + "weight")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u8(), || err_buf.clone() + "weight")?;
 
                 item_tile.quality = tiledata_file_rdr
-                    .read_u8()
-                    .wrap_err(err_buf.clone() + "quality")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u8(), || err_buf.clone() + "quality")?;
 
-                let _unk0 = tiledata_file_rdr
-                    .read_u16::<LittleEndian>()
-                    .wrap_err(err_buf.clone() + "unk field 0")?;
+                item_tile.unk0 = tiledata_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || err_buf.clone() + "unk field 0")?;
 
-                let _unk1 = tiledata_file_rdr
-                    .read_u8()
-                    .wrap_err(err_buf.clone() + "unk field 1")?;
+                item_tile.unk1 = tiledata_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u8(), || err_buf.clone() + "unk field 1")?;
 
                 item_tile.quantity = tiledata_file_rdr
-                    .read_u8()
-                    .wrap_err(err_buf.clone() + "weight")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u8(), || err_buf.clone() + "quantity")?;
 
                 item_tile.anim_id = tiledata_file_rdr
-                    .read_u16::<LittleEndian>()
-                    .wrap_err(err_buf.clone() + "anim id")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u16::<LittleEndian>(), || err_buf.clone() + "anim id")?;
 
-                let _unk2 = tiledata_file_rdr
-                    .read_u8()
-                    .wrap_err(err_buf.clone() + "unk field 2")?;
+                item_tile.unk2 = tiledata_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u8(), || err_buf.clone() + "unk field 2")?;
 
                 item_tile.hue_extra = tiledata_file_rdr
-                    .read_u8()
-                    .wrap_err(err_buf.clone() + "hue extra")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u8(), || err_buf.clone() + "hue extra")?;
 
                 item_tile.stacking_offset = tiledata_file_rdr
-                    .read_u8()
-                    .wrap_err(err_buf.clone() + "stacking offset")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u8(), || err_buf.clone() + "stacking offset")?;
 
                 item_tile.value = tiledata_file_rdr
-                    .read_u8()
-                    .wrap_err(err_buf.clone() + "value")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u8(), || err_buf.clone() + "value")?;
 
                 item_tile.height = tiledata_file_rdr
-                    .read_i8()
-                    .wrap_err(err_buf.clone() + "height")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_i8(), || err_buf.clone() + "height")?;
 
                 tiledata_file_rdr
-                    .read_exact(&mut item_tile.name)
-                    .wrap_err(err_buf.clone() + "name")?;
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_exact(&mut item_tile.name), || err_buf.clone() + "name")?;
 
                 i_tile = i_tile.saturating_add(1);
             }
@@ -579,6 +800,56 @@ pub fn load(file_path: PathBuf) -> eyre::Result<TileData> {
 
         Ok(tiledata)
     }
+
+    /// The content of tiledata.mul, in the layout of the loaded file (or of new_empty).
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let land_block_size = 4 + self.land_tile_binary_size as usize * LandTile::TILES_PER_BLOCK;
+        let item_block_size = 4 + self.item_tile_binary_size as usize * ItemTile::TILES_PER_BLOCK;
+        let mut buf = Vec::with_capacity(
+            land_block_size * self.land_block_headers.len() + item_block_size * self.item_block_headers.len(),
+        );
+
+        for (header, tiles) in self.land_block_headers.iter().zip(self.land_data.chunks(LandTile::TILES_PER_BLOCK)) {
+            buf.extend_from_slice(&header.to_le_bytes());
+            for land_tile in tiles {
+                buf.extend_from_slice(&land_tile.flags.value().to_le_bytes());
+                if self.land_tile_binary_size == LandTileBinSize::HS {
+                    buf.extend_from_slice(&land_tile.unk_hs.to_le_bytes());
+                }
+                buf.extend_from_slice(&land_tile.texture_id.to_le_bytes());
+                buf.extend_from_slice(&land_tile.name);
+            }
+        }
+
+        for (header, tiles) in self.item_block_headers.iter().zip(self.item_data.chunks(ItemTile::TILES_PER_BLOCK)) {
+            buf.extend_from_slice(&header.to_le_bytes());
+            for item_tile in tiles {
+                buf.extend_from_slice(&item_tile.flags.value().to_le_bytes());
+                if self.item_tile_binary_size == ItemTileBinSize::HS {
+                    buf.extend_from_slice(&item_tile.unk_hs.to_le_bytes());
+                }
+                buf.push(item_tile.weight);
+                buf.push(item_tile.quality);
+                buf.extend_from_slice(&item_tile.unk0.to_le_bytes());
+                buf.push(item_tile.unk1);
+                buf.push(item_tile.quantity);
+                buf.extend_from_slice(&item_tile.anim_id.to_le_bytes());
+                buf.push(item_tile.unk2);
+                buf.push(item_tile.hue_extra);
+                buf.push(item_tile.stacking_offset);
+                buf.push(item_tile.value);
+                buf.push(item_tile.height as u8);
+                buf.extend_from_slice(&item_tile.name);
+            }
+        }
+        buf
+    }
+
+    /// Writes a tiledata.mul, which load() reads back as this TileData.
+    pub fn save(&self, file_path: &Path) -> Result<()> {
+        std::fs::write(file_path, self.to_bytes())
+            .io_context(|| format!("Write tiledata at '{}'", file_path.to_string_lossy()))
+    }
 }
 
 /* End of Tiledata struct */
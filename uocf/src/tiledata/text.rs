@@ -0,0 +1,326 @@
+// Tiledata as a human-editable document (JSON or TOML), e.g. to edit names, flags and item properties in a text
+//  editor and write a new tiledata.mul from it instead of using a hex editor.
+// - Tiles with every field zeroed (unused) are left out; on import, the tiles not listed are zeroed.
+// - Flags are listed by name (see Flags::NAMED_BITS); only the set ones are exported. Unknown names are rejected.
+// - The fields of unknown meaning are exported only when they aren't zero, so that the file is written back as it was.
+// - Names are exported as text when they're ASCII padded with zeros. Other names (bytes after the terminator, not
+//   ASCII) are exported as their 20 raw bytes (name_bytes) instead, so they're written back as they were too.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use super::{Flags, ItemTile, LandTile, TileData, name_to_bytes};
+use crate::errors::{Result, UocfError};
+
+const DOC_NAME: &str = "tiledata document";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TileDataDoc {
+    /// File layout: 1 (classic), 2 or 3 (High Seas). See TileData::revision.
+    pub revision: u8,
+    /// Headers of the blocks of 32 tiles: left out if they're all zero.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub land_block_headers: Vec<u32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub item_block_headers: Vec<u32>,
+    #[serde(default)]
+    pub land: Vec<LandTileDoc>,
+    #[serde(default)]
+    pub items: Vec<ItemTileDoc>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct LandTileDoc {
+    pub id: u16,
+    #[serde(default)]
+    pub name: String,
+    /// The raw bytes of the name, when it isn't ASCII padded with zeros: then name is empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub name_bytes: Vec<u8>,
+    #[serde(default)]
+    pub texture_id: u16,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub unk_hs: u32,
+    #[serde(default)]
+    pub flags: BTreeMap<String, bool>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ItemTileDoc {
+    pub id: u16,
+    #[serde(default)]
+    pub name: String,
+    /// The raw bytes of the name, when it isn't ASCII padded with zeros: then name is empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub name_bytes: Vec<u8>,
+    #[serde(default)]
+    pub weight: u8,
+    #[serde(default)]
+    pub quality: u8,
+    #[serde(default)]
+    pub quantity: u8,
+    #[serde(default)]
+    pub anim_id: u16,
+    #[serde(default)]
+    pub hue_extra: u8,
+    #[serde(default)]
+    pub stacking_offset: u8,
+    #[serde(default)]
+    pub value: u8,
+    /// As stored in the file: not halved for bridges.
+    #[serde(default)]
+    pub height: i8,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub unk_hs: u32,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub unk0: u16,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub unk1: u8,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub unk2: u8,
+    #[serde(default)]
+    pub flags: BTreeMap<String, bool>,
+}
+
+fn is_zero<T: Default + PartialEq>(value: &T) -> bool {
+    *value == T::default()
+}
+
+/// The name as text, or as raw bytes when set_name wouldn't give them back.
+fn name_to_doc(name: &[u8; LandTile::NAME_LEN]) -> (String, Vec<u8>) {
+    let null_pos = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    match std::str::from_utf8(&name[..null_pos]) {
+        Ok(text) if name_to_bytes(text).is_ok_and(|bytes| bytes == *name) => (text.to_owned(), Vec::new()),
+        _ => (String::new(), name.to_vec()),
+    }
+}
+
+fn name_from_doc(name: &str, name_bytes: &[u8], tile: &str) -> Result<[u8; LandTile::NAME_LEN]> {
+    if name_bytes.is_empty() {
+        return name_to_bytes(name);
+    }
+    if !name.is_empty() {
+        return Err(UocfError::malformed(
+            DOC_NAME,
+            0,
+            format!("{tile}: both name and name_bytes are set."),
+        ));
+    }
+    name_bytes.try_into().map_err(|_| {
+        UocfError::malformed(
+            DOC_NAME,
+            0,
+            format!(
+                "{tile}: name_bytes has {} bytes, not {}.",
+                name_bytes.len(),
+                LandTile::NAME_LEN
+            ),
+        )
+    })
+}
+
+fn flags_to_doc(flags: &Flags) -> BTreeMap<String, bool> {
+    flags.names().map(|name| (name.to_owned(), true)).collect()
+}
+
+fn flags_from_doc(flags: &BTreeMap<String, bool>, tile: &str) -> Result<Flags> {
+    let mut value = 0;
+    for (name, &set) in flags {
+        let bit = Flags::bit_of(name)
+            .ok_or_else(|| UocfError::malformed(DOC_NAME, 0, format!("{tile}: unknown flag '{name}'.")))?;
+        if set {
+            value |= bit;
+        }
+    }
+    Ok(Flags::from_value(value))
+}
+
+fn block_headers_to_doc(headers: &[u32]) -> Vec<u32> {
+    if headers.iter().all(|&header| header == 0) {
+        Vec::new()
+    } else {
+        headers.to_vec()
+    }
+}
+
+fn block_headers_from_doc(doc_headers: &[u32], headers: &mut [u32], what: &str) -> Result<()> {
+    if doc_headers.is_empty() {
+        return Ok(());
+    }
+    if doc_headers.len() != headers.len() {
+        return Err(UocfError::malformed(
+            DOC_NAME,
+            0,
+            format!(
+                "{what}: {} values, this revision has {}.",
+                doc_headers.len(),
+                headers.len()
+            ),
+        ));
+    }
+    headers.copy_from_slice(doc_headers);
+    Ok(())
+}
+
+impl LandTile {
+    fn is_unused(&self) -> bool {
+        self.flags.value() == 0 && self.unk_hs == 0 && self.texture_id == 0 && self.name.iter().all(|&c| c == 0)
+    }
+}
+
+impl ItemTile {
+    fn is_unused(&self) -> bool {
+        self.flags.value() == 0
+            && self.unk_hs == 0
+            && self.weight == 0
+            && self.quality == 0
+            && self.unk0 == 0
+            && self.unk1 == 0
+            && self.quantity == 0
+            && self.anim_id == 0
+            && self.unk2 == 0
+            && self.hue_extra == 0
+            && self.stacking_offset == 0
+            && self.value == 0
+            && self.height == 0
+            && self.name.iter().all(|&c| c == 0)
+    }
+}
+
+impl TileData {
+    pub fn to_doc(&self) -> TileDataDoc {
+        let land = self
+            .land_tiles()
+            .filter(|(_, land_tile)| !land_tile.is_unused())
+            .map(|(id, land_tile)| {
+                let (name, name_bytes) = name_to_doc(&land_tile.name);
+                LandTileDoc {
+                    id,
+                    name,
+                    name_bytes,
+                    texture_id: land_tile.texture_id,
+                    unk_hs: land_tile.unk_hs,
+                    flags: flags_to_doc(&land_tile.flags),
+                }
+            })
+            .collect();
+        let items = self
+            .item_tiles()
+            .filter(|(_, item_tile)| !item_tile.is_unused())
+            .map(|(id, item_tile)| {
+                let (name, name_bytes) = name_to_doc(&item_tile.name);
+                ItemTileDoc {
+                    id,
+                    name,
+                    name_bytes,
+                    weight: item_tile.weight,
+                    quality: item_tile.quality,
+                    quantity: item_tile.quantity,
+                    anim_id: item_tile.anim_id,
+                    hue_extra: item_tile.hue_extra,
+                    stacking_offset: item_tile.stacking_offset,
+                    value: item_tile.value,
+                    height: item_tile.height,
+                    unk_hs: item_tile.unk_hs,
+                    unk0: item_tile.unk0,
+                    unk1: item_tile.unk1,
+                    unk2: item_tile.unk2,
+                    flags: flags_to_doc(&item_tile.flags),
+                }
+            })
+            .collect();
+        TileDataDoc {
+            revision: self.revision(),
+            land_block_headers: block_headers_to_doc(&self.land_block_headers),
+            item_block_headers: block_headers_to_doc(&self.item_block_headers),
+            land,
+            items,
+        }
+    }
+
+    /// Builds the tiledata described by the document: it can then be written with save().
+    pub fn from_doc(doc: &TileDataDoc) -> Result<TileData> {
+        let mut tiledata = TileData::new_empty(doc.revision)?;
+        block_headers_from_doc(
+            &doc.land_block_headers,
+            &mut tiledata.land_block_headers,
+            "land_block_headers",
+        )?;
+        block_headers_from_doc(
+            &doc.item_block_headers,
+            &mut tiledata.item_block_headers,
+            "item_block_headers",
+        )?;
+
+        let mut seen = vec![false; tiledata.land_tile_count()];
+        for land_doc in &doc.land {
+            let tile = format!("land tile 0x{:04X}", land_doc.id);
+            let land_tile = tiledata
+                .land_data
+                .get_mut(land_doc.id as usize)
+                .ok_or_else(|| UocfError::out_of_range(format!("{tile}: there are 0x{:X} land tiles.", seen.len())))?;
+            if std::mem::replace(&mut seen[land_doc.id as usize], true) {
+                return Err(UocfError::malformed(DOC_NAME, 0, format!("{tile} is listed twice.")));
+            }
+            land_tile.flags = flags_from_doc(&land_doc.flags, &tile)?;
+            land_tile.unk_hs = land_doc.unk_hs;
+            land_tile.texture_id = land_doc.texture_id;
+            land_tile.name = name_from_doc(&land_doc.name, &land_doc.name_bytes, &tile)?;
+        }
+
+        let mut seen = vec![false; tiledata.item_tile_count()];
+        for item_doc in &doc.items {
+            let tile = format!("item tile 0x{:04X}", item_doc.id);
+            let item_tile = tiledata.item_data.get_mut(item_doc.id as usize).ok_or_else(|| {
+                UocfError::out_of_range(format!(
+                    "{tile}: revision {} has 0x{:X} item tiles.",
+                    doc.revision,
+                    seen.len()
+                ))
+            })?;
+            if std::mem::replace(&mut seen[item_doc.id as usize], true) {
+                return Err(UocfError::malformed(DOC_NAME, 0, format!("{tile} is listed twice.")));
+            }
+            item_tile.flags = flags_from_doc(&item_doc.flags, &tile)?;
+            item_tile.unk_hs = item_doc.unk_hs;
+            item_tile.weight = item_doc.weight;
+            item_tile.quality = item_doc.quality;
+            item_tile.unk0 = item_doc.unk0;
+            item_tile.unk1 = item_doc.unk1;
+            item_tile.quantity = item_doc.quantity;
+            item_tile.anim_id = item_doc.anim_id;
+            item_tile.unk2 = item_doc.unk2;
+            item_tile.hue_extra = item_doc.hue_extra;
+            item_tile.stacking_offset = item_doc.stacking_offset;
+            item_tile.value = item_doc.value;
+            item_tile.height = item_doc.height;
+            item_tile.name = name_from_doc(&item_doc.name, &item_doc.name_bytes, &tile)?;
+        }
+        Ok(tiledata)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(&self.to_doc())
+            .map_err(|e| UocfError::malformed(DOC_NAME, 0, format!("can't write JSON: {e}")))
+    }
+
+    pub fn from_json(text: &str) -> Result<TileData> {
+        let doc: TileDataDoc =
+            serde_json::from_str(text).map_err(|e| UocfError::malformed(DOC_NAME, 0, format!("JSON: {e}")))?;
+        Self::from_doc(&doc)
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        toml::to_string(&self.to_doc()).map_err(|e| UocfError::malformed(DOC_NAME, 0, format!("can't write TOML: {e}")))
+    }
+
+    pub fn from_toml(text: &str) -> Result<TileData> {
+        let doc: TileDataDoc =
+            toml::from_str(text).map_err(|e| UocfError::malformed(DOC_NAME, 0, format!("TOML: {e}")))?;
+        Self::from_doc(&doc)
+    }
+}
@@ -0,0 +1,261 @@
+#![allow(dead_code)]
+
+// Reader for UOP (Mythic Package) archives, used by newer clients in place of some .mul files
+//  (e.g. map0LegacyMUL.uop instead of map0.mul).
+// A UOP file is a container of entries, identified by the hash of their (virtual) file name.
+// Entries are described by tables chained in a linked list of blocks through the file.
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::HashMap;
+use std::io::{BufReader, SeekFrom, prelude::*};
+use std::path::{Path, PathBuf};
+
+use crate::errors::{IoResultExt, Result, UocfError, file_name_of};
+use crate::vfs;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct UopEntry {
+    // Position of the entry header in the file. Entry data starts right after it.
+    pub offset: u64,
+    pub header_len: u32,
+    pub compressed_len: u32,
+    pub decompressed_len: u32,
+    pub hash: u64,
+    pub data_block_hash: u32,
+    pub compression: u16,
+}
+impl UopEntry {
+    pub const PACKED_SIZE: usize = 8 + 4 + 4 + 4 + 8 + 4 + 2;
+    pub const COMPRESSION_NONE: u16 = 0;
+
+    /// Position of the entry data in the file.
+    pub fn data_offset(&self) -> u64 {
+        self.offset + self.header_len as u64
+    }
+    pub fn is_compressed(&self) -> bool {
+        self.compression != Self::COMPRESSION_NONE
+    }
+}
+
+pub struct UopFile {
+    pub version: u32,
+    entries: HashMap<u64, UopEntry>,
+}
+impl UopFile {
+    // "MYP\0"
+    pub const MAGIC: u32 = 0x0050_594D;
+
+    /// Returns true if the file at the given path starts with the UOP magic number.
+    pub fn is_uop(file_path: &Path) -> bool {
+        let Ok(mut file_handle) = vfs::open(file_path) else {
+            return false;
+        };
+        matches!(file_handle.read_u32::<LittleEndian>(), Ok(Self::MAGIC))
+    }
+
+    /// Like is_uop, for the data of rdr. Its position is moved back to the start.
+    pub fn is_uop_reader(rdr: &mut (impl Read + Seek + ?Sized)) -> bool {
+        let is_uop = rdr.seek(SeekFrom::Start(0)).is_ok()
+            && matches!(rdr.read_u32::<LittleEndian>(), Ok(Self::MAGIC));
+        rdr.seek(SeekFrom::Start(0)).is_ok() && is_uop
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn entry(&self, hash: u64) -> Option<&UopEntry> {
+        self.entries.get(&hash)
+    }
+
+    pub fn entry_by_name(&self, file_name: &str) -> Option<&UopEntry> {
+        self.entries.get(&hash_file_name(file_name))
+    }
+
+    pub fn load(file_path: PathBuf) -> Result<UopFile> {
+        let file_name = file_name_of(&file_path)?;
+        let file_path = vfs::canonicalize(&file_path)
+            .io_context(|| format!("Check {file_name} path"))?;
+
+        let file_handle = vfs::open(&file_path)
+            .io_context(|| format!("Open uop file at '{file_name}'"))?;
+        Self::from_reader(BufReader::new(file_handle), &file_name)
+    }
+
+    /// Like load, reading the entry tables from rdr: the whole uop file (a file, a byte slice with std::io::Cursor...).
+    ///  file_name is used in the error messages.
+    pub fn from_reader(mut rdr: impl Read + Seek, file_name: &str) -> Result<UopFile> {
+        let file_size = rdr
+            .seek(SeekFrom::End(0))
+            .io_context(|| format!("Get {file_name} size"))?;
+        rdr.seek(SeekFrom::Start(0))
+            .io_context(|| format!("Reading {file_name}: seek to the start"))?;
+
+        /* Read the header */
+        let strerr_base = "header: ";
+        let magic = rdr
+            .read_u32::<LittleEndian>()
+            .read_context(file_name, 0, || format!("{strerr_base}magic"))?;
+        if magic != Self::MAGIC {
+            return Err(UocfError::malformed(
+                file_name,
+                0,
+                format!("not a valid UOP file (bad magic number 0x{magic:X})."),
+            ));
+        }
+        let version = rdr
+            .read_u32::<LittleEndian>()
+            .read_context(file_name, 0, || format!("{strerr_base}version"))?;
+        let _signature = rdr
+            .read_u32::<LittleEndian>()
+            .read_context(file_name, 0, || format!("{strerr_base}signature"))?;
+        let mut next_block = rdr
+            .read_u64::<LittleEndian>()
+            .read_context(file_name, 0, || format!("{strerr_base}first block offset"))?;
+        let _block_capacity = rdr
+            .read_u32::<LittleEndian>()
+            .read_context(file_name, 0, || format!("{strerr_base}block capacity"))?;
+        let file_count = rdr
+            .read_u32::<LittleEndian>()
+            .read_context(file_name, 0, || format!("{strerr_base}file count"))?;
+
+        let mut uop = UopFile {
+            version,
+            entries: HashMap::with_capacity(file_count as usize),
+        };
+
+        /* Walk the linked list of entry tables */
+        let strerr_base = "entry table: ";
+        while next_block != 0 {
+            if next_block >= file_size {
+                return Err(UocfError::malformed(
+                    file_name,
+                    next_block,
+                    "entry table out of the file bounds.",
+                ));
+            }
+            let table_offset = next_block;
+            rdr.seek(SeekFrom::Start(table_offset))
+                .io_context(|| format!("Reading {file_name}: seek to 0x{table_offset:X}"))?;
+
+            let block_file_count = rdr
+                .read_u32::<LittleEndian>()
+                .read_context(file_name, table_offset, || format!("{strerr_base}file count"))?;
+            next_block = rdr
+                .read_u64::<LittleEndian>()
+                .read_context(file_name, table_offset, || format!("{strerr_base}next block offset"))?;
+
+            for _ in 0..block_file_count {
+                let entry = UopEntry {
+                    offset: rdr
+                        .read_u64::<LittleEndian>()
+                        .read_context(file_name, table_offset, || format!("{strerr_base}offset"))?,
+                    header_len: rdr
+                        .read_u32::<LittleEndian>()
+                        .read_context(file_name, table_offset, || format!("{strerr_base}header length"))?,
+                    compressed_len: rdr
+                        .read_u32::<LittleEndian>()
+                        .read_context(file_name, table_offset, || format!("{strerr_base}compressed length"))?,
+                    decompressed_len: rdr
+                        .read_u32::<LittleEndian>()
+                        .read_context(file_name, table_offset, || format!("{strerr_base}decompressed length"))?,
+                    hash: rdr
+                        .read_u64::<LittleEndian>()
+                        .read_context(file_name, table_offset, || format!("{strerr_base}hash"))?,
+                    data_block_hash: rdr
+                        .read_u32::<LittleEndian>()
+                        .read_context(file_name, table_offset, || format!("{strerr_base}data block hash"))?,
+                    compression: rdr
+                        .read_u16::<LittleEndian>()
+                        .read_context(file_name, table_offset, || format!("{strerr_base}compression flag"))?,
+                };
+                // Tables are preallocated, unused slots have a null offset.
+                if entry.offset == 0 {
+                    continue;
+                }
+                uop.entries.insert(entry.hash, entry);
+            }
+        }
+
+        println!(
+            "Loaded {} (0x{:x}) UOP entries from '{file_name}'.",
+            uop.entries.len(),
+            uop.entries.len()
+        );
+        Ok(uop)
+    }
+}
+
+/// Hash of a virtual file name inside a UOP archive (Bob Jenkins' hashlittle2, as used by the client).
+pub fn hash_file_name(file_name: &str) -> u64 {
+    let s = file_name.as_bytes();
+    let len = s.len();
+    let le_u32 = |i: usize| -> u32 { u32::from_le_bytes([s[i], s[i + 1], s[i + 2], s[i + 3]]) };
+
+    let mut a: u32 = (len as u32).wrapping_add(0xDEAD_BEEF);
+    let mut b: u32 = a;
+    let mut c: u32 = a;
+
+    let mut i: usize = 0;
+    while i + 12 < len {
+        a = a.wrapping_add(le_u32(i));
+        b = b.wrapping_add(le_u32(i + 4));
+        c = c.wrapping_add(le_u32(i + 8));
+
+        // mix(a, b, c)
+        a = a.wrapping_sub(c);
+        a ^= c.rotate_left(4);
+        c = c.wrapping_add(b);
+        b = b.wrapping_sub(a);
+        b ^= a.rotate_left(6);
+        a = a.wrapping_add(c);
+        c = c.wrapping_sub(b);
+        c ^= b.rotate_left(8);
+        b = b.wrapping_add(a);
+        a = a.wrapping_sub(c);
+        a ^= c.rotate_left(16);
+        c = c.wrapping_add(b);
+        b = b.wrapping_sub(a);
+        b ^= a.rotate_left(19);
+        a = a.wrapping_add(c);
+        c = c.wrapping_sub(b);
+        c ^= b.rotate_left(4);
+        b = b.wrapping_add(a);
+
+        i += 12;
+    }
+
+    if i == len {
+        // Only reached with an empty name (the last 1..=12 bytes are always left for the tail):
+        //  the final mix is skipped, like the client does.
+        return ((b as u64) << 32) | c as u64;
+    }
+
+    // Tail: bytes 0..4 go in a, 4..8 in b, 8..12 in c (little endian).
+    for (j, &byte) in s[i..].iter().enumerate() {
+        let shifted = (byte as u32) << (8 * (j % 4));
+        match j / 4 {
+            0 => a = a.wrapping_add(shifted),
+            1 => b = b.wrapping_add(shifted),
+            _ => c = c.wrapping_add(shifted),
+        }
+    }
+
+    // final(a, b, c)
+    c ^= b;
+    c = c.wrapping_sub(b.rotate_left(14));
+    a ^= c;
+    a = a.wrapping_sub(c.rotate_left(11));
+    b ^= a;
+    b = b.wrapping_sub(a.rotate_left(25));
+    c ^= b;
+    c = c.wrapping_sub(b.rotate_left(16));
+    a ^= c;
+    a = a.wrapping_sub(c.rotate_left(4));
+    b ^= a;
+    b = b.wrapping_sub(a.rotate_left(14));
+    c ^= b;
+    c = c.wrapping_sub(b.rotate_left(24));
+
+    ((b as u64) << 32) | c as u64
+}
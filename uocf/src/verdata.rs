@@ -0,0 +1,129 @@
+#![allow(dead_code)]
+
+// Reader for verdata.mul, used by old clients (pre-UOP) to patch the data of other mul files.
+// The file starts with the patch count, followed by a table of patch entries. Each entry tells which block
+//  (e.g. a texture, an art tile, a tiledata block) of which file it replaces, and where the new data is stored
+//  inside verdata.mul itself.
+
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::collections::HashMap;
+use std::io::{Cursor, prelude::*};
+use std::path::PathBuf;
+
+use crate::errors::{CursorReadExt, IoResultExt, Result, UocfError};
+use crate::vfs;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct VerdataPatch {
+    pub file_id: u32,
+    pub block_id: u32,
+    // Position and size of the patch data in verdata.mul.
+    pub lookup: u32,
+    pub len: u32,
+    pub extra: u32,
+}
+impl VerdataPatch {
+    pub const PACKED_SIZE: usize = 4 * 5;
+}
+
+pub struct Verdata {
+    patches: HashMap<(u32, u32), VerdataPatch>,
+    // The whole file: it's small, and it's only read at startup.
+    file_data: Vec<u8>,
+}
+
+impl Verdata {
+    // Ids of the patched files, as stored in the patch entries.
+    const FILE_NAME: &'static str = "verdata.mul";
+
+    pub const FILE_ID_MAP0: u32 = 0x00;
+    pub const FILE_ID_STAIDX0: u32 = 0x01;
+    pub const FILE_ID_STATICS0: u32 = 0x02;
+    pub const FILE_ID_ARTIDX: u32 = 0x03;
+    pub const FILE_ID_ART: u32 = 0x04;
+    pub const FILE_ID_TEXIDX: u32 = 0x09;
+    pub const FILE_ID_TEXMAPS: u32 = 0x0A;
+    pub const FILE_ID_TILEDATA: u32 = 0x1E;
+    pub const FILE_ID_HUES: u32 = 0x20;
+
+    pub fn patch_count(&self) -> usize {
+        self.patches.len()
+    }
+
+    pub fn patch(&self, file_id: u32, block_id: u32) -> Option<&VerdataPatch> {
+        self.patches.get(&(file_id, block_id))
+    }
+
+    /// Iterate over the patches of the given file, in no particular order.
+    pub fn patches_for_file(&self, file_id: u32) -> impl Iterator<Item = &VerdataPatch> {
+        self.patches
+            .values()
+            .filter(move |patch| patch.file_id == file_id)
+    }
+
+    /// Data replacing the given block of the given file, if there's a patch for it.
+    pub fn patch_data(&self, file_id: u32, block_id: u32) -> Option<&[u8]> {
+        self.patch(file_id, block_id)
+            .and_then(|patch| self.data_of(patch))
+    }
+
+    /// Data of a patch. Returns None for patches pointing outside the file.
+    pub fn data_of(&self, patch: &VerdataPatch) -> Option<&[u8]> {
+        let start = patch.lookup as usize;
+        let end = start.checked_add(patch.len as usize)?;
+        self.file_data.get(start..end)
+    }
+
+    pub fn load(file_path: PathBuf) -> Result<Verdata> {
+        let file_path = vfs::canonicalize(&file_path)
+            .io_context(|| "Check verdata.mul path")?;
+
+        let mut file_handle = vfs::open(&file_path).io_context(|| {
+            format!("Open verdata.mul at '{}'", file_path.to_string_lossy())
+        })?;
+        let file_size = file_handle.len().io_context(|| "Get verdata.mul size")? as usize;
+
+        let mut file_data = vec![0; file_size];
+        file_handle
+            .read_exact(file_data.as_mut())
+            .io_context(|| "Read verdata.mul")?;
+
+        let mut verdata_file_rdr = Cursor::new(&file_data);
+        let patch_qty = verdata_file_rdr
+            .read_u32::<LittleEndian>()
+            .read_context(Self::FILE_NAME, 0, || "patch count")? as usize;
+        if 4 + (patch_qty * VerdataPatch::PACKED_SIZE) > file_size {
+            return Err(UocfError::malformed(
+                Self::FILE_NAME,
+                0,
+                format!("{patch_qty} patches don't fit in {file_size} bytes."),
+            ));
+        }
+
+        let mut patches = HashMap::with_capacity(patch_qty);
+        for i_patch in 0..patch_qty {
+            let err_buf = format!("patch {i_patch} (0x{:x}): reading ", i_patch);
+            let patch = VerdataPatch {
+                file_id: verdata_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "file id")?,
+                block_id: verdata_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "block id")?,
+                lookup: verdata_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "lookup")?,
+                len: verdata_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "length")?,
+                extra: verdata_file_rdr
+                    .read_field(Self::FILE_NAME, |rdr| rdr.read_u32::<LittleEndian>(), || err_buf.clone() + "extra")?,
+            };
+            // Later entries override earlier ones for the same block.
+            patches.insert((patch.file_id, patch.block_id), patch);
+        }
+
+        println!(
+            "Loaded {} (0x{:x}) Verdata patches.",
+            patches.len(),
+            patches.len()
+        );
+        Ok(Verdata { patches, file_data })
+    }
+}
@@ -0,0 +1,151 @@
+// Virtual file system: the loaders open their files through here. Files added to it are found at their path as if
+//  they were on disk, the others are read from the file system:
+// - memory files (add_file), e.g. picked by the user in a browser, on wasm32, where there's no file system;
+// - sources read on demand (add_source), e.g. the files of a remote UO folder (see remote).
+// The added files are shared by the whole process.
+// Paths are matched as given: the added files of a folder are found by UoInstallation::scan, which then builds their
+//  paths from the folder path, so they must be added under the same folder path passed to scan.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Cursor, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, LazyLock, RwLock};
+
+/// Reader of a VfsSource.
+pub trait VfsRead: Read + Seek + Send + Sync {}
+impl<T: Read + Seek + Send + Sync> VfsRead for T {}
+
+/// A file of the vfs whose data isn't in memory: it's read on demand by the readers it opens.
+pub trait VfsSource: Send + Sync {
+    fn len(&self) -> u64;
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    fn open(&self) -> io::Result<Box<dyn VfsRead>>;
+}
+
+#[derive(Clone)]
+enum VfsEntry {
+    Memory(Arc<[u8]>),
+    Source(Arc<dyn VfsSource>),
+}
+
+static FILES: LazyLock<RwLock<HashMap<PathBuf, VfsEntry>>> =
+    LazyLock::new(|| RwLock::new(HashMap::new()));
+
+fn entry(path: &Path) -> Option<VfsEntry> {
+    FILES.read().ok()?.get(path).cloned()
+}
+
+fn add_entry(path: PathBuf, entry: VfsEntry) {
+    if let Ok(mut files) = FILES.write() {
+        files.insert(path, entry);
+    }
+}
+
+/// Adds a file in memory at the given path, replacing the one already there, if any.
+pub fn add_file(path: impl Into<PathBuf>, bytes: impl Into<Arc<[u8]>>) {
+    add_entry(path.into(), VfsEntry::Memory(bytes.into()));
+}
+
+/// Adds a file read on demand from source at the given path, replacing the one already there, if any.
+pub fn add_source(path: impl Into<PathBuf>, source: Arc<dyn VfsSource>) {
+    add_entry(path.into(), VfsEntry::Source(source));
+}
+
+/// Drops the added files in the given folder. Returns how many there were.
+pub fn remove_folder(folder: &Path) -> usize {
+    let Ok(mut files) = FILES.write() else {
+        return 0;
+    };
+    let count = files.len();
+    files.retain(|path, _| path.parent() != Some(folder));
+    count - files.len()
+}
+
+/// Paths of the files added in the given folder.
+pub fn files_in(folder: &Path) -> Vec<PathBuf> {
+    let Ok(files) = FILES.read() else {
+        return Vec::new();
+    };
+    files
+        .keys()
+        .filter(|path| path.parent() == Some(folder))
+        .cloned()
+        .collect()
+}
+
+pub fn exists(path: &Path) -> bool {
+    entry(path).is_some() || path.exists()
+}
+
+/// The path itself for an added file, else the canonical path on disk.
+pub fn canonicalize(path: &Path) -> io::Result<PathBuf> {
+    match entry(path) {
+        Some(_) => Ok(path.to_owned()),
+        None => path.canonicalize(),
+    }
+}
+
+pub fn read_to_string(path: &Path) -> io::Result<String> {
+    match entry(path) {
+        Some(VfsEntry::Memory(bytes)) => String::from_utf8(bytes.to_vec())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+        Some(VfsEntry::Source(_)) => {
+            let mut text = String::new();
+            open(path)?.read_to_string(&mut text)?;
+            Ok(text)
+        }
+        None => fs::read_to_string(path),
+    }
+}
+
+pub fn open(path: &Path) -> io::Result<VfsFile> {
+    match entry(path) {
+        Some(VfsEntry::Memory(bytes)) => Ok(VfsFile::Memory(Cursor::new(bytes))),
+        Some(VfsEntry::Source(source)) => Ok(VfsFile::Source {
+            len: source.len(),
+            rdr: source.open()?,
+        }),
+        None => File::open(path).map(VfsFile::Disk),
+    }
+}
+
+/// A file opened with open: unbuffered, like File.
+pub enum VfsFile {
+    Disk(File),
+    Memory(Cursor<Arc<[u8]>>),
+    Source { rdr: Box<dyn VfsRead>, len: u64 },
+}
+impl VfsFile {
+    pub fn len(&self) -> io::Result<u64> {
+        match self {
+            Self::Disk(file) => file.metadata().map(|metadata| metadata.len()),
+            Self::Memory(cursor) => Ok(cursor.get_ref().len() as u64),
+            Self::Source { len, .. } => Ok(*len),
+        }
+    }
+
+    pub fn is_empty(&self) -> io::Result<bool> {
+        self.len().map(|len| len == 0)
+    }
+}
+impl Read for VfsFile {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Disk(file) => file.read(buf),
+            Self::Memory(cursor) => cursor.read(buf),
+            Self::Source { rdr, .. } => rdr.read(buf),
+        }
+    }
+}
+impl Seek for VfsFile {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            Self::Disk(file) => file.seek(pos),
+            Self::Memory(cursor) => cursor.seek(pos),
+            Self::Source { rdr, .. } => rdr.seek(pos),
+        }
+    }
+}
@@ -0,0 +1,131 @@
+// Checksum: the same bytes give the same value however they're split, and any change gives another one.
+// Fingerprint: small files are hashed whole; in big ones, changes to the sampled parts and to the size are seen.
+
+mod common;
+
+use common::{CASES, Rng};
+use std::io::Cursor;
+use std::path::Path;
+use uocf::checksum::{Checksum, Fingerprint};
+use uocf::installation::UoInstallation;
+use uocf::vfs;
+
+fn checksum(bytes: &[u8]) -> u64 {
+    let mut checksum = Checksum::new();
+    checksum.update(bytes);
+    checksum.finish()
+}
+
+#[test]
+fn split_independent() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let bytes: Vec<u8> = (0..rng.below(1000)).map(|_| rng.u8()).collect();
+        let expected = checksum(&bytes);
+
+        let mut split = Checksum::new();
+        let mut rest = &bytes[..];
+        while !rest.is_empty() {
+            let len = (rng.below(20) as usize).min(rest.len());
+            split.update(&rest[..len]);
+            rest = &rest[len..];
+        }
+        assert_eq!(split.finish(), expected, "seed {seed}");
+
+        let mut from_reader = Checksum::new();
+        from_reader.update_from_reader(Cursor::new(&bytes)).unwrap();
+        assert_eq!(from_reader.finish(), expected, "seed {seed}");
+    }
+}
+
+#[test]
+fn detects_changes() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let bytes: Vec<u8> = (0..1 + rng.below(1000)).map(|_| rng.u8()).collect();
+        let expected = checksum(&bytes);
+
+        let mut changed = bytes.clone();
+        let i = rng.below(bytes.len() as u32) as usize;
+        changed[i] ^= 1 << rng.below(8);
+        assert_ne!(checksum(&changed), expected, "seed {seed}");
+
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert_ne!(checksum(&longer), expected, "seed {seed}");
+        assert_ne!(checksum(&bytes[..bytes.len() - 1]), expected, "seed {seed}");
+    }
+    assert_ne!(checksum(&[]), checksum(&[0]));
+}
+
+fn fingerprint(bytes: &[u8]) -> Fingerprint {
+    Fingerprint::of_reader(Cursor::new(bytes)).unwrap()
+}
+
+#[test]
+fn fingerprint_small_file_is_whole() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let bytes: Vec<u8> = (0..1 + rng.below(100_000)).map(|_| rng.u8()).collect();
+        let expected = fingerprint(&bytes);
+        assert_eq!(expected.size, bytes.len() as u64);
+
+        let mut changed = bytes.clone();
+        let i = rng.below(bytes.len() as u32) as usize;
+        changed[i] = changed[i].wrapping_add(1);
+        assert_ne!(fingerprint(&changed), expected, "seed {seed}, byte {i}");
+    }
+}
+
+#[test]
+fn fingerprint_big_file_samples() {
+    let mut rng = Rng::new(0);
+    let bytes: Vec<u8> = (0..3_000_000).map(|_| rng.u8()).collect();
+    let expected = fingerprint(&bytes);
+
+    // The first and last bytes, and the first byte of the samples (the first one starts after the first 64 KiB).
+    for i in [0, 65_535, 65_536, bytes.len() - 65_536, bytes.len() - 1] {
+        let mut changed = bytes.clone();
+        changed[i] ^= 0x80;
+        assert_ne!(fingerprint(&changed), expected, "byte {i}");
+    }
+    let mut longer = bytes.clone();
+    longer.push(0);
+    assert_ne!(fingerprint(&longer), expected);
+    // Between the samples: not seen, that's the trade-off.
+    let mut unsampled = bytes.clone();
+    unsampled[70_000] ^= 0x80;
+    assert_eq!(fingerprint(&unsampled), expected);
+}
+
+#[test]
+fn fingerprint_of_installation_files() {
+    let folder = Path::new("/uocf_fingerprint_test/uo");
+    vfs::add_file(folder.join("hues.mul"), vec![1, 2, 3]);
+    vfs::add_file(folder.join("radarcol.mul"), vec![1, 2, 3, 4]);
+    vfs::add_file(folder.join("unknown.bin"), vec![5]);
+    let installation = UoInstallation::scan(folder).unwrap();
+    let files = installation.files();
+    assert_eq!(
+        files,
+        vec![&folder.join("hues.mul"), &folder.join("radarcol.mul")]
+    );
+
+    let fingerprints: Vec<Fingerprint> = files
+        .iter()
+        .map(|path| Fingerprint::of_file(path).unwrap())
+        .collect();
+    assert_eq!(fingerprints[0], fingerprint(&[1, 2, 3]));
+    assert_eq!(fingerprints[1], fingerprint(&[1, 2, 3, 4]));
+    assert!(
+        Fingerprint::of_file(&folder.join("missing.mul"))
+            .unwrap_err()
+            .is_not_found()
+    );
+    // The combined value depends on the order.
+    assert_ne!(
+        Fingerprint::combine(&fingerprints),
+        Fingerprint::combine(fingerprints.iter().rev())
+    );
+    vfs::remove_folder(folder);
+}
@@ -0,0 +1,272 @@
+// Synthetic UO client files for the parser tests: random content in the layout of each file, written independently of
+//  the uocf writers, with the values the parsers must read back. The layouts are spelled out here on purpose: a
+//  mistake in the offset math of a parser doesn't cancel out with the same mistake in the test.
+#![allow(dead_code)]
+
+use std::path::{Path, PathBuf};
+
+/// Cases generated by each property test.
+pub const CASES: u64 = 8;
+
+/// Deterministic pseudo random numbers (xorshift64*): a failing case is reproduced by its seed.
+pub struct Rng(u64);
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // Zero is the only state xorshift can't leave.
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+    pub fn u64(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+    pub fn u32(&mut self) -> u32 {
+        (self.u64() >> 32) as u32
+    }
+    pub fn u16(&mut self) -> u16 {
+        (self.u64() >> 48) as u16
+    }
+    pub fn u8(&mut self) -> u8 {
+        (self.u64() >> 56) as u8
+    }
+    /// A number in 0..n.
+    pub fn below(&mut self, n: u32) -> u32 {
+        (self.u64() % n as u64) as u32
+    }
+    /// A tile name: ASCII, 0 to 20 chars, null padded to 20 bytes.
+    pub fn name(&mut self) -> [u8; 20] {
+        let mut name = [0; 20];
+        for byte in name.iter_mut().take(self.below(21) as usize) {
+            *byte = b' ' + self.below(95) as u8;
+        }
+        name
+    }
+}
+
+pub fn write_file(folder: &Path, file_name: &str, bytes: &[u8]) -> PathBuf {
+    let file_path = folder.join(file_name);
+    std::fs::write(&file_path, bytes).expect("Can't write the test file");
+    file_path
+}
+
+pub fn fixture_path(file_name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(file_name)
+}
+
+/* tiledata.mul */
+
+/// Layout of a tiledata revision: 1 is the classic one, 2 and 3 the High Seas ones (an extra u32 in each tile).
+pub struct TileDataLayout {
+    pub high_seas: bool,
+    pub item_count: usize,
+}
+impl TileDataLayout {
+    pub const LAND_COUNT: usize = 0x4000;
+    pub const TILES_PER_BLOCK: usize = 32;
+
+    pub fn of_revision(revision: u8) -> Self {
+        match revision {
+            1 => Self {
+                high_seas: false,
+                item_count: 0x4000,
+            },
+            2 => Self {
+                high_seas: true,
+                item_count: 0x8000,
+            },
+            3 => Self {
+                high_seas: true,
+                item_count: 0x10000,
+            },
+            _ => panic!("No tiledata revision {revision}"),
+        }
+    }
+    pub fn land_tile_size(&self) -> usize {
+        if self.high_seas { 30 } else { 26 }
+    }
+    pub fn item_tile_size(&self) -> usize {
+        if self.high_seas { 41 } else { 37 }
+    }
+    /// Size of a block: a u32 header, then 32 tiles.
+    pub fn land_block_size(&self) -> usize {
+        4 + self.land_tile_size() * Self::TILES_PER_BLOCK
+    }
+    pub fn item_block_size(&self) -> usize {
+        4 + self.item_tile_size() * Self::TILES_PER_BLOCK
+    }
+    pub fn land_section_size(&self) -> usize {
+        self.land_block_size() * Self::LAND_COUNT / Self::TILES_PER_BLOCK
+    }
+    pub fn file_size(&self) -> usize {
+        self.land_section_size() + self.item_block_size() * self.item_count / Self::TILES_PER_BLOCK
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LandRecord {
+    pub flags: u32,
+    /// Only in the High Seas layout: 0 otherwise.
+    pub unk_hs: u32,
+    pub texture_id: u16,
+    pub name: [u8; 20],
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ItemRecord {
+    pub flags: u32,
+    /// Only in the High Seas layout: 0 otherwise.
+    pub unk_hs: u32,
+    pub weight: u8,
+    pub quality: u8,
+    pub unk0: u16,
+    pub unk1: u8,
+    pub quantity: u8,
+    pub anim_id: u16,
+    pub unk2: u8,
+    pub hue_extra: u8,
+    pub stacking_offset: u8,
+    pub value: u8,
+    pub height: i8,
+    pub name: [u8; 20],
+}
+
+pub struct SynthTileData {
+    pub bytes: Vec<u8>,
+    pub land: Vec<LandRecord>,
+    pub items: Vec<ItemRecord>,
+}
+
+/// A tiledata.mul of the given revision, with random tiles and block headers.
+pub fn synth_tiledata(revision: u8, rng: &mut Rng) -> SynthTileData {
+    let layout = TileDataLayout::of_revision(revision);
+    let mut bytes = Vec::with_capacity(layout.file_size());
+    let mut land = Vec::with_capacity(TileDataLayout::LAND_COUNT);
+    let mut items = Vec::with_capacity(layout.item_count);
+
+    for i_tile in 0..TileDataLayout::LAND_COUNT {
+        if i_tile % TileDataLayout::TILES_PER_BLOCK == 0 {
+            bytes.extend_from_slice(&rng.u32().to_le_bytes());
+        }
+        let tile = LandRecord {
+            flags: rng.u32(),
+            unk_hs: if layout.high_seas { rng.u32() } else { 0 },
+            texture_id: rng.u16(),
+            name: rng.name(),
+        };
+        bytes.extend_from_slice(&tile.flags.to_le_bytes());
+        if layout.high_seas {
+            bytes.extend_from_slice(&tile.unk_hs.to_le_bytes());
+        }
+        bytes.extend_from_slice(&tile.texture_id.to_le_bytes());
+        bytes.extend_from_slice(&tile.name);
+        land.push(tile);
+    }
+
+    for i_tile in 0..layout.item_count {
+        if i_tile % TileDataLayout::TILES_PER_BLOCK == 0 {
+            bytes.extend_from_slice(&rng.u32().to_le_bytes());
+        }
+        let tile = ItemRecord {
+            flags: rng.u32(),
+            unk_hs: if layout.high_seas { rng.u32() } else { 0 },
+            weight: rng.u8(),
+            quality: rng.u8(),
+            unk0: rng.u16(),
+            unk1: rng.u8(),
+            quantity: rng.u8(),
+            anim_id: rng.u16(),
+            unk2: rng.u8(),
+            hue_extra: rng.u8(),
+            stacking_offset: rng.u8(),
+            value: rng.u8(),
+            height: rng.u8() as i8,
+            name: rng.name(),
+        };
+        bytes.extend_from_slice(&tile.flags.to_le_bytes());
+        if layout.high_seas {
+            bytes.extend_from_slice(&tile.unk_hs.to_le_bytes());
+        }
+        bytes.extend_from_slice(&[tile.weight, tile.quality]);
+        bytes.extend_from_slice(&tile.unk0.to_le_bytes());
+        bytes.extend_from_slice(&[tile.unk1, tile.quantity]);
+        bytes.extend_from_slice(&tile.anim_id.to_le_bytes());
+        bytes.extend_from_slice(&[
+            tile.unk2,
+            tile.hue_extra,
+            tile.stacking_offset,
+            tile.value,
+            tile.height as u8,
+        ]);
+        bytes.extend_from_slice(&tile.name);
+        items.push(tile);
+    }
+
+    assert_eq!(bytes.len(), layout.file_size());
+    SynthTileData { bytes, land, items }
+}
+
+/* Index files (*idx.mul) */
+
+/// An index file entry: lookup, length and extra, or None for an unused entry.
+pub type IndexRecord = Option<(u32, u32, u32)>;
+
+/// An index file with count entries, a quarter of them unused (lookup 0xFFFFFFFF).
+pub fn synth_index(count: usize, rng: &mut Rng) -> (Vec<u8>, Vec<IndexRecord>) {
+    let mut bytes = Vec::with_capacity(count * 12);
+    let mut records = Vec::with_capacity(count);
+    for _ in 0..count {
+        // Extra 0xFFFFFFFF also marks an unused entry: a used one never has it.
+        let record =
+            (rng.below(4) != 0).then(|| (rng.below(u32::MAX), rng.u32(), rng.below(u32::MAX)));
+        let (lookup, len, extra) = record.unwrap_or((u32::MAX, rng.u32(), rng.u32()));
+        for value in [lookup, len, extra] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        records.push(record);
+    }
+    (bytes, records)
+}
+
+/* map*.mul */
+
+/// A map file: blocks of 8x8 cells, stored column by column (block x, then y). Each block is a u32 header, then its
+///  cells row by row: id (u16) and z (i8).
+pub struct SynthMap {
+    pub bytes: Vec<u8>,
+    pub width_blocks: u32,
+    pub height_blocks: u32,
+    /// Id and z of the cells of each block, in file order.
+    pub blocks: Vec<[(u16, i8); 64]>,
+}
+impl SynthMap {
+    /// Id and z of a cell, given its block and its position in the block.
+    pub fn cell(&self, block_x: u32, block_y: u32, cell_x: u32, cell_y: u32) -> (u16, i8) {
+        self.blocks[(block_x * self.height_blocks + block_y) as usize]
+            [(cell_y * 8 + cell_x) as usize]
+    }
+}
+
+pub fn synth_map(width_blocks: u32, height_blocks: u32, rng: &mut Rng) -> SynthMap {
+    let block_count = (width_blocks * height_blocks) as usize;
+    let mut bytes = Vec::with_capacity(block_count * (4 + 64 * 3));
+    let mut blocks = Vec::with_capacity(block_count);
+    for _ in 0..block_count {
+        bytes.extend_from_slice(&rng.u32().to_le_bytes());
+        let mut cells = [(0, 0); 64];
+        for cell in cells.iter_mut() {
+            *cell = (rng.u16(), rng.u8() as i8);
+            bytes.extend_from_slice(&cell.0.to_le_bytes());
+            bytes.push(cell.1 as u8);
+        }
+        blocks.push(cells);
+    }
+    SynthMap {
+        bytes,
+        width_blocks,
+        height_blocks,
+        blocks,
+    }
+}
@@ -0,0 +1,24 @@
+// Bad input given to the loaders is returned as a UocfError, not a panic.
+
+use std::path::PathBuf;
+
+use uocf::UocfError;
+use uocf::cliloc::Cliloc;
+use uocf::generic_index::IndexFile;
+
+#[test]
+fn paths_without_file_name_are_errors() {
+    for path in ["/", ".."] {
+        assert!(
+            matches!(Cliloc::load(PathBuf::from(path)), Err(UocfError::Io { .. })),
+            "cliloc at '{path}'"
+        );
+        assert!(
+            matches!(
+                IndexFile::load(PathBuf::from(path)),
+                Err(UocfError::Io { .. })
+            ),
+            "index at '{path}'"
+        );
+    }
+}
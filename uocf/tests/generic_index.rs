@@ -0,0 +1,69 @@
+// Synthetic index files through IndexFile::load, and a golden test against tests/fixtures/texidx_16.mul.
+
+mod common;
+
+use common::Rng;
+use uocf::generic_index::IndexFile;
+
+fn load(bytes: &[u8]) -> IndexFile {
+    let folder = tempfile::tempdir().expect("Can't create a temporary folder");
+    IndexFile::load(common::write_file(folder.path(), "test_idx.mul", bytes))
+        .expect("Can't load the index file")
+}
+
+/// Lookup, length and extra of an entry, None if it's unused.
+fn entry(index: &IndexFile, i: usize) -> Option<(u32, u32, u32)> {
+    let element = index.element(i).expect("Missing index entry");
+    match (element.lookup(), element.len(), element.extra()) {
+        (Some(lookup), Some(len), Some(extra)) => Some((lookup, len, extra)),
+        (None, None, None) => None,
+        partial => panic!("Entry {i} partially valid: {partial:?}"),
+    }
+}
+
+#[test]
+fn synthetic_index_round_trips() {
+    for seed in 0..common::CASES {
+        let mut rng = Rng::new(seed);
+        let count = rng.below(2000) as usize;
+        let (mut bytes, records) = common::synth_index(count, &mut rng);
+        // Some index files have a few trailing bytes, less than an entry: they're ignored.
+        bytes.extend(std::iter::repeat_n(0xAB, rng.below(12) as usize));
+
+        let index = load(&bytes);
+        assert_eq!(index.element_count(), count, "seed {seed}");
+        for (i, record) in records.iter().enumerate() {
+            assert_eq!(entry(&index, i), *record, "seed {seed}, entry {i}");
+        }
+        assert!(
+            index.element(count).is_err(),
+            "seed {seed}: entry past the end"
+        );
+    }
+}
+
+#[test]
+fn index_from_memory_matches_file() {
+    let (bytes, records) = common::synth_index(100, &mut Rng::new(0));
+    let index = IndexFile::from_reader(bytes.as_slice(), "test_idx.mul")
+        .expect("Can't read the index from memory");
+    assert_eq!(index.element_count(), load(&bytes).element_count());
+    for (i, record) in records.iter().enumerate() {
+        assert_eq!(entry(&index, i), *record, "entry {i}");
+    }
+}
+
+#[test]
+fn golden_index() {
+    let index = IndexFile::load(common::fixture_path("texidx_16.mul"))
+        .expect("Can't load the index fixture");
+    assert_eq!(index.element_count(), 16);
+    assert_eq!(entry(&index, 0), Some((0x0, 0x8000, 0xd8c0)));
+    assert_eq!(entry(&index, 1), Some((0x2000, 0x2000, 0x3e7c)));
+    assert_eq!(entry(&index, 3), None);
+    assert_eq!(entry(&index, 8), Some((0x10000, 0x8000, 0x78be)));
+    assert_eq!(entry(&index, 9), None);
+    // An extra of 0xFFFFFFFF marks the entry as unused too.
+    assert_eq!(entry(&index, 12), None);
+    assert_eq!(entry(&index, 15), Some((0x1e000, 0x2000, 0x2c0a)));
+}
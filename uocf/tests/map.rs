@@ -0,0 +1,444 @@
+// Synthetic map files through MapPlane and MapPlaneShared, with both file backends, and a golden test against
+//  tests/fixtures/map0_8x8.mul.
+// Saving edits to copies of the fixture: the bytes written, the edits of patched blocks going to mapdif, the backups.
+
+mod common;
+
+use std::io::Cursor;
+use std::path::Path;
+
+use common::{Rng, SynthMap};
+use uocf::geo::map::{
+    FileBackend, MapBlock, MapBlockRelPos, MapCell, MapPlane, MapPlaneShared, MapSizeCells,
+};
+
+const BACKENDS: [FileBackend; 2] = [FileBackend::Read, FileBackend::Mmap];
+
+fn open(map_path: &Path, width_blocks: u32, height_blocks: u32, backend: FileBackend) -> MapPlane {
+    let size = MapSizeCells {
+        width: width_blocks * MapBlock::CELLS_PER_ROW,
+        height: height_blocks * MapBlock::CELLS_PER_COLUMN,
+    };
+    MapPlane::init_with_size(map_path.to_path_buf(), 0, backend, size)
+        .expect("Can't open the map file")
+}
+
+fn assert_block_matches(map_plane: &MapPlane, synth: &SynthMap, pos: MapBlockRelPos, case: &str) {
+    let block = map_plane
+        .block(pos)
+        .unwrap_or_else(|| panic!("{case}: block {pos:?} not loaded"));
+    for cell_y in 0..MapBlock::CELLS_PER_COLUMN {
+        for cell_x in 0..MapBlock::CELLS_PER_ROW {
+            let cell = block.cell(cell_x, cell_y).expect("Cell out of the block");
+            assert_eq!(
+                (cell.id, cell.z),
+                synth.cell(pos.x, pos.y, cell_x, cell_y),
+                "{case}: block {pos:?}, cell ({cell_x}, {cell_y})"
+            );
+        }
+    }
+}
+
+#[test]
+fn synthetic_map_blocks_round_trip() {
+    let folder = tempfile::tempdir().expect("Can't create a temporary folder");
+    for seed in 0..common::CASES {
+        let mut rng = Rng::new(seed);
+        let (width_blocks, height_blocks) = (1 + rng.below(24), 1 + rng.below(24));
+        let synth = common::synth_map(width_blocks, height_blocks, &mut rng);
+        let map_path = common::write_file(folder.path(), &format!("map_{seed}.mul"), &synth.bytes);
+
+        // Scattered blocks, in random order.
+        let mut positions: Vec<MapBlockRelPos> = Vec::new();
+        for _ in 0..1 + rng.below(width_blocks * height_blocks) {
+            let pos = MapBlockRelPos {
+                x: rng.below(width_blocks),
+                y: rng.below(height_blocks),
+            };
+            if !positions.contains(&pos) {
+                positions.push(pos);
+            }
+        }
+        for backend in BACKENDS {
+            let case = format!("seed {seed}, {width_blocks}x{height_blocks} blocks, {backend:?}");
+            let mut map_plane = open(&map_path, width_blocks, height_blocks, backend);
+            map_plane
+                .load_blocks(&mut positions.clone())
+                .expect("Can't load the blocks");
+            for &pos in &positions {
+                assert_block_matches(&map_plane, &synth, pos, &case);
+            }
+
+            // The whole map, read sequentially.
+            let mut all_positions: Vec<MapBlockRelPos> = (0..width_blocks)
+                .flat_map(|x| (0..height_blocks).map(move |y| MapBlockRelPos { x, y }))
+                .collect();
+            let mut map_plane = open(&map_path, width_blocks, height_blocks, backend);
+            map_plane
+                .load_blocks(&mut all_positions)
+                .expect("Can't load the whole map");
+            assert_eq!(
+                map_plane.cached_block_count(),
+                all_positions.len(),
+                "{case}"
+            );
+            for &pos in &all_positions {
+                assert_block_matches(&map_plane, &synth, pos, &case);
+            }
+        }
+    }
+}
+
+#[test]
+fn synthetic_map_cells_and_columns() {
+    let folder = tempfile::tempdir().expect("Can't create a temporary folder");
+    for seed in 0..common::CASES {
+        let mut rng = Rng::new(seed);
+        let (width_blocks, height_blocks) = (1 + rng.below(16), 1 + rng.below(16));
+        let synth = common::synth_map(width_blocks, height_blocks, &mut rng);
+        let map_path = common::write_file(folder.path(), &format!("map_{seed}.mul"), &synth.bytes);
+        for backend in BACKENDS {
+            let case = format!("seed {seed}, {width_blocks}x{height_blocks} blocks, {backend:?}");
+            let shared = MapPlaneShared::new(open(&map_path, width_blocks, height_blocks, backend));
+            for _ in 0..64 {
+                let (x, y) = (rng.below(width_blocks * 8), rng.below(height_blocks * 8));
+                let cell = shared.cell(x, y).expect("Can't read the cell");
+                assert_eq!(
+                    (cell.id, cell.z),
+                    synth.cell(x / 8, y / 8, x % 8, y % 8),
+                    "{case}: cell ({x}, {y})"
+                );
+            }
+            assert!(
+                shared.cell(width_blocks * 8, 0).is_err(),
+                "{case}: cell out of the map"
+            );
+
+            let x = rng.below(width_blocks);
+            let column = shared
+                .read_block_column(x)
+                .expect("Can't read the block column");
+            assert_eq!(column.len(), height_blocks as usize, "{case}");
+            for (y, block) in column.iter().enumerate() {
+                let cells: Vec<(u16, i8)> =
+                    block.cells().iter().map(|cell| (cell.id, cell.z)).collect();
+                assert_eq!(
+                    cells,
+                    synth.blocks[(x * height_blocks) as usize + y],
+                    "{case}: column {x}, block {y}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn synthetic_map_from_memory() {
+    for seed in 0..common::CASES {
+        let mut rng = Rng::new(seed);
+        let (width_blocks, height_blocks) = (1 + rng.below(16), 1 + rng.below(16));
+        let synth = common::synth_map(width_blocks, height_blocks, &mut rng);
+        let size = MapSizeCells {
+            width: width_blocks * MapBlock::CELLS_PER_ROW,
+            height: height_blocks * MapBlock::CELLS_PER_COLUMN,
+        };
+        let mut positions: Vec<MapBlockRelPos> = (0..width_blocks)
+            .flat_map(|x| (0..height_blocks).map(move |y| MapBlockRelPos { x, y }))
+            .collect();
+        let map_planes = [
+            (
+                "from_reader",
+                MapPlane::from_reader(Cursor::new(synth.bytes.clone()), 0, size),
+            ),
+            (
+                "from_bytes",
+                MapPlane::from_bytes(synth.bytes.clone(), 0, size),
+            ),
+        ];
+        for (source, map_plane) in map_planes {
+            let case = format!("seed {seed}, {width_blocks}x{height_blocks} blocks, {source}");
+            let mut map_plane = map_plane.expect("Can't read the map from memory");
+            map_plane
+                .load_blocks(&mut positions)
+                .expect("Can't load the whole map");
+            for &pos in &positions {
+                assert_block_matches(&map_plane, &synth, pos, &case);
+            }
+
+            // Edits stay in memory: there's no file to write them to.
+            map_plane
+                .set_cell(0, 0, MapCell { id: 3, z: 0 })
+                .expect("Can't edit the map");
+            assert!(map_plane.save_edits(false).is_err(), "{case}");
+            assert!(map_plane.has_edits(), "{case}");
+        }
+    }
+}
+
+#[test]
+fn map_of_wrong_size_is_refused() {
+    let folder = tempfile::tempdir().expect("Can't create a temporary folder");
+    let synth = common::synth_map(4, 4, &mut Rng::new(0));
+    let map_path = common::write_file(
+        folder.path(),
+        "map0.mul",
+        &synth.bytes[..synth.bytes.len() - 1],
+    );
+    let size = MapSizeCells {
+        width: 32,
+        height: 32,
+    };
+    assert!(MapPlane::init_with_size(map_path.clone(), 0, FileBackend::Read, size).is_err());
+    // Not a multiple of the block size.
+    let size = MapSizeCells {
+        width: 30,
+        height: 32,
+    };
+    assert!(MapPlane::init_with_size(map_path, 0, FileBackend::Read, size).is_err());
+}
+
+#[test]
+fn golden_map() {
+    let map_path = common::fixture_path("map0_8x8.mul");
+    for backend in BACKENDS {
+        let mut map_plane = open(&map_path, 8, 8, backend);
+        let mut positions = vec![
+            MapBlockRelPos { x: 0, y: 0 },
+            MapBlockRelPos { x: 3, y: 5 },
+            MapBlockRelPos { x: 7, y: 0 },
+            MapBlockRelPos { x: 7, y: 7 },
+        ];
+        map_plane
+            .load_blocks(&mut positions)
+            .expect("Can't load the fixture blocks");
+        let cell = |block_x, block_y, cell_x, cell_y| {
+            let block = map_plane
+                .block(MapBlockRelPos {
+                    x: block_x,
+                    y: block_y,
+                })
+                .expect("Block not loaded");
+            let cell = block.cell(cell_x, cell_y).expect("Cell out of the block");
+            (cell.id, cell.z)
+        };
+        assert_eq!(cell(0, 0, 0, 0), (0xba6d, 82), "{backend:?}");
+        assert_eq!(cell(0, 0, 7, 0), (0xafd5, -127), "{backend:?}");
+        assert_eq!(cell(0, 0, 0, 1), (0xbe89, 113), "{backend:?}");
+        assert_eq!(cell(3, 5, 2, 7), (0x9bfd, 39), "{backend:?}");
+        assert_eq!(cell(7, 0, 4, 3), (0xed5f, 114), "{backend:?}");
+        assert_eq!(cell(7, 7, 7, 7), (0x8428, -43), "{backend:?}");
+    }
+}
+
+/// Width and height of tests/fixtures/map0_8x8.mul, in blocks.
+const FIXTURE_BLOCKS: u32 = 8;
+
+/// Offset of a cell in a map or mapdif file, from the offset of its block.
+fn cell_offset(block_offset: usize, x: u32, y: u32) -> usize {
+    let in_block =
+        MapCell::coords_in_block_y(y) * MapBlock::CELLS_PER_ROW + MapCell::coords_in_block_x(x);
+    block_offset + 4 + in_block as usize * MapCell::PACKED_SIZE
+}
+
+/// Offset of the block holding the cell in the map file (blocks are stored column by column).
+fn map_block_offset(x: u32, y: u32) -> usize {
+    let block_idx = MapCell::coords_of_parent_block_x(x) * FIXTURE_BLOCKS
+        + MapCell::coords_of_parent_block_y(y);
+    block_idx as usize * MapBlock::PACKED_SIZE
+}
+
+fn set_cell_bytes(bytes: &mut [u8], offset: usize, cell: MapCell) {
+    bytes[offset..offset + 2].copy_from_slice(&cell.id.to_le_bytes());
+    bytes[offset + 2] = cell.z as u8;
+}
+
+fn backup_path(file_path: &Path) -> std::path::PathBuf {
+    let mut backup_path = file_path.as_os_str().to_owned();
+    backup_path.push(".bak");
+    backup_path.into()
+}
+
+#[test]
+fn saved_edits_are_written_to_the_map_file() {
+    let original =
+        std::fs::read(common::fixture_path("map0_8x8.mul")).expect("Can't read the fixture");
+    let edits = [
+        (0, 0, MapCell { id: 0x0003, z: -5 }),
+        (7, 7, MapCell { id: 0x1234, z: 127 }),
+        (
+            27,
+            45,
+            MapCell {
+                id: 0xABCD,
+                z: -128,
+            },
+        ),
+        (63, 63, MapCell { id: 0x00FF, z: 0 }),
+    ];
+    for backend in BACKENDS {
+        let folder = tempfile::tempdir().expect("Can't create a temporary folder");
+        let map_path = common::write_file(folder.path(), "map0.mul", &original);
+        let mut map_plane = open(&map_path, FIXTURE_BLOCKS, FIXTURE_BLOCKS, backend);
+        let mut expected = original.clone();
+        for (x, y, cell) in edits {
+            map_plane.set_cell(x, y, cell).expect("Can't edit the map");
+            set_cell_bytes(
+                &mut expected,
+                cell_offset(map_block_offset(x, y), x, y),
+                cell,
+            );
+        }
+        // (0, 0) and (7, 7) are in the same block.
+        assert_eq!(
+            map_plane.save_edits(false).expect("Can't save the edits"),
+            3,
+            "{backend:?}"
+        );
+        assert!(!map_plane.has_edits(), "{backend:?}");
+        drop(map_plane);
+
+        let saved = std::fs::read(&map_path).expect("Can't read the saved map");
+        assert!(saved == expected, "{backend:?}: the saved file differs");
+        assert!(
+            !backup_path(&map_path).exists(),
+            "{backend:?}: backup made without make_backup"
+        );
+        let map_plane =
+            MapPlaneShared::new(open(&map_path, FIXTURE_BLOCKS, FIXTURE_BLOCKS, backend));
+        for (x, y, cell) in edits {
+            let read = map_plane.cell(x, y).expect("Can't read the saved cell");
+            assert_eq!(
+                (read.id, read.z),
+                (cell.id, cell.z),
+                "{backend:?}: cell ({x}, {y})"
+            );
+        }
+    }
+}
+
+#[test]
+fn edits_of_patched_blocks_are_written_to_mapdif() {
+    let original =
+        std::fs::read(common::fixture_path("map0_8x8.mul")).expect("Can't read the fixture");
+    // Block (2, 3) is patched by the only block of mapdif.
+    let patched_block_idx: u32 = 2 * FIXTURE_BLOCKS + 3;
+    let mut rng = Rng::new(0);
+    let mut diff = vec![0; MapBlock::PACKED_SIZE];
+    for byte in &mut diff {
+        *byte = rng.u8();
+    }
+    let (patched_x, patched_y) = (2 * 8 + 5, 3 * 8 + 1);
+    let (unpatched_x, unpatched_y) = (40, 9);
+    let patched_cell = MapCell { id: 0x4321, z: 12 };
+    let unpatched_cell = MapCell { id: 0x0042, z: -12 };
+
+    for backend in BACKENDS {
+        let folder = tempfile::tempdir().expect("Can't create a temporary folder");
+        let map_path = common::write_file(folder.path(), "map0.mul", &original);
+        let difl_path = common::write_file(
+            folder.path(),
+            "mapdifl0.mul",
+            &patched_block_idx.to_le_bytes(),
+        );
+        let dif_path = common::write_file(folder.path(), "mapdif0.mul", &diff);
+        let mut map_plane = open(&map_path, FIXTURE_BLOCKS, FIXTURE_BLOCKS, backend);
+        map_plane
+            .load_diffs(difl_path.clone(), dif_path.clone())
+            .expect("Can't load the diffs");
+
+        map_plane
+            .set_cell(patched_x, patched_y, patched_cell)
+            .expect("Can't edit the map");
+        map_plane
+            .set_cell(unpatched_x, unpatched_y, unpatched_cell)
+            .expect("Can't edit the map");
+        assert_eq!(
+            map_plane.save_edits(true).expect("Can't save the edits"),
+            2,
+            "{backend:?}"
+        );
+        drop(map_plane);
+
+        // The patched block only changes in mapdif, the other one in map.
+        let mut expected_map = original.clone();
+        let offset = cell_offset(
+            map_block_offset(unpatched_x, unpatched_y),
+            unpatched_x,
+            unpatched_y,
+        );
+        set_cell_bytes(&mut expected_map, offset, unpatched_cell);
+        let mut expected_diff = diff.clone();
+        set_cell_bytes(
+            &mut expected_diff,
+            cell_offset(0, patched_x, patched_y),
+            patched_cell,
+        );
+        assert!(
+            std::fs::read(&map_path).unwrap() == expected_map,
+            "{backend:?}: map0.mul differs"
+        );
+        assert!(
+            std::fs::read(&dif_path).unwrap() == expected_diff,
+            "{backend:?}: mapdif0.mul differs"
+        );
+        assert!(
+            std::fs::read(&difl_path).unwrap() == patched_block_idx.to_le_bytes(),
+            "{backend:?}"
+        );
+
+        // Read back with the patches.
+        let mut map_plane = open(&map_path, FIXTURE_BLOCKS, FIXTURE_BLOCKS, backend);
+        map_plane
+            .load_diffs(difl_path, dif_path)
+            .expect("Can't load the diffs");
+        let map_plane = MapPlaneShared::new(map_plane);
+        for (x, y, cell) in [
+            (patched_x, patched_y, patched_cell),
+            (unpatched_x, unpatched_y, unpatched_cell),
+        ] {
+            let read = map_plane.cell(x, y).expect("Can't read the saved cell");
+            assert_eq!(
+                (read.id, read.z),
+                (cell.id, cell.z),
+                "{backend:?}: cell ({x}, {y})"
+            );
+        }
+    }
+}
+
+#[test]
+fn backups_hold_the_original_bytes() {
+    let original =
+        std::fs::read(common::fixture_path("map0_8x8.mul")).expect("Can't read the fixture");
+    let diff = vec![0x11; MapBlock::PACKED_SIZE];
+    let folder = tempfile::tempdir().expect("Can't create a temporary folder");
+    let map_path = common::write_file(folder.path(), "map0.mul", &original);
+    let difl_path = common::write_file(folder.path(), "mapdifl0.mul", &0u32.to_le_bytes());
+    let dif_path = common::write_file(folder.path(), "mapdif0.mul", &diff);
+    let mut map_plane = open(&map_path, FIXTURE_BLOCKS, FIXTURE_BLOCKS, FileBackend::Read);
+    map_plane
+        .load_diffs(difl_path.clone(), dif_path.clone())
+        .expect("Can't load the diffs");
+
+    // Only the map file is written: mapdif isn't backed up.
+    map_plane
+        .set_cell(20, 20, MapCell { id: 1, z: 1 })
+        .expect("Can't edit the map");
+    map_plane.save_edits(true).expect("Can't save the edits");
+    assert!(std::fs::read(backup_path(&map_path)).unwrap() == original);
+    assert!(!backup_path(&dif_path).exists());
+
+    // The backups already made are kept: they hold the original bytes, not the ones of the previous save.
+    map_plane
+        .set_cell(20, 20, MapCell { id: 2, z: 2 })
+        .expect("Can't edit the map");
+    map_plane
+        .set_cell(0, 0, MapCell { id: 3, z: 3 })
+        .expect("Can't edit the map");
+    map_plane.save_edits(true).expect("Can't save the edits");
+    assert!(std::fs::read(backup_path(&map_path)).unwrap() == original);
+    assert!(std::fs::read(backup_path(&dif_path)).unwrap() == diff);
+    assert!(std::fs::read(&map_path).unwrap() != original);
+    assert!(std::fs::read(&dif_path).unwrap() != diff);
+    assert!(!backup_path(&difl_path).exists());
+}
@@ -0,0 +1,142 @@
+// A UO folder served by remote::serve on localhost, added to the vfs by RemoteFolder and read by the loaders.
+
+mod common;
+
+use std::io::{ErrorKind, Read, Seek, SeekFrom, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::Path;
+
+use common::Rng;
+use uocf::geo::map::{FileBackend, MapBlockRelPos, MapPlane, MapSizeCells};
+use uocf::installation::UoInstallation;
+use uocf::remote::RemoteFolder;
+use uocf::tiledata::TileData;
+use uocf::vfs;
+
+#[test]
+fn remote_files_are_scanned_and_loaded() {
+    let served = tempfile::tempdir().expect("Can't create a temporary folder");
+    let mut rng = Rng::new(0);
+    let tiledata = common::synth_tiledata(2, &mut rng);
+    let map = common::synth_map(4, 4, &mut rng);
+    common::write_file(served.path(), "tiledata.mul", &tiledata.bytes);
+    common::write_file(served.path(), "map0.mul", &map.bytes);
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Can't listen on localhost");
+    let address = listener.local_addr().expect("No local address").to_string();
+    let served_folder = served.path().to_path_buf();
+    std::thread::spawn(move || uocf::remote::serve(listener, served_folder));
+
+    let remote = RemoteFolder::connect(&address).expect("Can't connect to the file server");
+    assert_eq!(remote.files().len(), 2);
+    let folder = Path::new("/uocf_remote_test/uo");
+    assert_eq!(remote.add_to_vfs(folder), 2);
+
+    let installation = UoInstallation::scan(folder).expect("Can't scan the remote folder");
+    let tiledata_path = installation.tiledata().expect("tiledata.mul not found");
+    let loaded = TileData::load(tiledata_path.clone(), None).expect("Can't load the tiledata");
+    assert!(loaded.to_bytes() == tiledata.bytes);
+
+    let map_path = installation
+        .map_plane(0)
+        .expect("map0.mul not found")
+        .map
+        .clone();
+    let size = MapSizeCells {
+        width: 32,
+        height: 32,
+    };
+    let mut map_plane = MapPlane::init_with_size(map_path.clone(), 0, FileBackend::Mmap, size)
+        .expect("Can't open the map");
+    let positions = [MapBlockRelPos { x: 3, y: 1 }, MapBlockRelPos { x: 0, y: 2 }];
+    map_plane
+        .load_blocks(&mut positions.to_vec())
+        .expect("Can't load the blocks");
+    for pos in positions {
+        let cell = map_plane
+            .block(pos)
+            .and_then(|block| block.cell(7, 1).ok())
+            .expect("Block not loaded");
+        assert_eq!((cell.id, cell.z), map.cell(pos.x, pos.y, 7, 1));
+    }
+
+    // Seeks back and forth in the read-ahead buffer, and reads at the end.
+    let mut file = vfs::open(&map_path).expect("Can't open the remote map");
+    let mut bytes = [0; 16];
+    for offset in [map.bytes.len() as u64 - 8, 100, 2_000, 99] {
+        file.seek(SeekFrom::Start(offset)).expect("Can't seek");
+        let len = file.read(&mut bytes).expect("Can't read");
+        let offset = offset as usize;
+        assert!(len > 0 && bytes[..len] == map.bytes[offset..offset + len]);
+    }
+    file.seek(SeekFrom::End(0)).expect("Can't seek");
+    assert_eq!(file.read(&mut bytes).expect("Can't read"), 0);
+
+    // Only the files of the served folder can be read.
+    assert!(remote.read_at("../tiledata.mul", 0, 4).is_err());
+    assert!(remote.read_at("hues.mul", 0, 4).is_err());
+    assert!(remote.read_at(".", 0, 4).is_err());
+    vfs::remove_folder(folder);
+}
+
+/// Reads a request of the protocol (see remote.rs), and returns its kind.
+fn read_request(stream: &mut TcpStream) -> u8 {
+    let mut kind = [0];
+    stream
+        .read_exact(&mut kind)
+        .expect("Can't read the request");
+    if kind[0] == 2 {
+        let mut name_len = [0; 2];
+        stream
+            .read_exact(&mut name_len)
+            .expect("Can't read the request");
+        let mut rest = vec![0; u16::from_le_bytes(name_len) as usize + 8 + 4];
+        stream
+            .read_exact(&mut rest)
+            .expect("Can't read the request");
+    }
+    kind[0]
+}
+
+fn write_response(stream: &mut TcpStream, payload_len: u32, payload: &[u8]) {
+    let mut response = vec![0];
+    response.extend_from_slice(&payload_len.to_le_bytes());
+    response.extend_from_slice(payload);
+    stream
+        .write_all(&response)
+        .expect("Can't write the response");
+}
+
+#[test]
+fn bad_responses_are_errors_and_drop_the_connection() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("Can't listen on localhost");
+    let address = listener.local_addr().expect("No local address").to_string();
+    std::thread::spawn(move || {
+        // First connection: the file list, a read answered with more bytes than asked, then a response longer than
+        //  any the client accepts.
+        let (mut stream, _) = listener.accept().expect("Can't accept");
+        read_request(&mut stream);
+        write_response(&mut stream, 4, &0u32.to_le_bytes());
+        read_request(&mut stream);
+        write_response(&mut stream, 8, &[0xAA; 8]);
+        read_request(&mut stream);
+        write_response(&mut stream, u32::MAX, &[0xBB; 64]);
+        // Second connection: a good read.
+        let (mut stream, _) = listener.accept().expect("Can't accept");
+        read_request(&mut stream);
+        write_response(&mut stream, 4, &[1, 2, 3, 4]);
+    });
+
+    let remote = RemoteFolder::connect(&address).expect("Can't connect to the fake server");
+    let too_long = remote.read_at("map0.mul", 0, 4).unwrap_err();
+    assert_eq!(too_long.kind(), ErrorKind::InvalidData);
+    let oversized = remote.read_at("map0.mul", 0, 4).unwrap_err();
+    assert_eq!(oversized.kind(), ErrorKind::InvalidData);
+    // The rest of the oversized response isn't read as the next one: that goes through a new connection.
+    assert_eq!(
+        remote
+            .read_at("map0.mul", 0, 4)
+            .expect("Can't read after reconnecting"),
+        [1, 2, 3, 4]
+    );
+}
@@ -0,0 +1,42 @@
+// Sextant coordinates against the values of the emulators' Sextant.Format.
+
+use uocf::geo::sextant::Sextant;
+
+fn format(map_index: u32, x: u32, y: u32) -> Option<String> {
+    Sextant::from_coords(map_index, x, y, 7168, 4096).map(|sextant| sextant.to_string())
+}
+
+#[test]
+fn britannia() {
+    // The throne of Lord British.
+    assert_eq!(format(0, 1323, 1624).as_deref(), Some("0°0'S 0°0'E"));
+    // Britain bank.
+    assert_eq!(format(1, 1434, 1699).as_deref(), Some("6°35'S 7°48'E"));
+    assert_eq!(format(0, 1000, 1000).as_deref(), Some("54°50'N 22°42'W"));
+    // Past 180 degrees east: wraps to the west.
+    assert_eq!(format(0, 4000, 1624).as_deref(), Some("0°0'S 171°46'W"));
+}
+
+#[test]
+fn lost_lands() {
+    assert_eq!(format(0, 5936, 3112).as_deref(), Some("0°0'S 0°0'E"));
+    assert_eq!(format(0, 6000, 3000).as_deref(), Some("9°50'N 4°30'E"));
+    // Past 5120 but north of the lost lands.
+    assert_eq!(format(0, 5500, 1000), None);
+    assert_eq!(format(0, 7000, 3000), None);
+}
+
+#[test]
+fn other_maps() {
+    assert_eq!(
+        Sextant::from_coords(2, 1323, 1624, 2304, 1600),
+        None,
+        "Out of Ilshenar"
+    );
+    assert_eq!(
+        Sextant::from_coords(2, 1000, 1000, 2304, 1600)
+            .map(|sextant| sextant.to_string())
+            .as_deref(),
+        Some("54°50'N 22°42'W")
+    );
+}
@@ -0,0 +1,139 @@
+// Standing spots of a cell from hand-made tiledata flags, and WorldQuery on a synthetic map.
+
+mod common;
+
+use common::{CASES, Rng, synth_map};
+use uocf::geo::map::{MapBlock, MapCell, MapPlane, MapPlaneShared, MapSizeCells};
+use uocf::geo::statics::StaticItem;
+use uocf::geo::surface::{SurfaceSource, SurfaceSpot, WorldQuery, standing_spots, step_spot};
+use uocf::tiledata::{Flags, TileData};
+
+const GRASS: u16 = 0x0003;
+const WATER: u16 = 0x00A8;
+const FLOOR: u16 = 0x0100;
+const TABLE: u16 = 0x0101;
+const WALL: u16 = 0x0102;
+const BRIDGE: u16 = 0x0103;
+const PLANT: u16 = 0x0104;
+
+fn flags(names: &[&str]) -> Flags {
+    Flags::from_value(names.iter().map(|name| Flags::bit_of(name).unwrap()).sum())
+}
+
+fn tiledata() -> TileData {
+    let mut tiledata = TileData::new_empty(3).unwrap();
+    tiledata.land_tile_mut(WATER).unwrap().flags = flags(&["impassable", "wet"]);
+    for (id, names, height) in [
+        (FLOOR, &["surface"][..], 0),
+        (TABLE, &["surface"][..], 6),
+        (WALL, &["impassable", "wall"][..], 20),
+        (BRIDGE, &["surface", "bridge"][..], 4),
+        (PLANT, &["foliage"][..], 10),
+    ] {
+        let item_tile = tiledata.item_tile_mut(id).unwrap();
+        item_tile.flags = flags(names);
+        item_tile.set_height_raw(height);
+    }
+    tiledata
+}
+
+fn item(id: u16, z: i8) -> StaticItem {
+    StaticItem {
+        id,
+        z,
+        ..Default::default()
+    }
+}
+
+fn zs(spots: &[SurfaceSpot]) -> Vec<i32> {
+    spots.iter().map(|spot| spot.z).collect()
+}
+
+#[test]
+fn land_only() {
+    let tiledata = tiledata();
+    let spots = standing_spots(&tiledata, MapCell { id: GRASS, z: 5 }, []);
+    assert_eq!(
+        spots,
+        vec![SurfaceSpot {
+            z: 5,
+            source: SurfaceSource::Land
+        }]
+    );
+    assert!(standing_spots(&tiledata, MapCell { id: WATER, z: -5 }, []).is_empty());
+}
+
+#[test]
+fn statics() {
+    let tiledata = tiledata();
+    let land = MapCell { id: GRASS, z: 0 };
+    let spots_with = |items: &[StaticItem]| zs(&standing_spots(&tiledata, land, items));
+
+    // An upper floor leaves room for the body on the ground.
+    assert_eq!(spots_with(&[item(FLOOR, 20)]), vec![0, 20]);
+    // A table doesn't.
+    assert_eq!(spots_with(&[item(TABLE, 0)]), vec![6]);
+    let spots = standing_spots(&tiledata, land, &[item(TABLE, 0)]);
+    assert_eq!(spots[0].source, SurfaceSource::Static(TABLE));
+    // Walls block, foliage doesn't.
+    assert!(spots_with(&[item(WALL, 0)]).is_empty());
+    assert_eq!(spots_with(&[item(PLANT, 0)]), vec![0]);
+    // Bridges count half their height.
+    assert_eq!(spots_with(&[item(BRIDGE, 20)]), vec![0, 22]);
+    // Over the water, only the bridge.
+    assert_eq!(
+        zs(&standing_spots(
+            &tiledata,
+            MapCell { id: WATER, z: -5 },
+            &[item(BRIDGE, 0)]
+        )),
+        vec![2]
+    );
+    // A wall right above the floor blocks it, not the ground far below.
+    assert_eq!(spots_with(&[item(FLOOR, 20), item(WALL, 25)]), vec![0]);
+}
+
+#[test]
+fn steps() {
+    let tiledata = tiledata();
+    let spots = standing_spots(&tiledata, MapCell { id: GRASS, z: 0 }, &[item(FLOOR, 20)]);
+    assert_eq!(step_spot(&spots, 0).map(|spot| spot.z), Some(0));
+    // Up to 14 above.
+    assert_eq!(step_spot(&spots, 6).map(|spot| spot.z), Some(20));
+    assert_eq!(step_spot(&spots, 5).map(|spot| spot.z), Some(0));
+    // Going down is always allowed.
+    assert_eq!(step_spot(&spots, 100).map(|spot| spot.z), Some(20));
+    assert_eq!(step_spot(&spots, -15).map(|spot| spot.z), None);
+}
+
+#[test]
+fn world_query_land() {
+    let tiledata = tiledata();
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let synth = synth_map(2, 3, &mut rng);
+        let size = MapSizeCells {
+            width: synth.width_blocks * MapBlock::CELLS_PER_ROW,
+            height: synth.height_blocks * MapBlock::CELLS_PER_COLUMN,
+        };
+        let map_plane =
+            MapPlaneShared::new(MapPlane::from_bytes(synth.bytes.clone(), 0, size).unwrap());
+        let mut query = WorldQuery::new(&tiledata, &map_plane, None);
+        for _ in 0..32 {
+            let (x, y) = (rng.below(size.width), rng.below(size.height));
+            let (id, z) = synth.cell(x / 8, y / 8, x % 8, y % 8);
+            let expected = (id != WATER).then_some(z as i32);
+            assert_eq!(
+                query.surface_top_z(x, y).unwrap(),
+                expected,
+                "seed {seed}, ({x}, {y})"
+            );
+            assert_eq!(
+                query.step_z(x, y, z as i32 - 15).unwrap(),
+                None,
+                "seed {seed}, ({x}, {y})"
+            );
+        }
+        assert!(query.standing_spots(size.width, 0).is_err(), "seed {seed}");
+    }
+}
@@ -0,0 +1,229 @@
+// Synthetic texmaps.mul and texidx.mul through TexMap2D::from_reader_with_progress: the textures decoded in parallel
+//  land in their slots, with the pixels of the serial conversion, and the progress reaches the texture count. The lazy
+//  mode (from_reader_lazy) and the rgba cache (write_rgba_cache, read_rgba_cache) must give the same textures.
+// A texidx.mul shorter than the texture slots is an error, not a panic.
+
+mod common;
+
+use common::{CASES, Rng};
+use std::io::Cursor;
+use std::sync::Mutex;
+use uocf::UocfError;
+use uocf::generic_index::IndexFile;
+use uocf::geo::land_texture_2d::{LandTextureSize, TexMap2D};
+
+/// Texture slots read by TexMap2D.
+const TEXMAP_SLOTS: u32 = 0x1388;
+
+struct SynthTexMaps {
+    texmaps: Vec<u8>,
+    texidx: Vec<u8>,
+    /// Size and bgra5551 pixels of the texture in each slot, if any.
+    textures: Vec<Option<(LandTextureSize, Vec<u16>)>>,
+}
+
+/// A texture in about one slot out of four, stored one after the other; the other slots are unused, or have an
+///  unknown size or a lookup past the end of the file.
+fn synth_texmaps(rng: &mut Rng) -> SynthTexMaps {
+    let mut synth = SynthTexMaps {
+        texmaps: Vec::new(),
+        texidx: Vec::new(),
+        textures: Vec::new(),
+    };
+    let mut entries = Vec::new();
+    for _ in 0..TEXMAP_SLOTS {
+        let (entry, texture) = match rng.below(8) {
+            0 | 1 => {
+                let size = if rng.below(4) == 0 {
+                    LandTextureSize::Big
+                } else {
+                    LandTextureSize::Small
+                };
+                let (width, height) = size.dimensions();
+                let pixels: Vec<u16> = (0..width * height).map(|_| rng.u16()).collect();
+                let lookup = synth.texmaps.len() as u32;
+                synth
+                    .texmaps
+                    .extend(pixels.iter().flat_map(|pixel| pixel.to_le_bytes()));
+                ((lookup, pixels.len() as u32 * 2), Some((size, pixels)))
+            }
+            2 => ((0, 0x1234), None),
+            3 => ((u32::MAX - 1, 0x2000), None),
+            _ => ((u32::MAX, 0), None),
+        };
+        entries.push(entry);
+        synth.textures.push(texture);
+    }
+    for (lookup, len) in entries {
+        for value in [lookup, len, 0] {
+            synth.texidx.extend_from_slice(&value.to_le_bytes());
+        }
+    }
+    synth
+}
+
+fn rgba8888(pixel: u16) -> [u8; 4] {
+    let channel = |shift: u16| (((pixel >> shift) & 0x1F) << 3) as u8;
+    [channel(10), channel(5), channel(0), 0xFF]
+}
+
+#[test]
+fn parallel_load_matches_synthetic_textures() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let synth = synth_texmaps(&mut rng);
+        let texidx = IndexFile::from_reader(Cursor::new(&synth.texidx), "texidx.mul").unwrap();
+        let calls = Mutex::new(Vec::new());
+        let texmap = TexMap2D::from_reader_with_progress(
+            Cursor::new(&synth.texmaps),
+            &texidx,
+            None,
+            |done, total| {
+                calls.lock().unwrap().push((done, total));
+            },
+        )
+        .unwrap();
+
+        let expected_count = synth.textures.iter().flatten().count();
+        let calls = calls.into_inner().unwrap();
+        assert_eq!(calls.len(), expected_count, "seed {seed}");
+        assert!(
+            calls.iter().all(|&(_, total)| total == expected_count),
+            "seed {seed}"
+        );
+        assert_eq!(
+            calls.iter().map(|&(done, _)| done).max(),
+            Some(expected_count),
+            "seed {seed}"
+        );
+
+        for (id, texture) in synth.textures.iter().enumerate() {
+            let element = texmap.element(id);
+            let Some((size, pixels)) = texture else {
+                assert!(element.is_none(), "seed {seed}, texture 0x{id:x}");
+                continue;
+            };
+            let element =
+                element.unwrap_or_else(|| panic!("seed {seed}: texture 0x{id:x} missing"));
+            assert_eq!(*element.id(), id as u32, "seed {seed}");
+            assert_eq!(element.size(), size, "seed {seed}, texture 0x{id:x}");
+            let expected: Vec<u8> = pixels.iter().flat_map(|&pixel| rgba8888(pixel)).collect();
+            assert!(
+                *element.pixel_data() == expected,
+                "seed {seed}, texture 0x{id:x}: wrong pixels"
+            );
+        }
+    }
+}
+
+#[test]
+fn truncated_texidx_is_an_error() {
+    let mut rng = Rng::new(0);
+    let synth = synth_texmaps(&mut rng);
+    // 12 bytes per index entry: the last slots are missing.
+    let texidx_len = synth.texidx.len() - 12 * 10;
+    let texidx =
+        IndexFile::from_reader(Cursor::new(&synth.texidx[..texidx_len]), "texidx.mul").unwrap();
+    let full = TexMap2D::from_reader(Cursor::new(&synth.texmaps), &texidx, None);
+    assert!(matches!(full, Err(UocfError::OutOfRange { .. })));
+    let lazy = TexMap2D::from_reader_lazy(Cursor::new(synth.texmaps), &texidx, None);
+    assert!(matches!(lazy, Err(UocfError::OutOfRange { .. })));
+}
+
+#[test]
+fn lazy_load_matches_full_load() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let synth = synth_texmaps(&mut rng);
+        let texidx = IndexFile::from_reader(Cursor::new(&synth.texidx), "texidx.mul").unwrap();
+        let full = TexMap2D::from_reader(Cursor::new(&synth.texmaps), &texidx, None).unwrap();
+        let lazy = TexMap2D::from_reader_lazy(Cursor::new(synth.texmaps), &texidx, None).unwrap();
+        assert!(lazy.is_lazy() && !full.is_lazy());
+        assert_eq!(lazy.len(), full.len());
+
+        // Requested in a scattered order, some twice.
+        for _ in 0..2 * full.len() {
+            let id = rng.below(full.len() as u32 + 8) as usize;
+            let (lazy_element, full_element) = (lazy.element(id), full.element(id));
+            assert_eq!(
+                lazy_element.map(|element| (*element.id(), *element.size())),
+                full_element.map(|element| (*element.id(), *element.size())),
+                "seed {seed}, texture 0x{id:x}"
+            );
+            assert!(
+                lazy_element.map(|element| element.pixel_data())
+                    == full_element.map(|element| element.pixel_data()),
+                "seed {seed}, texture 0x{id:x}: wrong pixels"
+            );
+        }
+    }
+}
+
+fn assert_same_textures(texmap: &TexMap2D, expected: &TexMap2D, seed: u64) {
+    assert_eq!(texmap.len(), expected.len(), "seed {seed}");
+    for id in 0..expected.len() {
+        let (element, expected_element) = (texmap.element(id), expected.element(id));
+        assert_eq!(
+            element.map(|element| (*element.id(), *element.size())),
+            expected_element.map(|element| (*element.id(), *element.size())),
+            "seed {seed}, texture 0x{id:x}"
+        );
+        assert!(
+            element.map(|element| element.pixel_data())
+                == expected_element.map(|element| element.pixel_data()),
+            "seed {seed}, texture 0x{id:x}: wrong pixels"
+        );
+    }
+}
+
+#[test]
+fn rgba_cache_round_trips() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let synth = synth_texmaps(&mut rng);
+        let texidx = IndexFile::from_reader(Cursor::new(&synth.texidx), "texidx.mul").unwrap();
+        let texmap = TexMap2D::from_reader(Cursor::new(&synth.texmaps), &texidx, None).unwrap();
+        let key = rng.u64();
+        let mut cache = Vec::new();
+        texmap.write_rgba_cache(&mut cache, key).unwrap();
+
+        let cached = TexMap2D::read_rgba_cache(Cursor::new(&cache), key)
+            .unwrap()
+            .unwrap_or_else(|| panic!("seed {seed}: cache refused"));
+        assert!(!cached.is_lazy());
+        assert_same_textures(&cached, &texmap, seed);
+
+        // The lazy mode writes the same cache.
+        let lazy = TexMap2D::from_reader_lazy(Cursor::new(synth.texmaps), &texidx, None).unwrap();
+        let mut lazy_cache = Vec::new();
+        lazy.write_rgba_cache(&mut lazy_cache, key).unwrap();
+        assert!(lazy_cache == cache, "seed {seed}");
+
+        // Stale: another key, or another layout version.
+        assert!(
+            TexMap2D::read_rgba_cache(Cursor::new(&cache), key ^ 1)
+                .unwrap()
+                .is_none()
+        );
+        let mut other_version = cache.clone();
+        other_version[8] ^= 0xFF;
+        assert!(
+            TexMap2D::read_rgba_cache(Cursor::new(&other_version), key)
+                .unwrap()
+                .is_none()
+        );
+        // Empty (e.g. a cache file that couldn't be written) or truncated.
+        assert!(
+            TexMap2D::read_rgba_cache(Cursor::new(&[]), key)
+                .unwrap()
+                .is_none()
+        );
+        if cache.len() > 24 {
+            let truncated = &cache[..cache.len() - 1];
+            assert!(
+                TexMap2D::read_rgba_cache(Cursor::new(truncated), key).is_err(),
+                "seed {seed}"
+            );
+        }
+    }
+}
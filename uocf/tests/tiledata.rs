@@ -0,0 +1,283 @@
+// Round trips of synthetic tiledata.mul files of every revision through TileData::load and to_bytes, and golden tests
+//  against the first blocks of real-like files (tests/fixtures/tiledata_rev*_head.bin).
+
+mod common;
+
+use common::{ItemRecord, LandRecord, Rng, TileDataLayout};
+use uocf::tiledata::{ItemTile, LandTile, TileData};
+
+fn land_record(tile: &LandTile) -> LandRecord {
+    LandRecord {
+        flags: tile.flags.value(),
+        unk_hs: tile.unk_hs,
+        texture_id: tile.texture_id,
+        name: tile.name,
+    }
+}
+
+fn item_record(tile: &ItemTile) -> ItemRecord {
+    let mut name = [0; 20];
+    name[..tile.name_ascii().len()].copy_from_slice(tile.name_ascii().as_bytes());
+    ItemRecord {
+        flags: tile.flags.value(),
+        unk_hs: tile.unk_hs,
+        weight: tile.weight,
+        quality: tile.quality,
+        unk0: tile.unk0,
+        unk1: tile.unk1,
+        quantity: tile.quantity,
+        anim_id: tile.anim_id,
+        unk2: tile.unk2,
+        hue_extra: tile.hue_extra,
+        stacking_offset: tile.stacking_offset,
+        value: tile.value,
+        height: tile.height_raw(),
+        name,
+    }
+}
+
+fn load(bytes: &[u8]) -> uocf::errors::Result<TileData> {
+    let folder = tempfile::tempdir().expect("Can't create a temporary folder");
+    TileData::load(
+        common::write_file(folder.path(), "tiledata.mul", bytes),
+        None,
+    )
+}
+
+#[test]
+fn synthetic_tiledata_round_trips() {
+    for revision in [1, 2, 3] {
+        for seed in 0..common::CASES {
+            let mut rng = Rng::new(seed);
+            let synth = common::synth_tiledata(revision, &mut rng);
+            let tiledata = load(&synth.bytes).expect("Can't load the synthetic tiledata");
+            let case = format!("revision {revision}, seed {seed}");
+
+            assert_eq!(tiledata.revision(), revision, "{case}");
+            assert_eq!(tiledata.land_tile_count(), synth.land.len(), "{case}");
+            assert_eq!(tiledata.item_tile_count(), synth.items.len(), "{case}");
+            for (id, tile) in tiledata.land_tiles() {
+                assert_eq!(tile.tile_id, id as i32, "{case}");
+                assert_eq!(
+                    land_record(tile),
+                    synth.land[id as usize],
+                    "{case}, land tile 0x{id:x}"
+                );
+            }
+            for (id, tile) in tiledata.item_tiles() {
+                assert_eq!(tile.tile_id, id as i32, "{case}");
+                assert_eq!(
+                    item_record(tile),
+                    synth.items[id as usize],
+                    "{case}, item tile 0x{id:x}"
+                );
+            }
+            // Block headers and every unknown field included, the file is written back byte for byte.
+            assert!(
+                tiledata.to_bytes() == synth.bytes,
+                "{case}: to_bytes differs from the loaded file"
+            );
+        }
+    }
+}
+
+#[test]
+fn tiledata_from_memory_matches_file() {
+    let synth = common::synth_tiledata(3, &mut Rng::new(0));
+    let from_file = load(&synth.bytes).expect("Can't load the synthetic tiledata");
+    let from_memory = TileData::from_reader(std::io::Cursor::new(&synth.bytes), None)
+        .expect("Can't read the synthetic tiledata from memory");
+    assert_eq!(from_memory.revision(), from_file.revision());
+    assert!(from_memory.to_bytes() == from_file.to_bytes());
+}
+
+#[test]
+fn tiledata_of_unknown_size_is_refused() {
+    let synth = common::synth_tiledata(1, &mut Rng::new(0));
+    // Too short for the land tiles, then between two revisions.
+    for size in [
+        TileDataLayout::of_revision(1).land_section_size() - 1,
+        synth.bytes.len() + 1,
+    ] {
+        let mut bytes = synth.bytes.clone();
+        bytes.resize(size, 0);
+        assert!(
+            load(&bytes).is_err(),
+            "A tiledata of {size} bytes was loaded"
+        );
+    }
+}
+
+#[test]
+fn new_empty_tiledata_is_loaded_back() {
+    for revision in [1, 2, 3] {
+        let bytes = TileData::new_empty(revision)
+            .expect("Can't create an empty tiledata")
+            .to_bytes();
+        assert_eq!(
+            bytes.len(),
+            TileDataLayout::of_revision(revision).file_size()
+        );
+        let tiledata = load(&bytes).expect("Can't load the empty tiledata");
+        assert_eq!(tiledata.revision(), revision);
+        assert!(tiledata.to_bytes() == bytes);
+    }
+}
+
+/// A tiledata of the given revision holding the fixture blocks: the first land block, then the first item block. The
+///  other tiles are zeroed.
+fn load_fixture(revision: u8) -> TileData {
+    let layout = TileDataLayout::of_revision(revision);
+    let head = std::fs::read(common::fixture_path(&format!(
+        "tiledata_rev{revision}_head.bin"
+    )))
+    .expect("Can't read the tiledata fixture");
+    let (land_block, item_block) = head.split_at(layout.land_block_size());
+    assert_eq!(
+        item_block.len(),
+        layout.item_block_size(),
+        "Fixture of the wrong revision"
+    );
+    let mut bytes = vec![0; layout.file_size()];
+    bytes[..land_block.len()].copy_from_slice(land_block);
+    let item_section = layout.land_section_size();
+    bytes[item_section..item_section + item_block.len()].copy_from_slice(item_block);
+    load(&bytes).expect("Can't load the tiledata fixture")
+}
+
+#[test]
+fn golden_tiledata_classic() {
+    let tiledata = load_fixture(1);
+    assert_eq!(tiledata.revision(), 1);
+
+    let land = |id| tiledata.land_tile(id).expect("Missing land tile");
+    assert_eq!(
+        (
+            land(0).flags.value(),
+            land(0).texture_id,
+            land(0).name_ascii()
+        ),
+        (0xb1386f61, 0xa815, "")
+    );
+    assert_eq!(
+        (
+            land(1).flags.value(),
+            land(1).texture_id,
+            land(1).name_ascii()
+        ),
+        (0xb9b4ad72, 0xfdcd, "grass")
+    );
+    assert_eq!(land(8).name_ascii(), "twenty chars long!!!");
+    assert_eq!(
+        (
+            land(31).flags.value(),
+            land(31).texture_id,
+            land(31).name_ascii()
+        ),
+        (0x185f1b98, 0x1605, "sand")
+    );
+    assert_eq!(land(1).unk_hs, 0);
+    assert_eq!(land(32).flags.value(), 0);
+
+    let item = |id| tiledata.item_tile(id).expect("Missing item tile");
+    assert_eq!(
+        (
+            item(0).flags.value(),
+            item(0).weight,
+            item(0).quality,
+            item(0).unk0,
+            item(0).unk1,
+            item(0).quantity
+        ),
+        (0x92170269, 0xfa, 0xa0, 0x94e1, 0x5b, 0x8c)
+    );
+    assert_eq!(
+        (
+            item(0).anim_id,
+            item(0).unk2,
+            item(0).hue_extra,
+            item(0).stacking_offset,
+            item(0).value
+        ),
+        (0x2abf, 0xea, 0xda, 0x77, 0x0c)
+    );
+    assert_eq!((item(0).height_raw(), item(0).name_ascii()), (-85, "rock"));
+    assert_eq!(
+        (item(5).flags.value(), item(5).anim_id, item(5).height_raw()),
+        (0x488c3e34, 0x3ce8, 106)
+    );
+    assert_eq!(item(5).name_ascii(), "twenty chars long!!!");
+    assert_eq!(
+        (
+            item(31).flags.value(),
+            item(31).value,
+            item(31).height_raw()
+        ),
+        (0x1ec7c253, 0xea, -3)
+    );
+    assert_eq!(item(31).name_ascii(), "cave floor");
+    assert_eq!(item(32).flags.value(), 0);
+}
+
+#[test]
+fn golden_tiledata_high_seas() {
+    let tiledata = load_fixture(3);
+    assert_eq!(tiledata.revision(), 3);
+
+    let land = |id| tiledata.land_tile(id).expect("Missing land tile");
+    assert_eq!(
+        (land(0).flags.value(), land(0).unk_hs, land(0).texture_id),
+        (0x3ffafb61, 0xdbb21cb7, 0x909c)
+    );
+    assert_eq!(
+        (land(1).flags.value(), land(1).unk_hs, land(1).texture_id),
+        (0x25cbab10, 0x468be85e, 0x4f9a)
+    );
+    assert_eq!(land(1).name_ascii(), "grass");
+    assert_eq!(land(8).name_ascii(), "twenty chars long!!!");
+    assert_eq!(
+        (land(31).flags.value(), land(31).unk_hs, land(31).texture_id),
+        (0xd1b86be8, 0xf4e4eb26, 0x9732)
+    );
+    assert_eq!(land(31).name_ascii(), "sand");
+
+    let item = |id| tiledata.item_tile(id).expect("Missing item tile");
+    assert_eq!(
+        (
+            item(0).flags.value(),
+            item(0).unk_hs,
+            item(0).weight,
+            item(0).quality,
+            item(0).unk0,
+            item(0).unk1
+        ),
+        (0x59e8cbd3, 0xc0484199, 0x9b, 0x5e, 0x473f, 0x34)
+    );
+    assert_eq!(
+        (
+            item(0).quantity,
+            item(0).anim_id,
+            item(0).unk2,
+            item(0).hue_extra,
+            item(0).stacking_offset
+        ),
+        (0x82, 0x8035, 0x4a, 0x32, 0xb0)
+    );
+    assert_eq!(
+        (item(0).value, item(0).height_raw(), item(0).name_ascii()),
+        (0xb9, -29, "rock")
+    );
+    assert_eq!(
+        (item(5).flags.value(), item(5).unk_hs, item(5).height_raw()),
+        (0x05ef78ef, 0x0ad7830d, -70)
+    );
+    assert_eq!(
+        (item(31).flags.value(), item(31).unk_hs, item(31).anim_id),
+        (0xd8177d8b, 0x27a58845, 0x546b)
+    );
+    assert_eq!(
+        (item(31).height_raw(), item(31).name_ascii()),
+        (104, "cave floor")
+    );
+    assert_eq!(tiledata.item_tile_count(), 0x10000);
+}
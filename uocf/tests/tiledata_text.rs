@@ -0,0 +1,95 @@
+// Round trips of synthetic tiledata.mul files of every revision through the JSON and TOML documents: mul -> doc -> mul
+//  gives the same bytes, names that aren't ASCII padded with zeros included.
+#![cfg(feature = "serde")]
+
+mod common;
+
+use common::{Rng, TileDataLayout};
+use std::io::Cursor;
+use uocf::tiledata::TileData;
+
+const NAME_LEN: usize = 20;
+
+/// Overwrites the name of a land tile (item: false) or of an item tile (item: true) in the file bytes.
+fn set_raw_name(
+    bytes: &mut [u8],
+    layout: &TileDataLayout,
+    item: bool,
+    id: usize,
+    name: &[u8; NAME_LEN],
+) {
+    let per_block = TileDataLayout::TILES_PER_BLOCK;
+    let (section_start, block_size, tile_size) = if item {
+        (
+            layout.land_section_size(),
+            layout.item_block_size(),
+            layout.item_tile_size(),
+        )
+    } else {
+        (0, layout.land_block_size(), layout.land_tile_size())
+    };
+    // The name is the last field of a tile.
+    let tile_end =
+        section_start + (id / per_block) * block_size + 4 + (id % per_block + 1) * tile_size;
+    bytes[tile_end - NAME_LEN..tile_end].copy_from_slice(name);
+}
+
+/// A synthetic tiledata, with a few names that can't be written as ASCII text.
+fn synth_bytes(revision: u8, seed: u64) -> Vec<u8> {
+    let layout = TileDataLayout::of_revision(revision);
+    let mut bytes = common::synth_tiledata(revision, &mut Rng::new(seed)).bytes;
+    let mut after_terminator = [0; NAME_LEN];
+    after_terminator[..2].copy_from_slice(b"ab");
+    after_terminator[3..6].copy_from_slice(b"xyz");
+    let mut not_ascii = [0; NAME_LEN];
+    not_ascii[..4].copy_from_slice(b"caf\xE9");
+    let not_terminated = [b'n'; NAME_LEN];
+    set_raw_name(&mut bytes, &layout, false, 1, &after_terminator);
+    set_raw_name(&mut bytes, &layout, false, 33, &not_ascii);
+    set_raw_name(&mut bytes, &layout, true, 2, &not_ascii);
+    set_raw_name(&mut bytes, &layout, true, 40, &after_terminator);
+    set_raw_name(&mut bytes, &layout, true, 41, &not_terminated);
+    bytes
+}
+
+#[test]
+fn json_and_toml_round_trip() {
+    for revision in [1, 2, 3] {
+        for seed in 0..2 {
+            let bytes = synth_bytes(revision, seed);
+            let tiledata = TileData::from_reader(Cursor::new(&bytes), None)
+                .expect("Can't read the synthetic tiledata");
+            let case = format!("revision {revision}, seed {seed}");
+
+            let json = tiledata.to_json().expect("Can't write JSON");
+            // Raw bytes for the names not ASCII or with bytes after the terminator, text for the
+            //  one of 20 chars.
+            assert_eq!(json.matches("\"name_bytes\"").count(), 4, "{case}");
+            let from_json = TileData::from_json(&json).expect("Can't read JSON");
+            assert!(
+                from_json.to_bytes() == bytes,
+                "{case}: JSON round trip differs"
+            );
+
+            let toml = tiledata.to_toml().expect("Can't write TOML");
+            let from_toml = TileData::from_toml(&toml).expect("Can't read TOML");
+            assert!(
+                from_toml.to_bytes() == bytes,
+                "{case}: TOML round trip differs"
+            );
+        }
+    }
+}
+
+#[test]
+fn bad_name_bytes_are_refused() {
+    // Not 20 bytes.
+    let json = r#"{ "revision": 1, "land": [{ "id": 1, "name_bytes": [97, 98] }] }"#;
+    assert!(TileData::from_json(json).is_err());
+    // Both the name and its bytes.
+    let name_bytes = format!("{:?}", [b'a'; NAME_LEN]);
+    let json = format!(
+        r#"{{ "revision": 1, "items": [{{ "id": 1, "name": "a", "name_bytes": {name_bytes} }}] }}"#
+    );
+    assert!(TileData::from_json(&json).is_err());
+}
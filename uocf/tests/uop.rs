@@ -0,0 +1,23 @@
+// hash_file_name: the empty name skips the final mix, so it hashes to the initial state (0xDEADBEEF in b and c).
+
+use uocf::uop::hash_file_name;
+
+#[test]
+fn empty_name_hash() {
+    assert_eq!(hash_file_name(""), 0xDEAD_BEEF_DEAD_BEEF);
+}
+
+#[test]
+fn names_hash_differently() {
+    let names = [
+        "",
+        "a",
+        "build/artlegacymul/00000000.tga",
+        "build/artlegacymul/00000001.tga",
+    ];
+    for (i, a) in names.iter().enumerate() {
+        for b in &names[i + 1..] {
+            assert_ne!(hash_file_name(a), hash_file_name(b), "{a:?} and {b:?}");
+        }
+    }
+}
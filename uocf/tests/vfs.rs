@@ -0,0 +1,58 @@
+// Files added in memory to the vfs, found by UoInstallation::scan and read by the path-based loaders.
+
+mod common;
+
+use std::path::Path;
+
+use common::Rng;
+use uocf::geo::map::{FileBackend, MapBlockRelPos, MapCell, MapPlane, MapSizeCells};
+use uocf::installation::UoInstallation;
+use uocf::tiledata::TileData;
+use uocf::vfs;
+
+#[test]
+fn memory_files_are_scanned_and_loaded() {
+    // Not on disk: the folder exists only in the vfs.
+    let folder = Path::new("/uocf_vfs_test/uo");
+    let mut rng = Rng::new(0);
+    let tiledata = common::synth_tiledata(1, &mut rng);
+    let map = common::synth_map(4, 4, &mut rng);
+    vfs::add_file(folder.join("TileData.mul"), tiledata.bytes.clone());
+    vfs::add_file(folder.join("map0.mul"), map.bytes.clone());
+
+    let installation = UoInstallation::scan(folder).expect("Can't scan the vfs folder");
+    let tiledata_path = installation.tiledata().expect("tiledata.mul not found");
+    let loaded = TileData::load(tiledata_path.clone(), None).expect("Can't load the tiledata");
+    assert!(loaded.to_bytes() == tiledata.bytes);
+
+    let map_path = installation
+        .map_plane(0)
+        .expect("map0.mul not found")
+        .map
+        .clone();
+    let size = MapSizeCells {
+        width: 32,
+        height: 32,
+    };
+    for backend in [FileBackend::Read, FileBackend::Mmap] {
+        let mut map_plane = MapPlane::init_with_size(map_path.clone(), 0, backend, size)
+            .expect("Can't open the map");
+        let pos = MapBlockRelPos { x: 2, y: 3 };
+        map_plane
+            .load_blocks(&mut vec![pos])
+            .expect("Can't load the block");
+        let cell = map_plane
+            .block(pos)
+            .and_then(|block| block.cell(5, 6).ok())
+            .expect("Block not loaded");
+        assert_eq!((cell.id, cell.z), map.cell(2, 3, 5, 6), "{backend:?}");
+        // Memory files can't be written.
+        map_plane
+            .set_cell(0, 0, MapCell { id: 1, z: 0 })
+            .expect("Can't edit the map");
+        assert!(map_plane.save_edits(false).is_err(), "{backend:?}");
+    }
+
+    assert_eq!(vfs::remove_folder(folder), 2);
+    assert!(UoInstallation::scan(folder).is_err());
+}